@@ -0,0 +1,375 @@
+use anyhow::Result;
+use chrono::DateTime;
+use clap::{Parser, Subcommand, ValueEnum};
+use console::style;
+use crv_edge::pb::{
+    BranchSortField, CherryPickReq, CreateBranchReq, GetBranchDiffReq, ListBranchesReq,
+    SetBranchProtectionReq, branch_service_client::BranchServiceClient,
+};
+use tabled::{Table, Tabled, settings::Style};
+use tonic::transport::Channel;
+
+#[derive(Parser)]
+pub struct BranchCli {
+    #[command(subcommand)]
+    pub branch_commands: BranchCommands,
+}
+
+#[derive(Subcommand)]
+pub enum BranchCommands {
+    Create(CreateCli),
+    List(ListCli),
+    CherryPick(CherryPickCli),
+    Diff(DiffCli),
+    Protect(ProtectCli),
+    Unprotect(UnprotectCli),
+}
+
+impl BranchCli {
+    pub async fn handle(&self, channel: &Channel) -> Result<()> {
+        match &self.branch_commands {
+            BranchCommands::Create(cli) => cli.handle(channel).await,
+            BranchCommands::List(cli) => cli.handle(channel).await,
+            BranchCommands::CherryPick(cli) => cli.handle(channel).await,
+            BranchCommands::Diff(cli) => cli.handle(channel).await,
+            BranchCommands::Protect(cli) => set_protection(channel, &cli.name, true).await,
+            BranchCommands::Unprotect(cli) => set_protection(channel, &cli.name, false).await,
+        }
+    }
+}
+
+#[derive(Parser)]
+pub struct CreateCli {
+    /// Branch name
+    pub name: String,
+
+    /// Base branch, the new branch starts from its current HEAD
+    #[arg(short, long)]
+    pub base: String,
+
+    /// Start the new branch at this changelist of the base branch instead of its HEAD
+    #[arg(
+        long = "at-cl",
+        value_name = "CHANGELIST",
+        value_parser = clap::value_parser!(i64).range(1..)
+    )]
+    pub at_cl: Option<i64>,
+
+    /// Branch owners, defaults to the current user
+    #[arg(short, long, value_delimiter = ',')]
+    pub owners: Vec<String>,
+
+    /// Branch description
+    #[arg(short, long, default_value = "")]
+    pub description: String,
+}
+
+impl CreateCli {
+    pub async fn handle(&self, channel: &Channel) -> Result<()> {
+        let mut client = BranchServiceClient::new(channel.clone());
+
+        let response = client
+            .create_branch(CreateBranchReq {
+                branch_id: self.name.clone(),
+                description: self.description.clone(),
+                base_branch_id: self.base.clone(),
+                owners: self.owners.clone(),
+                at_changelist_id: self.at_cl.unwrap_or(0),
+            })
+            .await?
+            .into_inner();
+
+        if !response.success {
+            anyhow::bail!(
+                "Failed to create branch {}: {}",
+                self.name,
+                response.message
+            );
+        }
+
+        let base = match self.at_cl {
+            Some(changelist_id) => format!("{}@{changelist_id}", self.base),
+            None => self.base.clone(),
+        };
+        println!(
+            "{} Branch {} created from {}",
+            style("✓").green(),
+            style(&self.name).cyan(),
+            style(base).cyan()
+        );
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum SortBy {
+    /// Newest branches first
+    CreatedAt,
+    /// Branches with the latest HEAD changelist first
+    #[value(name = "head-cl")]
+    HeadCl,
+}
+
+#[derive(Tabled)]
+struct BranchRow {
+    #[tabled(rename = "Branch")]
+    id: String,
+    #[tabled(rename = "HEAD")]
+    head_changelist_id: i64,
+    #[tabled(rename = "Created By")]
+    created_by: String,
+    #[tabled(rename = "Created At")]
+    created_at: String,
+    #[tabled(rename = "Description")]
+    description: String,
+}
+
+#[derive(Parser)]
+pub struct ListCli {
+    /// Sort order
+    #[arg(long, value_enum, default_value_t = SortBy::CreatedAt)]
+    pub sort: SortBy,
+
+    /// Page number, starting from 0
+    #[arg(short, long, default_value_t = 0)]
+    pub page: u32,
+
+    /// Number of branches per page, 0 for the server default
+    #[arg(short = 's', long, default_value_t = 0)]
+    pub page_size: u32,
+}
+
+impl ListCli {
+    pub async fn handle(&self, channel: &Channel) -> Result<()> {
+        let mut client = BranchServiceClient::new(channel.clone());
+
+        let sort_by = match self.sort {
+            SortBy::CreatedAt => BranchSortField::CreatedAt,
+            SortBy::HeadCl => BranchSortField::HeadChangelistId,
+        };
+        let response = client
+            .list_branches(ListBranchesReq {
+                page: self.page,
+                page_size: self.page_size,
+                sort_by: sort_by as i32,
+            })
+            .await?
+            .into_inner();
+
+        if super::json_output() {
+            return super::print_json(&response);
+        }
+
+        if response.branches.is_empty() {
+            println!("{}", style("No branches found.").yellow());
+            return Ok(());
+        }
+
+        let rows: Vec<BranchRow> = response
+            .branches
+            .into_iter()
+            .map(|b| BranchRow {
+                id: b.id,
+                head_changelist_id: b.head_changelist_id,
+                created_by: b.created_by,
+                created_at: DateTime::from_timestamp_millis(b.created_at)
+                    .map(|t| t.format("%Y-%m-%d %H:%M:%S").to_string())
+                    .unwrap_or_default(),
+                description: b.description,
+            })
+            .collect();
+
+        let mut table = Table::new(&rows);
+        table.with(Style::rounded());
+
+        println!("\n{}", table);
+        println!(
+            "\n{} of {} branch(es) shown",
+            style(rows.len()).cyan(),
+            style(response.total_count).cyan()
+        );
+        Ok(())
+    }
+}
+
+/// Apply the changes of a changelist on one branch to another branch
+#[derive(Parser)]
+pub struct CherryPickCli {
+    /// Branch the changelist was submitted to, defaults to the default branch
+    #[arg(long, default_value = "")]
+    pub from_branch: String,
+
+    /// Changelist to apply
+    #[arg(long)]
+    pub cl: i64,
+
+    /// Branch to apply the changelist to
+    #[arg(long)]
+    pub to_branch: String,
+
+    /// Description of the new changelist, defaults to the original description
+    #[arg(short, long, default_value = "")]
+    pub description: String,
+}
+
+#[derive(Tabled)]
+struct ConflictRow {
+    #[tabled(rename = "Path")]
+    path: String,
+    #[tabled(rename = "Expected")]
+    expected: String,
+    #[tabled(rename = "On Target")]
+    current: String,
+}
+
+/// 文件不存在时版本为 0:0
+fn format_version(generation: i64, revision: i64) -> String {
+    if generation == 0 && revision == 0 {
+        "-".to_string()
+    } else {
+        format!("{generation}:{revision}")
+    }
+}
+
+impl CherryPickCli {
+    pub async fn handle(&self, channel: &Channel) -> Result<()> {
+        let mut client = BranchServiceClient::new(channel.clone());
+
+        let response = client
+            .cherry_pick(CherryPickReq {
+                source_branch_id: self.from_branch.clone(),
+                changelist_id: self.cl,
+                target_branch_id: self.to_branch.clone(),
+                description: self.description.clone(),
+            })
+            .await?
+            .into_inner();
+
+        if !response.conflicts.is_empty() {
+            let rows: Vec<ConflictRow> = response
+                .conflicts
+                .into_iter()
+                .map(|c| ConflictRow {
+                    path: c.path,
+                    expected: format_version(c.expected_generation, c.expected_revision),
+                    current: format_version(c.current_generation, c.current_revision),
+                })
+                .collect();
+            let mut table = Table::new(&rows);
+            table.with(Style::rounded());
+            println!("\n{}", table);
+            anyhow::bail!(
+                "Changelist {} conflicts with branch {} on {} file(s)",
+                self.cl,
+                self.to_branch,
+                rows.len()
+            );
+        }
+
+        println!(
+            "{} Changelist {} applied to {} as changelist {}",
+            style("✓").green(),
+            style(self.cl).cyan(),
+            style(&self.to_branch).cyan(),
+            style(response.new_changelist_id).cyan()
+        );
+        Ok(())
+    }
+}
+
+/// Compare the files of two branches at their heads
+#[derive(Parser)]
+pub struct DiffCli {
+    /// First branch, shown as `-` for files only it has
+    #[arg(long = "a")]
+    pub branch_a: String,
+
+    /// Second branch, shown as `+` for files only it has
+    #[arg(long = "b")]
+    pub branch_b: String,
+
+    /// Depot path to compare, e.g. //assets/...
+    #[arg(long, default_value = "//...")]
+    pub path: String,
+}
+
+impl DiffCli {
+    pub async fn handle(&self, channel: &Channel) -> Result<()> {
+        let mut client = BranchServiceClient::new(channel.clone());
+
+        let response = client
+            .get_branch_diff(GetBranchDiffReq {
+                branch_a_id: self.branch_a.clone(),
+                branch_b_id: self.branch_b.clone(),
+                depot_wildcard: self.path.clone(),
+            })
+            .await?
+            .into_inner();
+
+        if response.only_in_a.is_empty()
+            && response.only_in_b.is_empty()
+            && response.modified.is_empty()
+        {
+            println!("{}", style("No differences found.").yellow());
+            return Ok(());
+        }
+
+        for path in &response.only_in_a {
+            println!("  {} {}", style("-").red(), path);
+        }
+        for path in &response.modified {
+            println!("  {} {}", style("~").yellow(), path);
+        }
+        for path in &response.only_in_b {
+            println!("  {} {}", style("+").green(), path);
+        }
+
+        println!(
+            "\n{} only in {}, {} only in {}, {} modified",
+            style(response.only_in_a.len()).cyan(),
+            style(&self.branch_a).cyan(),
+            style(response.only_in_b.len()).cyan(),
+            style(&self.branch_b).cyan(),
+            style(response.modified.len()).cyan()
+        );
+        Ok(())
+    }
+}
+
+/// Reject direct submits to a branch so changes can only be merged into it, admin only
+#[derive(Parser)]
+pub struct ProtectCli {
+    /// Branch name
+    pub name: String,
+}
+
+/// Allow direct submits to a protected branch again, admin only
+#[derive(Parser)]
+pub struct UnprotectCli {
+    /// Branch name
+    pub name: String,
+}
+
+async fn set_protection(channel: &Channel, name: &str, is_protected: bool) -> Result<()> {
+    let mut client = BranchServiceClient::new(channel.clone());
+
+    client
+        .set_branch_protection(SetBranchProtectionReq {
+            branch_id: name.to_string(),
+            is_protected,
+        })
+        .await?;
+
+    let state = if is_protected {
+        "only accepts merges"
+    } else {
+        "accepts direct submits"
+    };
+    println!(
+        "{} Branch {} now {}",
+        style("✓").green(),
+        style(name).cyan(),
+        state
+    );
+    Ok(())
+}
@@ -1,5 +1,12 @@
 use anyhow::Result;
+use chrono::DateTime;
 use clap::{Parser, Subcommand};
+use console::style;
+use crv_edge::pb::{
+    DescribeChangelistReq, GetChangelistChainReq,
+    changelist_service_client::ChangelistServiceClient,
+};
+use tabled::{Table, Tabled, settings::Style};
 use tonic::transport::Channel;
 
 #[derive(Parser)]
@@ -58,12 +65,189 @@ impl ListCli {
     }
 }
 
+/// Show a changelist's description, status and files
 #[derive(Parser)]
-pub struct DescribeCli;
+pub struct DescribeCli {
+    /// Changelist ID
+    pub changelist_id: String,
+
+    /// Only show the summary, without the file list
+    #[arg(short, long)]
+    pub short: bool,
+}
 
 impl DescribeCli {
     pub async fn handle(&self, channel: &Channel) -> Result<()> {
-        todo!()
+        describe_changelist(
+            channel,
+            &self.changelist_id,
+            !self.short,
+            super::json_output(),
+        )
+        .await
+    }
+}
+
+/// 打印 changelist 摘要，`crv describe --changelist` 也使用这里的输出格式
+pub async fn describe_changelist(
+    channel: &Channel,
+    changelist_id: &str,
+    list_files: bool,
+    json: bool,
+) -> Result<()> {
+    let mut client = ChangelistServiceClient::new(channel.clone());
+    let response = client
+        .describe_changelist(DescribeChangelistReq {
+            changelist_id: changelist_id.to_string(),
+            list_files,
+        })
+        .await?
+        .into_inner();
+    let changelist = response.changelist.unwrap_or_default();
+
+    if json {
+        let files = response
+            .files
+            .iter()
+            .map(|f| {
+                serde_json::json!({
+                    "path": f.path,
+                    "action": f.action,
+                    "generation": f.generation,
+                    "revision": f.revision,
+                })
+            })
+            .collect::<Vec<_>>();
+        let summary = serde_json::json!({
+            "id": changelist.id,
+            "workspaceName": response.workspace_name,
+            "description": changelist.description,
+            "status": changelist.status,
+            "fileCount": changelist.file_count,
+            "files": files,
+        });
+        println!("{}", serde_json::to_string_pretty(&summary)?);
+        return Ok(());
+    }
+
+    println!(
+        "{} on workspace {} {}",
+        style(format!("Change {}", changelist.id)).bold(),
+        style(&response.workspace_name).cyan(),
+        style(format!("*{}*", changelist.status)).yellow()
+    );
+    println!();
+    for line in changelist.description.lines() {
+        println!("\t{line}");
+    }
+    println!();
+
+    if !list_files {
+        println!("{} file(s)", changelist.file_count);
+        return Ok(());
+    }
+    if response.files.is_empty() {
+        println!("{}", style("No files in this changelist.").yellow());
+        return Ok(());
+    }
+    println!("Affected files ...");
+    println!();
+    for file in response.files {
+        // 新增的文件还没有版本
+        let revision = if file.generation == 0 && file.revision == 0 {
+            String::new()
+        } else {
+            format!("#{}:{}", file.generation, file.revision)
+        };
+        let action = if file.action.is_empty() {
+            style("(not opened)".to_string()).dim()
+        } else {
+            style(file.action).green()
+        };
+        println!("... {}{} {}", file.path, revision, action);
+    }
+    Ok(())
+}
+
+#[derive(Tabled)]
+struct ChangelistRow {
+    #[tabled(rename = "CL")]
+    changelist_id: i64,
+    #[tabled(rename = "Author")]
+    author: String,
+    #[tabled(rename = "Files")]
+    files_count: u64,
+    #[tabled(rename = "Committed At")]
+    committed_at: String,
+    #[tabled(rename = "Description")]
+    description: String,
+}
+
+/// Show submitted changelists of a branch, oldest first
+#[derive(Parser)]
+pub struct LogCli {
+    /// Branch whose history to show; omit for the default branch
+    #[arg(short, long)]
+    pub branch: Option<String>,
+
+    /// First changelist to show, 0 for the start of the history
+    #[arg(long = "from", default_value_t = 0)]
+    pub from_cl: i64,
+
+    /// Last changelist to show, 0 for the branch head
+    #[arg(long = "to", default_value_t = 0)]
+    pub to_cl: i64,
+}
+
+impl LogCli {
+    pub async fn handle(&self, channel: &Channel) -> Result<()> {
+        let mut client = ChangelistServiceClient::new(channel.clone());
+
+        let response = client
+            .get_changelist_chain(GetChangelistChainReq {
+                branch_id: self.branch.clone().unwrap_or_default(),
+                from_cl: self.from_cl,
+                to_cl: self.to_cl,
+            })
+            .await?
+            .into_inner();
+
+        if super::json_output() {
+            return super::print_json(&response);
+        }
+
+        if response.changelists.is_empty() {
+            println!("{}", style("No changelists in this range.").yellow());
+            return Ok(());
+        }
+
+        let rows: Vec<ChangelistRow> = response
+            .changelists
+            .into_iter()
+            .map(|cl| ChangelistRow {
+                changelist_id: cl.changelist_id,
+                author: cl.author,
+                files_count: cl.files_count,
+                committed_at: DateTime::from_timestamp(cl.committed_at, 0)
+                    .map(|t| t.format("%Y-%m-%d %H:%M:%S").to_string())
+                    .unwrap_or_default(),
+                description: cl.description,
+            })
+            .collect();
+
+        let mut table = Table::new(&rows);
+        table.with(Style::rounded());
+
+        println!("\n{}", table);
+        println!("\n{} changelist(s)", style(rows.len()).cyan());
+        if response.truncated {
+            println!(
+                "{}",
+                style("Only the latest changelists are shown, narrow the range with --from.")
+                    .yellow()
+            );
+        }
+        Ok(())
     }
 }
 
@@ -0,0 +1,203 @@
+use anyhow::Result;
+use chrono::DateTime;
+use clap::{Parser, Subcommand};
+use console::style;
+use crv_edge::pb::{
+    DeleteCheckpointReq, ListCheckpointsReq, RestoreCheckpointReq, SaveCheckpointReq,
+    workspace_service_client::WorkspaceServiceClient,
+};
+use tabled::{Table, Tabled, settings::Style};
+use tonic::transport::Channel;
+
+#[derive(Parser)]
+pub struct CheckpointCli {
+    #[command(subcommand)]
+    pub checkpoint_commands: CheckpointCommands,
+}
+
+#[derive(Subcommand)]
+pub enum CheckpointCommands {
+    Save(SaveCli),
+    Restore(RestoreCli),
+    List(ListCli),
+    Delete(DeleteCli),
+}
+
+impl CheckpointCli {
+    pub async fn handle(&self, channel: &Channel) -> Result<()> {
+        match &self.checkpoint_commands {
+            CheckpointCommands::Save(cli) => cli.handle(channel).await,
+            CheckpointCommands::Restore(cli) => cli.handle(channel).await,
+            CheckpointCommands::List(cli) => cli.handle(channel).await,
+            CheckpointCommands::Delete(cli) => cli.handle(channel).await,
+        }
+    }
+}
+
+#[derive(Parser)]
+pub struct SaveCli {
+    /// Workspace name
+    #[arg(short, long)]
+    pub workspace: String,
+
+    /// Checkpoint name
+    pub name: String,
+}
+
+impl SaveCli {
+    pub async fn handle(&self, channel: &Channel) -> Result<()> {
+        let mut client = WorkspaceServiceClient::new(channel.clone());
+
+        client
+            .save_checkpoint(SaveCheckpointReq {
+                workspace_name: self.workspace.clone(),
+                checkpoint_name: self.name.clone(),
+            })
+            .await?;
+
+        println!(
+            "{}",
+            style(format!("Checkpoint {} saved successfully!", self.name)).green()
+        );
+        Ok(())
+    }
+}
+
+#[derive(Parser)]
+pub struct RestoreCli {
+    /// Workspace name
+    #[arg(short, long)]
+    pub workspace: String,
+
+    /// Checkpoint name
+    pub name: String,
+}
+
+impl RestoreCli {
+    pub async fn handle(&self, channel: &Channel) -> Result<()> {
+        let mut client = WorkspaceServiceClient::new(channel.clone());
+
+        println!(
+            "{}",
+            style(format!("Restoring checkpoint {}...", self.name)).cyan()
+        );
+
+        let response = client
+            .restore_checkpoint(RestoreCheckpointReq {
+                workspace_name: self.workspace.clone(),
+                checkpoint_name: self.name.clone(),
+            })
+            .await?
+            .into_inner();
+
+        for path in &response.restored_paths {
+            println!("  {} {}", style("✓").green(), path);
+        }
+        for path in &response.removed_paths {
+            println!("  {} {}", style("-").red(), path);
+        }
+
+        println!(
+            "{}",
+            style(format!(
+                "Checkpoint {} restored: {} file(s) restored, {} file(s) removed.",
+                self.name,
+                response.restored_paths.len(),
+                response.removed_paths.len()
+            ))
+            .green()
+        );
+        Ok(())
+    }
+}
+
+#[derive(Parser)]
+pub struct ListCli {
+    /// Workspace name
+    #[arg(short, long)]
+    pub workspace: String,
+}
+
+#[derive(Tabled)]
+struct CheckpointRow {
+    #[tabled(rename = "Name")]
+    name: String,
+    #[tabled(rename = "Created At")]
+    created_at: String,
+    #[tabled(rename = "Active Files")]
+    active_file_count: i32,
+    #[tabled(rename = "Synced Files")]
+    file_count: i32,
+}
+
+impl ListCli {
+    pub async fn handle(&self, channel: &Channel) -> Result<()> {
+        let mut client = WorkspaceServiceClient::new(channel.clone());
+
+        let response = client
+            .list_checkpoints(ListCheckpointsReq {
+                workspace_name: self.workspace.clone(),
+            })
+            .await?
+            .into_inner();
+
+        if super::json_output() {
+            return super::print_json(&response);
+        }
+
+        if response.checkpoints.is_empty() {
+            println!("{}", style("No checkpoints found.").yellow());
+            return Ok(());
+        }
+
+        let rows: Vec<CheckpointRow> = response
+            .checkpoints
+            .into_iter()
+            .map(|c| CheckpointRow {
+                name: c.name,
+                created_at: DateTime::from_timestamp(c.created_at, 0)
+                    .map(|t| t.format("%Y-%m-%d %H:%M:%S").to_string())
+                    .unwrap_or_default(),
+                active_file_count: c.active_file_count,
+                file_count: c.file_count,
+            })
+            .collect();
+
+        let mut table = Table::new(&rows);
+        table.with(Style::rounded());
+
+        println!("\n{}", table);
+        println!("\n{} checkpoint(s) found", style(rows.len()).cyan());
+
+        Ok(())
+    }
+}
+
+#[derive(Parser)]
+pub struct DeleteCli {
+    /// Workspace name
+    #[arg(short, long)]
+    pub workspace: String,
+
+    /// Checkpoint name
+    pub name: String,
+}
+
+impl DeleteCli {
+    pub async fn handle(&self, channel: &Channel) -> Result<()> {
+        let mut client = WorkspaceServiceClient::new(channel.clone());
+
+        client
+            .delete_checkpoint(DeleteCheckpointReq {
+                workspace_name: self.workspace.clone(),
+                checkpoint_name: self.name.clone(),
+            })
+            .await?;
+
+        println!(
+            "{}",
+            style(format!("Checkpoint {} deleted successfully!", self.name)).green()
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,203 @@
+use std::io::{self, Write};
+
+use anyhow::Result;
+use clap::{CommandFactory, Parser, ValueEnum};
+use crv_edge::pb::{
+    ListActiveFilesReq, ListBranchesReq, ListWorkspacesReq,
+    branch_service_client::BranchServiceClient, file_service_client::FileServiceClient,
+    workspace_service_client::WorkspaceServiceClient,
+};
+use tonic::transport::Channel;
+
+use super::Cli;
+
+const BIN_NAME: &str = "crv";
+
+/// 补全脚本支持的 shell
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+    Powershell,
+}
+
+impl Shell {
+    fn generator(self) -> clap_complete::Shell {
+        match self {
+            Shell::Bash => clap_complete::Shell::Bash,
+            Shell::Zsh => clap_complete::Shell::Zsh,
+            Shell::Fish => clap_complete::Shell::Fish,
+            Shell::Powershell => clap_complete::Shell::PowerShell,
+        }
+    }
+
+    /// 追加在静态补全之后的脚本，运行时通过 `crv complete-values` 补全已打开的文件和分支名
+    fn dynamic_script(self) -> &'static str {
+        match self {
+            Shell::Bash => BASH_DYNAMIC,
+            Shell::Zsh => ZSH_DYNAMIC,
+            Shell::Fish => FISH_DYNAMIC,
+            // PowerShell 只能注册一个补全器，暂时只提供静态补全
+            Shell::Powershell => "",
+        }
+    }
+}
+
+const BASH_DYNAMIC: &str = r#"
+_crv_dynamic() {
+    local cur="${COMP_WORDS[COMP_CWORD]}"
+    local prev="${COMP_WORDS[COMP_CWORD-1]}"
+    if [[ "$prev" == "--branch" || "$prev" == "-b" ]]; then
+        COMPREPLY=( $(compgen -W "$(crv complete-values branches 2>/dev/null)" -- "$cur") )
+        return 0
+    fi
+    if [[ ${COMP_CWORD} -ge 2 && "$cur" != -* && "$prev" != -* ]]; then
+        case "${COMP_WORDS[1]}" in
+            submit|describe|lock)
+                COMPREPLY=( $(compgen -W "$(crv complete-values paths 2>/dev/null)" -- "$cur") )
+                if [[ ${#COMPREPLY[@]} -gt 0 ]]; then
+                    return 0
+                fi
+                ;;
+        esac
+    fi
+    _crv "$@"
+}
+complete -F _crv_dynamic -o nosort -o bashdefault -o default crv
+"#;
+
+const ZSH_DYNAMIC: &str = r#"
+_crv_dynamic() {
+    if [[ ${words[CURRENT-1]} == --branch || ${words[CURRENT-1]} == -b ]]; then
+        compadd -- ${(f)"$(crv complete-values branches 2>/dev/null)"}
+        return
+    fi
+    if (( CURRENT > 2 )) && [[ ${words[CURRENT]} != -* && ${words[CURRENT-1]} != -* ]]; then
+        case ${words[2]} in
+            submit|describe|lock)
+                compadd -- ${(f)"$(crv complete-values paths 2>/dev/null)"}
+                ;;
+        esac
+    fi
+    _crv "$@"
+}
+compdef _crv_dynamic crv
+"#;
+
+const FISH_DYNAMIC: &str = r#"
+complete -c crv -l branch -s b -f -r -a '(crv complete-values branches 2>/dev/null)'
+complete -c crv -n '__fish_seen_subcommand_from submit describe lock' -a '(crv complete-values paths 2>/dev/null)'
+"#;
+
+/// 输出 `shell` 的补全脚本
+pub fn write_completion(shell: Shell, out: &mut dyn Write) -> io::Result<()> {
+    let mut command = Cli::command();
+    clap_complete::generate(shell.generator(), &mut command, BIN_NAME, out);
+    out.write_all(shell.dynamic_script().as_bytes())
+}
+
+/// Print a shell completion script, e.g. `source <(crv completion bash)`
+#[derive(Parser)]
+pub struct CompletionCli {
+    /// Target shell
+    #[arg(value_enum)]
+    pub shell: Shell,
+}
+
+impl CompletionCli {
+    pub fn handle(&self) -> Result<()> {
+        write_completion(self.shell, &mut io::stdout())?;
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum ValueKind {
+    /// Files opened in any workspace, as listed by `crv showactive`
+    Paths,
+    /// Branch names, as listed by `crv branch list`
+    Branches,
+}
+
+/// Print completion candidates one per line, used by the completion scripts
+#[derive(Parser)]
+#[command(hide = true)]
+pub struct CompleteValuesCli {
+    #[arg(value_enum)]
+    pub kind: ValueKind,
+}
+
+impl CompleteValuesCli {
+    pub async fn handle(&self, channel: &Channel) -> Result<()> {
+        let values = match self.kind {
+            ValueKind::Paths => active_file_paths(channel).await?,
+            ValueKind::Branches => branch_names(channel).await?,
+        };
+        for value in values {
+            println!("{value}");
+        }
+        Ok(())
+    }
+}
+
+async fn active_file_paths(channel: &Channel) -> Result<Vec<String>> {
+    let workspace_names = WorkspaceServiceClient::new(channel.clone())
+        .list_workspaces(ListWorkspacesReq {})
+        .await?
+        .into_inner()
+        .workspace_names;
+
+    let mut client = FileServiceClient::new(channel.clone());
+    let mut paths = Vec::new();
+    for workspace_name in workspace_names {
+        let response = client
+            .list_active_files(ListActiveFilesReq {
+                workspace_name,
+                path: ".".to_string(),
+            })
+            .await?
+            .into_inner();
+        paths.extend(response.active_files.into_iter().map(|file| file.path));
+    }
+    Ok(paths)
+}
+
+async fn branch_names(channel: &Channel) -> Result<Vec<String>> {
+    let mut client = BranchServiceClient::new(channel.clone());
+    let mut names = Vec::new();
+    for page in 0.. {
+        let response = client
+            .list_branches(ListBranchesReq {
+                page,
+                page_size: 0,
+                sort_by: 0,
+            })
+            .await?
+            .into_inner();
+        if response.branches.is_empty() {
+            break;
+        }
+        names.extend(response.branches.into_iter().map(|branch| branch.id));
+        if names.len() as u64 >= response.total_count {
+            break;
+        }
+    }
+    Ok(names)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn completion_is_generated_for_every_shell() {
+        for shell in Shell::value_variants() {
+            let mut out = Vec::new();
+            write_completion(*shell, &mut out).unwrap();
+            let script = String::from_utf8(out).unwrap();
+            assert!(!script.is_empty(), "empty completion for {shell:?}");
+            assert!(script.contains("showactive"), "{shell:?}");
+        }
+    }
+}
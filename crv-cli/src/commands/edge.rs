@@ -1,12 +1,18 @@
 use std::collections::BTreeMap;
 
 use anyhow::Result;
+use chrono::DateTime;
 use clap::{Parser, Subcommand};
 use console::{Emoji, style};
 use crv_edge::{
     daemon_server::config::BootstrapConfig,
-    pb::{BonjourReq, GetRuntimeConfigReq, system_service_client::SystemServiceClient},
+    pb::{
+        ApplyUpdateReq, BonjourReq, CheckUpdateReq, GetConfigReq, GetRepositoryStatsReq,
+        GetRuntimeConfigReq, HealthCheckReq, HealthStatus, SetConfigReq, StartWatchReq,
+        StopWatchReq, system_service_client::SystemServiceClient,
+    },
 };
+use tabled::{Table, Tabled, settings::Style};
 use tonic::transport::Channel;
 
 #[derive(Parser)]
@@ -22,6 +28,7 @@ impl EdgeCli {
             EdgeCommands::Bonjour(bonjour) => bonjour.handle(channel).await,
             EdgeCommands::BootstrapConfig(bootstrap_config) => bootstrap_config.handle().await,
             EdgeCommands::RuntimeConfig(runtime_config) => runtime_config.handle(channel).await,
+            EdgeCommands::Update(update) => update.handle(channel).await,
         }
     }
 }
@@ -31,6 +38,7 @@ pub enum EdgeCommands {
     Bonjour(BonjourCli),
     BootstrapConfig(BootstrapConfigCli),
     RuntimeConfig(RuntimeConfigCli),
+    Update(UpdateCli),
 }
 
 #[derive(Parser)]
@@ -55,10 +63,59 @@ impl BootstrapConfigCli {
 
         let mut settings = BTreeMap::new();
         settings.insert("daemon_port", format!("{}", bootstrap_config.daemon_port));
+        if let Some(daemon_bind_addr) = &bootstrap_config.daemon_bind_addr {
+            settings.insert("daemon_bind_addr", daemon_bind_addr.clone());
+        }
         settings.insert(
             "embedded_database_root",
             bootstrap_config.embedded_database_root.to_string(),
         );
+        settings.insert("hive_tls", format!("{}", bootstrap_config.hive_tls));
+        settings.insert(
+            "request_timeout_secs",
+            format!("{}", bootstrap_config.request_timeout_secs),
+        );
+        settings.insert(
+            "hive_failure_threshold",
+            format!("{}", bootstrap_config.hive_failure_threshold),
+        );
+        settings.insert(
+            "hive_circuit_open_secs",
+            format!("{}", bootstrap_config.hive_circuit_open_secs),
+        );
+        settings.insert("metrics_port", format!("{}", bootstrap_config.metrics_port));
+        settings.insert(
+            "disable_metrics",
+            format!("{}", bootstrap_config.disable_metrics),
+        );
+        settings.insert(
+            "auto_track_changes",
+            format!("{}", bootstrap_config.auto_track_changes),
+        );
+        if let Some(upload_bandwidth_kbps) = bootstrap_config.upload_bandwidth_kbps {
+            settings.insert("upload_bandwidth_kbps", format!("{upload_bandwidth_kbps}"));
+        }
+        if let Some(pre_submit_hook) = &bootstrap_config.pre_submit_hook {
+            settings.insert("pre_submit_hook", pre_submit_hook.clone());
+        }
+        if let Some(update_url) = &bootstrap_config.update_url {
+            settings.insert("update_url", update_url.clone());
+        }
+        for (key, value) in [
+            ("hive_ca_cert_path", &bootstrap_config.hive_ca_cert_path),
+            (
+                "hive_client_cert_path",
+                &bootstrap_config.hive_client_cert_path,
+            ),
+            (
+                "hive_client_key_path",
+                &bootstrap_config.hive_client_key_path,
+            ),
+        ] {
+            if let Some(value) = value {
+                settings.insert(key, value.clone());
+            }
+        }
 
         println!(
             "\n{}\n",
@@ -153,3 +210,257 @@ impl RuntimeConfigCli {
         Ok(())
     }
 }
+
+#[derive(Parser)]
+#[command(about = "Persist a runtime setting of the edge daemon, it takes effect immediately.", long_about = None)]
+pub struct SetCli {
+    /// Setting name: remote_addr, editor or user
+    pub key: String,
+    pub value: String,
+}
+
+impl SetCli {
+    pub async fn handle(&self, channel: &Channel) -> Result<()> {
+        let mut client = SystemServiceClient::new(channel.clone());
+        client
+            .set_config(SetConfigReq {
+                key: self.key.clone(),
+                value: self.value.clone(),
+            })
+            .await?;
+        println!(
+            "{} {} {} {}",
+            style("✓").green(),
+            style(&self.key).cyan(),
+            style("→").dim(),
+            style(&self.value).yellow()
+        );
+        Ok(())
+    }
+}
+
+#[derive(Parser)]
+#[command(about = "Show the effective value of a runtime setting.", long_about = None)]
+pub struct GetCli {
+    /// Setting name: remote_addr, editor or user
+    pub key: String,
+}
+
+impl GetCli {
+    pub async fn handle(&self, channel: &Channel) -> Result<()> {
+        let mut client = SystemServiceClient::new(channel.clone());
+        let item = client
+            .get_config(GetConfigReq {
+                key: self.key.clone(),
+            })
+            .await?
+            .into_inner()
+            .item
+            .unwrap_or_default();
+        println!(
+            "{} {} {} {}",
+            style(&self.key).cyan(),
+            style("→").dim(),
+            style(format!("\"{}\"", item.value)).yellow(),
+            style(format!("({})", item.source)).dim()
+        );
+        Ok(())
+    }
+}
+
+#[derive(Parser)]
+#[command(about = "Check edge daemon health, exit non-zero if anything is unhealthy.", long_about = None)]
+pub struct PingCli;
+
+impl PingCli {
+    pub async fn handle(&self, channel: &Channel) -> Result<()> {
+        let mut client = SystemServiceClient::new(channel.clone());
+        let health = client.health_check(HealthCheckReq {}).await?.into_inner();
+
+        let check = |ok: bool| {
+            if ok {
+                style("✓").green()
+            } else {
+                style("✗").red()
+            }
+        };
+        println!("{}", style("HEALTH").bold().dim());
+        println!(" {} rocksdb", check(health.rocksdb_ok));
+        println!(" {} hive", check(health.hive_reachable));
+        println!(
+            " {} disk free {}",
+            Emoji("●", "*"),
+            style(format!("{} bytes", health.disk_free_bytes)).yellow()
+        );
+
+        let status = health.status();
+        if status != HealthStatus::Healthy {
+            anyhow::bail!("Edge is {}", status.as_str_name());
+        }
+        println!("{}", style("Edge is healthy.").bold().green());
+        Ok(())
+    }
+}
+
+#[derive(Parser)]
+#[command(about = "Show repository-wide statistics reported by hive.", long_about = None)]
+pub struct InfoCli;
+
+#[derive(Tabled)]
+struct InfoRow {
+    #[tabled(rename = "Field")]
+    field: &'static str,
+    #[tabled(rename = "Value")]
+    value: String,
+}
+
+impl InfoCli {
+    pub async fn handle(&self, channel: &Channel) -> Result<()> {
+        let mut client = SystemServiceClient::new(channel.clone());
+        let stats = client
+            .get_repository_stats(GetRepositoryStatsReq {})
+            .await?
+            .into_inner();
+
+        let format_time = |timestamp: i64| {
+            if timestamp == 0 {
+                return "-".to_string();
+            }
+            DateTime::from_timestamp(timestamp, 0)
+                .map(|t| t.format("%Y-%m-%d %H:%M:%S").to_string())
+                .unwrap_or_default()
+        };
+        let rows = vec![
+            InfoRow {
+                field: "Files",
+                value: stats.total_files.to_string(),
+            },
+            InfoRow {
+                field: "Changelists",
+                value: stats.total_changelists.to_string(),
+            },
+            InfoRow {
+                field: "Branches",
+                value: stats.total_branches.to_string(),
+            },
+            InfoRow {
+                field: "Chunk Storage",
+                value: format!("{} bytes", stats.total_chunk_bytes),
+            },
+            InfoRow {
+                field: "Dedup Ratio",
+                value: format!("{:.2}", stats.dedup_ratio),
+            },
+            InfoRow {
+                field: "Oldest Changelist",
+                value: format_time(stats.oldest_cl_timestamp),
+            },
+            InfoRow {
+                field: "Newest Changelist",
+                value: format_time(stats.newest_cl_timestamp),
+            },
+        ];
+
+        let mut table = Table::new(&rows);
+        table.with(Style::rounded());
+        println!("{}", table);
+        Ok(())
+    }
+}
+
+#[derive(Parser)]
+#[command(about = "Automatically open modified files for edit.", long_about = None)]
+pub struct WatchCli {
+    #[command(subcommand)]
+    pub watch_commands: WatchCommands,
+}
+
+#[derive(Subcommand)]
+pub enum WatchCommands {
+    /// Start watching all workspaces
+    Start,
+    /// Stop watching
+    Stop,
+}
+
+impl WatchCli {
+    pub async fn handle(&self, channel: &Channel) -> Result<()> {
+        let mut client = SystemServiceClient::new(channel.clone());
+        match self.watch_commands {
+            WatchCommands::Start => {
+                let response = client.start_watch(StartWatchReq {}).await?.into_inner();
+                if response.workspace_names.is_empty() {
+                    println!("{}", style("No workspaces found.").yellow());
+                    return Ok(());
+                }
+                for workspace_name in &response.workspace_names {
+                    println!("  {} {}", style("✓").green(), workspace_name);
+                }
+                println!(
+                    "{}",
+                    style(format!(
+                        "Watching {} workspace(s).",
+                        response.workspace_names.len()
+                    ))
+                    .green()
+                );
+            }
+            WatchCommands::Stop => {
+                let response = client.stop_watch(StopWatchReq {}).await?.into_inner();
+                if response.was_running {
+                    println!("{}", style("Stopped watching workspaces.").green());
+                } else {
+                    println!("{}", style("Watcher is not running.").yellow());
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Parser)]
+#[command(about = "Update the edge daemon to the latest version and restart it.", long_about = None)]
+pub struct UpdateCli {
+    /// Only check whether a newer version is available
+    #[arg(long)]
+    pub check: bool,
+}
+
+impl UpdateCli {
+    pub async fn handle(&self, channel: &Channel) -> Result<()> {
+        let mut client = SystemServiceClient::new(channel.clone());
+        if self.check {
+            let response = client.check_update(CheckUpdateReq {}).await?.into_inner();
+            if response.update_available {
+                println!(
+                    "{} {} -> {}",
+                    style("Update available:").green(),
+                    response.current_version,
+                    style(&response.latest_version).cyan()
+                );
+            } else {
+                println!(
+                    "{}",
+                    style(format!("Edge {} is up to date.", response.current_version)).yellow()
+                );
+            }
+            return Ok(());
+        }
+
+        let response = client.apply_update(ApplyUpdateReq {}).await?.into_inner();
+        if response.installed_version.is_empty() {
+            println!(
+                "{}",
+                style(format!("Edge {} is up to date.", response.previous_version)).yellow()
+            );
+        } else {
+            println!(
+                "{} Updated edge {} -> {}, restarting",
+                style("✓").green(),
+                response.previous_version,
+                style(&response.installed_version).cyan()
+            );
+        }
+        Ok(())
+    }
+}
@@ -1,10 +1,21 @@
 use std::process;
 
 use anyhow::Result;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime};
 use clap::Parser;
 use console::style;
-use crv_edge::pb::{AddReq, DeleteReq, ListActiveFilesReq, SubmitReq, SyncReq, file_service_client::FileServiceClient, CheckoutReq};
+use crv_edge::pb::{
+    AddReq, CheckoutReq, DeleteReq, DescribeReq, GetFileContentReq, ListActiveFilesReq,
+    ListLockedFilesReq, LockReq, MoveReq, SubmitReq, SyncEventStatus, SyncWithProgressReq,
+    file_service_client::FileServiceClient,
+};
 use dialoguer::{Input, theme::ColorfulTheme};
+use indicatif::{ProgressBar, ProgressStyle};
+use similar::{ChangeTag, TextDiff};
+use std::collections::HashMap;
+use std::io::{self, IsTerminal};
+use tabled::{Table, Tabled, settings::Style};
+use termcolor::{Color, ColorChoice, ColorSpec, StandardStream, WriteColor};
 use tokio::signal;
 use tokio_stream::StreamExt;
 use tonic::transport::Channel;
@@ -15,7 +26,8 @@ pub struct AddCli {
     #[arg(short, long)]
     pub workspace: String,
 
-    /// Paths to add (can be local paths, workspace paths, or depot paths)
+    /// Paths to add (can be local paths, workspace paths, or depot paths).
+    /// A depot wildcard such as //depot/src/... adds every new or changed file under it
     #[arg(required = true)]
     pub paths: Vec<String>,
 }
@@ -83,7 +95,6 @@ impl CheckoutCli {
     }
 }
 
-
 #[derive(Parser)]
 pub struct SubmitCli {
     /// Workspace name
@@ -97,6 +108,14 @@ pub struct SubmitCli {
     /// Submit description
     #[arg(short, long)]
     pub description: Option<String>,
+
+    /// Check for conflicts, locks and missing chunks without submitting
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// Branch to submit to; defaults to the default branch
+    #[arg(short, long)]
+    pub branch: Option<String>,
 }
 
 impl SubmitCli {
@@ -112,12 +131,18 @@ impl SubmitCli {
                 .interact_text()?
         };
 
-        println!("{}", style("Submitting files...").cyan());
+        if self.dry_run {
+            println!("{}", style("Checking files (dry run)...").cyan());
+        } else {
+            println!("{}", style("Submitting files...").cyan());
+        }
 
         let request = SubmitReq {
             workspace_name: self.workspace.clone(),
             paths: self.paths.clone(),
             description,
+            dry_run: self.dry_run,
+            branch_id: self.branch.clone().unwrap_or_default(),
         };
 
         let mut stream = client.submit(request).await?.into_inner();
@@ -158,7 +183,14 @@ impl SubmitCli {
             }
         }
 
-        println!("{}", style("Submit completed successfully!").green());
+        if self.dry_run {
+            println!(
+                "{}",
+                style("Dry run passed, nothing was submitted.").green()
+            );
+        } else {
+            println!("{}", style("Submit completed successfully!").green());
+        }
         Ok(())
     }
 }
@@ -176,79 +208,204 @@ pub struct SyncCli {
     /// Force sync even if files are already up to date
     #[arg(short, long, default_value = "false")]
     pub force: bool,
+
+    /// Sync to the last changelist committed at or before this UTC time
+    /// (e.g. "2026-10-16 12:00:00", "2026-10-16" or RFC 3339)
+    #[arg(long, value_parser = parse_as_of)]
+    pub as_of: Option<i64>,
+
+    /// Sync to the file state at this changelist instead of the latest one;
+    /// checked-out files are left untouched
+    #[arg(
+        long = "at-cl",
+        value_name = "CHANGELIST",
+        conflicts_with = "as_of",
+        value_parser = clap::value_parser!(i64).range(1..)
+    )]
+    pub at_cl: Option<i64>,
+
+    /// Only use the file tree cached by the last sync, without contacting the hive
+    #[arg(long, conflicts_with_all = ["as_of", "at_cl"])]
+    pub offline: bool,
+}
+
+/// 将 `--as-of` 参数解析为 UTC 毫秒时间戳，不带时区的时间按 UTC 处理
+fn parse_as_of(value: &str) -> Result<i64, String> {
+    let value = value.trim();
+    if let Ok(datetime) = DateTime::parse_from_rfc3339(value) {
+        return Ok(datetime.timestamp_millis());
+    }
+    for format in ["%Y-%m-%d %H:%M:%S", "%Y-%m-%d %H:%M"] {
+        if let Ok(datetime) = NaiveDateTime::parse_from_str(value, format) {
+            return Ok(datetime.and_utc().timestamp_millis());
+        }
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        return Ok(date.and_time(NaiveTime::MIN).and_utc().timestamp_millis());
+    }
+    Err(format!(
+        "invalid time '{value}', expected \"YYYY-MM-DD[ HH:MM[:SS]]\" or RFC 3339"
+    ))
+}
+
+fn sync_status_name(status: SyncEventStatus) -> &'static str {
+    match status {
+        SyncEventStatus::Unspecified => "unspecified",
+        SyncEventStatus::Pending => "pending",
+        SyncEventStatus::Downloading => "downloading",
+        SyncEventStatus::Verifying => "verifying",
+        SyncEventStatus::Done => "done",
+        SyncEventStatus::Failed => "failed",
+    }
 }
 
 impl SyncCli {
     pub async fn handle(&self, channel: &Channel) -> Result<()> {
         let mut client = FileServiceClient::new(channel.clone());
 
-        println!("{}", style("Syncing files...").cyan());
-
-        let request = SyncReq {
+        let request = SyncWithProgressReq {
             workspace_name: self.workspace.clone(),
             paths: self.paths.clone(),
             force: self.force,
+            changelist_id: self.at_cl.unwrap_or(0),
+            as_of_millis: self.as_of.unwrap_or(0),
+            offline: self.offline,
         };
 
-        let mut stream = client.sync(request).await?.into_inner();
+        // `--json` 时逐行输出进度事件
+        let json = super::json_output();
+        if !json {
+            println!("{}", style("Syncing files...").cyan());
+        }
+
+        let mut stream = client.sync_with_progress(request).await?.into_inner();
 
         // Spawn Ctrl+C handler
         tokio::spawn(async move {
             if let Ok(_) = signal::ctrl_c().await {
-                println!("\n{}", style("Cancelling sync...").bold().yellow());
+                if !json {
+                    println!("\n{}", style("Cancelling sync...").bold().yellow());
+                }
                 process::exit(0);
             }
         });
 
-        // Process the stream
-        while let Some(progress) = stream.next().await {
-            match progress {
-                Ok(p) => {
-                    if let Some(payload) = p.payload {
-                        use crv_edge::pb::sync_progress::Payload;
-                        match payload {
-                            Payload::Metadata(meta) => {
-                                let total_files_to_sync = meta.files.len();
-                                let total_bytes_to_sync =
-                                    meta.files.iter().map(|x| x.size).sum::<i64>();
-                                println!(
-                                    "Total files to sync: {}, total size: {} bytes",
-                                    total_files_to_sync, total_bytes_to_sync
-                                );
-                            }
-                            Payload::FileUpdate(update) => {
-                                let status_icon = if !update.warning.is_empty() {
-                                    style("!").yellow()
-                                } else {
-                                    style("✓").green()
-                                };
-
-                                println!(
-                                    "  {} {} [{}] (completed: {} bytes)",
-                                    status_icon,
-                                    update.path,
-                                    update.info,
-                                    update.bytes_completed_so_far
-                                );
-
-                                if !update.info.is_empty() {
-                                    println!("    {}", style(update.info).dim());
-                                }
-                                if !update.warning.is_empty() {
-                                    println!("    {}", style(update.warning).yellow());
-                                }
-                            }
-                        }
-                    }
-                }
+        let pb = if !json && console::Term::stdout().is_term() {
+            let pb = ProgressBar::new(0);
+            pb.set_style(
+                ProgressStyle::with_template(
+                    "{spinner:.green} [{elapsed_precise}] {bar:40.cyan/blue} {bytes:>9}/{total_bytes:9} {msg}",
+                )
+                .unwrap()
+                .progress_chars("##-"),
+            );
+            Some(pb)
+        } else {
+            None
+        };
+
+        // 每个文件已计入进度条的字节数
+        let mut downloaded: HashMap<String, i64> = HashMap::new();
+        let mut done_count = 0;
+        let mut failed_count = 0;
+
+        while let Some(event) = stream.next().await {
+            let event = match event {
+                Ok(event) => event,
                 Err(e) => {
+                    if let Some(pb) = &pb {
+                        pb.abandon();
+                    }
                     eprintln!("{} {}", style("Error:").red(), e);
                     return Err(e.into());
                 }
+            };
+            let status = event.status();
+
+            if json {
+                println!(
+                    "{}",
+                    serde_json::json!({
+                        "filePath": event.file_path,
+                        "bytesDownloaded": event.bytes_downloaded,
+                        "bytesTotal": event.bytes_total,
+                        "status": sync_status_name(status),
+                        "error": event.error,
+                    })
+                );
+            }
+
+            match status {
+                SyncEventStatus::Pending => {
+                    if let Some(pb) = &pb {
+                        pb.inc_length(event.bytes_total.max(0) as u64);
+                    }
+                }
+                SyncEventStatus::Downloading | SyncEventStatus::Verifying => {
+                    if let Some(pb) = &pb {
+                        let previous =
+                            downloaded.insert(event.file_path.clone(), event.bytes_downloaded);
+                        pb.inc((event.bytes_downloaded - previous.unwrap_or(0)).max(0) as u64);
+                        pb.set_message(event.file_path.clone());
+                    }
+                }
+                SyncEventStatus::Done => {
+                    done_count += 1;
+                    if let Some(pb) = &pb {
+                        // 删除或未下载完整的文件也按总大小计入，保证结束时进度条走满
+                        let previous = downloaded.remove(&event.file_path).unwrap_or(0);
+                        pb.inc((event.bytes_total - previous).max(0) as u64);
+                    } else if !json {
+                        println!("  {} {}", style("✓").green(), event.file_path);
+                    }
+                }
+                SyncEventStatus::Failed => {
+                    failed_count += 1;
+                    if let Some(pb) = &pb {
+                        let previous = downloaded.remove(&event.file_path).unwrap_or(0);
+                        pb.inc((event.bytes_total - previous).max(0) as u64);
+                        pb.println(format!(
+                            "  {} {}: {}",
+                            style("✗").red(),
+                            event.file_path,
+                            event.error
+                        ));
+                    } else if !json {
+                        println!(
+                            "  {} {}: {}",
+                            style("✗").red(),
+                            event.file_path,
+                            style(&event.error).yellow()
+                        );
+                    }
+                }
+                SyncEventStatus::Unspecified => {}
+            }
+        }
+
+        if let Some(pb) = &pb {
+            pb.finish_and_clear();
+        }
+
+        if failed_count > 0 {
+            if !json {
+                println!(
+                    "{} {} file(s) synced, {} file(s) failed",
+                    style("!").yellow(),
+                    style(done_count).cyan(),
+                    style(failed_count).red()
+                );
             }
+            anyhow::bail!("{} file(s) failed to sync", failed_count);
         }
 
-        println!("{}", style("Sync completed successfully!").green());
+        if !json {
+            println!(
+                "{} {} file(s) synced",
+                style("Sync completed successfully!").green(),
+                style(done_count).cyan()
+            );
+        }
         Ok(())
     }
 }
@@ -294,6 +451,200 @@ impl DeleteCli {
     }
 }
 
+#[derive(Parser)]
+pub struct DescribeCli {
+    /// Workspace name
+    #[arg(short, long, required_unless_present = "changelist")]
+    pub workspace: Option<String>,
+
+    /// Paths to describe (can be local paths or workspace paths)
+    #[arg(required_unless_present = "changelist")]
+    pub paths: Vec<String>,
+
+    /// Describe a changelist instead of files
+    #[arg(short, long, conflicts_with_all = ["workspace", "paths"])]
+    pub changelist: Option<String>,
+}
+
+#[derive(Tabled)]
+struct DescribeRow {
+    #[tabled(rename = "Field")]
+    field: String,
+    #[tabled(rename = "Value")]
+    value: String,
+}
+
+impl DescribeCli {
+    pub async fn handle(&self, channel: &Channel) -> Result<()> {
+        if let Some(changelist_id) = &self.changelist {
+            return super::changelist::describe_changelist(
+                channel,
+                changelist_id,
+                true,
+                super::json_output(),
+            )
+            .await;
+        }
+
+        let mut client = FileServiceClient::new(channel.clone());
+
+        let request = DescribeReq {
+            workspace_name: self.workspace.clone().unwrap_or_default(),
+            paths: self.paths.clone(),
+        };
+
+        let response = client.describe(request).await?.into_inner();
+
+        if super::json_output() {
+            let files = response
+                .files
+                .iter()
+                .map(|f| {
+                    serde_json::json!({
+                        "workspacePath": f.workspace_path,
+                        "depotPath": f.depot_path,
+                        "localPath": f.local_path,
+                        "generation": f.generation,
+                        "revision": f.revision,
+                        "changelistId": f.changelist_id,
+                        "size": f.size,
+                        "chunkHashes": f.chunk_hashes,
+                        "action": f.action,
+                        "fileMode": format!("{:o}", f.file_mode),
+                        "lockedBy": f.locked_by,
+                        "lockTicket": f.lock_ticket,
+                        "firstChangelistId": f.first_changelist_id,
+                        "firstIntroducedBy": f.first_introduced_by,
+                    })
+                })
+                .collect::<Vec<_>>();
+            println!("{}", serde_json::to_string_pretty(&files)?);
+            return Ok(());
+        }
+
+        if response.files.is_empty() {
+            println!("{}", style("No synced files found.").yellow());
+            return Ok(());
+        }
+
+        for file in response.files {
+            let rows = vec![
+                DescribeRow {
+                    field: "Workspace Path".to_string(),
+                    value: file.workspace_path.clone(),
+                },
+                DescribeRow {
+                    field: "Depot Path".to_string(),
+                    value: file.depot_path,
+                },
+                DescribeRow {
+                    field: "Local Path".to_string(),
+                    value: file.local_path,
+                },
+                DescribeRow {
+                    field: "Revision".to_string(),
+                    value: format!("{}:{}", file.generation, file.revision),
+                },
+                DescribeRow {
+                    field: "Changelist".to_string(),
+                    value: file.changelist_id.to_string(),
+                },
+                DescribeRow {
+                    field: "Size".to_string(),
+                    value: format!("{} bytes", file.size),
+                },
+                DescribeRow {
+                    field: "Chunks".to_string(),
+                    value: file.chunk_hashes.join("\n"),
+                },
+                DescribeRow {
+                    field: "Opened For".to_string(),
+                    value: if file.action.is_empty() {
+                        "-".to_string()
+                    } else {
+                        file.action
+                    },
+                },
+                DescribeRow {
+                    field: "Mode".to_string(),
+                    value: if file.file_mode == 0 {
+                        "-".to_string()
+                    } else {
+                        format!("{:o}", file.file_mode)
+                    },
+                },
+                DescribeRow {
+                    field: "Locked By".to_string(),
+                    value: match (file.locked_by.is_empty(), file.lock_ticket.is_empty()) {
+                        (true, _) => "-".to_string(),
+                        (false, true) => file.locked_by,
+                        (false, false) => {
+                            format!("{} (ticket {})", file.locked_by, file.lock_ticket)
+                        }
+                    },
+                },
+                DescribeRow {
+                    field: "First Introduced".to_string(),
+                    value: format!(
+                        "changelist {} by {}",
+                        file.first_changelist_id, file.first_introduced_by
+                    ),
+                },
+            ];
+
+            let mut table = Table::new(rows);
+            table.with(Style::rounded());
+            println!("{}", table);
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Parser)]
+pub struct MoveCli {
+    /// Workspace name
+    #[arg(short, long)]
+    pub workspace: String,
+
+    /// Source file (local path or workspace path)
+    pub src: String,
+
+    /// Destination file, or a directory ending with a path separator to keep the file name
+    pub dest: String,
+
+    /// Local changelist to put the move into
+    #[arg(short, long)]
+    pub changelist: Option<String>,
+}
+
+impl MoveCli {
+    pub async fn handle(&self, channel: &Channel) -> Result<()> {
+        let mut client = FileServiceClient::new(channel.clone());
+
+        println!("{}", style("Moving file...").cyan());
+
+        let request = MoveReq {
+            workspace_name: self.workspace.clone(),
+            from_path: self.src.clone(),
+            to_path: self.dest.clone(),
+            changelist_id: self.changelist.clone().unwrap_or_default(),
+        };
+
+        let response = client.r#move(request).await?.into_inner();
+
+        println!(
+            "  {} {} {} {}",
+            style("✓").green(),
+            response.from_path,
+            style("→").dim(),
+            response.to_path
+        );
+        println!("{}", style("Moved 1 file successfully!").green());
+        Ok(())
+    }
+}
+
 #[derive(Parser)]
 pub struct RevertCli;
 
@@ -325,6 +676,10 @@ impl ListActiveFilesCli {
 
         let response = client.list_active_files(request).await?.into_inner();
 
+        if super::json_output() {
+            return super::print_json(&response);
+        }
+
         if response.active_files.is_empty() {
             println!("{}", style("No active files found.").yellow());
         } else {
@@ -339,7 +694,7 @@ impl ListActiveFilesCli {
             println!();
             for file_info in response.active_files {
                 let action_color = match file_info.action.as_str() {
-                    "add" => style(&file_info.action).green(),
+                    "add" | "move/add" => style(&file_info.action).green(),
                     "edit" => style(&file_info.action).yellow(),
                     "delete" => style(&file_info.action).red(),
                     _ => style(&file_info.action).white(),
@@ -353,10 +708,301 @@ impl ListActiveFilesCli {
 }
 
 #[derive(Parser)]
-pub struct LockCli;
+pub struct LockCli {
+    /// List files currently locked on the hive
+    #[arg(long)]
+    pub list: bool,
+
+    /// Branch to lock files on or list locks on; defaults to the default branch
+    #[arg(long)]
+    pub branch: Option<String>,
+
+    /// Take a shared read lock, preventing others from submitting the files
+    #[arg(long, conflicts_with = "list")]
+    pub read: bool,
+
+    /// Workspace name, required when locking files
+    #[arg(short, long)]
+    pub workspace: Option<String>,
+
+    /// Paths to lock (can be local paths or workspace paths)
+    pub paths: Vec<String>,
+}
+
+#[derive(Tabled)]
+struct LockedFileRow {
+    #[tabled(rename = "Path")]
+    path: String,
+    #[tabled(rename = "Locked By")]
+    locked_by: String,
+    #[tabled(rename = "Mode")]
+    mode: String,
+    #[tabled(rename = "File ID")]
+    file_id: String,
+}
 
 impl LockCli {
     pub async fn handle(&self, channel: &Channel) -> Result<()> {
-        todo!()
+        if !self.list {
+            return self.lock(channel).await;
+        }
+
+        let mut client = FileServiceClient::new(channel.clone());
+
+        let request = ListLockedFilesReq {
+            branch_id: self.branch.clone().unwrap_or_default(),
+        };
+
+        let response = client.list_locked_files(request).await?.into_inner();
+
+        if response.locked_files.is_empty() {
+            println!("{}", style("No locked files found.").yellow());
+            return Ok(());
+        }
+
+        let count = response.locked_files.len();
+        let rows: Vec<LockedFileRow> = response
+            .locked_files
+            .into_iter()
+            .map(|f| LockedFileRow {
+                path: f.path,
+                locked_by: f.locked_by,
+                mode: if f.read { "read" } else { "write" }.to_string(),
+                file_id: f.file_id,
+            })
+            .collect();
+
+        let mut table = Table::new(rows);
+        table.with(Style::rounded());
+        println!("{}", table);
+        println!("\n{} file(s) locked", count);
+
+        Ok(())
+    }
+
+    async fn lock(&self, channel: &Channel) -> Result<()> {
+        if !self.read {
+            anyhow::bail!(
+                "Exclusive locks are taken by submit, use `crv lock --read PATH` or `crv lock --list`"
+            );
+        }
+        let Some(workspace) = self.workspace.clone() else {
+            anyhow::bail!("--workspace is required when locking files");
+        };
+        if self.paths.is_empty() {
+            anyhow::bail!("No paths given to lock");
+        }
+
+        let mut client = FileServiceClient::new(channel.clone());
+        let response = client
+            .lock(LockReq {
+                workspace_name: workspace,
+                paths: self.paths.clone(),
+                read: true,
+                branch_id: self.branch.clone().unwrap_or_default(),
+            })
+            .await?
+            .into_inner();
+
+        for path in &response.locked_paths {
+            println!("{} {}", style("✓").green(), path);
+        }
+        println!(
+            "\n{} file(s) read-locked, ticket: {}",
+            response.locked_paths.len(),
+            response.ticket
+        );
+
+        Ok(())
+    }
+}
+
+#[derive(Parser)]
+pub struct DiffFileCli {
+    /// Workspace name
+    #[arg(short, long)]
+    pub workspace: String,
+
+    /// File to compare (can be a local path, workspace path or depot path)
+    pub path: String,
+}
+
+impl DiffFileCli {
+    pub async fn handle(&self, channel: &Channel) -> Result<()> {
+        let mut client = FileServiceClient::new(channel.clone());
+
+        let described = client
+            .describe(DescribeReq {
+                workspace_name: self.workspace.clone(),
+                paths: vec![self.path.clone()],
+            })
+            .await?
+            .into_inner();
+        let Some(file) = described.files.into_iter().next() else {
+            println!(
+                "{}",
+                style(format!("{} has not been synced.", self.path)).yellow()
+            );
+            return Ok(());
+        };
+
+        let synced = client
+            .get_file_content(GetFileContentReq {
+                workspace_name: self.workspace.clone(),
+                depot_path: file.depot_path.clone(),
+            })
+            .await?
+            .into_inner();
+        let local = match std::fs::read(&synced.local_path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => {
+                return Err(anyhow::anyhow!("Failed to read {}: {e}", synced.local_path));
+            }
+        };
+
+        let choice = if io::stdout().is_terminal() {
+            ColorChoice::Auto
+        } else {
+            ColorChoice::Never
+        };
+        let mut stdout = StandardStream::stdout(choice);
+        write_diff(
+            &mut stdout,
+            &synced.content,
+            &local,
+            &format!(
+                "{}#{}:{}",
+                file.depot_path, synced.generation, synced.revision
+            ),
+            &synced.local_path,
+        )?;
+        Ok(())
+    }
+}
+
+/// 含有 NUL 字节或不是合法 UTF-8 的内容按二进制文件处理
+fn is_binary(content: &[u8]) -> bool {
+    content.contains(&0) || std::str::from_utf8(content).is_err()
+}
+
+/// 以 unified diff 格式输出 `old` 到 `new` 的差异，新增行为绿色，删除行为红色
+fn write_diff<W: WriteColor>(
+    out: &mut W,
+    old: &[u8],
+    new: &[u8],
+    old_label: &str,
+    new_label: &str,
+) -> io::Result<()> {
+    if old == new {
+        return writeln!(out, "No differences");
+    }
+
+    if is_binary(old) || is_binary(new) {
+        return writeln!(
+            out,
+            "Binary files differ ({} bytes -> {} bytes)",
+            old.len(),
+            new.len()
+        );
+    }
+    let old_text = String::from_utf8_lossy(old);
+    let new_text = String::from_utf8_lossy(new);
+
+    out.set_color(ColorSpec::new().set_bold(true))?;
+    writeln!(out, "--- {old_label}")?;
+    writeln!(out, "+++ {new_label}")?;
+    out.reset()?;
+
+    let diff = TextDiff::from_lines(old_text.as_ref(), new_text.as_ref());
+    for hunk in diff.unified_diff().context_radius(3).iter_hunks() {
+        out.set_color(ColorSpec::new().set_fg(Some(Color::Cyan)))?;
+        writeln!(out, "{}", hunk.header())?;
+        out.reset()?;
+        for change in hunk.iter_changes() {
+            let (sign, color) = match change.tag() {
+                ChangeTag::Delete => ('-', Some(Color::Red)),
+                ChangeTag::Insert => ('+', Some(Color::Green)),
+                ChangeTag::Equal => (' ', None),
+            };
+            out.set_color(ColorSpec::new().set_fg(color))?;
+            write!(out, "{sign}{}", change.value())?;
+            out.reset()?;
+            if change.missing_newline() {
+                writeln!(out)?;
+                writeln!(out, "\\ No newline at end of file")?;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use termcolor::NoColor;
+
+    fn render(old: &[u8], new: &[u8]) -> String {
+        let mut out = NoColor::new(Vec::new());
+        write_diff(&mut out, old, new, "//depot/a.txt#1:1", "a.txt").unwrap();
+        String::from_utf8(out.into_inner()).unwrap()
+    }
+
+    #[test]
+    fn text_changes_render_as_unified_diff() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("a.txt");
+        let synced = "one\ntwo\nthree\nfour\nfive\nsix\nseven\neight\nnine\nten\n";
+        std::fs::write(
+            &path,
+            "one\nTWO\nthree\nfour\nfive\nsix\nseven\neight\nnine\nten\neleven\n",
+        )
+        .unwrap();
+        let local = std::fs::read(&path).unwrap();
+
+        let output = render(synced.as_bytes(), &local);
+        assert_eq!(
+            output,
+            "--- //depot/a.txt#1:1\n\
+             +++ a.txt\n\
+             @@ -1,5 +1,5 @@\n\
+             \x20one\n\
+             -two\n\
+             +TWO\n\
+             \x20three\n\
+             \x20four\n\
+             \x20five\n\
+             @@ -8,3 +8,4 @@\n\
+             \x20eight\n\
+             \x20nine\n\
+             \x20ten\n\
+             +eleven\n"
+        );
+    }
+
+    #[test]
+    fn identical_content_has_no_differences() {
+        assert_eq!(render(b"same\n", b"same\n"), "No differences\n");
+    }
+
+    #[test]
+    fn missing_trailing_newline_is_marked() {
+        let output = render(b"a\n", b"a\nb");
+        assert!(
+            output.ends_with("+b\n\\ No newline at end of file\n"),
+            "{output}"
+        );
+    }
+
+    #[test]
+    fn binary_files_report_sizes() {
+        assert_eq!(
+            render(b"text\n", &[0x89, b'P', b'N', b'G', 0, 0]),
+            "Binary files differ (5 bytes -> 6 bytes)\n"
+        );
+        assert_eq!(
+            render(&[0xff, 0xfe], &[0xff]),
+            "Binary files differ (2 bytes -> 1 bytes)\n"
+        );
     }
 }
@@ -0,0 +1,203 @@
+use anyhow::Result;
+use clap::{Parser, Subcommand};
+use console::style;
+use crv_edge::pb::{
+    ExportRepositorySnapshotReq, GetRepositoryLayoutReq, RebuildIndexReq,
+    SetBranchDescriptionFormatReq, branch_service_client::BranchServiceClient,
+    system_service_client::SystemServiceClient,
+};
+use std::path::PathBuf;
+use tabled::{Table, Tabled, settings::Style};
+use tokio::io::AsyncWriteExt;
+use tonic::transport::Channel;
+
+/// Hive configuration, admin only
+#[derive(Parser)]
+pub struct HiveCli {
+    #[command(subcommand)]
+    pub hive_commands: HiveCommands,
+}
+
+#[derive(Subcommand)]
+pub enum HiveCommands {
+    SetDescriptionFormat(SetDescriptionFormatCli),
+    RebuildIndex(RebuildIndexCli),
+    #[command(name = "repo-layout")]
+    RepoLayout(RepoLayoutCli),
+    Backup(BackupCli),
+}
+
+impl HiveCli {
+    pub async fn handle(&self, channel: &Channel) -> Result<()> {
+        match &self.hive_commands {
+            HiveCommands::SetDescriptionFormat(cli) => cli.handle(channel).await,
+            HiveCommands::RebuildIndex(cli) => cli.handle(channel).await,
+            HiveCommands::RepoLayout(cli) => cli.handle(channel).await,
+            HiveCommands::Backup(cli) => cli.handle(channel).await,
+        }
+    }
+}
+
+/// Require changelist descriptions on a branch to match a regex
+#[derive(Parser)]
+pub struct SetDescriptionFormatCli {
+    /// Branch name
+    #[arg(short, long)]
+    pub branch: String,
+
+    /// Regex the description must match, empty to fall back to the hive-wide format
+    #[arg(short, long, default_value = "")]
+    pub regex: String,
+}
+
+impl SetDescriptionFormatCli {
+    pub async fn handle(&self, channel: &Channel) -> Result<()> {
+        let mut client = BranchServiceClient::new(channel.clone());
+
+        client
+            .set_branch_description_format(SetBranchDescriptionFormatReq {
+                branch_id: self.branch.clone(),
+                regex: self.regex.clone(),
+            })
+            .await?;
+
+        if self.regex.is_empty() {
+            println!(
+                "{} Branch {} now uses the hive-wide description format",
+                style("✓").green(),
+                style(&self.branch).cyan()
+            );
+        } else {
+            println!(
+                "{} Descriptions on branch {} must match {}",
+                style("✓").green(),
+                style(&self.branch).cyan(),
+                style(&self.regex).cyan()
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Rebuild the chunk index of every sealed pack from its data file
+#[derive(Parser)]
+pub struct RebuildIndexCli {}
+
+impl RebuildIndexCli {
+    pub async fn handle(&self, channel: &Channel) -> Result<()> {
+        let mut client = SystemServiceClient::new(channel.clone());
+
+        let rsp = client.rebuild_index(RebuildIndexReq {}).await?.into_inner();
+
+        println!(
+            "{} Rebuilt index of {} packs ({} chunks)",
+            style("✓").green(),
+            style(rsp.packs).cyan(),
+            style(rsp.chunks).cyan()
+        );
+        Ok(())
+    }
+}
+
+/// List the packs of the chunk repository and check their integrity
+#[derive(Parser)]
+pub struct RepoLayoutCli {}
+
+#[derive(Tabled)]
+struct BundleRow {
+    #[tabled(rename = "Bundle")]
+    id: String,
+    #[tabled(rename = "Chunks")]
+    chunk_count: u64,
+    #[tabled(rename = "Size")]
+    size_bytes: u64,
+    #[tabled(rename = "Valid")]
+    is_valid: String,
+}
+
+impl RepoLayoutCli {
+    pub async fn handle(&self, channel: &Channel) -> Result<()> {
+        let mut client = SystemServiceClient::new(channel.clone());
+
+        let rsp = client
+            .get_repository_layout(GetRepositoryLayoutReq {})
+            .await?
+            .into_inner();
+
+        if super::json_output() {
+            return super::print_json(&rsp);
+        }
+
+        if rsp.bundles.is_empty() {
+            println!("{}", style("No bundles found.").yellow());
+            return Ok(());
+        }
+
+        let invalid = rsp.bundles.iter().filter(|b| !b.is_valid).count();
+        let rows: Vec<BundleRow> = rsp
+            .bundles
+            .into_iter()
+            .map(|b| BundleRow {
+                id: b.id,
+                chunk_count: b.chunk_count,
+                size_bytes: b.size_bytes,
+                is_valid: if b.is_valid { "yes" } else { "no" }.to_string(),
+            })
+            .collect();
+
+        let mut table = Table::new(&rows);
+        table.with(Style::rounded());
+
+        println!("\n{}", table);
+        println!("\n{} bundle(s) found", style(rows.len()).cyan());
+        if invalid > 0 {
+            anyhow::bail!("{} bundle(s) are corrupted", invalid);
+        }
+        Ok(())
+    }
+}
+
+/// Export the chunk repository and metadata database as a tar archive
+#[derive(Parser)]
+pub struct BackupCli {
+    /// Path of the tar archive to write
+    #[arg(short, long)]
+    pub out: PathBuf,
+}
+
+impl BackupCli {
+    pub async fn handle(&self, channel: &Channel) -> Result<()> {
+        let mut client = SystemServiceClient::new(channel.clone());
+
+        let mut stream = client
+            .export_repository_snapshot(ExportRepositorySnapshotReq {})
+            .await?
+            .into_inner();
+
+        let mut file = tokio::fs::File::create(&self.out).await?;
+        let mut total_bytes = 0u64;
+        let result: Result<()> = async {
+            while let Some(message) = stream.message().await? {
+                file.write_all(&message.data).await?;
+                total_bytes += message.data.len() as u64;
+            }
+            file.flush().await?;
+            Ok(())
+        }
+        .await;
+        // 导出中断时不保留不完整的归档
+        if let Err(e) = result {
+            drop(file);
+            let _ = tokio::fs::remove_file(&self.out).await;
+            return Err(e);
+        }
+
+        println!(
+            "{} Backup written to {} ({} bytes)",
+            style("✓").green(),
+            style(self.out.display()).cyan(),
+            style(total_bytes).cyan()
+        );
+        Ok(())
+    }
+}
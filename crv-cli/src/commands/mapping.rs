@@ -0,0 +1,182 @@
+use anyhow::Result;
+use clap::{Parser, Subcommand};
+use console::style;
+use crv_edge::pb::{
+    AddWorkspaceMappingReq, ListWorkspaceMappingsReq, RemoveWorkspaceMappingReq,
+    WorkspaceMappingDoc, workspace_service_client::WorkspaceServiceClient,
+};
+use tabled::{Table, Tabled, settings::Style};
+use tonic::transport::Channel;
+
+#[derive(Parser)]
+pub struct WorkspaceMappingCli {
+    #[command(subcommand)]
+    pub mapping_commands: WorkspaceMappingCommands,
+}
+
+#[derive(Subcommand)]
+pub enum WorkspaceMappingCommands {
+    Add(AddCli),
+    Remove(RemoveCli),
+    List(ListCli),
+}
+
+impl WorkspaceMappingCli {
+    pub async fn handle(&self, channel: &Channel) -> Result<()> {
+        match &self.mapping_commands {
+            WorkspaceMappingCommands::Add(cli) => cli.handle(channel).await,
+            WorkspaceMappingCommands::Remove(cli) => cli.handle(channel).await,
+            WorkspaceMappingCommands::List(cli) => cli.handle(channel).await,
+        }
+    }
+}
+
+#[derive(Tabled)]
+struct MappingRow {
+    #[tabled(rename = "Priority")]
+    priority: i32,
+    #[tabled(rename = "Depot")]
+    depot_wildcard: String,
+    #[tabled(rename = "Local")]
+    local_dir: String,
+    #[tabled(rename = "Recursive")]
+    recursive: bool,
+    #[tabled(rename = "Filter")]
+    filter: String,
+}
+
+fn print_mappings(mut mappings: Vec<WorkspaceMappingDoc>) {
+    if mappings.is_empty() {
+        println!("{}", style("No mappings found.").yellow());
+        return;
+    }
+
+    mappings.sort_by_key(|m| m.priority);
+    let rows: Vec<MappingRow> = mappings
+        .into_iter()
+        .map(|m| MappingRow {
+            priority: m.priority,
+            depot_wildcard: m.depot_wildcard,
+            local_dir: if m.local_dir.is_empty() {
+                "(excluded)".to_string()
+            } else {
+                m.local_dir
+            },
+            recursive: m.recursive,
+            filter: if m.filter.is_empty() {
+                "*".to_string()
+            } else {
+                format!("*.{}", m.filter)
+            },
+        })
+        .collect();
+
+    let mut table = Table::new(&rows);
+    table.with(Style::rounded());
+
+    println!("\n{}", table);
+    println!("\n{} mapping(s) found", style(rows.len()).cyan());
+}
+
+#[derive(Parser)]
+pub struct AddCli {
+    /// Workspace name
+    #[arg(short, long)]
+    pub workspace: String,
+
+    /// Depot path wildcard, e.g. //project/assets/...~png
+    pub depot_wildcard: String,
+
+    /// Local directory under the workspace root; omit to exclude the depot paths instead
+    pub local_dir: Option<String>,
+
+    /// Position to insert the mapping at; later mappings override earlier ones.
+    /// Defaults to the highest priority
+    #[arg(short, long)]
+    pub priority: Option<i32>,
+}
+
+impl AddCli {
+    pub async fn handle(&self, channel: &Channel) -> Result<()> {
+        let mut client = WorkspaceServiceClient::new(channel.clone());
+
+        let response = client
+            .add_workspace_mapping(AddWorkspaceMappingReq {
+                workspace_name: self.workspace.clone(),
+                depot_wildcard: self.depot_wildcard.clone(),
+                local_dir: self.local_dir.clone().unwrap_or_default(),
+                priority: self.priority,
+            })
+            .await?
+            .into_inner();
+
+        println!(
+            "{} Mapping {} added to workspace {}",
+            style("✓").green(),
+            style(&self.depot_wildcard).cyan(),
+            style(&self.workspace).cyan()
+        );
+        print_mappings(response.mappings);
+        Ok(())
+    }
+}
+
+#[derive(Parser)]
+pub struct RemoveCli {
+    /// Workspace name
+    #[arg(short, long)]
+    pub workspace: String,
+
+    /// Priority of the mapping to remove, as shown by `crv workspace list-mappings`
+    pub priority: i32,
+}
+
+impl RemoveCli {
+    pub async fn handle(&self, channel: &Channel) -> Result<()> {
+        let mut client = WorkspaceServiceClient::new(channel.clone());
+
+        let response = client
+            .remove_workspace_mapping(RemoveWorkspaceMappingReq {
+                workspace_name: self.workspace.clone(),
+                priority: self.priority,
+            })
+            .await?
+            .into_inner();
+
+        println!(
+            "{} Mapping {} removed from workspace {}",
+            style("✓").green(),
+            style(self.priority).cyan(),
+            style(&self.workspace).cyan()
+        );
+        print_mappings(response.mappings);
+        Ok(())
+    }
+}
+
+#[derive(Parser)]
+pub struct ListCli {
+    /// Workspace name
+    #[arg(short, long)]
+    pub workspace: String,
+}
+
+impl ListCli {
+    pub async fn handle(&self, channel: &Channel) -> Result<()> {
+        let mut client = WorkspaceServiceClient::new(channel.clone());
+
+        let response = client
+            .list_workspace_mappings(ListWorkspaceMappingsReq {
+                workspace_name: self.workspace.clone(),
+            })
+            .await?
+            .into_inner();
+
+        if super::json_output() {
+            return super::print_json(&response);
+        }
+
+        print_mappings(response.mappings);
+        Ok(())
+    }
+}
@@ -1,11 +1,23 @@
+mod branch;
 mod changelist;
+mod checkpoint;
+mod completion;
 mod debug;
 mod edge;
 mod file;
+mod hive;
+mod mapping;
+pub mod repl;
+mod snapshot;
+mod tag;
+mod user;
 mod workspace;
 
+use std::sync::atomic::{AtomicBool, Ordering};
+
 use anyhow::Result;
 use clap::{Parser, Subcommand};
+use serde::Serialize;
 use tonic::transport::Channel;
 
 #[derive(Parser)]
@@ -15,18 +27,30 @@ pub struct Cli {
     #[arg(long, help = "Start the interactive REPL shell")]
     pub repl: bool,
 
+    #[arg(long, global = true, help = "Print command output as JSON")]
+    pub json: bool,
+
     #[command(subcommand)]
     pub command: Option<Commands>,
 }
 
 impl Cli {
     pub async fn handle(&self, channel: &Channel) -> Result<()> {
+        JSON_OUTPUT.store(self.json, Ordering::Relaxed);
         if let Some(command) = &self.command {
             match command {
                 Commands::Edge(edge_cli) => edge_cli.handle(channel).await,
+                Commands::Ping(ping_cli) => ping_cli.handle(channel).await,
+                Commands::Info(info_cli) => info_cli.handle(channel).await,
+                Commands::Watch(watch_cli) => watch_cli.handle(channel).await,
+                Commands::Set(set_cli) => set_cli.handle(channel).await,
+                Commands::Get(get_cli) => get_cli.handle(channel).await,
                 Commands::Add(add_cli) => add_cli.handle(channel).await,
                 Commands::Checkout(checkout_cli) => checkout_cli.handle(channel).await,
                 Commands::Delete(delete_cli) => delete_cli.handle(channel).await,
+                Commands::Move(move_cli) => move_cli.handle(channel).await,
+                Commands::Describe(describe_cli) => describe_cli.handle(channel).await,
+                Commands::Diff(diff_cli) => diff_cli.handle(channel).await,
                 Commands::ListActiveFiles(list_cli) => list_cli.handle(channel).await,
                 Commands::Sync(sync_cli) => sync_cli.handle(channel).await,
                 Commands::Lock(lock_cli) => lock_cli.handle(channel).await,
@@ -34,7 +58,18 @@ impl Cli {
                 Commands::Revert(revert_cli) => revert_cli.handle(channel).await,
                 Commands::Workspace(workspace_cli) => workspace_cli.handle(channel).await,
                 Commands::Changelist(changelist_cli) => changelist_cli.handle(channel).await,
+                Commands::Log(log_cli) => log_cli.handle(channel).await,
+                Commands::Branch(branch_cli) => branch_cli.handle(channel).await,
+                Commands::Checkpoint(checkpoint_cli) => checkpoint_cli.handle(channel).await,
+                Commands::User(user_cli) => user_cli.handle(channel).await,
+                Commands::Tag(tag_cli) => tag_cli.handle(channel).await,
+                Commands::Snapshot(snapshot_cli) => snapshot_cli.handle(channel).await,
+                Commands::Hive(hive_cli) => hive_cli.handle(channel).await,
                 Commands::Debug(debug_cli) => debug_cli.handle(channel).await,
+                Commands::Completion(completion_cli) => completion_cli.handle(),
+                Commands::CompleteValues(complete_values_cli) => {
+                    complete_values_cli.handle(channel).await
+                }
             }
         } else {
             Ok(())
@@ -42,12 +77,38 @@ impl Cli {
     }
 }
 
+/// 全局的 `--json` 参数，REPL 中每条命令执行前都会重新设置
+static JSON_OUTPUT: AtomicBool = AtomicBool::new(false);
+
+/// 当前命令是否以 JSON 格式输出
+pub(crate) fn json_output() -> bool {
+    JSON_OUTPUT.load(Ordering::Relaxed)
+}
+
+fn render_json<T: Serialize>(value: &T) -> Result<String> {
+    Ok(serde_json::to_string_pretty(value)?)
+}
+
+/// 以 JSON 格式打印响应，供 `jq` 等工具处理
+pub(crate) fn print_json<T: Serialize>(value: &T) -> Result<()> {
+    println!("{}", render_json(value)?);
+    Ok(())
+}
+
 #[derive(Subcommand)]
 pub enum Commands {
     Edge(edge::EdgeCli),
+    Ping(edge::PingCli),
+    Info(edge::InfoCli),
+    Watch(edge::WatchCli),
+    Set(edge::SetCli),
+    Get(edge::GetCli),
     Add(file::AddCli),
     Checkout(file::CheckoutCli),
     Delete(file::DeleteCli),
+    Move(file::MoveCli),
+    Describe(file::DescribeCli),
+    Diff(file::DiffFileCli),
     #[command(name = "showactive")]
     ListActiveFiles(file::ListActiveFilesCli),
     Sync(file::SyncCli),
@@ -56,5 +117,55 @@ pub enum Commands {
     Revert(file::RevertCli),
     Workspace(workspace::WorkspaceCli),
     Changelist(changelist::ChangelistCli),
+    Log(changelist::LogCli),
+    Branch(branch::BranchCli),
+    Checkpoint(checkpoint::CheckpointCli),
+    User(user::UserCli),
+    Tag(tag::TagCli),
+    Snapshot(snapshot::SnapshotCli),
+    Hive(hive::HiveCli),
     Debug(debug::DebugCli),
+    Completion(completion::CompletionCli),
+    CompleteValues(completion::CompleteValuesCli),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crv_edge::pb::{BranchSummary, ListBranchesRsp};
+
+    #[test]
+    fn json_flag_is_accepted_after_subcommands() {
+        let cli = Cli::try_parse_from(["crv", "branch", "list", "--json"]).unwrap();
+        assert!(cli.json);
+        let cli = Cli::try_parse_from(["crv", "--json", "sync", "-w", "ws", "//..."]).unwrap();
+        assert!(cli.json);
+    }
+
+    #[test]
+    fn log_accepts_changelist_range() {
+        let cli = Cli::try_parse_from(["crv", "log", "--from", "3", "--to", "7"]).unwrap();
+        let Some(Commands::Log(log)) = cli.command else {
+            panic!("expected log command");
+        };
+        assert_eq!((log.from_cl, log.to_cl), (3, 7));
+        assert_eq!(log.branch, None);
+    }
+
+    #[test]
+    fn list_responses_render_as_json() {
+        let response = ListBranchesRsp {
+            branches: vec![BranchSummary {
+                id: "main".to_string(),
+                head_changelist_id: 42,
+                ..Default::default()
+            }],
+            total_count: 1,
+        };
+        let output = render_json(&response).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&output).unwrap();
+        assert_eq!(value["totalCount"], 1);
+        assert_eq!(value["branches"][0]["id"], "main");
+        assert_eq!(value["branches"][0]["headChangelistId"], 42);
+    }
 }
@@ -0,0 +1,229 @@
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+use anyhow::Result;
+use clap::{Command, CommandFactory, Parser};
+use rustyline::completion::{Completer, Pair};
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::history::FileHistory;
+use rustyline::validate::Validator;
+use rustyline::{Context, Editor, Helper};
+use tonic::transport::Channel;
+
+use super::Cli;
+
+const PROMPT: &str = "crv> ";
+const HISTORY_FILE: &str = ".crv_history";
+
+/// 历史记录保存在用户主目录下
+fn history_path() -> Option<PathBuf> {
+    directories::BaseDirs::new().map(|dirs| dirs.home_dir().join(HISTORY_FILE))
+}
+
+/// 根据 clap 的命令定义补全子命令与参数名
+struct ReplHelper {
+    command: Command,
+}
+
+impl ReplHelper {
+    fn new() -> Self {
+        Self {
+            command: Cli::command(),
+        }
+    }
+
+    /// 已输入的单词确定的子命令下，以 `prefix` 开头的候选项
+    fn candidates(&self, words: &[&str], prefix: &str) -> Vec<String> {
+        let mut command = &self.command;
+        for word in words {
+            match command.find_subcommand(word) {
+                Some(subcommand) => command = subcommand,
+                None => break,
+            }
+        }
+
+        let mut candidates: Vec<String> = if prefix.starts_with('-') {
+            command
+                .get_arguments()
+                .filter(|arg| !arg.is_hide_set())
+                .filter_map(|arg| arg.get_long())
+                .map(|long| format!("--{long}"))
+                .collect()
+        } else {
+            command
+                .get_subcommands()
+                .filter(|subcommand| !subcommand.is_hide_set())
+                .map(|subcommand| subcommand.get_name().to_string())
+                .collect()
+        };
+        if words.is_empty() && !prefix.starts_with('-') {
+            candidates.extend(["exit", "help", "quit"].map(str::to_string));
+        }
+        candidates.retain(|candidate| candidate.starts_with(prefix));
+        candidates.sort();
+        candidates
+    }
+}
+
+impl Completer for ReplHelper {
+    type Candidate = Pair;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<Pair>)> {
+        let line = &line[..pos];
+        let start = line.rfind(char::is_whitespace).map_or(0, |i| i + 1);
+        let words: Vec<&str> = line[..start].split_whitespace().collect();
+        let candidates = self
+            .candidates(&words, &line[start..])
+            .into_iter()
+            .map(|candidate| Pair {
+                display: candidate.clone(),
+                replacement: candidate,
+            })
+            .collect();
+        Ok((start, candidates))
+    }
+}
+
+impl Hinter for ReplHelper {
+    type Hint = String;
+}
+
+impl Highlighter for ReplHelper {}
+
+impl Validator for ReplHelper {}
+
+impl Helper for ReplHelper {}
+
+/// 交互式 shell：逐行读取命令并复用同一个连接执行，支持历史记录与 Tab 补全
+pub async fn run(channel: Channel) -> Result<()> {
+    println!(
+        "{}",
+        console::style("Welcome to CRV Edge Shell").bold().cyan()
+    );
+    println!("Type 'exit' or 'quit' to leave, 'help' for commands.\n");
+
+    let mut editor = match Editor::<ReplHelper, FileHistory>::new() {
+        Ok(editor) => editor,
+        Err(e) => {
+            // 某些终端（如调试器的控制台）不支持 rustyline，退回到逐行读取标准输入
+            eprintln!("Line editing is unavailable ({e}), falling back to plain input.");
+            return run_plain(channel).await;
+        }
+    };
+    editor.set_helper(Some(ReplHelper::new()));
+    let history_path = history_path();
+    if let Some(path) = &history_path {
+        // 第一次运行时历史文件还不存在
+        let _ = editor.load_history(path);
+    }
+
+    loop {
+        let line = match editor.readline(PROMPT) {
+            Ok(line) => line,
+            // Ctrl-C 放弃当前输入
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => break,
+            Err(e) => return Err(e.into()),
+        };
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let _ = editor.add_history_entry(line);
+        if line == "exit" || line == "quit" {
+            break;
+        }
+        run_line(line, &channel).await;
+    }
+
+    if let Some(Err(e)) = history_path.as_ref().map(|path| editor.save_history(path)) {
+        eprintln!("Failed to save history: {e}");
+    }
+    println!("{}", console::style("bye~").bold().green());
+    Ok(())
+}
+
+/// 不支持行编辑时的退化模式
+async fn run_plain(channel: Channel) -> Result<()> {
+    loop {
+        print!("{PROMPT}");
+        io::stdout().flush()?;
+
+        let mut input = String::new();
+        if io::stdin().read_line(&mut input)? == 0 {
+            break;
+        }
+        let line = input.trim();
+        if line.is_empty() {
+            continue;
+        }
+        if line == "exit" || line == "quit" {
+            break;
+        }
+        run_line(line, &channel).await;
+    }
+    println!("{}", console::style("bye~").bold().green());
+    Ok(())
+}
+
+/// 解析并执行一行命令，出错时打印错误并继续
+async fn run_line(line: &str, channel: &Channel) {
+    let Some(args) = shlex::split(line) else {
+        eprintln!("{}: unbalanced quotes", console::style("Error").red());
+        return;
+    };
+    if let Err(e) = handle_command(&args, channel).await {
+        eprintln!("{}: {}", console::style("Error").red(), e);
+    }
+}
+
+/// 解析并处理 REPL 中的单条命令
+async fn handle_command(args: &[String], channel: &Channel) -> Result<()> {
+    // clap 的第一个参数是程序名，需要在开头插入一个占位符
+    let full_args = std::iter::once("crv").chain(args.iter().map(String::as_str));
+
+    // 使用 try_parse_from 而不是 parse，防止解析失败时直接退出程序
+    match Cli::try_parse_from(full_args) {
+        Ok(cli) if cli.repl => {
+            println!("{}", console::style("Already in the REPL.").yellow());
+            Ok(())
+        }
+        Ok(cli) => cli.handle(channel).await,
+        // help 或解析错误时 clap 会生成提示，直接打印即可
+        Err(e) => {
+            e.print()?;
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn subcommands_and_flags_are_completed() {
+        let helper = ReplHelper::new();
+        assert_eq!(helper.candidates(&[], "sub"), vec!["submit"]);
+        assert!(
+            helper
+                .candidates(&[], "")
+                .contains(&"workspace".to_string())
+        );
+        assert!(
+            helper
+                .candidates(&["branch"], "")
+                .contains(&"list".to_string())
+        );
+        assert_eq!(helper.candidates(&["sync"], "--for"), vec!["--force"]);
+        // 隐藏的命令不出现在补全中
+        assert!(helper.candidates(&[], "complete-").is_empty());
+    }
+}
@@ -0,0 +1,204 @@
+use anyhow::Result;
+use chrono::DateTime;
+use clap::{Parser, Subcommand};
+use console::style;
+use crv_edge::pb::{
+    CreateSnapshotReq, DeleteSnapshotReq, ListSnapshotsReq, RestoreSnapshotReq,
+    snapshot_service_client::SnapshotServiceClient,
+};
+use tabled::{Table, Tabled, settings::Style};
+use tonic::transport::Channel;
+
+#[derive(Parser)]
+pub struct SnapshotCli {
+    #[command(subcommand)]
+    pub snapshot_commands: SnapshotCommands,
+}
+
+#[derive(Subcommand)]
+pub enum SnapshotCommands {
+    Create(CreateCli),
+    List(ListCli),
+    Delete(DeleteCli),
+    Restore(RestoreCli),
+}
+
+impl SnapshotCli {
+    pub async fn handle(&self, channel: &Channel) -> Result<()> {
+        match &self.snapshot_commands {
+            SnapshotCommands::Create(cli) => cli.handle(channel).await,
+            SnapshotCommands::List(cli) => cli.handle(channel).await,
+            SnapshotCommands::Delete(cli) => cli.handle(channel).await,
+            SnapshotCommands::Restore(cli) => cli.handle(channel).await,
+        }
+    }
+}
+
+#[derive(Parser)]
+pub struct CreateCli {
+    /// Snapshot name, must be unique, e.g. release-1.2
+    pub name: String,
+
+    /// Branch to snapshot
+    #[arg(short, long)]
+    pub branch: String,
+
+    /// Changelist to pin; omit to use the branch's current head
+    #[arg(long = "cl", default_value_t = 0)]
+    pub changelist_id: i64,
+
+    /// Snapshot description
+    #[arg(short, long, default_value = "")]
+    pub description: String,
+}
+
+impl CreateCli {
+    pub async fn handle(&self, channel: &Channel) -> Result<()> {
+        let mut client = SnapshotServiceClient::new(channel.clone());
+
+        let response = client
+            .create_snapshot(CreateSnapshotReq {
+                branch_id: self.branch.clone(),
+                changelist_id: self.changelist_id,
+                name: self.name.clone(),
+                description: self.description.clone(),
+            })
+            .await?
+            .into_inner();
+
+        let changelist_id = response
+            .snapshot
+            .map(|s| s.changelist_id)
+            .unwrap_or(self.changelist_id);
+        println!(
+            "{} Snapshot {} created on branch {} at changelist {}",
+            style("✓").green(),
+            style(&self.name).cyan(),
+            style(&self.branch).cyan(),
+            style(changelist_id).cyan()
+        );
+        Ok(())
+    }
+}
+
+#[derive(Tabled)]
+struct SnapshotRow {
+    #[tabled(rename = "ID")]
+    id: i64,
+    #[tabled(rename = "Name")]
+    name: String,
+    #[tabled(rename = "Branch")]
+    branch_id: String,
+    #[tabled(rename = "CL")]
+    changelist_id: i64,
+    #[tabled(rename = "Created By")]
+    created_by: String,
+    #[tabled(rename = "Created At")]
+    created_at: String,
+    #[tabled(rename = "Description")]
+    description: String,
+}
+
+#[derive(Parser)]
+pub struct ListCli {
+    /// Only list snapshots of this branch; omit to list all branches
+    #[arg(short, long)]
+    pub branch: Option<String>,
+}
+
+impl ListCli {
+    pub async fn handle(&self, channel: &Channel) -> Result<()> {
+        let mut client = SnapshotServiceClient::new(channel.clone());
+
+        let response = client
+            .list_snapshots(ListSnapshotsReq {
+                branch_id: self.branch.clone().unwrap_or_default(),
+            })
+            .await?
+            .into_inner();
+
+        if super::json_output() {
+            return super::print_json(&response);
+        }
+
+        if response.snapshots.is_empty() {
+            println!("{}", style("No snapshots found.").yellow());
+            return Ok(());
+        }
+
+        let rows: Vec<SnapshotRow> = response
+            .snapshots
+            .into_iter()
+            .map(|s| SnapshotRow {
+                id: s.id,
+                name: s.name,
+                branch_id: s.branch_id,
+                changelist_id: s.changelist_id,
+                created_by: s.created_by,
+                created_at: DateTime::from_timestamp_millis(s.created_at)
+                    .map(|t| t.format("%Y-%m-%d %H:%M:%S").to_string())
+                    .unwrap_or_default(),
+                description: s.description,
+            })
+            .collect();
+
+        let mut table = Table::new(&rows);
+        table.with(Style::rounded());
+
+        println!("\n{}", table);
+        println!("\n{} snapshot(s) found", style(rows.len()).cyan());
+        Ok(())
+    }
+}
+
+#[derive(Parser)]
+pub struct DeleteCli {
+    /// Snapshot id to delete
+    pub snapshot_id: i64,
+}
+
+impl DeleteCli {
+    pub async fn handle(&self, channel: &Channel) -> Result<()> {
+        let mut client = SnapshotServiceClient::new(channel.clone());
+
+        client
+            .delete_snapshot(DeleteSnapshotReq {
+                snapshot_id: self.snapshot_id,
+            })
+            .await?;
+
+        println!(
+            "{} Snapshot {} deleted",
+            style("✓").green(),
+            style(self.snapshot_id).cyan()
+        );
+        Ok(())
+    }
+}
+
+#[derive(Parser)]
+pub struct RestoreCli {
+    /// Snapshot id to restore; moves the branch head back to the snapshot's changelist
+    pub snapshot_id: i64,
+}
+
+impl RestoreCli {
+    pub async fn handle(&self, channel: &Channel) -> Result<()> {
+        let mut client = SnapshotServiceClient::new(channel.clone());
+
+        let response = client
+            .restore_snapshot(RestoreSnapshotReq {
+                snapshot_id: self.snapshot_id,
+            })
+            .await?
+            .into_inner();
+
+        println!(
+            "{} Branch {} restored to changelist {}",
+            style("✓").green(),
+            style(&response.branch_id).cyan(),
+            style(response.changelist_id).cyan()
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,326 @@
+use anyhow::Result;
+use chrono::DateTime;
+use clap::{Parser, Subcommand};
+use console::style;
+use crv_edge::pb::{
+    GetChangelistByTagReq, ListFileRevisionsByTagReq, TagChangelistReq, TagFileRevisionReq,
+    UntagChangelistReq, tag_service_client::TagServiceClient,
+};
+use std::str::FromStr;
+use tabled::{Table, Tabled, settings::Style};
+use tonic::transport::Channel;
+
+#[derive(Parser)]
+pub struct TagCli {
+    #[command(subcommand)]
+    pub tag_commands: TagCommands,
+}
+
+#[derive(Subcommand)]
+pub enum TagCommands {
+    Add(AddCli),
+    Remove(RemoveCli),
+    List(ListCli),
+    Revision(RevisionCli),
+    ListRevisions(ListRevisionsCli),
+}
+
+impl TagCli {
+    pub async fn handle(&self, channel: &Channel) -> Result<()> {
+        match &self.tag_commands {
+            TagCommands::Add(cli) => cli.handle(channel).await,
+            TagCommands::Remove(cli) => cli.handle(channel).await,
+            TagCommands::List(cli) => cli.handle(channel).await,
+            TagCommands::Revision(cli) => cli.handle(channel).await,
+            TagCommands::ListRevisions(cli) => cli.handle(channel).await,
+        }
+    }
+}
+
+#[derive(Parser)]
+pub struct AddCli {
+    /// Changelist id to tag
+    #[arg(long = "cl")]
+    pub changelist_id: i64,
+
+    /// Label to attach, e.g. release/v1.2
+    pub label: String,
+}
+
+impl AddCli {
+    pub async fn handle(&self, channel: &Channel) -> Result<()> {
+        let mut client = TagServiceClient::new(channel.clone());
+
+        client
+            .tag_changelist(TagChangelistReq {
+                changelist_id: self.changelist_id,
+                label: self.label.clone(),
+            })
+            .await?;
+
+        println!(
+            "{} Changelist {} tagged with {}",
+            style("✓").green(),
+            style(self.changelist_id).cyan(),
+            style(&self.label).cyan()
+        );
+        Ok(())
+    }
+}
+
+#[derive(Parser)]
+pub struct RemoveCli {
+    /// Changelist id to untag
+    #[arg(long = "cl")]
+    pub changelist_id: i64,
+
+    /// Label to remove
+    pub label: String,
+}
+
+impl RemoveCli {
+    pub async fn handle(&self, channel: &Channel) -> Result<()> {
+        let mut client = TagServiceClient::new(channel.clone());
+
+        client
+            .untag_changelist(UntagChangelistReq {
+                changelist_id: self.changelist_id,
+                label: self.label.clone(),
+            })
+            .await?;
+
+        println!(
+            "{} Label {} removed from changelist {}",
+            style("✓").green(),
+            style(&self.label).cyan(),
+            style(self.changelist_id).cyan()
+        );
+        Ok(())
+    }
+}
+
+#[derive(Tabled)]
+struct TaggedChangelistRow {
+    #[tabled(rename = "CL")]
+    changelist_id: i64,
+    #[tabled(rename = "Author")]
+    author: String,
+    #[tabled(rename = "Description")]
+    description: String,
+    #[tabled(rename = "Committed At")]
+    committed_at: String,
+    #[tabled(rename = "Labels")]
+    labels: String,
+}
+
+#[derive(Parser)]
+pub struct ListCli {
+    /// Only list changelists reachable from this branch's head; omit to search all branches
+    #[arg(short, long)]
+    pub branch: Option<String>,
+
+    /// Label to search for
+    #[arg(short, long)]
+    pub tag: String,
+
+    /// Maximum number of changelists to list, 0 for the server default
+    #[arg(short, long, default_value_t = 0)]
+    pub limit: u32,
+}
+
+impl ListCli {
+    pub async fn handle(&self, channel: &Channel) -> Result<()> {
+        let mut client = TagServiceClient::new(channel.clone());
+
+        let response = client
+            .get_changelist_by_tag(GetChangelistByTagReq {
+                branch_id: self.branch.clone().unwrap_or_default(),
+                label: self.tag.clone(),
+                limit: self.limit,
+            })
+            .await?
+            .into_inner();
+
+        if super::json_output() {
+            return super::print_json(&response);
+        }
+
+        if response.changelists.is_empty() {
+            println!(
+                "{}",
+                style(format!("No changelists tagged with {}.", self.tag)).yellow()
+            );
+            return Ok(());
+        }
+
+        let rows: Vec<TaggedChangelistRow> = response
+            .changelists
+            .into_iter()
+            .map(|cl| TaggedChangelistRow {
+                changelist_id: cl.changelist_id,
+                author: cl.author,
+                description: cl.description,
+                committed_at: DateTime::from_timestamp(cl.committed_at, 0)
+                    .map(|t| t.format("%Y-%m-%d %H:%M:%S").to_string())
+                    .unwrap_or_default(),
+                labels: cl.labels.join(", "),
+            })
+            .collect();
+
+        let mut table = Table::new(&rows);
+        table.with(Style::rounded());
+
+        println!("\n{}", table);
+        println!("\n{} changelist(s) found", style(rows.len()).cyan());
+        Ok(())
+    }
+}
+
+/// A file revision written as `DEPOT_PATH#GENERATION:REVISION`, e.g. `//art/hero.png#1:3`
+#[derive(Debug, Clone, PartialEq)]
+pub struct RevisionId {
+    pub path: String,
+    pub generation: i64,
+    pub revision: i64,
+}
+
+impl FromStr for RevisionId {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let invalid = || format!("invalid revision '{s}', expected DEPOT_PATH#GENERATION:REVISION");
+        let (path, version) = s.rsplit_once('#').ok_or_else(invalid)?;
+        let (generation, revision) = version.split_once(':').ok_or_else(invalid)?;
+        if path.is_empty() {
+            return Err(invalid());
+        }
+        Ok(Self {
+            path: path.to_string(),
+            generation: generation.parse().map_err(|_| invalid())?,
+            revision: revision.parse().map_err(|_| invalid())?,
+        })
+    }
+}
+
+/// Add or remove pipeline tags on a file revision
+#[derive(Parser)]
+pub struct RevisionCli {
+    /// File revision as DEPOT_PATH#GENERATION:REVISION, e.g. //art/hero.png#1:3
+    pub revision_id: RevisionId,
+
+    /// Tag to add, can be repeated
+    #[arg(short, long)]
+    pub add: Vec<String>,
+
+    /// Tag to remove, can be repeated
+    #[arg(short, long)]
+    pub remove: Vec<String>,
+}
+
+impl RevisionCli {
+    pub async fn handle(&self, channel: &Channel) -> Result<()> {
+        let mut client = TagServiceClient::new(channel.clone());
+
+        let response = client
+            .tag_file_revision(TagFileRevisionReq {
+                path: self.revision_id.path.clone(),
+                generation: self.revision_id.generation,
+                revision: self.revision_id.revision,
+                tags_to_add: self.add.clone(),
+                tags_to_remove: self.remove.clone(),
+            })
+            .await?
+            .into_inner();
+
+        let tags = if response.tags.is_empty() {
+            "no tags".to_string()
+        } else {
+            response.tags.join(", ")
+        };
+        println!(
+            "{} Revision {}#{}:{} now has {}",
+            style("✓").green(),
+            style(&self.revision_id.path).cyan(),
+            self.revision_id.generation,
+            self.revision_id.revision,
+            style(tags).cyan()
+        );
+        Ok(())
+    }
+}
+
+#[derive(Tabled)]
+struct TaggedFileRevisionRow {
+    #[tabled(rename = "Path")]
+    path: String,
+    #[tabled(rename = "Revision")]
+    revision: String,
+    #[tabled(rename = "CL")]
+    changelist_id: i64,
+    #[tabled(rename = "Size")]
+    size: i64,
+    #[tabled(rename = "Tags")]
+    tags: String,
+}
+
+/// List file revisions carrying a tag
+#[derive(Parser)]
+pub struct ListRevisionsCli {
+    /// Only list revisions submitted up to this branch's head; omit to search all branches
+    #[arg(short, long)]
+    pub branch: Option<String>,
+
+    /// Tag to search for
+    #[arg(short, long)]
+    pub tag: String,
+
+    /// Maximum number of revisions to list, 0 for the server default
+    #[arg(short, long, default_value_t = 0)]
+    pub limit: u32,
+}
+
+impl ListRevisionsCli {
+    pub async fn handle(&self, channel: &Channel) -> Result<()> {
+        let mut client = TagServiceClient::new(channel.clone());
+
+        let response = client
+            .list_file_revisions_by_tag(ListFileRevisionsByTagReq {
+                branch_id: self.branch.clone().unwrap_or_default(),
+                tag: self.tag.clone(),
+                limit: self.limit,
+            })
+            .await?
+            .into_inner();
+
+        if super::json_output() {
+            return super::print_json(&response);
+        }
+
+        if response.revisions.is_empty() {
+            println!(
+                "{}",
+                style(format!("No file revisions tagged with {}.", self.tag)).yellow()
+            );
+            return Ok(());
+        }
+
+        let rows: Vec<TaggedFileRevisionRow> = response
+            .revisions
+            .into_iter()
+            .map(|r| TaggedFileRevisionRow {
+                path: r.path,
+                revision: format!("{}:{}", r.generation, r.revision),
+                changelist_id: r.changelist_id,
+                size: r.size,
+                tags: r.tags.join(", "),
+            })
+            .collect();
+
+        let mut table = Table::new(&rows);
+        table.with(Style::rounded());
+
+        println!("\n{}", table);
+        println!("\n{} revision(s) found", style(rows.len()).cyan());
+        Ok(())
+    }
+}
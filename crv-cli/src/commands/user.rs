@@ -0,0 +1,177 @@
+use anyhow::Result;
+use chrono::DateTime;
+use clap::{Parser, Subcommand};
+use console::style;
+use crv_edge::pb::{
+    DeleteUserReq, ListUsersReq, UpdateUserPasswordReq, user_service_client::UserServiceClient,
+};
+use dialoguer::{Confirm, Password, theme::ColorfulTheme};
+use tabled::{Table, Tabled, settings::Style};
+use tonic::transport::Channel;
+
+#[derive(Parser)]
+pub struct UserCli {
+    #[command(subcommand)]
+    pub user_commands: UserCommands,
+}
+
+#[derive(Subcommand)]
+pub enum UserCommands {
+    List(ListCli),
+    UpdatePassword(UpdatePasswordCli),
+    Delete(DeleteCli),
+}
+
+impl UserCli {
+    pub async fn handle(&self, channel: &Channel) -> Result<()> {
+        match &self.user_commands {
+            UserCommands::List(cli) => cli.handle(channel).await,
+            UserCommands::UpdatePassword(cli) => cli.handle(channel).await,
+            UserCommands::Delete(cli) => cli.handle(channel).await,
+        }
+    }
+}
+
+#[derive(Tabled)]
+struct UserRow {
+    #[tabled(rename = "Username")]
+    username: String,
+    #[tabled(rename = "Created At")]
+    created_at: String,
+}
+
+#[derive(Parser)]
+pub struct ListCli {
+    /// Page number, starting from 0
+    #[arg(short, long, default_value_t = 0)]
+    pub page: u32,
+
+    /// Number of users per page, 0 for the server default
+    #[arg(short = 's', long, default_value_t = 0)]
+    pub page_size: u32,
+}
+
+impl ListCli {
+    pub async fn handle(&self, channel: &Channel) -> Result<()> {
+        let mut client = UserServiceClient::new(channel.clone());
+
+        let response = client
+            .list_users(ListUsersReq {
+                page: self.page,
+                page_size: self.page_size,
+            })
+            .await?
+            .into_inner();
+
+        if super::json_output() {
+            return super::print_json(&response);
+        }
+
+        if response.users.is_empty() {
+            println!("{}", style("No users found.").yellow());
+            return Ok(());
+        }
+
+        let rows: Vec<UserRow> = response
+            .users
+            .into_iter()
+            .map(|u| UserRow {
+                username: u.username,
+                created_at: if u.created_at == 0 {
+                    "-".to_string()
+                } else {
+                    DateTime::from_timestamp_millis(u.created_at)
+                        .map(|t| t.format("%Y-%m-%d %H:%M:%S").to_string())
+                        .unwrap_or_default()
+                },
+            })
+            .collect();
+
+        let mut table = Table::new(&rows);
+        table.with(Style::rounded());
+
+        println!("\n{}", table);
+        println!(
+            "\n{} of {} user(s) shown",
+            style(rows.len()).cyan(),
+            style(response.total).cyan()
+        );
+        Ok(())
+    }
+}
+
+#[derive(Parser)]
+pub struct UpdatePasswordCli {
+    /// Username
+    pub username: String,
+
+    /// New password, prompted for if omitted
+    #[arg(short, long)]
+    pub password: Option<String>,
+}
+
+impl UpdatePasswordCli {
+    pub async fn handle(&self, channel: &Channel) -> Result<()> {
+        let new_password = if let Some(password) = &self.password {
+            password.clone()
+        } else {
+            Password::with_theme(&ColorfulTheme::default())
+                .with_prompt(format!("New password for {}", self.username))
+                .with_confirmation("Confirm new password", "Passwords do not match")
+                .interact()?
+        };
+
+        let mut client = UserServiceClient::new(channel.clone());
+        client
+            .update_user_password(UpdateUserPasswordReq {
+                username: self.username.clone(),
+                new_password,
+            })
+            .await?;
+
+        println!(
+            "{} Password of user {} updated",
+            style("✓").green(),
+            style(&self.username).cyan()
+        );
+        Ok(())
+    }
+}
+
+#[derive(Parser)]
+pub struct DeleteCli {
+    /// Username
+    pub username: String,
+
+    /// Skip the confirmation prompt
+    #[arg(short, long)]
+    pub yes: bool,
+}
+
+impl DeleteCli {
+    pub async fn handle(&self, channel: &Channel) -> Result<()> {
+        if !self.yes
+            && !Confirm::with_theme(&ColorfulTheme::default())
+                .with_prompt(format!("Delete user {}?", self.username))
+                .default(false)
+                .interact()?
+        {
+            println!("{}", style("Aborted.").yellow());
+            return Ok(());
+        }
+
+        let mut client = UserServiceClient::new(channel.clone());
+        client
+            .delete_user(DeleteUserReq {
+                username: self.username.clone(),
+            })
+            .await?;
+
+        println!(
+            "{} User {} deleted",
+            style("✓").green(),
+            style(&self.username).cyan()
+        );
+        Ok(())
+    }
+}
@@ -4,11 +4,13 @@ use anyhow::Result;
 use clap::{Parser, Subcommand};
 use console::style;
 use crv_edge::pb::{
-    CreateWorkspaceReq, GetRuntimeConfigReq, ListWorkspacesReq,
-    system_service_client::SystemServiceClient, workspace_service_client::WorkspaceServiceClient,
+    CreateWorkspaceReq, DescribeWorkspaceReq, GetRuntimeConfigReq, ListWorkspacesReq,
+    SwitchWorkspaceReq, ValidateWorkspaceReq, system_service_client::SystemServiceClient,
+    workspace_service_client::WorkspaceServiceClient,
 };
 use dialoguer::{Input, theme::ColorfulTheme};
 use tabled::{Table, Tabled, settings::Style};
+use tonic::Request;
 use tonic::transport::Channel;
 
 #[derive(Parser)]
@@ -23,6 +25,12 @@ pub enum WorkspaceCommands {
     Delete(DeleteCli),
     List(ListCli),
     Describe(DescribeCli),
+    Validate(ValidateCli),
+    Switch(SwitchCli),
+    #[command(name = "set-mapping")]
+    Mapping(super::mapping::WorkspaceMappingCli),
+    #[command(name = "list-mappings")]
+    ListMappings(super::mapping::ListCli),
 }
 
 impl WorkspaceCli {
@@ -32,12 +40,33 @@ impl WorkspaceCli {
             WorkspaceCommands::Delete(cli) => cli.handle(channel).await,
             WorkspaceCommands::List(cli) => cli.handle(channel).await,
             WorkspaceCommands::Describe(cli) => cli.handle(channel).await,
+            WorkspaceCommands::Validate(cli) => cli.handle(channel).await,
+            WorkspaceCommands::Switch(cli) => cli.handle(channel).await,
+            WorkspaceCommands::Mapping(cli) => cli.handle(channel).await,
+            WorkspaceCommands::ListMappings(cli) => cli.handle(channel).await,
         }
     }
 }
 
 #[derive(Parser)]
-pub struct CreateCli;
+pub struct CreateCli {
+    /// Workspace name, prompted for when omitted
+    #[arg(long)]
+    pub name: Option<String>,
+
+    /// Workspace root path, prompted for when omitted
+    #[arg(long)]
+    pub root: Option<String>,
+
+    /// File containing the workspace mapping. When omitted, the mapping is edited in the
+    /// editor unless both --name and --root are given, in which case it is left empty
+    #[arg(long)]
+    pub mapping_file: Option<String>,
+
+    /// Hive to register the workspace on, defaults to the edge's configured hive
+    #[arg(long)]
+    pub hive_url: Option<String>,
+}
 
 impl CreateCli {
     pub async fn handle(&self, channel: &Channel) -> Result<()> {
@@ -47,31 +76,41 @@ impl CreateCli {
             .await?; // todo use editor set in it.
 
         // Step 1: Enter workspace name
-        let workspace_name = Input::<String>::with_theme(&ColorfulTheme::default())
-            .with_prompt("Workspace name")
-            .interact_text()
-            .expect("Meet error");
+        let workspace_name = match &self.name {
+            Some(name) => name.clone(),
+            None => Input::<String>::with_theme(&ColorfulTheme::default())
+                .with_prompt("Workspace name")
+                .interact_text()
+                .expect("Meet error"),
+        };
 
         if workspace_name.trim().is_empty() {
             anyhow::bail!("Workspace name cannot be empty");
         }
 
         // Step 2: Enter workspace root path with completion
-        let workspace_root = Input::<String>::with_theme(&ColorfulTheme::default())
-            .with_prompt("Workspace root path")
-            .completion_with(&PathCompletion)
-            .interact_text()
-            .expect("Meet error");
+        let workspace_root = match &self.root {
+            Some(root) => root.clone(),
+            None => Input::<String>::with_theme(&ColorfulTheme::default())
+                .with_prompt("Workspace root path")
+                .completion_with(&PathCompletion)
+                .interact_text()
+                .expect("Meet error"),
+        };
 
         if workspace_root.trim().is_empty() {
             anyhow::bail!("Workspace root path cannot be empty");
         }
 
         // Step 3: Enter workspace mapping in editor
-        let mapping = edit::edit(
-            "# Enter workspace mapping view here\n# Lines starting with # will be ignored\n",
-        )
-        .expect("Meet error");
+        let mapping = match &self.mapping_file {
+            Some(path) => std::fs::read_to_string(path)?,
+            None if self.name.is_some() && self.root.is_some() => String::new(),
+            None => edit::edit(
+                "# Enter workspace mapping view here\n# Lines starting with # will be ignored\n",
+            )
+            .expect("Meet error"),
+        };
 
         // Process the mapping content (remove comment lines)
         let mapping = mapping
@@ -91,11 +130,17 @@ impl CreateCli {
         println!("  Root: {}", style(&workspace_root).cyan());
         println!("  Mapping: {} lines", style(mapping.lines().count()).cyan());
 
-        let create_req = CreateWorkspaceReq {
+        let mut create_req = Request::new(CreateWorkspaceReq {
             workspace_name,
             workspace_root,
             workspace_mapping: mapping,
-        };
+        });
+        if let Some(hive_url) = &self.hive_url {
+            let overrides = serde_json::json!({ "remote_addr": hive_url });
+            create_req
+                .metadata_mut()
+                .insert("x-crv-config-override", overrides.to_string().parse()?);
+        }
         let mut workspace_client = WorkspaceServiceClient::new(channel.clone());
         workspace_client.create_workspace(create_req).await?;
 
@@ -184,6 +229,10 @@ impl ListCli {
 
         let workspaces = response.into_inner();
 
+        if super::json_output() {
+            return super::print_json(&workspaces);
+        }
+
         if workspaces.workspace_names.is_empty() {
             println!("{}", style("No workspaces found.").yellow());
             return Ok(());
@@ -211,10 +260,166 @@ impl ListCli {
 }
 
 #[derive(Parser)]
-pub struct DescribeCli;
+pub struct DescribeCli {
+    /// Workspace name
+    pub name: String,
+
+    /// Only list files under this depot path, e.g. //project/src/...
+    #[arg(long)]
+    pub path: Option<String>,
+}
+
+#[derive(Tabled)]
+struct WorkspaceFileRow {
+    #[tabled(rename = "Depot Path")]
+    depot_path: String,
+    #[tabled(rename = "Local Path")]
+    local_path: String,
+    #[tabled(rename = "Revision")]
+    revision: String,
+    #[tabled(rename = "Size")]
+    size: i64,
+}
 
 impl DescribeCli {
     pub async fn handle(&self, channel: &Channel) -> Result<()> {
-        todo!()
+        let mut workspace_client = WorkspaceServiceClient::new(channel.clone());
+
+        let response = workspace_client
+            .describe_workspace(DescribeWorkspaceReq {
+                workspace_name: self.name.clone(),
+                depot_wildcard: self.path.clone().unwrap_or_default(),
+            })
+            .await?
+            .into_inner();
+
+        if super::json_output() {
+            return super::print_json(&response);
+        }
+
+        println!(
+            "Workspace {} at {} (changelist {})",
+            style(&response.workspace_name).cyan(),
+            response.workspace_path,
+            style(response.changelist_id).cyan()
+        );
+
+        if response.files.is_empty() {
+            println!("{}", style("No files found.").yellow());
+            return Ok(());
+        }
+
+        let rows: Vec<WorkspaceFileRow> = response
+            .files
+            .into_iter()
+            .map(|f| WorkspaceFileRow {
+                depot_path: f.depot_path,
+                local_path: f.local_path,
+                revision: format!("{}#{}", f.generation, f.revision),
+                size: f.size,
+            })
+            .collect();
+
+        let mut table = Table::new(&rows);
+        table.with(Style::rounded());
+
+        println!("\n{}", table);
+        println!("\n{} file(s) mapped", style(rows.len()).cyan());
+
+        Ok(())
+    }
+}
+
+#[derive(Parser)]
+pub struct ValidateCli {
+    /// Workspace name
+    pub name: String,
+}
+
+impl ValidateCli {
+    pub async fn handle(&self, channel: &Channel) -> Result<()> {
+        let mut workspace_client = WorkspaceServiceClient::new(channel.clone());
+
+        let response = workspace_client
+            .validate_workspace(ValidateWorkspaceReq {
+                workspace_name: self.name.clone(),
+            })
+            .await?
+            .into_inner();
+
+        if response.conflicting_local_paths.is_empty() {
+            println!(
+                "{} Workspace {} mappings are conflict free.",
+                style("✓").green(),
+                style(&self.name).cyan()
+            );
+            return Ok(());
+        }
+
+        println!(
+            "{}",
+            style(format!(
+                "Workspace {} has conflicting mappings on these local paths:",
+                self.name
+            ))
+            .red()
+        );
+        for local_path in &response.conflicting_local_paths {
+            println!("  {}", style(local_path).yellow());
+        }
+        anyhow::bail!(
+            "{} mapping conflict(s) found",
+            response.conflicting_local_paths.len()
+        )
+    }
+}
+
+#[derive(Parser)]
+pub struct SwitchCli {
+    /// Workspace name to switch to
+    pub name: String,
+}
+
+impl SwitchCli {
+    pub async fn handle(&self, channel: &Channel) -> Result<()> {
+        let mut workspace_client = WorkspaceServiceClient::new(channel.clone());
+
+        println!(
+            "{}",
+            style(format!("Switching to workspace {}...", self.name)).cyan()
+        );
+        let response = workspace_client
+            .switch_workspace(SwitchWorkspaceReq {
+                workspace_name: self.name.clone(),
+            })
+            .await?
+            .into_inner();
+
+        if !response.checkpoint_name.is_empty() {
+            println!(
+                "  Saved workspace {} as checkpoint {}",
+                style(&response.previous_workspace).cyan(),
+                style(&response.checkpoint_name).cyan()
+            );
+        }
+        for path in &response.removed_paths {
+            println!("  {} {}", style("-").red(), path);
+        }
+        for path in &response.reused_paths {
+            println!("  {} {}", style("~").yellow(), path);
+        }
+        for path in &response.downloaded_paths {
+            println!("  {} {}", style("+").green(), path);
+        }
+
+        println!(
+            "{} Switched to workspace {} ({} downloaded, {} reused, {} removed)",
+            style("✓").green(),
+            style(&self.name).cyan(),
+            response.downloaded_paths.len(),
+            response.reused_paths.len(),
+            response.removed_paths.len()
+        );
+        Ok(())
     }
 }
@@ -0,0 +1,56 @@
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+/// 每个测试使用独立的主目录，避免读写真实的配置与历史记录
+fn temp_home() -> PathBuf {
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    let home = std::env::temp_dir().join(format!("crv-cli-test-{}-{nanos}", std::process::id()));
+    std::fs::create_dir_all(&home).unwrap();
+    home
+}
+
+#[test]
+fn repl_runs_commands_from_stdin() {
+    let home = temp_home();
+    let mut child = Command::new(env!("CARGO_BIN_EXE_crv"))
+        .arg("--repl")
+        .env("HOME", &home)
+        .env("XDG_CONFIG_HOME", home.join(".config"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(b"completion fish\n\nnot-a-command\nhelp\nexit\n")
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        output.status.success(),
+        "stdout: {stdout}\nstderr: {stderr}"
+    );
+
+    assert!(stdout.contains("Welcome to CRV Edge Shell"));
+    // completion fish
+    assert!(stdout.contains("complete -c crv"));
+    // 无法解析的命令报错后继续执行后面的命令
+    assert!(stderr.contains("not-a-command"));
+    // help
+    assert!(stdout.contains("Usage:"));
+    assert!(stdout.contains("bye~"));
+
+    let history = std::fs::read_to_string(home.join(".crv_history")).unwrap();
+    assert!(history.contains("completion fish"));
+    assert!(history.contains("help"));
+
+    std::fs::remove_dir_all(home).unwrap();
+}
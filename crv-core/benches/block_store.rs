@@ -0,0 +1,51 @@
+//! 对比 `BlockStore` 的内存缓存与直接读盘：反复读取同样的 100 个 block 10 000 轮
+use criterion::{Criterion, black_box, criterion_group, criterion_main};
+use crv_core::storage::file_block::FileBlock;
+use crv_core::storage::{BlockStore, read_block};
+
+const BLOCKS: u32 = 100;
+const ROUNDS: u32 = 10_000;
+
+fn stored_blocks(store: &BlockStore) -> Vec<String> {
+    (0..BLOCKS)
+        .map(|i| {
+            let mut data = vec![0u8; 4096];
+            data[..4].copy_from_slice(&i.to_le_bytes());
+            let block = FileBlock::from_bytes(data);
+            let id = block.id.clone();
+            store.put(block).unwrap();
+            id
+        })
+        .collect()
+}
+
+fn repeated_reads(c: &mut Criterion) {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let store = BlockStore::new(temp_dir.path());
+    let ids = stored_blocks(&store);
+
+    let mut group = c.benchmark_group("block_store_100x10k");
+    group.sample_size(10);
+    group.bench_function("cached", |b| {
+        b.iter(|| {
+            for _ in 0..ROUNDS {
+                for id in &ids {
+                    black_box(store.get(id).unwrap());
+                }
+            }
+        })
+    });
+    group.bench_function("disk", |b| {
+        b.iter(|| {
+            for _ in 0..ROUNDS {
+                for id in &ids {
+                    black_box(read_block(store.root(), id).unwrap());
+                }
+            }
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, repeated_reads);
+criterion_main!(benches);
@@ -0,0 +1,63 @@
+//! 对比增量合并索引与从 pack 全量重建索引的耗时，规模为 10 000 个 chunk
+use criterion::{BatchSize, Criterion, criterion_group, criterion_main};
+use crv_core::repository::{
+    Compression, IndexEntry, IndexSnapshot, MutableIndex, Repository, compute_chunk_hash,
+};
+
+const TOTAL_CHUNKS: u32 = 10_000;
+const INCREMENTAL_CHUNKS: u32 = 1_000;
+
+fn chunk_data(i: u32) -> Vec<u8> {
+    let mut data = vec![0u8; 64];
+    data[..4].copy_from_slice(&i.to_le_bytes());
+    data
+}
+
+fn entries(range: std::ops::Range<u32>) -> Vec<IndexEntry> {
+    range
+        .map(|i| {
+            IndexEntry::new(
+                compute_chunk_hash(&chunk_data(i)),
+                u64::from(i) * 102,
+                64,
+                0,
+            )
+        })
+        .collect()
+}
+
+fn incremental_merge(c: &mut Criterion) {
+    let base = entries(0..TOTAL_CHUNKS - INCREMENTAL_CHUNKS);
+    let incoming = entries(TOTAL_CHUNKS - INCREMENTAL_CHUNKS..TOTAL_CHUNKS);
+    c.bench_function("index_incremental_merge_10k", |b| {
+        b.iter_batched(
+            || {
+                let temp_dir = tempfile::tempdir().unwrap();
+                let mut index =
+                    MutableIndex::create_new(temp_dir.path().join("bench.idx")).unwrap();
+                index
+                    .merge(IndexSnapshot::from_entries(base.clone()).unwrap())
+                    .unwrap();
+                let snapshot = IndexSnapshot::from_entries(incoming.clone()).unwrap();
+                (temp_dir, index, snapshot)
+            },
+            |(_temp_dir, mut index, snapshot)| index.merge(snapshot).unwrap(),
+            BatchSize::PerIteration,
+        )
+    });
+}
+
+fn full_rebuild(c: &mut Criterion) {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let repo = Repository::new(temp_dir.path()).unwrap();
+    for i in 0..TOTAL_CHUNKS {
+        repo.write_chunk(&chunk_data(i), Compression::None).unwrap();
+    }
+    repo.seal_all().unwrap();
+    c.bench_function("index_full_rebuild_10k", |b| {
+        b.iter(|| repo.rebuild_index_from_packs().unwrap())
+    });
+}
+
+criterion_group!(benches, incremental_merge, full_rebuild);
+criterion_main!(benches);
@@ -1,8 +1,9 @@
+pub mod logger;
 pub mod metadata;
 pub mod parsers;
 pub mod path;
+pub mod repository;
 pub mod storage;
+pub mod tree;
 pub mod user;
 pub mod workspace;
-pub mod repository;
-pub mod tree;
\ No newline at end of file
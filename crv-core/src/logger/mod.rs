@@ -0,0 +1,3 @@
+//! 日志
+pub mod reader;
+pub mod recovery;
@@ -0,0 +1,226 @@
+//! 分页读取 JSON Lines 格式的日志
+//!
+//! [`LogReader::tail`] 从文件末尾向前按块读取，只读取最后几条记录所在的部分，不必读完整个日志。
+//! 与 [`RecoveryLog`](super::recovery::RecoveryLog) 一样，最后一行写了一半时忽略该行。
+use super::recovery::LogEntry;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+
+/// `tail` 每次向前读取的字节数
+const TAIL_CHUNK_SIZE: u64 = 8 * 1024;
+
+pub struct LogReader<R> {
+    inner: R,
+}
+
+impl LogReader<File> {
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        Ok(Self::new(File::open(path)?))
+    }
+}
+
+impl<R: Read + Seek> LogReader<R> {
+    pub fn new(inner: R) -> Self {
+        Self { inner }
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    /// 读取第 `page` 页（从 0 开始）的记录，每页 `page_size` 条，超出日志末尾时返回空
+    pub fn read_page(&mut self, page: usize, page_size: usize) -> io::Result<Vec<LogEntry>> {
+        self.inner.seek(SeekFrom::Start(0))?;
+        let skip = page.saturating_mul(page_size);
+
+        let mut entries = Vec::with_capacity(page_size);
+        let mut index = 0;
+        for line in BufReader::new(&mut self.inner).lines() {
+            if entries.len() >= page_size {
+                break;
+            }
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            index += 1;
+            if index <= skip {
+                continue;
+            }
+            match serde_json::from_str(&line) {
+                Ok(entry) => entries.push(entry),
+                // 写了一半的最后一行
+                Err(_) => break,
+            }
+        }
+        Ok(entries)
+    }
+
+    /// 按顺序返回最后 `n` 条记录，日志不足 `n` 条时返回全部
+    pub fn tail(&mut self, n: usize) -> io::Result<Vec<LogEntry>> {
+        if n == 0 {
+            return Ok(Vec::new());
+        }
+
+        let mut pos = self.inner.seek(SeekFrom::End(0))?;
+        // 文件中 `pos` 之后的内容
+        let mut buf = Vec::new();
+        while pos > 0 {
+            let start = pos.saturating_sub(TAIL_CHUNK_SIZE);
+            let mut chunk = vec![0; (pos - start) as usize];
+            self.inner.seek(SeekFrom::Start(start))?;
+            self.inner.read_exact(&mut chunk)?;
+            chunk.extend_from_slice(&buf);
+            buf = chunk;
+            pos = start;
+
+            // 多读到一个换行才能确定最前面的一条记录是完整的
+            if buf.iter().filter(|&&b| b == b'\n').count() > n {
+                let entries = parse_tail(&buf, pos == 0, n)?;
+                if entries.len() >= n {
+                    return Ok(entries);
+                }
+            }
+        }
+        parse_tail(&buf, true, n)
+    }
+}
+
+/// 解析 `buf` 中的最后 `n` 条记录，`from_start` 为假时 `buf` 的第一行可能不完整，直接跳过
+fn parse_tail(buf: &[u8], from_start: bool, n: usize) -> io::Result<Vec<LogEntry>> {
+    let buf = if from_start {
+        buf
+    } else {
+        match buf.iter().position(|&b| b == b'\n') {
+            Some(i) => &buf[i + 1..],
+            None => &[],
+        }
+    };
+
+    let lines: Vec<&[u8]> = buf
+        .split(|&b| b == b'\n')
+        .filter(|line| !line.trim_ascii().is_empty())
+        .collect();
+    let torn_last = !buf.ends_with(b"\n");
+
+    let mut entries = Vec::with_capacity(n.min(lines.len()));
+    for (i, line) in lines.iter().enumerate() {
+        match serde_json::from_slice(line) {
+            Ok(entry) => entries.push(entry),
+            Err(_) if torn_last && i + 1 == lines.len() => {}
+            Err(e) => return Err(io::Error::new(io::ErrorKind::InvalidData, e)),
+        }
+    }
+    let skip = entries.len().saturating_sub(n);
+    entries.drain(..skip);
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::logger::recovery::RecoveryLog;
+    use std::fs::OpenOptions;
+    use std::io::Write;
+
+    /// 记录读取了多少字节
+    struct CountingReader {
+        inner: File,
+        bytes_read: u64,
+    }
+
+    impl Read for CountingReader {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let n = self.inner.read(buf)?;
+            self.bytes_read += n as u64;
+            Ok(n)
+        }
+    }
+
+    impl Seek for CountingReader {
+        fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+            self.inner.seek(pos)
+        }
+    }
+
+    fn commit(i: usize) -> LogEntry {
+        LogEntry::Commit {
+            file_id: format!("file-{i}"),
+        }
+    }
+
+    fn write_log(path: &Path, count: usize) {
+        let log = RecoveryLog::new(path);
+        for i in 0..count {
+            log.append(&commit(i)).unwrap();
+        }
+    }
+
+    #[test]
+    fn tail_reads_only_the_end_of_the_log() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("recovery.log");
+        write_log(&path, 10_000);
+        let file_len = std::fs::metadata(&path).unwrap().len();
+
+        let mut reader = LogReader::new(CountingReader {
+            inner: File::open(&path).unwrap(),
+            bytes_read: 0,
+        });
+        let entries = reader.tail(100).unwrap();
+        assert_eq!(entries, (9_900..10_000).map(commit).collect::<Vec<_>>());
+
+        let bytes_read = reader.into_inner().bytes_read;
+        assert!(
+            bytes_read < file_len / 10,
+            "read {bytes_read} of {file_len} bytes"
+        );
+    }
+
+    #[test]
+    fn tail_returns_whole_short_log() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("recovery.log");
+        write_log(&path, 3);
+
+        let mut reader = LogReader::open(&path).unwrap();
+        assert_eq!(
+            reader.tail(10).unwrap(),
+            (0..3).map(commit).collect::<Vec<_>>()
+        );
+        assert!(reader.tail(0).unwrap().is_empty());
+    }
+
+    #[test]
+    fn torn_last_line_is_ignored() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("recovery.log");
+        write_log(&path, 5);
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(br#"{"op":"commit","fil"#).unwrap();
+        drop(file);
+
+        let mut reader = LogReader::open(&path).unwrap();
+        assert_eq!(reader.tail(2).unwrap(), vec![commit(3), commit(4)]);
+        assert_eq!(reader.read_page(1, 3).unwrap(), vec![commit(3), commit(4)]);
+    }
+
+    #[test]
+    fn pages_cover_the_log_in_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("recovery.log");
+        write_log(&path, 25);
+
+        let mut reader = LogReader::open(&path).unwrap();
+        assert_eq!(
+            reader.read_page(0, 10).unwrap(),
+            (0..10).map(commit).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            reader.read_page(2, 10).unwrap(),
+            (20..25).map(commit).collect::<Vec<_>>()
+        );
+        assert!(reader.read_page(3, 10).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,320 @@
+//! 预写式恢复日志
+//!
+//! 写入文件前先在日志中追加一条 [`LogEntry::Begin`]，内容写入 [`partial_path`] 给出的临时文件，
+//! 完成后替换目标文件并追加 [`LogEntry::Commit`]；失败时删除临时文件并追加 [`LogEntry::Abort`]。
+//! 进程在两者之间崩溃时，下次启动调用 [`RecoveryLog::recover`] 即可找到未完成的操作并清理现场，
+//! 不会留下写了一半的文件。
+//!
+//! 日志为 JSON Lines 格式，每条记录写入后立即刷盘。崩溃时最后一行可能不完整，读取时忽略。
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// 日志中的一条记录，`file_id` 标识同一个文件上的操作
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum LogEntry {
+    /// 开始写入 `target_path`，内容先写入其临时文件
+    Begin {
+        file_id: String,
+        target_path: PathBuf,
+    },
+    /// 临时文件已替换目标文件
+    Commit { file_id: String },
+    /// 写入失败，临时文件已删除
+    Abort { file_id: String },
+}
+
+/// 目标文件对应的临时文件，与目标文件位于同一目录，以便通过重命名原子地替换
+pub fn partial_path(target_path: &Path) -> PathBuf {
+    let mut path = target_path.as_os_str().to_owned();
+    path.push(".crv-partial");
+    PathBuf::from(path)
+}
+
+/// 重放日志得到的状态
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RecoveryState {
+    /// 已经开始但没有提交或放弃的操作，键为 `file_id`
+    pending: BTreeMap<String, PathBuf>,
+}
+
+impl RecoveryState {
+    /// 按顺序应用一条记录
+    pub fn apply(&mut self, entry: LogEntry) {
+        match entry {
+            LogEntry::Begin {
+                file_id,
+                target_path,
+            } => {
+                self.pending.insert(file_id, target_path);
+            }
+            LogEntry::Commit { file_id } | LogEntry::Abort { file_id } => {
+                self.pending.remove(&file_id);
+            }
+        }
+    }
+
+    /// 未完成的操作及其目标文件
+    pub fn pending(&self) -> impl Iterator<Item = (&str, &Path)> {
+        self.pending
+            .iter()
+            .map(|(file_id, target_path)| (file_id.as_str(), target_path.as_path()))
+    }
+
+    pub fn is_clean(&self) -> bool {
+        self.pending.is_empty()
+    }
+}
+
+/// 恢复时对一个未完成的操作所做的处理
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RecoveryAction {
+    /// 临时文件仍在，说明目标文件没有被替换，删除临时文件
+    RolledBack {
+        file_id: String,
+        target_path: PathBuf,
+    },
+    /// 临时文件已经替换了目标文件，只是没来得及记录提交
+    Completed {
+        file_id: String,
+        target_path: PathBuf,
+    },
+}
+
+/// 追加写入的恢复日志，可在多个线程间共享
+pub struct RecoveryLog {
+    path: PathBuf,
+    /// 第一次写入时才打开，日志文件不存在时不会创建
+    file: Mutex<Option<File>>,
+}
+
+impl RecoveryLog {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            file: Mutex::new(None),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn begin(&self, file_id: &str, target_path: &Path) -> io::Result<()> {
+        self.append(&LogEntry::Begin {
+            file_id: file_id.to_string(),
+            target_path: target_path.to_path_buf(),
+        })
+    }
+
+    pub fn commit(&self, file_id: &str) -> io::Result<()> {
+        self.append(&LogEntry::Commit {
+            file_id: file_id.to_string(),
+        })
+    }
+
+    pub fn abort(&self, file_id: &str) -> io::Result<()> {
+        self.append(&LogEntry::Abort {
+            file_id: file_id.to_string(),
+        })
+    }
+
+    /// 追加一条记录并刷盘
+    pub fn append(&self, entry: &LogEntry) -> io::Result<()> {
+        let mut line = serde_json::to_vec(entry).map_err(io::Error::other)?;
+        line.push(b'\n');
+
+        let mut file = self.file.lock().expect("recovery log poisoned");
+        if file.is_none() {
+            if let Some(parent) = self.path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            *file = Some(
+                OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(&self.path)?,
+            );
+        }
+        let file = file.as_mut().expect("recovery log opened above");
+        file.write_all(&line)?;
+        file.sync_data()
+    }
+
+    /// 重放日志，日志不存在时返回空状态
+    pub fn state(&self) -> io::Result<RecoveryState> {
+        let _guard = self.file.lock().expect("recovery log poisoned");
+        self.read_state()
+    }
+
+    fn read_state(&self) -> io::Result<RecoveryState> {
+        let file = match File::open(&self.path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(RecoveryState::default()),
+            Err(e) => return Err(e),
+        };
+        let mut state = RecoveryState::default();
+        for line in BufReader::new(file).lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            // 崩溃时最后一条记录可能只写了一半，此时对应的操作仍按未完成处理
+            match serde_json::from_str(&line) {
+                Ok(entry) => state.apply(entry),
+                Err(_) => break,
+            }
+        }
+        Ok(state)
+    }
+
+    /// 处理所有未完成的操作并清空日志，应在开始新的写入前调用
+    ///
+    /// 临时文件仍在的操作被回滚（删除临时文件）；临时文件已经不在的操作说明重命名已经完成，视为已提交。
+    pub fn recover(&self) -> io::Result<Vec<RecoveryAction>> {
+        let mut file = self.file.lock().expect("recovery log poisoned");
+        let state = self.read_state()?;
+
+        let mut actions = Vec::new();
+        for (file_id, target_path) in state.pending() {
+            let partial = partial_path(target_path);
+            let action = match std::fs::remove_file(&partial) {
+                Ok(()) => RecoveryAction::RolledBack {
+                    file_id: file_id.to_string(),
+                    target_path: target_path.to_path_buf(),
+                },
+                Err(e) if e.kind() == io::ErrorKind::NotFound => RecoveryAction::Completed {
+                    file_id: file_id.to_string(),
+                    target_path: target_path.to_path_buf(),
+                },
+                Err(e) => return Err(e),
+            };
+            actions.push(action);
+        }
+
+        // 所有操作都已处理，之后的记录从空日志开始
+        *file = None;
+        match std::fs::remove_file(&self.path) {
+            Ok(()) => {}
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+        }
+        Ok(actions)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 按写入方的流程写入目标文件，`crash` 为真时在提交前停止
+    fn write_file(log: &RecoveryLog, file_id: &str, target: &Path, content: &[u8], crash: bool) {
+        log.begin(file_id, target).unwrap();
+        let partial = partial_path(target);
+        std::fs::write(&partial, content).unwrap();
+        if crash {
+            return;
+        }
+        std::fs::rename(&partial, target).unwrap();
+        log.commit(file_id).unwrap();
+    }
+
+    #[test]
+    fn committed_and_aborted_entries_are_not_pending() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = RecoveryLog::new(dir.path().join("recovery.log"));
+        assert!(log.state().unwrap().is_clean());
+
+        write_file(&log, "a", &dir.path().join("a.txt"), b"a", false);
+        log.begin("b", &dir.path().join("b.txt")).unwrap();
+        log.abort("b").unwrap();
+        assert!(log.state().unwrap().is_clean());
+
+        log.begin("c", &dir.path().join("c.txt")).unwrap();
+        let state = log.state().unwrap();
+        let pending: Vec<_> = state.pending().collect();
+        assert_eq!(pending, vec![("c", dir.path().join("c.txt").as_path())]);
+    }
+
+    #[test]
+    fn crash_between_begin_and_commit_is_rolled_back() {
+        let dir = tempfile::tempdir().unwrap();
+        let log_path = dir.path().join("recovery.log");
+        let target = dir.path().join("file.txt");
+        std::fs::write(&target, b"old content").unwrap();
+
+        {
+            let log = RecoveryLog::new(&log_path);
+            write_file(&log, "file", &target, b"new con", true);
+        }
+
+        // 重新启动后恢复
+        let log = RecoveryLog::new(&log_path);
+        let actions = log.recover().unwrap();
+        assert_eq!(
+            actions,
+            vec![RecoveryAction::RolledBack {
+                file_id: "file".to_string(),
+                target_path: target.clone(),
+            }]
+        );
+        assert!(!partial_path(&target).exists());
+        assert_eq!(std::fs::read(&target).unwrap(), b"old content");
+        assert!(log.state().unwrap().is_clean());
+        assert!(log.recover().unwrap().is_empty());
+
+        // 恢复后可以继续写入
+        write_file(&log, "file", &target, b"new content", false);
+        assert_eq!(std::fs::read(&target).unwrap(), b"new content");
+        assert!(log.state().unwrap().is_clean());
+    }
+
+    #[test]
+    fn crash_after_rename_is_completed() {
+        let dir = tempfile::tempdir().unwrap();
+        let log_path = dir.path().join("recovery.log");
+        let target = dir.path().join("file.txt");
+
+        let log = RecoveryLog::new(&log_path);
+        log.begin("file", &target).unwrap();
+        std::fs::write(partial_path(&target), b"new content").unwrap();
+        std::fs::rename(partial_path(&target), &target).unwrap();
+        drop(log);
+
+        let log = RecoveryLog::new(&log_path);
+        let actions = log.recover().unwrap();
+        assert_eq!(
+            actions,
+            vec![RecoveryAction::Completed {
+                file_id: "file".to_string(),
+                target_path: target.clone(),
+            }]
+        );
+        assert_eq!(std::fs::read(&target).unwrap(), b"new content");
+    }
+
+    #[test]
+    fn torn_last_entry_is_ignored() {
+        let dir = tempfile::tempdir().unwrap();
+        let log_path = dir.path().join("recovery.log");
+        let target = dir.path().join("file.txt");
+
+        let log = RecoveryLog::new(&log_path);
+        log.begin("file", &target).unwrap();
+        std::fs::write(partial_path(&target), b"partial").unwrap();
+        // 模拟写提交记录时崩溃
+        let mut file = OpenOptions::new().append(true).open(&log_path).unwrap();
+        file.write_all(br#"{"op":"commit","fil"#).unwrap();
+        drop(file);
+
+        let state = log.state().unwrap();
+        assert_eq!(state.pending().count(), 1);
+        let actions = log.recover().unwrap();
+        assert!(matches!(actions[..], [RecoveryAction::RolledBack { .. }]));
+        assert!(!partial_path(&target).exists());
+    }
+}
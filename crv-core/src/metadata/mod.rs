@@ -1,3 +1,7 @@
+use crate::path::basic::DepotPath;
+use crate::path::normalize::path_config;
+use crate::repository::{Compression, RepositoryError};
+use bincode::{Decode, Encode};
 use serde::{Deserialize, Serialize};
 
 /// `users` 集合
@@ -13,6 +17,23 @@ pub struct UserDoc {
 pub struct BranchMetadata {
     /// 分支描述
     pub description: String,
+    /// 分支所有者的用户名，为空表示任何登录用户都可以基于该分支创建新分支
+    #[serde(default)]
+    pub owners: Vec<String>,
+    /// 提交描述需要匹配的正则，设置后覆盖 hive 配置中的 `changelist_description_regex`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description_regex: Option<String>,
+}
+
+impl BranchMetadata {
+    /// 有所有者的分支只有所有者或管理员才能写入或基于它创建新分支
+    pub fn has_owners(&self) -> bool {
+        !self.owners.is_empty()
+    }
+
+    pub fn is_owned_by(&self, username: &str) -> bool {
+        self.owners.iter().any(|owner| owner == username)
+    }
 }
 
 /// `branches` 集合
@@ -28,10 +49,31 @@ pub struct BranchDoc {
     pub created_by: String,
     /// 当前 HEAD 指向的 changelist
     pub head_changelist_id: i64,
+    /// 不接受直接提交，只能通过 Merge 合入变更；
+    /// 与 [`BranchMetadata::has_owners`] 限制写入者的所有者检查相互独立
+    #[serde(default)]
+    pub is_protected: bool,
     /// 附加元信息
     pub metadata: BranchMetadata,
 }
 
+/// `snapshots` 集合：将分支固定在某个 changelist 上的命名快照，创建后不可修改
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SnapshotDoc {
+    pub id: i64,
+    /// 快照名称，全局唯一
+    pub name: String,
+    pub branch_id: String,
+    /// 快照固定的 changelist
+    pub changelist_id: i64,
+    /// 创建人用户名
+    pub created_by: String,
+    /// 创建时间（Linux 时间戳，毫秒）
+    pub created_at: i64,
+    pub description: String,
+}
+
 /// `changelists` 集合中 `metadata` 字段
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ChangelistMetadata {
@@ -88,6 +130,17 @@ pub struct FileMetadata {
     pub first_introduced_by: String,
 }
 
+/// 由 depot 路径计算 `files` 集合中的文件 ID。
+///
+/// 路径先按全局的 [`PathConfig`](crate::path::normalize::PathConfig) 规范化，
+/// 因此大小写不敏感时仅大小写不同的路径会得到同一个 ID。
+pub fn derive_file_id_from_path(path: &DepotPath) -> String {
+    let normalized = path.normalize(path_config().case_sensitive);
+    blake3::hash(normalized.to_custom_string().as_bytes())
+        .to_hex()
+        .to_string()
+}
+
 /// `files` 集合
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -105,18 +158,71 @@ pub struct FileDoc {
     pub metadata: FileMetadata,
 }
 
+/// file revision 内容的压缩方式，同时也是 edge 下载该 revision 时使用的传输编码
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, Encode, Decode)]
+#[serde(rename_all = "lowercase")]
+pub enum CompressionType {
+    /// 不压缩，旧版本的 revision 都是这种方式
+    #[default]
+    None,
+    Zstd,
+}
+
+impl CompressionType {
+    /// 在 gRPC 消息中使用的名称
+    pub fn as_str(self) -> &'static str {
+        match self {
+            CompressionType::None => "none",
+            CompressionType::Zstd => "zstd",
+        }
+    }
+
+    /// 解析 gRPC 消息中的名称，空字符串视为 `none`
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "" | "none" => Some(CompressionType::None),
+            "zstd" => Some(CompressionType::Zstd),
+            _ => None,
+        }
+    }
+
+    pub fn compress(self, data: &[u8]) -> Result<Vec<u8>, RepositoryError> {
+        Ok(Compression::from(self).encode(data)?.payload.into_owned())
+    }
+
+    pub fn decompress(self, data: &[u8]) -> Result<Vec<u8>, RepositoryError> {
+        Compression::from(self).decode(data)
+    }
+}
+
+impl From<CompressionType> for Compression {
+    fn from(value: CompressionType) -> Self {
+        match value {
+            CompressionType::None => Compression::None,
+            CompressionType::Zstd => Compression::Zstd,
+        }
+    }
+}
+
 /// `fileRevision` 集合中 `metadata` 字段
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct FileRevisionMetadata {
     /// 文件权限，例如 `"755"`
     pub file_mode: String,
-    /// 内容哈希，用于去重/校验
+    /// 内容哈希，用于去重/校验：按顺序串联所有 chunk 后整个文件内容的 Blake3 哈希，
+    /// 而不是第一个 chunk 的哈希
     pub hash: String,
     /// 是否为二进制文件
     pub is_binary: bool,
     /// 语言，例如 `"cpp"`
     pub language: String,
+    /// 资源流水线标签，例如 `"approved"`、`"needs-review"`、`"deprecated"`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// 内容的压缩方式，没有记录的旧 revision 视为不压缩
+    #[serde(default)]
+    pub compression_type: CompressionType,
 }
 
 /// `fileRevision` 集合
@@ -143,4 +249,83 @@ pub struct FileRevisionDoc {
     pub metadata: FileRevisionMetadata,
 }
 
-// 工作区管理不是通用逻辑，现已从 core 中移除，请 Edge 在自己的逻辑中定义， 后续 Hive 中会定义用于交换 checkout 和 lock 信息的 gRPC 接口
\ No newline at end of file
+// 工作区管理不是通用逻辑，现已从 core 中移除，请 Edge 在自己的逻辑中定义， 后续 Hive 中会定义用于交换 checkout 和 lock 信息的 gRPC 接口
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::path::normalize::{PathConfig, set_path_config};
+
+    #[test]
+    fn file_id_ignores_case_only_when_configured() {
+        let upper = DepotPath::parse("//Src/Module/A.cpp").unwrap();
+        let lower = DepotPath::parse("//src/module/a.cpp").unwrap();
+        let nfd = DepotPath::parse("//src/module/cafe\u{301}.cpp").unwrap();
+        let nfc = DepotPath::parse("//src/module/caf\u{e9}.cpp").unwrap();
+
+        // 全局配置被多个断言共享，放在同一个测试中依次修改，避免与其他测试相互干扰
+        assert_ne!(
+            derive_file_id_from_path(&upper),
+            derive_file_id_from_path(&lower)
+        );
+        assert_eq!(
+            derive_file_id_from_path(&nfd),
+            derive_file_id_from_path(&nfc)
+        );
+
+        set_path_config(PathConfig {
+            case_sensitive: false,
+        });
+        assert_eq!(
+            derive_file_id_from_path(&upper),
+            derive_file_id_from_path(&lower)
+        );
+        assert_eq!(
+            derive_file_id_from_path(&nfd),
+            derive_file_id_from_path(&nfc)
+        );
+        set_path_config(PathConfig::default());
+    }
+
+    fn revision_metadata(compression_type: CompressionType) -> FileRevisionMetadata {
+        FileRevisionMetadata {
+            file_mode: "644".to_string(),
+            hash: "hash".to_string(),
+            is_binary: true,
+            language: String::new(),
+            tags: vec![],
+            compression_type,
+        }
+    }
+
+    #[test]
+    fn compression_type_roundtrips_through_metadata_and_content() {
+        let content = b"chronoverse ".repeat(1024);
+        for compression_type in [CompressionType::None, CompressionType::Zstd] {
+            let json = serde_json::to_value(revision_metadata(compression_type)).unwrap();
+            assert_eq!(json["compressionType"], compression_type.as_str());
+            let parsed: FileRevisionMetadata = serde_json::from_value(json).unwrap();
+            assert_eq!(parsed.compression_type, compression_type);
+            assert_eq!(
+                CompressionType::parse(compression_type.as_str()),
+                Some(compression_type)
+            );
+
+            let stored = compression_type.compress(&content).unwrap();
+            assert_eq!(compression_type.decompress(&stored).unwrap(), content);
+            if compression_type == CompressionType::Zstd {
+                assert!(stored.len() < content.len());
+            }
+        }
+    }
+
+    #[test]
+    fn metadata_without_compression_type_is_uncompressed() {
+        let mut json = serde_json::to_value(revision_metadata(CompressionType::Zstd)).unwrap();
+        json.as_object_mut().unwrap().remove("compressionType");
+        let parsed: FileRevisionMetadata = serde_json::from_value(json).unwrap();
+        assert_eq!(parsed.compression_type, CompressionType::None);
+        assert_eq!(CompressionType::parse(""), Some(CompressionType::None));
+        assert_eq!(CompressionType::parse("lz4"), None);
+    }
+}
@@ -101,7 +101,7 @@ pub fn depot_path_wildcard_parser<'src>()
     let regex_depot_wildcard = just("r://")
         .labelled("regex depot wildcard prefix")
         .then(none_of("\n\r").repeated().collect())
-        .map(|(_, pattern)| DepotPathWildcard::Regex(RegexDepotWildcard { pattern }));
+        .map(|(_, pattern)| DepotPathWildcard::Regex(RegexDepotWildcard::new(pattern)));
 
     choice((range_depot_wildcard, regex_depot_wildcard))
 }
@@ -1,6 +1,11 @@
 use crate::parsers;
+use bincode::de::Decoder;
+use bincode::enc::Encoder;
+use bincode::error::{DecodeError, EncodeError};
 use bincode::{Decode, Encode};
+use regex::Regex;
 use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
 use thiserror::Error;
 
 /// 求两个切片的公共前缀的结束索引，如果返回 0 则代表没有公共前缀
@@ -151,6 +156,25 @@ impl DepotPath {
     pub fn parse(path: &str) -> PathResult<Self> {
         parsers::path::depot_path(path)
     }
+
+    /// 求该路径相对于 `base` 所在目录的各级路径组成（子目录 + 文件名），不在 `base` 目录下时返回 None。
+    ///
+    /// 只使用 `base` 的目录部分，`base.file` 会被忽略。
+    pub fn relative_to(&self, base: &DepotPath) -> Option<Vec<String>> {
+        let suffix = self.dirs.strip_prefix(base.dirs.as_slice())?;
+        let mut components = suffix.to_vec();
+        components.push(self.file.clone());
+        Some(components)
+    }
+
+    /// 求两个路径最深的公共目录，返回的路径只有目录部分，文件名为空
+    pub fn common_ancestor(&self, other: &DepotPath) -> DepotPath {
+        let common_prefix_end = common_prefix_end_index(&self.dirs, &other.dirs);
+        DepotPath {
+            dirs: self.dirs[..common_prefix_end].to_vec(),
+            file: String::new(),
+        }
+    }
 }
 
 /// 通配 Depot Path
@@ -192,6 +216,37 @@ impl DepotPathWildcard {
     pub fn parse(wildcard: &str) -> PathResult<Self> {
         parsers::path::depot_path_wildcard(wildcard)
     }
+
+    /// 判断一个 depot path 是否被该 wildcard 匹配，如果匹配则返回 depot path 相对于 wildcard 的部分
+    pub fn match_and_get_diff(&self, depot_path: &DepotPath) -> Option<DepotPathDiff> {
+        match self {
+            DepotPathWildcard::Range(range_depot_wildcard) => {
+                range_depot_wildcard.match_and_get_diff(depot_path)?;
+                let base = DepotPath {
+                    dirs: range_depot_wildcard.dirs.clone(),
+                    file: String::new(),
+                };
+                let mut components = depot_path.relative_to(&base)?;
+                let file = components.pop()?;
+                Some(DepotPathDiff {
+                    dirs: components,
+                    file,
+                })
+            }
+            DepotPathWildcard::Regex(regex_depot_wildcard) => {
+                regex_depot_wildcard.match_and_get_diff(depot_path)
+            }
+        }
+    }
+}
+
+/// depot path 被 wildcard 匹配后，相对于 wildcard 的部分
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DepotPathDiff {
+    /// 相对于 wildcard 的子目录
+    pub dirs: Vec<String>,
+    /// 文件名
+    pub file: String,
 }
 
 /// 范围索引 Depot Path
@@ -223,12 +278,67 @@ impl RangeDepotWildcard {
 }
 
 /// 正则 Depot Path
-#[derive(Debug, Clone, Serialize, Deserialize, Encode, Decode)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RegexDepotWildcard {
     /// 原始正则表达式字符串
     pub pattern: String,
+    /// 编译后的正则表达式，第一次匹配时编译；编译失败时为 None，不匹配任何路径
+    #[serde(skip)]
+    regex: OnceLock<Option<Regex>>,
 }
 
+impl RegexDepotWildcard {
+    pub fn new(pattern: String) -> Self {
+        Self {
+            pattern,
+            regex: OnceLock::new(),
+        }
+    }
+
+    /// 用正则表达式匹配 depot path 去掉开头 `//` 后的完整字符串。
+    ///
+    /// 匹配成功时，命名分组 `dirs` 与 `file` 分别作为差分的子目录与文件名；
+    /// 没有对应的分组时，子目录为 depot path 的所有目录，文件名为 depot path 的文件名。
+    pub fn match_and_get_diff(&self, depot_path: &DepotPath) -> Option<DepotPathDiff> {
+        let regex = self
+            .regex
+            .get_or_init(|| Regex::new(&format!("^(?:{})$", self.pattern)).ok())
+            .as_ref()?;
+        let path_string = depot_path.to_custom_string();
+        let captures = regex.captures(path_string.trim_start_matches('/'))?;
+
+        let dirs = match captures.name("dirs") {
+            Some(dirs) => dirs
+                .as_str()
+                .split('/')
+                .filter(|dir| !dir.is_empty())
+                .map(|dir| dir.to_string())
+                .collect(),
+            None => depot_path.dirs.clone(),
+        };
+        let file = match captures.name("file") {
+            Some(file) => file.as_str().to_string(),
+            None => depot_path.file.clone(),
+        };
+        Some(DepotPathDiff { dirs, file })
+    }
+}
+
+// 只编码原始正则表达式，与之前派生实现的编码结果保持一致
+impl Encode for RegexDepotWildcard {
+    fn encode<E: Encoder>(&self, encoder: &mut E) -> Result<(), EncodeError> {
+        self.pattern.encode(encoder)
+    }
+}
+
+impl<Context> Decode<Context> for RegexDepotWildcard {
+    fn decode<D: Decoder<Context = Context>>(decoder: &mut D) -> Result<Self, DecodeError> {
+        Ok(Self::new(String::decode(decoder)?))
+    }
+}
+
+bincode::impl_borrow_decode!(RegexDepotWildcard);
+
 /// 本地目录路径（规范化后的绝对路径，精确到目录）
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Encode, Decode)]
 pub struct LocalDir(pub Vec<String>);
@@ -405,6 +515,17 @@ impl LocalPathWildcard {
         }
         Some(local_dir_diff)
     }
+
+    /// 判断一个 local path 是否被该 wildcard 匹配：目录需要在 wildcard 的目录下
+    /// （非递归时只能是该目录本身），文件名需要匹配文件通配符
+    pub fn match_path(&self, local_path: &LocalPath) -> bool {
+        self.match_and_get_diff(local_path).is_some()
+    }
+
+    /// 保留被该 wildcard 匹配的路径，顺序不变
+    pub fn filter_paths<'a>(&self, paths: &'a [LocalPath]) -> Vec<&'a LocalPath> {
+        paths.iter().filter(|path| self.match_path(path)).collect()
+    }
 }
 
 #[cfg(test)]
@@ -643,6 +764,131 @@ mod test_depot_path {
         assert!(matches!(depot_path_err, PathError::RegexError(_)));
         println!("{}:{}", path, depot_path_err);
     }
+
+    #[test]
+    fn test_regex_depot_wildcard_match_and_get_diff() {
+        let depot_path = DepotPath::parse("//project/src/a/b/main.rs").unwrap();
+
+        // 没有命名分组时使用完整路径
+        let wildcard = DepotPathWildcard::parse(r"r://project/.*\.rs").unwrap();
+        let diff = wildcard.match_and_get_diff(&depot_path).unwrap();
+        assert_eq!(diff.dirs, vec!["project", "src", "a", "b"]);
+        assert_eq!(diff.file, "main.rs");
+
+        // 命名分组 dirs 与 file
+        let wildcard =
+            DepotPathWildcard::parse(r"r://project/src/(?P<dirs>.*)/(?P<file>[^/]+)\.rs").unwrap();
+        let diff = wildcard.match_and_get_diff(&depot_path).unwrap();
+        assert_eq!(diff.dirs, vec!["a", "b"]);
+        assert_eq!(diff.file, "main");
+
+        // 正则需要匹配完整路径
+        let wildcard = DepotPathWildcard::parse(r"r://project/src").unwrap();
+        assert!(wildcard.match_and_get_diff(&depot_path).is_none());
+
+        // 非法正则解析失败，直接构造时不匹配任何路径
+        assert!(DepotPathWildcard::parse(r"r://project/(.*").is_err());
+        let wildcard = RegexDepotWildcard::new(r"project/(.*".to_string());
+        assert!(wildcard.match_and_get_diff(&depot_path).is_none());
+
+        // 范围通配
+        let wildcard = DepotPathWildcard::parse("//project/src/...").unwrap();
+        let diff = wildcard.match_and_get_diff(&depot_path).unwrap();
+        assert_eq!(diff.dirs, vec!["a", "b"]);
+        assert_eq!(diff.file, "main.rs");
+    }
+
+    #[test]
+    fn test_regex_depot_wildcard_encode_only_pattern() {
+        let config = bincode::config::standard();
+        let wildcard = RegexDepotWildcard::new(r"project/.*\.rs".to_string());
+        let bytes = bincode::encode_to_vec(&wildcard, config).unwrap();
+        assert_eq!(
+            bytes,
+            bincode::encode_to_vec(&wildcard.pattern, config).unwrap()
+        );
+
+        let (decoded, _): (RegexDepotWildcard, usize) =
+            bincode::decode_from_slice(&bytes, config).unwrap();
+        assert_eq!(decoded.pattern, wildcard.pattern);
+    }
+
+    #[test]
+    fn test_depot_path_relative_to() {
+        let path = DepotPath::parse("//project/src/path/basic.rs").unwrap();
+        let base = DepotPath::parse("//project/src/main.rs").unwrap();
+        assert_eq!(path.relative_to(&base).unwrap(), vec!["path", "basic.rs"]);
+        assert_eq!(path.relative_to(&path).unwrap(), vec!["basic.rs"]);
+
+        let outside = DepotPath::parse("//project/doc/readme.md").unwrap();
+        assert!(outside.relative_to(&base).is_none());
+        // 只有目录名的前缀相同时不算在 base 下
+        let sibling = DepotPath::parse("//project/src2/main.rs").unwrap();
+        assert!(sibling.relative_to(&base).is_none());
+    }
+
+    #[test]
+    fn test_depot_path_common_ancestor() {
+        let a = DepotPath::parse("//project/src/path/basic.rs").unwrap();
+        let b = DepotPath::parse("//project/src/tree/mod.rs").unwrap();
+        let ancestor = a.common_ancestor(&b);
+        assert_eq!(ancestor.dirs, vec!["project", "src"]);
+        assert!(ancestor.file.is_empty());
+
+        let c = DepotPath::parse("//other/lib.rs").unwrap();
+        assert!(a.common_ancestor(&c).dirs.is_empty());
+        assert!(a.relative_to(&a.common_ancestor(&b)).is_some());
+        assert!(b.relative_to(&a.common_ancestor(&b)).is_some());
+    }
+}
+
+#[cfg(test)]
+mod test_depot_path_relative {
+    use super::*;
+    use proptest::prelude::*;
+
+    proptest! {
+        #[test]
+        fn relative_to_inverts_prepending_base(
+            base_dirs in proptest::collection::vec("[a-z0-9]{1,6}", 0..4),
+            sub_dirs in proptest::collection::vec("[a-z0-9]{1,6}", 0..4),
+            base_file in "[a-z]{1,8}\\.[a-z]{1,3}",
+            file in "[a-z]{1,8}\\.[a-z]{1,3}",
+        ) {
+            let base = DepotPath { dirs: base_dirs.clone(), file: base_file };
+            let mut dirs = base_dirs;
+            dirs.extend(sub_dirs.iter().cloned());
+            let path = DepotPath { dirs, file: file.clone() };
+
+            let mut expected = sub_dirs;
+            expected.push(file);
+            prop_assert_eq!(path.relative_to(&base), Some(expected.clone()));
+
+            // 把相对部分重新拼到 base 目录后，应得到原路径
+            let file = expected.pop().unwrap();
+            let mut rebuilt_dirs = base.dirs.clone();
+            rebuilt_dirs.extend(expected);
+            prop_assert_eq!(DepotPath { dirs: rebuilt_dirs, file }, path);
+        }
+
+        #[test]
+        fn path_is_relative_to_common_ancestor(
+            a_dirs in proptest::collection::vec("[a-c]{1,2}", 0..4),
+            b_dirs in proptest::collection::vec("[a-c]{1,2}", 0..4),
+        ) {
+            let a = DepotPath { dirs: a_dirs, file: "a.txt".to_string() };
+            let b = DepotPath { dirs: b_dirs, file: "b.txt".to_string() };
+            let ancestor = a.common_ancestor(&b);
+
+            prop_assert!(a.relative_to(&ancestor).is_some());
+            prop_assert!(b.relative_to(&ancestor).is_some());
+            prop_assert_eq!(&ancestor, &b.common_ancestor(&a));
+            // 公共目录是最深的：再往下一级就不能同时包含两个路径
+            if ancestor.dirs.len() < a.dirs.len() && ancestor.dirs.len() < b.dirs.len() {
+                prop_assert_ne!(&a.dirs[ancestor.dirs.len()], &b.dirs[ancestor.dirs.len()]);
+            }
+        }
+    }
 }
 
 #[cfg(test)]
@@ -1043,3 +1289,113 @@ mod test_local_path {
         println!("{}:{}", path, depot_path_err);
     }
 }
+
+#[cfg(test)]
+mod test_local_path_wildcard {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn local_path(path: &str) -> LocalPath {
+        LocalPath::parse(path).unwrap()
+    }
+
+    #[test]
+    fn test_match_path() {
+        let recursive = LocalPathWildcard::parse("/home/crv/src/...~rs").unwrap();
+        assert!(recursive.match_path(&local_path("/home/crv/src/main.rs")));
+        assert!(recursive.match_path(&local_path("/home/crv/src/path/basic.rs")));
+        assert!(!recursive.match_path(&local_path("/home/crv/src/Cargo.toml")));
+        assert!(!recursive.match_path(&local_path("/home/crv/main.rs")));
+        assert!(!recursive.match_path(&local_path("/home/crv/src2/main.rs")));
+
+        let flat = LocalPathWildcard::parse("/home/crv/src/").unwrap();
+        assert!(flat.match_path(&local_path("/home/crv/src/Cargo.toml")));
+        assert!(!flat.match_path(&local_path("/home/crv/src/path/basic.rs")));
+
+        let paths = [
+            local_path("/home/crv/src/main.rs"),
+            local_path("/home/crv/README.md"),
+            local_path("/home/crv/src/path/basic.rs"),
+        ];
+        assert_eq!(recursive.filter_paths(&paths), vec![&paths[0], &paths[2]]);
+        assert_eq!(flat.filter_paths(&paths), vec![&paths[0]]);
+    }
+
+    fn arb_filename_wildcard() -> impl Strategy<Value = FilenameWildcard> {
+        prop_oneof![
+            "[a-z]{1,8}\\.[a-z]{1,3}".prop_map(FilenameWildcard::Exact),
+            "[a-z]{1,3}".prop_map(|ext| FilenameWildcard::Extension(format!(".{ext}"))),
+            Just(FilenameWildcard::All),
+        ]
+    }
+
+    /// 生成一个被 wildcard 匹配的文件名
+    fn matching_file(wildcard: &FilenameWildcard, stem: &str) -> String {
+        match wildcard {
+            FilenameWildcard::Exact(name) => name.clone(),
+            FilenameWildcard::Extension(ext) => format!("{stem}{ext}"),
+            FilenameWildcard::All => stem.to_string(),
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn matching_path_is_never_filtered(
+            dirs in proptest::collection::vec("[a-z0-9]{1,6}", 0..4),
+            sub_dirs in proptest::collection::vec("[a-z0-9]{1,6}", 0..3),
+            recursive in any::<bool>(),
+            wildcard in arb_filename_wildcard(),
+            stem in "[a-z]{1,8}",
+        ) {
+            let mut path_dirs = dirs.clone();
+            if recursive {
+                path_dirs.extend(sub_dirs);
+            }
+            let path = LocalPath {
+                dirs: LocalDir(path_dirs),
+                file: matching_file(&wildcard, &stem),
+            };
+            let wildcard = LocalPathWildcard { dirs: LocalDir(dirs), recursive, wildcard };
+
+            prop_assert!(wildcard.match_path(&path));
+            let paths = [path];
+            prop_assert_eq!(wildcard.filter_paths(&paths).len(), 1);
+        }
+
+        #[test]
+        fn path_outside_wildcard_is_always_filtered(
+            dirs in proptest::collection::vec("[a-z0-9]{1,6}", 1..4),
+            changed in any::<prop::sample::Index>(),
+            sub_dirs in proptest::collection::vec("[a-z0-9]{1,6}", 0..3),
+            recursive in any::<bool>(),
+            wildcard in arb_filename_wildcard(),
+            stem in "[a-z]{1,8}",
+        ) {
+            // 修改 wildcard 目录中的一级，使路径不在其目录下
+            let mut path_dirs = dirs.clone();
+            let index = changed.index(path_dirs.len());
+            path_dirs[index].push('_');
+            path_dirs.extend(sub_dirs.iter().cloned());
+            let outside = LocalPath {
+                dirs: LocalDir(path_dirs),
+                file: matching_file(&wildcard, &stem),
+            };
+
+            // 非递归时子目录中的文件也不匹配
+            let mut nested_dirs = dirs.clone();
+            nested_dirs.extend(sub_dirs.iter().cloned());
+            let nested = LocalPath {
+                dirs: LocalDir(nested_dirs),
+                file: matching_file(&wildcard, &stem),
+            };
+
+            let wildcard = LocalPathWildcard { dirs: LocalDir(dirs), recursive, wildcard };
+            prop_assert!(!wildcard.match_path(&outside));
+            if !recursive && !sub_dirs.is_empty() {
+                prop_assert!(!wildcard.match_path(&nested));
+            }
+            let paths = [outside];
+            prop_assert!(wildcard.filter_paths(&paths).is_empty());
+        }
+    }
+}
@@ -0,0 +1,264 @@
+//! `.crvignore` 文件的解析与匹配，语法与 `.gitignore` 相同：
+//!
+//! - 空行与 `#` 开头的行会被忽略，`\#`、`\!` 用于转义；
+//! - `!` 开头的规则重新包含之前被排除的路径；
+//! - `/` 结尾的规则只匹配目录；
+//! - 包含 `/`（结尾的除外）的规则相对于 `.crvignore` 所在的目录匹配，否则匹配任意层级的文件名；
+//! - 支持 `*`、`?`、`[...]` 与 `**` 通配符。
+//!
+//! 一个文件适用其所有上级目录中的 `.crvignore`，离文件越近的规则优先级越高；
+//! 目录被排除时，目录下的所有文件都会被排除。
+use crate::path::basic::{LocalDir, LocalPath};
+use std::collections::HashMap;
+use std::path::Path;
+
+pub const IGNORE_FILE_NAME: &str = ".crvignore";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct IgnoreRule {
+    /// 按 `/` 切分后的模式，非锚定的规则以 `**` 开头
+    segments: Vec<String>,
+    negated: bool,
+    dir_only: bool,
+}
+
+impl IgnoreRule {
+    fn parse(line: &str) -> Option<Self> {
+        let line = line.trim_end();
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+
+        let (negated, line) = match line.strip_prefix('!') {
+            Some(rest) => (true, rest),
+            None => (false, line.strip_prefix('\\').unwrap_or(line)),
+        };
+        let (dir_only, line) = match line.strip_suffix('/') {
+            Some(rest) => (true, rest),
+            None => (false, line),
+        };
+        let anchored = line.contains('/');
+        let line = line.strip_prefix('/').unwrap_or(line);
+        if line.is_empty() {
+            return None;
+        }
+
+        let mut segments = vec![];
+        if !anchored {
+            segments.push("**".to_string());
+        }
+        segments.extend(line.split('/').map(|s| s.to_string()));
+        Some(Self {
+            segments,
+            negated,
+            dir_only,
+        })
+    }
+
+    /// `path` 为相对于 `.crvignore` 所在目录的路径
+    fn matches(&self, path: &[String], is_dir: bool) -> bool {
+        if self.dir_only && !is_dir {
+            return false;
+        }
+        match_segments(&self.segments, path)
+    }
+}
+
+fn match_segments(pattern: &[String], path: &[String]) -> bool {
+    match pattern.split_first() {
+        None => path.is_empty(),
+        Some((segment, rest)) if segment == "**" => {
+            (0..=path.len()).any(|skip| match_segments(rest, &path[skip..]))
+        }
+        Some((segment, rest)) => match path.split_first() {
+            Some((name, path_rest)) => {
+                glob_match(
+                    &segment.chars().collect::<Vec<_>>(),
+                    &name.chars().collect::<Vec<_>>(),
+                ) && match_segments(rest, path_rest)
+            }
+            None => false,
+        },
+    }
+}
+
+/// 匹配单个路径段，支持 `*`、`?`、`[...]` 与 `\` 转义
+fn glob_match(pattern: &[char], name: &[char]) -> bool {
+    match pattern.first() {
+        None => name.is_empty(),
+        Some('*') => (0..=name.len()).any(|skip| glob_match(&pattern[1..], &name[skip..])),
+        Some('?') => !name.is_empty() && glob_match(&pattern[1..], &name[1..]),
+        Some('[') => {
+            let Some(c) = name.first() else {
+                return false;
+            };
+            match match_class(&pattern[1..], *c) {
+                Some((matched, consumed)) => {
+                    matched && glob_match(&pattern[1 + consumed..], &name[1..])
+                }
+                // 没有闭合的 `[` 按普通字符处理
+                None => *c == '[' && glob_match(&pattern[1..], &name[1..]),
+            }
+        }
+        Some('\\') if pattern.len() > 1 => {
+            name.first() == Some(&pattern[1]) && glob_match(&pattern[2..], &name[1..])
+        }
+        Some(p) => name.first() == Some(p) && glob_match(&pattern[1..], &name[1..]),
+    }
+}
+
+/// 匹配字符类（`[` 之后的部分），返回是否匹配以及字符类占用的字符数（含 `]`）
+fn match_class(pattern: &[char], c: char) -> Option<(bool, usize)> {
+    let (negated, start) = match pattern.first() {
+        Some('!') | Some('^') => (true, 1),
+        _ => (false, 0),
+    };
+    let mut matched = false;
+    let mut i = start;
+    while i < pattern.len() {
+        if pattern[i] == ']' && i > start {
+            return Some((matched != negated, i + 1));
+        }
+        if i + 2 < pattern.len() && pattern[i + 1] == '-' && pattern[i + 2] != ']' {
+            matched |= pattern[i] <= c && c <= pattern[i + 2];
+            i += 3;
+        } else {
+            matched |= pattern[i] == c;
+            i += 1;
+        }
+    }
+    None
+}
+
+/// 一个 `.crvignore` 文件中的所有规则
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IgnoreFile {
+    rules: Vec<IgnoreRule>,
+}
+
+impl IgnoreFile {
+    pub fn parse(content: &str) -> Self {
+        Self {
+            rules: content.lines().filter_map(IgnoreRule::parse).collect(),
+        }
+    }
+
+    /// 按规则判断路径是否被排除，后面的规则覆盖前面的；没有规则匹配时返回 None
+    pub fn check(&self, path: &[String], is_dir: bool) -> Option<bool> {
+        self.rules
+            .iter()
+            .rev()
+            .find(|rule| rule.matches(path, is_dir))
+            .map(|rule| !rule.negated)
+    }
+}
+
+/// 按目录缓存读取到的 `.crvignore`，判断本地文件是否被排除
+#[derive(Debug, Default)]
+pub struct IgnoreMatcher {
+    files: HashMap<Vec<String>, Option<IgnoreFile>>,
+}
+
+impl IgnoreMatcher {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 从文件所在目录向上逐级查找 `.crvignore`，判断文件是否被排除
+    pub fn is_ignored(&mut self, local_path: &LocalPath) -> bool {
+        let mut components = local_path.dirs.0.clone();
+        components.push(local_path.file.clone());
+
+        // 依次检查每一级目录与文件本身，任意一级被排除则文件被排除
+        for end in 1..=components.len() {
+            let is_dir = end < components.len();
+            let mut ignored = false;
+            // 不读取文件系统根目录下的 .crvignore
+            for base in 1..end {
+                if let Some(result) = self
+                    .load(&components[..base])
+                    .and_then(|file| file.check(&components[base..end], is_dir))
+                {
+                    ignored = result;
+                }
+            }
+            if ignored {
+                return true;
+            }
+        }
+        false
+    }
+
+    fn load(&mut self, dir: &[String]) -> Option<&IgnoreFile> {
+        self.files
+            .entry(dir.to_vec())
+            .or_insert_with(|| {
+                let dir = LocalDir(dir.to_vec()).to_local_path_string();
+                let content = std::fs::read_to_string(Path::new(&dir).join(IGNORE_FILE_NAME));
+                content.ok().map(|content| IgnoreFile::parse(&content))
+            })
+            .as_ref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn path(s: &str) -> Vec<String> {
+        s.split('/').map(|x| x.to_string()).collect()
+    }
+
+    #[test]
+    fn unanchored_patterns_match_at_any_depth() {
+        let file = IgnoreFile::parse("# build outputs\n*.tmp\nbuild/\n");
+        assert_eq!(file.check(&path("a/b/c.tmp"), false), Some(true));
+        assert_eq!(file.check(&path("c.tmp"), false), Some(true));
+        assert_eq!(file.check(&path("a/build"), true), Some(true));
+        assert_eq!(file.check(&path("a/build"), false), None);
+        assert_eq!(file.check(&path("a/c.txt"), false), None);
+    }
+
+    #[test]
+    fn anchored_patterns_match_relative_to_ignore_file() {
+        let file = IgnoreFile::parse("/out\nassets/**/*.psd\n");
+        assert_eq!(file.check(&path("out"), true), Some(true));
+        assert_eq!(file.check(&path("a/out"), true), None);
+        assert_eq!(file.check(&path("assets/x.psd"), false), Some(true));
+        assert_eq!(file.check(&path("assets/a/b/x.psd"), false), Some(true));
+        assert_eq!(file.check(&path("other/assets/x.psd"), false), None);
+    }
+
+    #[test]
+    fn later_negated_rules_reinclude_paths() {
+        let file = IgnoreFile::parse("*.log\n!keep.log\n");
+        assert_eq!(file.check(&path("a.log"), false), Some(true));
+        assert_eq!(file.check(&path("keep.log"), false), Some(false));
+    }
+
+    #[test]
+    fn glob_supports_character_classes() {
+        let file = IgnoreFile::parse("file[0-9].txt\n[!a]?.bin\n");
+        assert_eq!(file.check(&path("file3.txt"), false), Some(true));
+        assert_eq!(file.check(&path("filex.txt"), false), None);
+        assert_eq!(file.check(&path("bc.bin"), false), Some(true));
+        assert_eq!(file.check(&path("ac.bin"), false), None);
+    }
+
+    #[test]
+    fn matcher_collects_ignore_files_from_parent_dirs() {
+        let root = tempfile::tempdir().unwrap();
+        let root_path = root.path().canonicalize().unwrap();
+        std::fs::create_dir_all(root_path.join("a/b")).unwrap();
+        std::fs::write(root_path.join(IGNORE_FILE_NAME), "*.tmp\ntarget/\n").unwrap();
+        std::fs::write(root_path.join("a").join(IGNORE_FILE_NAME), "!keep.tmp\n").unwrap();
+
+        let local_path =
+            |relative: &str| LocalPath::parse(&root_path.join(relative).to_string_lossy()).unwrap();
+        let mut matcher = IgnoreMatcher::new();
+        assert!(matcher.is_ignored(&local_path("a/b/x.tmp")));
+        assert!(!matcher.is_ignored(&local_path("a/b/keep.tmp")));
+        assert!(matcher.is_ignored(&local_path("a/target/x.txt")));
+        assert!(!matcher.is_ignored(&local_path("a/b/x.txt")));
+    }
+}
@@ -1,2 +1,4 @@
 pub mod basic;
 pub mod engine;
+pub mod ignore;
+pub mod normalize;
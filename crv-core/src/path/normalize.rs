@@ -0,0 +1,108 @@
+//! Depot 路径的规范化
+//!
+//! 在 macOS (HFS+/APFS) 与 Windows (NTFS) 上，`//Src/Module/A.cpp` 与 `//src/module/a.cpp`
+//! 指向同一个文件，而同一个文件名也可能以不同的 Unicode 形式（如 NFD）出现。
+//! 比较或哈希 depot 路径之前应先统一为 NFC 形式，并在大小写不敏感时转换为小写。
+use crate::path::basic::DepotPath;
+use std::sync::RwLock;
+use unicode_normalization::UnicodeNormalization;
+
+/// 全局的路径配置
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PathConfig {
+    /// 路径比较是否区分大小写
+    pub case_sensitive: bool,
+}
+
+impl Default for PathConfig {
+    fn default() -> Self {
+        Self {
+            case_sensitive: true,
+        }
+    }
+}
+
+static PATH_CONFIG: RwLock<PathConfig> = RwLock::new(PathConfig {
+    case_sensitive: true,
+});
+
+/// 获取当前的全局路径配置
+pub fn path_config() -> PathConfig {
+    *PATH_CONFIG.read().expect("path config poisoned")
+}
+
+/// 修改全局路径配置，应在启动时、处理任何路径之前调用
+pub fn set_path_config(config: PathConfig) {
+    *PATH_CONFIG.write().expect("path config poisoned") = config;
+}
+
+/// 规范化单个路径段：大小写不敏感时转为小写，然后统一为 NFC 形式
+fn normalize_component(component: &str, case_sensitive: bool) -> String {
+    if case_sensitive {
+        component.nfc().collect()
+    } else {
+        component.to_lowercase().nfc().collect()
+    }
+}
+
+impl DepotPath {
+    /// 返回规范化后的路径，所有路径段统一为 Unicode NFC 形式，`case_sensitive` 为 false 时转为小写
+    pub fn normalize(&self, case_sensitive: bool) -> DepotPath {
+        DepotPath {
+            dirs: self
+                .dirs
+                .iter()
+                .map(|dir| normalize_component(dir, case_sensitive))
+                .collect(),
+            file: normalize_component(&self.file, case_sensitive),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn depot_path(path: &str) -> DepotPath {
+        DepotPath::parse(path).unwrap()
+    }
+
+    #[test]
+    fn case_insensitive_normalization_lowercases_all_components() {
+        let upper = depot_path("//Src/Module/A.cpp").normalize(false);
+        let lower = depot_path("//src/module/a.cpp").normalize(false);
+        assert_eq!(upper, lower);
+        assert_eq!(upper.to_custom_string(), "//src/module/a.cpp");
+    }
+
+    #[test]
+    fn case_sensitive_normalization_keeps_case() {
+        let path = depot_path("//Src/Module/A.cpp");
+        assert_eq!(path.normalize(true), path);
+        assert_ne!(
+            path.normalize(true),
+            depot_path("//src/module/a.cpp").normalize(true)
+        );
+    }
+
+    #[test]
+    fn decomposed_characters_are_composed() {
+        // "é" 的 NFD 形式为 "e" + U+0301
+        let nfd = depot_path("//cafe\u{301}/Re\u{301}sume\u{301}.txt");
+        let nfc = depot_path("//caf\u{e9}/R\u{e9}sum\u{e9}.txt");
+        assert_ne!(nfd, nfc);
+        assert_eq!(nfd.normalize(true), nfc);
+        assert_eq!(
+            nfd.normalize(false).to_custom_string(),
+            "//caf\u{e9}/r\u{e9}sum\u{e9}.txt"
+        );
+    }
+
+    #[test]
+    fn non_ascii_case_folding_round_trips() {
+        let path = depot_path("//Ünïcödé/ÄBC/文件.TXT").normalize(false);
+        assert_eq!(path.to_custom_string(), "//ünïcödé/äbc/文件.txt");
+        assert_eq!(DepotPath::parse(&path.to_custom_string()).unwrap(), path);
+        assert_eq!(path.normalize(false), path);
+    }
+}
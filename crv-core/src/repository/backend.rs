@@ -0,0 +1,151 @@
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+use super::error::{RepositoryError, Result};
+use super::io_utils::ensure_parent_dir;
+
+/// 仓库对象的存储后端，路径统一使用 `/` 分隔的相对路径（如 `shard-0a/pack-000001.dat`）
+pub trait StorageBackend: Send + Sync {
+    /// 写入整个对象，已存在时覆盖
+    fn write(&self, path: &str, data: &[u8]) -> Result<()>;
+    /// 读取整个对象，不存在时返回 `NotFound` 的 I/O 错误
+    fn read(&self, path: &str) -> Result<Vec<u8>>;
+    fn exists(&self, path: &str) -> Result<bool>;
+    /// 删除对象，不存在时视为成功
+    fn delete(&self, path: &str) -> Result<()>;
+    /// 列出以 `prefix` 开头的所有对象路径，按字典序排列
+    fn list(&self, prefix: &str) -> Result<Vec<String>>;
+}
+
+/// 以本地目录为根的存储后端
+pub struct LocalFsBackend {
+    root: PathBuf,
+}
+
+impl LocalFsBackend {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// 将对象路径解析为根目录下的文件路径，拒绝绝对路径与 `..`，避免越出根目录
+    fn resolve(&self, path: &str) -> Result<PathBuf> {
+        let relative = Path::new(path);
+        let valid = !path.is_empty()
+            && relative
+                .components()
+                .all(|c| matches!(c, Component::Normal(_)));
+        if !valid {
+            return Err(RepositoryError::InvalidObjectPath(path.to_string()));
+        }
+        Ok(self.root.join(relative))
+    }
+
+    fn collect(&self, dir: &Path, prefix: &str, out: &mut Vec<String>) -> Result<()> {
+        for entry in fs::read_dir(dir)? {
+            let entry = entry?;
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let path = if prefix.is_empty() {
+                name
+            } else {
+                format!("{prefix}/{name}")
+            };
+            if entry.file_type()?.is_dir() {
+                self.collect(&entry.path(), &path, out)?;
+            } else {
+                out.push(path);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl StorageBackend for LocalFsBackend {
+    fn write(&self, path: &str, data: &[u8]) -> Result<()> {
+        let target = self.resolve(path)?;
+        ensure_parent_dir(&target)?;
+        fs::write(target, data)?;
+        Ok(())
+    }
+
+    fn read(&self, path: &str) -> Result<Vec<u8>> {
+        Ok(fs::read(self.resolve(path)?)?)
+    }
+
+    fn exists(&self, path: &str) -> Result<bool> {
+        Ok(self.resolve(path)?.is_file())
+    }
+
+    fn delete(&self, path: &str) -> Result<()> {
+        match fs::remove_file(self.resolve(path)?) {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+            other => Ok(other?),
+        }
+    }
+
+    fn list(&self, prefix: &str) -> Result<Vec<String>> {
+        let mut paths = Vec::new();
+        if self.root.is_dir() {
+            self.collect(&self.root, "", &mut paths)?;
+        }
+        paths.retain(|p| p.starts_with(prefix));
+        paths.sort();
+        Ok(paths)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn local_backend_round_trip() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let backend = LocalFsBackend::new(temp_dir.path().join("objects"));
+
+        assert!(backend.list("").unwrap().is_empty());
+        backend.write("shard-0a/pack-000001.dat", b"data").unwrap();
+        backend.write("shard-0a/pack-000001.idx", b"index").unwrap();
+        backend.write("shard-ff/pack-000000.dat", b"other").unwrap();
+
+        assert_eq!(backend.read("shard-0a/pack-000001.dat").unwrap(), b"data");
+        assert!(backend.exists("shard-0a/pack-000001.idx").unwrap());
+        assert!(!backend.exists("shard-0a/pack-000002.idx").unwrap());
+        assert_eq!(
+            backend.list("shard-0a/").unwrap(),
+            vec!["shard-0a/pack-000001.dat", "shard-0a/pack-000001.idx"]
+        );
+
+        backend.write("shard-0a/pack-000001.dat", b"new").unwrap();
+        assert_eq!(backend.read("shard-0a/pack-000001.dat").unwrap(), b"new");
+
+        backend.delete("shard-0a/pack-000001.dat").unwrap();
+        backend.delete("shard-0a/pack-000001.dat").unwrap();
+        assert!(!backend.exists("shard-0a/pack-000001.dat").unwrap());
+        assert_eq!(
+            backend.list("").unwrap(),
+            vec!["shard-0a/pack-000001.idx", "shard-ff/pack-000000.dat"]
+        );
+        match backend.read("shard-0a/pack-000001.dat") {
+            Err(RepositoryError::Io(e)) => assert_eq!(e.kind(), io::ErrorKind::NotFound),
+            other => panic!("unexpected result: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn local_backend_rejects_paths_outside_root() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let backend = LocalFsBackend::new(temp_dir.path());
+
+        for path in ["", "../escape", "/etc/passwd", "a/../../b"] {
+            assert!(matches!(
+                backend.write(path, b"x"),
+                Err(RepositoryError::InvalidObjectPath(_))
+            ));
+        }
+    }
+}
@@ -1,15 +1,15 @@
 use std::fs::{File, OpenOptions};
-use std::io::{Read, Seek, SeekFrom, Write};
+use std::io::{BufReader, Read, Seek, SeekFrom, Write};
 use std::path::{Path, PathBuf};
 
 use super::chunk::{ChunkHash, ChunkRecord, Compression, compute_chunk_hash};
-use super::error::{RepositoryError, Result};
-use super::index::{IndexEntry, MutableIndex};
-use super::layout::RepositoryLayout;
 use super::constants::{
     PACK_ENTRY_FIXED_SECTION, PACK_HEADER_SIZE, PACK_MAGIC, PACK_TRAILER_SIZE, PACK_VERSION,
 };
+use super::error::{RepositoryError, Result};
+use super::index::{IndexEntry, MutableIndex};
 use super::io_utils::{compute_crc32, ensure_parent_dir};
+use super::layout::RepositoryLayout;
 
 pub struct PackIdentity {
     pub shard: u8,
@@ -217,10 +217,7 @@ impl PackReader {
         }
         verify_pack_header(&mut file)?;
         let (data_len, _sealed) = detect_data_len(&mut file, total_len)?;
-        Ok(Self {
-            file,
-            data_len,
-        })
+        Ok(Self { file, data_len })
     }
 
     pub fn read_chunk(&mut self, entry: &IndexEntry) -> Result<Vec<u8>> {
@@ -249,6 +246,41 @@ impl PackReader {
     }
 }
 
+/// 顺序扫描 pack 文件中每个条目的头部，返回对应的索引条目以及 pack 是否已封存
+///
+/// 只读取条目头部，payload 直接跳过；条目越过数据区末尾时视为 pack 损坏。
+pub(crate) fn scan_pack_entries(path: impl AsRef<Path>) -> Result<(Vec<IndexEntry>, bool)> {
+    let mut file = OpenOptions::new().read(true).open(path.as_ref())?;
+    let total_len = file.metadata()?.len();
+    if total_len < PACK_HEADER_SIZE {
+        return Err(RepositoryError::Corrupted("pack 文件长度非法"));
+    }
+    verify_pack_header(&mut file)?;
+    let (data_len, sealed) = detect_data_len(&mut file, total_len)?;
+    file.seek(SeekFrom::Start(PACK_HEADER_SIZE))?;
+
+    let mut reader = BufReader::new(file);
+    let mut entries = Vec::new();
+    let mut offset = PACK_HEADER_SIZE;
+    while offset < data_len {
+        if offset + PACK_ENTRY_FIXED_SECTION > data_len {
+            return Err(RepositoryError::Corrupted("pack 条目头部不完整"));
+        }
+        let stored_len = read_u32(&mut reader)?;
+        let flags = read_u16(&mut reader)?;
+        let mut hash = [0u8; super::constants::HASH_SIZE];
+        reader.read_exact(&mut hash)?;
+        let end = offset + PACK_ENTRY_FIXED_SECTION + stored_len as u64;
+        if end > data_len {
+            return Err(RepositoryError::Corrupted("pack 条目长度超出数据区"));
+        }
+        reader.seek_relative(i64::from(stored_len))?;
+        entries.push(IndexEntry::new(hash, offset, stored_len, flags));
+        offset = end;
+    }
+    Ok((entries, sealed))
+}
+
 fn write_pack_header(file: &mut File) -> Result<()> {
     file.write_all(&PACK_MAGIC.to_le_bytes())?;
     file.write_all(&PACK_VERSION.to_le_bytes())?;
@@ -296,13 +328,13 @@ fn detect_data_len(file: &mut File, total_len: u64) -> Result<(u64, bool)> {
     }
 }
 
-fn read_u32(file: &mut File) -> Result<u32> {
+fn read_u32(file: &mut impl Read) -> Result<u32> {
     let mut buf = [0u8; 4];
     file.read_exact(&mut buf)?;
     Ok(u32::from_le_bytes(buf))
 }
 
-fn read_u16(file: &mut File) -> Result<u16> {
+fn read_u16(file: &mut impl Read) -> Result<u16> {
     let mut buf = [0u8; 2];
     file.read_exact(&mut buf)?;
     Ok(u16::from_le_bytes(buf))
@@ -321,20 +353,24 @@ mod tests {
 
         let chunk_a = bundle.append_chunk(b"hello world", Compression::None)?;
         let chunk_b = bundle.append_chunk(b"crv repository data", Compression::Lz4)?;
+        let chunk_c = bundle.append_chunk(&[7u8; 4096], Compression::Zstd)?;
 
         bundle.seal()?;
 
         let (dat_path, idx_path) = layout.pack_paths(0xAA, 1)?;
 
         let snapshot = IndexSnapshot::open(&idx_path)?;
-        assert_eq!(snapshot.entries().len(), 2);
+        assert_eq!(snapshot.entries().len(), 3);
 
         let entry_a = snapshot.find(&chunk_a.hash).expect("chunk a entry");
         let entry_b = snapshot.find(&chunk_b.hash).expect("chunk b entry");
+        let entry_c = snapshot.find(&chunk_c.hash).expect("chunk c entry");
+        assert!(chunk_c.stored_len < chunk_c.logical_len);
 
         let mut reader = PackReader::open(&dat_path)?;
         assert_eq!(reader.read_chunk(entry_a)?, b"hello world");
         assert_eq!(reader.read_chunk(entry_b)?, b"crv repository data".to_vec());
+        assert_eq!(reader.read_chunk(entry_c)?, vec![7u8; 4096]);
 
         Ok(())
     }
@@ -9,12 +9,17 @@ use super::error::{RepositoryError, Result};
 pub type ChunkHash = [u8; HASH_SIZE];
 
 pub const LZ4_FLAG: u16 = 0x0001;
-pub const KNOWN_FLAG_MASK: u16 = LZ4_FLAG;
+pub const ZSTD_FLAG: u16 = 0x0002;
+pub const KNOWN_FLAG_MASK: u16 = LZ4_FLAG | ZSTD_FLAG;
+
+/// zstd 压缩级别，兼顾压缩率与提交时的 CPU 开销
+const ZSTD_LEVEL: i32 = 3;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Compression {
     None,
     Lz4,
+    Zstd,
 }
 
 impl Compression {
@@ -22,6 +27,7 @@ impl Compression {
         match self {
             Compression::None => 0,
             Compression::Lz4 => LZ4_FLAG,
+            Compression::Zstd => ZSTD_FLAG,
         }
     }
 
@@ -29,10 +35,11 @@ impl Compression {
         if flags & !KNOWN_FLAG_MASK != 0 {
             return Err(RepositoryError::UnsupportedCompression(flags));
         }
-        if flags & LZ4_FLAG != 0 {
-            Ok(Compression::Lz4)
-        } else {
-            Ok(Compression::None)
+        match (flags & LZ4_FLAG != 0, flags & ZSTD_FLAG != 0) {
+            (false, false) => Ok(Compression::None),
+            (true, false) => Ok(Compression::Lz4),
+            (false, true) => Ok(Compression::Zstd),
+            (true, true) => Err(RepositoryError::UnsupportedCompression(flags)),
         }
     }
 
@@ -46,6 +53,10 @@ impl Compression {
                 payload: Cow::Owned(compress_prepend_size(original)),
                 compression: Compression::Lz4,
             }),
+            Compression::Zstd => Ok(EncodedChunk {
+                payload: Cow::Owned(zstd::bulk::compress(original, ZSTD_LEVEL)?),
+                compression: Compression::Zstd,
+            }),
         }
     }
 
@@ -54,6 +65,8 @@ impl Compression {
             Compression::None => Ok(encoded.to_vec()),
             Compression::Lz4 => decompress_size_prepended(encoded)
                 .map_err(|_| RepositoryError::Corrupted("LZ4 数据损坏")),
+            Compression::Zstd => zstd::stream::decode_all(encoded)
+                .map_err(|_| RepositoryError::Corrupted("Zstd 数据损坏")),
         }
     }
 }
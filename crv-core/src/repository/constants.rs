@@ -16,3 +16,5 @@ pub const SHARD_DIR_PREFIX: &str = "shard-";
 pub const PACK_FILE_PREFIX: &str = "pack-";
 pub const PACK_DATA_SUFFIX: &str = ".dat";
 pub const PACK_INDEX_SUFFIX: &str = ".idx";
+/// pack 数据文件转移到对象存储后留下的占位文件后缀，接在 `.dat` 之后
+pub const PACK_OFFLOADED_SUFFIX: &str = ".offloaded";
@@ -0,0 +1,282 @@
+//! Chunk 的二进制差量编码
+//!
+//! 大文件只改动了少量字节时，新 chunk 与上一个版本的 chunk 绝大部分内容相同。
+//! 这里使用 rsync 风格的滚动哈希将 base chunk 按固定大小的块建立索引，
+//! 在 target chunk 中查找相同的块，生成由 COPY（从 base 复制）与 INSERT（插入新数据）
+//! 组成的差量数据，上传差量即可在服务端重建 target chunk。
+//!
+//! 差量格式：`magic(4) | target_len(varint) | op*`，其中
+//! - COPY：`0x00 | base_offset(varint) | len(varint)`
+//! - INSERT：`0x01 | len(varint) | data`
+
+use std::collections::HashMap;
+
+use super::error::{RepositoryError, Result};
+
+const DELTA_MAGIC: &[u8; 4] = b"CRVD";
+const OP_COPY: u8 = 0x00;
+const OP_INSERT: u8 = 0x01;
+
+/// 建立索引与匹配时使用的块大小
+pub const DELTA_BLOCK_SIZE: usize = 32;
+
+/// 同一个弱哈希下最多记录的 base 偏移量，避免重复数据（如全零）导致索引膨胀
+const MAX_CANDIDATES: usize = 8;
+
+/// adler32 风格的滚动哈希
+struct RollingHash {
+    a: u32,
+    b: u32,
+}
+
+impl RollingHash {
+    fn new(block: &[u8]) -> Self {
+        let mut a = 0u32;
+        let mut b = 0u32;
+        for (i, &byte) in block.iter().enumerate() {
+            a = a.wrapping_add(byte as u32);
+            b = b.wrapping_add((block.len() - i) as u32 * byte as u32);
+        }
+        Self { a, b }
+    }
+
+    /// 窗口向后滑动一个字节：移出 `out`，移入 `incoming`
+    fn roll(&mut self, out: u8, incoming: u8) {
+        self.a = self
+            .a
+            .wrapping_sub(out as u32)
+            .wrapping_add(incoming as u32);
+        self.b = self
+            .b
+            .wrapping_sub(DELTA_BLOCK_SIZE as u32 * out as u32)
+            .wrapping_add(self.a);
+    }
+
+    fn digest(&self) -> u32 {
+        (self.b << 16) ^ self.a
+    }
+}
+
+fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn read_varint(data: &[u8], pos: &mut usize) -> Result<u64> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let byte = *data
+            .get(*pos)
+            .ok_or(RepositoryError::InvalidDelta("varint 被截断"))?;
+        *pos += 1;
+        value |= ((byte & 0x7f) as u64) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(RepositoryError::InvalidDelta("varint 过长"))
+}
+
+fn read_usize(data: &[u8], pos: &mut usize) -> Result<usize> {
+    usize::try_from(read_varint(data, pos)?)
+        .map_err(|_| RepositoryError::InvalidDelta("数值超出范围"))
+}
+
+fn emit_insert(out: &mut Vec<u8>, data: &[u8]) {
+    if data.is_empty() {
+        return;
+    }
+    out.push(OP_INSERT);
+    write_varint(out, data.len() as u64);
+    out.extend_from_slice(data);
+}
+
+fn emit_copy(out: &mut Vec<u8>, offset: usize, len: usize) {
+    out.push(OP_COPY);
+    write_varint(out, offset as u64);
+    write_varint(out, len as u64);
+}
+
+/// 计算从 `base` 得到 `target` 的差量数据
+pub fn compute_delta(base: &[u8], target: &[u8]) -> Vec<u8> {
+    let mut out = Vec::new();
+    out.extend_from_slice(DELTA_MAGIC);
+    write_varint(&mut out, target.len() as u64);
+
+    let mut index: HashMap<u32, Vec<usize>> = HashMap::new();
+    for offset in (0..base.len().saturating_sub(DELTA_BLOCK_SIZE - 1)).step_by(DELTA_BLOCK_SIZE) {
+        let candidates = index
+            .entry(RollingHash::new(&base[offset..offset + DELTA_BLOCK_SIZE]).digest())
+            .or_default();
+        if candidates.len() < MAX_CANDIDATES {
+            candidates.push(offset);
+        }
+    }
+
+    let mut literal_start = 0;
+    let mut pos = 0;
+    if target.len() >= DELTA_BLOCK_SIZE && !index.is_empty() {
+        let mut hash = RollingHash::new(&target[..DELTA_BLOCK_SIZE]);
+        loop {
+            let window = &target[pos..pos + DELTA_BLOCK_SIZE];
+            let matched = index.get(&hash.digest()).and_then(|candidates| {
+                candidates
+                    .iter()
+                    .copied()
+                    .find(|&offset| &base[offset..offset + DELTA_BLOCK_SIZE] == window)
+            });
+
+            if let Some(base_offset) = matched {
+                // 向前扩展到尚未输出的字面量中，向后扩展到不再相同为止
+                let mut back = 0;
+                while pos - back > literal_start
+                    && base_offset > back
+                    && base[base_offset - back - 1] == target[pos - back - 1]
+                {
+                    back += 1;
+                }
+                let base_start = base_offset - back;
+                let target_start = pos - back;
+                let mut len = DELTA_BLOCK_SIZE + back;
+                while target_start + len < target.len()
+                    && base_start + len < base.len()
+                    && base[base_start + len] == target[target_start + len]
+                {
+                    len += 1;
+                }
+
+                emit_insert(&mut out, &target[literal_start..target_start]);
+                emit_copy(&mut out, base_start, len);
+                pos = target_start + len;
+                literal_start = pos;
+                if pos + DELTA_BLOCK_SIZE > target.len() {
+                    break;
+                }
+                hash = RollingHash::new(&target[pos..pos + DELTA_BLOCK_SIZE]);
+            } else {
+                if pos + DELTA_BLOCK_SIZE >= target.len() {
+                    break;
+                }
+                hash.roll(target[pos], target[pos + DELTA_BLOCK_SIZE]);
+                pos += 1;
+            }
+        }
+    }
+    emit_insert(&mut out, &target[literal_start..]);
+    out
+}
+
+/// 将差量数据应用到 `base` 上，重建 target 数据
+///
+/// 差量数据可能来自网络，重建的数据超过 `max_target_len` 时直接拒绝。
+pub fn apply_delta(base: &[u8], delta: &[u8], max_target_len: usize) -> Result<Vec<u8>> {
+    if delta.len() < DELTA_MAGIC.len() || &delta[..DELTA_MAGIC.len()] != DELTA_MAGIC {
+        return Err(RepositoryError::InvalidDelta("magic 不匹配"));
+    }
+    let mut pos = DELTA_MAGIC.len();
+    let target_len = read_usize(delta, &mut pos)?;
+    if target_len > max_target_len {
+        return Err(RepositoryError::InvalidDelta("声明的长度超出限制"));
+    }
+    let mut out = Vec::with_capacity(target_len);
+
+    while pos < delta.len() {
+        let op = delta[pos];
+        pos += 1;
+        match op {
+            OP_COPY => {
+                let offset = read_usize(delta, &mut pos)?;
+                let len = read_usize(delta, &mut pos)?;
+                let end = offset
+                    .checked_add(len)
+                    .filter(|&end| end <= base.len())
+                    .ok_or(RepositoryError::InvalidDelta("COPY 超出 base 范围"))?;
+                out.extend_from_slice(&base[offset..end]);
+            }
+            OP_INSERT => {
+                let len = read_usize(delta, &mut pos)?;
+                let end = pos
+                    .checked_add(len)
+                    .filter(|&end| end <= delta.len())
+                    .ok_or(RepositoryError::InvalidDelta("INSERT 数据被截断"))?;
+                out.extend_from_slice(&delta[pos..end]);
+                pos = end;
+            }
+            _ => return Err(RepositoryError::InvalidDelta("未知的操作类型")),
+        }
+        if out.len() > target_len {
+            return Err(RepositoryError::InvalidDelta("重建的数据超出声明的长度"));
+        }
+    }
+
+    if out.len() != target_len {
+        return Err(RepositoryError::InvalidDelta("重建的数据长度与声明不一致"));
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 生成确定的伪随机数据，避免大段重复内容
+    fn pseudo_random(len: usize, seed: u64) -> Vec<u8> {
+        let mut state = seed;
+        (0..len)
+            .map(|_| {
+                state = state
+                    .wrapping_mul(6364136223846793005)
+                    .wrapping_add(1442695040888963407);
+                (state >> 33) as u8
+            })
+            .collect()
+    }
+
+    #[test]
+    fn one_byte_change_in_1mib_chunk_produces_small_delta() {
+        let base = pseudo_random(1024 * 1024, 42);
+        let mut target = base.clone();
+        target[512 * 1024 + 7] ^= 0xff;
+
+        let delta = compute_delta(&base, &target);
+        assert!(delta.len() < 64, "delta size {}", delta.len());
+        assert_eq!(apply_delta(&base, &delta, usize::MAX).unwrap(), target);
+    }
+
+    #[test]
+    fn round_trips_insertions_deletions_and_unrelated_data() {
+        let base = pseudo_random(100_000, 1);
+        let mut target = base[..30_000].to_vec();
+        target.extend_from_slice(b"inserted in the middle");
+        target.extend_from_slice(&base[40_000..]);
+        target.extend_from_slice(&pseudo_random(5_000, 2));
+
+        let delta = compute_delta(&base, &target);
+        assert!(delta.len() < 6_000, "delta size {}", delta.len());
+        assert_eq!(apply_delta(&base, &delta, usize::MAX).unwrap(), target);
+
+        for (base, target) in [
+            (&[][..], &b"short"[..]),
+            (&b"short"[..], &[][..]),
+            (&base[..], &pseudo_random(1_000, 3)[..]),
+        ] {
+            let delta = compute_delta(base, target);
+            assert_eq!(apply_delta(base, &delta, usize::MAX).unwrap(), target);
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_delta() {
+        let base = pseudo_random(1_000, 4);
+        let delta = compute_delta(&base, &base);
+
+        assert!(apply_delta(&base, b"XXXX", usize::MAX).is_err());
+        assert!(apply_delta(&base, &delta, base.len() - 1).is_err());
+        assert!(apply_delta(&base, &delta[..delta.len() - 1], usize::MAX).is_err());
+        // COPY 越过 base 的末尾
+        assert!(apply_delta(&base[..500], &delta, usize::MAX).is_err());
+    }
+}
@@ -33,8 +33,14 @@ pub enum RepositoryError {
     Corrupted(&'static str),
     #[error("未找到指定 chunk")]
     ChunkNotFound { hash: ChunkHash },
+    #[error("差量数据无效: {0}")]
+    InvalidDelta(&'static str),
     #[error("pack id 已达上限，无法继续创建新的 pack")]
     PackIdOverflow,
+    #[error("无效的对象路径: {0}")]
+    InvalidObjectPath(String),
+    #[error("未配置对象存储后端，无法转移或取回 pack")]
+    OffloadBackendMissing,
 }
 
 pub type Result<T> = std::result::Result<T, RepositoryError>;
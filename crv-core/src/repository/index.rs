@@ -92,6 +92,45 @@ impl MutableIndex {
         self.persist_unsealed()
     }
 
+    /// 将另一份索引快照合并进当前索引
+    ///
+    /// 两边条目都按哈希有序，按归并方式线性合并，完成后只写一次索引文件，
+    /// 避免逐条 `insert` 时每次都重写整个文件。两边存在相同哈希时，条目完全一致则只保留一份，
+    /// 否则返回 [`RepositoryError::DuplicateHash`]，当前索引保持不变。
+    pub fn merge(&mut self, other: IndexSnapshot) -> Result<()> {
+        self.ensure_open()?;
+        if other.entries.is_empty() {
+            return Ok(());
+        }
+        let mut merged = Vec::with_capacity(self.entries.len() + other.entries.len());
+        let mut left = self.entries.iter().peekable();
+        let mut right = other.entries.into_iter().peekable();
+        loop {
+            let ordering = match (left.peek(), right.peek()) {
+                (Some(l), Some(r)) => l.hash.cmp(&r.hash),
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => break,
+            };
+            match ordering {
+                Ordering::Less => merged.extend(left.next().cloned()),
+                Ordering::Greater => merged.extend(right.next()),
+                Ordering::Equal => {
+                    let existing = left.next().expect("left entry must exist");
+                    let incoming = right.next().expect("right entry must exist");
+                    if *existing != incoming {
+                        return Err(RepositoryError::DuplicateHash {
+                            hash: incoming.hash,
+                        });
+                    }
+                    merged.push(incoming);
+                }
+            }
+        }
+        self.entries = merged;
+        self.persist_unsealed()
+    }
+
     pub fn seal(&mut self) -> Result<()> {
         self.ensure_open()?;
         self.persist_sealed()?;
@@ -133,6 +172,18 @@ impl IndexSnapshot {
         Ok(Self { entries, sealed })
     }
 
+    /// 由内存中的条目构造未封存的快照，条目会按哈希排序，哈希重复时返回错误
+    pub fn from_entries(mut entries: Vec<IndexEntry>) -> Result<Self> {
+        entries.sort_by_key(|entry| entry.hash);
+        if let Some(pair) = entries.windows(2).find(|pair| pair[0].hash == pair[1].hash) {
+            return Err(RepositoryError::DuplicateHash { hash: pair[1].hash });
+        }
+        Ok(Self {
+            entries,
+            sealed: false,
+        })
+    }
+
     pub fn entries(&self) -> &[IndexEntry] {
         &self.entries
     }
@@ -295,3 +346,58 @@ fn write_index_file(path: &Path, entries: &[IndexEntry], sealed: bool) -> Result
     fs::rename(&tmp_path, path)?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(seed: u8, offset: u64) -> IndexEntry {
+        IndexEntry::new([seed; HASH_SIZE], offset, 16, 0)
+    }
+
+    #[test]
+    fn merge_interleaves_entries_and_persists() -> Result<()> {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("pack-000001.idx");
+        let mut index = MutableIndex::create_new(&path)?;
+        index.insert(entry(1, 10))?;
+        index.insert(entry(5, 50))?;
+
+        let incoming = IndexSnapshot::from_entries(vec![entry(9, 90), entry(3, 30), entry(5, 50)])?;
+        index.merge(incoming)?;
+
+        let hashes: Vec<u8> = index.entries().iter().map(|e| e.hash[0]).collect();
+        assert_eq!(hashes, vec![1, 3, 5, 9]);
+        let reloaded = IndexSnapshot::open(&path)?;
+        assert_eq!(reloaded.entries(), index.entries());
+        assert!(!reloaded.sealed());
+        Ok(())
+    }
+
+    #[test]
+    fn merge_rejects_conflicting_entries() -> Result<()> {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("pack-000001.idx");
+        let mut index = MutableIndex::create_new(&path)?;
+        index.insert(entry(1, 10))?;
+
+        let incoming = IndexSnapshot::from_entries(vec![entry(0, 0), entry(1, 99)])?;
+        assert!(matches!(
+            index.merge(incoming),
+            Err(RepositoryError::DuplicateHash { .. })
+        ));
+        assert_eq!(index.entries(), &[entry(1, 10)]);
+
+        assert!(matches!(
+            IndexSnapshot::from_entries(vec![entry(2, 0), entry(2, 1)]),
+            Err(RepositoryError::DuplicateHash { .. })
+        ));
+
+        index.seal()?;
+        assert!(matches!(
+            index.merge(IndexSnapshot::from_entries(vec![entry(7, 0)])?),
+            Err(RepositoryError::AlreadySealed { .. })
+        ));
+        Ok(())
+    }
+}
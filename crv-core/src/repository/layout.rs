@@ -1,14 +1,19 @@
 use std::collections::{BTreeSet, HashMap, VecDeque};
 use std::fs;
+use std::io::{self, Write};
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex, RwLock};
+use std::time::SystemTime;
 
-use super::bundle::{PackBundle, PackReader};
+use super::backend::StorageBackend;
+use super::bundle::{PackBundle, PackReader, scan_pack_entries};
 use super::chunk::{ChunkHash, ChunkRecord, Compression, compute_chunk_hash};
-use super::constants::{PACK_DATA_SUFFIX, PACK_FILE_PREFIX, PACK_INDEX_SUFFIX, SHARD_DIR_PREFIX};
+use super::constants::{
+    PACK_DATA_SUFFIX, PACK_FILE_PREFIX, PACK_INDEX_SUFFIX, PACK_OFFLOADED_SUFFIX, SHARD_DIR_PREFIX,
+};
 use super::error::{RepositoryError, Result};
-use super::index::{IndexEntry, IndexSnapshot};
-use super::io_utils::ensure_parent_dir;
+use super::index::{IndexEntry, IndexSnapshot, MutableIndex};
+use super::io_utils::{Blake3Stream, ensure_parent_dir};
 
 const DEFAULT_PACK_SOFT_LIMIT_BYTES: u64 = 512 * 1024 * 1024;
 const DEFAULT_HARD_PACK_SIZE_LIMIT_BYTES: u64 = 2 * 1024 * 1024 * 1024;
@@ -44,6 +49,15 @@ impl RepositoryLayout {
         format!("{PACK_FILE_PREFIX}{pack_id:06}")
     }
 
+    /// pack 数据文件在对象存储中的 key，如 `shard-0a/pack-000001.dat`
+    pub fn pack_object_key(shard: u8, pack_id: u32) -> String {
+        format!(
+            "{}/{}{PACK_DATA_SUFFIX}",
+            Self::shard_dir_name(shard),
+            Self::pack_base_name(pack_id)
+        )
+    }
+
     pub fn pack_paths(&self, shard: u8, pack_id: u32) -> Result<(PathBuf, PathBuf)> {
         let dir = self.ensure_shard_dir(shard)?;
         let base = Self::pack_base_name(pack_id);
@@ -53,7 +67,37 @@ impl RepositoryLayout {
         ensure_parent_dir(&idx_path)?;
         Ok((dat_path, idx_path))
     }
+}
+
+/// 重建索引的统计结果
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct IndexRebuildReport {
+    /// 重建了索引的 pack 数量
+    pub packs: u64,
+    /// 重建后索引中的 chunk 总数
+    pub chunks: u64,
+}
 
+/// 转移 pack 到对象存储的统计结果
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct OffloadReport {
+    /// 转移的 pack 数量
+    pub packs: u64,
+    /// 转移的数据文件总字节数
+    pub bytes: u64,
+}
+
+/// 单个 pack 的检查结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PackHealth {
+    pub shard: u8,
+    pub pack_id: u32,
+    /// 索引中的 chunk 数量，索引无法读取时为数据文件中扫描到的数量
+    pub chunk_count: u64,
+    /// 数据文件的字节数，已转移到对象存储的 pack 为 0
+    pub size_bytes: u64,
+    /// 数据文件与索引都能通过校验且彼此一致
+    pub is_valid: bool,
 }
 
 pub struct Repository {
@@ -63,6 +107,7 @@ pub struct Repository {
     pack_soft_limit: u64,
     hard_size_limit: u64,
     hard_chunk_limit: u64,
+    offload_backend: Option<Box<dyn StorageBackend>>,
 }
 
 impl Repository {
@@ -99,9 +144,16 @@ impl Repository {
             pack_soft_limit: pack_soft_limit.max(1),
             hard_size_limit: hard_size_limit.max(1),
             hard_chunk_limit: hard_chunk_limit.max(1),
+            offload_backend: None,
         })
     }
 
+    /// 设置转移冷 pack 使用的对象存储，读取已转移的 pack 时也从这里取回
+    pub fn with_offload_backend(mut self, backend: Box<dyn StorageBackend>) -> Self {
+        self.offload_backend = Some(backend);
+        self
+    }
+
     pub fn layout(&self) -> &RepositoryLayout {
         &self.layout
     }
@@ -140,6 +192,19 @@ impl Repository {
         Err(RepositoryError::ChunkNotFound { hash: *hash })
     }
 
+    /// 按顺序读取文件的所有 chunk，计算整个文件内容的 Blake3 哈希
+    pub fn compute_content_hash(&self, hashes: &[ChunkHash]) -> Result<ChunkHash> {
+        let mut hasher = Blake3Stream::new();
+        for hash in hashes {
+            let (entry, dat_path) = self
+                .locate_chunk(hash)?
+                .ok_or(RepositoryError::ChunkNotFound { hash: *hash })?;
+            let mut reader = PackReader::open(&dat_path)?;
+            hasher.update(&reader.read_chunk(&entry)?);
+        }
+        Ok(hasher.finalize())
+    }
+
     pub fn seal_shard(&self, shard: u8) -> Result<()> {
         let lock = &self.shards[shard as usize];
         let mut guard = lock
@@ -164,6 +229,261 @@ impl Repository {
         guard.seal_specific(pack_id)
     }
 
+    /// 所有 pack 数据文件占用的字节数（不含索引文件）
+    pub fn disk_usage(&self) -> Result<u64> {
+        let mut total = 0;
+        for shard in 0u16..=0xFF {
+            let dir = self
+                .layout
+                .root
+                .join(RepositoryLayout::shard_dir_name(shard as u8));
+            if !dir.exists() {
+                continue;
+            }
+            for entry in fs::read_dir(dir)? {
+                let entry = entry?;
+                if parse_pack_id(entry.path(), PACK_DATA_SUFFIX).is_some() {
+                    total += entry.metadata()?.len();
+                }
+            }
+        }
+        Ok(total)
+    }
+
+    /// 扫描所有非活跃 pack 的数据文件，重新生成它们的 `.idx` 索引
+    ///
+    /// 用于索引文件丢失或损坏后的恢复。活跃 pack 的索引由写入方维护，不参与重建。
+    /// 每个 shard 重建期间持有写锁，新索引先写入临时文件，再原子替换旧索引。
+    pub fn rebuild_index_from_packs(&self) -> Result<IndexRebuildReport> {
+        let mut report = IndexRebuildReport::default();
+        for shard in 0u16..=0xFF {
+            let shard = shard as u8;
+            let guard = self.shards[shard as usize]
+                .write()
+                .map_err(|_| RepositoryError::Corrupted("shard lock poisoned"))?;
+            for pack_id in guard.sealed_pack_ids() {
+                let (dat_path, _) = self.layout.pack_paths(shard, pack_id)?;
+                // 已转移的 pack 本地没有数据文件，保留原有索引
+                if !dat_path.exists() && offloaded_path(&dat_path).exists() {
+                    continue;
+                }
+                report.chunks += self.rebuild_pack_index(shard, pack_id)?;
+                report.packs += 1;
+            }
+        }
+        Ok(report)
+    }
+
+    fn rebuild_pack_index(&self, shard: u8, pack_id: u32) -> Result<u64> {
+        let (dat_path, idx_path) = self.layout.pack_paths(shard, pack_id)?;
+        let (entries, sealed) = scan_pack_entries(&dat_path)?;
+        let snapshot = IndexSnapshot::from_entries(entries)?;
+        let chunk_count = snapshot.entries().len() as u64;
+
+        let rebuild_path = idx_path.with_extension(format!("rebuild{PACK_INDEX_SUFFIX}"));
+        let mut index = MutableIndex::create_new(&rebuild_path)?;
+        index.merge(snapshot)?;
+        if sealed {
+            index.seal()?;
+        }
+        fs::rename(&rebuild_path, &idx_path)?;
+
+        self.index_cache
+            .lock()
+            .map_err(|_| RepositoryError::Corrupted("index cache lock poisoned"))?
+            .remove((shard, pack_id));
+        Ok(chunk_count)
+    }
+
+    /// 检查所有 pack（包括活跃 pack）的完整性
+    ///
+    /// 扫描数据文件的头部与每个条目，已封存的 pack 还会校验 CRC；索引也需要通过校验，
+    /// 并且与扫描出的条目完全一致。已转移到对象存储的 pack 只检查本地索引。
+    pub fn inspect_packs(&self) -> Result<Vec<PackHealth>> {
+        let mut packs = Vec::new();
+        for shard in 0u16..=0xFF {
+            let shard = shard as u8;
+            // 持有读锁，避免与同一 shard 的写入、封存交错
+            let guard = self.shards[shard as usize]
+                .read()
+                .map_err(|_| RepositoryError::Corrupted("shard lock poisoned"))?;
+            for pack_id in guard.all_pack_ids() {
+                packs.push(self.inspect_pack(shard, pack_id)?);
+            }
+        }
+        Ok(packs)
+    }
+
+    fn inspect_pack(&self, shard: u8, pack_id: u32) -> Result<PackHealth> {
+        let (dat_path, idx_path) = self.layout.pack_paths(shard, pack_id)?;
+        let index = IndexSnapshot::open(&idx_path).ok();
+        let mut health = PackHealth {
+            shard,
+            pack_id,
+            chunk_count: index
+                .as_ref()
+                .map_or(0, |index| index.entries().len() as u64),
+            size_bytes: 0,
+            is_valid: false,
+        };
+        if !dat_path.exists() {
+            health.is_valid = index.is_some() && offloaded_path(&dat_path).exists();
+            return Ok(health);
+        }
+        health.size_bytes = fs::metadata(&dat_path)?.len();
+        let Ok((mut entries, sealed)) = scan_pack_entries(&dat_path) else {
+            return Ok(health);
+        };
+        let Some(index) = index else {
+            health.chunk_count = entries.len() as u64;
+            return Ok(health);
+        };
+        entries.sort_by_key(|entry| entry.hash);
+        health.is_valid = index.sealed() == sealed && index.entries() == entries.as_slice();
+        Ok(health)
+    }
+
+    /// 依次访问所有 pack（包括活跃 pack）的数据文件、索引文件与 `.offloaded` 占位文件
+    ///
+    /// 回调参数为相对仓库根目录的路径与完整路径。访问某个 shard 的文件期间持有该 shard 的读锁，
+    /// 回调读取文件时不会有并发写入或封存。
+    pub fn visit_pack_files(
+        &self,
+        mut visit: impl FnMut(&Path, &Path) -> Result<()>,
+    ) -> Result<()> {
+        for shard in 0u16..=0xFF {
+            let shard = shard as u8;
+            let guard = self.shards[shard as usize]
+                .read()
+                .map_err(|_| RepositoryError::Corrupted("shard lock poisoned"))?;
+            for pack_id in guard.all_pack_ids() {
+                let (dat_path, idx_path) = self.layout.pack_paths(shard, pack_id)?;
+                let offloaded = offloaded_path(&dat_path);
+                for path in [dat_path, idx_path, offloaded] {
+                    if !path.exists() {
+                        continue;
+                    }
+                    let relative = path.strip_prefix(&self.layout.root).map_err(|_| {
+                        RepositoryError::Corrupted("pack file outside repository root")
+                    })?;
+                    visit(relative, &path)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// 校验一个不属于本仓库的 pack 数据文件（如从备份中解出的文件），返回其中的 chunk 数量
+    ///
+    /// 同目录下的索引标记为已封存时，数据文件必须通过 CRC 校验；每个 chunk 都会被解码，
+    /// 重新计算出的哈希必须与条目中的哈希一致。
+    pub fn verify_pack_file(dat_path: &Path) -> Result<u64> {
+        let (entries, sealed) = scan_pack_entries(dat_path)?;
+        let idx_path = dat_path.with_extension(PACK_INDEX_SUFFIX.trim_start_matches('.'));
+        if idx_path.exists() && IndexSnapshot::open(&idx_path)?.sealed() && !sealed {
+            return Err(RepositoryError::CrcMismatch {
+                path: dat_path.to_path_buf(),
+            });
+        }
+        let mut reader = PackReader::open(dat_path)?;
+        for entry in &entries {
+            let data = reader.read_chunk(entry)?;
+            if compute_chunk_hash(&data) != entry.hash {
+                return Err(RepositoryError::Corrupted("chunk 内容与哈希不匹配"));
+            }
+        }
+        Ok(entries.len() as u64)
+    }
+
+    /// 将一个不属于本仓库的 pack 数据文件中、本仓库还没有的 chunk 写入仓库，返回新写入的 chunk 数量
+    ///
+    /// 已存在的 chunk 直接跳过，重复导入同一个 pack 不会产生重复数据。调用前应先用
+    /// [`Repository::verify_pack_file`] 校验数据文件，这里只校验每个被写入的 chunk 的哈希。
+    pub fn import_pack_file(&self, dat_path: &Path) -> Result<u64> {
+        let (entries, _) = scan_pack_entries(dat_path)?;
+        let mut reader = PackReader::open(dat_path)?;
+        let mut imported = 0;
+        for entry in entries {
+            if self.locate_chunk(&entry.hash)?.is_some() {
+                continue;
+            }
+            let data = reader.read_chunk(&entry)?;
+            if compute_chunk_hash(&data) != entry.hash {
+                return Err(RepositoryError::Corrupted("chunk 内容与哈希不匹配"));
+            }
+            self.write_chunk(&data, Compression::from_flags(entry.flags)?)?;
+            imported += 1;
+        }
+        Ok(imported)
+    }
+
+    /// 将最后修改时间早于 `older_than` 的非活跃 pack 数据文件转移到对象存储
+    ///
+    /// 本地保留索引，并把数据文件替换为内容是对象 key 的 `.offloaded` 占位文件；
+    /// 之后读取其中的 chunk 时会自动取回整个数据文件。
+    pub fn offload_sealed_packs(&self, older_than: SystemTime) -> Result<OffloadReport> {
+        let backend = self
+            .offload_backend
+            .as_deref()
+            .ok_or(RepositoryError::OffloadBackendMissing)?;
+        let mut report = OffloadReport::default();
+        for shard in 0u16..=0xFF {
+            let shard = shard as u8;
+            // 持有写锁，避免与取回同一 shard 的 pack 交错
+            let guard = self.shards[shard as usize]
+                .write()
+                .map_err(|_| RepositoryError::Corrupted("shard lock poisoned"))?;
+            for pack_id in guard.sealed_pack_ids() {
+                let (dat_path, _) = self.layout.pack_paths(shard, pack_id)?;
+                let modified = match fs::metadata(&dat_path) {
+                    Ok(meta) => meta.modified()?,
+                    Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+                    Err(e) => return Err(e.into()),
+                };
+                if modified >= older_than {
+                    continue;
+                }
+                let data = fs::read(&dat_path)?;
+                let key = RepositoryLayout::pack_object_key(shard, pack_id);
+                backend.write(&key, &data)?;
+                fs::write(offloaded_path(&dat_path), &key)?;
+                fs::remove_file(&dat_path)?;
+                report.packs += 1;
+                report.bytes += data.len() as u64;
+            }
+        }
+        Ok(report)
+    }
+
+    /// 从对象存储取回已转移的 pack 数据文件，并删除占位文件
+    fn fetch_offloaded(&self, shard: u8, dat_path: &Path) -> Result<()> {
+        let _guard = self.shards[shard as usize]
+            .read()
+            .map_err(|_| RepositoryError::Corrupted("shard lock poisoned"))?;
+        let marker = offloaded_path(dat_path);
+        let key = match fs::read_to_string(&marker) {
+            Ok(key) => key,
+            // 已被并发的读取取回
+            Err(e) if e.kind() == io::ErrorKind::NotFound && dat_path.exists() => return Ok(()),
+            Err(e) => return Err(e.into()),
+        };
+        let backend = self
+            .offload_backend
+            .as_deref()
+            .ok_or(RepositoryError::OffloadBackendMissing)?;
+        let data = backend.read(key.trim())?;
+
+        // 先写入同目录的临时文件再替换，避免读到不完整的数据文件
+        let dir = dat_path.parent().unwrap_or_else(|| Path::new("."));
+        let mut temp = tempfile::NamedTempFile::new_in(dir)?;
+        temp.write_all(&data)?;
+        temp.persist(dat_path).map_err(|e| e.error)?;
+        match fs::remove_file(&marker) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
     pub fn locate_chunk(&self, hash: &ChunkHash) -> Result<Option<(IndexEntry, PathBuf)>> {
         let shard = hash[0];
         let lock = &self.shards[shard as usize];
@@ -178,7 +498,12 @@ impl Repository {
             }
             guard.all_pack_ids()
         };
-        self.locate_in_pack_ids(shard, &pack_ids, hash)
+        let located = self.locate_in_pack_ids(shard, &pack_ids, hash)?;
+        match &located {
+            Some((_, dat_path)) if !dat_path.exists() => self.fetch_offloaded(shard, dat_path)?,
+            _ => {}
+        }
+        Ok(located)
     }
 
     fn locate_in_pack_ids(
@@ -211,7 +536,7 @@ impl Repository {
         if let Some(snapshot) = snapshot_opt {
             if let Some(entry) = snapshot.find(hash) {
                 let (dat_path, _) = self.layout.pack_paths(shard, pack_id)?;
-                if dat_path.exists() {
+                if dat_path.exists() || offloaded_path(&dat_path).exists() {
                     return Ok(Some((entry.clone(), dat_path)));
                 }
             }
@@ -314,15 +639,17 @@ impl ShardState {
         }
         Ok(())
     }
-
 }
 
 fn discover_existing_packs(layout: &RepositoryLayout, shard: u8) -> Result<(BTreeSet<u32>, u32)> {
     let dir = layout.ensure_shard_dir(shard)?;
+    let offloaded_suffix = format!("{PACK_DATA_SUFFIX}{PACK_OFFLOADED_SUFFIX}");
     let mut packs = BTreeSet::new();
     for entry in fs::read_dir(dir)? {
         let entry = entry?;
-        if let Some(id) = parse_pack_id(entry.path(), PACK_DATA_SUFFIX) {
+        let id = parse_pack_id(entry.path(), PACK_DATA_SUFFIX)
+            .or_else(|| parse_pack_id(entry.path(), &offloaded_suffix));
+        if let Some(id) = id {
             packs.insert(id);
         }
     }
@@ -368,6 +695,13 @@ impl IndexCache {
         }
     }
 
+    fn remove(&mut self, key: (u8, u32)) {
+        self.map.remove(&key);
+        if let Some(pos) = self.order.iter().position(|k| *k == key) {
+            self.order.remove(pos);
+        }
+    }
+
     fn get_or_load(
         &mut self,
         layout: &RepositoryLayout,
@@ -376,7 +710,7 @@ impl IndexCache {
     ) -> Result<Option<Arc<IndexSnapshot>>> {
         let key = (shard, pack_id);
         let (dat_path, idx_path) = layout.pack_paths(shard, pack_id)?;
-        if !idx_path.exists() || !dat_path.exists() {
+        if !idx_path.exists() || !(dat_path.exists() || offloaded_path(&dat_path).exists()) {
             return Ok(None);
         }
         let meta = idx_path.metadata()?;
@@ -401,6 +735,12 @@ impl IndexCache {
     }
 }
 
+/// pack 数据文件转移后留下的占位文件路径
+fn offloaded_path(dat_path: &Path) -> PathBuf {
+    let mut path = dat_path.as_os_str().to_owned();
+    path.push(PACK_OFFLOADED_SUFFIX);
+    PathBuf::from(path)
+}
 
 fn parse_pack_id(path: PathBuf, suffix: &str) -> Option<u32> {
     let name = path.file_name()?.to_str()?;
@@ -415,7 +755,7 @@ fn parse_pack_id(path: PathBuf, suffix: &str) -> Option<u32> {
 mod tests {
     use super::*;
     use crate::repository::{Compression, compute_chunk_hash};
-    use std::sync::{Arc, mpsc, Mutex};
+    use std::sync::{Arc, Mutex, mpsc};
     use std::thread;
     use std::time::Duration;
 
@@ -454,6 +794,120 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn content_hash_covers_all_chunks_in_order() -> Result<()> {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let repo = Repository::new(temp_dir.path())?;
+        let first = repo.write_chunk(b"first chunk, ", Compression::None)?;
+        let second = repo.write_chunk(b"second chunk", Compression::Lz4)?;
+
+        let hash = repo.compute_content_hash(&[first.hash, second.hash])?;
+        assert_eq!(hash, compute_chunk_hash(b"first chunk, second chunk"));
+        assert_ne!(hash, first.hash);
+        assert_ne!(hash, repo.compute_content_hash(&[second.hash, first.hash])?);
+        Ok(())
+    }
+
+    #[test]
+    fn disk_usage_counts_pack_data_files() -> Result<()> {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let repo = Repository::new(temp_dir.path())?;
+        assert_eq!(repo.disk_usage()?, 0);
+
+        let record = repo.write_chunk(&[7u8; 4096], Compression::None)?;
+        repo.seal_all()?;
+        let (_, dat_path) = repo.locate_chunk(&record.hash)?.expect("chunk must exist");
+        assert_eq!(repo.disk_usage()?, fs::metadata(dat_path)?.len());
+        Ok(())
+    }
+
+    #[test]
+    fn inspect_packs_flags_corrupted_bundle() -> Result<()> {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let repo = Repository::new(temp_dir.path())?;
+        let healthy = repo.write_chunk(b"healthy pack", Compression::None)?;
+        let corrupted = repo.write_chunk(b"corrupted pack", Compression::None)?;
+        assert_ne!(healthy.hash[0], corrupted.hash[0]);
+        repo.seal_all()?;
+
+        // 修改 payload 的最后一个字节，pack 的 CRC 不再匹配
+        let (dat_path, _) = repo.layout().pack_paths(corrupted.hash[0], 1)?;
+        let mut data = fs::read(&dat_path)?;
+        let last_payload = data.len() - crate::repository::PACK_TRAILER_SIZE as usize - 1;
+        data[last_payload] ^= 0xFF;
+        fs::write(&dat_path, &data)?;
+
+        let packs = repo.inspect_packs()?;
+        assert_eq!(packs.len(), 2);
+        let find = |shard: u8| packs.iter().find(|p| p.shard == shard).unwrap();
+        let (healthy_dat, _) = repo.layout().pack_paths(healthy.hash[0], 1)?;
+        assert_eq!(
+            find(healthy.hash[0]),
+            &PackHealth {
+                shard: healthy.hash[0],
+                pack_id: 1,
+                chunk_count: 1,
+                size_bytes: fs::metadata(healthy_dat)?.len(),
+                is_valid: true,
+            }
+        );
+        assert!(!find(corrupted.hash[0]).is_valid);
+        assert_eq!(find(corrupted.hash[0]).chunk_count, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn visit_pack_files_lists_data_and_index_files() -> Result<()> {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let repo = Repository::new(temp_dir.path())?;
+        let record = repo.write_chunk(b"visited pack", Compression::None)?;
+        repo.seal_all()?;
+
+        let mut visited = Vec::new();
+        repo.visit_pack_files(|relative, path| {
+            assert_eq!(path, temp_dir.path().join(relative));
+            visited.push(relative.to_string_lossy().replace('\\', "/"));
+            Ok(())
+        })?;
+        let base = format!(
+            "{}/{}",
+            RepositoryLayout::shard_dir_name(record.hash[0]),
+            RepositoryLayout::pack_base_name(1)
+        );
+        assert_eq!(
+            visited,
+            vec![
+                format!("{base}{PACK_DATA_SUFFIX}"),
+                format!("{base}{PACK_INDEX_SUFFIX}")
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn import_pack_file_skips_existing_chunks() -> Result<()> {
+        let source_dir = tempfile::tempdir().unwrap();
+        let source = Repository::new(source_dir.path())?;
+        let first = source.write_chunk(b"imported chunk", Compression::None)?;
+        source.seal_all()?;
+        let (dat_path, _) = source.layout().pack_paths(first.hash[0], 1)?;
+        assert_eq!(Repository::verify_pack_file(&dat_path)?, 1);
+
+        let target_dir = tempfile::tempdir().unwrap();
+        let target = Repository::new(target_dir.path())?;
+        assert_eq!(target.import_pack_file(&dat_path)?, 1);
+        assert_eq!(target.import_pack_file(&dat_path)?, 0);
+        assert_eq!(target.read_chunk(&first.hash)?, b"imported chunk");
+
+        // 已封存的 pack 被修改后无法通过校验
+        let mut data = fs::read(&dat_path)?;
+        let last_payload = data.len() - crate::repository::PACK_TRAILER_SIZE as usize - 1;
+        data[last_payload] ^= 0xFF;
+        fs::write(&dat_path, &data)?;
+        assert!(Repository::verify_pack_file(&dat_path).is_err());
+        Ok(())
+    }
+
     #[test]
     fn seal_specific_bundle() -> Result<()> {
         let temp_dir = tempfile::tempdir().unwrap();
@@ -548,7 +1002,12 @@ mod tests {
         let mut pack = PackWriter::create_new(&dat_path)?;
         let payload = b"orphan chunk";
         let hash = compute_chunk_hash(payload);
-        let _ = pack.append_chunk(hash, payload.len() as u32, Compression::None.to_flags(), payload)?;
+        let _ = pack.append_chunk(
+            hash,
+            payload.len() as u32,
+            Compression::None.to_flags(),
+            payload,
+        )?;
         drop(pack); // 模拟崩溃前未写 idx
 
         // 启动新的 Repository，应忽略缺失 idx 的 .dat
@@ -563,11 +1022,160 @@ mod tests {
         assert_eq!(repo.read_chunk(&record.hash)?, b"fresh chunk");
         let guard = repo.shards[shard as usize].read().unwrap();
         // 新 pack id 应大于遗留的 1
-        assert!(guard
-            .active
-            .as_ref()
-            .map(|b| b.identity().pack_id >= 2)
-            .unwrap_or(true));
+        assert!(
+            guard
+                .active
+                .as_ref()
+                .map(|b| b.identity().pack_id >= 2)
+                .unwrap_or(true)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn rebuild_index_restores_missing_and_corrupted_indexes() -> Result<()> {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let repo = Repository::with_pack_soft_limit(temp_dir.path(), u64::MAX)?;
+        let (shard, chunks) = generate_chunks_for_same_shard(4, 64);
+        let first = repo.write_chunk(&chunks[0], Compression::None)?;
+        let second = repo.write_chunk(&chunks[1], Compression::Lz4)?;
+        repo.seal_shard(shard)?;
+        let third = repo.write_chunk(&chunks[2], Compression::None)?;
+        repo.seal_shard(shard)?;
+        // 活跃 pack 不参与重建
+        let active = repo.write_chunk(&chunks[3], Compression::None)?;
+
+        let (_, first_idx) = repo.layout().pack_paths(shard, 1)?;
+        let (_, second_idx) = repo.layout().pack_paths(shard, 2)?;
+        fs::remove_file(&first_idx)?;
+        fs::write(&second_idx, b"garbage")?;
+        assert!(repo.read_chunk(&first.hash).is_err());
+
+        let report = repo.rebuild_index_from_packs()?;
+        assert_eq!(
+            report,
+            IndexRebuildReport {
+                packs: 2,
+                chunks: 3
+            }
+        );
+        assert!(IndexSnapshot::open(&first_idx)?.sealed());
+        assert_eq!(repo.read_chunk(&first.hash)?, chunks[0]);
+        assert_eq!(repo.read_chunk(&second.hash)?, chunks[1]);
+        assert_eq!(repo.read_chunk(&third.hash)?, chunks[2]);
+        assert_eq!(repo.read_chunk(&active.hash)?, chunks[3]);
+        Ok(())
+    }
+
+    /// 模拟 S3 的内存对象存储
+    #[derive(Clone, Default)]
+    struct MemoryBackend {
+        objects: Arc<Mutex<HashMap<String, Vec<u8>>>>,
+    }
+
+    impl StorageBackend for MemoryBackend {
+        fn write(&self, path: &str, data: &[u8]) -> Result<()> {
+            self.objects
+                .lock()
+                .unwrap()
+                .insert(path.to_string(), data.to_vec());
+            Ok(())
+        }
+
+        fn read(&self, path: &str) -> Result<Vec<u8>> {
+            self.objects
+                .lock()
+                .unwrap()
+                .get(path)
+                .cloned()
+                .ok_or_else(|| io::Error::from(io::ErrorKind::NotFound).into())
+        }
+
+        fn exists(&self, path: &str) -> Result<bool> {
+            Ok(self.objects.lock().unwrap().contains_key(path))
+        }
+
+        fn delete(&self, path: &str) -> Result<()> {
+            self.objects.lock().unwrap().remove(path);
+            Ok(())
+        }
+
+        fn list(&self, prefix: &str) -> Result<Vec<String>> {
+            let mut keys: Vec<_> = self
+                .objects
+                .lock()
+                .unwrap()
+                .keys()
+                .filter(|k| k.starts_with(prefix))
+                .cloned()
+                .collect();
+            keys.sort();
+            Ok(keys)
+        }
+    }
+
+    #[test]
+    fn offloaded_packs_are_fetched_back_on_read() -> Result<()> {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let backend = MemoryBackend::default();
+        let repo = Repository::with_pack_soft_limit(temp_dir.path(), u64::MAX)?
+            .with_offload_backend(Box::new(backend.clone()));
+        let (shard, chunks) = generate_chunks_for_same_shard(3, 64);
+        let first = repo.write_chunk(&chunks[0], Compression::None)?;
+        let second = repo.write_chunk(&chunks[1], Compression::Lz4)?;
+        repo.seal_shard(shard)?;
+        let active = repo.write_chunk(&chunks[2], Compression::None)?;
+
+        // 阈值之后修改过的 pack 不转移
+        let report = repo.offload_sealed_packs(SystemTime::UNIX_EPOCH)?;
+        assert_eq!(report, OffloadReport::default());
+
+        let (dat_path, _) = repo.layout().pack_paths(shard, 1)?;
+        let dat_len = fs::metadata(&dat_path)?.len();
+        let later = SystemTime::now() + Duration::from_secs(3600);
+        let report = repo.offload_sealed_packs(later)?;
+        // 活跃 pack 不参与转移
+        assert_eq!(
+            report,
+            OffloadReport {
+                packs: 1,
+                bytes: dat_len
+            }
+        );
+        let key = RepositoryLayout::pack_object_key(shard, 1);
+        assert!(!dat_path.exists());
+        assert_eq!(fs::read_to_string(offloaded_path(&dat_path))?, key);
+        assert!(backend.exists(&key)?);
+
+        // 重复写入的检测不需要取回数据
+        assert!(matches!(
+            repo.write_chunk(&chunks[0], Compression::None),
+            Err(RepositoryError::DuplicateHash { .. })
+        ));
+        assert!(!dat_path.exists());
+
+        // 重新打开后仍能识别已转移的 pack，读取时自动取回
+        drop(repo);
+        let repo = Repository::with_pack_soft_limit(temp_dir.path(), u64::MAX)?
+            .with_offload_backend(Box::new(backend.clone()));
+        assert_eq!(repo.read_chunk(&first.hash)?, chunks[0]);
+        assert!(dat_path.exists());
+        assert!(!offloaded_path(&dat_path).exists());
+        assert_eq!(repo.read_chunk(&second.hash)?, chunks[1]);
+        assert_eq!(repo.read_chunk(&active.hash)?, chunks[2]);
+        let record = repo.write_chunk(b"after offload", Compression::None)?;
+        assert_ne!(repo.locate_chunk(&record.hash)?.unwrap().1, dat_path);
+        Ok(())
+    }
+
+    #[test]
+    fn offload_requires_backend() -> Result<()> {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let repo = Repository::new(temp_dir.path())?;
+        assert!(matches!(
+            repo.offload_sealed_packs(SystemTime::now()),
+            Err(RepositoryError::OffloadBackendMissing)
+        ));
         Ok(())
     }
 
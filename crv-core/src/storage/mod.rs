@@ -1,12 +1,23 @@
 pub mod file_block;
 
 use crate::storage::file_block::FileBlock;
+use lru::LruCache;
 use std::fs::{self, File};
 use std::io::{Read, Write};
+use std::num::NonZeroUsize;
 use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
 use std::time::SystemTime;
+use thiserror::Error;
+
+/// Number of blocks [`BlockStore::new`] keeps in memory.
+pub const DEFAULT_BLOCK_CACHE_CAPACITY: usize = 1_000;
 
 /// Options to control chunking behaviors.
+///
+/// CDC sizes must satisfy `cdc_min_size <= cdc_avg_size <= cdc_max_size`, see
+/// [`ChunkingOptions::validate`]. Use [`ChunkingOptions::builder`] to construct
+/// options that are checked as they are set.
 #[derive(Debug, Clone)]
 pub struct ChunkingOptions {
     /// Fixed block size for large files.
@@ -37,13 +48,109 @@ impl Default for ChunkingOptions {
     }
 }
 
+/// Invalid combination of [`ChunkingOptions`].
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum ChunkingOptionsError {
+    #[error("cdc_min_size ({min}) exceeds cdc_avg_size ({avg})")]
+    MinExceedsAvg { min: usize, avg: usize },
+    #[error("cdc_avg_size ({avg}) exceeds cdc_max_size ({max})")]
+    AvgExceedsMax { avg: usize, max: usize },
+    #[error("cdc_window_size must be greater than zero")]
+    WindowSizeZero,
+    #[error("fixed_block_size must be greater than zero")]
+    FixedBlockSizeZero,
+}
+
+impl ChunkingOptions {
+    pub fn builder() -> ChunkingOptionsBuilder {
+        ChunkingOptionsBuilder::default()
+    }
+
+    /// Check the invariants the chunking algorithms rely on.
+    pub fn validate(&self) -> Result<(), ChunkingOptionsError> {
+        if self.cdc_min_size > self.cdc_avg_size {
+            return Err(ChunkingOptionsError::MinExceedsAvg {
+                min: self.cdc_min_size,
+                avg: self.cdc_avg_size,
+            });
+        }
+        if self.cdc_avg_size > self.cdc_max_size {
+            return Err(ChunkingOptionsError::AvgExceedsMax {
+                avg: self.cdc_avg_size,
+                max: self.cdc_max_size,
+            });
+        }
+        if self.cdc_window_size == 0 {
+            return Err(ChunkingOptionsError::WindowSizeZero);
+        }
+        if self.fixed_block_size == 0 {
+            return Err(ChunkingOptionsError::FixedBlockSizeZero);
+        }
+        Ok(())
+    }
+}
+
+/// Builds [`ChunkingOptions`] starting from the defaults.
+///
+/// Every setter validates the options as they stand after the change, so when
+/// moving CDC sizes past each other set them in an order that keeps
+/// `min <= avg <= max`, e.g. raise `max` before `avg` before `min`.
+#[derive(Debug, Clone, Default)]
+pub struct ChunkingOptionsBuilder {
+    options: ChunkingOptions,
+}
+
+impl ChunkingOptionsBuilder {
+    pub fn with_fixed_block_size(self, n: usize) -> Result<Self, ChunkingOptionsError> {
+        self.set(|o| o.fixed_block_size = n)
+    }
+
+    pub fn with_small_file_threshold(self, n: usize) -> Result<Self, ChunkingOptionsError> {
+        self.set(|o| o.small_file_threshold = n)
+    }
+
+    pub fn with_cdc_window_size(self, n: usize) -> Result<Self, ChunkingOptionsError> {
+        self.set(|o| o.cdc_window_size = n)
+    }
+
+    pub fn with_cdc_min_size(self, n: usize) -> Result<Self, ChunkingOptionsError> {
+        self.set(|o| o.cdc_min_size = n)
+    }
+
+    pub fn with_cdc_avg_size(self, n: usize) -> Result<Self, ChunkingOptionsError> {
+        self.set(|o| o.cdc_avg_size = n)
+    }
+
+    pub fn with_cdc_max_size(self, n: usize) -> Result<Self, ChunkingOptionsError> {
+        self.set(|o| o.cdc_max_size = n)
+    }
+
+    pub fn build(self) -> ChunkingOptions {
+        self.options
+    }
+
+    fn set(
+        mut self,
+        update: impl FnOnce(&mut ChunkingOptions),
+    ) -> Result<Self, ChunkingOptionsError> {
+        update(&mut self.options);
+        self.options.validate()?;
+        Ok(self)
+    }
+}
+
 /// Split a file and persist blocks under `store_root` using 3-level directory layout.
 /// Returns the list of `FileBlock`s (with ids computed by blake3).
+///
+/// Fails with [`std::io::ErrorKind::InvalidInput`] if `options` are invalid.
 pub fn chunk_and_store_file<P: AsRef<Path>, Q: AsRef<Path>>(
     source_file: P,
     store_root: Q,
     options: &ChunkingOptions,
 ) -> std::io::Result<Vec<FileBlock>> {
+    options
+        .validate()
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
     let meta = fs::metadata(&source_file)?;
     let file_len = meta.len() as usize;
     let mut blocks: Vec<FileBlock> = Vec::new();
@@ -102,8 +209,11 @@ fn cdc_chunk_ranges(bytes: &[u8], options: &ChunkingOptions) -> Vec<(usize, usiz
 
     let min_size = options.cdc_min_size.min(len);
     let max_size = options.cdc_max_size.min(len);
-    let avg_pow2 = options.cdc_avg_size.next_power_of_two();
-    let mask: u64 = (avg_pow2 as u64) - 1;
+    // An average beyond the largest power of two never matches, only max_size cuts.
+    let mask: u64 = options
+        .cdc_avg_size
+        .checked_next_power_of_two()
+        .map_or(u64::MAX, |avg_pow2| avg_pow2 as u64 - 1);
     let w = options.cdc_window_size.min(len.max(1));
 
     // Initialize random table deterministically to avoid large static.
@@ -149,7 +259,7 @@ fn cdc_chunk_ranges(bytes: &[u8], options: &ChunkingOptions) -> Vec<(usize, usiz
 }
 
 fn rotl64(x: u64, r: u32) -> u64 {
-    (x << r) | (x >> (64 - r))
+    x.rotate_left(r)
 }
 
 fn build_gear_table() -> [u64; 256] {
@@ -180,17 +290,10 @@ fn persist_block_if_needed<P: AsRef<Path>>(
     block: &FileBlock,
     store_root: P,
 ) -> std::io::Result<PathBuf> {
-    let id = &block.id;
-    let p1 = &id[0..2];
-    let p2 = &id[2..4];
-    let p3 = &id[4..6];
-    let mut dir = PathBuf::from(store_root.as_ref());
-    dir.push(p1);
-    dir.push(p2);
-    dir.push(p3);
-    fs::create_dir_all(&dir)?;
-    let mut path = dir;
-    path.push(id);
+    let path = block_path(store_root, &block.id).ok_or_else(|| invalid_block_id(&block.id))?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
 
     if path.exists() {
         return Ok(path);
@@ -200,3 +303,292 @@ fn persist_block_if_needed<P: AsRef<Path>>(
     f.write_all(&block.block_data)?;
     Ok(path)
 }
+
+/// Read a block persisted under `store_root` straight from disk, without caching.
+pub fn read_block<P: AsRef<Path>>(store_root: P, id: &str) -> std::io::Result<Vec<u8>> {
+    let path = block_path(store_root, id).ok_or_else(|| invalid_block_id(id))?;
+    fs::read(path)
+}
+
+/// Location of a block in the 3-level layout, `None` if `id` is not a hex id.
+fn block_path<P: AsRef<Path>>(store_root: P, id: &str) -> Option<PathBuf> {
+    if id.len() < 6 || !id.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+    let mut path = PathBuf::from(store_root.as_ref());
+    path.push(&id[0..2]);
+    path.push(&id[2..4]);
+    path.push(&id[4..6]);
+    path.push(id);
+    Some(path)
+}
+
+fn invalid_block_id(id: &str) -> std::io::Error {
+    std::io::Error::new(
+        std::io::ErrorKind::InvalidInput,
+        format!("invalid block id: {id:?}"),
+    )
+}
+
+/// Content-addressable block store with an in-memory LRU cache in front of the
+/// on-disk layout written by [`chunk_and_store_file`].
+///
+/// Blocks are immutable once written, so cached entries never go stale.
+pub struct BlockStore {
+    lru: Mutex<LruCache<String, Arc<Vec<u8>>>>,
+    root: PathBuf,
+}
+
+impl BlockStore {
+    /// Store rooted at `root` caching up to [`DEFAULT_BLOCK_CACHE_CAPACITY`] blocks.
+    pub fn new<P: AsRef<Path>>(root: P) -> Self {
+        Self::with_capacity(
+            root,
+            NonZeroUsize::new(DEFAULT_BLOCK_CACHE_CAPACITY).unwrap(),
+        )
+    }
+
+    pub fn with_capacity<P: AsRef<Path>>(root: P, capacity: NonZeroUsize) -> Self {
+        Self {
+            lru: Mutex::new(LruCache::new(capacity)),
+            root: root.as_ref().to_path_buf(),
+        }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Look up a block, reading it from disk and caching it on a miss.
+    ///
+    /// Returns `None` if the block does not exist or can not be read.
+    pub fn get(&self, id: &str) -> Option<Arc<Vec<u8>>> {
+        if let Some(data) = self.lru.lock().unwrap().get(id) {
+            return Some(Arc::clone(data));
+        }
+        // Read outside the lock so a slow disk does not block cache hits.
+        let data = Arc::new(read_block(&self.root, id).ok()?);
+        self.lru
+            .lock()
+            .unwrap()
+            .put(id.to_string(), Arc::clone(&data));
+        Some(data)
+    }
+
+    /// Persist `block` if it is not on disk yet and cache its content.
+    pub fn put(&self, block: FileBlock) -> std::io::Result<PathBuf> {
+        let path = persist_block_if_needed(&block, &self.root)?;
+        self.lru
+            .lock()
+            .unwrap()
+            .put(block.id, Arc::new(block.block_data));
+        Ok(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_options_are_valid() {
+        assert_eq!(ChunkingOptions::default().validate(), Ok(()));
+    }
+
+    #[test]
+    fn invalid_options_are_rejected() {
+        let options = ChunkingOptions {
+            cdc_min_size: 64,
+            cdc_avg_size: 32,
+            ..Default::default()
+        };
+        assert_eq!(
+            options.validate(),
+            Err(ChunkingOptionsError::MinExceedsAvg { min: 64, avg: 32 })
+        );
+
+        let options = ChunkingOptions {
+            cdc_avg_size: 128 * 1024,
+            ..Default::default()
+        };
+        assert_eq!(
+            options.validate(),
+            Err(ChunkingOptionsError::AvgExceedsMax {
+                avg: 128 * 1024,
+                max: 64 * 1024
+            })
+        );
+
+        let options = ChunkingOptions {
+            cdc_window_size: 0,
+            ..Default::default()
+        };
+        assert_eq!(
+            options.validate(),
+            Err(ChunkingOptionsError::WindowSizeZero)
+        );
+
+        let options = ChunkingOptions {
+            fixed_block_size: 0,
+            ..Default::default()
+        };
+        assert_eq!(
+            options.validate(),
+            Err(ChunkingOptionsError::FixedBlockSizeZero)
+        );
+    }
+
+    #[test]
+    fn builder_validates_each_setter() {
+        let err = ChunkingOptions::builder()
+            .with_cdc_min_size(48 * 1024)
+            .unwrap_err();
+        assert_eq!(
+            err,
+            ChunkingOptionsError::MinExceedsAvg {
+                min: 48 * 1024,
+                avg: 32 * 1024
+            }
+        );
+        assert!(matches!(
+            ChunkingOptions::builder().with_cdc_avg_size(1024 * 1024),
+            Err(ChunkingOptionsError::AvgExceedsMax { .. })
+        ));
+        assert!(matches!(
+            ChunkingOptions::builder().with_cdc_max_size(1024),
+            Err(ChunkingOptionsError::AvgExceedsMax { .. })
+        ));
+        assert_eq!(
+            ChunkingOptions::builder()
+                .with_cdc_window_size(0)
+                .unwrap_err(),
+            ChunkingOptionsError::WindowSizeZero
+        );
+
+        let options = ChunkingOptions::builder()
+            .with_cdc_max_size(1024 * 1024)
+            .and_then(|b| b.with_cdc_avg_size(256 * 1024))
+            .and_then(|b| b.with_cdc_min_size(64 * 1024))
+            .and_then(|b| b.with_cdc_window_size(64))
+            .unwrap()
+            .build();
+        assert_eq!(
+            (
+                options.cdc_min_size,
+                options.cdc_avg_size,
+                options.cdc_max_size,
+                options.cdc_window_size
+            ),
+            (64 * 1024, 256 * 1024, 1024 * 1024, 64)
+        );
+    }
+
+    #[test]
+    fn invalid_options_fail_chunking() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("source");
+        fs::write(&source, b"content").unwrap();
+        let options = ChunkingOptions {
+            cdc_window_size: 0,
+            ..Default::default()
+        };
+        let err = chunk_and_store_file(&source, dir.path().join("store"), &options).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn block_store_serves_blocks_from_cache_and_disk() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = BlockStore::new(dir.path());
+        let block = FileBlock::from_bytes(b"block content".to_vec());
+        let id = block.id.clone();
+        let path = store.put(block).unwrap();
+
+        assert_eq!(store.get(&id).unwrap().as_slice(), b"block content");
+        // Cached blocks do not touch the disk again.
+        fs::remove_file(&path).unwrap();
+        assert_eq!(store.get(&id).unwrap().as_slice(), b"block content");
+
+        // A fresh store only has the disk to fall back to.
+        let block = FileBlock::from_bytes(b"on disk".to_vec());
+        let id = block.id.clone();
+        persist_block_if_needed(&block, dir.path()).unwrap();
+        let fresh = BlockStore::new(dir.path());
+        assert_eq!(fresh.get(&id).unwrap().as_slice(), b"on disk");
+        assert_eq!(read_block(dir.path(), &id).unwrap(), b"on disk");
+
+        assert!(fresh.get(&"0".repeat(64)).is_none());
+        assert!(fresh.get("../../etc").is_none());
+    }
+
+    #[test]
+    fn evicted_blocks_are_read_back_from_disk() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = BlockStore::with_capacity(dir.path(), NonZeroUsize::new(1).unwrap());
+        let first = FileBlock::from_bytes(b"first".to_vec());
+        let second = FileBlock::from_bytes(b"second".to_vec());
+        let first_id = first.id.clone();
+        let second_id = second.id.clone();
+        store.put(first).unwrap();
+        store.put(second).unwrap();
+
+        assert_eq!(store.get(&first_id).unwrap().as_slice(), b"first");
+        assert_eq!(store.get(&second_id).unwrap().as_slice(), b"second");
+    }
+
+    fn arb_options() -> impl proptest::strategy::Strategy<Value = ChunkingOptions> {
+        use proptest::prelude::*;
+
+        (
+            0usize..4096,
+            0usize..8192,
+            0usize..200,
+            0usize..2048,
+            prop_oneof![0usize..4096, Just(usize::MAX)],
+            0usize..8192,
+        )
+            .prop_map(
+                |(
+                    fixed_block_size,
+                    small_file_threshold,
+                    cdc_window_size,
+                    cdc_min_size,
+                    cdc_avg_size,
+                    cdc_max_size,
+                )| ChunkingOptions {
+                    fixed_block_size,
+                    small_file_threshold,
+                    cdc_window_size,
+                    cdc_min_size,
+                    cdc_avg_size,
+                    cdc_max_size,
+                },
+            )
+    }
+
+    proptest::proptest! {
+        #![proptest_config(proptest::prelude::ProptestConfig::with_cases(64))]
+
+        #[test]
+        fn chunking_never_panics(
+            options in arb_options(),
+            data in proptest::collection::vec(proptest::prelude::any::<u8>(), 0..8192),
+        ) {
+            let dir = tempfile::tempdir().unwrap();
+            let source = dir.path().join("source");
+            fs::write(&source, &data).unwrap();
+
+            match chunk_and_store_file(&source, dir.path().join("store"), &options) {
+                Ok(blocks) => {
+                    proptest::prop_assert!(options.validate().is_ok());
+                    let joined: Vec<u8> = blocks.iter().flat_map(|b| b.block_data.clone()).collect();
+                    proptest::prop_assert_eq!(joined, data);
+                }
+                Err(e) => {
+                    proptest::prop_assert_eq!(e.kind(), std::io::ErrorKind::InvalidInput);
+                    proptest::prop_assert!(options.validate().is_err());
+                }
+            }
+        }
+    }
+}
@@ -1,6 +1,6 @@
 use std::collections::{HashMap, HashSet};
 
-use super::{construct_tree_from_changelist, FileTree, FileTreeResult};
+use super::{FileTree, FileTreeResult, construct_tree_from_changelist};
 
 /// Branch 级别的 DepotTree 状态。
 ///
@@ -17,12 +17,7 @@ pub struct BranchDepotState {
 
 impl BranchDepotState {
     /// 缓存某个 changelist + 路径通配下的文件树。
-    pub fn cache_file_tree(
-        &mut self,
-        changelist_id: i64,
-        depot_wildcard: &str,
-        tree: FileTree,
-    ) {
+    pub fn cache_file_tree(&mut self, changelist_id: i64, depot_wildcard: &str, tree: FileTree) {
         self.file_tree_cache
             .insert((changelist_id, depot_wildcard.to_string()), tree);
     }
@@ -49,6 +44,95 @@ impl BranchDepotState {
     }
 }
 
+/// 文件锁的模式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum LockMode {
+    /// 共享读锁：多个读锁可以共存，但会阻止写锁（即阻止提交）
+    Read,
+    /// 独占写锁：仅在文件没有任何读锁、写锁时才能获得
+    #[default]
+    Write,
+}
+
+/// 单个文件上的锁状态
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LockEntry {
+    /// 文件的 depot 路径，通过 `try_lock_files` 加锁时路径未知，为空
+    pub path: String,
+    /// 持有写锁的 ticket
+    pub write_ticket: Option<String>,
+    /// 持有读锁的 ticket 集合
+    pub read_tickets: HashSet<String>,
+}
+
+impl LockEntry {
+    /// 判断能否以指定模式加锁。
+    ///
+    /// 读锁只与写锁冲突；写锁与任何已有的锁冲突。
+    pub fn can_grant(&self, mode: LockMode) -> bool {
+        match mode {
+            LockMode::Read => self.write_ticket.is_none(),
+            LockMode::Write => self.write_ticket.is_none() && self.read_tickets.is_empty(),
+        }
+    }
+
+    /// 以指定模式记录 ticket 持有的锁，调用方需要先通过 `can_grant` 检查。
+    pub fn grant(&mut self, mode: LockMode, ticket: &str) {
+        match mode {
+            LockMode::Read => {
+                self.read_tickets.insert(ticket.to_string());
+            }
+            LockMode::Write => self.write_ticket = Some(ticket.to_string()),
+        }
+    }
+
+    /// 释放 ticket 持有的锁（无论读写）。
+    pub fn release(&mut self, ticket: &str) {
+        if self.write_ticket.as_deref() == Some(ticket) {
+            self.write_ticket = None;
+        }
+        self.read_tickets.remove(ticket);
+    }
+
+    /// 是否已经没有任何锁
+    pub fn is_free(&self) -> bool {
+        self.write_ticket.is_none() && self.read_tickets.is_empty()
+    }
+
+    /// 当前的锁模式，存在写锁时为 `Write`，否则为 `Read`
+    pub fn mode(&self) -> LockMode {
+        if self.write_ticket.is_some() {
+            LockMode::Write
+        } else {
+            LockMode::Read
+        }
+    }
+
+    /// 持有锁的 ticket，按字典序排列，匿名（空）ticket 不列出
+    pub fn holders(&self) -> Vec<String> {
+        let mut holders: Vec<String> = self
+            .write_ticket
+            .iter()
+            .chain(self.read_tickets.iter())
+            .filter(|t| !t.is_empty())
+            .cloned()
+            .collect();
+        holders.sort();
+        holders
+    }
+}
+
+/// 被锁定的文件
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LockedFileInfo {
+    pub file_id: String,
+    /// 文件的 depot 路径，通过 `try_lock_files` 加锁时路径未知，为空
+    pub path: String,
+    pub mode: LockMode,
+    /// 持有锁的 ticket，匿名加锁时为空
+    pub holders: Vec<String>,
+}
+
 /// DepotTree：在内存中维护所有分支的 Depot 视图状态。
 ///
 /// 该结构本身不涉及并发控制，也不直接访问数据库，仅作为
@@ -57,7 +141,7 @@ impl BranchDepotState {
 pub struct DepotTree {
     branches: HashMap<String, BranchDepotState>,
     /// 全局文件锁集合，key = (branch_id, file_id)
-    locked_files: HashSet<(String, String)>,
+    locked_files: HashMap<(String, String), LockEntry>,
 }
 
 impl DepotTree {
@@ -98,25 +182,61 @@ impl DepotTree {
     ///
     /// - `locked`：本次成功加锁的文件 ID 列表；
     /// - `conflicted`：已经被其他操作锁定、无法加锁的文件 ID 列表。
-    pub fn try_lock_files<I>(
+    pub fn try_lock_files<I>(&mut self, branch_id: &str, file_ids: I) -> (Vec<String>, Vec<String>)
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        self.try_lock_files_with_paths(
+            branch_id,
+            file_ids
+                .into_iter()
+                .map(|fid| (fid.as_ref().to_string(), String::new())),
+        )
+    }
+
+    /// 为指定分支的一组文件尝试加锁，同时记录文件路径，供 `list_locked_files` 展示。
+    ///
+    /// `files` 的元素为 (文件 ID, 文件路径)，返回值与 `try_lock_files` 相同。
+    pub fn try_lock_files_with_paths<I>(
         &mut self,
         branch_id: &str,
-        file_ids: I,
+        files: I,
     ) -> (Vec<String>, Vec<String>)
     where
-        I: IntoIterator,
-        I::Item: AsRef<str>,
+        I: IntoIterator<Item = (String, String)>,
+    {
+        self.try_lock_files_with_mode(branch_id, files, LockMode::Write, "")
+    }
+
+    /// 以指定模式为一组文件加锁，锁记录在 `ticket` 名下。
+    ///
+    /// 多个读锁可以共存；写锁仅在文件没有任何读锁、写锁时才能获得。
+    /// 只要有一个文件冲突，本次就不加任何锁。
+    pub fn try_lock_files_with_mode<I>(
+        &mut self,
+        branch_id: &str,
+        files: I,
+        mode: LockMode,
+        ticket: &str,
+    ) -> (Vec<String>, Vec<String>)
+    where
+        I: IntoIterator<Item = (String, String)>,
     {
         let branch = branch_id.to_string();
-        let mut unique_ids = HashSet::new();
-        for fid in file_ids {
-            unique_ids.insert(fid.as_ref().to_string());
+        let mut unique_files = HashMap::new();
+        for (fid, path) in files {
+            unique_files.entry(fid).or_insert(path);
         }
 
         // 先检查是否存在已被锁定的 (branch, file) 组合
-        let conflicted: Vec<String> = unique_ids
-            .iter()
-            .filter(|fid| self.locked_files.contains(&(branch.clone(), (*fid).clone())))
+        let conflicted: Vec<String> = unique_files
+            .keys()
+            .filter(|fid| {
+                self.locked_files
+                    .get(&(branch.clone(), (*fid).clone()))
+                    .is_some_and(|entry| !entry.can_grant(mode))
+            })
             .cloned()
             .collect();
 
@@ -126,14 +246,39 @@ impl DepotTree {
         }
 
         // 所有文件均可加锁，一次性加锁
-        for fid in &unique_ids {
-            self.locked_files.insert((branch.clone(), fid.clone()));
+        let mut locked = Vec::with_capacity(unique_files.len());
+        for (fid, path) in unique_files {
+            let entry = self
+                .locked_files
+                .entry((branch.clone(), fid.clone()))
+                .or_default();
+            if entry.path.is_empty() {
+                entry.path = path;
+            }
+            entry.grant(mode, ticket);
+            locked.push(fid);
         }
-
-        let locked = unique_ids.into_iter().collect();
         (locked, Vec::new())
     }
 
+    /// 释放 `ticket` 在指定分支下一组文件上持有的锁，其他 ticket 的锁不受影响。
+    pub fn unlock_files_for_ticket<I>(&mut self, branch_id: &str, file_ids: I, ticket: &str)
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        let branch = branch_id.to_string();
+        for fid in file_ids {
+            let key = (branch.clone(), fid.as_ref().to_string());
+            if let Some(entry) = self.locked_files.get_mut(&key) {
+                entry.release(ticket);
+                if entry.is_free() {
+                    self.locked_files.remove(&key);
+                }
+            }
+        }
+    }
+
     /// 释放指定分支下一组文件的锁。
     pub fn unlock_files<I>(&mut self, branch_id: &str, file_ids: I)
     where
@@ -150,7 +295,33 @@ impl DepotTree {
     /// 查询某个文件在指定分支下当前是否已被锁定。
     pub fn is_locked(&self, branch_id: &str, file_id: &str) -> bool {
         self.locked_files
-            .contains(&(branch_id.to_string(), file_id.to_string()))
+            .contains_key(&(branch_id.to_string(), file_id.to_string()))
+    }
+
+    /// 列出指定分支下当前被锁定的所有文件，按路径与文件 ID 排序。
+    pub fn list_locked_files(&self, branch_id: &str) -> Vec<LockedFileInfo> {
+        let mut files: Vec<LockedFileInfo> = self
+            .locked_files
+            .iter()
+            .filter(|((branch, _), _)| branch == branch_id)
+            .map(|((_, file_id), entry)| LockedFileInfo {
+                file_id: file_id.clone(),
+                path: entry.path.clone(),
+                mode: entry.mode(),
+                holders: entry.holders(),
+            })
+            .collect();
+        files.sort_by(|a, b| (&a.path, &a.file_id).cmp(&(&b.path, &b.file_id)));
+        files
+    }
+
+    /// 强制释放某个文件的锁，不论是谁加的锁，供管理员处理残留的锁。
+    ///
+    /// 返回该文件之前是否处于锁定状态。
+    pub fn force_unlock(&mut self, branch_id: &str, file_id: &str) -> bool {
+        self.locked_files
+            .remove(&(branch_id.to_string(), file_id.to_string()))
+            .is_some()
     }
 
     /// 为指定分支缓存某个 changelist + 路径通配下的文件树。
@@ -257,8 +428,7 @@ mod tests {
     fn test_file_locking_basic() {
         let mut depot = DepotTree::new();
 
-        let (locked, conflicted) =
-            depot.try_lock_files("branch_main", ["f1", "f2", "f1"].as_ref());
+        let (locked, conflicted) = depot.try_lock_files("branch_main", ["f1", "f2", "f1"].as_ref());
         // f1、f2 应该被成功加锁，重复的 f1 不影响结果
         assert_eq!(locked.len(), 2);
         assert!(locked.contains(&"f1".to_string()));
@@ -278,6 +448,109 @@ mod tests {
         assert!(conflicted3.is_empty());
     }
 
+    #[test]
+    fn test_list_locked_files() {
+        let mut depot = DepotTree::new();
+
+        let (locked, conflicted) = depot.try_lock_files_with_paths(
+            "branch_main",
+            [
+                ("f2".to_string(), "//src/b.cpp".to_string()),
+                ("f1".to_string(), "//src/a.cpp".to_string()),
+                ("f3".to_string(), "//assets/c.png".to_string()),
+            ],
+        );
+        assert_eq!(locked.len(), 3);
+        assert!(conflicted.is_empty());
+        depot.try_lock_files("branch_other", ["f4"].as_ref());
+
+        let files = depot.list_locked_files("branch_main");
+        assert_eq!(files.len(), 3);
+        let paths: Vec<&str> = files.iter().map(|f| f.path.as_str()).collect();
+        assert_eq!(paths, vec!["//assets/c.png", "//src/a.cpp", "//src/b.cpp"]);
+        assert_eq!(files[1].file_id, "f1");
+
+        let other = depot.list_locked_files("branch_other");
+        assert_eq!(
+            other,
+            vec![LockedFileInfo {
+                file_id: "f4".to_string(),
+                path: String::new(),
+                mode: LockMode::Write,
+                holders: Vec::new(),
+            }]
+        );
+        assert!(depot.list_locked_files("branch_missing").is_empty());
+    }
+
+    #[test]
+    fn test_force_unlock() {
+        let mut depot = DepotTree::new();
+        depot.try_lock_files_with_paths(
+            "branch_main",
+            [("f1".to_string(), "//src/a.cpp".to_string())],
+        );
+
+        assert!(depot.force_unlock("branch_main", "f1"));
+        assert!(!depot.is_locked("branch_main", "f1"));
+        assert!(!depot.force_unlock("branch_main", "f1"));
+        assert!(depot.list_locked_files("branch_main").is_empty());
+    }
+
+    #[test]
+    fn test_concurrent_read_locks_succeed() {
+        let mut depot = DepotTree::new();
+        let file = || [("f1".to_string(), "//src/a.cpp".to_string())];
+
+        let (locked, conflicted) =
+            depot.try_lock_files_with_mode("branch_main", file(), LockMode::Read, "alice");
+        assert_eq!(locked, vec!["f1".to_string()]);
+        assert!(conflicted.is_empty());
+
+        let (locked, conflicted) =
+            depot.try_lock_files_with_mode("branch_main", file(), LockMode::Read, "bob");
+        assert_eq!(locked, vec!["f1".to_string()]);
+        assert!(conflicted.is_empty());
+
+        let files = depot.list_locked_files("branch_main");
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].mode, LockMode::Read);
+        assert_eq!(
+            files[0].holders,
+            vec!["alice".to_string(), "bob".to_string()]
+        );
+
+        // 读锁不能与写锁共存
+        let (_locked, conflicted) =
+            depot.try_lock_files_with_mode("branch_main", file(), LockMode::Write, "carol");
+        assert_eq!(conflicted, vec!["f1".to_string()]);
+    }
+
+    #[test]
+    fn test_write_lock_rejected_while_any_read_lock_held() {
+        let mut depot = DepotTree::new();
+        let file = || [("f1".to_string(), "//src/a.cpp".to_string())];
+
+        depot.try_lock_files_with_mode("branch_main", file(), LockMode::Read, "alice");
+        depot.try_lock_files_with_mode("branch_main", file(), LockMode::Read, "bob");
+
+        // 释放一个读锁后仍有读锁，写锁依然被拒绝
+        depot.unlock_files_for_ticket("branch_main", ["f1"], "alice");
+        let (locked, conflicted) = depot.try_lock_files("branch_main", ["f1"]);
+        assert!(locked.is_empty());
+        assert_eq!(conflicted, vec!["f1".to_string()]);
+
+        // 所有读锁释放后可以加写锁，写锁存在时读锁被拒绝
+        depot.unlock_files_for_ticket("branch_main", ["f1"], "bob");
+        assert!(!depot.is_locked("branch_main", "f1"));
+        let (locked, _) =
+            depot.try_lock_files_with_mode("branch_main", file(), LockMode::Write, "carol");
+        assert_eq!(locked, vec!["f1".to_string()]);
+        let (_, conflicted) =
+            depot.try_lock_files_with_mode("branch_main", file(), LockMode::Read, "alice");
+        assert_eq!(conflicted, vec!["f1".to_string()]);
+    }
+
     #[test]
     fn test_branch_isolation_for_locks() {
         let mut depot = DepotTree::new();
@@ -303,29 +576,39 @@ mod tests {
         depot.cache_file_tree("branch_main", 100, "//src/module/...", tree_a.clone());
         depot.cache_file_tree("branch_main", 200, "//src/module/...", tree_b.clone());
 
-        assert!(depot
-            .get_cached_file_tree("branch_main", 100, "//src/module/...")
-            .is_some());
-        assert!(depot
-            .get_cached_file_tree("branch_main", 200, "//src/module/...")
-            .is_some());
-        assert!(depot
-            .get_cached_file_tree("branch_main", 300, "//src/module/...")
-            .is_none());
+        assert!(
+            depot
+                .get_cached_file_tree("branch_main", 100, "//src/module/...")
+                .is_some()
+        );
+        assert!(
+            depot
+                .get_cached_file_tree("branch_main", 200, "//src/module/...")
+                .is_some()
+        );
+        assert!(
+            depot
+                .get_cached_file_tree("branch_main", 300, "//src/module/...")
+                .is_none()
+        );
 
         depot.clear_file_tree_cache_for_changelist("branch_main", 100);
-        assert!(depot
-            .get_cached_file_tree("branch_main", 100, "//src/module/...")
-            .is_none());
-        assert!(depot
-            .get_cached_file_tree("branch_main", 200, "//src/module/...")
-            .is_some());
+        assert!(
+            depot
+                .get_cached_file_tree("branch_main", 100, "//src/module/...")
+                .is_none()
+        );
+        assert!(
+            depot
+                .get_cached_file_tree("branch_main", 200, "//src/module/...")
+                .is_some()
+        );
 
         depot.clear_all_file_tree_cache("branch_main");
-        assert!(depot
-            .get_cached_file_tree("branch_main", 200, "//src/module/...")
-            .is_none());
+        assert!(
+            depot
+                .get_cached_file_tree("branch_main", 200, "//src/module/...")
+                .is_none()
+        );
     }
 }
-
-
@@ -1,5 +1,5 @@
 use serde::{Deserialize, Serialize};
-use std::collections::{BTreeMap, HashMap};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 
 use crate::metadata::{BranchDoc, ChangelistAction, ChangelistDoc, FileDoc, FileRevisionDoc};
 use crate::path::basic::{DepotPath, DepotPathWildcard};
@@ -8,7 +8,7 @@ use thiserror::Error;
 pub mod depot_tree;
 
 /// 文件树整体结构，描述某个根目录下的层级关系
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct FileTree {
     /// 根目录下的所有顶层节点
@@ -16,7 +16,7 @@ pub struct FileTree {
 }
 
 /// 文件树中的节点，包含目录与文件两种类型
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(tag = "nodeType", rename_all = "camelCase")]
 pub enum FileTreeNode {
     /// 目录节点
@@ -45,6 +45,93 @@ pub enum FileTreeNode {
     },
 }
 
+impl FileTree {
+    /// 序列化为 JSON，用于在本地缓存文件树快照
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::to_value(self).expect("FileTree 总是可以序列化为 JSON")
+    }
+
+    /// 从 `to_json` 的结果还原文件树
+    pub fn from_json(val: &serde_json::Value) -> Result<FileTree, serde_json::Error> {
+        FileTree::deserialize(val)
+    }
+
+    /// 深度优先遍历文件树，按节点顺序返回所有文件的完整路径
+    ///
+    /// 路径由从根到文件经过的目录名拼接而成，因此是相对于构建文件树时的基准路径的。
+    pub fn flatten(&self) -> Vec<DepotPath> {
+        fn walk(nodes: &[FileTreeNode], dirs: &mut Vec<String>, out: &mut Vec<DepotPath>) {
+            for node in nodes {
+                match node {
+                    FileTreeNode::Directory { name, children } => {
+                        dirs.push(name.clone());
+                        walk(children, dirs, out);
+                        dirs.pop();
+                    }
+                    FileTreeNode::File { name, .. } => out.push(DepotPath {
+                        dirs: dirs.clone(),
+                        file: name.clone(),
+                    }),
+                }
+            }
+        }
+
+        let mut out = Vec::new();
+        walk(&self.nodes, &mut Vec::new(), &mut out);
+        out
+    }
+
+    /// 由文件节点及其所在目录构建文件树，目录与文件都按名称排序
+    ///
+    /// 非文件节点会被忽略，同一目录下的同名文件只保留最后一个。
+    pub fn from_files<I>(files: I) -> FileTree
+    where
+        I: IntoIterator<Item = (Vec<String>, FileTreeNode)>,
+    {
+        let mut root = DirNode::default();
+        for (dirs, file_node) in files {
+            insert_file(&mut root, &dirs, file_node);
+        }
+        FileTree {
+            nodes: to_nodes(root),
+        }
+    }
+}
+
+/// 构建文件树时使用的中间结构，按目录层级组织文件
+#[derive(Default)]
+struct DirNode {
+    children: BTreeMap<String, DirNode>,
+    files: BTreeMap<String, FileTreeNode>,
+}
+
+fn insert_file(root: &mut DirNode, dir_parts: &[String], file_node: FileTreeNode) {
+    let mut current = root;
+    for part in dir_parts {
+        current = current.children.entry(part.clone()).or_default();
+    }
+    if let FileTreeNode::File { name, .. } = &file_node {
+        current.files.insert(name.clone(), file_node);
+    }
+}
+
+fn to_nodes(node: DirNode) -> Vec<FileTreeNode> {
+    let mut result = Vec::new();
+
+    // 目录（按名称排序）
+    for (name, child) in node.children {
+        let children = to_nodes(child);
+        result.push(FileTreeNode::Directory { name, children });
+    }
+
+    // 文件（按名称排序）
+    for (_name, file_node) in node.files {
+        result.push(file_node);
+    }
+
+    result
+}
+
 /// 构建文件树时可能出现的错误
 #[derive(Debug, Error)]
 pub enum FileTreeError {
@@ -72,7 +159,8 @@ pub type FileTreeResult<T> = Result<T, FileTreeError>;
 ///
 /// - `branch_id`：目标分支 ID。
 /// - `changelist_id`：目标 changelist ID。
-/// - `depot_wildcard`：类似 `//src/module/...` 的 depot 路径通配符，仅支持范围通配形式。
+/// - `depot_wildcard`：depot 路径通配符，支持类似 `//src/module/...` 的范围通配与 `r://` 开头的正则通配；
+///   正则通配可通过命名分组 `dirs` 与 `file` 指定文件在树中的目录与文件名，否则使用完整路径。
 /// - `get_*` 系列函数：由调用方提供的访问后端存储的函数，用于按 ID 读取对象。
 #[allow(non_snake_case)]
 pub fn construct_tree_from_changelist<GB, GC, GF, GR>(
@@ -107,19 +195,10 @@ where
         });
     }
 
-    // 2. 解析 depot 路径通配符，只支持范围通配（Range）形式
+    // 2. 解析 depot 路径通配符
     let wildcard = DepotPathWildcard::parse(depot_wildcard)
         .map_err(|e| FileTreeError::InvalidDepotPathWildcard(e.to_string()))?;
 
-    let range_wildcard = match wildcard {
-        DepotPathWildcard::Range(r) => r,
-        DepotPathWildcard::Regex(_) => {
-            return Err(FileTreeError::InvalidDepotPathWildcard(
-                "构建文件树暂不支持正则通配符，请使用 //path/... 形式的范围通配".to_string(),
-            ));
-        }
-    };
-
     // 3. 自顶向下回溯 changelist 链，计算在目标 changelist 下可见的文件最新 revision
     //
     // key: file_id
@@ -132,9 +211,7 @@ where
     let mut steps: u32 = 0;
 
     while current_id > 0 {
-        let cl = match get_changelist(current_id)
-            .map_err(FileTreeError::Backend)?
-        {
+        let cl = match get_changelist(current_id).map_err(FileTreeError::Backend)? {
             Some(c) => c,
             None => break, // 提前结束：历史链中断
         };
@@ -173,46 +250,6 @@ where
     }
 
     // 4. 使用中间结构按目录层级组织文件，然后再转换为 FileTree
-    #[derive(Default)]
-    struct DirNode {
-        children: BTreeMap<String, DirNode>,
-        files: BTreeMap<String, FileTreeNode>,
-    }
-
-    fn insert_file(
-        root: &mut DirNode,
-        dir_parts: &[String],
-        file_node: FileTreeNode,
-    ) {
-        let mut current = root;
-        for part in dir_parts {
-            current = current
-                .children
-                .entry(part.clone())
-                .or_insert_with(DirNode::default);
-        }
-        if let FileTreeNode::File { name, .. } = &file_node {
-            current.files.insert(name.clone(), file_node);
-        }
-    }
-
-    fn to_nodes(node: DirNode) -> Vec<FileTreeNode> {
-        let mut result = Vec::new();
-
-        // 目录（按名称排序）
-        for (name, child) in node.children {
-            let children = to_nodes(child);
-            result.push(FileTreeNode::Directory { name, children });
-        }
-
-        // 文件（按名称排序）
-        for (_name, file_node) in node.files {
-            result.push(file_node);
-        }
-
-        result
-    }
-
     let mut root = DirNode::default();
 
     // 5. 遍历可见文件，过滤路径并插入树中
@@ -222,9 +259,7 @@ where
             None => continue, // 在目标 changelist 下已被删除
         };
 
-        let revision = match get_file_revision(&revision_id)
-            .map_err(FileTreeError::Backend)?
-        {
+        let revision = match get_file_revision(&revision_id).map_err(FileTreeError::Backend)? {
             Some(r) => r,
             None => {
                 return Err(FileTreeError::Backend(format!(
@@ -238,14 +273,10 @@ where
             continue;
         }
 
-        let file = match get_file(&file_id)
-            .map_err(FileTreeError::Backend)?
-        {
+        let file = match get_file(&file_id).map_err(FileTreeError::Backend)? {
             Some(f) => f,
             None => {
-                return Err(FileTreeError::Backend(format!(
-                    "找不到文件，id={file_id}"
-                )));
+                return Err(FileTreeError::Backend(format!("找不到文件，id={file_id}")));
             }
         };
 
@@ -253,17 +284,14 @@ where
         let depot_path =
             DepotPath::parse(&file.path).map_err(|e| FileTreeError::Backend(e.to_string()))?;
 
-        // 使用范围通配符过滤路径，并获取相对于基准路径的目录差分
-        let diff = match range_wildcard.match_and_get_diff(&depot_path) {
+        // 使用通配符过滤路径，并获取相对于基准路径的目录差分
+        let diff = match wildcard.match_and_get_diff(&depot_path) {
             Some(d) => d,
             None => continue, // 不在指定路径下，跳过
         };
 
-        // diff 即为相对于基准路径（例如 //src/module）下的子目录列表
-        let relative_dirs: Vec<String> = diff.to_vec();
-
         let file_node = FileTreeNode::File {
-            name: depot_path.file,
+            name: diff.file,
             file_id: file.id,
             reivision_id: revision.id,
             changelist_id: revision.changelist_id,
@@ -272,7 +300,7 @@ where
             revision_created_at: revision.created_at,
         };
 
-        insert_file(&mut root, &relative_dirs, file_node);
+        insert_file(&mut root, &diff.dirs, file_node);
     }
 
     Ok(FileTree {
@@ -280,11 +308,112 @@ where
     })
 }
 
+/// 两棵文件树中都存在、但 revision 不同的文件
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiffedFile {
+    pub path: DepotPath,
+    pub old_revision_id: String,
+    pub new_revision_id: String,
+}
+
+/// 两棵文件树之间的差异，路径相对于构建文件树时的基准路径
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TreeDiff {
+    /// 只存在于新树中的文件
+    pub added: Vec<DepotPath>,
+    /// 只存在于旧树中的文件
+    pub removed: Vec<DepotPath>,
+    /// 两棵树中都存在但 revision 不同的文件
+    pub modified: Vec<DiffedFile>,
+}
+
+/// 比较旧文件树 `a` 与新文件树 `b`。
+///
+/// 逐层按名称匹配同一目录下的节点：只在一侧存在的目录，其下所有文件都计为新增或删除；
+/// 同名的文件与目录视为不同的节点。两侧都存在的文件按 revision_id 判断是否修改。
+/// 结果按目录层级、名称排序。
+pub fn diff_trees(a: &FileTree, b: &FileTree) -> TreeDiff {
+    /// 同一目录下的节点，键为 (名称, 是否为目录)
+    fn index(nodes: &[FileTreeNode]) -> BTreeMap<(&str, bool), &FileTreeNode> {
+        nodes
+            .iter()
+            .map(|node| match node {
+                FileTreeNode::Directory { name, .. } => ((name.as_str(), true), node),
+                FileTreeNode::File { name, .. } => ((name.as_str(), false), node),
+            })
+            .collect()
+    }
+
+    /// 收集节点下的所有文件
+    fn collect(node: &FileTreeNode, dirs: &mut Vec<String>, out: &mut Vec<DepotPath>) {
+        match node {
+            FileTreeNode::Directory { name, children } => {
+                dirs.push(name.clone());
+                for child in children {
+                    collect(child, dirs, out);
+                }
+                dirs.pop();
+            }
+            FileTreeNode::File { name, .. } => out.push(DepotPath {
+                dirs: dirs.clone(),
+                file: name.clone(),
+            }),
+        }
+    }
+
+    fn walk(a: &[FileTreeNode], b: &[FileTreeNode], dirs: &mut Vec<String>, diff: &mut TreeDiff) {
+        let a = index(a);
+        let b = index(b);
+        let keys: BTreeSet<(&str, bool)> = a.keys().chain(b.keys()).copied().collect();
+        for key in keys {
+            match (a.get(&key), b.get(&key)) {
+                (
+                    Some(FileTreeNode::Directory {
+                        name,
+                        children: old,
+                    }),
+                    Some(FileTreeNode::Directory { children: new, .. }),
+                ) => {
+                    dirs.push(name.clone());
+                    walk(old, new, dirs, diff);
+                    dirs.pop();
+                }
+                (
+                    Some(FileTreeNode::File {
+                        name,
+                        reivision_id: old,
+                        ..
+                    }),
+                    Some(FileTreeNode::File {
+                        reivision_id: new, ..
+                    }),
+                ) if old != new => diff.modified.push(DiffedFile {
+                    path: DepotPath {
+                        dirs: dirs.clone(),
+                        file: name.clone(),
+                    },
+                    old_revision_id: old.clone(),
+                    new_revision_id: new.clone(),
+                }),
+                (Some(node), None) => collect(node, dirs, &mut diff.removed),
+                (None, Some(node)) => collect(node, dirs, &mut diff.added),
+                // 两侧 revision 相同的文件；键中包含节点类型，两侧同键的节点类型一定相同
+                _ => {}
+            }
+        }
+    }
+
+    let mut diff = TreeDiff::default();
+    walk(&a.nodes, &b.nodes, &mut Vec::new(), &mut diff);
+    diff
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::metadata::{
-        BranchMetadata, ChangelistChange, ChangelistMetadata, FileMetadata, FileRevisionMetadata,
+        BranchMetadata, ChangelistChange, ChangelistMetadata, CompressionType, FileMetadata,
+        FileRevisionMetadata,
     };
     use std::collections::HashMap;
 
@@ -311,8 +440,11 @@ mod tests {
             created_at: 0,
             created_by: "userA".to_string(),
             head_changelist_id: 300,
+            is_protected: false,
             metadata: BranchMetadata {
                 description: "main".to_string(),
+                owners: vec![],
+                description_regex: None,
             },
         }
     }
@@ -366,6 +498,8 @@ mod tests {
                     hash: "h1".to_string(),
                     is_binary: false,
                     language: "cpp".to_string(),
+                    tags: vec![],
+                    compression_type: CompressionType::None,
                 },
             },
         );
@@ -387,6 +521,8 @@ mod tests {
                     hash: "h2".to_string(),
                     is_binary: false,
                     language: "cpp".to_string(),
+                    tags: vec![],
+                    compression_type: CompressionType::None,
                 },
             },
         );
@@ -408,6 +544,8 @@ mod tests {
                     hash: "h3".to_string(),
                     is_binary: false,
                     language: "cpp".to_string(),
+                    tags: vec![],
+                    compression_type: CompressionType::None,
                 },
             },
         );
@@ -575,8 +713,11 @@ mod tests {
             created_at: 0,
             created_by: "userLong".to_string(),
             head_changelist_id: 10,
+            is_protected: false,
             metadata: BranchMetadata {
                 description: "long random branch".to_string(),
+                owners: vec![],
+                description_regex: None,
             },
         };
 
@@ -664,6 +805,8 @@ mod tests {
                             hash: format!("h_{rev_id}"),
                             is_binary: false,
                             language: "txt".to_string(),
+                            tags: vec![],
+                            compression_type: CompressionType::None,
                         },
                     },
                 );
@@ -704,6 +847,8 @@ mod tests {
                                 hash: format!("h_{rev_id}"),
                                 is_binary: false,
                                 language: "txt".to_string(),
+                                tags: vec![],
+                                compression_type: CompressionType::None,
                             },
                         },
                     );
@@ -712,7 +857,9 @@ mod tests {
                 }
             };
 
-            let revision_str = revision_id_opt.clone().unwrap_or_else(|| format!("{file_id}_del{i}"));
+            let revision_str = revision_id_opt
+                .clone()
+                .unwrap_or_else(|| format!("{file_id}_del{i}"));
 
             cls.insert(
                 i,
@@ -804,8 +951,11 @@ mod tests {
             created_at: 0,
             created_by: "userLarge".to_string(),
             head_changelist_id: 1,
+            is_protected: false,
             metadata: BranchMetadata {
                 description: "large branch".to_string(),
+                owners: vec![],
+                description_regex: None,
             },
         };
 
@@ -822,18 +972,9 @@ mod tests {
             let is_module_file = i % 2 == 0;
             let path = if is_module_file {
                 // 生成多层目录结构，增加树的深度和广度
-                format!(
-                    "//src/module/dir_{}/sub_{}/file_{}.txt",
-                    i % 5,
-                    i % 3,
-                    i
-                )
+                format!("//src/module/dir_{}/sub_{}/file_{}.txt", i % 5, i % 3, i)
             } else {
-                format!(
-                    "//src/other/dir_{}/file_{}.txt",
-                    i % 4,
-                    i
-                )
+                format!("//src/other/dir_{}/file_{}.txt", i % 4, i)
             };
 
             files.insert(
@@ -866,6 +1007,8 @@ mod tests {
                         hash: format!("h_{rev_id}"),
                         is_binary: false,
                         language: "txt".to_string(),
+                        tags: vec![],
+                        compression_type: CompressionType::None,
                     },
                 },
             );
@@ -925,7 +1068,10 @@ mod tests {
         .expect("constructTreeFromHead should succeed on large scale");
 
         // 打印大规模文件树，便于观察目录结构与节点数量
-        println!("=== FileTree for branch_large @ CL1 (large scale) ===\n{:#?}", tree);
+        println!(
+            "=== FileTree for branch_large @ CL1 (large scale) ===\n{:#?}",
+            tree
+        );
 
         let mut tree_files: HashMap<String, String> = HashMap::new();
         collect_files(&tree.nodes, &mut tree_files);
@@ -959,5 +1105,348 @@ mod tests {
         assert_eq!(tree_files.len(), expected_module_files);
         assert!(expected_module_files > 10); // 简单检查规模足够大
     }
-}
 
+    /// 将文件树展平为 file_id -> 文件在树中的路径
+    fn collect_paths(nodes: &[FileTreeNode], prefix: &str, out: &mut HashMap<String, String>) {
+        for node in nodes {
+            match node {
+                FileTreeNode::Directory { name, children } => {
+                    collect_paths(children, &format!("{prefix}{name}/"), out);
+                }
+                FileTreeNode::File { name, file_id, .. } => {
+                    out.insert(file_id.clone(), format!("{prefix}{name}"));
+                }
+            }
+        }
+    }
+
+    /// 简单的线性同余伪随机数生成器，保证每次运行生成的用例一致
+    struct Lcg(u64);
+
+    impl Lcg {
+        fn next(&mut self, bound: usize) -> usize {
+            self.0 = self
+                .0
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            ((self.0 >> 33) as usize) % bound
+        }
+    }
+
+    #[test]
+    fn construct_tree_regex_wildcard_property() {
+        // 对随机生成的路径集合，树中的文件应恰好是被正则完整匹配的路径，
+        // 且位置由命名分组 dirs、file 决定，没有分组时为完整路径
+        let cases: Vec<(&str, fn(&str) -> String)> = vec![
+            (r"src/.*\.rs", |path| path.to_string()),
+            (r"(?:src|lib)/[ab]/.*", |path| path.to_string()),
+            (r"src/(?P<dirs>(?:[^/]+/)*)(?P<file>[^/]+\.txt)", |path| {
+                path.strip_prefix("src/").unwrap().to_string()
+            }),
+        ];
+        let roots = ["src", "lib", "doc"];
+        let dirs = ["a", "b", "c"];
+        let exts = ["rs", "txt", "md"];
+
+        for seed in 0..20u64 {
+            let mut rng = Lcg(seed);
+            let branch = BranchDoc {
+                id: "branch_regex".to_string(),
+                created_at: 0,
+                created_by: "userRegex".to_string(),
+                head_changelist_id: 1,
+                is_protected: false,
+                metadata: BranchMetadata {
+                    description: "regex branch".to_string(),
+                    owners: vec![],
+                    description_regex: None,
+                },
+            };
+
+            let mut files: HashMap<String, FileDoc> = HashMap::new();
+            let mut revs: HashMap<String, FileRevisionDoc> = HashMap::new();
+            let mut changes: Vec<ChangelistChange> = Vec::new();
+            for i in 0..30 {
+                let mut path = format!("//{}", roots[rng.next(roots.len())]);
+                for _ in 0..rng.next(4) {
+                    path.push_str(&format!("/{}", dirs[rng.next(dirs.len())]));
+                }
+                path.push_str(&format!("/file_{i}.{}", exts[rng.next(exts.len())]));
+
+                let file_id = format!("f{i}");
+                let rev_id = format!("rev_{i}");
+                files.insert(
+                    file_id.clone(),
+                    FileDoc {
+                        id: file_id.clone(),
+                        path,
+                        seen_on_branches: vec!["branch_regex".to_string()],
+                        created_at: 0,
+                        metadata: FileMetadata {
+                            first_introduced_by: "userRegex".to_string(),
+                        },
+                    },
+                );
+                revs.insert(
+                    rev_id.clone(),
+                    FileRevisionDoc {
+                        id: rev_id.clone(),
+                        branch_id: "branch_regex".to_string(),
+                        file_id: file_id.clone(),
+                        changelist_id: 1,
+                        binary_id: vec![format!("blob_{rev_id}")],
+                        parent_revision_id: String::new(),
+                        size: 1,
+                        is_delete: false,
+                        created_at: 1,
+                        metadata: FileRevisionMetadata {
+                            file_mode: "644".to_string(),
+                            hash: format!("h_{rev_id}"),
+                            is_binary: false,
+                            language: "txt".to_string(),
+                            tags: vec![],
+                            compression_type: CompressionType::None,
+                        },
+                    },
+                );
+                changes.push(ChangelistChange {
+                    file: file_id,
+                    action: ChangelistAction::Create,
+                    revision: rev_id,
+                });
+            }
+            let cl = ChangelistDoc {
+                id: 1,
+                parent_changelist_id: 0,
+                branch_id: "branch_regex".to_string(),
+                author: "userRegex".to_string(),
+                description: "regex create".to_string(),
+                files_count: changes.len() as i64,
+                changes,
+                committed_at: 1,
+                metadata: ChangelistMetadata { labels: vec![] },
+            };
+
+            for (pattern, position) in &cases {
+                let tree = construct_tree_from_changelist(
+                    "branch_regex",
+                    &format!("r://{pattern}"),
+                    1,
+                    |_| Ok(Some(branch.clone())),
+                    |id| Ok((id == 1).then(|| cl.clone())),
+                    |id| Ok(files.get(id).cloned()),
+                    |id| Ok(revs.get(id).cloned()),
+                )
+                .expect("constructTreeFromHead should succeed with regex wildcard");
+
+                let mut tree_paths: HashMap<String, String> = HashMap::new();
+                collect_paths(&tree.nodes, "", &mut tree_paths);
+
+                let regex = regex::Regex::new(&format!("^(?:{pattern})$")).unwrap();
+                let expected: HashMap<String, String> = files
+                    .values()
+                    .filter(|file| regex.is_match(&file.path[2..]))
+                    .map(|file| (file.id.clone(), position(&file.path[2..])))
+                    .collect();
+                assert_eq!(tree_paths, expected, "seed {seed}, pattern {pattern}");
+            }
+        }
+    }
+
+    fn file_node(name: &str) -> FileTreeNode {
+        FileTreeNode::File {
+            name: name.to_string(),
+            file_id: format!("file_{name}"),
+            reivision_id: format!("rev_{name}"),
+            changelist_id: 1,
+            binary_id: vec![],
+            size: 0,
+            revision_created_at: 0,
+        }
+    }
+
+    #[test]
+    fn flatten_prepends_parent_directories() {
+        let tree = FileTree {
+            nodes: vec![
+                FileTreeNode::Directory {
+                    name: "src".to_string(),
+                    children: vec![
+                        FileTreeNode::Directory {
+                            name: "empty".to_string(),
+                            children: vec![],
+                        },
+                        file_node("main.rs"),
+                    ],
+                },
+                file_node("README.md"),
+            ],
+        };
+
+        let paths: Vec<String> = tree
+            .flatten()
+            .iter()
+            .map(|p| p.to_custom_string())
+            .collect();
+        assert_eq!(paths, vec!["//src/main.rs", "//README.md"]);
+    }
+
+    fn file_revision(name: &str, revision: &str) -> FileTreeNode {
+        FileTreeNode::File {
+            name: name.to_string(),
+            file_id: format!("file_{name}"),
+            reivision_id: revision.to_string(),
+            changelist_id: 1,
+            binary_id: vec![],
+            size: 0,
+            revision_created_at: 0,
+        }
+    }
+
+    fn dirs(path: &str) -> Vec<String> {
+        path.split('/')
+            .filter(|d| !d.is_empty())
+            .map(str::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn diff_trees_reports_added_removed_and_modified_files() {
+        let old = FileTree {
+            nodes: vec![
+                FileTreeNode::Directory {
+                    name: "src".to_string(),
+                    children: vec![
+                        FileTreeNode::Directory {
+                            name: "old".to_string(),
+                            children: vec![file_node("legacy.rs")],
+                        },
+                        file_revision("lib.rs", "r1"),
+                        file_revision("main.rs", "r1"),
+                    ],
+                },
+                file_node("README.md"),
+                file_node("build"),
+            ],
+        };
+        let new = FileTree::from_files([
+            (dirs("src"), file_revision("main.rs", "r2")),
+            (dirs("src"), file_revision("lib.rs", "r1")),
+            (dirs("src/new"), file_node("util.rs")),
+            (vec![], file_node("README.md")),
+            (vec![], file_node("LICENSE")),
+            // 同名的文件与目录是不同的节点
+            (dirs("build"), file_node("out.bin")),
+        ]);
+
+        let diff = diff_trees(&old, &new);
+        let strings = |paths: &[DepotPath]| -> Vec<String> {
+            paths.iter().map(|p| p.to_custom_string()).collect()
+        };
+        assert_eq!(
+            strings(&diff.added),
+            vec!["//LICENSE", "//build/out.bin", "//src/new/util.rs"]
+        );
+        assert_eq!(
+            strings(&diff.removed),
+            vec!["//build", "//src/old/legacy.rs"]
+        );
+        assert_eq!(
+            diff.modified,
+            vec![DiffedFile {
+                path: DepotPath::parse("//src/main.rs").unwrap(),
+                old_revision_id: "r1".to_string(),
+                new_revision_id: "r2".to_string(),
+            }]
+        );
+
+        // 交换两侧后新增与删除互换
+        let reversed = diff_trees(&new, &old);
+        assert_eq!(reversed.added, diff.removed);
+        assert_eq!(reversed.removed, diff.added);
+        assert_eq!(reversed.modified[0].old_revision_id, "r2");
+    }
+
+    #[test]
+    fn diff_against_empty_tree_lists_every_file() {
+        let tree = FileTree::from_files([
+            (dirs("a/b"), file_node("c.txt")),
+            (dirs("a"), file_node("d.txt")),
+        ]);
+        let empty = FileTree { nodes: vec![] };
+
+        let diff = diff_trees(&empty, &tree);
+        assert_eq!(diff.added, tree.flatten());
+        assert!(diff.removed.is_empty() && diff.modified.is_empty());
+        assert_eq!(diff_trees(&tree, &empty).removed, tree.flatten());
+        assert_eq!(diff_trees(&empty, &empty), TreeDiff::default());
+    }
+
+    fn arb_file_tree() -> impl proptest::strategy::Strategy<Value = FileTree> {
+        use proptest::prelude::*;
+
+        let file = (
+            "[a-z0-9_]{1,8}(\\.[a-z]{1,3})?",
+            "[a-f0-9]{8}",
+            any::<i64>(),
+            proptest::collection::vec("[a-f0-9]{8}", 0..3),
+            any::<i64>(),
+            any::<i64>(),
+        )
+            .prop_map(
+                |(name, id, changelist_id, binary_id, size, revision_created_at)| {
+                    FileTreeNode::File {
+                        name,
+                        file_id: format!("file_{id}"),
+                        reivision_id: format!("rev_{id}"),
+                        changelist_id,
+                        binary_id,
+                        size,
+                        revision_created_at,
+                    }
+                },
+            );
+        let node = file.prop_recursive(4, 64, 6, |inner| {
+            ("[a-z0-9_]{1,8}", proptest::collection::vec(inner, 0..6))
+                .prop_map(|(name, children)| FileTreeNode::Directory { name, children })
+        });
+        proptest::collection::vec(node, 0..6).prop_map(|nodes| FileTree { nodes })
+    }
+
+    /// 统计文件树中文件节点的数量
+    fn count_files(nodes: &[FileTreeNode]) -> usize {
+        nodes
+            .iter()
+            .map(|node| match node {
+                FileTreeNode::Directory { children, .. } => count_files(children),
+                FileTreeNode::File { .. } => 1,
+            })
+            .sum()
+    }
+
+    proptest::proptest! {
+        #[test]
+        fn file_tree_json_roundtrip(tree in arb_file_tree()) {
+            let json = tree.to_json();
+            let restored = FileTree::from_json(&json).unwrap();
+            proptest::prop_assert_eq!(&restored, &tree);
+            proptest::prop_assert_eq!(restored.to_json(), json);
+            proptest::prop_assert_eq!(restored.flatten(), tree.flatten());
+        }
+
+        #[test]
+        fn flatten_yields_one_parseable_path_per_file(tree in arb_file_tree()) {
+            let paths = tree.flatten();
+            proptest::prop_assert_eq!(paths.len(), count_files(&tree.nodes));
+            for path in paths {
+                let reparsed = DepotPath::parse(&path.to_custom_string()).unwrap();
+                proptest::prop_assert_eq!(reparsed, path);
+            }
+        }
+
+        #[test]
+        fn diff_of_tree_with_itself_is_empty(tree in arb_file_tree()) {
+            proptest::prop_assert_eq!(diff_trees(&tree, &tree), TreeDiff::default());
+        }
+    }
+}
@@ -165,6 +165,26 @@ impl ConflictDetector {
         Ok(())
     }
 
+    /// 收集所有存在冲突的本地路径
+    ///
+    /// 与 `verify_mappings()` 的判定规则相同，但不会在第一个冲突处返回，
+    /// 结果按映射顺序排列并去重，便于一次性向用户报告全部冲突。
+    pub fn find_conflicts(&self) -> Vec<String> {
+        let mut conflicts: Vec<String> = vec![];
+
+        for mapping in &self.mappings {
+            let filter_counts =
+                self.count_mappings_by_filter(&mapping.local_path, mapping.is_file_mapping());
+
+            if self.has_filter_conflict(&filter_counts) && !conflicts.contains(&mapping.local_path)
+            {
+                conflicts.push(mapping.local_path.clone());
+            }
+        }
+
+        conflicts
+    }
+
     /// 统计能到达指定本地路径的映射，按文件名过滤器分组计数
     ///
     /// 返回：HashMap<FilenameFilter, usize>，键是过滤器类型，值是该类型的映射数量
@@ -406,6 +426,25 @@ mod tests {
         println!("检测到冲突: {:?}", result.err());
     }
 
+    #[test]
+    fn test_find_all_conflicts() {
+        // a/b/ -> z/x/ 与 a/b/c/d/ -> z/x/y/t/ 冲突，m/ -> n/ 与 o/ -> n/ 冲突
+        let mappings = vec![
+            PathMapping::from_strings("a/b/", "z/x/"),
+            PathMapping::from_strings("a/b/c/d/", "z/x/y/t/"),
+            PathMapping::from_strings("m/", "n/"),
+            PathMapping::from_strings("o/", "n/"),
+            PathMapping::from_strings("p/", "q/"),
+        ];
+
+        let detector = ConflictDetector::new(mappings);
+        assert_eq!(detector.find_conflicts(), vec!["z/x/y/t/", "n/"]);
+        assert!(detector.verify_mappings().is_err());
+
+        let detector = ConflictDetector::new(vec![PathMapping::from_strings("p/", "q/")]);
+        assert!(detector.find_conflicts().is_empty());
+    }
+
     #[test]
     fn test_no_conflict() {
         // 测试用例：a/b/ -> z/x/, a/b/c/d/ -> z/x/c/d/
@@ -1,8 +1,7 @@
 use crate::{
     parsers,
     path::basic::{
-        DepotPath, DepotPathWildcard, LocalDir, LocalPath, RangeDepotWildcard,
-        FilenameWildcard,
+        DepotPath, DepotPathWildcard, FilenameWildcard, LocalDir, LocalPath, RangeDepotWildcard,
     },
     workspace::conflict_detector_v2::{ConflictDetector, FilenameFilter, PathMapping},
 };
@@ -74,6 +73,58 @@ pub struct FolderMapping {
     pub local_folder: LocalDir,
 }
 
+impl WorkspaceMapping {
+    /// 由 depot 通配路径和本地目录构造一条映射，`local_dir` 为 `None` 时构造排除映射。
+    ///
+    /// 包含映射只支持范围通配路径，且本地目录必须位于 workspace 根目录下。
+    pub fn from_parts(
+        depot_wildcard: &str,
+        local_dir: Option<&str>,
+        root_dir: &LocalDir,
+    ) -> WorkspaceResult<Self> {
+        let depot_wildcard = parsers::path::depot_path_wildcard(depot_wildcard)
+            .map_err(|e| WorkspaceError::SyntaxError(format!("{}", e)))?;
+        let Some(local_dir) = local_dir else {
+            return Ok(WorkspaceMapping::Exclude(ExcludeMapping(depot_wildcard)));
+        };
+
+        let DepotPathWildcard::Range(depot_folder) = depot_wildcard else {
+            return Err(WorkspaceError::SyntaxError(
+                "regex depot wildcards can only be used in exclude mappings".to_string(),
+            ));
+        };
+        let local_folder = parsers::path::local_dir(local_dir)
+            .map_err(|e| WorkspaceError::SyntaxError(format!("{}", e)))?;
+        if root_dir.match_and_get_diff(&local_folder).is_none() {
+            return Err(WorkspaceError::SyntaxError(format!(
+                "local dir {} is not under workspace root {}",
+                local_folder.to_unix_path_string(),
+                root_dir.to_unix_path_string()
+            )));
+        }
+
+        Ok(WorkspaceMapping::Include(IncludeMapping::Folder(
+            FolderMapping {
+                depot_folder,
+                local_folder,
+            },
+        )))
+    }
+}
+
+/// 一条映射的展开形式，用于查看和编辑 workspace 映射
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WorkspaceMappingDoc {
+    /// depot 通配路径，如 `//a/b/...~png`
+    pub depot_wildcard: String,
+    /// 本地路径（unix 风格），排除映射为空
+    pub local_dir: String,
+    pub recursive: bool,
+    pub filter: FilenameFilter,
+    /// 映射在列表中的位置，数值越大越优先（后面的映射覆盖前面的）
+    pub priority: i32,
+}
+
 /// Workspace 配置 TODO: 需要接到 WorkspaceEntity 中
 #[derive(Debug, Clone, Serialize, Deserialize, Encode, Decode)]
 pub struct WorkspaceConfig {
@@ -315,26 +366,42 @@ impl WorkspaceConfig {
         )
     }
 
+    /// 按顺序将所有包含映射转换为 conflict_detector_v2 的 PathMapping，排除映射不参与冲突检测
+    pub fn path_mappings(&self) -> Vec<PathMapping> {
+        self.mappings
+            .iter()
+            .filter_map(|mapping| match mapping {
+                WorkspaceMapping::Include(include_mapping) => {
+                    Some(Self::include_mapping_to_path_mapping(include_mapping))
+                }
+                WorkspaceMapping::Exclude(_) => None,
+            })
+            .collect()
+    }
+
     /// 将 IncludeMapping 转换为 conflict_detector_v2 的 PathMapping
     fn include_mapping_to_path_mapping(mapping: &IncludeMapping) -> PathMapping {
         match mapping {
             IncludeMapping::File(file_mapping) => {
                 // 文件映射：直接转换为带文件名的路径
-                let server_path = file_mapping.depot_file.to_custom_string().trim_start_matches("/").to_string();
-                let local_path = file_mapping.local_file.to_unix_path_string().trim_start_matches("/").to_string();
-                
+                let server_path = file_mapping
+                    .depot_file
+                    .to_custom_string()
+                    .trim_start_matches("/")
+                    .to_string();
+                let local_path = file_mapping
+                    .local_file
+                    .to_unix_path_string()
+                    .trim_start_matches("/")
+                    .to_string();
+
                 let filename_filter = std::path::Path::new(&file_mapping.local_file.file)
                     .extension()
                     .and_then(|ext| ext.to_str())
                     .map(|ext| FilenameFilter::Extension(ext.to_string()))
                     .unwrap_or(FilenameFilter::All);
 
-                PathMapping::new(
-                    server_path,
-                    local_path,
-                    false,
-                    filename_filter,
-                )
+                PathMapping::new(server_path, local_path, false, filename_filter)
             }
             IncludeMapping::Folder(folder_mapping) => {
                 // 文件夹映射：转换为目录路径（以 / 结尾）
@@ -355,27 +422,79 @@ impl WorkspaceConfig {
                     path
                 };
                 let recursive = folder_mapping.depot_folder.recursive;
-                
+
                 // 转换文件名过滤器
-                let filename_filter = match &folder_mapping.depot_folder.wildcard {
-                    FilenameWildcard::All => FilenameFilter::All,
-                    FilenameWildcard::Extension(ext) => {
-                        // 移除开头的 '.' 如果存在
-                        let ext_str = if ext.starts_with('.') {
-                            &ext[1..]
-                        } else {
-                            ext
-                        };
-                        FilenameFilter::Extension(ext_str.to_string())
-                    }
-                    FilenameWildcard::Exact(_) => FilenameFilter::All, // 精确文件名视为 All
-                };
+                let filename_filter = Self::filename_filter(&folder_mapping.depot_folder.wildcard);
 
                 PathMapping::new(server_path, local_path, recursive, filename_filter)
             }
         }
     }
 
+    /// 将文件名通配符转换为冲突检测使用的文件名过滤器
+    fn filename_filter(wildcard: &FilenameWildcard) -> FilenameFilter {
+        match wildcard {
+            FilenameWildcard::All => FilenameFilter::All,
+            FilenameWildcard::Extension(ext) => {
+                // 移除开头的 '.' 如果存在
+                let ext_str = ext.strip_prefix('.').unwrap_or(ext);
+                FilenameFilter::Extension(ext_str.to_string())
+            }
+            FilenameWildcard::Exact(_) => FilenameFilter::All, // 精确文件名视为 All
+        }
+    }
+
+    /// 按优先级（即映射在列表中的位置）列出所有映射
+    pub fn mapping_docs(&self) -> Vec<WorkspaceMappingDoc> {
+        self.mappings
+            .iter()
+            .enumerate()
+            .map(|(priority, mapping)| self.mapping_doc(mapping, priority as i32))
+            .collect()
+    }
+
+    fn mapping_doc(&self, mapping: &WorkspaceMapping, priority: i32) -> WorkspaceMappingDoc {
+        match mapping {
+            WorkspaceMapping::Include(include_mapping) => {
+                let path_mapping = Self::include_mapping_to_path_mapping(include_mapping);
+                let (depot_wildcard, local_dir) = match include_mapping {
+                    IncludeMapping::File(file_mapping) => (
+                        file_mapping.depot_file.to_custom_string(),
+                        file_mapping.local_file.to_unix_path_string(),
+                    ),
+                    IncludeMapping::Folder(folder_mapping) => (
+                        DepotPathWildcard::Range(folder_mapping.depot_folder.clone())
+                            .to_custom_string(),
+                        folder_mapping.local_folder.to_unix_path_string(),
+                    ),
+                };
+                WorkspaceMappingDoc {
+                    depot_wildcard,
+                    local_dir,
+                    recursive: path_mapping.recursive,
+                    filter: path_mapping.filename_filter,
+                    priority,
+                }
+            }
+            WorkspaceMapping::Exclude(ExcludeMapping(depot_wildcard)) => {
+                let (recursive, filter) = match depot_wildcard {
+                    DepotPathWildcard::Range(range) => {
+                        (range.recursive, Self::filename_filter(&range.wildcard))
+                    }
+                    // 正则表达式可以匹配任意层级的任意文件
+                    DepotPathWildcard::Regex(_) => (true, FilenameFilter::All),
+                };
+                WorkspaceMappingDoc {
+                    depot_wildcard: depot_wildcard.to_custom_string(),
+                    local_dir: String::new(),
+                    recursive,
+                    filter,
+                    priority,
+                }
+            }
+        }
+    }
+
     /// 给定两个映射的索引，判断是否存在冲突
     fn verify_mapping_pair(&self, index_1: usize, index_2: usize) -> Result<(), String> {
         // 使用 conflict_detector_v2 进行冲突检测
@@ -384,14 +503,14 @@ impl WorkspaceConfig {
             return Ok(());
         }
         let (primary, secondary) = race_pair.unwrap();
-        
+
         // 转换为 PathMapping 并使用 v2 检测器
         let mapping_1 = Self::include_mapping_to_path_mapping(&primary);
         let mapping_2 = Self::include_mapping_to_path_mapping(&secondary);
         println!("mapping_1: {:?}", mapping_1);
         println!("mapping_2: {:?}", mapping_2);
         let detector = ConflictDetector::new(vec![mapping_2, mapping_1]);
-        
+
         match detector.verify_mappings() {
             Ok(_) => Ok(()),
             Err(e) => Err(format!("Mapping conflict: {}", e)),
@@ -0,0 +1,136 @@
+//! 对比不同连接池大小下并发 1 000 个 `check_chunks` 请求的吞吐量，连接池大小为 1、4、8
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use crv_edge::daemon_server::config::HiveClientConfig;
+use crv_edge::daemon_server::state::ChannelPool;
+use crv_edge::hive_pb::{CheckChunksReq, CheckChunksRsp, hive_service_client::HiveServiceClient};
+use std::convert::Infallible;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::runtime::Runtime;
+use tokio_stream::wrappers::TcpListenerStream;
+use tonic::codegen::{BoxFuture, Context, Poll, Service, StdError, http};
+use tonic::server::{Grpc, NamedService, UnaryService};
+use tonic::{Request, Response, Status};
+use tonic_prost::ProstCodec;
+
+const CONCURRENT_REQUESTS: usize = 1_000;
+const POOL_SIZES: [usize; 3] = [1, 4, 8];
+/// hive 处理每个请求的耗时
+const HIVE_LATENCY: Duration = Duration::from_millis(2);
+/// hive 在单条连接上允许的并发流数量
+const MAX_CONCURRENT_STREAMS: u32 = 64;
+
+/// 只实现了 CheckChunks 的 hive，认为所有 chunk 都不存在
+#[derive(Clone)]
+struct StubHive;
+
+struct CheckChunks;
+
+impl UnaryService<CheckChunksReq> for CheckChunks {
+    type Response = CheckChunksRsp;
+    type Future = BoxFuture<Response<CheckChunksRsp>, Status>;
+
+    fn call(&mut self, request: Request<CheckChunksReq>) -> Self::Future {
+        Box::pin(async move {
+            tokio::time::sleep(HIVE_LATENCY).await;
+            Ok(Response::new(CheckChunksRsp {
+                missing_chunk_hashes: request.into_inner().chunk_hashes,
+            }))
+        })
+    }
+}
+
+impl<B> Service<http::Request<B>> for StubHive
+where
+    B: tonic::codegen::Body + Send + 'static,
+    B::Error: Into<StdError> + Send + 'static,
+{
+    type Response = http::Response<tonic::body::Body>;
+    type Error = Infallible;
+    type Future = BoxFuture<Self::Response, Self::Error>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: http::Request<B>) -> Self::Future {
+        Box::pin(async move {
+            if request.uri().path() != "/hive_proto.HiveService/CheckChunks" {
+                return Ok(Status::unimplemented("only CheckChunks is implemented").into_http());
+            }
+            let mut grpc = Grpc::new(ProstCodec::<CheckChunksRsp, CheckChunksReq>::default());
+            Ok(grpc.unary(CheckChunks, request).await)
+        })
+    }
+}
+
+impl NamedService for StubHive {
+    const NAME: &'static str = "hive_proto.HiveService";
+}
+
+async fn spawn_hive() -> String {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(
+        tonic::transport::Server::builder()
+            .max_concurrent_streams(Some(MAX_CONCURRENT_STREAMS))
+            .add_service(StubHive)
+            .serve_with_incoming(TcpListenerStream::new(listener)),
+    );
+    addr
+}
+
+async fn check_chunks(pool: &ChannelPool, addr: &str, i: usize) {
+    let channel = pool.get_channel(addr).unwrap();
+    HiveServiceClient::new(channel)
+        .check_chunks(CheckChunksReq {
+            chunk_hashes: vec![format!("{i:064x}")],
+        })
+        .await
+        .unwrap();
+}
+
+async fn concurrent_check_chunks(pool: Arc<ChannelPool>, addr: Arc<str>) {
+    let mut tasks = Vec::with_capacity(CONCURRENT_REQUESTS);
+    for i in 0..CONCURRENT_REQUESTS {
+        let pool = pool.clone();
+        let addr = addr.clone();
+        tasks.push(tokio::spawn(
+            async move { check_chunks(&pool, &addr, i).await },
+        ));
+    }
+    for task in tasks {
+        task.await.unwrap();
+    }
+}
+
+fn check_chunks_throughput(c: &mut Criterion) {
+    let runtime = Runtime::new().unwrap();
+    let addr: Arc<str> = runtime.block_on(spawn_hive()).into();
+
+    let mut group = c.benchmark_group("concurrent_check_chunks");
+    group.throughput(Throughput::Elements(CONCURRENT_REQUESTS as u64));
+    group.sample_size(20);
+    for pool_size in POOL_SIZES {
+        let pool = Arc::new(ChannelPool::with_config(HiveClientConfig {
+            connection_pool_size: pool_size,
+            ..Default::default()
+        }));
+        // 逐条建立连接，收到 hive 的并发流上限后再并发请求，否则超出上限的流会被拒绝
+        runtime.block_on(async {
+            for i in 0..pool_size {
+                check_chunks(&pool, &addr, i).await;
+            }
+        });
+
+        group.bench_with_input(
+            BenchmarkId::from_parameter(pool_size),
+            &pool_size,
+            |b, _| b.iter(|| runtime.block_on(concurrent_check_chunks(pool.clone(), addr.clone()))),
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, check_chunks_throughput);
+criterion_main!(benches);
@@ -8,6 +8,9 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     // 编译 proto 文件，输出到 OUT_DIR
     tonic_prost_build::configure()
+        // crv-cli 的 `--json` 输出直接序列化响应消息，字段名与 proto3 JSON 映射一致
+        .type_attribute(".", "#[derive(serde::Serialize)]")
+        .type_attribute(".", "#[serde(rename_all = \"camelCase\")]")
         // 可选配置，比如关闭生成 server、client、改变输出路径等
         // .build_server(false)
         // .out_dir("src/generated")
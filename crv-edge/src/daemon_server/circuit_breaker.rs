@@ -0,0 +1,209 @@
+//! hive 不可达时的熔断器
+//!
+//! 连续失败达到阈值后熔断一段时间，期间所有请求直接失败，不再等待网络超时；
+//! 熔断时间结束后只放行一个探测请求，探测成功则恢复，失败则继续熔断。
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tonic::Status;
+
+/// 熔断器配置
+#[derive(Clone, Debug)]
+pub struct CircuitBreakerConfig {
+    /// 连续失败多少次后熔断
+    pub failure_threshold: u32,
+    /// 熔断持续的时间
+    pub open_duration: Duration,
+}
+
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        Self {
+            failure_threshold: 5,
+            open_duration: Duration::from_secs(30),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CircuitState {
+    /// 正常放行请求
+    Closed,
+    /// 熔断中，直到 `until` 之前的请求都直接失败
+    Open { until: Instant },
+    /// 探测请求进行中，其余请求直接失败。
+    /// 探测请求超过熔断时长仍未返回（例如请求被取消）时，允许重新探测
+    HalfOpen { probe_started: Instant },
+}
+
+struct Inner {
+    state: CircuitState,
+    consecutive_failures: u32,
+}
+
+pub struct CircuitBreaker {
+    config: CircuitBreakerConfig,
+    inner: Mutex<Inner>,
+}
+
+impl CircuitBreaker {
+    pub fn new(config: CircuitBreakerConfig) -> Self {
+        Self {
+            config,
+            inner: Mutex::new(Inner {
+                state: CircuitState::Closed,
+                consecutive_failures: 0,
+            }),
+        }
+    }
+
+    pub fn state(&self) -> CircuitState {
+        self.inner.lock().expect("circuit breaker poisoned").state
+    }
+
+    /// 请求发出前调用，熔断中返回 `Unavailable`
+    pub fn try_acquire(&self) -> Result<(), Status> {
+        self.try_acquire_at(Instant::now())
+    }
+
+    /// 请求成功（收到 hive 的响应）后调用
+    pub fn record_success(&self) {
+        let mut inner = self.inner.lock().expect("circuit breaker poisoned");
+        inner.state = CircuitState::Closed;
+        inner.consecutive_failures = 0;
+    }
+
+    /// 请求因网络原因失败后调用
+    pub fn record_failure(&self) {
+        self.record_failure_at(Instant::now())
+    }
+
+    fn try_acquire_at(&self, now: Instant) -> Result<(), Status> {
+        let mut inner = self.inner.lock().expect("circuit breaker poisoned");
+        let probe_allowed = match inner.state {
+            CircuitState::Closed => return Ok(()),
+            CircuitState::Open { until } => now >= until,
+            CircuitState::HalfOpen { probe_started } => {
+                now >= probe_started + self.config.open_duration
+            }
+        };
+        if !probe_allowed {
+            return Err(Status::unavailable("circuit breaker open"));
+        }
+        inner.state = CircuitState::HalfOpen { probe_started: now };
+        Ok(())
+    }
+
+    fn record_failure_at(&self, now: Instant) {
+        let mut inner = self.inner.lock().expect("circuit breaker poisoned");
+        inner.consecutive_failures = inner.consecutive_failures.saturating_add(1);
+        let should_open = match inner.state {
+            CircuitState::Closed => inner.consecutive_failures >= self.config.failure_threshold,
+            CircuitState::HalfOpen { .. } => true,
+            // 熔断前发出的请求陆续失败，不延长熔断时间
+            CircuitState::Open { .. } => false,
+        };
+        if should_open {
+            inner.state = CircuitState::Open {
+                until: now + self.config.open_duration,
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OPEN_DURATION: Duration = Duration::from_secs(30);
+
+    fn breaker() -> CircuitBreaker {
+        CircuitBreaker::new(CircuitBreakerConfig {
+            failure_threshold: 3,
+            open_duration: OPEN_DURATION,
+        })
+    }
+
+    #[test]
+    fn opens_after_consecutive_failures() {
+        let breaker = breaker();
+        let now = Instant::now();
+
+        breaker.record_failure_at(now);
+        breaker.record_failure_at(now);
+        // 中间的成功会重置计数
+        breaker.record_success();
+        breaker.record_failure_at(now);
+        breaker.record_failure_at(now);
+        assert_eq!(breaker.state(), CircuitState::Closed);
+        assert!(breaker.try_acquire_at(now).is_ok());
+
+        breaker.record_failure_at(now);
+        assert_eq!(
+            breaker.state(),
+            CircuitState::Open {
+                until: now + OPEN_DURATION
+            }
+        );
+        let status = breaker.try_acquire_at(now).unwrap_err();
+        assert_eq!(status.code(), tonic::Code::Unavailable);
+        assert_eq!(status.message(), "circuit breaker open");
+    }
+
+    #[test]
+    fn probe_after_open_duration_closes_on_success() {
+        let breaker = breaker();
+        let now = Instant::now();
+        for _ in 0..3 {
+            breaker.record_failure_at(now);
+        }
+
+        assert!(breaker.try_acquire_at(now + OPEN_DURATION / 2).is_err());
+
+        // 熔断结束后只放行一个探测请求
+        let probe_at = now + OPEN_DURATION;
+        assert!(breaker.try_acquire_at(probe_at).is_ok());
+        assert_eq!(
+            breaker.state(),
+            CircuitState::HalfOpen {
+                probe_started: probe_at
+            }
+        );
+        assert!(breaker.try_acquire_at(probe_at).is_err());
+
+        breaker.record_success();
+        assert_eq!(breaker.state(), CircuitState::Closed);
+        assert!(breaker.try_acquire_at(probe_at).is_ok());
+    }
+
+    #[test]
+    fn failed_probe_reopens_and_is_retried_later() {
+        let breaker = breaker();
+        let now = Instant::now();
+        for _ in 0..3 {
+            breaker.record_failure_at(now);
+        }
+
+        let probe_at = now + OPEN_DURATION;
+        assert!(breaker.try_acquire_at(probe_at).is_ok());
+        breaker.record_failure_at(probe_at);
+        assert_eq!(
+            breaker.state(),
+            CircuitState::Open {
+                until: probe_at + OPEN_DURATION
+            }
+        );
+        assert!(breaker.try_acquire_at(probe_at).is_err());
+
+        // 再次经过熔断时长后重新探测
+        assert!(breaker.try_acquire_at(probe_at + OPEN_DURATION).is_ok());
+
+        // 探测请求一直没有返回时，同样在熔断时长后允许重新探测
+        let stuck_at = probe_at + OPEN_DURATION;
+        assert!(
+            breaker
+                .try_acquire_at(stuck_at + OPEN_DURATION / 2)
+                .is_err()
+        );
+        assert!(breaker.try_acquire_at(stuck_at + OPEN_DURATION).is_ok());
+    }
+}
@@ -2,9 +2,20 @@
 
 use directories::ProjectDirs;
 use serde::{Deserialize, Serialize};
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+use tonic::transport::{Certificate, ClientTlsConfig, Endpoint, Identity};
 
 use crate::{
-    daemon_server::error::{AppError, AppResult},
+    daemon_server::{
+        circuit_breaker::CircuitBreakerConfig,
+        error::{AppError, AppResult},
+        retry::RetryPolicy,
+        state::{DEFAULT_MAX_OPEN_FILES, DEFAULT_SYNC_WRITE_BUFFER_SIZE},
+    },
+    hive_client::DEFAULT_HIVE_CONNECTION_POOL_SIZE,
     pb,
 };
 
@@ -13,15 +24,127 @@ use crate::{
 pub struct BootstrapConfig {
     /// daemon 启动时的端口号
     pub daemon_port: u16,
+    /// daemon 监听的地址（如 `0.0.0.0:31822`），不填时只监听本机的 `[::1]:{daemon_port}`
+    #[serde(default)]
+    pub daemon_bind_addr: Option<String>,
     /// 嵌入式数据库存放数据的根目录
     pub embedded_database_root: String,
+    /// 是否使用 TLS 连接 hive，开启后 hive 地址必须使用 https
+    #[serde(default)]
+    pub hive_tls: bool,
+    /// 校验 hive 证书的 CA 证书（PEM），不填时使用系统内置的根证书
+    #[serde(default)]
+    pub hive_ca_cert_path: Option<String>,
+    /// 双向 TLS 时 edge 使用的客户端证书（PEM）
+    #[serde(default)]
+    pub hive_client_cert_path: Option<String>,
+    /// 双向 TLS 时 edge 使用的客户端私钥（PEM）
+    #[serde(default)]
+    pub hive_client_key_path: Option<String>,
+    /// 请求 hive 的超时时间（秒），流式请求中为每条消息的超时时间，0 表示不限制
+    #[serde(default)]
+    pub request_timeout_secs: u64,
+    /// 连续多少次无法连接 hive 后熔断，熔断期间请求 hive 会直接失败
+    #[serde(default = "default_hive_failure_threshold")]
+    pub hive_failure_threshold: u32,
+    /// 熔断持续的时间（秒），之后会尝试重新连接 hive
+    #[serde(default = "default_hive_circuit_open_secs")]
+    pub hive_circuit_open_secs: u64,
+    /// 遇到暂时性错误时最多尝试请求 hive 的次数（包含第一次），仅对幂等请求生效
+    #[serde(default = "default_hive_retry_max_attempts")]
+    pub hive_retry_max_attempts: u32,
+    /// 第一次重试前等待的时间（毫秒），之后每次翻倍
+    #[serde(default = "default_hive_retry_base_delay_ms")]
+    pub hive_retry_base_delay_ms: u64,
+    /// 暴露 Prometheus 指标（`/metrics`）的端口号
+    #[serde(default = "default_metrics_port")]
+    pub metrics_port: u16,
+    /// 是否关闭 Prometheus 指标服务
+    #[serde(default)]
+    pub disable_metrics: bool,
+    /// 是否启用差量上传：签出文件时保存其 chunk，提交时只上传相对于上一个版本的差量
+    #[serde(default)]
+    pub enable_delta_upload: bool,
+    /// 是否在启动时监听所有工作区，自动将被修改的已同步文件标记为 Edit
+    #[serde(default)]
+    pub auto_track_changes: bool,
+    /// 上传 chunk 的带宽上限（KiB/s），不填时不限制
+    #[serde(default)]
+    pub upload_bandwidth_kbps: Option<u64>,
+    /// 提交前执行的可执行文件，以非零状态退出时中止提交
+    #[serde(default)]
+    pub pre_submit_hook: Option<String>,
+    /// 更新清单的地址，不填时不支持自动更新
+    #[serde(default)]
+    pub update_url: Option<String>,
+    /// 同步时写入文件的缓冲区大小（KiB）
+    #[serde(default = "default_sync_write_buffer_kb")]
+    pub sync_write_buffer_kb: usize,
+    /// 同步时最多同时下载的文件数量，所有同步任务共享
+    #[serde(default = "default_max_open_files")]
+    pub max_open_files: usize,
+    /// 与每个 hive 建立的连接数，请求按轮询顺序分配到各个连接上
+    #[serde(default = "default_hive_connection_pool_size")]
+    pub hive_connection_pool_size: usize,
+}
+
+fn default_metrics_port() -> u16 {
+    34563
+}
+
+fn default_sync_write_buffer_kb() -> usize {
+    DEFAULT_SYNC_WRITE_BUFFER_SIZE / 1024
+}
+
+fn default_max_open_files() -> usize {
+    DEFAULT_MAX_OPEN_FILES
+}
+
+fn default_hive_connection_pool_size() -> usize {
+    DEFAULT_HIVE_CONNECTION_POOL_SIZE
+}
+
+fn default_hive_failure_threshold() -> u32 {
+    CircuitBreakerConfig::default().failure_threshold
+}
+
+fn default_hive_circuit_open_secs() -> u64 {
+    CircuitBreakerConfig::default().open_duration.as_secs()
+}
+
+fn default_hive_retry_max_attempts() -> u32 {
+    RetryPolicy::default().max_attempts
+}
+
+fn default_hive_retry_base_delay_ms() -> u64 {
+    RetryPolicy::default().base_delay_ms
 }
 
 impl Default for BootstrapConfig {
     fn default() -> Self {
         Self {
             daemon_port: 31822,
+            daemon_bind_addr: None,
             embedded_database_root: Self::get_default_data_dir(),
+            hive_tls: false,
+            hive_ca_cert_path: None,
+            hive_client_cert_path: None,
+            hive_client_key_path: None,
+            request_timeout_secs: 0,
+            hive_failure_threshold: default_hive_failure_threshold(),
+            hive_circuit_open_secs: default_hive_circuit_open_secs(),
+            hive_retry_max_attempts: default_hive_retry_max_attempts(),
+            hive_retry_base_delay_ms: default_hive_retry_base_delay_ms(),
+            metrics_port: default_metrics_port(),
+            disable_metrics: false,
+            enable_delta_upload: false,
+            auto_track_changes: false,
+            upload_bandwidth_kbps: None,
+            pre_submit_hook: None,
+            update_url: None,
+            sync_write_buffer_kb: default_sync_write_buffer_kb(),
+            max_open_files: default_max_open_files(),
+            hive_connection_pool_size: default_hive_connection_pool_size(),
         }
     }
 }
@@ -49,6 +172,135 @@ impl BootstrapConfig {
             .map_err(|e| AppError::Config(format!("{e}")))?;
         Ok(config)
     }
+
+    /// daemon 监听的地址
+    pub fn daemon_bind_addr(&self) -> AppResult<SocketAddr> {
+        match &self.daemon_bind_addr {
+            Some(addr) => SocketAddr::from_str(addr.trim())
+                .map_err(|e| AppError::Config(format!("Invalid daemon bind address {addr}: {e}"))),
+            None => Ok(SocketAddr::new(
+                Ipv6Addr::LOCALHOST.into(),
+                self.daemon_port,
+            )),
+        }
+    }
+
+    /// 本机客户端连接 daemon 的地址，daemon 监听所有网卡时通过回环地址连接
+    pub fn daemon_url(&self) -> AppResult<String> {
+        let mut addr = self.daemon_bind_addr()?;
+        if addr.ip().is_unspecified() {
+            addr.set_ip(match addr {
+                SocketAddr::V4(_) => Ipv4Addr::LOCALHOST.into(),
+                SocketAddr::V6(_) => Ipv6Addr::LOCALHOST.into(),
+            });
+        }
+        Ok(format!("http://{addr}"))
+    }
+
+    /// 连接 hive 时使用的配置
+    pub fn hive_client_config(&self) -> HiveClientConfig {
+        HiveClientConfig {
+            tls: self.hive_tls,
+            ca_cert_path: self.hive_ca_cert_path.as_ref().map(PathBuf::from),
+            client_cert_path: self.hive_client_cert_path.as_ref().map(PathBuf::from),
+            client_key_path: self.hive_client_key_path.as_ref().map(PathBuf::from),
+            request_timeout: (self.request_timeout_secs > 0)
+                .then(|| Duration::from_secs(self.request_timeout_secs)),
+            circuit_breaker: CircuitBreakerConfig {
+                failure_threshold: self.hive_failure_threshold.max(1),
+                open_duration: Duration::from_secs(self.hive_circuit_open_secs),
+            },
+            retry: RetryPolicy {
+                max_attempts: self.hive_retry_max_attempts.max(1),
+                base_delay_ms: self.hive_retry_base_delay_ms,
+                ..Default::default()
+            },
+            delta_upload: self.enable_delta_upload,
+            upload_bytes_per_sec: self
+                .upload_bandwidth_kbps
+                .map(|kbps| kbps.saturating_mul(1024)),
+            connection_pool_size: self.hive_connection_pool_size.max(1),
+        }
+    }
+}
+
+/// edge 连接 hive 时使用的配置。
+#[derive(Clone, Default)]
+pub struct HiveClientConfig {
+    /// 是否使用 TLS
+    pub tls: bool,
+    /// CA 证书（PEM）
+    pub ca_cert_path: Option<PathBuf>,
+    /// 客户端证书（PEM），需要与私钥同时指定
+    pub client_cert_path: Option<PathBuf>,
+    /// 客户端私钥（PEM），需要与证书同时指定
+    pub client_key_path: Option<PathBuf>,
+    /// 请求超时时间，None 表示不限制
+    pub request_timeout: Option<Duration>,
+    /// hive 不可达时的熔断配置
+    pub circuit_breaker: CircuitBreakerConfig,
+    /// 幂等请求遇到暂时性错误时的重试策略
+    pub retry: RetryPolicy,
+    /// 是否启用差量上传
+    pub delta_upload: bool,
+    /// 上传 chunk 的带宽上限（字节/秒），None 表示不限制
+    pub upload_bytes_per_sec: Option<u64>,
+    /// 与每个 hive 建立的连接数，0 与 1 相同
+    pub connection_pool_size: usize,
+}
+
+impl HiveClientConfig {
+    /// 根据配置构建连接 hive 的 endpoint，开启 TLS 时会读取配置中的证书文件
+    pub fn endpoint(&self, addr: &str) -> AppResult<Endpoint> {
+        let endpoint = Endpoint::from_shared(addr.to_string())
+            .map_err(|e| AppError::Config(format!("Invalid hive address {addr}: {e}")))?;
+        if !self.tls {
+            return Ok(endpoint);
+        }
+
+        // tonic 只会对 https 地址发起 TLS 握手，这里直接拒绝，避免静默降级为明文连接
+        if endpoint.uri().scheme_str() != Some("https") {
+            return Err(AppError::Config(format!(
+                "Hive TLS is enabled, but address {addr} does not use https."
+            )));
+        }
+        endpoint
+            .tls_config(self.tls_config()?)
+            .map_err(|e| AppError::Config(format!("Invalid hive TLS config: {e}")))
+    }
+
+    fn tls_config(&self) -> AppResult<ClientTlsConfig> {
+        let mut tls_config = ClientTlsConfig::new();
+        match &self.ca_cert_path {
+            Some(ca_cert_path) => {
+                tls_config =
+                    tls_config.ca_certificate(Certificate::from_pem(read_pem(ca_cert_path)?));
+            }
+            None => {
+                tls_config = tls_config.with_enabled_roots();
+            }
+        }
+        match (&self.client_cert_path, &self.client_key_path) {
+            (Some(cert_path), Some(key_path)) => {
+                tls_config = tls_config.identity(Identity::from_pem(
+                    read_pem(cert_path)?,
+                    read_pem(key_path)?,
+                ));
+            }
+            (None, None) => {}
+            _ => {
+                return Err(AppError::Config(
+                    "Hive client certificate and key must be specified together.".to_string(),
+                ));
+            }
+        }
+        Ok(tls_config)
+    }
+}
+
+fn read_pem(path: &Path) -> AppResult<Vec<u8>> {
+    std::fs::read(path)
+        .map_err(|e| AppError::Config(format!("Failed to read {}: {e}", path.display())))
 }
 
 /// daemon 运行时所需的配置项。
@@ -72,6 +324,8 @@ pub struct RuntimeConfigItem {
 pub enum RuntimeConfigSource {
     Default,
     Set,
+    /// 来自请求工作目录及其上级目录中的 `.crvconfig`
+    File,
     Override,
 }
 
@@ -80,12 +334,26 @@ impl Into<String> for RuntimeConfigSource {
         match self {
             RuntimeConfigSource::Default => "default".to_string(),
             RuntimeConfigSource::Set => "set".to_string(),
+            RuntimeConfigSource::File => "file".to_string(),
             RuntimeConfigSource::Override => "override".to_string(),
         }
     }
 }
 
 impl RuntimeConfig {
+    /// 可以通过名称读写的配置项
+    pub const KEYS: [&'static str; 3] = ["remote_addr", "editor", "user"];
+
+    /// 按名称获取配置项，名称与 `RuntimeConfigOverride` 的字段名一致
+    pub fn get(&self, key: &str) -> Option<&RuntimeConfigItem> {
+        match key {
+            "remote_addr" => Some(&self.remote_addr),
+            "editor" => Some(&self.editor),
+            "user" => Some(&self.user),
+            _ => None,
+        }
+    }
+
     /// 合并逻辑：将 override 应用到 self 上
     pub fn merge(&mut self, other: RuntimeConfigOverride, source: RuntimeConfigSource) {
         if let Some(value) = other.remote_addr {
@@ -129,9 +397,41 @@ impl Into<pb::RuntimeConfigItem> for RuntimeConfigItem {
 }
 
 /// 从用户请求的元数据中提取的用于覆盖运行时配置的信息。
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Default)]
 pub struct RuntimeConfigOverride {
     pub remote_addr: Option<String>,
     pub editor: Option<String>,
     pub user: Option<String>,
 }
+
+/// 按目录生效的运行时配置文件，内容为 TOML，key 与 `RuntimeConfigOverride` 的字段名一致
+pub const CRVCONFIG_FILE_NAME: &str = ".crvconfig";
+
+impl RuntimeConfigOverride {
+    /// 从 `dir` 开始逐级向上查找 `.crvconfig` 并合并，离 `dir` 越近的文件优先级越高
+    pub fn from_crvconfig_files(dir: &Path) -> AppResult<Self> {
+        let mut merged = Self::default();
+        for dir in dir.ancestors() {
+            let path = dir.join(CRVCONFIG_FILE_NAME);
+            if !path.is_file() {
+                continue;
+            }
+            let text = std::fs::read_to_string(&path)
+                .map_err(|e| AppError::Config(format!("Failed to read {}: {e}", path.display())))?;
+            let config: Self = toml::from_str(&text).map_err(|e| {
+                AppError::Config(format!("Failed to parse {}: {e}", path.display()))
+            })?;
+            merged = merged.or(config);
+        }
+        Ok(merged)
+    }
+
+    /// 以 self 为准，self 中没有的配置项取 `fallback` 中的值
+    fn or(self, fallback: Self) -> Self {
+        Self {
+            remote_addr: self.remote_addr.or(fallback.remote_addr),
+            editor: self.editor.or(fallback.editor),
+            user: self.user.or(fallback.user),
+        }
+    }
+}
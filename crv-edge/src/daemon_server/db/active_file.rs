@@ -2,13 +2,25 @@
 
 use crate::daemon_server::db::*;
 use bincode::{Decode, Encode};
-use crv_core::path::basic::{WorkspaceDir, WorkspacePath};
+use crv_core::path::basic::{DepotPath, WorkspaceDir, WorkspacePath};
+use rocksdb::WriteBatchWithTransaction;
+use std::collections::BTreeMap;
+
+/// 移动操作中目标文件所记录的源文件信息，提交时用于让 hive 追溯文件的来源
+#[derive(Encode, Decode, PartialEq, Eq, Clone)]
+pub struct MoveSource {
+    pub depot_path: DepotPath,
+    pub generation: i64,
+    pub revision: i64,
+}
 
 #[derive(Encode, Decode, PartialEq, Eq, Clone)]
 pub enum Action {
     Add,
     Delete,
     Edit,
+    /// 由 move 产生的新文件，源文件会同时被标记为 Delete
+    MoveAdd(MoveSource),
 }
 
 impl Action {
@@ -17,6 +29,7 @@ impl Action {
             Action::Add => "add".to_string(),
             Action::Edit => "edit".to_string(),
             Action::Delete => "delete".to_string(),
+            Action::MoveAdd(_) => "move/add".to_string(),
         }
     }
 }
@@ -87,4 +100,49 @@ impl DbManager {
 
         return Ok(result);
     }
+
+    /// 按操作类型统计所有工作区中打开的文件数
+    pub fn count_active_files(&self) -> Result<BTreeMap<String, usize>, DbError> {
+        let cf = self
+            .inner
+            .cf_handle(Self::CF_ACTIVE_FILE)
+            .expect(&format!("cf {} must exist", Self::CF_ACTIVE_FILE));
+
+        let mut result = BTreeMap::new();
+        for item in self.inner.iterator_cf(cf, IteratorMode::Start) {
+            let (_, value) = item?;
+            let action: Action = bincode::decode_from_slice(&value, bincode::config::standard())?.0;
+            *result.entry(action.to_custom_string()).or_insert(0) += 1;
+        }
+        Ok(result)
+    }
+
+    /// 删除 changelist 中记录的文件的 active file，返回删除的条目数；changelist 不存在时返回 DbError::NotFound
+    pub fn cleanup_active_files_for_changelist(
+        &self,
+        changelist_id: &String,
+    ) -> Result<u64, DbError> {
+        let Some(meta) = self.get_changelist_meta(changelist_id)? else {
+            return Err(DbError::NotFound(format!(
+                "Changelist {changelist_id} does not exist."
+            )));
+        };
+        let cf = self
+            .inner
+            .cf_handle(Self::CF_ACTIVE_FILE)
+            .expect(&format!("cf {} must exist", Self::CF_ACTIVE_FILE));
+
+        let mut batch = WriteBatchWithTransaction::<true>::default();
+        let mut count = 0;
+        for path in &meta.workspace_paths {
+            let key = path.to_custom_string();
+            // 文件可能已经 revert
+            if self.inner.get_cf(cf, &key)?.is_some() {
+                batch.delete_cf(cf, key);
+                count += 1;
+            }
+        }
+        self.inner.write(batch)?;
+        Ok(count)
+    }
 }
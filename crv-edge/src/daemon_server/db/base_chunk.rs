@@ -0,0 +1,31 @@
+//! 签出文件时保存的 base chunk，提交时用于计算差量，只上传改动的部分
+
+use crate::daemon_server::db::*;
+
+impl DbManager {
+    pub fn set_base_chunk(&self, chunk_hash: &str, data: &[u8]) -> Result<(), DbError> {
+        let cf = self
+            .inner
+            .cf_handle(Self::CF_BASE_CHUNK)
+            .expect(&format!("cf {} must exist", Self::CF_BASE_CHUNK));
+        self.inner.put_cf(cf, chunk_hash, data)?;
+        Ok(())
+    }
+
+    pub fn get_base_chunk(&self, chunk_hash: &str) -> Result<Option<Vec<u8>>, DbError> {
+        let cf = self
+            .inner
+            .cf_handle(Self::CF_BASE_CHUNK)
+            .expect(&format!("cf {} must exist", Self::CF_BASE_CHUNK));
+        Ok(self.inner.get_cf(cf, chunk_hash)?)
+    }
+
+    pub fn delete_base_chunk(&self, chunk_hash: &str) -> Result<(), DbError> {
+        let cf = self
+            .inner
+            .cf_handle(Self::CF_BASE_CHUNK)
+            .expect(&format!("cf {} must exist", Self::CF_BASE_CHUNK));
+        self.inner.delete_cf(cf, chunk_hash)?;
+        Ok(())
+    }
+}
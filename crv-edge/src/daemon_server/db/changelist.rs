@@ -4,9 +4,9 @@ use crv_core::path::basic::WorkspacePath;
 
 #[derive(Encode, Decode)]
 pub struct ChangelistMeta {
-    description: String,
-    workspace_name: String,
-    workspace_paths: Vec<WorkspacePath>,
+    pub description: String,
+    pub workspace_name: String,
+    pub workspace_paths: Vec<WorkspacePath>,
 }
 
 impl DbManager {
@@ -0,0 +1,339 @@
+//! Checkpoint 是工作区本地状态（active file 与已同步文件的元数据）的快照，
+//! 用于在高风险操作前保存现场，之后无需访问 hive 即可回滚本地状态。
+
+use crate::daemon_server::db::active_file::Action;
+use crate::daemon_server::db::file::FileMeta;
+use crate::daemon_server::db::*;
+use bincode::{Decode, Encode};
+use crv_core::path::basic::WorkspacePath;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(Encode, Decode)]
+pub struct CheckpointDoc {
+    pub workspace_name: String,
+    /// 创建时间，unix 时间戳（秒）
+    pub created_at: i64,
+    /// 保存时工作区下的全部 active file
+    pub active_files: Vec<(WorkspacePath, Action)>,
+    /// 保存时工作区下的全部已同步文件元数据，即当时的同步位置
+    pub files: Vec<(WorkspacePath, FileMeta)>,
+}
+
+impl DbManager {
+    fn checkpoint_key(workspace_name: &str, name: &str) -> String {
+        format!("{workspace_name}/{name}")
+    }
+
+    /// 工作区下所有 workspace path 的公共前缀
+    fn workspace_key_prefix(workspace_name: &str) -> String {
+        format!("//{workspace_name}/")
+    }
+
+    /// 在一个事务中读取工作区当前的 active file 与文件元数据，并写入名为 `name` 的 checkpoint。
+    ///
+    /// 同名 checkpoint 已存在时返回 DbError::Invalid。
+    pub fn save_checkpoint(&self, workspace_name: &str, name: &str) -> Result<(), DbError> {
+        let key = Self::checkpoint_key(workspace_name, name);
+        let prefix = Self::workspace_key_prefix(workspace_name);
+        let created_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs() as i64)
+            .unwrap_or_default();
+
+        loop {
+            let transaction = self.inner.transaction();
+            let checkpoint_cf = self
+                .inner
+                .cf_handle(Self::CF_CHECKPOINT)
+                .expect(&format!("cf {} must exist", Self::CF_CHECKPOINT));
+            let active_file_cf = self
+                .inner
+                .cf_handle(Self::CF_ACTIVE_FILE)
+                .expect(&format!("cf {} must exist", Self::CF_ACTIVE_FILE));
+            let file_cf = self
+                .inner
+                .cf_handle(Self::CF_FILE)
+                .expect(&format!("cf {} must exist", Self::CF_FILE));
+
+            if transaction.get_cf(checkpoint_cf, &key)?.is_some() {
+                return Err(DbError::Invalid(format!(
+                    "Checkpoint {name} already exists in workspace {workspace_name}."
+                )));
+            }
+
+            let mut active_files = Vec::new();
+            let iter = transaction.iterator_cf(
+                active_file_cf,
+                IteratorMode::From(prefix.as_bytes(), rocksdb::Direction::Forward),
+            );
+            for item in iter {
+                let (key, value) = item?;
+                if !key.starts_with(prefix.as_bytes()) {
+                    break;
+                }
+                let path = Self::decode_workspace_path_key(&key)?;
+                let action: Action =
+                    bincode::decode_from_slice(&value, bincode::config::standard())?.0;
+                active_files.push((path, action));
+            }
+
+            let mut files = Vec::new();
+            let iter = transaction.iterator_cf(
+                file_cf,
+                IteratorMode::From(prefix.as_bytes(), rocksdb::Direction::Forward),
+            );
+            for item in iter {
+                let (key, value) = item?;
+                if !key.starts_with(prefix.as_bytes()) {
+                    break;
+                }
+                let path = Self::decode_workspace_path_key(&key)?;
+                let meta: FileMeta =
+                    bincode::decode_from_slice(&value, bincode::config::standard())?.0;
+                files.push((path, meta));
+            }
+
+            let doc = CheckpointDoc {
+                workspace_name: workspace_name.to_string(),
+                created_at,
+                active_files,
+                files,
+            };
+            transaction.put_cf(
+                checkpoint_cf,
+                &key,
+                bincode::encode_to_vec(doc, bincode::config::standard())?,
+            )?;
+
+            if transaction.commit().is_ok() {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn get_checkpoint(
+        &self,
+        workspace_name: &str,
+        name: &str,
+    ) -> Result<Option<CheckpointDoc>, DbError> {
+        let cf = self
+            .inner
+            .cf_handle(Self::CF_CHECKPOINT)
+            .expect(&format!("cf {} must exist", Self::CF_CHECKPOINT));
+        match self
+            .inner
+            .get_cf(cf, Self::checkpoint_key(workspace_name, name))?
+        {
+            Some(bytes) => {
+                let doc: CheckpointDoc =
+                    bincode::decode_from_slice(&bytes, bincode::config::standard())?.0;
+                Ok(Some(doc))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// 列出工作区下的全部 checkpoint，返回 (名称, 快照) 列表
+    pub fn list_checkpoints(
+        &self,
+        workspace_name: &str,
+    ) -> Result<Vec<(String, CheckpointDoc)>, DbError> {
+        let cf = self
+            .inner
+            .cf_handle(Self::CF_CHECKPOINT)
+            .expect(&format!("cf {} must exist", Self::CF_CHECKPOINT));
+        let prefix = format!("{workspace_name}/");
+        let iter = self.inner.iterator_cf(
+            cf,
+            IteratorMode::From(prefix.as_bytes(), rocksdb::Direction::Forward),
+        );
+
+        let mut result = Vec::new();
+        for item in iter {
+            let (key, value) = item?;
+            if !key.starts_with(prefix.as_bytes()) {
+                break;
+            }
+            let name = String::from_utf8_lossy(&key[prefix.len()..]).to_string();
+            let doc: CheckpointDoc =
+                bincode::decode_from_slice(&value, bincode::config::standard())?.0;
+            result.push((name, doc));
+        }
+
+        Ok(result)
+    }
+
+    pub fn delete_checkpoint(&self, workspace_name: &str, name: &str) -> Result<(), DbError> {
+        let cf = self
+            .inner
+            .cf_handle(Self::CF_CHECKPOINT)
+            .expect(&format!("cf {} must exist", Self::CF_CHECKPOINT));
+        let key = Self::checkpoint_key(workspace_name, name);
+        if self.inner.get_cf(cf, &key)?.is_none() {
+            return Err(DbError::NotFound(format!(
+                "Checkpoint {name} does not exist in workspace {workspace_name}."
+            )));
+        }
+        self.inner.delete_cf(cf, key)?;
+        Ok(())
+    }
+
+    /// 在一个事务中用 checkpoint 覆盖工作区当前的 active file 与文件元数据，返回被恢复的快照。
+    ///
+    /// 该方法只负责元数据，本地文件内容需要调用方根据快照中的 chunk hash 自行恢复。
+    pub fn restore_checkpoint(
+        &self,
+        workspace_name: &str,
+        name: &str,
+    ) -> Result<CheckpointDoc, DbError> {
+        let key = Self::checkpoint_key(workspace_name, name);
+        let prefix = Self::workspace_key_prefix(workspace_name);
+
+        loop {
+            let transaction = self.inner.transaction();
+            let checkpoint_cf = self
+                .inner
+                .cf_handle(Self::CF_CHECKPOINT)
+                .expect(&format!("cf {} must exist", Self::CF_CHECKPOINT));
+            let active_file_cf = self
+                .inner
+                .cf_handle(Self::CF_ACTIVE_FILE)
+                .expect(&format!("cf {} must exist", Self::CF_ACTIVE_FILE));
+            let file_cf = self
+                .inner
+                .cf_handle(Self::CF_FILE)
+                .expect(&format!("cf {} must exist", Self::CF_FILE));
+
+            let doc: CheckpointDoc = match transaction.get_cf(checkpoint_cf, &key)? {
+                Some(bytes) => bincode::decode_from_slice(&bytes, bincode::config::standard())?.0,
+                None => {
+                    return Err(DbError::NotFound(format!(
+                        "Checkpoint {name} does not exist in workspace {workspace_name}."
+                    )));
+                }
+            };
+
+            // 清空工作区当前的状态
+            for cf in [active_file_cf, file_cf] {
+                let iter = transaction.iterator_cf(
+                    cf,
+                    IteratorMode::From(prefix.as_bytes(), rocksdb::Direction::Forward),
+                );
+                for item in iter {
+                    let (key, _) = item?;
+                    if !key.starts_with(prefix.as_bytes()) {
+                        break;
+                    }
+                    transaction.delete_cf(cf, key)?;
+                }
+            }
+
+            // 写入快照
+            for (path, action) in &doc.active_files {
+                transaction.put_cf(
+                    active_file_cf,
+                    path.to_custom_string(),
+                    bincode::encode_to_vec(action, bincode::config::standard())?,
+                )?;
+            }
+            for (path, meta) in &doc.files {
+                transaction.put_cf(
+                    file_cf,
+                    path.to_custom_string(),
+                    bincode::encode_to_vec(meta, bincode::config::standard())?,
+                )?;
+            }
+
+            if transaction.commit().is_ok() {
+                return Ok(doc);
+            }
+        }
+    }
+
+    fn decode_workspace_path_key(key: &[u8]) -> Result<WorkspacePath, DbError> {
+        let path_string = String::from_utf8_lossy(key);
+        WorkspacePath::parse(&path_string)
+            .map_err(|e| DbError::Invalid(format!("Can't parse workspace path {path_string}: {e}")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::daemon_server::db::file::{FileLocation, FileRevision};
+    use crate::test_support::TempDb;
+    use crv_core::path::basic::{DepotPath, LocalPath, WorkspaceDir};
+
+    fn file_meta(path: &WorkspacePath, revision: i64) -> FileMeta {
+        FileMeta {
+            location: FileLocation {
+                local_path: LocalPath::parse(&format!("/root/ws/{}", path.file)).unwrap(),
+                workspace_path: path.clone(),
+                depot_path: DepotPath::parse(&format!("//depot/{}", path.file)).unwrap(),
+            },
+            current_revision: FileRevision {
+                generation: 1,
+                revision,
+            },
+            changelist_id: revision,
+            size: 0,
+            chunk_hashes: vec![],
+        }
+    }
+
+    #[test]
+    fn save_and_restore_checkpoint() {
+        let db = TempDb::new();
+
+        let a = WorkspacePath::parse("//ws/a.txt").unwrap();
+        let b = WorkspacePath::parse("//ws/b.txt").unwrap();
+        let other = WorkspacePath::parse("//other/c.txt").unwrap();
+
+        db.set_file_meta(a.clone(), file_meta(&a, 1)).unwrap();
+        db.set_active_file_action(a.clone(), Action::Edit).unwrap();
+        db.set_file_meta(other.clone(), file_meta(&other, 1))
+            .unwrap();
+        db.save_checkpoint("ws", "before").unwrap();
+        assert!(db.save_checkpoint("ws", "before").is_err());
+
+        // 保存之后的改动
+        db.set_file_meta(a.clone(), file_meta(&a, 2)).unwrap();
+        db.set_file_meta(b.clone(), file_meta(&b, 1)).unwrap();
+        db.set_active_file_action(b.clone(), Action::Delete)
+            .unwrap();
+        db.set_file_meta(other.clone(), file_meta(&other, 5))
+            .unwrap();
+
+        let doc = db.restore_checkpoint("ws", "before").unwrap();
+        assert_eq!(doc.files.len(), 1);
+        assert_eq!(doc.active_files.len(), 1);
+
+        let ws_root = WorkspaceDir {
+            workspace_name: "ws".to_string(),
+            dirs: vec![],
+        };
+        let files = db.get_file_meta_under_dir(&ws_root).unwrap();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].1.current_revision.revision, 1);
+        let active = db.get_active_file_under_dir(&ws_root).unwrap();
+        assert_eq!(active.len(), 1);
+        assert!(active[0].1 == Action::Edit);
+        // 其他工作区不受影响
+        assert_eq!(
+            db.get_file_meta(&other)
+                .unwrap()
+                .unwrap()
+                .current_revision
+                .revision,
+            5
+        );
+
+        let checkpoints = db.list_checkpoints("ws").unwrap();
+        assert_eq!(checkpoints.len(), 1);
+        assert_eq!(checkpoints[0].0, "before");
+        db.delete_checkpoint("ws", "before").unwrap();
+        assert!(db.get_checkpoint("ws", "before").unwrap().is_none());
+    }
+}
@@ -1,6 +1,14 @@
 use crate::daemon_server::{config::RuntimeConfigOverride, db::*};
 
 impl DbManager {
+    const KEY_HEALTH_SENTINEL: &'static str = "health-sentinel";
+    /// 运行时配置项的名称及其在 app_config 列族中的 key
+    const RUNTIME_CONFIG_KEYS: [(&'static str, &'static str); 3] = [
+        ("remote_addr", "remote-addr"),
+        ("editor", "editor"),
+        ("user", "user"),
+    ];
+
     pub fn load_runtime_config(&self) -> Result<RuntimeConfigOverride, DbError> {
         let remote_addr = self.get_config("remote-addr")?;
         let editor = self.get_config("editor")?;
@@ -39,4 +47,31 @@ impl DbManager {
         self.inner.put_cf(cf, key, value.as_bytes())?;
         Ok(())
     }
+
+    /// 持久化一项运行时配置，`key` 不是运行时配置项时返回 DbError::Invalid
+    pub fn set_runtime_config(&self, key: &str, value: &str) -> Result<(), DbError> {
+        let Some((_, db_key)) = Self::RUNTIME_CONFIG_KEYS
+            .iter()
+            .find(|(name, _)| *name == key)
+        else {
+            return Err(DbError::Invalid(format!(
+                "Unknown config key {key}, expected one of: {}.",
+                Self::RUNTIME_CONFIG_KEYS.map(|(name, _)| name).join(", ")
+            )));
+        };
+        self.set_config(db_key, value)
+    }
+
+    /// 打开数据库时写入哨兵 key，供健康检查读取
+    pub(super) fn write_health_sentinel(&self) -> Result<(), DbError> {
+        self.set_config(Self::KEY_HEALTH_SENTINEL, "ok")
+    }
+
+    /// 通过读取哨兵 key 检查数据库是否可用
+    pub fn check_health(&self) -> Result<(), DbError> {
+        match self.get_config(Self::KEY_HEALTH_SENTINEL)? {
+            Some(_) => Ok(()),
+            None => Err(DbError::NotFound("health sentinel is missing".to_string())),
+        }
+    }
 }
@@ -19,6 +19,12 @@ pub struct FileLocation {
 pub struct FileMeta {
     pub location: FileLocation,
     pub current_revision: FileRevision,
+    /// 当前版本所属的 changelist
+    pub changelist_id: i64,
+    /// 当前版本的文件大小
+    pub size: i64,
+    /// 当前版本的 chunk hash 列表
+    pub chunk_hashes: Vec<String>,
 }
 
 impl DbManager {
@@ -93,20 +99,16 @@ impl DbManager {
         return Ok(result);
     }
 
+    /// 将文件从 active file 中移除并写入最新 revision 信息，两者在同一个事务中提交
     pub fn submit_file(&self, path: WorkspacePath, file_meta: FileMeta) -> Result<(), DbError> {
-        // 将文件从 active file 中移除
-        let cf = self
-            .inner
-            .cf_handle(Self::CF_ACTIVE_FILE)
-            .expect(&format!("cf {} must exist", Self::CF_ACTIVE_FILE));
-        self.inner.delete_cf(cf, path.to_custom_string())?;
-        // 写入最新 revision 信息
-        let cf = self
-            .inner
-            .cf_handle(Self::CF_FILE)
-            .expect(&format!("cf {} must exist", Self::CF_FILE));
-        let bytes = bincode::encode_to_vec(file_meta, bincode::config::standard())?;
-        self.inner.put_cf(cf, path.to_custom_string(), bytes)?;
+        loop {
+            let mut transaction = self.begin_transaction();
+            transaction.delete_active_file(&path)?;
+            transaction.set_file(&path, &file_meta)?;
+            if transaction.commit().is_ok() {
+                break;
+            }
+        }
         Ok(())
     }
 }
@@ -0,0 +1,65 @@
+//! 最近一次从 hive 获取到的文件树的本地缓存，hive 不可用时同步只使用缓存计算同步计划
+
+use crate::daemon_server::db::*;
+use crv_core::metadata::CompressionType;
+
+/// hive 上某个文件最近一次看到的 revision
+#[derive(Encode, Decode, Clone, Debug, PartialEq)]
+pub struct CachedFileRevision {
+    pub depot_path: String,
+    pub generation: i64,
+    pub revision: i64,
+    pub changelist_id: i64,
+    pub chunk_hashes: Vec<String>,
+    pub size: i64,
+    /// 整个文件内容的哈希，旧版本的 revision 为空
+    pub content_hash: String,
+    /// 下载 chunk 时使用的压缩方式
+    pub compression_type: CompressionType,
+}
+
+impl DbManager {
+    /// 用新获取到的文件树替换整个缓存
+    pub fn replace_hive_cache(&self, files: &[CachedFileRevision]) -> Result<(), DbError> {
+        loop {
+            let transaction = self.inner.transaction();
+            let cf = self
+                .inner
+                .cf_handle(Self::CF_HIVE_CACHE)
+                .expect(&format!("cf {} must exist", Self::CF_HIVE_CACHE));
+
+            for item in transaction.iterator_cf(cf, IteratorMode::Start) {
+                let (key, _) = item?;
+                transaction.delete_cf(cf, key)?;
+            }
+            for file in files {
+                transaction.put_cf(
+                    cf,
+                    &file.depot_path,
+                    bincode::encode_to_vec(file, bincode::config::standard())?,
+                )?;
+            }
+
+            if transaction.commit().is_ok() {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// 读取缓存的文件树，按 depot path 排序
+    pub fn get_hive_cache(&self) -> Result<Vec<CachedFileRevision>, DbError> {
+        let cf = self
+            .inner
+            .cf_handle(Self::CF_HIVE_CACHE)
+            .expect(&format!("cf {} must exist", Self::CF_HIVE_CACHE));
+        let mut files = Vec::new();
+        for item in self.inner.iterator_cf(cf, IteratorMode::Start) {
+            let (_, value) = item?;
+            let file: CachedFileRevision =
+                bincode::decode_from_slice(&value, bincode::config::standard())?.0;
+            files.push(file);
+        }
+        Ok(files)
+    }
+}
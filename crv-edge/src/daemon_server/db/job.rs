@@ -0,0 +1,66 @@
+//! Job 的持久化，用于 daemon 重启后恢复未完成的 Job
+
+use crate::daemon_server::db::*;
+use crate::daemon_server::job::{JobData, JobRetentionPolicy, WorkerProtocol};
+
+/// 持久化到数据库中的 Job
+#[derive(Encode, Decode, Debug, Clone, PartialEq)]
+pub struct JobRecord {
+    pub data: JobData,
+    pub protocol: WorkerProtocol,
+    pub retention_policy: JobRetentionPolicy,
+}
+
+impl DbManager {
+    /// 写入 Job，已存在时覆盖
+    pub fn set_job(&self, record: &JobRecord) -> Result<(), DbError> {
+        let cf = self
+            .inner
+            .cf_handle(Self::CF_JOB)
+            .expect(&format!("cf {} must exist", Self::CF_JOB));
+        let bytes = bincode::encode_to_vec(record, bincode::config::standard())?;
+        self.inner.put_cf(cf, &record.data.id, bytes)?;
+        Ok(())
+    }
+
+    pub fn get_job(&self, id: &str) -> Result<Option<JobRecord>, DbError> {
+        let cf = self
+            .inner
+            .cf_handle(Self::CF_JOB)
+            .expect(&format!("cf {} must exist", Self::CF_JOB));
+        match self.inner.get_cf(cf, id)? {
+            Some(bytes) => {
+                let record: JobRecord =
+                    bincode::decode_from_slice(&bytes, bincode::config::standard())?.0;
+                Ok(Some(record))
+            }
+            None => Ok(None),
+        }
+    }
+
+    pub fn delete_job(&self, id: &str) -> Result<(), DbError> {
+        let cf = self
+            .inner
+            .cf_handle(Self::CF_JOB)
+            .expect(&format!("cf {} must exist", Self::CF_JOB));
+        self.inner.delete_cf(cf, id)?;
+        Ok(())
+    }
+
+    /// 读取所有持久化的 Job
+    pub fn list_jobs(&self) -> Result<Vec<JobRecord>, DbError> {
+        let cf = self
+            .inner
+            .cf_handle(Self::CF_JOB)
+            .expect(&format!("cf {} must exist", Self::CF_JOB));
+
+        let mut result = Vec::new();
+        for item in self.inner.iterator_cf(cf, IteratorMode::Start) {
+            let (_, value) = item?;
+            let record: JobRecord =
+                bincode::decode_from_slice(&value, bincode::config::standard())?.0;
+            result.push(record);
+        }
+        Ok(result)
+    }
+}
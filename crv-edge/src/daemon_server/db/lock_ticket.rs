@@ -0,0 +1,26 @@
+//! 通过 crv lock 获得的读锁 ticket，按工作区路径保存，用于在 describe 中展示
+
+use crate::daemon_server::db::*;
+use crv_core::path::basic::WorkspacePath;
+
+impl DbManager {
+    pub fn set_lock_ticket(&self, path: &WorkspacePath, ticket: &str) -> Result<(), DbError> {
+        let cf = self
+            .inner
+            .cf_handle(Self::CF_LOCK_TICKET)
+            .expect(&format!("cf {} must exist", Self::CF_LOCK_TICKET));
+        self.inner.put_cf(cf, path.to_custom_string(), ticket)?;
+        Ok(())
+    }
+
+    pub fn get_lock_ticket(&self, path: &WorkspacePath) -> Result<Option<String>, DbError> {
+        let cf = self
+            .inner
+            .cf_handle(Self::CF_LOCK_TICKET)
+            .expect(&format!("cf {} must exist", Self::CF_LOCK_TICKET));
+        Ok(self
+            .inner
+            .get_cf(cf, path.to_custom_string())?
+            .map(|bytes| String::from_utf8_lossy(&bytes).into_owned()))
+    }
+}
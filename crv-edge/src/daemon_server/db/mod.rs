@@ -1,12 +1,19 @@
 pub mod active_file;
+pub mod base_chunk;
 pub mod changelist;
+pub mod checkpoint;
 pub mod config;
 pub mod file;
+pub mod hive_cache;
+pub mod job;
+pub mod lock_ticket;
+pub mod schema;
+pub mod transaction;
 pub mod workspace;
 
 use bincode::{Decode, Encode};
 use rocksdb::{ColumnFamilyDescriptor, IteratorMode, OptimisticTransactionDB, Options};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use thiserror::Error;
 
@@ -38,6 +45,8 @@ pub struct DbManager {
     // 使用 Arc 让 DB 可以在多线程（gRPC handlers）间安全共享
     // rust-rocksdb 的 DB 本身是 Thread-safe 的
     inner: Arc<OptimisticTransactionDB>,
+    /// 数据库所在的根目录
+    root: PathBuf,
 }
 
 impl DbManager {
@@ -48,6 +57,11 @@ impl DbManager {
     const CF_FILE: &'static str = "file";
     const CF_CHANGELIST: &'static str = "changelist";
     const CF_ACTIVE_FILE: &'static str = "active_file";
+    const CF_CHECKPOINT: &'static str = "checkpoint";
+    const CF_JOB: &'static str = "jobs";
+    const CF_BASE_CHUNK: &'static str = "base_chunks";
+    const CF_HIVE_CACHE: &'static str = "hive_cache";
+    const CF_LOCK_TICKET: &'static str = "lock_ticket";
 
     pub fn new<P: AsRef<Path>>(root: P) -> Result<Self, DbError> {
         let mut opts = Options::default();
@@ -65,11 +79,53 @@ impl DbManager {
             ColumnFamilyDescriptor::new(Self::CF_FILE, Options::default()),
             ColumnFamilyDescriptor::new(Self::CF_CHANGELIST, Options::default()),
             ColumnFamilyDescriptor::new(Self::CF_ACTIVE_FILE, Options::default()),
+            ColumnFamilyDescriptor::new(Self::CF_CHECKPOINT, Options::default()),
+            ColumnFamilyDescriptor::new(Self::CF_JOB, Options::default()),
+            ColumnFamilyDescriptor::new(Self::CF_BASE_CHUNK, Options::default()),
+            ColumnFamilyDescriptor::new(Self::CF_HIVE_CACHE, Options::default()),
+            ColumnFamilyDescriptor::new(Self::CF_LOCK_TICKET, Options::default()),
         ];
 
         let db = OptimisticTransactionDB::open_cf_descriptors(&opts, path, cfs)?;
-        Ok(Self {
+        let manager = Self {
             inner: Arc::new(db),
-        })
+            root: root.to_path_buf(),
+        };
+        manager.migrate_schema()?;
+        manager.write_health_sentinel()?;
+        Ok(manager)
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// 各列族的估计数据大小（字节）
+    pub fn column_family_sizes(&self) -> Result<Vec<(&'static str, u64)>, DbError> {
+        let mut result = vec![];
+        for name in [
+            Self::CF_APP_CONFIG,
+            Self::CF_WORKSPACE,
+            Self::CF_META_REVISION,
+            Self::CF_FILE,
+            Self::CF_CHANGELIST,
+            Self::CF_ACTIVE_FILE,
+            Self::CF_CHECKPOINT,
+            Self::CF_JOB,
+            Self::CF_BASE_CHUNK,
+            Self::CF_HIVE_CACHE,
+            Self::CF_LOCK_TICKET,
+        ] {
+            let cf = self
+                .inner
+                .cf_handle(name)
+                .expect(&format!("cf {} must exist", name));
+            let size = self
+                .inner
+                .property_int_value_cf(&cf, "rocksdb.estimate-live-data-size")?
+                .unwrap_or(0);
+            result.push((name, size));
+        }
+        Ok(result)
     }
 }
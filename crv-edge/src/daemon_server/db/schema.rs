@@ -0,0 +1,296 @@
+use crate::daemon_server::db::file::{FileLocation, FileMeta, FileRevision};
+use crate::daemon_server::db::hive_cache::CachedFileRevision;
+use crate::daemon_server::db::*;
+use crv_core::metadata::CompressionType;
+use rocksdb::Transaction;
+
+/// 当前数据库结构的版本。修改持久化格式时递增，并在 [`MIGRATIONS`] 末尾追加对应的迁移函数
+pub const SCHEMA_VERSION: u32 = 2;
+
+type Migration = fn(&DbManager, &Transaction<'_, OptimisticTransactionDB>) -> Result<(), DbError>;
+
+/// 第 i 个迁移函数把数据库从版本 i 升级到版本 i + 1
+const MIGRATIONS: [Migration; SCHEMA_VERSION as usize] =
+    [DbManager::migrate_v0_to_v1, DbManager::migrate_v1_to_v2];
+
+/// 最初的文件元数据格式，只记录了文件的位置与版本
+#[derive(Encode, Decode)]
+struct FileMetaV0 {
+    location: FileLocation,
+    current_revision: FileRevision,
+}
+
+/// v1 中 hive 文件树缓存的格式，没有记录 revision 的压缩方式
+#[derive(Encode, Decode)]
+struct CachedFileRevisionV1 {
+    depot_path: String,
+    generation: i64,
+    revision: i64,
+    changelist_id: i64,
+    chunk_hashes: Vec<String>,
+    size: i64,
+    content_hash: String,
+}
+
+impl DbManager {
+    const KEY_SCHEMA_VERSION: &'static str = "schema_version";
+
+    /// 数据库中记录的结构版本，没有记录时为 0
+    pub fn schema_version(&self) -> Result<u32, DbError> {
+        let cf = self
+            .inner
+            .cf_handle(Self::CF_APP_CONFIG)
+            .expect(&format!("cf {} must exist", Self::CF_APP_CONFIG));
+        match self.inner.get_cf(cf, Self::KEY_SCHEMA_VERSION)? {
+            Some(bytes) => String::from_utf8_lossy(&bytes)
+                .parse::<u32>()
+                .map_err(|e| DbError::Invalid(format!("Bad schema version: {e}"))),
+            None => Ok(0),
+        }
+    }
+
+    /// 依次执行迁移函数，把数据库升级到 [`SCHEMA_VERSION`]
+    ///
+    /// 每一步迁移与版本号的更新在同一个事务中提交，中途失败时数据库停留在上一个完整的版本。
+    pub(super) fn migrate_schema(&self) -> Result<(), DbError> {
+        let stored = self.schema_version()?;
+        if stored > SCHEMA_VERSION {
+            return Err(DbError::Invalid(format!(
+                "Database schema version {stored} is newer than the supported version {SCHEMA_VERSION}"
+            )));
+        }
+
+        let cf = self
+            .inner
+            .cf_handle(Self::CF_APP_CONFIG)
+            .expect(&format!("cf {} must exist", Self::CF_APP_CONFIG));
+        for version in stored..SCHEMA_VERSION {
+            let transaction = self.inner.transaction();
+            MIGRATIONS[version as usize](self, &transaction)?;
+            transaction.put_cf(cf, Self::KEY_SCHEMA_VERSION, format!("{}", version + 1))?;
+            transaction.commit()?;
+        }
+        Ok(())
+    }
+
+    /// v0 没有记录版本号。最初的文件元数据只有位置与版本，之后追加了 changelist、文件大小与
+    /// chunk hash，但没有迁移旧数据，因此 v0 的数据库中两种格式并存。
+    ///
+    /// 旧格式的记录补齐为 0 与空列表，下一次同步该文件时会写入真实的值。
+    fn migrate_v0_to_v1(
+        &self,
+        transaction: &Transaction<'_, OptimisticTransactionDB>,
+    ) -> Result<(), DbError> {
+        let config = bincode::config::standard();
+        let cf = self
+            .inner
+            .cf_handle(Self::CF_FILE)
+            .expect(&format!("cf {} must exist", Self::CF_FILE));
+        for item in transaction.iterator_cf(cf, IteratorMode::Start) {
+            let (key, value) = item?;
+            // 旧格式的字节不足以解码出新格式，新格式解码为旧格式时会剩余字节
+            match bincode::decode_from_slice::<FileMeta, _>(&value, config) {
+                Ok((_, read)) if read == value.len() => continue,
+                _ => {}
+            }
+            let (old, read): (FileMetaV0, usize) = bincode::decode_from_slice(&value, config)?;
+            if read != value.len() {
+                return Err(DbError::Invalid(format!(
+                    "Unknown file meta format for {}",
+                    String::from_utf8_lossy(&key)
+                )));
+            }
+            let file = FileMeta {
+                location: old.location,
+                current_revision: old.current_revision,
+                changelist_id: 0,
+                size: 0,
+                chunk_hashes: vec![],
+            };
+            transaction.put_cf(cf, key, bincode::encode_to_vec(&file, config)?)?;
+        }
+        Ok(())
+    }
+
+    /// v2 在 hive 文件树缓存中记录了 revision 的压缩方式，v1 缓存的 revision 都没有压缩
+    fn migrate_v1_to_v2(
+        &self,
+        transaction: &Transaction<'_, OptimisticTransactionDB>,
+    ) -> Result<(), DbError> {
+        let cf = self
+            .inner
+            .cf_handle(Self::CF_HIVE_CACHE)
+            .expect(&format!("cf {} must exist", Self::CF_HIVE_CACHE));
+        for item in transaction.iterator_cf(cf, IteratorMode::Start) {
+            let (key, value) = item?;
+            let old: CachedFileRevisionV1 =
+                bincode::decode_from_slice(&value, bincode::config::standard())?.0;
+            let file = CachedFileRevision {
+                depot_path: old.depot_path,
+                generation: old.generation,
+                revision: old.revision,
+                changelist_id: old.changelist_id,
+                chunk_hashes: old.chunk_hashes,
+                size: old.size,
+                content_hash: old.content_hash,
+                compression_type: CompressionType::None,
+            };
+            transaction.put_cf(
+                cf,
+                key,
+                bincode::encode_to_vec(&file, bincode::config::standard())?,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+    use crv_core::path::basic::{DepotPath, LocalPath, WorkspacePath};
+
+    fn location(file: &str) -> FileLocation {
+        FileLocation {
+            local_path: LocalPath::parse(&format!("/root/ws/{file}")).unwrap(),
+            workspace_path: WorkspacePath::parse(&format!("//ws/{file}")).unwrap(),
+            depot_path: DepotPath::parse(&format!("//{file}")).unwrap(),
+        }
+    }
+
+    #[test]
+    fn v0_database_is_migrated_without_data_loss() {
+        let dir = TempDir::new();
+        let root = dir.path();
+        let changelist_id;
+        {
+            let db = DbManager::new(root).unwrap();
+            db.set_config("remote-addr", "127.0.0.1:34560").unwrap();
+            changelist_id = db
+                .create_changelist("v0 changelist".to_string(), "ws".to_string())
+                .unwrap();
+            // 最初的格式写入的文件元数据
+            let cf = db.inner.cf_handle(DbManager::CF_FILE).unwrap();
+            let old = FileMetaV0 {
+                location: location("a.txt"),
+                current_revision: FileRevision {
+                    generation: 1,
+                    revision: 3,
+                },
+            };
+            db.inner
+                .put_cf(
+                    cf,
+                    "//ws/a.txt",
+                    bincode::encode_to_vec(&old, bincode::config::standard()).unwrap(),
+                )
+                .unwrap();
+            // 追加字段之后、记录版本号之前写入的文件元数据
+            db.set_file_meta(
+                WorkspacePath::parse("//ws/b.txt").unwrap(),
+                FileMeta {
+                    location: location("b.txt"),
+                    current_revision: FileRevision {
+                        generation: 2,
+                        revision: 1,
+                    },
+                    changelist_id: 7,
+                    size: 5,
+                    chunk_hashes: vec!["bb".to_string()],
+                },
+            )
+            .unwrap();
+            // 删除版本号，模拟 v0 版本写入的数据库
+            let cf = db.inner.cf_handle(DbManager::CF_APP_CONFIG).unwrap();
+            db.inner
+                .delete_cf(cf, DbManager::KEY_SCHEMA_VERSION)
+                .unwrap();
+            assert_eq!(db.schema_version().unwrap(), 0);
+        }
+
+        let db = DbManager::new(root).unwrap();
+        assert_eq!(db.schema_version().unwrap(), SCHEMA_VERSION);
+        assert_eq!(
+            db.load_runtime_config().unwrap().remote_addr.as_deref(),
+            Some("127.0.0.1:34560")
+        );
+        let changelist = db.get_changelist_meta(&changelist_id).unwrap().unwrap();
+        assert_eq!(changelist.description, "v0 changelist");
+
+        let a = db
+            .get_file_meta(&WorkspacePath::parse("//ws/a.txt").unwrap())
+            .unwrap()
+            .unwrap();
+        assert_eq!(a.location.depot_path.to_custom_string(), "//a.txt");
+        assert_eq!(a.location.workspace_path.to_custom_string(), "//ws/a.txt");
+        assert_eq!(
+            (a.current_revision.generation, a.current_revision.revision),
+            (1, 3)
+        );
+        assert_eq!((a.changelist_id, a.size), (0, 0));
+        assert!(a.chunk_hashes.is_empty());
+
+        let b = db
+            .get_file_meta(&WorkspacePath::parse("//ws/b.txt").unwrap())
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            (b.current_revision.generation, b.current_revision.revision),
+            (2, 1)
+        );
+        assert_eq!((b.changelist_id, b.size), (7, 5));
+        assert_eq!(b.chunk_hashes, vec!["bb"]);
+    }
+
+    #[test]
+    fn v1_hive_cache_is_migrated_as_uncompressed() {
+        let dir = TempDir::new();
+        let root = dir.path();
+        {
+            let db = DbManager::new(root).unwrap();
+            let old = CachedFileRevisionV1 {
+                depot_path: "//a.txt".to_string(),
+                generation: 1,
+                revision: 2,
+                changelist_id: 3,
+                chunk_hashes: vec!["aa".to_string()],
+                size: 4,
+                content_hash: "aa".to_string(),
+            };
+            let cf = db.inner.cf_handle(DbManager::CF_HIVE_CACHE).unwrap();
+            db.inner
+                .put_cf(
+                    cf,
+                    &old.depot_path,
+                    bincode::encode_to_vec(&old, bincode::config::standard()).unwrap(),
+                )
+                .unwrap();
+            db.set_config(DbManager::KEY_SCHEMA_VERSION, "1").unwrap();
+        }
+
+        let db = DbManager::new(root).unwrap();
+        assert_eq!(db.schema_version().unwrap(), SCHEMA_VERSION);
+        let files = db.get_hive_cache().unwrap();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].revision, 2);
+        assert_eq!(files[0].content_hash, "aa");
+        assert_eq!(files[0].compression_type, CompressionType::None);
+    }
+
+    #[test]
+    fn newer_schema_version_is_rejected() {
+        let dir = TempDir::new();
+        let root = dir.path();
+        {
+            let db = DbManager::new(root).unwrap();
+            db.set_config(
+                DbManager::KEY_SCHEMA_VERSION,
+                &format!("{}", SCHEMA_VERSION + 1),
+            )
+            .unwrap();
+        }
+
+        assert!(matches!(DbManager::new(root), Err(DbError::Invalid(_))));
+    }
+}
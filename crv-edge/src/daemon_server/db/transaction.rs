@@ -0,0 +1,208 @@
+//! 跨列族的原子写入，用于需要同时修改多个列族的操作，如提交文件、删除 workspace
+
+use crate::daemon_server::db::active_file::Action;
+use crate::daemon_server::db::file::FileMeta;
+use crate::daemon_server::db::workspace::WorkspaceMeta;
+use crate::daemon_server::db::*;
+use crv_core::path::basic::WorkspacePath;
+use rocksdb::{ColumnFamily, Transaction};
+
+/// 对 OptimisticTransactionDB 事务的封装，写入在 [`DbTransaction::commit`] 之前对其他读者不可见，
+/// 未 commit 就 drop 时所有写入都会被丢弃
+///
+/// 事务是乐观的：commit 时若事务读写过的 key 已被其他写入修改，commit 返回 DbError::RocksDb，
+/// 调用方需要重新开始事务并重试
+pub struct DbTransaction<'a> {
+    manager: &'a DbManager,
+    inner: Transaction<'a, OptimisticTransactionDB>,
+}
+
+impl DbManager {
+    pub fn begin_transaction(&self) -> DbTransaction<'_> {
+        DbTransaction {
+            manager: self,
+            inner: self.inner.transaction(),
+        }
+    }
+}
+
+impl<'a> DbTransaction<'a> {
+    fn cf(&self, name: &str) -> &'a ColumnFamily {
+        self.manager
+            .inner
+            .cf_handle(name)
+            .expect(&format!("cf {} must exist", name))
+    }
+
+    /// 写入 workspace 的元数据，并更新 workspace 的 META_REVISION
+    pub fn set_workspace(
+        &mut self,
+        workspace_name: &str,
+        meta: &WorkspaceMeta,
+    ) -> Result<(), DbError> {
+        let bytes = bincode::encode_to_vec(meta, bincode::config::standard())?;
+        self.inner
+            .put_cf(self.cf(DbManager::CF_WORKSPACE), workspace_name, bytes)?;
+        self.inner.put_cf(
+            self.cf(DbManager::CF_META_REVISION),
+            DbManager::KEY_WORKSPACE_META_REVISON,
+            uuid::Uuid::new_v4().as_bytes(),
+        )?;
+        Ok(())
+    }
+
+    /// 删除 workspace 的元数据，若它是激活的 workspace 则一并取消激活
+    pub fn delete_workspace(&mut self, workspace_name: &str) -> Result<(), DbError> {
+        self.inner
+            .delete_cf(self.cf(DbManager::CF_WORKSPACE), workspace_name)?;
+        let app_config_cf = self.cf(DbManager::CF_APP_CONFIG);
+        let active = self
+            .inner
+            .get_cf(app_config_cf, DbManager::KEY_ACTIVE_WORKSPACE)?;
+        if active.as_deref() == Some(workspace_name.as_bytes()) {
+            self.inner
+                .delete_cf(app_config_cf, DbManager::KEY_ACTIVE_WORKSPACE)?;
+        }
+        Ok(())
+    }
+
+    pub fn set_file(&mut self, path: &WorkspacePath, meta: &FileMeta) -> Result<(), DbError> {
+        let bytes = bincode::encode_to_vec(meta, bincode::config::standard())?;
+        self.inner
+            .put_cf(self.cf(DbManager::CF_FILE), path.to_custom_string(), bytes)?;
+        Ok(())
+    }
+
+    pub fn delete_file(&mut self, path: &WorkspacePath) -> Result<(), DbError> {
+        self.inner
+            .delete_cf(self.cf(DbManager::CF_FILE), path.to_custom_string())?;
+        Ok(())
+    }
+
+    pub fn set_active_file_action(
+        &mut self,
+        path: &WorkspacePath,
+        action: &Action,
+    ) -> Result<(), DbError> {
+        let bytes = bincode::encode_to_vec(action, bincode::config::standard())?;
+        self.inner.put_cf(
+            self.cf(DbManager::CF_ACTIVE_FILE),
+            path.to_custom_string(),
+            bytes,
+        )?;
+        Ok(())
+    }
+
+    pub fn delete_active_file(&mut self, path: &WorkspacePath) -> Result<(), DbError> {
+        self.inner
+            .delete_cf(self.cf(DbManager::CF_ACTIVE_FILE), path.to_custom_string())?;
+        Ok(())
+    }
+
+    /// 删除 workspace 下的所有 active file，返回删除的条目数
+    pub fn delete_active_files_for_workspace(
+        &mut self,
+        workspace_name: &str,
+    ) -> Result<u64, DbError> {
+        let cf = self.cf(DbManager::CF_ACTIVE_FILE);
+        // key 为 WorkspacePath 的字符串形式，以 `//{workspace_name}/` 开头
+        let prefix = format!("//{workspace_name}/");
+        let prefix_bytes = prefix.as_bytes();
+
+        let mut keys = Vec::new();
+        for item in self.inner.iterator_cf(
+            cf,
+            IteratorMode::From(prefix_bytes, rocksdb::Direction::Forward),
+        ) {
+            let (key, _) = item?;
+            if !key.starts_with(prefix_bytes) {
+                break;
+            }
+            keys.push(key);
+        }
+        for key in &keys {
+            self.inner.delete_cf(cf, key)?;
+        }
+        Ok(keys.len() as u64)
+    }
+
+    pub fn commit(self) -> Result<(), DbError> {
+        self.inner.commit()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::daemon_server::db::file::{FileLocation, FileRevision};
+    use crate::test_support::TempDb;
+    use crv_core::path::basic::{DepotPath, LocalPath};
+
+    fn file_meta(path: &WorkspacePath, revision: i64) -> FileMeta {
+        FileMeta {
+            location: FileLocation {
+                local_path: LocalPath::parse(&format!("/root/ws/{}", path.file)).unwrap(),
+                workspace_path: path.clone(),
+                depot_path: DepotPath::parse(&format!("//depot/{}", path.file)).unwrap(),
+            },
+            current_revision: FileRevision {
+                generation: 1,
+                revision,
+            },
+            changelist_id: revision,
+            size: 0,
+            chunk_hashes: vec![],
+        }
+    }
+
+    fn revision(db: &DbManager, path: &WorkspacePath) -> Option<i64> {
+        db.get_file_meta(path)
+            .unwrap()
+            .map(|meta| meta.current_revision.revision)
+    }
+
+    #[test]
+    fn commit_applies_all_writes() {
+        let db = TempDb::new();
+        let a = WorkspacePath::parse("//ws/a.txt").unwrap();
+        let b = WorkspacePath::parse("//ws/b.txt").unwrap();
+        db.set_file_meta(a.clone(), file_meta(&a, 1)).unwrap();
+        db.set_active_file_action(a.clone(), Action::Edit).unwrap();
+
+        let mut transaction = db.begin_transaction();
+        transaction.delete_active_file(&a).unwrap();
+        transaction.set_file(&a, &file_meta(&a, 2)).unwrap();
+        transaction
+            .set_active_file_action(&b, &Action::Add)
+            .unwrap();
+        // commit 之前其他读者看不到写入
+        assert_eq!(revision(&db, &a), Some(1));
+        assert!(db.get_active_file_action(&b).unwrap().is_none());
+        transaction.commit().unwrap();
+
+        assert_eq!(revision(&db, &a), Some(2));
+        assert!(db.get_active_file_action(&a).unwrap().is_none());
+        assert!(db.get_active_file_action(&b).unwrap() == Some(Action::Add));
+    }
+
+    #[test]
+    fn dropped_transaction_leaves_db_unchanged() {
+        let db = TempDb::new();
+        let a = WorkspacePath::parse("//ws/a.txt").unwrap();
+        let b = WorkspacePath::parse("//ws/b.txt").unwrap();
+        db.set_file_meta(a.clone(), file_meta(&a, 1)).unwrap();
+        db.set_active_file_action(a.clone(), Action::Edit).unwrap();
+
+        {
+            let mut transaction = db.begin_transaction();
+            transaction.delete_active_file(&a).unwrap();
+            transaction.delete_file(&a).unwrap();
+            transaction.set_file(&b, &file_meta(&b, 1)).unwrap();
+        }
+
+        assert_eq!(revision(&db, &a), Some(1));
+        assert!(db.get_active_file_action(&a).unwrap() == Some(Action::Edit));
+        assert_eq!(revision(&db, &b), None);
+    }
+}
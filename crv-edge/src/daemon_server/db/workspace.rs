@@ -1,6 +1,9 @@
 use crate::daemon_server::db::*;
 use bincode::{Decode, Encode};
-use crv_core::{path::basic::LocalDir, workspace::entity::WorkspaceConfig};
+use crv_core::{
+    path::basic::LocalDir,
+    workspace::entity::{WorkspaceConfig, WorkspaceMapping},
+};
 
 #[derive(Encode, Decode)]
 pub struct WorkspaceMeta {
@@ -9,7 +12,9 @@ pub struct WorkspaceMeta {
 }
 
 impl DbManager {
-    const KEY_WORKSPACE_META_REVISON: &'static str = "workspace";
+    pub(super) const KEY_WORKSPACE_META_REVISON: &'static str = "workspace";
+    /// 激活的 workspace 存放在 app_config 列族中，workspace 列族只保存 WorkspaceMeta
+    pub(super) const KEY_ACTIVE_WORKSPACE: &'static str = "active-workspace";
 
     /// 这个方法用于创建一个 workspace，它会检查预创建的 workspace 的 root path 是否和
     /// 某个已有的 worksapce 的 root path 相冲突，但是不会检查 mapping views 是否合法，
@@ -116,6 +121,64 @@ impl DbManager {
         Ok(())
     }
 
+    /// 删除处于 Pending 状态的 workspace，用于创建失败时的回滚；已确认的 workspace 不会被删除
+    pub fn discard_pending_workspace(&self, workspace_name: &String) -> Result<(), DbError> {
+        let Some(workspace_meta) = self.get_workspace_meta(workspace_name)? else {
+            return Ok(());
+        };
+        if workspace_meta.status != Status::Pending {
+            return Err(DbError::Invalid(format!(
+                "{workspace_name} has been confirmed."
+            )));
+        }
+        let cf = self
+            .inner
+            .cf_handle(Self::CF_WORKSPACE)
+            .expect(&format!("cf {} must exist", Self::CF_WORKSPACE));
+        self.inner.delete_cf(cf, workspace_name)?;
+        Ok(())
+    }
+
+    /// 删除已确认的 workspace，若它是激活的 workspace 则一并取消激活；不会清理 workspace 下的 active file
+    pub fn delete_workspace(&self, workspace_name: &String) -> Result<(), DbError> {
+        if self.get_confirmed_workspace_meta(workspace_name)?.is_none() {
+            return Err(DbError::NotFound(format!(
+                "{workspace_name} does not exist."
+            )));
+        }
+        loop {
+            let mut transaction = self.begin_transaction();
+            transaction.delete_workspace(workspace_name)?;
+            if transaction.commit().is_ok() {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// 替换已确认的 workspace 的映射列表，不检查映射是否冲突，调用方需要先完成检查
+    pub fn set_workspace_mappings(
+        &self,
+        workspace_name: &String,
+        mappings: Vec<WorkspaceMapping>,
+    ) -> Result<(), DbError> {
+        let Some(mut workspace_meta) = self.get_confirmed_workspace_meta(workspace_name)? else {
+            return Err(DbError::NotFound(format!(
+                "{workspace_name} does not exist."
+            )));
+        };
+        workspace_meta.config.mappings = mappings;
+
+        loop {
+            let mut transaction = self.begin_transaction();
+            transaction.set_workspace(workspace_name, &workspace_meta)?;
+            if transaction.commit().is_ok() {
+                break;
+            }
+        }
+        Ok(())
+    }
+
     fn get_workspace_meta(
         &self,
         workspace_name: &String,
@@ -205,4 +268,55 @@ impl DbManager {
 
         Ok(workspace_names)
     }
+
+    pub fn get_active_workspace(&self) -> Result<Option<String>, DbError> {
+        let cf = self
+            .inner
+            .cf_handle(Self::CF_APP_CONFIG)
+            .expect(&format!("cf {} must exist", Self::CF_APP_CONFIG));
+        Ok(self
+            .inner
+            .get_cf(cf, Self::KEY_ACTIVE_WORKSPACE)?
+            .map(|bytes| String::from_utf8_lossy(&bytes).to_string()))
+    }
+
+    /// 以 compare-and-set 的方式修改激活的 workspace，`workspace_name` 为 None 表示没有激活的 workspace。
+    ///
+    /// 当前值与 `expected` 不一致时（例如另一个切换请求已经先完成）返回 DbError::Invalid。
+    pub fn switch_active_workspace(
+        &self,
+        expected: Option<&str>,
+        workspace_name: Option<&str>,
+    ) -> Result<(), DbError> {
+        loop {
+            let transaction = self.inner.transaction();
+            let cf = self
+                .inner
+                .cf_handle(Self::CF_APP_CONFIG)
+                .expect(&format!("cf {} must exist", Self::CF_APP_CONFIG));
+
+            let current = transaction
+                .get_cf(cf, Self::KEY_ACTIVE_WORKSPACE)?
+                .map(|bytes| String::from_utf8_lossy(&bytes).to_string());
+            if current.as_deref() != expected {
+                return Err(DbError::Invalid(format!(
+                    "Active workspace has been changed to {} by others.",
+                    current.unwrap_or("<none>".to_string())
+                )));
+            }
+
+            match workspace_name {
+                Some(workspace_name) => {
+                    transaction.put_cf(cf, Self::KEY_ACTIVE_WORKSPACE, workspace_name.as_bytes())?
+                }
+                None => transaction.delete_cf(cf, Self::KEY_ACTIVE_WORKSPACE)?,
+            }
+
+            if transaction.commit().is_ok() {
+                break;
+            }
+        }
+
+        Ok(())
+    }
 }
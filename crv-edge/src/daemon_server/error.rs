@@ -26,6 +26,19 @@ pub enum AppError {
 
     #[error("Resource not found: {0}")]
     NotFound(String),
+
+    #[error(
+        "Hive version {major}.{minor} is incompatible, requires at least {}.{}",
+        crate::hive_client::MIN_HIVE_MAJOR,
+        crate::hive_client::MIN_HIVE_MINOR
+    )]
+    HiveVersionIncompatible { major: u32, minor: u32 },
+
+    #[error("Pre-submit hook rejected the submit: {0}")]
+    PreSubmitHookFailed(String),
+
+    #[error("Update failed: {0}")]
+    UpdateFailed(String),
 }
 
 impl From<Status> for AppError {
@@ -45,6 +58,10 @@ impl From<AppError> for Status {
             AppError::Raw(status) => status,
             AppError::HiveClient(msg) => Status::internal(format!("Hive Client Error: {}", msg)),
             AppError::NotFound(msg) => Status::not_found(msg),
+            AppError::UpdateFailed(_) => Status::unavailable(err.to_string()),
+            AppError::HiveVersionIncompatible { .. } | AppError::PreSubmitHookFailed(_) => {
+                Status::failed_precondition(err.to_string())
+            }
         }
     }
 }
@@ -0,0 +1,50 @@
+use crate::daemon_server::config::RuntimeConfig;
+use crate::daemon_server::error::AppResult;
+use crate::daemon_server::state::AppState;
+use crate::hive_pb::{self, hive_service_client::HiveServiceClient};
+use crate::pb::{CherryPickConflict, CherryPickReq, CherryPickRsp};
+use tonic::{Request, Response};
+
+pub async fn handle(
+    state: AppState,
+    req: Request<CherryPickReq>,
+) -> AppResult<Response<CherryPickRsp>> {
+    let runtime_config = RuntimeConfig::from_req(&req)?;
+    let channel = state
+        .hive_channel
+        .get_channel(&runtime_config.remote_addr.value)?;
+
+    let mut hive_client = HiveServiceClient::new(channel);
+
+    // hive 需要登录用户，透传调用方携带的 authorization 头
+    let authorization = req.metadata().get("authorization").cloned();
+    let request_body = req.into_inner();
+    let mut hive_req = Request::new(hive_pb::CherryPickReq {
+        source_branch_id: request_body.source_branch_id,
+        changelist_id: request_body.changelist_id,
+        target_branch_id: request_body.target_branch_id,
+        description: request_body.description,
+    });
+    if let Some(authorization) = authorization {
+        hive_req
+            .metadata_mut()
+            .insert("authorization", authorization);
+    }
+
+    let hive_rsp = hive_client.cherry_pick(hive_req).await?.into_inner();
+
+    Ok(Response::new(CherryPickRsp {
+        new_changelist_id: hive_rsp.new_changelist_id,
+        conflicts: hive_rsp
+            .conflicts
+            .into_iter()
+            .map(|c| CherryPickConflict {
+                path: c.path,
+                expected_generation: c.expected_file_generation,
+                expected_revision: c.expected_file_revision,
+                current_generation: c.current_file_generation,
+                current_revision: c.current_file_revision,
+            })
+            .collect(),
+    }))
+}
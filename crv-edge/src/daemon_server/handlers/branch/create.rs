@@ -0,0 +1,41 @@
+use crate::daemon_server::config::RuntimeConfig;
+use crate::daemon_server::error::AppResult;
+use crate::daemon_server::state::AppState;
+use crate::hive_pb::{self, hive_service_client::HiveServiceClient};
+use crate::pb::{CreateBranchReq, CreateBranchRsp};
+use tonic::{Request, Response};
+
+pub async fn handle(
+    state: AppState,
+    req: Request<CreateBranchReq>,
+) -> AppResult<Response<CreateBranchRsp>> {
+    let runtime_config = RuntimeConfig::from_req(&req)?;
+    let channel = state
+        .hive_channel
+        .get_channel(&runtime_config.remote_addr.value)?;
+
+    let mut hive_client = HiveServiceClient::new(channel);
+
+    // hive 需要登录用户，透传调用方携带的 authorization 头
+    let authorization = req.metadata().get("authorization").cloned();
+    let request_body = req.into_inner();
+    let mut hive_req = Request::new(hive_pb::CreateBranchReq {
+        branch_id: request_body.branch_id,
+        description: request_body.description,
+        base_branch_id: request_body.base_branch_id,
+        owners: request_body.owners,
+        at_changelist_id: request_body.at_changelist_id,
+    });
+    if let Some(authorization) = authorization {
+        hive_req
+            .metadata_mut()
+            .insert("authorization", authorization);
+    }
+
+    let hive_rsp = hive_client.create_branch(hive_req).await?.into_inner();
+
+    Ok(Response::new(CreateBranchRsp {
+        success: hive_rsp.success,
+        message: hive_rsp.message,
+    }))
+}
@@ -0,0 +1,40 @@
+use crate::daemon_server::config::RuntimeConfig;
+use crate::daemon_server::error::AppResult;
+use crate::daemon_server::state::AppState;
+use crate::hive_pb::{self, hive_service_client::HiveServiceClient};
+use crate::pb::{GetBranchDiffReq, GetBranchDiffRsp};
+use tonic::{Request, Response};
+
+pub async fn handle(
+    state: AppState,
+    req: Request<GetBranchDiffReq>,
+) -> AppResult<Response<GetBranchDiffRsp>> {
+    let runtime_config = RuntimeConfig::from_req(&req)?;
+    let channel = state
+        .hive_channel
+        .get_channel(&runtime_config.remote_addr.value)?;
+
+    let mut hive_client = HiveServiceClient::new(channel);
+
+    // hive 需要登录用户，透传调用方携带的 authorization 头
+    let authorization = req.metadata().get("authorization").cloned();
+    let request_body = req.into_inner();
+    let mut hive_req = Request::new(hive_pb::GetBranchDiffReq {
+        branch_a_id: request_body.branch_a_id,
+        branch_b_id: request_body.branch_b_id,
+        depot_wildcard: request_body.depot_wildcard,
+    });
+    if let Some(authorization) = authorization {
+        hive_req
+            .metadata_mut()
+            .insert("authorization", authorization);
+    }
+
+    let hive_rsp = hive_client.get_branch_diff(hive_req).await?.into_inner();
+
+    Ok(Response::new(GetBranchDiffRsp {
+        only_in_a: hive_rsp.only_in_a,
+        only_in_b: hive_rsp.only_in_b,
+        modified: hive_rsp.modified,
+    }))
+}
@@ -0,0 +1,53 @@
+use crate::daemon_server::config::RuntimeConfig;
+use crate::daemon_server::error::AppResult;
+use crate::daemon_server::state::AppState;
+use crate::hive_pb::{self, hive_service_client::HiveServiceClient};
+use crate::pb::{BranchSortField, BranchSummary, ListBranchesReq, ListBranchesRsp};
+use tonic::{Request, Response};
+
+pub async fn handle(
+    state: AppState,
+    req: Request<ListBranchesReq>,
+) -> AppResult<Response<ListBranchesRsp>> {
+    let runtime_config = RuntimeConfig::from_req(&req)?;
+    let channel = state
+        .hive_channel
+        .get_channel(&runtime_config.remote_addr.value)?;
+
+    let mut hive_client = HiveServiceClient::new(channel);
+
+    // hive 需要登录用户，透传调用方携带的 authorization 头
+    let authorization = req.metadata().get("authorization").cloned();
+    let request_body = req.into_inner();
+    let sort_by = match request_body.sort_by() {
+        BranchSortField::CreatedAt => hive_pb::BranchSortField::CreatedAt,
+        BranchSortField::HeadChangelistId => hive_pb::BranchSortField::HeadChangelistId,
+    };
+    let mut hive_req = Request::new(hive_pb::ListBranchesReq {
+        page: request_body.page,
+        page_size: request_body.page_size,
+        sort_by: sort_by as i32,
+    });
+    if let Some(authorization) = authorization {
+        hive_req
+            .metadata_mut()
+            .insert("authorization", authorization);
+    }
+
+    let hive_rsp = hive_client.list_branches(hive_req).await?.into_inner();
+
+    Ok(Response::new(ListBranchesRsp {
+        branches: hive_rsp
+            .branches
+            .into_iter()
+            .map(|b| BranchSummary {
+                id: b.id,
+                description: b.description,
+                head_changelist_id: b.head_changelist_id,
+                created_by: b.created_by,
+                created_at: b.created_at,
+            })
+            .collect(),
+        total_count: hive_rsp.total_count,
+    }))
+}
@@ -0,0 +1,6 @@
+pub mod cherry_pick;
+pub mod create;
+pub mod diff;
+pub mod list;
+pub mod set_description_format;
+pub mod set_protection;
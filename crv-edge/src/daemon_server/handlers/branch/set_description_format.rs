@@ -0,0 +1,35 @@
+use crate::daemon_server::config::RuntimeConfig;
+use crate::daemon_server::error::AppResult;
+use crate::daemon_server::state::AppState;
+use crate::hive_pb::{self, hive_service_client::HiveServiceClient};
+use crate::pb::{SetBranchDescriptionFormatReq, SetBranchDescriptionFormatRsp};
+use tonic::{Request, Response};
+
+pub async fn handle(
+    state: AppState,
+    req: Request<SetBranchDescriptionFormatReq>,
+) -> AppResult<Response<SetBranchDescriptionFormatRsp>> {
+    let runtime_config = RuntimeConfig::from_req(&req)?;
+    let channel = state
+        .hive_channel
+        .get_channel(&runtime_config.remote_addr.value)?;
+
+    let mut hive_client = HiveServiceClient::new(channel);
+
+    // hive 需要管理员权限，透传调用方携带的 authorization 头
+    let authorization = req.metadata().get("authorization").cloned();
+    let request_body = req.into_inner();
+    let mut hive_req = Request::new(hive_pb::SetBranchDescriptionFormatReq {
+        branch_id: request_body.branch_id,
+        regex: request_body.regex,
+    });
+    if let Some(authorization) = authorization {
+        hive_req
+            .metadata_mut()
+            .insert("authorization", authorization);
+    }
+
+    hive_client.set_branch_description_format(hive_req).await?;
+
+    Ok(Response::new(SetBranchDescriptionFormatRsp {}))
+}
@@ -0,0 +1,86 @@
+use crate::daemon_server::error::{AppError, AppResult};
+use crate::daemon_server::state::AppState;
+use crate::pb::{DeleteChangelistReq, DeleteChangelistRsp};
+use tonic::{Request, Response, Status};
+
+/// 删除 changelist，同时 revert 其中记录的文件（删除对应的 active file）
+pub async fn handle(
+    state: AppState,
+    req: Request<DeleteChangelistReq>,
+) -> AppResult<Response<DeleteChangelistRsp>> {
+    let request_body = req.into_inner();
+
+    let meta = state
+        .db
+        .get_changelist_meta(&request_body.changelist_id)?
+        .ok_or(AppError::Raw(Status::not_found(format!(
+            "Changelist {} not found.",
+            request_body.changelist_id
+        ))))?;
+    if meta.workspace_name != request_body.workspace_name {
+        return Err(AppError::Raw(Status::invalid_argument(format!(
+            "Changelist {} does not belong to workspace {}.",
+            request_body.changelist_id, request_body.workspace_name
+        ))));
+    }
+
+    state
+        .db
+        .cleanup_active_files_for_changelist(&request_body.changelist_id)?;
+    state.db.delete_changelist(&request_body.changelist_id)?;
+
+    Ok(Response::new(DeleteChangelistRsp {}))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::daemon_server::db::active_file::Action;
+    use crate::test_support::TempDb;
+    use crv_core::path::basic::WorkspacePath;
+
+    #[tokio::test]
+    async fn deleting_changelist_reverts_its_files() {
+        let db = TempDb::new();
+        let state = db.state();
+        let in_changelist: Vec<_> = (0..3)
+            .map(|i| WorkspacePath::parse(&format!("//ws/file{i}.txt")).unwrap())
+            .collect();
+        let outside = WorkspacePath::parse("//ws/other.txt").unwrap();
+        for path in in_changelist.iter().chain([&outside]) {
+            state
+                .db
+                .set_active_file_action(path.clone(), Action::Edit)
+                .unwrap();
+        }
+        let changelist_id = state
+            .db
+            .create_changelist("fix".to_string(), "ws".to_string())
+            .unwrap();
+        state
+            .db
+            .append_changelist_workspace_paths(&changelist_id, in_changelist.clone())
+            .unwrap();
+
+        let req = |workspace_name: &str| {
+            Request::new(DeleteChangelistReq {
+                workspace_name: workspace_name.to_string(),
+                changelist_id: changelist_id.clone(),
+            })
+        };
+        handle(state.clone(), req("ws2")).await.unwrap_err();
+        handle(state.clone(), req("ws")).await.unwrap();
+
+        assert!(
+            state
+                .db
+                .get_changelist_meta(&changelist_id)
+                .unwrap()
+                .is_none()
+        );
+        for path in &in_changelist {
+            assert!(state.db.get_active_file_action(path).unwrap().is_none());
+        }
+        assert!(state.db.get_active_file_action(&outside).unwrap().is_some());
+    }
+}
@@ -0,0 +1,137 @@
+use crate::daemon_server::error::{AppError, AppResult};
+use crate::daemon_server::state::AppState;
+use crate::pb::{ChangelistFile, ChangelistInfo, DescribeChangelistReq, DescribeChangelistRsp};
+use tonic::{Request, Response, Status};
+
+/// 本地 changelist 都是尚未提交的
+const PENDING_STATUS: &str = "pending";
+
+pub async fn handle(
+    state: AppState,
+    req: Request<DescribeChangelistReq>,
+) -> AppResult<Response<DescribeChangelistRsp>> {
+    let request_body = req.into_inner();
+
+    let meta = state
+        .db
+        .get_changelist_meta(&request_body.changelist_id)?
+        .ok_or(AppError::Raw(Status::not_found(format!(
+            "Changelist {} not found.",
+            request_body.changelist_id
+        ))))?;
+
+    let mut files = Vec::new();
+    if request_body.list_files {
+        for path in &meta.workspace_paths {
+            // 文件可能已经 revert，此时 action 为空
+            let action = state
+                .db
+                .get_active_file_action(path)?
+                .map(|x| x.to_custom_string())
+                .unwrap_or_default();
+            let (generation, revision) = match state.db.get_file_meta(path)? {
+                Some(file_meta) => (
+                    file_meta.current_revision.generation,
+                    file_meta.current_revision.revision,
+                ),
+                None => (0, 0),
+            };
+            files.push(ChangelistFile {
+                path: path.to_custom_string(),
+                action,
+                generation,
+                revision,
+            });
+        }
+    }
+
+    Ok(Response::new(DescribeChangelistRsp {
+        workspace_name: meta.workspace_name,
+        changelist: Some(ChangelistInfo {
+            id: request_body.changelist_id,
+            description: meta.description,
+            file_count: meta.workspace_paths.len() as i32,
+            status: PENDING_STATUS.to_string(),
+        }),
+        files,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::daemon_server::db::active_file::Action;
+    use crate::test_support::TempDb;
+    use crv_core::path::basic::WorkspacePath;
+
+    #[tokio::test]
+    async fn describe_lists_files_with_actions() {
+        let db = TempDb::new();
+        let state = db.state();
+        let changelist_id = state
+            .db
+            .create_changelist("Fix the login page".to_string(), "ws".to_string())
+            .unwrap();
+        let added = WorkspacePath::parse("//ws/login.html").unwrap();
+        let reverted = WorkspacePath::parse("//ws/style.css").unwrap();
+        state
+            .db
+            .set_active_file_action(added.clone(), Action::Add)
+            .unwrap();
+        state
+            .db
+            .append_changelist_workspace_paths(&changelist_id, vec![added, reverted])
+            .unwrap();
+
+        let rsp = handle(
+            state.clone(),
+            Request::new(DescribeChangelistReq {
+                changelist_id: changelist_id.clone(),
+                list_files: true,
+            }),
+        )
+        .await
+        .unwrap()
+        .into_inner();
+
+        assert_eq!(rsp.workspace_name, "ws");
+        let changelist = rsp.changelist.unwrap();
+        assert_eq!(changelist.id, changelist_id);
+        assert_eq!(changelist.description, "Fix the login page");
+        assert_eq!(changelist.file_count, 2);
+        assert_eq!(changelist.status, "pending");
+        let files: Vec<_> = rsp
+            .files
+            .iter()
+            .map(|file| (file.path.as_str(), file.action.as_str()))
+            .collect();
+        assert_eq!(
+            files,
+            vec![("//ws/login.html", "add"), ("//ws/style.css", "")]
+        );
+
+        // 不需要文件列表时只返回摘要
+        let rsp = handle(
+            state.clone(),
+            Request::new(DescribeChangelistReq {
+                changelist_id,
+                list_files: false,
+            }),
+        )
+        .await
+        .unwrap()
+        .into_inner();
+        assert!(rsp.files.is_empty());
+        assert_eq!(rsp.changelist.unwrap().file_count, 2);
+
+        let missing = handle(
+            state,
+            Request::new(DescribeChangelistReq {
+                changelist_id: "404".to_string(),
+                list_files: true,
+            }),
+        )
+        .await;
+        assert!(missing.is_err());
+    }
+}
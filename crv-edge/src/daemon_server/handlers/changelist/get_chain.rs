@@ -0,0 +1,43 @@
+use crate::daemon_server::config::RuntimeConfig;
+use crate::daemon_server::error::AppResult;
+use crate::daemon_server::state::AppState;
+use crate::hive_pb::{self, hive_service_client::HiveServiceClient};
+use crate::pb::{ChangelistSummary, GetChangelistChainReq, GetChangelistChainRsp};
+use tonic::{Request, Response};
+
+pub async fn handle(
+    state: AppState,
+    req: Request<GetChangelistChainReq>,
+) -> AppResult<Response<GetChangelistChainRsp>> {
+    let runtime_config = RuntimeConfig::from_req(&req)?;
+    let channel = state
+        .hive_channel
+        .get_channel(&runtime_config.remote_addr.value)?;
+
+    let mut hive_client = HiveServiceClient::new(channel);
+
+    let request_body = req.into_inner();
+    let hive_rsp = hive_client
+        .get_changelist_chain(hive_pb::GetChangelistChainReq {
+            branch_id: request_body.branch_id,
+            from_cl: request_body.from_cl,
+            to_cl: request_body.to_cl,
+        })
+        .await?
+        .into_inner();
+
+    Ok(Response::new(GetChangelistChainRsp {
+        changelists: hive_rsp
+            .changelists
+            .into_iter()
+            .map(|cl| ChangelistSummary {
+                changelist_id: cl.changelist_id,
+                author: cl.author,
+                description: cl.description,
+                committed_at: cl.committed_at,
+                files_count: cl.files_count,
+            })
+            .collect(),
+        truncated: hive_rsp.truncated,
+    }))
+}
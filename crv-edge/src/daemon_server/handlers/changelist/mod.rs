@@ -0,0 +1,3 @@
+pub mod delete;
+pub mod describe;
+pub mod get_chain;
@@ -28,3 +28,65 @@ pub async fn handle(state: AppState, req: Request<BonjourReq>) -> AppResult<Resp
 
     Ok(Response::new(response))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::daemon_server::config::{HiveClientConfig, RuntimeConfigItem, RuntimeConfigSource};
+    use crate::daemon_server::handlers::edge::stub_hive::StubHive;
+    use crate::hive_pb::hive_service_server::HiveServiceServer;
+    use crate::test_support::TempDb;
+    use tokio_stream::wrappers::TcpListenerStream;
+    use tonic::transport::{Identity, Server, ServerTlsConfig};
+
+    #[tokio::test]
+    async fn bonjour_hive_over_tls() {
+        // 测试时生成自签名证书，同时作为 hive 的服务端证书和 edge 信任的 CA
+        let certified = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+        let db = TempDb::new();
+        let ca_cert_path = db.root().join("hive.pem");
+        std::fs::write(&ca_cert_path, certified.cert.pem()).unwrap();
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let identity =
+            Identity::from_pem(certified.cert.pem(), certified.signing_key.serialize_pem());
+        tokio::spawn(
+            Server::builder()
+                .tls_config(ServerTlsConfig::new().identity(identity))
+                .unwrap()
+                .add_service(HiveServiceServer::new(StubHive::default()))
+                .serve_with_incoming(TcpListenerStream::new(listener)),
+        );
+
+        let state = AppState::with_hive_client_config(
+            db.db(),
+            HiveClientConfig {
+                tls: true,
+                ca_cert_path: Some(ca_cert_path),
+                ..Default::default()
+            },
+        );
+        let mut runtime_config = RuntimeConfig::default();
+        runtime_config.remote_addr = RuntimeConfigItem {
+            value: format!("https://localhost:{port}"),
+            source: RuntimeConfigSource::Override,
+        };
+        let mut req = Request::new(BonjourReq {});
+        req.extensions_mut().insert(runtime_config);
+
+        let rsp = handle(state, req).await.unwrap().into_inner();
+        assert_eq!(rsp.daemon_version, "1.2");
+        assert_eq!(rsp.platform, "test");
+    }
+
+    #[test]
+    fn tls_requires_https_address() {
+        let config = HiveClientConfig {
+            tls: true,
+            ..Default::default()
+        };
+        assert!(config.endpoint("http://localhost:34560").is_err());
+        assert!(config.endpoint("https://localhost:34560").is_ok());
+    }
+}
@@ -0,0 +1,42 @@
+use std::pin::Pin;
+
+use crate::daemon_server::config::RuntimeConfig;
+use crate::daemon_server::error::AppResult;
+use crate::daemon_server::state::AppState;
+use crate::hive_pb::{self, hive_service_client::HiveServiceClient};
+use crate::pb::{ExportRepositorySnapshotReq, ExportRepositorySnapshotRsp};
+use tokio_stream::{Stream, StreamExt};
+use tonic::{Request, Response, Status};
+
+pub type ExportRepositorySnapshotStream =
+    Pin<Box<dyn Stream<Item = Result<ExportRepositorySnapshotRsp, Status>> + Send>>;
+
+/// 让 hive 导出 chunk 仓库与元数据库的 tar 归档，原样转发 hive 返回的每一段数据
+pub async fn handle(
+    state: AppState,
+    req: Request<ExportRepositorySnapshotReq>,
+) -> AppResult<Response<ExportRepositorySnapshotStream>> {
+    let runtime_config = RuntimeConfig::from_req(&req)?;
+    let channel = state
+        .hive_channel
+        .get_channel(&runtime_config.remote_addr.value)?;
+
+    let mut hive_client = HiveServiceClient::new(channel);
+
+    // hive 需要管理员权限，透传调用方携带的 authorization 头
+    let mut hive_req = Request::new(hive_pb::ExportRepositorySnapshotReq {});
+    if let Some(authorization) = req.metadata().get("authorization").cloned() {
+        hive_req
+            .metadata_mut()
+            .insert("authorization", authorization);
+    }
+
+    let hive_stream = hive_client
+        .export_repository_snapshot(hive_req)
+        .await?
+        .into_inner();
+    let stream = hive_stream
+        .map(|message| message.map(|message| ExportRepositorySnapshotRsp { data: message.data }));
+
+    Ok(Response::new(Box::pin(stream)))
+}
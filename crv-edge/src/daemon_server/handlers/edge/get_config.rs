@@ -0,0 +1,25 @@
+use crate::daemon_server::config::RuntimeConfig;
+use crate::daemon_server::error::AppResult;
+use crate::daemon_server::state::AppState;
+use crate::pb::{GetConfigReq, GetConfigRsp};
+use tonic::{Request, Response, Status};
+
+/// 返回一项运行时配置当前生效的值及其来源
+pub async fn handle(
+    _state: AppState,
+    req: Request<GetConfigReq>,
+) -> AppResult<Response<GetConfigRsp>> {
+    let runtime_config = RuntimeConfig::from_req(&req)?;
+    let key = req.into_inner().key;
+
+    let item = runtime_config.get(&key).cloned().ok_or_else(|| {
+        Status::invalid_argument(format!(
+            "Unknown config key {key}, expected one of: {}.",
+            RuntimeConfig::KEYS.join(", ")
+        ))
+    })?;
+
+    Ok(Response::new(GetConfigRsp {
+        item: Some(item.into()),
+    }))
+}
@@ -0,0 +1,45 @@
+use crate::daemon_server::config::RuntimeConfig;
+use crate::daemon_server::error::AppResult;
+use crate::daemon_server::state::AppState;
+use crate::hive_pb::{self, hive_service_client::HiveServiceClient};
+use crate::pb::{BundleInfo, GetRepositoryLayoutReq, GetRepositoryLayoutRsp};
+use tonic::{Request, Response};
+
+/// 让 hive 检查 chunk 仓库中每个 pack 的完整性
+pub async fn handle(
+    state: AppState,
+    req: Request<GetRepositoryLayoutReq>,
+) -> AppResult<Response<GetRepositoryLayoutRsp>> {
+    let runtime_config = RuntimeConfig::from_req(&req)?;
+    let channel = state
+        .hive_channel
+        .get_channel(&runtime_config.remote_addr.value)?;
+
+    let mut hive_client = HiveServiceClient::new(channel);
+
+    // hive 需要管理员权限，透传调用方携带的 authorization 头
+    let mut hive_req = Request::new(hive_pb::GetRepositoryLayoutReq {});
+    if let Some(authorization) = req.metadata().get("authorization").cloned() {
+        hive_req
+            .metadata_mut()
+            .insert("authorization", authorization);
+    }
+
+    let hive_rsp = hive_client
+        .get_repository_layout(hive_req)
+        .await?
+        .into_inner();
+
+    Ok(Response::new(GetRepositoryLayoutRsp {
+        bundles: hive_rsp
+            .bundles
+            .into_iter()
+            .map(|b| BundleInfo {
+                id: b.id,
+                chunk_count: b.chunk_count,
+                size_bytes: b.size_bytes,
+                is_valid: b.is_valid,
+            })
+            .collect(),
+    }))
+}
@@ -0,0 +1,33 @@
+use crate::daemon_server::config::RuntimeConfig;
+use crate::daemon_server::error::AppResult;
+use crate::daemon_server::state::AppState;
+use crate::hive_pb::{self, hive_service_client::HiveServiceClient};
+use crate::pb::{GetRepositoryStatsReq, GetRepositoryStatsRsp};
+use tonic::{Request, Response};
+
+/// 向 hive 查询仓库的统计信息
+pub async fn handle(
+    state: AppState,
+    req: Request<GetRepositoryStatsReq>,
+) -> AppResult<Response<GetRepositoryStatsRsp>> {
+    let runtime_config = RuntimeConfig::from_req(&req)?;
+    let channel = state
+        .hive_channel
+        .get_channel(&runtime_config.remote_addr.value)?;
+
+    let mut hive_client = HiveServiceClient::new(channel);
+    let hive_rsp = hive_client
+        .get_repository_stats(hive_pb::GetRepositoryStatsReq {})
+        .await?
+        .into_inner();
+
+    Ok(Response::new(GetRepositoryStatsRsp {
+        total_files: hive_rsp.total_files,
+        total_changelists: hive_rsp.total_changelists,
+        total_branches: hive_rsp.total_branches,
+        total_chunk_bytes: hive_rsp.total_chunk_bytes,
+        dedup_ratio: hive_rsp.dedup_ratio,
+        oldest_cl_timestamp: hive_rsp.oldest_cl_timestamp,
+        newest_cl_timestamp: hive_rsp.newest_cl_timestamp,
+    }))
+}
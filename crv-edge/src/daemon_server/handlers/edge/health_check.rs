@@ -0,0 +1,94 @@
+use crate::daemon_server::config::RuntimeConfig;
+use crate::daemon_server::error::AppResult;
+use crate::daemon_server::state::AppState;
+use crate::hive_pb::{self, hive_service_client::HiveServiceClient};
+use crate::pb::{HealthCheckReq, HealthCheckRsp, HealthStatus};
+use std::time::Duration;
+use tonic::{Request, Response};
+
+/// 探测 hive 的超时时间，健康检查需要快速返回
+const HIVE_PROBE_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// 检查本地数据库、hive 连通性与磁盘剩余空间，可用于容器的就绪探针
+pub async fn handle(
+    state: AppState,
+    req: Request<HealthCheckReq>,
+) -> AppResult<Response<HealthCheckRsp>> {
+    let runtime_config = RuntimeConfig::from_req(&req)?;
+
+    let rocksdb_ok = state.db.check_health().is_ok();
+    let hive_reachable = probe_hive(&state, &runtime_config.remote_addr.value).await;
+    let disk_free_bytes = fs2::available_space(state.db.root()).unwrap_or(0);
+
+    let status = if !rocksdb_ok {
+        HealthStatus::Unhealthy
+    } else if !hive_reachable {
+        HealthStatus::Degraded
+    } else {
+        HealthStatus::Healthy
+    };
+
+    Ok(Response::new(HealthCheckRsp {
+        status: status.into(),
+        rocksdb_ok,
+        hive_reachable,
+        disk_free_bytes,
+    }))
+}
+
+async fn probe_hive(state: &AppState, remote_addr: &str) -> bool {
+    let Ok(channel) = state.hive_channel.get_channel(remote_addr) else {
+        return false;
+    };
+    let mut hive_client = HiveServiceClient::new(channel);
+    let probe = hive_client.bonjour(hive_pb::BonjourReq {});
+    matches!(
+        tokio::time::timeout(HIVE_PROBE_TIMEOUT, probe).await,
+        Ok(Ok(_))
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::daemon_server::config::{RuntimeConfigItem, RuntimeConfigSource};
+    use crate::daemon_server::handlers::edge::stub_hive::{self, StubHive};
+    use crate::test_support::TempDb;
+    use tonic::Status;
+
+    async fn health_check(hive: StubHive) -> HealthCheckRsp {
+        let db = TempDb::new();
+        let state = db.state();
+
+        let mut runtime_config = RuntimeConfig::default();
+        runtime_config.remote_addr = RuntimeConfigItem {
+            value: stub_hive::spawn(hive).await,
+            source: RuntimeConfigSource::Override,
+        };
+        let mut req = Request::new(HealthCheckReq {});
+        req.extensions_mut().insert(runtime_config);
+
+        handle(state, req).await.unwrap().into_inner()
+    }
+
+    #[tokio::test]
+    async fn healthy_when_hive_responds() {
+        let rsp = health_check(StubHive::default()).await;
+        assert_eq!(rsp.status(), HealthStatus::Healthy);
+        assert!(rsp.rocksdb_ok);
+        assert!(rsp.hive_reachable);
+        assert!(rsp.disk_free_bytes > 0);
+    }
+
+    #[tokio::test]
+    async fn hive_unavailable_is_reported_as_unreachable() {
+        let rsp = health_check(StubHive {
+            bonjour_error: Some(Status::unavailable("hive is down")),
+            ..Default::default()
+        })
+        .await;
+        assert_eq!(rsp.status(), HealthStatus::Degraded);
+        assert!(rsp.rocksdb_ok);
+        assert!(!rsp.hive_reachable);
+    }
+}
@@ -1,3 +1,15 @@
 pub mod bonjour;
 pub mod bonjour_hive;
+pub mod export_repository_snapshot;
+pub mod get_config;
+pub mod get_repository_layout;
+pub mod get_repository_stats;
 pub mod get_runtime_config;
+pub mod health_check;
+pub mod rebuild_index;
+pub mod set_config;
+pub mod update;
+pub mod watch;
+
+#[cfg(test)]
+pub(crate) mod stub_hive;
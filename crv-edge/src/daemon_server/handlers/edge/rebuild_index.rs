@@ -0,0 +1,34 @@
+use crate::daemon_server::config::RuntimeConfig;
+use crate::daemon_server::error::AppResult;
+use crate::daemon_server::state::AppState;
+use crate::hive_pb::{self, hive_service_client::HiveServiceClient};
+use crate::pb::{RebuildIndexReq, RebuildIndexRsp};
+use tonic::{Request, Response};
+
+/// 让 hive 从 pack 数据文件重建索引
+pub async fn handle(
+    state: AppState,
+    req: Request<RebuildIndexReq>,
+) -> AppResult<Response<RebuildIndexRsp>> {
+    let runtime_config = RuntimeConfig::from_req(&req)?;
+    let channel = state
+        .hive_channel
+        .get_channel(&runtime_config.remote_addr.value)?;
+
+    let mut hive_client = HiveServiceClient::new(channel);
+
+    // hive 需要管理员权限，透传调用方携带的 authorization 头
+    let mut hive_req = Request::new(hive_pb::RebuildIndexReq {});
+    if let Some(authorization) = req.metadata().get("authorization").cloned() {
+        hive_req
+            .metadata_mut()
+            .insert("authorization", authorization);
+    }
+
+    let hive_rsp = hive_client.rebuild_index(hive_req).await?.into_inner();
+
+    Ok(Response::new(RebuildIndexRsp {
+        packs: hive_rsp.packs,
+        chunks: hive_rsp.chunks,
+    }))
+}
@@ -0,0 +1,117 @@
+use crate::daemon_server::config::RuntimeConfig;
+use crate::daemon_server::error::AppResult;
+use crate::daemon_server::state::AppState;
+use crate::pb::{SetConfigReq, SetConfigRsp};
+use tonic::transport::Endpoint;
+use tonic::{Request, Response, Status};
+
+/// 持久化一项运行时配置。运行时配置在每个请求进入时从数据库读取，修改后的值对之后的请求立即生效，
+/// 不需要重启 daemon
+pub async fn handle(
+    state: AppState,
+    req: Request<SetConfigReq>,
+) -> AppResult<Response<SetConfigRsp>> {
+    let SetConfigReq { key, value } = req.into_inner();
+
+    if !RuntimeConfig::KEYS.contains(&key.as_str()) {
+        return Err(Status::invalid_argument(format!(
+            "Unknown config key {key}, expected one of: {}.",
+            RuntimeConfig::KEYS.join(", ")
+        ))
+        .into());
+    }
+    if value.trim().is_empty() {
+        return Err(Status::invalid_argument(format!("Value of {key} can't be empty.")).into());
+    }
+    if key == "remote_addr" {
+        Endpoint::from_shared(value.clone())
+            .map_err(|e| Status::invalid_argument(format!("Invalid hive address {value}: {e}")))?;
+    }
+
+    state.db.set_runtime_config(&key, &value)?;
+    Ok(Response::new(SetConfigRsp {}))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::daemon_server::handlers::edge::get_config;
+    use crate::daemon_server::middleware;
+    use crate::pb::GetConfigReq;
+    use crate::test_support::TempDb;
+
+    async fn set(state: &AppState, key: &str, value: &str) -> AppResult<Response<SetConfigRsp>> {
+        handle(
+            state.clone(),
+            Request::new(SetConfigReq {
+                key: key.to_string(),
+                value: value.to_string(),
+            }),
+        )
+        .await
+    }
+
+    /// 与真实请求一样经过 config 中间件，读取当前生效的配置
+    async fn get(state: &AppState, key: &str) -> (String, String) {
+        let (metadata, extensions, _) = middleware::config::call(state.clone(), Request::new(()))
+            .unwrap()
+            .into_parts();
+        let req = Request::from_parts(
+            metadata,
+            extensions,
+            GetConfigReq {
+                key: key.to_string(),
+            },
+        );
+        let item = get_config::handle(state.clone(), req)
+            .await
+            .unwrap()
+            .into_inner()
+            .item
+            .unwrap();
+        (item.value, item.source)
+    }
+
+    #[tokio::test]
+    async fn set_value_is_used_by_later_requests() {
+        let db = TempDb::new();
+        let state = db.state();
+        assert_eq!(get(&state, "editor").await.1, "default");
+
+        set(&state, "editor", "nano").await.unwrap();
+        set(&state, "remote_addr", "http://hive.example.com:34560")
+            .await
+            .unwrap();
+
+        assert_eq!(
+            get(&state, "editor").await,
+            ("nano".to_string(), "set".to_string())
+        );
+        assert_eq!(
+            get(&state, "remote_addr").await.0,
+            "http://hive.example.com:34560"
+        );
+    }
+
+    #[tokio::test]
+    async fn invalid_keys_and_values_are_rejected() {
+        let db = TempDb::new();
+        let state = db.state();
+        for (key, value) in [
+            ("embedded_database_root", "/tmp"),
+            ("editor", " "),
+            ("remote_addr", "not a uri"),
+        ] {
+            let status: Status = set(&state, key, value).await.unwrap_err().into();
+            assert_eq!(status.code(), tonic::Code::InvalidArgument, "{key}={value}");
+        }
+        assert!(
+            state
+                .db
+                .load_runtime_config()
+                .unwrap()
+                .remote_addr
+                .is_none()
+        );
+    }
+}
@@ -0,0 +1,610 @@
+//! 测试用的 hive 服务端
+use crate::hive_pb::{
+    self,
+    hive_service_server::{HiveService, HiveServiceServer},
+};
+use crv_core::metadata::CompressionType;
+use std::collections::HashSet;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio_stream::Stream;
+use tokio_stream::wrappers::TcpListenerStream;
+use tonic::transport::Server;
+use tonic::{Request, Response, Status};
+
+type StubStream<T> = Pin<Box<dyn Stream<Item = Result<T, Status>> + Send + 'static>>;
+
+/// 只实现了 bonjour、check_chunks 与 workspace 登记的 hive，其余方法均返回 unimplemented
+#[derive(Default)]
+pub(crate) struct StubHive {
+    /// 设置后 bonjour 返回该错误，用于模拟 hive 不可用
+    pub bonjour_error: Option<Status>,
+    /// check_chunks 的前几次调用返回 unavailable，用于模拟暂时性错误
+    pub check_chunks_failures: u32,
+    /// check_chunks 被调用的次数
+    pub check_chunks_calls: Arc<AtomicU32>,
+    /// 已登记的 workspace 名称
+    pub workspaces: Arc<Mutex<HashSet<String>>>,
+    /// get_file_tree 返回的文件（depot path 与内容），每个文件只有一个 chunk
+    pub files: Vec<(String, Vec<u8>)>,
+    /// get_file_tree 额外返回的由多个 chunk 组成的文件（depot path 与各 chunk 的内容）
+    pub chunked_files: Vec<(String, Vec<Vec<u8>>)>,
+    /// get_file_tree 返回的所有 revision 的压缩方式
+    pub compression_type: CompressionType,
+    /// download_file_chunk 对这些 chunk 返回错误的内容，用于模拟数据损坏
+    pub corrupt_chunks: HashSet<String>,
+    /// download_file_chunk 依次收到的 chunk hash
+    pub downloaded_chunks: Arc<Mutex<Vec<String>>>,
+    /// 依次提交的 changelist 中的文件变更，内容为 None 表示删除。
+    /// 设置后 get_file_tree 返回请求的 changelist 时的文件，而不是 `files`
+    pub changelists: Vec<Vec<(String, Option<Vec<u8>>)>>,
+    /// bonjour 收到的 `x-request-id` 头
+    pub request_ids: Arc<Mutex<Vec<Option<String>>>>,
+    /// list_locked_files 返回的被锁定的文件
+    pub locked_files: Vec<hive_pb::LockedFile>,
+}
+
+fn chunk_hash(content: &[u8]) -> String {
+    hex::encode(blake3::hash(content).as_bytes())
+}
+
+fn file_revision(
+    path: &str,
+    revision: i64,
+    changelist_id: i64,
+    content: &[u8],
+) -> hive_pb::FileRevision {
+    hive_pb::FileRevision {
+        path: path.to_string(),
+        generation: 1,
+        revision,
+        changelist_id,
+        binary_id: vec![chunk_hash(content)],
+        size: content.len() as i64,
+        revision_created_at: 0,
+        content_hash: chunk_hash(content),
+        // 与旧版本的 hive 一样不填写，视为不压缩
+        compression_type: String::new(),
+    }
+}
+
+impl StubHive {
+    /// `changelists` 中截止到 `changelist_id`（0 表示最新）时未被删除的文件
+    fn files_at(&self, changelist_id: i64) -> Vec<hive_pb::FileRevision> {
+        let mut files: std::collections::BTreeMap<&str, (i64, i64, Option<&Vec<u8>>)> =
+            Default::default();
+        for (index, changes) in self.changelists.iter().enumerate() {
+            let id = index as i64 + 1;
+            if changelist_id > 0 && id > changelist_id {
+                break;
+            }
+            for (path, content) in changes {
+                let entry = files.entry(path).or_insert((0, id, None));
+                *entry = (entry.0 + 1, id, content.as_ref());
+            }
+        }
+        files
+            .into_iter()
+            .filter_map(|(path, (revision, id, content))| {
+                Some(file_revision(path, revision, id, content?))
+            })
+            .collect()
+    }
+
+    /// 所有文件内容，用于按 chunk hash 下载
+    fn contents(&self) -> impl Iterator<Item = &Vec<u8>> {
+        let history = self
+            .changelists
+            .iter()
+            .flatten()
+            .filter_map(|(_, content)| content.as_ref());
+        let chunks = self.chunked_files.iter().flat_map(|(_, chunks)| chunks);
+        self.files
+            .iter()
+            .map(|(_, content)| content)
+            .chain(history)
+            .chain(chunks)
+    }
+}
+
+#[tonic::async_trait]
+impl HiveService for StubHive {
+    async fn bonjour(
+        &self,
+        request: Request<hive_pb::BonjourReq>,
+    ) -> Result<Response<hive_pb::BonjourRsp>, Status> {
+        let request_id = request
+            .metadata()
+            .get("x-request-id")
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+        self.request_ids.lock().unwrap().push(request_id);
+        if let Some(status) = &self.bonjour_error {
+            return Err(status.clone());
+        }
+        Ok(Response::new(hive_pb::BonjourRsp {
+            major_version: 1,
+            minor_version: 2,
+            api_implementation: "stub".to_string(),
+            platform: "test".to_string(),
+            os: "test".to_string(),
+            architecture: "test".to_string(),
+        }))
+    }
+
+    async fn login(
+        &self,
+        _request: Request<hive_pb::LoginReq>,
+    ) -> Result<Response<hive_pb::LoginRsp>, Status> {
+        Err(Status::unimplemented("stub"))
+    }
+
+    async fn register(
+        &self,
+        _request: Request<hive_pb::RegisterReq>,
+    ) -> Result<Response<hive_pb::RegisterRsp>, Status> {
+        Err(Status::unimplemented("stub"))
+    }
+
+    async fn begin_oauth2_login(
+        &self,
+        _request: Request<hive_pb::BeginOauth2LoginReq>,
+    ) -> Result<Response<hive_pb::BeginOauth2LoginRsp>, Status> {
+        Err(Status::unimplemented("stub"))
+    }
+
+    async fn complete_oauth2_login(
+        &self,
+        _request: Request<hive_pb::CompleteOauth2LoginReq>,
+    ) -> Result<Response<hive_pb::CompleteOauth2LoginRsp>, Status> {
+        Err(Status::unimplemented("stub"))
+    }
+
+    async fn launch_submit(
+        &self,
+        _request: Request<hive_pb::LaunchSubmitReq>,
+    ) -> Result<Response<hive_pb::LaunchSubmitRsp>, Status> {
+        Err(Status::unimplemented("stub"))
+    }
+
+    async fn check_chunks(
+        &self,
+        request: Request<hive_pb::CheckChunksReq>,
+    ) -> Result<Response<hive_pb::CheckChunksRsp>, Status> {
+        let calls = self.check_chunks_calls.fetch_add(1, Ordering::SeqCst) + 1;
+        if calls <= self.check_chunks_failures {
+            return Err(Status::unavailable("hive is busy"));
+        }
+        // 认为所有 chunk 都不存在
+        Ok(Response::new(hive_pb::CheckChunksRsp {
+            missing_chunk_hashes: request.into_inner().chunk_hashes,
+        }))
+    }
+
+    type UploadFileChunkStream = StubStream<hive_pb::UploadFileChunkRsp>;
+
+    async fn upload_file_chunk(
+        &self,
+        _request: Request<tonic::Streaming<hive_pb::UploadFileChunkReq>>,
+    ) -> Result<Response<Self::UploadFileChunkStream>, Status> {
+        Err(Status::unimplemented("stub"))
+    }
+
+    async fn submit(
+        &self,
+        _request: Request<hive_pb::SubmitReq>,
+    ) -> Result<Response<hive_pb::SubmitRsp>, Status> {
+        Err(Status::unimplemented("stub"))
+    }
+
+    async fn get_file_tree(
+        &self,
+        request: Request<hive_pb::GetFileTreeReq>,
+    ) -> Result<Response<hive_pb::GetFileTreeRsp>, Status> {
+        let mut file_revisions: Vec<_> = if self.changelists.is_empty() {
+            self.files
+                .iter()
+                .map(|(path, content)| file_revision(path, 1, 1, content))
+                .collect()
+        } else {
+            self.files_at(request.into_inner().changelist_id)
+        };
+        for (path, chunks) in &self.chunked_files {
+            let content = chunks.concat();
+            file_revisions.push(hive_pb::FileRevision {
+                binary_id: chunks.iter().map(|chunk| chunk_hash(chunk)).collect(),
+                ..file_revision(path, 1, 1, &content)
+            });
+        }
+        if self.compression_type != CompressionType::None {
+            for file_revision in &mut file_revisions {
+                file_revision.compression_type = self.compression_type.as_str().to_string();
+            }
+        }
+        Ok(Response::new(hive_pb::GetFileTreeRsp { file_revisions }))
+    }
+
+    async fn get_changelist_at_time(
+        &self,
+        _request: Request<hive_pb::GetChangelistAtTimeReq>,
+    ) -> Result<Response<hive_pb::GetChangelistAtTimeRsp>, Status> {
+        Err(Status::unimplemented("stub"))
+    }
+
+    /// `changelists` 中修改过该文件的 changelist，第 N 个 changelist 的提交者为 `userN`
+    async fn get_file_history(
+        &self,
+        request: Request<hive_pb::GetFileHistoryReq>,
+    ) -> Result<Response<hive_pb::GetFileHistoryRsp>, Status> {
+        let path = request.into_inner().path;
+        let mut entries = Vec::new();
+        for (index, changes) in self.changelists.iter().enumerate() {
+            let id = index as i64 + 1;
+            for (_, content) in changes.iter().filter(|(p, _)| *p == path) {
+                entries.push(hive_pb::FileHistoryEntry {
+                    generation: 1,
+                    revision: entries.len() as i64 + 1,
+                    changelist_id: id,
+                    size: content.as_ref().map_or(0, |c| c.len() as i64),
+                    is_delete: content.is_none(),
+                    created_at: 0,
+                    author: format!("user{id}"),
+                    description: String::new(),
+                });
+            }
+        }
+        entries.reverse();
+        Ok(Response::new(hive_pb::GetFileHistoryRsp { entries }))
+    }
+
+    async fn find_file_by_path(
+        &self,
+        _request: Request<hive_pb::FindFileByPathReq>,
+    ) -> Result<Response<hive_pb::FindFileByPathRsp>, Status> {
+        Err(Status::unimplemented("stub"))
+    }
+
+    type DownloadFileChunkStream = StubStream<hive_pb::DownloadFileChunkResp>;
+
+    async fn download_file_chunk(
+        &self,
+        request: Request<hive_pb::DownloadFileChunkReq>,
+    ) -> Result<Response<Self::DownloadFileChunkStream>, Status> {
+        let request = request.into_inner();
+        let compression_type = CompressionType::parse(&request.compression)
+            .ok_or_else(|| Status::invalid_argument("unsupported compression"))?;
+        let mut chunks = Vec::new();
+        for hash in request.chunk_hashes {
+            self.downloaded_chunks.lock().unwrap().push(hash.clone());
+            let mut content = self
+                .contents()
+                .find(|content| chunk_hash(content) == hash)
+                .ok_or_else(|| Status::not_found(format!("chunk {hash} not found")))?
+                .clone();
+            if self.corrupt_chunks.contains(&hash) {
+                content.reverse();
+            }
+            let uncompressed_size = content.len() as u32;
+            let content = compression_type
+                .compress(&content)
+                .map_err(|e| Status::internal(e.to_string()))?;
+            chunks.push(Ok(hive_pb::DownloadFileChunkResp {
+                chunk_hash: hash,
+                offset: 0,
+                size: content.len() as u64,
+                content,
+                compression: compression_type.as_str().to_string(),
+                uncompressed_size,
+            }));
+        }
+        Ok(Response::new(
+            Box::pin(tokio_stream::iter(chunks)) as Self::DownloadFileChunkStream
+        ))
+    }
+
+    async fn create_branch(
+        &self,
+        _request: Request<hive_pb::CreateBranchReq>,
+    ) -> Result<Response<hive_pb::CreateBranchRsp>, Status> {
+        Err(Status::unimplemented("stub"))
+    }
+
+    async fn list_branches(
+        &self,
+        _request: Request<hive_pb::ListBranchesReq>,
+    ) -> Result<Response<hive_pb::ListBranchesRsp>, Status> {
+        Err(Status::unimplemented("stub"))
+    }
+
+    async fn set_branch_description_format(
+        &self,
+        _request: Request<hive_pb::SetBranchDescriptionFormatReq>,
+    ) -> Result<Response<hive_pb::SetBranchDescriptionFormatRsp>, Status> {
+        Err(Status::unimplemented("stub"))
+    }
+
+    async fn set_branch_protection(
+        &self,
+        _request: Request<hive_pb::SetBranchProtectionReq>,
+    ) -> Result<Response<hive_pb::SetBranchProtectionRsp>, Status> {
+        Err(Status::unimplemented("stub"))
+    }
+
+    async fn get_branch_diff(
+        &self,
+        _request: Request<hive_pb::GetBranchDiffReq>,
+    ) -> Result<Response<hive_pb::GetBranchDiffRsp>, Status> {
+        Err(Status::unimplemented("stub"))
+    }
+
+    type WatchBranchStream = StubStream<hive_pb::BranchEvent>;
+
+    async fn watch_branch(
+        &self,
+        _request: Request<hive_pb::WatchBranchReq>,
+    ) -> Result<Response<Self::WatchBranchStream>, Status> {
+        Err(Status::unimplemented("stub"))
+    }
+
+    async fn list_audit_log(
+        &self,
+        _request: Request<hive_pb::ListAuditLogReq>,
+    ) -> Result<Response<hive_pb::ListAuditLogRsp>, Status> {
+        Err(Status::unimplemented("stub"))
+    }
+
+    async fn register_workspace(
+        &self,
+        request: Request<hive_pb::RegisterWorkspaceReq>,
+    ) -> Result<Response<hive_pb::RegisterWorkspaceRsp>, Status> {
+        let name = request.into_inner().workspace_name;
+        if !self.workspaces.lock().unwrap().insert(name.clone()) {
+            return Err(Status::already_exists(format!("{name} already exists")));
+        }
+        Ok(Response::new(hive_pb::RegisterWorkspaceRsp {}))
+    }
+
+    async fn unregister_workspace(
+        &self,
+        request: Request<hive_pb::UnregisterWorkspaceReq>,
+    ) -> Result<Response<hive_pb::UnregisterWorkspaceRsp>, Status> {
+        let name = request.into_inner().workspace_name;
+        if !self.workspaces.lock().unwrap().remove(&name) {
+            return Err(Status::not_found(format!("{name} not found")));
+        }
+        Ok(Response::new(hive_pb::UnregisterWorkspaceRsp {}))
+    }
+
+    async fn describe_workspace(
+        &self,
+        _request: Request<hive_pb::DescribeWorkspaceReq>,
+    ) -> Result<Response<hive_pb::DescribeWorkspaceRsp>, Status> {
+        Err(Status::unimplemented("stub"))
+    }
+
+    async fn get_repository_stats(
+        &self,
+        _request: Request<hive_pb::GetRepositoryStatsReq>,
+    ) -> Result<Response<hive_pb::GetRepositoryStatsRsp>, Status> {
+        Err(Status::unimplemented("stub"))
+    }
+
+    async fn rebuild_index(
+        &self,
+        _request: Request<hive_pb::RebuildIndexReq>,
+    ) -> Result<Response<hive_pb::RebuildIndexRsp>, Status> {
+        Err(Status::unimplemented("stub"))
+    }
+
+    async fn get_repository_layout(
+        &self,
+        _request: Request<hive_pb::GetRepositoryLayoutReq>,
+    ) -> Result<Response<hive_pb::GetRepositoryLayoutRsp>, Status> {
+        Err(Status::unimplemented("stub"))
+    }
+
+    type ExportRepositorySnapshotStream = StubStream<hive_pb::ExportRepositorySnapshotRsp>;
+
+    async fn export_repository_snapshot(
+        &self,
+        _request: Request<hive_pb::ExportRepositorySnapshotReq>,
+    ) -> Result<Response<Self::ExportRepositorySnapshotStream>, Status> {
+        Err(Status::unimplemented("stub"))
+    }
+
+    async fn import_repository_snapshot(
+        &self,
+        _request: Request<tonic::Streaming<hive_pb::ImportRepositorySnapshotReq>>,
+    ) -> Result<Response<hive_pb::ImportRepositorySnapshotRsp>, Status> {
+        Err(Status::unimplemented("stub"))
+    }
+
+    async fn list_users(
+        &self,
+        _request: Request<hive_pb::ListUsersReq>,
+    ) -> Result<Response<hive_pb::ListUsersRsp>, Status> {
+        Err(Status::unimplemented("stub"))
+    }
+
+    async fn update_user_password(
+        &self,
+        _request: Request<hive_pb::UpdateUserPasswordReq>,
+    ) -> Result<Response<hive_pb::UpdateUserPasswordRsp>, Status> {
+        Err(Status::unimplemented("stub"))
+    }
+
+    async fn delete_user(
+        &self,
+        _request: Request<hive_pb::DeleteUserReq>,
+    ) -> Result<Response<hive_pb::DeleteUserRsp>, Status> {
+        Err(Status::unimplemented("stub"))
+    }
+
+    async fn get_user_profile(
+        &self,
+        _request: Request<hive_pb::GetUserProfileReq>,
+    ) -> Result<Response<hive_pb::GetUserProfileRsp>, Status> {
+        Err(Status::unimplemented("stub"))
+    }
+
+    async fn tag_changelist(
+        &self,
+        _request: Request<hive_pb::TagChangelistReq>,
+    ) -> Result<Response<hive_pb::TagChangelistRsp>, Status> {
+        Err(Status::unimplemented("stub"))
+    }
+
+    async fn untag_changelist(
+        &self,
+        _request: Request<hive_pb::UntagChangelistReq>,
+    ) -> Result<Response<hive_pb::UntagChangelistRsp>, Status> {
+        Err(Status::unimplemented("stub"))
+    }
+
+    async fn get_changelist_by_tag(
+        &self,
+        _request: Request<hive_pb::GetChangelistByTagReq>,
+    ) -> Result<Response<hive_pb::GetChangelistByTagRsp>, Status> {
+        Err(Status::unimplemented("stub"))
+    }
+
+    async fn tag_file_revision(
+        &self,
+        _request: Request<hive_pb::TagFileRevisionReq>,
+    ) -> Result<Response<hive_pb::TagFileRevisionRsp>, Status> {
+        Err(Status::unimplemented("stub"))
+    }
+
+    async fn list_file_revisions_by_tag(
+        &self,
+        _request: Request<hive_pb::ListFileRevisionsByTagReq>,
+    ) -> Result<Response<hive_pb::ListFileRevisionsByTagRsp>, Status> {
+        Err(Status::unimplemented("stub"))
+    }
+
+    async fn register_webhook(
+        &self,
+        _request: Request<hive_pb::RegisterWebhookReq>,
+    ) -> Result<Response<hive_pb::RegisterWebhookRsp>, Status> {
+        Err(Status::unimplemented("stub"))
+    }
+
+    async fn unregister_webhook(
+        &self,
+        _request: Request<hive_pb::UnregisterWebhookReq>,
+    ) -> Result<Response<hive_pb::UnregisterWebhookRsp>, Status> {
+        Err(Status::unimplemented("stub"))
+    }
+
+    async fn list_webhooks(
+        &self,
+        _request: Request<hive_pb::ListWebhooksReq>,
+    ) -> Result<Response<hive_pb::ListWebhooksRsp>, Status> {
+        Err(Status::unimplemented("stub"))
+    }
+
+    async fn list_locked_files(
+        &self,
+        _request: Request<hive_pb::ListLockedFilesReq>,
+    ) -> Result<Response<hive_pb::ListLockedFilesRsp>, Status> {
+        Ok(Response::new(hive_pb::ListLockedFilesRsp {
+            locked_files: self.locked_files.clone(),
+        }))
+    }
+
+    async fn get_submit_context(
+        &self,
+        _request: Request<hive_pb::GetSubmitContextReq>,
+    ) -> Result<Response<hive_pb::GetSubmitContextRsp>, Status> {
+        Err(Status::unimplemented("stub"))
+    }
+
+    async fn cancel_submit_context(
+        &self,
+        _request: Request<hive_pb::CancelSubmitContextReq>,
+    ) -> Result<Response<hive_pb::CancelSubmitContextRsp>, Status> {
+        Err(Status::unimplemented("stub"))
+    }
+
+    async fn delta_upload(
+        &self,
+        _request: Request<hive_pb::DeltaUploadReq>,
+    ) -> Result<Response<hive_pb::DeltaUploadRsp>, Status> {
+        Err(Status::unimplemented("stub"))
+    }
+
+    async fn squash_changelists(
+        &self,
+        _request: Request<hive_pb::SquashChangelistsReq>,
+    ) -> Result<Response<hive_pb::SquashChangelistsRsp>, Status> {
+        Err(Status::unimplemented("stub"))
+    }
+
+    async fn get_changelist_chain(
+        &self,
+        _request: Request<hive_pb::GetChangelistChainReq>,
+    ) -> Result<Response<hive_pb::GetChangelistChainRsp>, Status> {
+        Err(Status::unimplemented("stub"))
+    }
+
+    async fn cherry_pick(
+        &self,
+        _request: Request<hive_pb::CherryPickReq>,
+    ) -> Result<Response<hive_pb::CherryPickRsp>, Status> {
+        Err(Status::unimplemented("stub"))
+    }
+
+    async fn merge(
+        &self,
+        _request: Request<hive_pb::MergeReq>,
+    ) -> Result<Response<hive_pb::MergeRsp>, Status> {
+        Err(Status::unimplemented("stub"))
+    }
+
+    async fn create_snapshot(
+        &self,
+        _request: Request<hive_pb::CreateSnapshotReq>,
+    ) -> Result<Response<hive_pb::CreateSnapshotRsp>, Status> {
+        Err(Status::unimplemented("stub"))
+    }
+
+    async fn list_snapshots(
+        &self,
+        _request: Request<hive_pb::ListSnapshotsReq>,
+    ) -> Result<Response<hive_pb::ListSnapshotsRsp>, Status> {
+        Err(Status::unimplemented("stub"))
+    }
+
+    async fn delete_snapshot(
+        &self,
+        _request: Request<hive_pb::DeleteSnapshotReq>,
+    ) -> Result<Response<hive_pb::DeleteSnapshotRsp>, Status> {
+        Err(Status::unimplemented("stub"))
+    }
+
+    async fn restore_snapshot(
+        &self,
+        _request: Request<hive_pb::RestoreSnapshotReq>,
+    ) -> Result<Response<hive_pb::RestoreSnapshotRsp>, Status> {
+        Err(Status::unimplemented("stub"))
+    }
+}
+
+/// 在本地随机端口上启动 hive，返回其地址
+pub(crate) async fn spawn(hive: StubHive) -> String {
+    spawn_killable(hive).await.0
+}
+
+/// 与 `spawn` 相同，同时返回服务端任务的句柄，abort 后不再接受新的连接，用于模拟 hive 宕机
+pub(crate) async fn spawn_killable(hive: StubHive) -> (String, tokio::task::JoinHandle<()>) {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let handle = tokio::spawn(async move {
+        let _ = Server::builder()
+            .add_service(HiveServiceServer::new(hive))
+            .serve_with_incoming(TcpListenerStream::new(listener))
+            .await;
+    });
+    (format!("http://{addr}"), handle)
+}
@@ -0,0 +1,43 @@
+use crate::daemon_server::error::{AppError, AppResult};
+use crate::daemon_server::state::AppState;
+use crate::daemon_server::update::{self, CURRENT_VERSION};
+use crate::pb::{ApplyUpdateReq, ApplyUpdateRsp, CheckUpdateReq, CheckUpdateRsp};
+use tonic::{Request, Response};
+
+fn update_url(state: &AppState) -> AppResult<&str> {
+    state
+        .update_url
+        .as_deref()
+        .ok_or_else(|| AppError::Config("update_url is not configured".to_string()))
+}
+
+pub async fn check(
+    state: AppState,
+    _req: Request<CheckUpdateReq>,
+) -> AppResult<Response<CheckUpdateRsp>> {
+    let check = update::check_for_update(update_url(&state)?).await?;
+    Ok(Response::new(CheckUpdateRsp {
+        current_version: CURRENT_VERSION.to_string(),
+        latest_version: check.manifest.version,
+        update_available: check.update_available,
+        download_url: check.manifest.url,
+    }))
+}
+
+/// 安装最新版本，安装成功后关闭服务并重启；响应会在服务关闭前发出
+pub async fn apply(
+    state: AppState,
+    _req: Request<ApplyUpdateReq>,
+) -> AppResult<Response<ApplyUpdateRsp>> {
+    let exe = std::env::current_exe()
+        .map_err(|e| AppError::Internal(format!("failed to locate executable: {e}")))?;
+    let installed = update::apply_update(update_url(&state)?, &exe).await?;
+    if let Some(version) = &installed {
+        println!("Installed crv-edge {version}, restarting");
+        state.restart.request();
+    }
+    Ok(Response::new(ApplyUpdateRsp {
+        previous_version: CURRENT_VERSION.to_string(),
+        installed_version: installed.unwrap_or_default(),
+    }))
+}
@@ -0,0 +1,21 @@
+use crate::daemon_server::error::AppResult;
+use crate::daemon_server::state::AppState;
+use crate::pb::{StartWatchReq, StartWatchRsp, StopWatchReq, StopWatchRsp};
+use tonic::{Request, Response};
+
+/// 开始监听所有工作区，已经在监听时按当前的工作区列表重新监听
+pub async fn start(
+    state: AppState,
+    _req: Request<StartWatchReq>,
+) -> AppResult<Response<StartWatchRsp>> {
+    let workspace_names = state.file_watcher.start(state.clone())?;
+    Ok(Response::new(StartWatchRsp { workspace_names }))
+}
+
+pub async fn stop(
+    state: AppState,
+    _req: Request<StopWatchReq>,
+) -> AppResult<Response<StopWatchRsp>> {
+    let was_running = state.file_watcher.stop();
+    Ok(Response::new(StopWatchRsp { was_running }))
+}
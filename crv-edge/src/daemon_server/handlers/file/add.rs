@@ -1,9 +1,14 @@
 use crate::daemon_server::db::active_file::Action;
 use crate::daemon_server::error::{AppError, AppResult};
-use crate::daemon_server::handlers::utils::{expand_to_mapped_files_in_fs, normalize_paths_strict};
+use crate::daemon_server::handlers::utils::{
+    expand_depot_wildcard_in_fs, expand_to_mapped_files_in_fs, normalize_paths_strict,
+};
+use crate::daemon_server::handlers::workspace::checkpoint::local_chunk_hashes;
 use crate::daemon_server::state::AppState;
 use crate::pb::{AddReq, AddRsp};
+use crv_core::path::basic::DepotPathWildcard;
 use crv_core::path::engine::PathEngine;
+use crv_core::path::ignore::IgnoreMatcher;
 use tonic::{Request, Response, Status};
 
 pub async fn handle(state: AppState, req: Request<AddReq>) -> AppResult<Response<AddRsp>> {
@@ -20,13 +25,55 @@ pub async fn handle(state: AppState, req: Request<AddReq>) -> AppResult<Response
 
     let path_engine = PathEngine::new(workspace_meta.config.clone(), &request_body.workspace_name);
 
-    // 2. 规范化路径
-    let local_paths = normalize_paths_strict(&request_body.paths, &path_engine)?;
+    // 2. 规范化路径，含 `...` 的 depot 路径按通配路径处理
+    let (wildcards, paths): (Vec<String>, Vec<String>) = request_body
+        .paths
+        .into_iter()
+        .partition(|path| path.starts_with("//") && path.contains("..."));
+    let local_paths = normalize_paths_strict(&paths, &path_engine)?;
+    let wildcards = wildcards
+        .iter()
+        .map(|wildcard| {
+            DepotPathWildcard::parse(wildcard).map_err(|e| {
+                AppError::Raw(Status::invalid_argument(format!(
+                    "Can't parse depot wildcard {wildcard}: {e}"
+                )))
+            })
+        })
+        .collect::<AppResult<Vec<_>>>()?;
 
-    // 3. 展开为文件列表
-    let local_files = expand_to_mapped_files_in_fs(&local_paths, &path_engine);
+    // 3. 展开为文件列表，跳过被 .crvignore 排除的文件
+    let mut ignore_matcher = IgnoreMatcher::new();
+    let mut local_files = expand_to_mapped_files_in_fs(&local_paths, &path_engine)
+        .into_iter()
+        .filter(|file| !ignore_matcher.is_ignored(&file.local_path))
+        .collect::<Vec<_>>();
 
-    // 4. 转换为 workspace paths 并标记为 Add
+    // 通配路径下只添加未跟踪或内容有变化的文件，已打开的文件保持原有的 action
+    let mut edited_files = Vec::new();
+    for wildcard in &wildcards {
+        for file in expand_depot_wildcard_in_fs(wildcard, &workspace_meta.config, &path_engine) {
+            if ignore_matcher.is_ignored(&file.local_path)
+                || state
+                    .db
+                    .get_active_file_action(&file.workspace_path)?
+                    .is_some()
+            {
+                continue;
+            }
+            match state.db.get_file_meta(&file.workspace_path)? {
+                None => local_files.push(file),
+                Some(meta) => {
+                    let local_path = file.local_path.to_local_path_string();
+                    if local_chunk_hashes(&local_path).await.as_ref() != Some(&meta.chunk_hashes) {
+                        edited_files.push(file);
+                    }
+                }
+            }
+        }
+    }
+
+    // 4. 转换为 workspace paths 并标记为 Add，已跟踪的文件标记为 Edit
     let mut added_paths = Vec::new();
 
     for file in &local_files {
@@ -36,6 +83,198 @@ pub async fn handle(state: AppState, req: Request<AddReq>) -> AppResult<Response
             .set_active_file_action(file.workspace_path.clone(), Action::Add)?;
         added_paths.push(file.workspace_path.to_custom_string());
     }
+    for file in &edited_files {
+        state
+            .db
+            .set_active_file_action(file.workspace_path.clone(), Action::Edit)?;
+        added_paths.push(file.workspace_path.to_custom_string());
+    }
 
     Ok(Response::new(AddRsp { added_paths }))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::daemon_server::db::file::{FileLocation, FileMeta, FileRevision};
+    use crate::test_support::TempDb;
+    use crv_core::path::basic::{LocalPath, WorkspacePath};
+    use crv_core::path::ignore::IGNORE_FILE_NAME;
+    use crv_core::workspace::entity::WorkspaceConfig;
+    use std::path::{Path, PathBuf};
+
+    /// 创建名为 ws 的工作区，返回数据库、状态与工作区根目录
+    fn workspace(mappings: &str) -> (TempDb, AppState, PathBuf) {
+        let db = TempDb::new();
+        let workspace_root = db.root().join("ws");
+        std::fs::create_dir_all(&workspace_root).unwrap();
+
+        let state = db.state();
+        let config = WorkspaceConfig::from_specification(
+            "ws",
+            &format!("{}/", workspace_root.to_string_lossy()),
+            mappings,
+        )
+        .unwrap();
+        state
+            .db
+            .create_workspace_pending("ws".to_string(), config)
+            .unwrap();
+        state.db.confirm_workspace("ws".to_string()).unwrap();
+        (db, state, workspace_root)
+    }
+
+    fn write(path: &Path, content: &str) {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, content).unwrap();
+    }
+
+    async fn add(state: &AppState, paths: Vec<String>) -> Vec<String> {
+        let rsp = handle(
+            state.clone(),
+            Request::new(AddReq {
+                workspace_name: "ws".to_string(),
+                paths,
+            }),
+        )
+        .await
+        .unwrap()
+        .into_inner();
+        let mut added_paths = rsp.added_paths;
+        added_paths.sort();
+        added_paths
+    }
+
+    #[tokio::test]
+    async fn files_matching_crvignore_are_not_added() {
+        let (_db, state, workspace_root) = workspace("//... //ws/");
+        write(&workspace_root.join(IGNORE_FILE_NAME), "*.tmp\nbuild/\n");
+        write(&workspace_root.join("a.txt"), "a");
+        write(&workspace_root.join("b.tmp"), "b");
+        write(&workspace_root.join("build").join("c.txt"), "c");
+
+        let added_paths = add(
+            &state,
+            vec![format!("{}/", workspace_root.to_string_lossy())],
+        )
+        .await;
+        assert_eq!(added_paths, vec!["//ws/.crvignore", "//ws/a.txt"]);
+    }
+
+    #[tokio::test]
+    async fn depot_wildcard_adds_every_file_under_directory() {
+        let (_db, state, workspace_root) = workspace("//depot/... //ws/");
+        let mut expected = Vec::new();
+        for i in 0..50 {
+            let relative = format!("src/dir{}/sub{}/file{i}.txt", i % 5, i % 2);
+            write(&workspace_root.join(&relative), &format!("content {i}"));
+            expected.push(format!("//ws/{relative}"));
+        }
+        write(&workspace_root.join("docs").join("readme.md"), "docs");
+        expected.sort();
+
+        let added_paths = add(&state, vec!["//depot/src/...".to_string()]).await;
+        assert_eq!(added_paths.len(), 50);
+        assert_eq!(added_paths, expected);
+        for path in &added_paths {
+            let action = state
+                .db
+                .get_active_file_action(&WorkspacePath::parse(path).unwrap())
+                .unwrap();
+            assert!(matches!(action, Some(Action::Add)));
+        }
+    }
+
+    #[tokio::test]
+    async fn depot_wildcard_skips_unchanged_tracked_files() {
+        let (_db, state, workspace_root) = workspace("//depot/... //ws/");
+        let workspace_meta = state
+            .db
+            .get_confirmed_workspace_meta(&"ws".to_string())
+            .unwrap()
+            .unwrap();
+        let path_engine = PathEngine::new(workspace_meta.config, "ws");
+
+        // 两个文件已同步到本地，其中 changed.txt 随后在本地被修改
+        for (name, synced, local) in [("same.txt", "same", "same"), ("changed.txt", "old", "new")] {
+            let local_path = workspace_root.join("src").join(name);
+            write(&local_path, synced);
+            let chunk_hashes = local_chunk_hashes(&local_path.to_string_lossy())
+                .await
+                .unwrap();
+            write(&local_path, local);
+
+            let local_path = LocalPath::parse(&local_path.to_string_lossy()).unwrap();
+            let workspace_path = path_engine
+                .local_path_to_workspace_path(&local_path)
+                .unwrap();
+            let location = FileLocation {
+                depot_path: path_engine.mapping_local_path(&local_path).unwrap(),
+                workspace_path: workspace_path.clone(),
+                local_path,
+            };
+            state
+                .db
+                .set_file_meta(
+                    workspace_path,
+                    FileMeta {
+                        location,
+                        current_revision: FileRevision {
+                            generation: 1,
+                            revision: 1,
+                        },
+                        changelist_id: 1,
+                        size: synced.len() as i64,
+                        chunk_hashes,
+                    },
+                )
+                .unwrap();
+        }
+        write(&workspace_root.join("src").join("new.txt"), "new");
+        // 已打开的文件保持原有的 action
+        write(&workspace_root.join("src").join("opened.txt"), "opened");
+        let opened = WorkspacePath::parse("//ws/src/opened.txt").unwrap();
+        state
+            .db
+            .set_active_file_action(opened.clone(), Action::Delete)
+            .unwrap();
+
+        let added_paths = add(&state, vec!["//depot/src/...".to_string()]).await;
+        assert_eq!(
+            added_paths,
+            vec!["//ws/src/changed.txt", "//ws/src/new.txt"]
+        );
+
+        let action = |path: &str| {
+            state
+                .db
+                .get_active_file_action(&WorkspacePath::parse(path).unwrap())
+                .unwrap()
+        };
+        assert!(matches!(action("//ws/src/changed.txt"), Some(Action::Edit)));
+        assert!(matches!(action("//ws/src/new.txt"), Some(Action::Add)));
+        assert!(action("//ws/src/same.txt").is_none());
+        assert!(matches!(
+            action("//ws/src/opened.txt"),
+            Some(Action::Delete)
+        ));
+    }
+
+    #[tokio::test]
+    async fn invalid_depot_wildcard_is_rejected() {
+        let (_db, state, _) = workspace("//... //ws/");
+        let err = handle(
+            state,
+            Request::new(AddReq {
+                workspace_name: "ws".to_string(),
+                paths: vec!["//depot/.../src/...".to_string()],
+            }),
+        )
+        .await
+        .err()
+        .unwrap();
+        assert!(
+            matches!(err, AppError::Raw(status) if status.code() == tonic::Code::InvalidArgument)
+        );
+    }
+}
@@ -1,10 +1,13 @@
 use crate::daemon_server::db::active_file::Action;
 use crate::daemon_server::error::{AppError, AppResult};
+use crate::daemon_server::handlers::file::submit::CHUNK_SIZE;
 use crate::daemon_server::handlers::utils::{expand_to_mapped_files_in_fs, normalize_paths_strict};
 use crate::daemon_server::state::AppState;
 use crate::pb::{CheckoutReq, CheckoutRsp};
 use crv_core::path::basic::LocalPath;
 use crv_core::path::engine::PathEngine;
+use crv_core::repository::compute_chunk_hash;
+use tokio::{fs::File, io::AsyncReadExt};
 use tonic::{Request, Response, Status};
 
 pub async fn handle(
@@ -36,9 +39,9 @@ pub async fn handle(
 
     for file in &local_files {
         // 如果无法获取文件 meta，则说明文件不存在于远端或尚未拉新，跳过此文件
-        if state.db.get_file_meta(&file.workspace_path)?.is_none() {
+        let Some(file_meta) = state.db.get_file_meta(&file.workspace_path)? else {
             continue;
-        }
+        };
 
         // 如果文件已经存在于 active file，则跳过此文件
         if state
@@ -54,7 +57,38 @@ pub async fn handle(
             .db
             .set_active_file_action(file.workspace_path.clone(), Action::Edit)?;
         checkout_paths.push(file.workspace_path.to_custom_string());
+
+        // 启用差量上传时保存文件当前版本的 chunk，提交时作为计算差量的 base
+        if state.hive_channel.delta_upload_enabled() {
+            save_base_chunks(&state, &file.local_path, &file_meta.chunk_hashes).await;
+        }
     }
 
-    Ok(Response::new(CheckoutRsp { checkouted_paths: checkout_paths }))
+    Ok(Response::new(CheckoutRsp {
+        checkouted_paths: checkout_paths,
+    }))
+}
+
+/// 按提交时相同的方式切分文件，保存与当前版本一致的 chunk。
+///
+/// 文件已被修改或读取失败时跳过，提交时对应的 chunk 会完整上传。
+async fn save_base_chunks(state: &AppState, local_path: &LocalPath, chunk_hashes: &[String]) {
+    let Ok(mut file) = File::open(local_path.to_local_path_string()).await else {
+        return;
+    };
+    let mut chunk_buffer = vec![0u8; CHUNK_SIZE];
+    for expected_hash in chunk_hashes {
+        let n = match file.read(&mut chunk_buffer).await {
+            Ok(0) | Err(_) => return,
+            Ok(n) => n,
+        };
+        let chunk = &chunk_buffer[..n];
+        if hex::encode(compute_chunk_hash(chunk)) != *expected_hash {
+            continue;
+        }
+        if let Err(e) = state.db.set_base_chunk(expected_hash, chunk) {
+            eprintln!("Failed to save base chunk {expected_hash}: {e}");
+            return;
+        }
+    }
 }
@@ -0,0 +1,336 @@
+use crate::daemon_server::config::RuntimeConfig;
+use crate::daemon_server::error::{AppError, AppResult};
+use crate::daemon_server::handlers::utils::{
+    expand_to_mapped_files_in_edge_meta, normalize_paths_strict,
+};
+use crate::daemon_server::state::AppState;
+use crate::hive_pb::hive_service_client::HiveServiceClient;
+use crate::hive_pb::{GetFileHistoryReq, ListLockedFilesReq};
+use crate::pb::{DescribeReq, DescribeRsp, FileDescription};
+use crv_core::path::engine::PathEngine;
+use futures::stream::{self, StreamExt, TryStreamExt};
+use std::collections::HashMap;
+use tonic::{Request, Response, Status};
+
+/// 同时向 hive 查询文件历史的最大请求数
+const HISTORY_CONCURRENCY: usize = 8;
+
+pub async fn handle(
+    state: AppState,
+    req: Request<DescribeReq>,
+) -> AppResult<Response<DescribeRsp>> {
+    let runtime_config = RuntimeConfig::from_req(&req)?;
+    let request_body = req.into_inner();
+
+    // 1. 获取 workspace 信息
+    let workspace_meta = state
+        .db
+        .get_confirmed_workspace_meta(&request_body.workspace_name)?
+        .ok_or(AppError::Raw(Status::not_found(format!(
+            "Workspace {} not found.",
+            request_body.workspace_name
+        ))))?;
+
+    let path_engine = PathEngine::new(workspace_meta.config.clone(), &request_body.workspace_name);
+
+    // 2. 规范化路径
+    let local_paths = normalize_paths_strict(&request_body.paths, &path_engine)?;
+
+    // 3. 展开为文件列表，只有已经拉取到本地的文件才有元数据
+    let files = expand_to_mapped_files_in_edge_meta(&local_paths, &path_engine, state.clone())?;
+
+    let mut synced_files = Vec::new();
+    for file in files {
+        if let Some(meta) = state.db.get_file_meta(&file.workspace_path)? {
+            synced_files.push((file, meta));
+        }
+    }
+    if synced_files.is_empty() {
+        return Ok(Response::new(DescribeRsp { files: vec![] }));
+    }
+
+    // 4. 从 hive 查询锁的持有者，hive 上的锁不区分分支
+    // hive 不可达时仍然返回本地信息，来自 hive 的字段留空
+    let channel = state
+        .hive_channel
+        .get_channel(&runtime_config.remote_addr.value)?;
+    let mut hive_client = HiveServiceClient::new(channel);
+    let locked_by: HashMap<String, String> = match hive_client
+        .list_locked_files(ListLockedFilesReq {
+            branch_id: String::new(),
+        })
+        .await
+    {
+        Ok(rsp) => rsp
+            .into_inner()
+            .locked_files
+            .into_iter()
+            .map(|f| (f.path, f.locked_by))
+            .collect(),
+        Err(status) if status.code() == tonic::Code::Unavailable => HashMap::new(),
+        Err(status) => return Err(status.into()),
+    };
+
+    // 5. 并发查询每个文件首次引入的 changelist，历史从新到旧排列，最后一条即首次引入
+    let first_introduced: Vec<(i64, String)> =
+        stream::iter(synced_files.iter().map(|(file, _)| {
+            let mut hive_client = hive_client.clone();
+            let path = file.depot_path.to_custom_string();
+            async move {
+                match hive_client
+                    .get_file_history(GetFileHistoryReq { path })
+                    .await
+                {
+                    Ok(rsp) => Ok(rsp
+                        .into_inner()
+                        .entries
+                        .last()
+                        .map(|entry| (entry.changelist_id, entry.author.clone()))
+                        .unwrap_or_default()),
+                    Err(status) if status.code() == tonic::Code::Unavailable => {
+                        Ok(Default::default())
+                    }
+                    Err(status) => Err(AppError::from(status)),
+                }
+            }
+        }))
+        .buffered(HISTORY_CONCURRENCY)
+        .try_collect()
+        .await?;
+
+    // 6. 组装文件描述
+    let mut descriptions = Vec::new();
+    for ((file, meta), (first_changelist_id, first_introduced_by)) in
+        synced_files.into_iter().zip(first_introduced)
+    {
+        let depot_path = file.depot_path.to_custom_string();
+        let action = state
+            .db
+            .get_active_file_action(&file.workspace_path)?
+            .map(|x| x.to_custom_string())
+            .unwrap_or_default();
+
+        // 锁已经释放时本地保存的 ticket 也随之失效
+        let locked_by = locked_by.get(&depot_path).cloned().unwrap_or_default();
+        let lock_ticket = if locked_by.is_empty() {
+            String::new()
+        } else {
+            state
+                .db
+                .get_lock_ticket(&file.workspace_path)?
+                .unwrap_or_default()
+        };
+
+        let local_path = file.local_path.to_local_path_string();
+        descriptions.push(FileDescription {
+            workspace_path: file.workspace_path.to_custom_string(),
+            depot_path,
+            file_mode: file_mode(&local_path),
+            local_path,
+            generation: meta.current_revision.generation,
+            revision: meta.current_revision.revision,
+            changelist_id: meta.changelist_id,
+            size: meta.size,
+            chunk_hashes: meta.chunk_hashes,
+            action,
+            locked_by,
+            lock_ticket,
+            first_changelist_id,
+            first_introduced_by,
+        });
+    }
+
+    Ok(Response::new(DescribeRsp {
+        files: descriptions,
+    }))
+}
+
+/// 本地文件的权限位，文件不存在时为 0
+fn file_mode(local_path: &str) -> u32 {
+    let Ok(metadata) = std::fs::metadata(local_path) else {
+        return 0;
+    };
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        metadata.permissions().mode() & 0o7777
+    }
+    #[cfg(not(unix))]
+    {
+        if metadata.permissions().readonly() {
+            0o444
+        } else {
+            0o666
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::daemon_server::config::{RuntimeConfigItem, RuntimeConfigSource};
+    use crate::daemon_server::handlers::edge::stub_hive::{self, StubHive};
+    use crate::daemon_server::handlers::file::sync;
+    use crate::hive_pb::{LockMode, LockedFile};
+    use crate::pb::SyncWithProgressReq;
+    use crate::test_support::TempDb;
+    use crv_core::path::basic::WorkspacePath;
+    use crv_core::workspace::entity::WorkspaceConfig;
+    use tokio_stream::StreamExt;
+
+    fn request<T>(remote_addr: &str, body: T) -> Request<T> {
+        let mut runtime_config = RuntimeConfig::default();
+        runtime_config.remote_addr = RuntimeConfigItem {
+            value: remote_addr.to_string(),
+            source: RuntimeConfigSource::Override,
+        };
+        let mut req = Request::new(body);
+        req.extensions_mut().insert(runtime_config);
+        req
+    }
+
+    /// 创建 workspace 并从 hive 同步全部文件，返回 workspace 根目录
+    async fn synced_workspace(db: &TempDb, addr: &str) -> String {
+        let state = db.state();
+        let workspace_root = db.root().join("ws");
+        std::fs::create_dir_all(&workspace_root).unwrap();
+        let workspace_root = format!("{}/", workspace_root.to_string_lossy());
+        let config =
+            WorkspaceConfig::from_specification("ws", &workspace_root, "//... //ws/").unwrap();
+        state
+            .db
+            .create_workspace_pending("ws".to_string(), config)
+            .unwrap();
+        state.db.confirm_workspace("ws".to_string()).unwrap();
+
+        let events: Vec<_> = sync::handle_with_progress(
+            state,
+            request(
+                addr,
+                SyncWithProgressReq {
+                    workspace_name: "ws".to_string(),
+                    paths: vec![workspace_root.clone()],
+                    ..Default::default()
+                },
+            ),
+        )
+        .await
+        .unwrap()
+        .into_inner()
+        .collect()
+        .await;
+        assert!(events.iter().all(|event| event.is_ok()));
+        workspace_root
+    }
+
+    #[tokio::test]
+    async fn describe_synced_file() {
+        let content = b"hello world".to_vec();
+        let addr = stub_hive::spawn(StubHive {
+            changelists: vec![
+                vec![("//a.txt".to_string(), Some(b"hello".to_vec()))],
+                vec![("//a.txt".to_string(), Some(content.clone()))],
+            ],
+            locked_files: vec![LockedFile {
+                file_id: "a".to_string(),
+                path: "//a.txt".to_string(),
+                locked_by: "alice".to_string(),
+                mode: LockMode::Read as i32,
+            }],
+            ..Default::default()
+        })
+        .await;
+
+        let db = TempDb::new();
+        let state = db.state();
+        let workspace_root = synced_workspace(&db, &addr).await;
+
+        let local_path = format!("{workspace_root}a.txt");
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&local_path, std::fs::Permissions::from_mode(0o640)).unwrap();
+        }
+        let workspace_path = WorkspacePath::parse("//ws/a.txt").unwrap();
+        state
+            .db
+            .set_lock_ticket(&workspace_path, "ticket-1")
+            .unwrap();
+
+        let rsp = handle(
+            state,
+            request(
+                &addr,
+                DescribeReq {
+                    workspace_name: "ws".to_string(),
+                    paths: vec![local_path.clone()],
+                },
+            ),
+        )
+        .await
+        .unwrap()
+        .into_inner();
+
+        assert_eq!(rsp.files.len(), 1);
+        let file = &rsp.files[0];
+        assert_eq!(file.workspace_path, "//ws/a.txt");
+        assert_eq!(file.depot_path, "//a.txt");
+        assert_eq!(file.local_path, local_path);
+        assert_eq!((file.generation, file.revision), (1, 2));
+        assert_eq!(file.changelist_id, 2);
+        assert_eq!(file.size, content.len() as i64);
+        assert_eq!(
+            file.chunk_hashes,
+            vec![hex::encode(blake3::hash(&content).as_bytes())]
+        );
+        assert_eq!(file.action, "");
+        #[cfg(unix)]
+        assert_eq!(file.file_mode, 0o640);
+        #[cfg(not(unix))]
+        assert_ne!(file.file_mode, 0);
+        assert_eq!(file.locked_by, "alice");
+        assert_eq!(file.lock_ticket, "ticket-1");
+        assert_eq!(file.first_changelist_id, 1);
+        assert_eq!(file.first_introduced_by, "user1");
+    }
+
+    #[tokio::test]
+    async fn unreachable_hive_still_describes_local_state() {
+        let addr = stub_hive::spawn(StubHive {
+            changelists: vec![vec![("//a.txt".to_string(), Some(b"hello".to_vec()))]],
+            ..Default::default()
+        })
+        .await;
+        let db = TempDb::new();
+        let workspace_root = synced_workspace(&db, &addr).await;
+
+        // 换成一个没有 hive 监听的地址
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        drop(listener);
+
+        let local_path = format!("{workspace_root}a.txt");
+        let rsp = handle(
+            db.state(),
+            request(
+                &format!("http://127.0.0.1:{port}"),
+                DescribeReq {
+                    workspace_name: "ws".to_string(),
+                    paths: vec![local_path],
+                },
+            ),
+        )
+        .await
+        .unwrap()
+        .into_inner();
+
+        assert_eq!(rsp.files.len(), 1);
+        let file = &rsp.files[0];
+        assert_eq!(file.depot_path, "//a.txt");
+        assert_eq!(file.changelist_id, 1);
+        assert_eq!(file.locked_by, "");
+        assert_eq!(file.lock_ticket, "");
+        assert_eq!(file.first_changelist_id, 0);
+        assert_eq!(file.first_introduced_by, "");
+    }
+}
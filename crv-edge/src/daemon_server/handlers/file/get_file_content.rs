@@ -0,0 +1,107 @@
+use crate::daemon_server::config::RuntimeConfig;
+use crate::daemon_server::error::{AppError, AppResult};
+use crate::daemon_server::handlers::file::submit::FRAME_SIZE;
+use crate::daemon_server::state::AppState;
+use crate::hive_pb::{DownloadFileChunkReq, hive_service_client::HiveServiceClient};
+use crate::metrics;
+use crate::pb::{GetFileContentReq, GetFileContentRsp};
+use crv_core::path::basic::DepotPath;
+use crv_core::path::engine::PathEngine;
+use tonic::{Request, Response, Status};
+
+/// 返回文件最近一次同步到的版本的内容。
+///
+/// 签出时保存过 base chunk 的部分直接从本地读取，其余的 chunk 从 hive 下载。
+pub async fn handle(
+    state: AppState,
+    req: Request<GetFileContentReq>,
+) -> AppResult<Response<GetFileContentRsp>> {
+    let runtime_config = RuntimeConfig::from_req(&req)?;
+    let request_body = req.into_inner();
+
+    // 1. 获取 workspace 信息
+    let workspace_meta = state
+        .db
+        .get_confirmed_workspace_meta(&request_body.workspace_name)?
+        .ok_or(AppError::Raw(Status::not_found(format!(
+            "Workspace {} not found.",
+            request_body.workspace_name
+        ))))?;
+
+    let path_engine = PathEngine::new(workspace_meta.config.clone(), &request_body.workspace_name);
+
+    // 2. 映射到 workspace 中的文件
+    let depot_path = DepotPath::parse(&request_body.depot_path).map_err(|e| {
+        AppError::Raw(Status::invalid_argument(format!(
+            "Can't parse depot path {}: {e}",
+            request_body.depot_path
+        )))
+    })?;
+    let local_path = path_engine.mapping_depot_path(&depot_path).ok_or_else(|| {
+        AppError::Raw(Status::invalid_argument(format!(
+            "{} is not mapped in workspace {}.",
+            request_body.depot_path, request_body.workspace_name
+        )))
+    })?;
+    let workspace_path = path_engine
+        .local_path_to_workspace_path(&local_path)
+        .ok_or_else(|| {
+            AppError::Internal(format!(
+                "{} is outside the workspace root.",
+                local_path.to_local_path_string()
+            ))
+        })?;
+
+    // 3. 只有同步过的文件才有元数据
+    let meta = state.db.get_file_meta(&workspace_path)?.ok_or_else(|| {
+        AppError::NotFound(format!(
+            "{} has not been synced to workspace {}.",
+            request_body.depot_path, request_body.workspace_name
+        ))
+    })?;
+
+    // 4. 按顺序拼接所有 chunk，只在缺少 base chunk 时才连接 hive
+    let mut content = Vec::with_capacity(meta.size.max(0) as usize);
+    let mut hive = None;
+    for chunk_hash in &meta.chunk_hashes {
+        if let Some(chunk) = state.db.get_base_chunk(chunk_hash)? {
+            content.extend_from_slice(&chunk);
+            continue;
+        }
+
+        if hive.is_none() {
+            let channel = state
+                .hive_channel
+                .get_channel(&runtime_config.remote_addr.value)?;
+            hive = Some((HiveServiceClient::new(channel.clone()), channel));
+        }
+        let (hive_client, channel) = hive.as_mut().expect("hive connection is initialized");
+        let mut stream = hive_client
+            .download_file_chunk(DownloadFileChunkReq {
+                chunk_hashes: vec![chunk_hash.clone()],
+                packet_size: FRAME_SIZE as i64,
+                compression: String::new(),
+            })
+            .await?
+            .into_inner();
+        let mut chunk = Vec::new();
+        while let Some(rsp) = channel.next_message(&mut stream).await? {
+            metrics::collector().add_downloaded_bytes(rsp.content.len());
+            chunk.extend_from_slice(&rsp.content);
+        }
+        let received_hash = hex::encode(blake3::hash(&chunk).as_bytes());
+        if received_hash != *chunk_hash {
+            return Err(AppError::Internal(format!(
+                "chunk hash mismatch: expected {chunk_hash}, got {received_hash}"
+            )));
+        }
+        content.extend_from_slice(&chunk);
+    }
+
+    Ok(Response::new(GetFileContentRsp {
+        local_path: local_path.to_local_path_string(),
+        generation: meta.current_revision.generation,
+        revision: meta.current_revision.revision,
+        content,
+    }))
+}
@@ -0,0 +1,39 @@
+use crate::daemon_server::config::RuntimeConfig;
+use crate::daemon_server::error::AppResult;
+use crate::daemon_server::state::AppState;
+use crate::hive_pb::{self, hive_service_client::HiveServiceClient};
+use crate::pb::{ListLockedFilesReq, ListLockedFilesRsp, LockedFile};
+use tonic::{Request, Response};
+
+pub async fn handle(
+    state: AppState,
+    req: Request<ListLockedFilesReq>,
+) -> AppResult<Response<ListLockedFilesRsp>> {
+    let runtime_config = RuntimeConfig::from_req(&req)?;
+    let channel = state
+        .hive_channel
+        .get_channel(&runtime_config.remote_addr.value)?;
+
+    let mut hive_client = HiveServiceClient::new(channel);
+
+    let request_body = req.into_inner();
+    let hive_rsp = hive_client
+        .list_locked_files(hive_pb::ListLockedFilesReq {
+            branch_id: request_body.branch_id,
+        })
+        .await?
+        .into_inner();
+
+    Ok(Response::new(ListLockedFilesRsp {
+        locked_files: hive_rsp
+            .locked_files
+            .into_iter()
+            .map(|f| LockedFile {
+                read: f.mode() == hive_pb::LockMode::Read,
+                file_id: f.file_id,
+                path: f.path,
+                locked_by: f.locked_by,
+            })
+            .collect(),
+    }))
+}
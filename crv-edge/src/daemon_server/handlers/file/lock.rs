@@ -0,0 +1,90 @@
+use crate::daemon_server::config::RuntimeConfig;
+use crate::daemon_server::error::{AppError, AppResult};
+use crate::daemon_server::handlers::utils::{
+    expand_to_mapped_files_in_edge_meta, normalize_paths_strict,
+};
+use crate::daemon_server::state::AppState;
+use crate::hive_pb::hive_service_client::HiveServiceClient;
+use crate::hive_pb::{FileToLock, LaunchSubmitReq, LockMode};
+use crate::pb::{LockReq, LockRsp};
+use crv_core::path::engine::PathEngine;
+use tonic::{Request, Response, Status};
+
+pub async fn handle(state: AppState, req: Request<LockReq>) -> AppResult<Response<LockRsp>> {
+    let runtime_config = RuntimeConfig::from_req(&req)?;
+    let request_body = req.into_inner();
+
+    // 独占写锁由提交流程自动获取，这里只支持共享读锁
+    if !request_body.read {
+        return Err(AppError::Raw(Status::unimplemented(
+            "exclusive locks are acquired by submit, only read locks can be requested",
+        )));
+    }
+
+    let workspace_meta = state
+        .db
+        .get_confirmed_workspace_meta(&request_body.workspace_name)?
+        .ok_or(AppError::Raw(Status::not_found(format!(
+            "Workspace {} not found.",
+            request_body.workspace_name
+        ))))?;
+
+    let path_engine = PathEngine::new(workspace_meta.config.clone(), &request_body.workspace_name);
+    let local_paths = normalize_paths_strict(&request_body.paths, &path_engine)?;
+
+    // 只有已经拉取到本地的文件才能加读锁，锁定时要求 hive 上的版本与本地一致
+    let files = expand_to_mapped_files_in_edge_meta(&local_paths, &path_engine, state.clone())?;
+    let mut files_to_lock = Vec::new();
+    let mut workspace_paths = Vec::new();
+    for file in &files {
+        let Some(meta) = state.db.get_file_meta(&file.workspace_path)? else {
+            continue;
+        };
+        workspace_paths.push(file.workspace_path.clone());
+        files_to_lock.push(FileToLock {
+            path: file.depot_path.to_custom_string(),
+            expected_file_generation: Some(meta.current_revision.generation),
+            expected_file_revision: Some(meta.current_revision.revision),
+            mode: LockMode::Read as i32,
+        });
+    }
+    if files_to_lock.is_empty() {
+        return Err(AppError::Raw(Status::invalid_argument(
+            "no synced files match the given paths",
+        )));
+    }
+
+    let channel = state
+        .hive_channel
+        .get_channel(&runtime_config.remote_addr.value)?;
+    let mut hive_client = HiveServiceClient::new(channel);
+
+    let locked_paths: Vec<String> = files_to_lock.iter().map(|f| f.path.clone()).collect();
+    let hive_rsp = hive_client
+        .launch_submit(LaunchSubmitReq {
+            files: files_to_lock,
+            branch_id: request_body.branch_id.clone(),
+        })
+        .await?
+        .into_inner();
+    if !hive_rsp.success {
+        let conflicted: Vec<String> = hive_rsp
+            .file_unable_to_lock
+            .into_iter()
+            .map(|f| f.path)
+            .collect();
+        return Err(AppError::Raw(Status::failed_precondition(format!(
+            "Can't lock files: {}",
+            conflicted.join(", ")
+        ))));
+    }
+
+    for path in &workspace_paths {
+        state.db.set_lock_ticket(path, &hive_rsp.ticket)?;
+    }
+
+    Ok(Response::new(LockRsp {
+        locked_paths,
+        ticket: hive_rsp.ticket,
+    }))
+}
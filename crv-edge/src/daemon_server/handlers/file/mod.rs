@@ -1,6 +1,11 @@
 pub mod add;
 pub mod checkout;
 pub mod delete;
+pub mod describe;
+pub mod get_file_content;
 pub mod list_active_files;
+pub mod list_locked_files;
+pub mod lock;
+pub mod move_file;
 pub mod submit;
 pub mod sync;
@@ -0,0 +1,510 @@
+use crate::daemon_server::config::RuntimeConfig;
+use crate::daemon_server::context::SessionContext;
+use crate::daemon_server::db::active_file::{Action, MoveSource};
+use crate::daemon_server::db::file::FileLocation;
+use crate::daemon_server::error::{AppError, AppResult};
+use crate::daemon_server::handlers::utils::{
+    LocationUnion, expand_to_mapped_files_in_edge_meta, normalize_paths_strict,
+};
+use crate::daemon_server::state::AppState;
+use crate::hive_pb::ListLockedFilesReq;
+use crate::hive_pb::hive_service_client::HiveServiceClient;
+use crate::pb::{MoveReq, MoveRsp};
+use crv_core::path::basic::{LocalDir, LocalPath};
+use crv_core::path::engine::PathEngine;
+use std::path::Path;
+use tokio::fs;
+use tonic::{Request, Response, Status};
+
+pub async fn handle(state: AppState, req: Request<MoveReq>) -> AppResult<Response<MoveRsp>> {
+    let ctx = SessionContext::from_req(&req)?;
+    let runtime_config = RuntimeConfig::from_req(&req)?;
+    let request_body = req.into_inner();
+
+    // 1. 获取 workspace 信息
+    let workspace_meta = state
+        .db
+        .get_confirmed_workspace_meta(&request_body.workspace_name)?
+        .ok_or(AppError::Raw(Status::not_found(format!(
+            "Workspace {} not found.",
+            request_body.workspace_name
+        ))))?;
+
+    let path_engine = PathEngine::new(workspace_meta.config.clone(), &request_body.workspace_name);
+
+    // 2. 解析源文件，源文件必须是已经拉取到本地的单个文件
+    let from_paths = normalize_paths_strict(&[request_body.from_path.clone()], &path_engine)?;
+    if !matches!(
+        from_paths.as_slice(),
+        [LocationUnion::LocalPath(_)] | [LocationUnion::WorkspacePath(_)]
+    ) {
+        return Err(AppError::Raw(Status::invalid_argument(format!(
+            "Source {} must be a single file.",
+            request_body.from_path
+        ))));
+    }
+    let source = expand_to_mapped_files_in_edge_meta(&from_paths, &path_engine, state.clone())?
+        .pop()
+        .ok_or(AppError::Raw(Status::failed_precondition(format!(
+            "Source {} is not synced.",
+            request_body.from_path
+        ))))?;
+    let source_meta = state
+        .db
+        .get_file_meta(&source.workspace_path)?
+        .ok_or(AppError::Raw(Status::failed_precondition(format!(
+            "Source {} is not synced.",
+            request_body.from_path
+        ))))?;
+
+    // 源文件已经处于 add/delete/move 状态时不允许再次移动，仅 checkout 编辑中的文件可以移动
+    match state.db.get_active_file_action(&source.workspace_path)? {
+        None | Some(Action::Edit) => {}
+        Some(action) => {
+            return Err(AppError::Raw(Status::failed_precondition(format!(
+                "Source {} is already opened for {}.",
+                source.workspace_path.to_custom_string(),
+                action.to_custom_string()
+            ))));
+        }
+    }
+
+    if !Path::new(&source.local_path.to_local_path_string()).is_file() {
+        return Err(AppError::Raw(Status::failed_precondition(format!(
+            "Source {} does not exist on local disk.",
+            source.local_path.to_local_path_string()
+        ))));
+    }
+
+    // 3. 解析目标位置
+    let to_paths = normalize_paths_strict(&[request_body.to_path.clone()], &path_engine)?;
+    let destination = to_paths
+        .first()
+        .ok_or(AppError::Raw(Status::invalid_argument(format!(
+            "Destination {} is invalid.",
+            request_body.to_path
+        ))))?;
+    let destination = resolve_destination(&source, destination, &path_engine)?;
+
+    if state
+        .db
+        .get_file_meta(&destination.workspace_path)?
+        .is_some()
+        || state
+            .db
+            .get_active_file_action(&destination.workspace_path)?
+            .is_some()
+        || Path::new(&destination.local_path.to_local_path_string()).exists()
+    {
+        return Err(AppError::Raw(Status::already_exists(format!(
+            "Destination {} already exists.",
+            destination.workspace_path.to_custom_string()
+        ))));
+    }
+
+    // 4. 源文件被其他用户锁定时不允许移动，对方持有锁期间无法提交这次移动
+    let channel = state
+        .hive_channel
+        .get_channel(&runtime_config.remote_addr.value)?;
+    let source_depot_path = source.depot_path.to_custom_string();
+    let locked_files = HiveServiceClient::new(channel)
+        .list_locked_files(ListLockedFilesReq {
+            branch_id: String::new(),
+        })
+        .await?
+        .into_inner()
+        .locked_files;
+    if let Some(locked) = locked_files.iter().find(|f| f.path == source_depot_path) {
+        // 多个读锁的持有者以逗号分隔
+        if locked
+            .locked_by
+            .split(',')
+            .any(|holder| holder.trim() != ctx.username)
+        {
+            return Err(AppError::Raw(Status::failed_precondition(format!(
+                "Source {} is locked by {}.",
+                source.workspace_path.to_custom_string(),
+                locked.locked_by
+            ))));
+        }
+    }
+
+    // 5. 源文件标记为 Delete，目标文件标记为 MoveAdd 并记录源文件的最新版本，两者同时生效。
+    //    先在事务中写好记录，本地文件移动成功后才提交，移动失败时数据库保持原样
+    let move_add = Action::MoveAdd(MoveSource {
+        depot_path: source.depot_path.clone(),
+        generation: source_meta.current_revision.generation,
+        revision: source_meta.current_revision.revision,
+    });
+    let stage = || {
+        let mut transaction = state.db.begin_transaction();
+        transaction.set_active_file_action(&source.workspace_path, &Action::Delete)?;
+        transaction.set_active_file_action(&destination.workspace_path, &move_add)?;
+        Ok::<_, AppError>(transaction)
+    };
+    let mut transaction = stage()?;
+
+    // 6. 移动本地文件
+    let from = source.local_path.to_local_path_string();
+    let to = destination.local_path.to_local_path_string();
+    if let Some(parent) = Path::new(&to).parent() {
+        fs::create_dir_all(parent)
+            .await
+            .map_err(|e| AppError::Internal(format!("{e}")))?;
+    }
+    fs::rename(&from, &to)
+        .await
+        .map_err(|e| AppError::Internal(format!("{e}")))?;
+
+    // 乐观事务冲突时重新写入；此时文件已经移动，重新写入失败时将文件移回原处
+    loop {
+        if transaction.commit().is_ok() {
+            break;
+        }
+        transaction = match stage() {
+            Ok(transaction) => transaction,
+            Err(e) => {
+                if let Err(rollback) = fs::rename(&to, &from).await {
+                    return Err(AppError::Internal(format!(
+                        "{e}; failed to move {to} back to {from}: {rollback}"
+                    )));
+                }
+                return Err(e);
+            }
+        };
+    }
+
+    // 7. 归入指定的本地 changelist
+    if !request_body.changelist_id.is_empty() {
+        state.db.append_changelist_workspace_paths(
+            &request_body.changelist_id,
+            vec![
+                source.workspace_path.clone(),
+                destination.workspace_path.clone(),
+            ],
+        )?;
+    }
+
+    Ok(Response::new(MoveRsp {
+        from_path: source.workspace_path.to_custom_string(),
+        to_path: destination.workspace_path.to_custom_string(),
+    }))
+}
+
+/// 根据用户输入的目标位置计算移动后的文件位置，目标为目录时沿用源文件的文件名
+fn resolve_destination(
+    source: &FileLocation,
+    destination: &LocationUnion,
+    path_engine: &PathEngine,
+) -> AppResult<FileLocation> {
+    let into_dir = |dir: LocalDir| LocalPath {
+        dirs: dir,
+        file: source.local_path.file.clone(),
+    };
+    let local_path = match destination {
+        LocationUnion::LocalDir(local_dir) => Some(into_dir(local_dir.clone())),
+        LocationUnion::LocalPath(local_path) => Some(local_path.clone()),
+        LocationUnion::WorkspaceDir(workspace_dir) => path_engine
+            .workspace_dir_to_local_dir(workspace_dir)
+            .map(into_dir),
+        LocationUnion::WorkspacePath(workspace_path) => {
+            path_engine.workspace_path_to_local_path(workspace_path)
+        }
+    }
+    .ok_or(AppError::Raw(Status::invalid_argument(
+        "Destination does not under current workspace.",
+    )))?;
+
+    if local_path == source.local_path {
+        return Err(AppError::Raw(Status::invalid_argument(
+            "Destination is the same as source.",
+        )));
+    }
+
+    let workspace_path = path_engine
+        .local_path_to_workspace_path(&local_path)
+        .ok_or(AppError::Raw(Status::invalid_argument(format!(
+            "Path {} does not under current workspace.",
+            local_path.to_local_path_string()
+        ))))?;
+    let depot_path = path_engine
+        .mapping_local_path(&local_path)
+        .ok_or(AppError::Raw(Status::invalid_argument(format!(
+            "Path {} is not mapped to any depot path.",
+            local_path.to_local_path_string()
+        ))))?;
+
+    Ok(FileLocation {
+        local_path,
+        workspace_path,
+        depot_path,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::daemon_server::config::{RuntimeConfigItem, RuntimeConfigSource};
+    use crate::daemon_server::handlers::edge::stub_hive::{self, StubHive};
+    use crate::daemon_server::handlers::file::sync;
+    use crate::hive_pb::{LockMode, LockedFile};
+    use crate::pb::SyncWithProgressReq;
+    use crate::test_support::TempDb;
+    use crv_core::path::basic::{DepotPath, WorkspaceDir, WorkspacePath};
+    use crv_core::workspace::entity::WorkspaceConfig;
+    use tokio_stream::StreamExt;
+
+    fn engine() -> PathEngine {
+        let config = WorkspaceConfig::from_specification(
+            "workspace",
+            "/root/test/",
+            r#"
+            //crv/cli/... //workspace/cli/
+            //art/... //workspace/art/
+            "#,
+        )
+        .unwrap();
+        PathEngine::new(config, "workspace")
+    }
+
+    fn source(engine: &PathEngine, workspace_path: &str) -> FileLocation {
+        let workspace_path = WorkspacePath::parse(workspace_path).unwrap();
+        let local_path = engine
+            .workspace_path_to_local_path(&workspace_path)
+            .unwrap();
+        let depot_path = engine.mapping_local_path(&local_path).unwrap();
+        FileLocation {
+            local_path,
+            workspace_path,
+            depot_path,
+        }
+    }
+
+    #[test]
+    fn move_into_subdirectory() {
+        let engine = engine();
+        let source = source(&engine, "//workspace/cli/main.rs");
+        let destination =
+            LocationUnion::WorkspaceDir(WorkspaceDir::parse("//workspace/cli/src/bin/").unwrap());
+
+        let target = resolve_destination(&source, &destination, &engine).unwrap();
+        assert_eq!(
+            target.workspace_path.to_custom_string(),
+            "//workspace/cli/src/bin/main.rs"
+        );
+        assert_eq!(
+            target.local_path,
+            LocalPath::parse("/root/test/cli/src/bin/main.rs").unwrap()
+        );
+        assert_eq!(
+            target.depot_path,
+            DepotPath::parse("//crv/cli/src/bin/main.rs").unwrap()
+        );
+    }
+
+    #[test]
+    fn move_across_depot_roots() {
+        let engine = engine();
+        let source = source(&engine, "//workspace/cli/logo.png");
+        let destination =
+            LocationUnion::LocalPath(LocalPath::parse("/root/test/art/icons/logo.png").unwrap());
+
+        let target = resolve_destination(&source, &destination, &engine).unwrap();
+        assert_eq!(source.depot_path.to_custom_string(), "//crv/cli/logo.png");
+        assert_eq!(target.depot_path.to_custom_string(), "//art/icons/logo.png");
+        assert_eq!(
+            target.workspace_path.to_custom_string(),
+            "//workspace/art/icons/logo.png"
+        );
+    }
+
+    #[test]
+    fn move_rejects_unmapped_or_same_destination() {
+        let engine = engine();
+        let source = source(&engine, "//workspace/cli/main.rs");
+
+        let unmapped =
+            LocationUnion::LocalPath(LocalPath::parse("/root/test/docs/main.rs").unwrap());
+        assert!(resolve_destination(&source, &unmapped, &engine).is_err());
+
+        let same = LocationUnion::WorkspacePath(source.workspace_path.clone());
+        assert!(resolve_destination(&source, &same, &engine).is_err());
+    }
+
+    fn request<T>(remote_addr: &str, body: T) -> Request<T> {
+        let mut runtime_config = RuntimeConfig::default();
+        runtime_config.remote_addr = RuntimeConfigItem {
+            value: remote_addr.to_string(),
+            source: RuntimeConfigSource::Override,
+        };
+        let mut req = Request::new(body);
+        req.extensions_mut().insert(runtime_config);
+        req.extensions_mut().insert(SessionContext {
+            username: "alice".to_string(),
+            token: String::new(),
+        });
+        req
+    }
+
+    fn locked(path: &str, locked_by: &str) -> LockedFile {
+        LockedFile {
+            file_id: path.to_string(),
+            path: path.to_string(),
+            locked_by: locked_by.to_string(),
+            mode: LockMode::Read as i32,
+        }
+    }
+
+    fn move_req(addr: &str, from: &str, to: &str) -> Request<MoveReq> {
+        request(
+            addr,
+            MoveReq {
+                workspace_name: "ws".to_string(),
+                from_path: from.to_string(),
+                to_path: to.to_string(),
+                changelist_id: String::new(),
+            },
+        )
+    }
+
+    /// 创建映射 `//... //ws/` 的 workspace 并从 hive 拉取所有文件，返回 workspace 根目录
+    async fn synced_workspace(db: &TempDb, addr: &str) -> String {
+        let state = db.state();
+        let workspace_root = db.root().join("ws");
+        std::fs::create_dir_all(&workspace_root).unwrap();
+        let workspace_root = format!("{}/", workspace_root.to_string_lossy());
+        let config =
+            WorkspaceConfig::from_specification("ws", &workspace_root, "//... //ws/").unwrap();
+        state
+            .db
+            .create_workspace_pending("ws".to_string(), config)
+            .unwrap();
+        state.db.confirm_workspace("ws".to_string()).unwrap();
+        let events: Vec<_> = sync::handle_with_progress(
+            state,
+            request(
+                addr,
+                SyncWithProgressReq {
+                    workspace_name: "ws".to_string(),
+                    paths: vec![workspace_root.clone()],
+                    ..Default::default()
+                },
+            ),
+        )
+        .await
+        .unwrap()
+        .into_inner()
+        .collect()
+        .await;
+        assert!(events.iter().all(|event| event.is_ok()));
+        workspace_root
+    }
+
+    #[tokio::test]
+    async fn move_records_delete_and_move_add() {
+        let addr = stub_hive::spawn(StubHive {
+            files: vec![("//a.txt".to_string(), b"a".to_vec())],
+            ..Default::default()
+        })
+        .await;
+        let db = TempDb::new();
+        let state = db.state();
+        let workspace_root = synced_workspace(&db, &addr).await;
+
+        let rsp = handle(
+            state.clone(),
+            move_req(&addr, "//ws/a.txt", "//ws/sub/c.txt"),
+        )
+        .await
+        .unwrap()
+        .into_inner();
+        assert_eq!(rsp.from_path, "//ws/a.txt");
+        assert_eq!(rsp.to_path, "//ws/sub/c.txt");
+        assert!(!Path::new(&format!("{workspace_root}a.txt")).exists());
+        assert!(Path::new(&format!("{workspace_root}sub/c.txt")).is_file());
+
+        let a = WorkspacePath::parse("//ws/a.txt").unwrap();
+        let c = WorkspacePath::parse("//ws/sub/c.txt").unwrap();
+        let meta = state.db.get_file_meta(&a).unwrap().unwrap();
+        assert!(state.db.get_active_file_action(&a).unwrap() == Some(Action::Delete));
+        assert!(
+            state.db.get_active_file_action(&c).unwrap()
+                == Some(Action::MoveAdd(MoveSource {
+                    depot_path: DepotPath::parse("//a.txt").unwrap(),
+                    generation: meta.current_revision.generation,
+                    revision: meta.current_revision.revision,
+                }))
+        );
+    }
+
+    #[tokio::test]
+    async fn failed_local_move_leaves_db_unchanged() {
+        let addr = stub_hive::spawn(StubHive {
+            files: vec![
+                ("//a.txt".to_string(), b"a".to_vec()),
+                ("//b.txt".to_string(), b"b".to_vec()),
+            ],
+            ..Default::default()
+        })
+        .await;
+        let db = TempDb::new();
+        let state = db.state();
+        let workspace_root = synced_workspace(&db, &addr).await;
+
+        // a.txt 是文件，无法在其下创建目录，移动在修改磁盘之前失败
+        let Err(AppError::Internal(_)) = handle(
+            state.clone(),
+            move_req(&addr, "//ws/b.txt", "//ws/a.txt/c.txt"),
+        )
+        .await
+        else {
+            panic!("moving under a file should fail");
+        };
+        assert!(Path::new(&format!("{workspace_root}b.txt")).is_file());
+        for path in ["//ws/b.txt", "//ws/a.txt/c.txt"] {
+            let path = WorkspacePath::parse(path).unwrap();
+            assert!(state.db.get_active_file_action(&path).unwrap().is_none());
+        }
+
+        // 失败后仍然可以正常移动
+        handle(state.clone(), move_req(&addr, "//ws/b.txt", "//ws/c.txt"))
+            .await
+            .unwrap();
+        assert!(Path::new(&format!("{workspace_root}c.txt")).is_file());
+    }
+
+    #[tokio::test]
+    async fn source_locked_by_another_user_is_not_moved() {
+        let addr = stub_hive::spawn(StubHive {
+            files: vec![
+                ("//a.txt".to_string(), b"a".to_vec()),
+                ("//b.txt".to_string(), b"b".to_vec()),
+            ],
+            locked_files: vec![locked("//a.txt", "alice, bob"), locked("//b.txt", "alice")],
+            ..Default::default()
+        })
+        .await;
+        let db = TempDb::new();
+        let state = db.state();
+        let workspace_root = synced_workspace(&db, &addr).await;
+
+        // bob 也持有 a.txt 的读锁
+        let Err(AppError::Raw(status)) =
+            handle(state.clone(), move_req(&addr, "//ws/a.txt", "//ws/c.txt")).await
+        else {
+            panic!("moving a file locked by another user should fail");
+        };
+        assert_eq!(status.code(), tonic::Code::FailedPrecondition);
+        assert!(status.message().contains("bob"));
+        assert!(Path::new(&format!("{workspace_root}a.txt")).is_file());
+        assert!(!Path::new(&format!("{workspace_root}c.txt")).exists());
+        let a = WorkspacePath::parse("//ws/a.txt").unwrap();
+        assert!(state.db.get_active_file_action(&a).unwrap().is_none());
+
+        // 只有自己持有锁时可以移动
+        handle(state.clone(), move_req(&addr, "//ws/b.txt", "//ws/d.txt"))
+            .await
+            .unwrap();
+        assert!(Path::new(&format!("{workspace_root}d.txt")).is_file());
+    }
+}
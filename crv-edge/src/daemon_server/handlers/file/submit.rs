@@ -1,22 +1,27 @@
 use crate::daemon_server::config::RuntimeConfig;
 use crate::daemon_server::context::SessionContext;
+use crate::daemon_server::db::DbManager;
 use crate::daemon_server::db::active_file::Action;
 use crate::daemon_server::db::file::{FileLocation, FileMeta, FileRevision};
 use crate::daemon_server::error::{AppError, AppResult};
 use crate::daemon_server::handlers::utils::{
     expand_to_mapped_files_active, normalize_paths_strict,
 };
+use crate::daemon_server::hook::run_pre_submit_hook;
 use crate::daemon_server::job::{
     JobEvent, JobRetentionPolicy, JobStatus, MessageStoragePolicy, WorkerProtocol,
 };
-use crate::daemon_server::state::AppState;
+use crate::daemon_server::retry::with_retry;
+use crate::daemon_server::state::{AppState, HiveChannel};
 use crate::hive_pb::hive_service_client::HiveServiceClient;
 use crate::hive_pb::{
-    CheckChunksReq, FileChunk, FileToLock, LaunchSubmitReq, UploadFileChunkReq, UploadFileChunkRsp,
+    CheckChunksReq, DeltaUploadReq, FileChunk, FileToLock, LaunchSubmitReq, LockMode,
+    UploadFileChunkReq, UploadFileChunkRsp,
 };
+use crate::metrics;
 use crate::pb::{SubmitProgress, SubmitReq};
 use crv_core::path::engine::PathEngine;
-use crv_core::repository::compute_chunk_hash;
+use crv_core::repository::{compute_chunk_hash, compute_delta};
 use prost::Message;
 use std::pin::Pin;
 use std::sync::{Arc, Weak};
@@ -26,7 +31,6 @@ use tokio::sync::mpsc::Sender;
 use tokio::{fs::File, io::AsyncReadExt};
 use tokio_stream::wrappers::{BroadcastStream, ReceiverStream};
 use tokio_stream::{Stream, StreamExt};
-use tonic::transport::Channel;
 use tonic::{Request, Response, Status};
 
 pub type SubmitProgressStream =
@@ -58,10 +62,12 @@ struct FileToSubmit {
     location: FileLocation,
     action: Action,
     current_revision: Option<FileRevision>,
+    /// 当前版本的 chunk hash 列表，用于差量上传
+    base_chunk_hashes: Vec<String>,
 }
 
-const FRAME_SIZE: usize = 64 * 1024; // 64KB，单个报文中的数据大小
-const CHUNK_SIZE: usize = 4 * 1024 * 1024; // 4MB，内存中的处理窗口，也是一个 chunk 的大小
+pub(crate) const FRAME_SIZE: usize = 64 * 1024; // 64KB，单个报文中的数据大小
+pub(crate) const CHUNK_SIZE: usize = 4 * 1024 * 1024; // 4MB，内存中的处理窗口，也是一个 chunk 的大小
 const WORKER_COUNT: i32 = 8;
 
 pub async fn handle(
@@ -95,19 +101,35 @@ pub async fn handle(
         }
         let file_action = file_action.unwrap();
         // 获得文件当前 revision
-        let file_revision = if file_action == Action::Add {
-            None
-        } else {
-            let file_meta = state.db.get_file_meta(&file.workspace_path)?.unwrap();
-            Some(file_meta.current_revision)
-        };
+        let (file_revision, base_chunk_hashes) =
+            if matches!(file_action, Action::Add | Action::MoveAdd(_)) {
+                (None, vec![])
+            } else {
+                let file_meta = state.db.get_file_meta(&file.workspace_path)?.unwrap();
+                (Some(file_meta.current_revision), file_meta.chunk_hashes)
+            };
         files_to_submit.push(FileToSubmit {
             location: file.clone(),
             action: file_action,
             current_revision: file_revision,
+            base_chunk_hashes,
         });
     }
 
+    if let Some(hook) = &state.pre_submit_hook {
+        let local_paths: Vec<String> = files_to_submit
+            .iter()
+            .map(|file| file.location.local_path.to_local_path_string())
+            .collect();
+        run_pre_submit_hook(
+            hook,
+            request_body.branch_id.trim(),
+            &request_body.description,
+            &local_paths,
+        )
+        .await?;
+    }
+
     // make a replica for submit task to use
     let files_to_submit_replica = files_to_submit.clone();
 
@@ -125,11 +147,13 @@ pub async fn handle(
             path: file.location.depot_path.to_custom_string(),
             expected_file_generation: file.current_revision.as_ref().map(|x| x.generation),
             expected_file_revision: file.current_revision.as_ref().map(|x| x.revision),
+            mode: LockMode::Write as i32,
         });
     }
 
     let try_lock_req = LaunchSubmitReq {
         files: files_to_lock,
+        branch_id: request_body.branch_id.clone(),
     };
 
     let try_lock_file_response = hive_client.launch_submit(try_lock_req).await?.into_inner();
@@ -168,15 +192,26 @@ pub async fn handle(
 
     let rx = job.tx.subscribe();
     let description = request_body.description.clone();
+    let branch_id = request_body.branch_id.clone();
+    let dry_run = request_body.dry_run;
     let files_to_submit = Arc::new(Mutex::new(files_to_submit));
     let file_chunks = Arc::new(Mutex::new(vec![]));
 
     let (marker_tx, marker_rx) = tokio::sync::mpsc::channel::<()>(1);
 
-    let (upload_chunk_tx, upload_chunk_rx) = tokio::sync::mpsc::channel(WORKER_COUNT as usize * 10);
-    let upload_rsp = hive_client
-        .upload_file_chunk(ReceiverStream::new(upload_chunk_rx))
-        .await?;
+    // 建立上传流时还未发送任何数据，失败后可以安全地用新的流重试
+    let (upload_chunk_tx, upload_rsp) = with_retry(channel.retry_policy(), || {
+        let mut hive_client = hive_client.clone();
+        async move {
+            let (upload_chunk_tx, upload_chunk_rx) =
+                tokio::sync::mpsc::channel(WORKER_COUNT as usize * 10);
+            let upload_rsp = hive_client
+                .upload_file_chunk(ReceiverStream::new(upload_chunk_rx))
+                .await?;
+            Ok((upload_chunk_tx, upload_rsp))
+        }
+    })
+    .await?;
     let upload_rsp_stream = upload_rsp.into_inner();
 
     for i in 0..WORKER_COUNT {
@@ -187,8 +222,10 @@ pub async fn handle(
         let job_clone = job.clone();
         let ticket_clone = ticket.clone();
         let marker_clone = marker_tx.clone();
+        let db = state.db.clone();
         job.add_worker(async move {
             upload_task(
+                db,
                 files,
                 file_chunks,
                 chunks_amount as i64,
@@ -203,11 +240,14 @@ pub async fn handle(
         });
     }
 
+    let response_channel = channel.clone();
     job.add_worker(async move {
         submit_task(
             state.clone(),
             ticket,
             description,
+            branch_id,
+            dry_run,
             file_chunks,
             files_to_submit_replica,
             channel,
@@ -216,7 +256,9 @@ pub async fn handle(
         .await
     });
     let job_clone = job.clone();
-    job.add_worker(async move { response_task(upload_rsp_stream, job_clone).await });
+    job.add_worker(
+        async move { response_task(upload_rsp_stream, response_channel, job_clone).await },
+    );
 
     drop(marker_tx);
 
@@ -250,33 +292,68 @@ async fn submit_task(
     state: AppState,
     ticket: String,
     description: String,
+    branch_id: String,
+    dry_run: bool,
     file_chunks: Arc<Mutex<Vec<FileChunk>>>,
     files_to_submit: Vec<FileToSubmit>,
-    channel: Channel,
+    channel: HiveChannel,
     mut marker: tokio::sync::mpsc::Receiver<()>,
 ) -> Result<(), String> {
     while let Some(_) = marker.recv().await {}
-    let mut hive_client = HiveServiceClient::new(channel.clone());
+    let hive_client = HiveServiceClient::new(channel.clone());
+    // hive 按 request_id 去重，重试时保持不变，不会重复提交
     let submit_request = crate::hive_pb::SubmitReq {
         ticket,
         description,
         file_chunks: file_chunks.lock().await.clone(),
+        request_id: uuid::Uuid::new_v4().to_string(),
+        dry_run,
+        branch_id,
     };
-    let submit_response = hive_client
-        .submit(submit_request)
-        .await
-        .map_err(|x| format!("{x}"))?
-        .into_inner();
+    let submit_response = with_retry(channel.retry_policy(), || {
+        let mut hive_client = hive_client.clone();
+        let submit_request = submit_request.clone();
+        async move { hive_client.submit(submit_request).await }
+    })
+    .await
+    .map_err(|x| format!("{x}"))?
+    .into_inner();
 
     if !submit_response.success {
-        return Err(format!(
-            "SubmitReq failed with error: {}",
-            submit_response.message
-        ));
+        let mut message = format!("SubmitReq failed with error: {}", submit_response.message);
+        for conflict in &submit_response.conflicts {
+            message.push_str(&format!(
+                "\n  conflict: {} (expected {}#{}, current {}#{})",
+                conflict.path,
+                conflict.expected_file_generation,
+                conflict.expected_file_revision,
+                conflict.current_file_generation,
+                conflict.current_file_revision
+            ));
+        }
+        if !submit_response.missing_chunks.is_empty() {
+            message.push_str(&format!(
+                "\n  {} chunk(s) missing on hive",
+                submit_response.missing_chunks.len()
+            ));
+        }
+        return Err(message);
+    }
+
+    // 预检没有产生新的版本，本地文件保持 checkout 状态
+    if dry_run {
+        return Ok(());
     }
 
     // 更新数据库
     for file in files_to_submit.iter() {
+        // 提交后上一个版本不再作为差量的 base，删除本地保存的 chunk
+        for base_chunk_hash in &file.base_chunk_hashes {
+            state
+                .db
+                .delete_base_chunk(base_chunk_hash)
+                .map_err(|x| format!("{x}"))?;
+        }
         if file.action == Action::Delete {
             state
                 .db
@@ -299,6 +376,9 @@ async fn submit_task(
                         generation: latest_revision.generation,
                         revision: latest_revision.revision,
                     },
+                    changelist_id: latest_revision.changelist_id,
+                    size: latest_revision.size,
+                    chunk_hashes: latest_revision.binary_id.clone(),
                 },
             )
             .map_err(|x| format!("{x}"))?;
@@ -309,10 +389,11 @@ async fn submit_task(
 }
 
 async fn upload_task(
+    db: Arc<DbManager>,
     files: Arc<Mutex<Vec<FileToSubmit>>>,
     file_chunks: Arc<Mutex<Vec<FileChunk>>>,
     chunks_amount: i64,
-    channel: Channel,
+    channel: HiveChannel,
     upload_chunk_tx: Sender<UploadFileChunkReq>,
     ticket: String,
     job: Arc<crate::daemon_server::job::Job>,
@@ -333,6 +414,7 @@ async fn upload_task(
                 let submit_file = FileChunk {
                     path: file_info.location.depot_path.to_custom_string(), // 使用服务器路径
                     binary_id: vec![],                                      // 块 Hash 列表
+                    ..Default::default()
                 };
                 file_chunks.lock().await.push(submit_file);
                 continue;
@@ -342,7 +424,7 @@ async fn upload_task(
                 .await
                 .map_err(|e| format!("Open error: {e}"))?;
 
-            let mut hive_client = HiveServiceClient::new(channel.clone());
+            let hive_client = HiveServiceClient::new(channel.clone());
             let mut chunk_hashes = vec![]; // 收集当前文件的所有块 hash
             let mut total_size = 0i64; // 当前已经传输的总大小
             let file_size = file.metadata().await.map_err(|x| format!("{x}"))?.len() as i64;
@@ -363,13 +445,18 @@ async fn upload_task(
                 chunk_hashes.push(chunk_hash.clone());
 
                 // 秒传逻辑：Check Chunks
-                let check_res = hive_client
-                    .check_chunks(CheckChunksReq {
-                        chunk_hashes: vec![chunk_hash.clone()],
-                    })
-                    .await
-                    .map_err(|x| format!("{x}"))?
-                    .into_inner();
+                let check_res = with_retry(channel.retry_policy(), || {
+                    let mut hive_client = hive_client.clone();
+                    let chunk_hashes = vec![chunk_hash.clone()];
+                    async move {
+                        hive_client
+                            .check_chunks(CheckChunksReq { chunk_hashes })
+                            .await
+                    }
+                })
+                .await
+                .map_err(|x| format!("{x}"))?
+                .into_inner();
 
                 // 如果这个 chunk 已经传输完毕，则跳过
                 if check_res.missing_chunk_hashes.is_empty() {
@@ -385,11 +472,49 @@ async fn upload_task(
                     continue;
                 }
 
+                // 差量上传：上一个版本对应位置的 chunk 在本地有保存时，只上传差量
+                let delta = match file_info.base_chunk_hashes.get(chunk_hashes.len() - 1) {
+                    Some(base_chunk_hash) if channel.delta_upload_enabled() => {
+                        compute_chunk_delta(&db, base_chunk_hash, &chunk_buffer[..n])
+                            .map(|delta| (base_chunk_hash.clone(), delta))
+                    }
+                    _ => None,
+                };
+                if let Some((base_chunk_hash, delta)) = delta {
+                    let delta_size = delta.len();
+                    channel.throttle_upload(delta_size).await;
+                    let delta_res = with_retry(channel.retry_policy(), || {
+                        let mut hive_client = hive_client.clone();
+                        let req = DeltaUploadReq {
+                            ticket: ticket.clone(),
+                            base_chunk_hash: base_chunk_hash.clone(),
+                            delta_data: delta.clone(),
+                            target_chunk_hash: chunk_hash.clone(),
+                        };
+                        async move { hive_client.delta_upload(req).await }
+                    })
+                    .await;
+                    // 差量上传失败（如 hive 上不存在 base chunk）时退回完整上传
+                    if delta_res.is_ok() {
+                        metrics::collector().add_uploaded_bytes(delta_size);
+                        total_size += n as i64;
+                        job.report_payload(SubmitProgress {
+                            path: file_info.location.local_path.to_local_path_string(),
+                            bytes_completed_so_far: total_size,
+                            size: file_size,
+                            info: format!("Chunk uploaded as {delta_size} bytes delta."),
+                            warning: String::new(),
+                        });
+                        continue;
+                    }
+                }
+
                 // 遍历切片并上传
                 let chunk = Arc::new(chunk_buffer[..n].to_vec());
                 let mut offset = 0i64;
                 let frames: Vec<&[u8]> = chunk.chunks(FRAME_SIZE).collect();
                 for (_, frame_data) in frames.iter().enumerate() {
+                    channel.throttle_upload(frame_data.len()).await;
                     upload_chunk_tx
                         .send(UploadFileChunkReq {
                             chunk_hash: chunk_hash.clone(),
@@ -403,6 +528,7 @@ async fn upload_task(
                         })
                         .await
                         .map_err(|x| format!("{x}"))?;
+                    metrics::collector().add_uploaded_bytes(frame_data.len());
 
                     // 更新进度
                     offset += frame_data.len() as i64;
@@ -417,10 +543,18 @@ async fn upload_task(
                 }
             }
             // --- 文件切块完成，收集文件 FileChunks 信息 ---
-            let submit_file = FileChunk {
+            let mut submit_file = FileChunk {
                 path: file_info.location.depot_path.to_custom_string(), // 使用服务器路径
                 binary_id: chunk_hashes,                                // 块 Hash 列表
+                ..Default::default()
             };
+            // 由 move 产生的文件，需要告知 hive 其来源以便追溯历史
+            if let Action::MoveAdd(source) = &file_info.action {
+                submit_file.is_rename = true;
+                submit_file.rename_from_path = source.depot_path.to_custom_string();
+                submit_file.rename_from_generation = source.generation;
+                submit_file.rename_from_revision = source.revision;
+            }
             file_chunks.lock().await.push(submit_file);
         } else {
             break;
@@ -431,12 +565,20 @@ async fn upload_task(
     Ok(())
 }
 
+/// 基于本地保存的 base chunk 计算差量，只有差量小于完整的 chunk 时才返回
+fn compute_chunk_delta(db: &DbManager, base_chunk_hash: &str, chunk: &[u8]) -> Option<Vec<u8>> {
+    let base = db.get_base_chunk(base_chunk_hash).ok()??;
+    let delta = compute_delta(&base, chunk);
+    (delta.len() < chunk.len()).then_some(delta)
+}
+
 async fn response_task(
     mut upload_rsp_stream: tonic::Streaming<UploadFileChunkRsp>,
+    channel: HiveChannel,
     job: Arc<crate::daemon_server::job::Job>,
 ) -> Result<(), String> {
-    while let Some(rsp) = upload_rsp_stream
-        .message()
+    while let Some(rsp) = channel
+        .next_message(&mut upload_rsp_stream)
         .await
         .map_err(|x| format!("{x}"))?
     {
@@ -461,3 +603,127 @@ async fn response_task(
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDb;
+
+    #[test]
+    fn one_byte_change_in_1mib_chunk_uploads_small_delta() {
+        let db = TempDb::new();
+        let base: Vec<u8> = (0..1024 * 1024u32)
+            .map(|i| (i.wrapping_mul(2654435761) >> 13) as u8)
+            .collect();
+        let base_hash = hex::encode(compute_chunk_hash(&base));
+
+        let mut chunk = base.clone();
+        chunk[777_777] ^= 0x01;
+
+        // 本地没有保存 base chunk 时完整上传
+        assert!(compute_chunk_delta(&db, &base_hash, &chunk).is_none());
+
+        db.set_base_chunk(&base_hash, &base).unwrap();
+        let delta = compute_chunk_delta(&db, &base_hash, &chunk).unwrap();
+        assert!(
+            delta.len() * 1000 < chunk.len(),
+            "delta size {}",
+            delta.len()
+        );
+        assert_eq!(
+            crv_core::repository::apply_delta(&base, &delta, chunk.len()).unwrap(),
+            chunk
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn failing_pre_submit_hook_rejects_submit() {
+        use crate::daemon_server::config::{RuntimeConfigItem, RuntimeConfigSource};
+        use crate::daemon_server::handlers::edge::stub_hive::{self, StubHive};
+        use crate::daemon_server::handlers::file::add;
+        use crate::pb::AddReq;
+        use crv_core::workspace::entity::WorkspaceConfig;
+        use std::os::unix::fs::PermissionsExt;
+
+        let db = TempDb::new();
+        let root = db.root();
+        let workspace_root = root.join("ws");
+        std::fs::create_dir_all(&workspace_root).unwrap();
+        std::fs::write(workspace_root.join("a.txt"), "a").unwrap();
+        std::fs::write(workspace_root.join("b.psd"), "b").unwrap();
+
+        // 钩子拒绝提交 .psd 文件
+        let hook = root.join("pre-submit.sh");
+        std::fs::write(
+            &hook,
+            "#!/bin/sh\n\
+             echo \"$CRV_BRANCH: $CRV_CHANGELIST_DESC\" > \"$(dirname \"$0\")/desc.txt\"\n\
+             case \"$CRV_FILES\" in\n\
+             *.psd*) echo 'psd files must go through the asset pipeline' >&2; exit 1 ;;\n\
+             esac\n",
+        )
+        .unwrap();
+        std::fs::set_permissions(&hook, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let mut state = db.state();
+        state.pre_submit_hook = Some(hook);
+        let workspace_root = format!("{}/", workspace_root.to_string_lossy());
+        let config =
+            WorkspaceConfig::from_specification("ws", &workspace_root, "//... //ws/").unwrap();
+        state
+            .db
+            .create_workspace_pending("ws".to_string(), config)
+            .unwrap();
+        state.db.confirm_workspace("ws".to_string()).unwrap();
+        add::handle(
+            state.clone(),
+            Request::new(AddReq {
+                workspace_name: "ws".to_string(),
+                paths: vec![workspace_root.clone()],
+            }),
+        )
+        .await
+        .unwrap();
+
+        let addr = stub_hive::spawn(StubHive::default()).await;
+        let request = |paths: Vec<String>| {
+            let mut runtime_config = RuntimeConfig::default();
+            runtime_config.remote_addr = RuntimeConfigItem {
+                value: addr.clone(),
+                source: RuntimeConfigSource::Override,
+            };
+            let mut req = Request::new(SubmitReq {
+                workspace_name: "ws".to_string(),
+                paths,
+                description: "add assets".to_string(),
+                dry_run: false,
+                branch_id: "release".to_string(),
+            });
+            req.extensions_mut().insert(runtime_config);
+            req.extensions_mut().insert(SessionContext {
+                username: "alice".to_string(),
+                token: String::new(),
+            });
+            req
+        };
+
+        let Err(e) = handle(state.clone(), request(vec![workspace_root.clone()])).await else {
+            panic!("submit should be rejected by the pre-submit hook");
+        };
+        assert!(
+            matches!(&e, AppError::PreSubmitHookFailed(msg) if msg == "psd files must go through the asset pipeline"),
+            "{e}"
+        );
+        assert_eq!(
+            std::fs::read_to_string(root.join("desc.txt")).unwrap(),
+            "release: add assets\n"
+        );
+
+        // 钩子放行后请求继续发往 hive，stub 不支持 launch_submit
+        let Err(e) = handle(state, request(vec![format!("{workspace_root}a.txt")])).await else {
+            panic!("stub hive does not support submit");
+        };
+        assert!(!matches!(e, AppError::PreSubmitHookFailed(_)), "{e}");
+    }
+}
@@ -1,37 +1,51 @@
 use crate::daemon_server::config::RuntimeConfig;
 use crate::daemon_server::db::active_file::Action;
 use crate::daemon_server::db::file::{FileLocation, FileMeta, FileRevision};
+use crate::daemon_server::db::hive_cache::CachedFileRevision;
 use crate::daemon_server::error::{AppError, AppResult};
 use crate::daemon_server::handlers::utils::{
-    expand_to_mapped_files_in_edge_meta, normalize_paths_strict,
+    expand_to_mapped_files_in_edge_meta, normalize_paths_strict, to_local_path_wildcard,
 };
+use crate::daemon_server::handlers::workspace::validate::verify_mappings;
 use crate::daemon_server::job::{
     Job, JobEvent, JobRetentionPolicy, JobStatus, MessageStoragePolicy, WorkerProtocol,
 };
-use crate::daemon_server::state::AppState;
+use crate::daemon_server::middleware::request_id;
+use crate::daemon_server::state::{AppState, HiveChannel};
 use crate::hive_pb::{
-    DownloadFileChunkReq, GetFileTreeReq, hive_service_client::HiveServiceClient,
+    DownloadFileChunkReq, GetChangelistAtTimeReq, GetFileTreeReq,
+    hive_service_client::HiveServiceClient,
 };
+use crate::metrics;
 use crate::pb::sync_progress::Payload::FileUpdate;
-use crate::pb::{SyncFileMetadata, SyncFileUpdate, SyncMetadata, SyncProgress, SyncReq};
+use crate::pb::{
+    SyncEventStatus, SyncFileUpdate, SyncProgress, SyncProgressEvent, SyncReq, SyncWithProgressReq,
+};
+use crv_core::logger::recovery;
+use crv_core::metadata::CompressionType;
 use crv_core::path::basic::DepotPath;
 use crv_core::path::engine::PathEngine;
+use crv_core::path::ignore::IgnoreMatcher;
 use prost::Message;
 use std::collections::{HashMap, HashSet};
 use std::ops::Sub;
+use std::path::{Path, PathBuf};
 use std::pin::Pin;
 use std::sync::{Arc, Weak};
 use std::task::{Context, Poll};
 use tokio::fs;
-use tokio::io::AsyncWriteExt;
-use tokio_stream::wrappers::BroadcastStream;
+use tokio::io::{AsyncWriteExt, BufWriter};
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::{BroadcastStream, ReceiverStream};
 use tokio_stream::{Stream, StreamExt};
-use tonic::transport::Channel;
 use tonic::{Request, Response, Status};
 
 pub type SyncProgressStream =
     Pin<Box<dyn Stream<Item = Result<SyncProgress, Status>> + Send + 'static>>;
 
+pub type SyncProgressEventStream =
+    Pin<Box<dyn Stream<Item = Result<SyncProgressEvent, Status>> + Send + 'static>>;
+
 struct JobCancelOnDropStream {
     stream: SyncProgressStream,
     job: Weak<Job>,
@@ -58,24 +72,126 @@ struct FileToSync {
     action: Action,
     // None only when action is Delete
     latest_revision: Option<FileRevision>,
+    changelist_id: i64,
+    size: i64,
+    chunk_hashes: Vec<String>,
+    /// 整个文件内容的哈希，为空时（旧版本的 revision）只校验各个 chunk
+    content_hash: String,
+    /// 下载 chunk 时请求的压缩方式，收到后解压再写入文件
+    compression_type: CompressionType,
+}
+
+/// 按实际收到的数据计算出的哈希
+struct ReceivedHashes {
     chunk_hashes: Vec<String>,
+    content_hash: String,
 }
 
 const FRAME_SIZE: usize = 64 * 1024; // 64KB，单个报文中的数据大小
 
-pub async fn handle(
-    state: AppState,
-    req: Request<SyncReq>,
-) -> AppResult<Response<SyncProgressStream>> {
-    let runtime_config = RuntimeConfig::from_req(&req)?;
-    let request_body = req.into_inner();
+/// SyncWithProgress 输出流的缓冲区大小，缓冲区满时丢弃 DOWNLOADING 事件
+const PROGRESS_EVENT_BUFFER: usize = 256;
 
-    let channel = state
-        .hive_channel
-        .get_channel(&runtime_config.remote_addr.value)?;
+/// 计算本次同步的目标 changelist，0 表示最新；指定了时间点时由 hive 解析为对应的 changelist
+async fn resolve_target_changelist(
+    hive_client: &mut HiveServiceClient<HiveChannel>,
+    request_body: &SyncReq,
+) -> AppResult<i64> {
+    if request_body.as_of_millis <= 0 {
+        return Ok(request_body.changelist_id);
+    }
+    if request_body.changelist_id > 0 {
+        return Err(AppError::Raw(Status::invalid_argument(
+            "changelist_id and as_of_millis cannot be specified at the same time.",
+        )));
+    }
+    let rsp = hive_client
+        .get_changelist_at_time(GetChangelistAtTimeReq {
+            branch_id: String::new(),
+            timestamp_millis: request_body.as_of_millis,
+        })
+        .await?
+        .into_inner();
+    Ok(rsp.changelist_id)
+}
 
-    let mut hive_client = HiveServiceClient::new(channel.clone());
+/// 从 hive 获取目标 changelist 的文件树，并用它刷新本地缓存
+async fn fetch_file_tree(
+    state: &AppState,
+    hive_client: &mut HiveServiceClient<HiveChannel>,
+    request_body: &SyncReq,
+) -> AppResult<Vec<CachedFileRevision>> {
+    // 构建 depot wildcard (暂时获取所有文件，后续可以优化为只获取需要的)
+    let depot_wildcard = "//...".to_string();
+    let changelist_id = resolve_target_changelist(hive_client, request_body).await?;
+
+    let file_tree_rsp = hive_client
+        .get_file_tree(GetFileTreeReq {
+            depot_wildcard,
+            changelist_id,
+            branch_id: String::new(),
+        })
+        .await?
+        .into_inner();
 
+    let files = file_tree_rsp
+        .file_revisions
+        .into_iter()
+        .map(|x| {
+            let compression_type =
+                CompressionType::parse(&x.compression_type).ok_or_else(|| {
+                    AppError::Internal(format!(
+                        "unsupported compression {} of {}",
+                        x.compression_type, x.path
+                    ))
+                })?;
+            Ok(CachedFileRevision {
+                depot_path: x.path,
+                generation: x.generation,
+                revision: x.revision,
+                changelist_id: x.changelist_id,
+                chunk_hashes: x.binary_id,
+                size: x.size,
+                content_hash: x.content_hash,
+                compression_type,
+            })
+        })
+        .collect::<AppResult<Vec<_>>>()?;
+    state.db.replace_hive_cache(&files)?;
+    Ok(files)
+}
+
+/// 获取 hive 上的文件树，返回的 bool 表示是否处于离线模式。
+///
+/// 指定离线或 hive 不可用时使用最近一次缓存的文件树，离线时不能指定同步的 changelist。
+async fn file_tree_or_cache(
+    state: &AppState,
+    hive_client: &mut HiveServiceClient<HiveChannel>,
+    request_body: &SyncReq,
+) -> AppResult<(Vec<CachedFileRevision>, bool)> {
+    let pinned = request_body.changelist_id > 0 || request_body.as_of_millis > 0;
+    if request_body.offline {
+        if pinned {
+            return Err(AppError::Raw(Status::invalid_argument(
+                "changelist_id and as_of_millis cannot be specified in offline mode.",
+            )));
+        }
+    } else {
+        match fetch_file_tree(state, hive_client, request_body).await {
+            // 缓存只保存了最近一次同步的文件树，同步到指定 changelist 时无法退化为离线模式
+            Err(AppError::Raw(status)) if status.code() == tonic::Code::Unavailable && !pinned => {}
+            result => return result.map(|files| (files, false)),
+        }
+    }
+    Ok((state.db.get_hive_cache()?, true))
+}
+
+/// 对比 edge 与 hive 上的文件，计算需要同步的文件列表，返回的 bool 表示是否处于离线模式
+async fn plan_sync(
+    state: &AppState,
+    hive_client: &mut HiveServiceClient<HiveChannel>,
+    request_body: &SyncReq,
+) -> AppResult<(Vec<FileToSync>, bool)> {
     // 1. 获取 workspace 信息
     let workspace_meta = state
         .db
@@ -85,6 +201,9 @@ pub async fn handle(
             request_body.workspace_name
         ))))?;
 
+    // 映射存在冲突时不进行任何同步
+    verify_mappings(&request_body.workspace_name, &workspace_meta.config)?;
+
     let path_engine = PathEngine::new(workspace_meta.config.clone(), &request_body.workspace_name);
 
     // 2. 规范化路径
@@ -95,16 +214,7 @@ pub async fn handle(
         expand_to_mapped_files_in_edge_meta(&local_paths, &path_engine, state.clone())?;
 
     // 4. 获取 hive files
-    // 构建 depot wildcard (暂时获取所有文件，后续可以优化为只获取需要的)
-    let depot_wildcard = "//...".to_string();
-
-    let file_tree_rsp = hive_client
-        .get_file_tree(GetFileTreeReq {
-            depot_wildcard,
-            changelist_id: 0,
-        })
-        .await?
-        .into_inner();
+    let (hive_files, offline) = file_tree_or_cache(state, hive_client, request_body).await?;
 
     // 5. 构建 FileToSync 列表，此时无法保证文件是未 checkout 的状态
     let mut file_to_sync = vec![];
@@ -113,21 +223,23 @@ pub async fn handle(
         .map(|x| (x.depot_path.to_custom_string(), x))
         .collect::<HashMap<_, _>>();
 
-    // 这个过程获取到的文件不一定都在参数指定的文件范围（local_paths）内，比如排除文件没办法静态计算
-    let hive_files_map = file_tree_rsp
-        .file_revisions
+    // 这个过程获取到的文件不一定都在参数指定的文件范围（local_paths）内，比如排除文件没办法静态计算，
+    // 因此映射到本地路径后还需要按范围过滤
+    let sync_range = local_paths
+        .iter()
+        .filter_map(|x| to_local_path_wildcard(x, &path_engine))
+        .collect::<Vec<_>>();
+    let hive_files_map = hive_files
         .iter()
         .filter_map(|x| {
             if x.generation == 0 && x.revision == 0 {
                 None
             } else {
-                Some((x.path.clone(), x))
+                Some((x.depot_path.clone(), x))
             }
         })
         .collect::<HashMap<_, _>>();
 
-    // todo 因此，这里需要过滤一下
-
     let edge_files_set = edge_files_map.keys().cloned().collect::<HashSet<_>>();
     let hive_files_set = hive_files_map.keys().cloned().collect::<HashSet<_>>();
 
@@ -152,15 +264,22 @@ pub async fn handle(
         files_to_edit.insert(k.clone());
     }
 
-    // 从 hive file map 中获取元数据
+    // 从 hive file map 中获取元数据，映射到的本地路径被 .crvignore 排除时不写入
+    let mut ignore_matcher = IgnoreMatcher::new();
     for file in files_to_add.iter().chain(files_to_edit.iter()) {
         let file_meta = hive_files_map.get(file).unwrap();
-        let depot_path = DepotPath::parse(&file_meta.path).unwrap();
+        let depot_path = DepotPath::parse(&file_meta.depot_path).unwrap();
         let local_path = path_engine.mapping_depot_path(&depot_path);
         if local_path.is_none() {
             continue;
         }
         let local_path = local_path.unwrap();
+        if !sync_range.iter().any(|x| x.match_path(&local_path)) {
+            continue;
+        }
+        if ignore_matcher.is_ignored(&local_path) {
+            continue;
+        }
         let workspace_path = path_engine
             .local_path_to_workspace_path(&local_path)
             .unwrap();
@@ -179,7 +298,11 @@ pub async fn handle(
                     generation: file_meta.generation,
                     revision: file_meta.revision,
                 }),
-                chunk_hashes: file_meta.binary_id.clone(),
+                changelist_id: file_meta.changelist_id,
+                size: file_meta.size,
+                chunk_hashes: file_meta.chunk_hashes.clone(),
+                content_hash: file_meta.content_hash.clone(),
+                compression_type: file_meta.compression_type,
             });
         } else {
             file_to_sync.push(FileToSync {
@@ -189,7 +312,11 @@ pub async fn handle(
                     generation: file_meta.generation,
                     revision: file_meta.revision,
                 }),
-                chunk_hashes: file_meta.binary_id.clone(),
+                changelist_id: file_meta.changelist_id,
+                size: file_meta.size,
+                chunk_hashes: file_meta.chunk_hashes.clone(),
+                content_hash: file_meta.content_hash.clone(),
+                compression_type: file_meta.compression_type,
             });
         }
     }
@@ -202,10 +329,31 @@ pub async fn handle(
             location: (*location).clone(),
             action: Action::Delete,
             latest_revision: None,
+            changelist_id: 0,
+            size: 0,
             chunk_hashes: vec![],
+            content_hash: String::new(),
+            compression_type: CompressionType::None,
         });
     }
 
+    Ok((file_to_sync, offline))
+}
+
+pub async fn handle(
+    state: AppState,
+    req: Request<SyncReq>,
+) -> AppResult<Response<SyncProgressStream>> {
+    let runtime_config = RuntimeConfig::from_req(&req)?;
+    let request_body = req.into_inner();
+
+    let channel = state
+        .hive_channel
+        .get_channel(&runtime_config.remote_addr.value)?;
+
+    let mut hive_client = HiveServiceClient::new(channel.clone());
+    let (file_to_sync, offline) = plan_sync(&state, &mut hive_client, &request_body).await?;
+
     // 7. 创建 Job
     let job = state.job_manager.create_job(
         None,
@@ -219,7 +367,9 @@ pub async fn handle(
 
     // 8. 添加 Worker
     let job_ref = job.clone();
-    job.add_worker(async move { sync_file(state_clone, file_to_sync, channel, job_ref).await });
+    job.add_worker(
+        async move { sync_file(state_clone, file_to_sync, channel, offline, job_ref).await },
+    );
 
     job.clone().start();
 
@@ -245,21 +395,280 @@ pub async fn handle(
     Ok(Response::new(Box::pin(wrapped_stream) as SyncProgressStream))
 }
 
+/// 按顺序下载文件的所有 chunk 写入 `dest`，每收到一个报文以累计的字节数调用 `on_progress`，
+/// 返回按实际收到的数据计算出的每个 chunk 与整个文件的 hash。
+///
+/// 内存中最多只保留一个 chunk：收齐后先按 revision 的压缩方式解压并校验其 hash，
+/// 一致时才追加到文件中，不一致时立即失败，不会把错误的内容写入文件。
+async fn download_file(
+    hive_client: &mut HiveServiceClient<HiveChannel>,
+    channel: &HiveChannel,
+    file: &FileToSync,
+    dest: &Path,
+    write_buffer_size: usize,
+    mut on_progress: impl FnMut(i64),
+) -> Result<ReceivedHashes, String> {
+    let file_fs = fs::File::create(dest).await.map_err(|x| format!("{x}"))?;
+    let mut writer = BufWriter::with_capacity(write_buffer_size, file_fs);
+
+    let mut bytes_completed_so_far = 0;
+    let mut received_hashes = Vec::with_capacity(file.chunk_hashes.len());
+    let mut content_hasher = blake3::Hasher::new();
+    for chunk_hash in &file.chunk_hashes {
+        let download_file_chunk_req = DownloadFileChunkReq {
+            chunk_hashes: vec![chunk_hash.clone()],
+            packet_size: FRAME_SIZE as i64,
+            compression: file.compression_type.as_str().to_string(),
+        };
+        let mut download_file_chunk_rsp_stream = hive_client
+            .download_file_chunk(download_file_chunk_req)
+            .await
+            .map_err(|x| format!("{x}"))?
+            .into_inner();
+
+        let mut chunk = Vec::new();
+        while let Some(rsp) = channel
+            .next_message(&mut download_file_chunk_rsp_stream)
+            .await
+            .map_err(|x| format!("{x}"))?
+        {
+            if CompressionType::parse(&rsp.compression) != Some(file.compression_type) {
+                return Err(format!("unexpected chunk compression {}", rsp.compression));
+            }
+            chunk.extend_from_slice(&rsp.content);
+            metrics::collector().add_downloaded_bytes(rsp.content.len());
+            // 压缩传输时进度在解压后按原始大小计算
+            if file.compression_type == CompressionType::None {
+                bytes_completed_so_far += rsp.content.len();
+                on_progress(bytes_completed_so_far as i64);
+            }
+        }
+        if file.compression_type != CompressionType::None {
+            chunk = file
+                .compression_type
+                .decompress(&chunk)
+                .map_err(|x| format!("decompress chunk {chunk_hash} failed: {x}"))?;
+            bytes_completed_so_far += chunk.len();
+            on_progress(bytes_completed_so_far as i64);
+        }
+
+        let received_hash = hex::encode(blake3::hash(&chunk).as_bytes());
+        if received_hash != *chunk_hash {
+            return Err(format!(
+                "chunk hash mismatch: expected {chunk_hash}, got {received_hash}"
+            ));
+        }
+        writer.write_all(&chunk).await.map_err(|x| format!("{x}"))?;
+        content_hasher.update(&chunk);
+        received_hashes.push(received_hash);
+    }
+    writer.flush().await.map_err(|x| format!("{x}"))?;
+
+    Ok(ReceivedHashes {
+        chunk_hashes: received_hashes,
+        content_hash: hex::encode(content_hasher.finalize().as_bytes()),
+    })
+}
+
+/// 离线时无法从 hive 下载，只能用签出时保存在本地的 base chunk 还原文件内容
+async fn restore_from_base_chunks(
+    app_state: &AppState,
+    file: &FileToSync,
+    dest: &Path,
+    mut on_progress: impl FnMut(i64),
+) -> Result<ReceivedHashes, String> {
+    // 先确认所有 chunk 都在本地，避免覆盖已有的文件后才发现内容不完整
+    let mut chunks = Vec::with_capacity(file.chunk_hashes.len());
+    for chunk_hash in &file.chunk_hashes {
+        let chunk = app_state
+            .db
+            .get_base_chunk(chunk_hash)
+            .map_err(|x| format!("{x}"))?
+            .ok_or_else(|| {
+                format!(
+                    "content of {} is not available offline",
+                    file.location.workspace_path.to_custom_string()
+                )
+            })?;
+        chunks.push(chunk);
+    }
+
+    let mut file_fs = fs::File::create(dest).await.map_err(|x| format!("{x}"))?;
+    let mut bytes_completed_so_far = 0;
+    let mut received_hashes = Vec::with_capacity(chunks.len());
+    let mut content_hasher = blake3::Hasher::new();
+    for chunk in &chunks {
+        file_fs.write_all(chunk).await.map_err(|x| format!("{x}"))?;
+        received_hashes.push(hex::encode(blake3::hash(chunk).as_bytes()));
+        content_hasher.update(chunk);
+        bytes_completed_so_far += chunk.len();
+        on_progress(bytes_completed_so_far as i64);
+    }
+    file_fs.flush().await.map_err(|x| format!("{x}"))?;
+
+    Ok(ReceivedHashes {
+        chunk_hashes: received_hashes,
+        content_hash: hex::encode(content_hasher.finalize().as_bytes()),
+    })
+}
+
+/// 获取文件内容写入本地的临时文件，在线时从 hive 下载，离线时从本地的 base chunk 还原
+///
+/// 开始前在恢复日志中记录，成功后需调用 [`commit_file_content`] 替换本地文件，失败时临时文件已被删除。
+async fn fetch_file_content(
+    app_state: &AppState,
+    hive_client: &mut HiveServiceClient<HiveChannel>,
+    channel: &HiveChannel,
+    file: &FileToSync,
+    offline: bool,
+    on_progress: impl FnMut(i64),
+) -> Result<ReceivedHashes, String> {
+    // 限制同时写入的文件数量，在记录恢复日志之前等待
+    let _permit = app_state
+        .sync_open_files
+        .acquire()
+        .await
+        .map_err(|x| format!("{x}"))?;
+    let target = PathBuf::from(file.location.local_path.to_local_path_string());
+    // 文件可能位于本地还不存在的子目录中
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent)
+            .await
+            .map_err(|x| format!("{x}"))?;
+    }
+    app_state
+        .recovery_log
+        .begin(&file.location.workspace_path.to_custom_string(), &target)
+        .map_err(|x| format!("{x}"))?;
+
+    let partial = recovery::partial_path(&target);
+    let result = if offline {
+        restore_from_base_chunks(app_state, file, &partial, on_progress).await
+    } else {
+        download_file(
+            hive_client,
+            channel,
+            file,
+            &partial,
+            app_state.sync_write_buffer_size,
+            on_progress,
+        )
+        .await
+    };
+    if result.is_err() {
+        abort_file_content(app_state, file).await;
+    }
+    result
+}
+
+/// 用下载完成的临时文件替换本地文件
+async fn commit_file_content(app_state: &AppState, file: &FileToSync) -> Result<(), String> {
+    let target = PathBuf::from(file.location.local_path.to_local_path_string());
+    if let Err(e) = fs::rename(recovery::partial_path(&target), &target).await {
+        abort_file_content(app_state, file).await;
+        return Err(format!("{e}"));
+    }
+    app_state
+        .recovery_log
+        .commit(&file.location.workspace_path.to_custom_string())
+        .map_err(|x| format!("{x}"))
+}
+
+/// 放弃下载的内容，本地文件保持不变
+async fn abort_file_content(app_state: &AppState, file: &FileToSync) {
+    let target = PathBuf::from(file.location.local_path.to_local_path_string());
+    let _ = fs::remove_file(recovery::partial_path(&target)).await;
+    // 记录失败时临时文件已经删除，下次启动恢复时视为已完成，不影响本地文件
+    let _ = app_state
+        .recovery_log
+        .abort(&file.location.workspace_path.to_custom_string());
+}
+
+/// 校验下载到的 chunk、拼接后的文件内容与文件的大小是否与 hive 记录的一致
+fn verify_download(
+    file: &FileToSync,
+    received: &ReceivedHashes,
+    bytes_downloaded: i64,
+) -> Result<(), String> {
+    let received_hashes = &received.chunk_hashes;
+    if let Some((expected, received)) = file
+        .chunk_hashes
+        .iter()
+        .zip(received_hashes)
+        .find(|(expected, received)| expected != received)
+    {
+        return Err(format!(
+            "chunk hash mismatch: expected {expected}, got {received}"
+        ));
+    }
+    if received_hashes.len() != file.chunk_hashes.len() {
+        return Err(format!(
+            "expected {} chunks, got {}",
+            file.chunk_hashes.len(),
+            received_hashes.len()
+        ));
+    }
+    if bytes_downloaded != file.size {
+        return Err(format!(
+            "size mismatch: expected {} bytes, got {bytes_downloaded}",
+            file.size
+        ));
+    }
+    if !file.content_hash.is_empty() && file.content_hash != received.content_hash {
+        return Err(format!(
+            "content hash mismatch: expected {}, got {}",
+            file.content_hash, received.content_hash
+        ));
+    }
+    Ok(())
+}
+
+/// 下载完成后记录文件的元数据
+fn save_file_meta(app_state: &AppState, file: FileToSync) -> Result<(), String> {
+    let file_meta = FileMeta {
+        location: file.location,
+        current_revision: file.latest_revision.unwrap(),
+        changelist_id: file.changelist_id,
+        size: file.size,
+        chunk_hashes: file.chunk_hashes,
+    };
+    app_state
+        .db
+        .set_file_meta(file_meta.location.workspace_path.clone(), file_meta)
+        .map_err(|x| format!("{x}"))
+}
+
+/// 删除本地文件及其元数据
+async fn delete_local_file(app_state: &AppState, file: &FileToSync) -> Result<(), String> {
+    app_state
+        .db
+        .delete_file(&file.location.workspace_path)
+        .map_err(|x| format!("{x}"))?;
+    fs::remove_file(file.location.local_path.to_local_path_string())
+        .await
+        .map_err(|x| format!("{x}"))
+}
+
+/// 本地已 checkout 的文件不会被拉新
+fn is_checked_out(app_state: &AppState, file: &FileToSync) -> Result<bool, String> {
+    Ok(app_state
+        .db
+        .get_active_file_action(&file.location.workspace_path)
+        .map_err(|x| format!("{x}"))?
+        .is_some())
+}
+
 async fn sync_file(
     app_state: AppState,
     files_to_sync: Vec<FileToSync>,
-    channel: Channel,
+    channel: HiveChannel,
+    offline: bool,
     job: Arc<Job>,
 ) -> Result<(), String> {
     let mut hive_client = HiveServiceClient::new(channel.clone());
     for file in files_to_sync {
         // 对于本地 checkout 的文件，跳过该文件的拉新。
-        if app_state
-            .db
-            .get_active_file_action(&file.location.workspace_path)
-            .map_err(|x| format!("{x}"))?
-            .is_some()
-        {
+        if is_checked_out(&app_state, &file)? {
             println!(
                 "Already checkout file {}, skip sync.",
                 file.location.workspace_path.to_custom_string()
@@ -268,59 +677,31 @@ async fn sync_file(
         }
 
         match file.action {
-            Action::Add | Action::Edit => {
-                let mut file_fs = fs::File::create(file.location.local_path.to_local_path_string())
-                    .await
-                    .map_err(|x| format!("{x}"))?;
-
-                let mut bytes_completed_so_far = 0;
-
-                for chunk_hash in file.chunk_hashes {
-                    let download_file_chunk_req = DownloadFileChunkReq {
-                        chunk_hashes: vec![chunk_hash],
-                        packet_size: FRAME_SIZE as i64,
-                    };
-                    let mut download_file_chunk_rsp_stream = hive_client
-                        .download_file_chunk(download_file_chunk_req)
-                        .await
-                        .map_err(|x| format!("{x}"))?
-                        .into_inner();
-
-                    while let Some(rsp) = download_file_chunk_rsp_stream
-                        .message()
-                        .await
-                        .map_err(|x| format!("{x}"))?
-                    {
-                        assert_eq!(rsp.compression, "none");
-                        file_fs.write_all(&rsp.content).await.unwrap();
-                        bytes_completed_so_far += rsp.content.len();
+            Action::Add | Action::Edit | Action::MoveAdd(_) => {
+                let path = file.location.workspace_path.to_custom_string();
+                fetch_file_content(
+                    &app_state,
+                    &mut hive_client,
+                    &channel,
+                    &file,
+                    offline,
+                    |bytes_completed_so_far| {
                         job.report_payload(SyncProgress {
                             payload: Some(FileUpdate(SyncFileUpdate {
-                                path: file.location.workspace_path.to_custom_string(),
-                                bytes_completed_so_far: bytes_completed_so_far as i64,
+                                path: path.clone(),
+                                bytes_completed_so_far,
                                 info: "".to_string(),
                                 warning: "".to_string(),
                             })),
                         })
-                    }
-                }
-                let file_meta = FileMeta {
-                    location: file.location,
-                    current_revision: file.latest_revision.unwrap(),
-                };
-                app_state
-                    .db
-                    .set_file_meta(file_meta.location.workspace_path.clone(), file_meta)
-                    .map_err(|x| format!("{x}"))?;
+                    },
+                )
+                .await?;
+                commit_file_content(&app_state, &file).await?;
+                save_file_meta(&app_state, file)?;
             }
             Action::Delete => {
-                app_state
-                    .db
-                    .delete_file(&file.location.workspace_path)
-                    .map_err(|x| format!("{x}"))?;
-                fs::remove_file(file.location.local_path.to_local_path_string())
-                    .await
-                    .map_err(|x| format!("{x}"))?;
+                delete_local_file(&app_state, &file).await?;
                 job.report_payload(SyncProgress {
                     payload: Some(FileUpdate(SyncFileUpdate {
                         path: file.location.workspace_path.to_custom_string(),
@@ -335,3 +716,594 @@ async fn sync_file(
 
     Ok(())
 }
+
+fn progress_event(
+    file: &FileToSync,
+    bytes_downloaded: i64,
+    status: SyncEventStatus,
+    error: String,
+) -> SyncProgressEvent {
+    SyncProgressEvent {
+        file_path: file.location.workspace_path.to_custom_string(),
+        bytes_downloaded,
+        bytes_total: file.size,
+        status: status.into(),
+        error,
+    }
+}
+
+/// 与 `handle` 相同的同步，但为每个文件报告状态，单个文件失败时报告 FAILED 并继续同步其余文件
+pub async fn handle_with_progress(
+    state: AppState,
+    req: Request<SyncWithProgressReq>,
+) -> AppResult<Response<SyncProgressEventStream>> {
+    let runtime_config = RuntimeConfig::from_req(&req)?;
+    let request_body = req.into_inner();
+    let request_body = SyncReq {
+        workspace_name: request_body.workspace_name,
+        paths: request_body.paths,
+        force: request_body.force,
+        changelist_id: request_body.changelist_id,
+        as_of_millis: request_body.as_of_millis,
+        offline: request_body.offline,
+    };
+
+    let channel = state
+        .hive_channel
+        .get_channel(&runtime_config.remote_addr.value)?;
+
+    let mut hive_client = HiveServiceClient::new(channel.clone());
+    let (file_to_sync, offline) = plan_sync(&state, &mut hive_client, &request_body).await?;
+
+    // 开始时会为每个文件发送 PENDING，这里用有界 channel 而不是 Job 的广播，
+    // 客户端读取较慢时反压同步过程，而不是丢失事件；客户端断开后 send 失败，同步随之停止
+    let (tx, rx) = mpsc::channel(PROGRESS_EVENT_BUFFER);
+    tokio::spawn(request_id::inherit(sync_file_with_progress(
+        state,
+        file_to_sync,
+        channel,
+        offline,
+        tx,
+    )));
+
+    Ok(Response::new(
+        Box::pin(ReceiverStream::new(rx)) as SyncProgressEventStream
+    ))
+}
+
+async fn sync_file_with_progress(
+    app_state: AppState,
+    files_to_sync: Vec<FileToSync>,
+    channel: HiveChannel,
+    offline: bool,
+    tx: mpsc::Sender<Result<SyncProgressEvent, Status>>,
+) {
+    for file in &files_to_sync {
+        let event = progress_event(file, 0, SyncEventStatus::Pending, String::new());
+        if tx.send(Ok(event)).await.is_err() {
+            return;
+        }
+    }
+
+    let mut hive_client = HiveServiceClient::new(channel.clone());
+    for file in files_to_sync {
+        let done_event = progress_event(&file, 0, SyncEventStatus::Done, String::new());
+        let event = match sync_one_file_with_progress(
+            &app_state,
+            &mut hive_client,
+            &channel,
+            offline,
+            file,
+            &tx,
+        )
+        .await
+        {
+            Ok(bytes_downloaded) => SyncProgressEvent {
+                bytes_downloaded,
+                ..done_event
+            },
+            Err(error) => SyncProgressEvent {
+                status: SyncEventStatus::Failed.into(),
+                error,
+                ..done_event
+            },
+        };
+        if tx.send(Ok(event)).await.is_err() {
+            return;
+        }
+    }
+}
+
+/// 同步单个文件，返回下载的字节数
+async fn sync_one_file_with_progress(
+    app_state: &AppState,
+    hive_client: &mut HiveServiceClient<HiveChannel>,
+    channel: &HiveChannel,
+    offline: bool,
+    file: FileToSync,
+    tx: &mpsc::Sender<Result<SyncProgressEvent, Status>>,
+) -> Result<i64, String> {
+    if is_checked_out(app_state, &file)? {
+        return Err("file is checked out, skipped".to_string());
+    }
+
+    match file.action {
+        Action::Add | Action::Edit | Action::MoveAdd(_) => {
+            let event = progress_event(&file, 0, SyncEventStatus::Downloading, String::new());
+            tx.send(Ok(event)).await.map_err(|x| format!("{x}"))?;
+
+            let mut bytes_downloaded = 0;
+            let on_progress = |bytes| {
+                bytes_downloaded = bytes;
+                // 中间进度允许丢弃，避免下载被慢速的客户端拖慢
+                let _ = tx.try_send(Ok(progress_event(
+                    &file,
+                    bytes,
+                    SyncEventStatus::Downloading,
+                    String::new(),
+                )));
+            };
+            let received_hashes =
+                fetch_file_content(app_state, hive_client, channel, &file, offline, on_progress)
+                    .await?;
+
+            let event = progress_event(
+                &file,
+                bytes_downloaded,
+                SyncEventStatus::Verifying,
+                String::new(),
+            );
+            if let Err(e) = tx.send(Ok(event)).await {
+                abort_file_content(app_state, &file).await;
+                return Err(format!("{e}"));
+            }
+            if let Err(e) = verify_download(&file, &received_hashes, bytes_downloaded) {
+                abort_file_content(app_state, &file).await;
+                return Err(e);
+            }
+
+            commit_file_content(app_state, &file).await?;
+            save_file_meta(app_state, file)?;
+            Ok(bytes_downloaded)
+        }
+        Action::Delete => {
+            delete_local_file(app_state, &file).await?;
+            Ok(0)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::daemon_server::config::{RuntimeConfigItem, RuntimeConfigSource};
+    use crate::daemon_server::handlers::edge::stub_hive::{self, StubHive};
+    use crate::pb::SyncEventStatus;
+    use crate::test_support::TempDb;
+    use crv_core::path::basic::{LocalPath, WorkspacePath};
+    use crv_core::workspace::entity::WorkspaceConfig;
+    use std::path::{Path, PathBuf};
+
+    fn file(chunk_hashes: &[&str], size: i64, content_hash: &str) -> FileToSync {
+        FileToSync {
+            location: FileLocation {
+                local_path: LocalPath::parse("/root/ws/a.txt").unwrap(),
+                workspace_path: WorkspacePath::parse("//ws/a.txt").unwrap(),
+                depot_path: DepotPath::parse("//a.txt").unwrap(),
+            },
+            action: Action::Add,
+            latest_revision: Some(FileRevision {
+                generation: 1,
+                revision: 1,
+            }),
+            changelist_id: 1,
+            size,
+            chunk_hashes: chunk_hashes.iter().map(|h| h.to_string()).collect(),
+            content_hash: content_hash.to_string(),
+            compression_type: CompressionType::None,
+        }
+    }
+
+    fn hashes(hashes: &[&str], content_hash: &str) -> ReceivedHashes {
+        ReceivedHashes {
+            chunk_hashes: hashes.iter().map(|h| h.to_string()).collect(),
+            content_hash: content_hash.to_string(),
+        }
+    }
+
+    #[test]
+    fn verify_download_accepts_matching_chunks() {
+        let received = hashes(&["aa", "bb"], "ff");
+        assert!(verify_download(&file(&["aa", "bb"], 10, "ff"), &received, 10).is_ok());
+        // 旧版本的 revision 没有记录内容哈希
+        assert!(verify_download(&file(&["aa", "bb"], 10, ""), &received, 10).is_ok());
+    }
+
+    #[test]
+    fn verify_download_rejects_mismatched_chunks_or_size() {
+        let file = file(&["aa", "bb"], 10, "ff");
+        assert!(
+            verify_download(&file, &hashes(&["aa", "cc"], "ff"), 10)
+                .unwrap_err()
+                .contains("chunk hash mismatch")
+        );
+        assert!(verify_download(&file, &hashes(&["aa"], "ff"), 10).is_err());
+        assert!(
+            verify_download(&file, &hashes(&["aa", "bb"], "ff"), 9)
+                .unwrap_err()
+                .contains("size mismatch")
+        );
+        assert!(
+            verify_download(&file, &hashes(&["aa", "bb"], "ee"), 10)
+                .unwrap_err()
+                .contains("content hash mismatch")
+        );
+    }
+
+    /// 创建一个映射整个 depot 的 workspace，返回其根目录
+    fn create_workspace(state: &AppState, root: &Path, name: &str) -> String {
+        let workspace_root = root.join(name);
+        std::fs::create_dir_all(&workspace_root).unwrap();
+        let workspace_root = format!("{}/", workspace_root.to_string_lossy());
+        let config =
+            WorkspaceConfig::from_specification(name, &workspace_root, &format!("//... //{name}/"))
+                .unwrap();
+        state
+            .db
+            .create_workspace_pending(name.to_string(), config)
+            .unwrap();
+        state.db.confirm_workspace(name.to_string()).unwrap();
+        workspace_root
+    }
+
+    async fn sync(
+        state: &AppState,
+        remote_addr: &str,
+        workspace_name: &str,
+        workspace_root: &str,
+        offline: bool,
+    ) -> AppResult<Vec<SyncProgressEvent>> {
+        let req = SyncWithProgressReq {
+            workspace_name: workspace_name.to_string(),
+            paths: vec![workspace_root.to_string()],
+            offline,
+            ..Default::default()
+        };
+        sync_with(state, remote_addr, req).await
+    }
+
+    async fn sync_with(
+        state: &AppState,
+        remote_addr: &str,
+        req: SyncWithProgressReq,
+    ) -> AppResult<Vec<SyncProgressEvent>> {
+        let mut runtime_config = RuntimeConfig::default();
+        runtime_config.remote_addr = RuntimeConfigItem {
+            value: remote_addr.to_string(),
+            source: RuntimeConfigSource::Override,
+        };
+        let mut req = Request::new(req);
+        req.extensions_mut().insert(runtime_config);
+        let stream = handle_with_progress(state.clone(), req).await?.into_inner();
+        Ok(stream.map(|event| event.unwrap()).collect().await)
+    }
+
+    fn final_status(events: &[SyncProgressEvent], file_path: &str) -> (SyncEventStatus, String) {
+        let event = events
+            .iter()
+            .rev()
+            .find(|event| event.file_path == file_path)
+            .unwrap();
+        (event.status(), event.error.clone())
+    }
+
+    #[tokio::test]
+    async fn sync_falls_back_to_cached_file_tree_when_hive_is_down() {
+        let db = TempDb::new();
+        let root = db.root();
+        let state = db.state();
+        let ws_root = create_workspace(&state, root, "ws");
+        let (addr, hive) = stub_hive::spawn_killable(StubHive {
+            files: vec![
+                ("//a.txt".to_string(), b"hello".to_vec()),
+                ("//b.txt".to_string(), b"world".to_vec()),
+            ],
+            ..Default::default()
+        })
+        .await;
+
+        let events = sync(&state, &addr, "ws", &ws_root, false).await.unwrap();
+        assert_eq!(final_status(&events, "//ws/a.txt").0, SyncEventStatus::Done);
+        assert_eq!(final_status(&events, "//ws/b.txt").0, SyncEventStatus::Done);
+        let mut cached = db
+            .get_hive_cache()
+            .unwrap()
+            .into_iter()
+            .map(|x| x.depot_path)
+            .collect::<Vec<_>>();
+        cached.sort();
+        assert_eq!(cached, vec!["//a.txt", "//b.txt"]);
+
+        // 签出 a.txt 时会在本地保存其 base chunk，b.txt 的内容则只在 hive 上
+        db.set_base_chunk(&hex::encode(blake3::hash(b"hello").as_bytes()), b"hello")
+            .unwrap();
+        hive.abort();
+        // 新的 AppState 不复用之前建立的连接
+        let state = db.state();
+        let dead_addr = addr.as_str();
+
+        // 指定离线时不能同步到特定的 changelist
+        let req = SyncWithProgressReq {
+            workspace_name: "ws".to_string(),
+            paths: vec![ws_root.clone()],
+            changelist_id: 1,
+            offline: true,
+            ..Default::default()
+        };
+        assert!(sync_with(&state, dead_addr, req).await.is_err());
+
+        for (name, offline) in [("forced", true), ("fallback", false)] {
+            let ws_root = create_workspace(&state, root, name);
+            let events = sync(&state, dead_addr, name, &ws_root, offline)
+                .await
+                .unwrap();
+
+            let a_path = format!("//{name}/a.txt");
+            assert_eq!(final_status(&events, &a_path).0, SyncEventStatus::Done);
+            assert_eq!(
+                std::fs::read_to_string(format!("{ws_root}a.txt")).unwrap(),
+                "hello"
+            );
+            let meta = db
+                .get_file_meta(&WorkspacePath::parse(&a_path).unwrap())
+                .unwrap()
+                .unwrap();
+            assert_eq!(meta.current_revision.revision, 1);
+
+            let (status, error) = final_status(&events, &format!("//{name}/b.txt"));
+            assert_eq!(status, SyncEventStatus::Failed);
+            assert!(error.contains("not available offline"));
+        }
+
+        // 离线同步不会改动缓存的文件树
+        assert_eq!(db.get_hive_cache().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn sync_to_changelist_materializes_its_file_state() {
+        let db = TempDb::new();
+        let root = db.root();
+        let state = db.state();
+        let ws_root = create_workspace(&state, root, "ws");
+        let content = |text: &str| Some(text.as_bytes().to_vec());
+        let addr = stub_hive::spawn(StubHive {
+            changelists: vec![
+                vec![
+                    ("//a.txt".to_string(), content("a1")),
+                    ("//b.txt".to_string(), content("b1")),
+                ],
+                vec![
+                    ("//a.txt".to_string(), content("a2")),
+                    ("//c.txt".to_string(), content("c2")),
+                ],
+                vec![
+                    ("//a.txt".to_string(), content("a3")),
+                    ("//b.txt".to_string(), None),
+                    ("//d.txt".to_string(), content("d3")),
+                ],
+            ],
+            ..Default::default()
+        })
+        .await;
+        let sync_at = |changelist_id| SyncWithProgressReq {
+            workspace_name: "ws".to_string(),
+            paths: vec![ws_root.clone()],
+            changelist_id,
+            ..Default::default()
+        };
+        let read = |name: &str| std::fs::read_to_string(format!("{ws_root}{name}")).ok();
+        // 本地记录的 (revision, changelist)
+        let synced = |name: &str| {
+            db.get_file_meta(&WorkspacePath::parse(&format!("//ws/{name}")).unwrap())
+                .unwrap()
+                .map(|meta| (meta.current_revision.revision, meta.changelist_id))
+        };
+
+        sync_with(&state, &addr, sync_at(2)).await.unwrap();
+        assert_eq!(read("a.txt").as_deref(), Some("a2"));
+        assert_eq!(read("b.txt").as_deref(), Some("b1"));
+        assert_eq!(read("c.txt").as_deref(), Some("c2"));
+        assert_eq!(read("d.txt"), None);
+        assert_eq!(synced("a.txt"), Some((2, 2)));
+        assert_eq!(synced("b.txt"), Some((1, 1)));
+        assert_eq!(synced("c.txt"), Some((1, 2)));
+
+        sync_with(&state, &addr, sync_at(0)).await.unwrap();
+        assert_eq!(read("a.txt").as_deref(), Some("a3"));
+        assert_eq!(read("b.txt"), None);
+        assert_eq!(read("d.txt").as_deref(), Some("d3"));
+        assert_eq!(synced("b.txt"), None);
+
+        // 回到 changelist 2 时已签出的文件不会被覆盖，并在进度中提示
+        db.set_active_file_action(WorkspacePath::parse("//ws/a.txt").unwrap(), Action::Edit)
+            .unwrap();
+        let events = sync_with(&state, &addr, sync_at(2)).await.unwrap();
+        let (status, error) = final_status(&events, "//ws/a.txt");
+        assert_eq!(status, SyncEventStatus::Failed);
+        assert!(error.contains("checked out"), "{error}");
+        assert_eq!(read("a.txt").as_deref(), Some("a3"));
+        assert_eq!(synced("a.txt"), Some((3, 3)));
+        assert_eq!(read("b.txt").as_deref(), Some("b1"));
+        assert_eq!(read("d.txt"), None);
+    }
+
+    #[tokio::test]
+    async fn sync_only_touches_files_in_requested_paths() {
+        let db = TempDb::new();
+        let root = db.root();
+        let state = db.state();
+        let ws_root = create_workspace(&state, root, "ws");
+        let addr = stub_hive::spawn(StubHive {
+            files: vec![
+                ("//a.txt".to_string(), b"a".to_vec()),
+                ("//src/b.rs".to_string(), b"b".to_vec()),
+                ("//src/nested/c.rs".to_string(), b"c".to_vec()),
+                ("//src2/d.rs".to_string(), b"d".to_vec()),
+            ],
+            ..Default::default()
+        })
+        .await;
+        let sync_paths = |paths: &[&str]| SyncWithProgressReq {
+            workspace_name: "ws".to_string(),
+            paths: paths.iter().map(|x| x.to_string()).collect(),
+            ..Default::default()
+        };
+        let exists = |name: &str| Path::new(&format!("{ws_root}{name}")).exists();
+
+        let events = sync_with(&state, &addr, sync_paths(&["//ws/src/"]))
+            .await
+            .unwrap();
+        let mut synced = events
+            .iter()
+            .map(|x| x.file_path.clone())
+            .collect::<Vec<_>>();
+        synced.sort();
+        synced.dedup();
+        assert_eq!(synced, vec!["//ws/src/b.rs", "//ws/src/nested/c.rs"]);
+        assert!(exists("src/b.rs"));
+        assert!(exists("src/nested/c.rs"));
+        assert!(!exists("a.txt"));
+        assert!(!exists("src2/d.rs"));
+
+        // 单个文件，包括本地路径
+        sync_with(&state, &addr, sync_paths(&[&format!("{ws_root}a.txt")]))
+            .await
+            .unwrap();
+        assert!(exists("a.txt"));
+        assert!(!exists("src2/d.rs"));
+    }
+
+    #[tokio::test]
+    async fn multi_chunk_file_is_written_chunk_by_chunk_in_order() {
+        let db = TempDb::new();
+        let root = db.root();
+        let mut state = db.state();
+        // 缓冲区小于 chunk，写入时会多次落盘
+        state.sync_write_buffer_size = 1024;
+        let chunks: Vec<Vec<u8>> = (0..3u8)
+            .map(|i| (0..100_000u32).map(|j| (j % 251) as u8 ^ i).collect())
+            .collect();
+        let chunk_hashes: Vec<String> = chunks
+            .iter()
+            .map(|chunk| hex::encode(blake3::hash(chunk).as_bytes()))
+            .collect();
+
+        let downloaded_chunks = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let ws_root = create_workspace(&state, root, "ws");
+        let addr = stub_hive::spawn(StubHive {
+            chunked_files: vec![("//big.bin".to_string(), chunks.clone())],
+            downloaded_chunks: downloaded_chunks.clone(),
+            ..Default::default()
+        })
+        .await;
+        let events = sync(&state, &addr, "ws", &ws_root, false).await.unwrap();
+        assert_eq!(
+            final_status(&events, "//ws/big.bin").0,
+            SyncEventStatus::Done
+        );
+        assert_eq!(*downloaded_chunks.lock().unwrap(), chunk_hashes);
+        assert_eq!(
+            std::fs::read(format!("{ws_root}big.bin")).unwrap(),
+            chunks.concat()
+        );
+
+        // 第二个 chunk 损坏时立即失败，不再下载后续的 chunk，也不会留下写了一半的文件
+        let downloaded_chunks = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let ws_root = create_workspace(&state, root, "corrupt");
+        let addr = stub_hive::spawn(StubHive {
+            chunked_files: vec![("//big.bin".to_string(), chunks.clone())],
+            corrupt_chunks: [chunk_hashes[1].clone()].into(),
+            downloaded_chunks: downloaded_chunks.clone(),
+            ..Default::default()
+        })
+        .await;
+        let events = sync(&state, &addr, "corrupt", &ws_root, false)
+            .await
+            .unwrap();
+        let (status, error) = final_status(&events, "//corrupt/big.bin");
+        assert_eq!(status, SyncEventStatus::Failed);
+        assert!(error.contains("chunk hash mismatch"), "{error}");
+        assert_eq!(*downloaded_chunks.lock().unwrap(), chunk_hashes[..2]);
+        let target = PathBuf::from(format!("{ws_root}big.bin"));
+        assert!(!target.exists());
+        assert!(!recovery::partial_path(&target).exists());
+    }
+
+    #[tokio::test]
+    async fn compressed_revision_is_decompressed_before_writing() {
+        let db = TempDb::new();
+        let root = db.root();
+        let state = db.state();
+        let chunks: Vec<Vec<u8>> = vec![b"zstd chunk ".repeat(4096), b"second ".repeat(4096)];
+
+        for compression_type in [CompressionType::None, CompressionType::Zstd] {
+            let name = compression_type.as_str();
+            let ws_root = create_workspace(&state, root, name);
+            let addr = stub_hive::spawn(StubHive {
+                chunked_files: vec![("//big.txt".to_string(), chunks.clone())],
+                compression_type,
+                ..Default::default()
+            })
+            .await;
+            let events = sync(&state, &addr, name, &ws_root, false).await.unwrap();
+            assert_eq!(
+                final_status(&events, &format!("//{name}/big.txt")).0,
+                SyncEventStatus::Done
+            );
+            assert_eq!(
+                std::fs::read(format!("{ws_root}big.txt")).unwrap(),
+                chunks.concat()
+            );
+            let cached = db.get_hive_cache().unwrap();
+            assert_eq!(cached[0].compression_type, compression_type);
+        }
+    }
+
+    #[tokio::test]
+    async fn interrupted_sync_is_rolled_back_on_restart() {
+        let db = TempDb::new();
+        let root = db.root();
+        let state = db.state();
+        let ws_root = create_workspace(&state, root, "ws");
+        let addr = stub_hive::spawn(StubHive {
+            files: vec![("//a.txt".to_string(), b"hello".to_vec())],
+            ..Default::default()
+        })
+        .await;
+
+        let events = sync(&state, &addr, "ws", &ws_root, false).await.unwrap();
+        assert_eq!(final_status(&events, "//ws/a.txt").0, SyncEventStatus::Done);
+        let target = PathBuf::from(format!("{ws_root}a.txt"));
+        assert_eq!(std::fs::read(&target).unwrap(), b"hello");
+        assert!(!recovery::partial_path(&target).exists());
+        assert!(state.recovery_log.state().unwrap().is_clean());
+
+        // 模拟下载新版本到一半时 daemon 崩溃
+        state.recovery_log.begin("//ws/a.txt", &target).unwrap();
+        std::fs::write(recovery::partial_path(&target), b"hel").unwrap();
+        drop(state);
+
+        let state = db.state();
+        let actions = state.recovery_log.recover().unwrap();
+        assert_eq!(
+            actions,
+            vec![recovery::RecoveryAction::RolledBack {
+                file_id: "//ws/a.txt".to_string(),
+                target_path: target.clone(),
+            }]
+        );
+        assert!(!recovery::partial_path(&target).exists());
+        assert_eq!(std::fs::read(&target).unwrap(), b"hello");
+        assert!(state.recovery_log.state().unwrap().is_clean());
+    }
+}
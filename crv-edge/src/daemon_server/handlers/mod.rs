@@ -1,7 +1,11 @@
 //! 业务逻辑层
+pub mod branch;
 pub mod changelist;
-pub mod job_system_debug;
 pub mod edge;
 pub mod file;
+pub mod job_system_debug;
+pub mod snapshot;
+pub mod tag;
+pub mod user;
 pub mod utils;
 pub mod workspace;
@@ -0,0 +1,40 @@
+use crate::daemon_server::config::RuntimeConfig;
+use crate::daemon_server::error::AppResult;
+use crate::daemon_server::handlers::snapshot::list::snapshot_from_hive;
+use crate::daemon_server::state::AppState;
+use crate::hive_pb::{self, hive_service_client::HiveServiceClient};
+use crate::pb::{CreateSnapshotReq, CreateSnapshotRsp};
+use tonic::{Request, Response};
+
+pub async fn handle(
+    state: AppState,
+    req: Request<CreateSnapshotReq>,
+) -> AppResult<Response<CreateSnapshotRsp>> {
+    let runtime_config = RuntimeConfig::from_req(&req)?;
+    let channel = state
+        .hive_channel
+        .get_channel(&runtime_config.remote_addr.value)?;
+
+    let mut hive_client = HiveServiceClient::new(channel);
+
+    // hive 需要登录用户，透传调用方携带的 authorization 头
+    let authorization = req.metadata().get("authorization").cloned();
+    let request_body = req.into_inner();
+    let mut hive_req = Request::new(hive_pb::CreateSnapshotReq {
+        branch_id: request_body.branch_id,
+        changelist_id: request_body.changelist_id,
+        name: request_body.name,
+        description: request_body.description,
+    });
+    if let Some(authorization) = authorization {
+        hive_req
+            .metadata_mut()
+            .insert("authorization", authorization);
+    }
+
+    let hive_rsp = hive_client.create_snapshot(hive_req).await?.into_inner();
+
+    Ok(Response::new(CreateSnapshotRsp {
+        snapshot: hive_rsp.snapshot.map(snapshot_from_hive),
+    }))
+}
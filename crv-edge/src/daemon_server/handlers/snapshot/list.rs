@@ -0,0 +1,52 @@
+use crate::daemon_server::config::RuntimeConfig;
+use crate::daemon_server::error::AppResult;
+use crate::daemon_server::state::AppState;
+use crate::hive_pb::{self, hive_service_client::HiveServiceClient};
+use crate::pb::{ListSnapshotsReq, ListSnapshotsRsp, SnapshotInfo};
+use tonic::{Request, Response};
+
+pub(crate) fn snapshot_from_hive(snapshot: hive_pb::SnapshotInfo) -> SnapshotInfo {
+    SnapshotInfo {
+        id: snapshot.id,
+        name: snapshot.name,
+        branch_id: snapshot.branch_id,
+        changelist_id: snapshot.changelist_id,
+        created_by: snapshot.created_by,
+        created_at: snapshot.created_at,
+        description: snapshot.description,
+    }
+}
+
+pub async fn handle(
+    state: AppState,
+    req: Request<ListSnapshotsReq>,
+) -> AppResult<Response<ListSnapshotsRsp>> {
+    let runtime_config = RuntimeConfig::from_req(&req)?;
+    let channel = state
+        .hive_channel
+        .get_channel(&runtime_config.remote_addr.value)?;
+
+    let mut hive_client = HiveServiceClient::new(channel);
+
+    // hive 需要登录用户，透传调用方携带的 authorization 头
+    let authorization = req.metadata().get("authorization").cloned();
+    let request_body = req.into_inner();
+    let mut hive_req = Request::new(hive_pb::ListSnapshotsReq {
+        branch_id: request_body.branch_id,
+    });
+    if let Some(authorization) = authorization {
+        hive_req
+            .metadata_mut()
+            .insert("authorization", authorization);
+    }
+
+    let hive_rsp = hive_client.list_snapshots(hive_req).await?.into_inner();
+
+    Ok(Response::new(ListSnapshotsRsp {
+        snapshots: hive_rsp
+            .snapshots
+            .into_iter()
+            .map(snapshot_from_hive)
+            .collect(),
+    }))
+}
@@ -0,0 +1,37 @@
+use crate::daemon_server::config::RuntimeConfig;
+use crate::daemon_server::error::AppResult;
+use crate::daemon_server::state::AppState;
+use crate::hive_pb::{self, hive_service_client::HiveServiceClient};
+use crate::pb::{RestoreSnapshotReq, RestoreSnapshotRsp};
+use tonic::{Request, Response};
+
+pub async fn handle(
+    state: AppState,
+    req: Request<RestoreSnapshotReq>,
+) -> AppResult<Response<RestoreSnapshotRsp>> {
+    let runtime_config = RuntimeConfig::from_req(&req)?;
+    let channel = state
+        .hive_channel
+        .get_channel(&runtime_config.remote_addr.value)?;
+
+    let mut hive_client = HiveServiceClient::new(channel);
+
+    // hive 需要管理员，透传调用方携带的 authorization 头
+    let authorization = req.metadata().get("authorization").cloned();
+    let request_body = req.into_inner();
+    let mut hive_req = Request::new(hive_pb::RestoreSnapshotReq {
+        snapshot_id: request_body.snapshot_id,
+    });
+    if let Some(authorization) = authorization {
+        hive_req
+            .metadata_mut()
+            .insert("authorization", authorization);
+    }
+
+    let hive_rsp = hive_client.restore_snapshot(hive_req).await?.into_inner();
+
+    Ok(Response::new(RestoreSnapshotRsp {
+        branch_id: hive_rsp.branch_id,
+        changelist_id: hive_rsp.changelist_id,
+    }))
+}
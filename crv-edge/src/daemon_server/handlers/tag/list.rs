@@ -0,0 +1,42 @@
+use crate::daemon_server::config::RuntimeConfig;
+use crate::daemon_server::error::AppResult;
+use crate::daemon_server::state::AppState;
+use crate::hive_pb::{self, hive_service_client::HiveServiceClient};
+use crate::pb::{GetChangelistByTagReq, GetChangelistByTagRsp, TaggedChangelist};
+use tonic::{Request, Response};
+
+pub async fn handle(
+    state: AppState,
+    req: Request<GetChangelistByTagReq>,
+) -> AppResult<Response<GetChangelistByTagRsp>> {
+    let runtime_config = RuntimeConfig::from_req(&req)?;
+    let channel = state
+        .hive_channel
+        .get_channel(&runtime_config.remote_addr.value)?;
+
+    let mut hive_client = HiveServiceClient::new(channel);
+
+    let request_body = req.into_inner();
+    let hive_rsp = hive_client
+        .get_changelist_by_tag(hive_pb::GetChangelistByTagReq {
+            branch_id: request_body.branch_id,
+            label: request_body.label,
+            limit: request_body.limit,
+        })
+        .await?
+        .into_inner();
+
+    Ok(Response::new(GetChangelistByTagRsp {
+        changelists: hive_rsp
+            .changelists
+            .into_iter()
+            .map(|cl| TaggedChangelist {
+                changelist_id: cl.changelist_id,
+                author: cl.author,
+                description: cl.description,
+                committed_at: cl.committed_at,
+                labels: cl.labels,
+            })
+            .collect(),
+    }))
+}
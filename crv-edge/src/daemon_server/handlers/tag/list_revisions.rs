@@ -0,0 +1,44 @@
+use crate::daemon_server::config::RuntimeConfig;
+use crate::daemon_server::error::AppResult;
+use crate::daemon_server::state::AppState;
+use crate::hive_pb::{self, hive_service_client::HiveServiceClient};
+use crate::pb::{ListFileRevisionsByTagReq, ListFileRevisionsByTagRsp, TaggedFileRevision};
+use tonic::{Request, Response};
+
+pub async fn handle(
+    state: AppState,
+    req: Request<ListFileRevisionsByTagReq>,
+) -> AppResult<Response<ListFileRevisionsByTagRsp>> {
+    let runtime_config = RuntimeConfig::from_req(&req)?;
+    let channel = state
+        .hive_channel
+        .get_channel(&runtime_config.remote_addr.value)?;
+
+    let mut hive_client = HiveServiceClient::new(channel);
+
+    let request_body = req.into_inner();
+    let hive_rsp = hive_client
+        .list_file_revisions_by_tag(hive_pb::ListFileRevisionsByTagReq {
+            branch_id: request_body.branch_id,
+            tag: request_body.tag,
+            limit: request_body.limit,
+        })
+        .await?
+        .into_inner();
+
+    Ok(Response::new(ListFileRevisionsByTagRsp {
+        revisions: hive_rsp
+            .revisions
+            .into_iter()
+            .map(|r| TaggedFileRevision {
+                path: r.path,
+                generation: r.generation,
+                revision: r.revision,
+                changelist_id: r.changelist_id,
+                size: r.size,
+                created_at: r.created_at,
+                tags: r.tags,
+            })
+            .collect(),
+    }))
+}
@@ -0,0 +1,5 @@
+pub mod add;
+pub mod list;
+pub mod list_revisions;
+pub mod remove;
+pub mod revision;
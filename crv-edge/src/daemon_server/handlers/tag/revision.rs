@@ -0,0 +1,40 @@
+use crate::daemon_server::config::RuntimeConfig;
+use crate::daemon_server::error::AppResult;
+use crate::daemon_server::state::AppState;
+use crate::hive_pb::{self, hive_service_client::HiveServiceClient};
+use crate::pb::{TagFileRevisionReq, TagFileRevisionRsp};
+use tonic::{Request, Response};
+
+pub async fn handle(
+    state: AppState,
+    req: Request<TagFileRevisionReq>,
+) -> AppResult<Response<TagFileRevisionRsp>> {
+    let runtime_config = RuntimeConfig::from_req(&req)?;
+    let channel = state
+        .hive_channel
+        .get_channel(&runtime_config.remote_addr.value)?;
+
+    let mut hive_client = HiveServiceClient::new(channel);
+
+    // hive 需要登录用户，透传调用方携带的 authorization 头
+    let authorization = req.metadata().get("authorization").cloned();
+    let request_body = req.into_inner();
+    let mut hive_req = Request::new(hive_pb::TagFileRevisionReq {
+        path: request_body.path,
+        generation: request_body.generation,
+        revision: request_body.revision,
+        tags_to_add: request_body.tags_to_add,
+        tags_to_remove: request_body.tags_to_remove,
+    });
+    if let Some(authorization) = authorization {
+        hive_req
+            .metadata_mut()
+            .insert("authorization", authorization);
+    }
+
+    let hive_rsp = hive_client.tag_file_revision(hive_req).await?.into_inner();
+
+    Ok(Response::new(TagFileRevisionRsp {
+        tags: hive_rsp.tags,
+    }))
+}
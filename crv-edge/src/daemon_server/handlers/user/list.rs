@@ -0,0 +1,45 @@
+use crate::daemon_server::config::RuntimeConfig;
+use crate::daemon_server::error::AppResult;
+use crate::daemon_server::state::AppState;
+use crate::hive_pb::{self, hive_service_client::HiveServiceClient};
+use crate::pb::{ListUsersReq, ListUsersRsp, UserSummary};
+use tonic::{Request, Response};
+
+pub async fn handle(
+    state: AppState,
+    req: Request<ListUsersReq>,
+) -> AppResult<Response<ListUsersRsp>> {
+    let runtime_config = RuntimeConfig::from_req(&req)?;
+    let channel = state
+        .hive_channel
+        .get_channel(&runtime_config.remote_addr.value)?;
+
+    let mut hive_client = HiveServiceClient::new(channel);
+
+    // hive 需要管理员权限，透传调用方携带的 authorization 头
+    let authorization = req.metadata().get("authorization").cloned();
+    let request_body = req.into_inner();
+    let mut hive_req = Request::new(hive_pb::ListUsersReq {
+        page: request_body.page,
+        page_size: request_body.page_size,
+    });
+    if let Some(authorization) = authorization {
+        hive_req
+            .metadata_mut()
+            .insert("authorization", authorization);
+    }
+
+    let hive_rsp = hive_client.list_users(hive_req).await?.into_inner();
+
+    Ok(Response::new(ListUsersRsp {
+        users: hive_rsp
+            .users
+            .into_iter()
+            .map(|u| UserSummary {
+                username: u.username,
+                created_at: u.created_at,
+            })
+            .collect(),
+        total: hive_rsp.total,
+    }))
+}
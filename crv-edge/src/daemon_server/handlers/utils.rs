@@ -1,8 +1,13 @@
 use crate::daemon_server::db::file::FileLocation;
 use crate::daemon_server::error::{AppError, AppResult};
 use crate::daemon_server::state::AppState;
-use crv_core::path::basic::{LocalDir, LocalPath, PathError, WorkspaceDir, WorkspacePath};
+use crv_core::path::basic::{
+    DepotPathWildcard, FilenameWildcard, LocalDir, LocalPath, LocalPathWildcard, PathError,
+    WorkspaceDir, WorkspacePath,
+};
 use crv_core::path::engine::PathEngine;
+use crv_core::workspace::entity::{IncludeMapping, WorkspaceConfig, WorkspaceMapping};
+use std::collections::BTreeMap;
 use std::path::Path;
 use tonic::Status;
 use walkdir::WalkDir;
@@ -205,6 +210,83 @@ pub fn expand_to_mapped_files_in_fs(
     result
 }
 
+/// 将 depot 通配路径展开为文件列表：按工作区映射找到通配路径对应的本地目录并递归遍历，
+/// 只保留映射回的 depot 路径与通配路径匹配的文件，本地不存在的文件不会出现在结果中
+pub fn expand_depot_wildcard_in_fs(
+    wildcard: &DepotPathWildcard,
+    workspace: &WorkspaceConfig,
+    path_engine: &PathEngine,
+) -> Vec<FileLocation> {
+    let mut local_dirs = vec![];
+    let mut local_files = vec![];
+    for mapping in &workspace.mappings {
+        let WorkspaceMapping::Include(include_mapping) = mapping else {
+            continue;
+        };
+        match include_mapping {
+            IncludeMapping::File(file_mapping) => {
+                if wildcard
+                    .match_and_get_diff(&file_mapping.depot_file)
+                    .is_some()
+                {
+                    local_files.push(file_mapping.local_file.clone());
+                }
+            }
+            IncludeMapping::Folder(folder_mapping) => {
+                let mapped_dirs = &folder_mapping.depot_folder.dirs;
+                let local_dir = match wildcard {
+                    // 通配路径在映射范围内，只需遍历对应的子目录
+                    DepotPathWildcard::Range(range) if range.dirs.starts_with(mapped_dirs) => {
+                        let mut dirs = folder_mapping.local_folder.0.clone();
+                        dirs.extend_from_slice(&range.dirs[mapped_dirs.len()..]);
+                        LocalDir(dirs)
+                    }
+                    DepotPathWildcard::Range(range) if mapped_dirs.starts_with(&range.dirs) => {
+                        folder_mapping.local_folder.clone()
+                    }
+                    DepotPathWildcard::Range(_) => continue,
+                    DepotPathWildcard::Regex(_) => folder_mapping.local_folder.clone(),
+                };
+                local_dirs.push(local_dir);
+            }
+        }
+    }
+
+    local_files.extend(local_dirs.iter().flat_map(|dir| {
+        WalkDir::new(dir.to_local_path_string())
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_file())
+            .map(|e| LocalPath::parse(&e.path().to_string_lossy()).unwrap())
+    }));
+
+    // 映射的本地目录可能互相嵌套，按本地路径去重
+    let mut result = BTreeMap::new();
+    for local_path in local_files {
+        if !Path::new(&local_path.to_local_path_string()).is_file() {
+            continue;
+        }
+        let Some(depot_path) = path_engine.mapping_local_path(&local_path) else {
+            continue;
+        };
+        if wildcard.match_and_get_diff(&depot_path).is_none() {
+            continue;
+        }
+        let Some(workspace_path) = path_engine.local_path_to_workspace_path(&local_path) else {
+            continue;
+        };
+        result.insert(
+            local_path.to_local_path_string(),
+            FileLocation {
+                local_path,
+                workspace_path,
+                depot_path,
+            },
+        );
+    }
+    result.into_values().collect()
+}
+
 /// 遍历当前工作区的活跃文件，将路径列表展开为文件列表，如果文件不是活跃文件，则不会出现在结果中
 pub fn expand_to_mapped_files_active(
     paths: &[LocationUnion],
@@ -260,6 +342,35 @@ pub fn expand_to_mapped_files_active(
     Ok(result)
 }
 
+/// 将规范化后的路径转化为本地路径通配符：目录递归匹配其下所有文件，文件只匹配其自身。
+///
+/// 路径不在当前工作区内时返回 None
+pub fn to_local_path_wildcard(
+    location: &LocationUnion,
+    path_engine: &PathEngine,
+) -> Option<LocalPathWildcard> {
+    let dir_wildcard = |dirs: LocalDir| LocalPathWildcard {
+        dirs,
+        recursive: true,
+        wildcard: FilenameWildcard::All,
+    };
+    let file_wildcard = |path: LocalPath| LocalPathWildcard {
+        dirs: path.dirs,
+        recursive: false,
+        wildcard: FilenameWildcard::Exact(path.file),
+    };
+    match location {
+        LocationUnion::LocalDir(local_dir) => Some(dir_wildcard(local_dir.clone())),
+        LocationUnion::LocalPath(local_path) => Some(file_wildcard(local_path.clone())),
+        LocationUnion::WorkspaceDir(workspace_dir) => path_engine
+            .workspace_dir_to_local_dir(workspace_dir)
+            .map(dir_wildcard),
+        LocationUnion::WorkspacePath(workspace_path) => path_engine
+            .workspace_path_to_local_path(workspace_path)
+            .map(file_wildcard),
+    }
+}
+
 /// 遍历当前工作区的文件元数据，将路径列表展开为文件列表，如果文件不在元数据内，则不会出现在结果中
 pub fn expand_to_mapped_files_in_edge_meta(
     paths: &[LocationUnion],
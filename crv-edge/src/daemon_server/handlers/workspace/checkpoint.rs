@@ -0,0 +1,394 @@
+use crate::daemon_server::config::RuntimeConfig;
+use crate::daemon_server::error::{AppError, AppResult};
+use crate::daemon_server::handlers::file::submit::{CHUNK_SIZE, FRAME_SIZE};
+use crate::daemon_server::state::{AppState, HiveChannel};
+use crate::hive_pb::DownloadFileChunkReq;
+use crate::hive_pb::hive_service_client::HiveServiceClient;
+use crate::metrics;
+use crate::pb::{
+    CheckpointInfo, DeleteCheckpointReq, DeleteCheckpointRsp, ListCheckpointsReq,
+    ListCheckpointsRsp, RestoreCheckpointReq, RestoreCheckpointRsp, SaveCheckpointReq,
+    SaveCheckpointRsp,
+};
+use crv_core::logger::recovery;
+use crv_core::path::basic::WorkspaceDir;
+use crv_core::repository::compute_chunk_hash;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use tokio::fs;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tonic::{Request, Response, Status};
+
+fn ensure_workspace(state: &AppState, workspace_name: &String) -> AppResult<()> {
+    state
+        .db
+        .get_confirmed_workspace_meta(workspace_name)?
+        .ok_or(AppError::Raw(Status::not_found(format!(
+            "Workspace {} not found.",
+            workspace_name
+        ))))?;
+    Ok(())
+}
+
+pub async fn save(
+    state: AppState,
+    req: Request<SaveCheckpointReq>,
+) -> AppResult<Response<SaveCheckpointRsp>> {
+    let request_body = req.into_inner();
+    ensure_workspace(&state, &request_body.workspace_name)?;
+
+    if request_body.checkpoint_name.trim().is_empty() {
+        return Err(AppError::Raw(Status::invalid_argument(
+            "Checkpoint name cannot be empty.",
+        )));
+    }
+
+    state
+        .db
+        .save_checkpoint(&request_body.workspace_name, &request_body.checkpoint_name)?;
+
+    Ok(Response::new(SaveCheckpointRsp {}))
+}
+
+pub async fn restore(
+    state: AppState,
+    req: Request<RestoreCheckpointReq>,
+) -> AppResult<Response<RestoreCheckpointRsp>> {
+    let runtime_config = RuntimeConfig::from_req(&req)?;
+    let request_body = req.into_inner();
+    ensure_workspace(&state, &request_body.workspace_name)?;
+
+    // 1. 读取快照，并记录恢复前已同步的文件，用于找出快照之后才同步下来的文件
+    let doc = state
+        .db
+        .get_checkpoint(&request_body.workspace_name, &request_body.checkpoint_name)?
+        .ok_or(AppError::Raw(Status::not_found(format!(
+            "Checkpoint {} does not exist in workspace {}.",
+            request_body.checkpoint_name, request_body.workspace_name
+        ))))?;
+    let workspace_root = WorkspaceDir {
+        workspace_name: request_body.workspace_name.clone(),
+        dirs: vec![],
+    };
+    let current_files = state.db.get_file_meta_under_dir(&workspace_root)?;
+
+    let opened_paths = doc
+        .active_files
+        .iter()
+        .map(|(path, _)| path.to_custom_string())
+        .collect::<HashSet<_>>();
+    let snapshot_paths = doc
+        .files
+        .iter()
+        .map(|(path, _)| path.to_custom_string())
+        .collect::<HashSet<_>>();
+
+    // 2. 按快照中的 chunk hash 把文件下载到临时文件并校验，打开中的文件保留本地内容。
+    // 任何一个文件失败时都不改动本地文件与元数据
+    let channel = state
+        .hive_channel
+        .get_channel(&runtime_config.remote_addr.value)?;
+    let mut staged = Vec::new();
+    for (path, meta) in &doc.files {
+        let path_string = path.to_custom_string();
+        if opened_paths.contains(&path_string) {
+            continue;
+        }
+        let local_path = meta.location.local_path.to_local_path_string();
+        if local_chunk_hashes(&local_path).await.as_ref() == Some(&meta.chunk_hashes) {
+            continue;
+        }
+        match download_partial(channel.clone(), &local_path, &meta.chunk_hashes).await {
+            Ok(partial) => staged.push((path_string, local_path, partial)),
+            Err(e) => {
+                discard_partials(staged.into_iter().map(|(_, _, partial)| partial)).await;
+                return Err(e);
+            }
+        }
+    }
+
+    // 3. 用临时文件替换本地文件
+    let mut restored_paths = Vec::new();
+    let mut staged = staged.into_iter();
+    while let Some((path_string, local_path, partial)) = staged.next() {
+        if let Err(e) = fs::rename(&partial, &local_path).await {
+            let _ = fs::remove_file(&partial).await;
+            discard_partials(staged.map(|(_, _, partial)| partial)).await;
+            return Err(AppError::Internal(format!("{e}")));
+        }
+        restored_paths.push(path_string);
+    }
+
+    // 4. 移除快照中不存在的本地文件
+    let mut removed_paths = Vec::new();
+    for (path, meta) in current_files {
+        let path_string = path.to_custom_string();
+        if snapshot_paths.contains(&path_string) || opened_paths.contains(&path_string) {
+            continue;
+        }
+        let local_path = meta.location.local_path.to_local_path_string();
+        if Path::new(&local_path).is_file() {
+            fs::remove_file(&local_path)
+                .await
+                .map_err(|e| AppError::Internal(format!("{e}")))?;
+        }
+        removed_paths.push(path_string);
+    }
+
+    // 5. 本地文件全部恢复后，再原子地切换 active file 与文件元数据
+    state
+        .db
+        .restore_checkpoint(&request_body.workspace_name, &request_body.checkpoint_name)?;
+
+    Ok(Response::new(RestoreCheckpointRsp {
+        restored_paths,
+        removed_paths,
+    }))
+}
+
+pub async fn list(
+    state: AppState,
+    req: Request<ListCheckpointsReq>,
+) -> AppResult<Response<ListCheckpointsRsp>> {
+    let request_body = req.into_inner();
+    ensure_workspace(&state, &request_body.workspace_name)?;
+
+    let checkpoints = state
+        .db
+        .list_checkpoints(&request_body.workspace_name)?
+        .into_iter()
+        .map(|(name, doc)| CheckpointInfo {
+            name,
+            created_at: doc.created_at,
+            active_file_count: doc.active_files.len() as i32,
+            file_count: doc.files.len() as i32,
+        })
+        .collect();
+
+    Ok(Response::new(ListCheckpointsRsp { checkpoints }))
+}
+
+pub async fn delete(
+    state: AppState,
+    req: Request<DeleteCheckpointReq>,
+) -> AppResult<Response<DeleteCheckpointRsp>> {
+    let request_body = req.into_inner();
+    ensure_workspace(&state, &request_body.workspace_name)?;
+
+    state
+        .db
+        .delete_checkpoint(&request_body.workspace_name, &request_body.checkpoint_name)?;
+
+    Ok(Response::new(DeleteCheckpointRsp {}))
+}
+
+/// 按提交时的切块方式计算本地文件的 chunk hash，文件不存在或无法读取时返回 None
+pub(crate) async fn local_chunk_hashes(local_path: &str) -> Option<Vec<String>> {
+    let mut file = fs::File::open(local_path).await.ok()?;
+    let mut hashes = Vec::new();
+    let mut buffer = vec![0u8; CHUNK_SIZE];
+    loop {
+        let n = file.read(&mut buffer).await.ok()?;
+        if n == 0 {
+            break;
+        }
+        hashes.push(hex::encode(compute_chunk_hash(&buffer[..n])));
+    }
+    Some(hashes)
+}
+
+/// 从 hive 下载 chunk 并校验，成功后原子地覆盖本地文件，失败时本地文件保持不变
+pub(crate) async fn download_file(
+    channel: HiveChannel,
+    local_path: &str,
+    chunk_hashes: &[String],
+) -> AppResult<()> {
+    let partial = download_partial(channel, local_path, chunk_hashes).await?;
+    if let Err(e) = fs::rename(&partial, local_path).await {
+        let _ = fs::remove_file(&partial).await;
+        return Err(AppError::Internal(format!("{e}")));
+    }
+    Ok(())
+}
+
+/// 从 hive 下载 chunk 写入本地文件旁的临时文件，返回临时文件的路径。
+///
+/// 失败时临时文件已被删除。
+async fn download_partial(
+    channel: HiveChannel,
+    local_path: &str,
+    chunk_hashes: &[String],
+) -> AppResult<PathBuf> {
+    let partial = recovery::partial_path(Path::new(local_path));
+    let result = write_chunks(channel, &partial, chunk_hashes).await;
+    if result.is_err() {
+        let _ = fs::remove_file(&partial).await;
+    }
+    result.map(|()| partial)
+}
+
+/// 删除尚未替换本地文件的临时文件
+async fn discard_partials(partials: impl IntoIterator<Item = PathBuf>) {
+    for partial in partials {
+        let _ = fs::remove_file(partial).await;
+    }
+}
+
+/// 逐个下载 chunk，校验 hash 一致后才写入 `dest`
+async fn write_chunks(channel: HiveChannel, dest: &Path, chunk_hashes: &[String]) -> AppResult<()> {
+    let mut hive_client = HiveServiceClient::new(channel.clone());
+
+    if let Some(parent) = dest.parent() {
+        fs::create_dir_all(parent)
+            .await
+            .map_err(|e| AppError::Internal(format!("{e}")))?;
+    }
+    let mut file_fs = fs::File::create(dest)
+        .await
+        .map_err(|e| AppError::Internal(format!("{e}")))?;
+
+    for chunk_hash in chunk_hashes {
+        let mut stream = hive_client
+            .download_file_chunk(DownloadFileChunkReq {
+                chunk_hashes: vec![chunk_hash.clone()],
+                packet_size: FRAME_SIZE as i64,
+                compression: String::new(),
+            })
+            .await?
+            .into_inner();
+
+        let mut chunk = Vec::new();
+        while let Some(rsp) = channel.next_message(&mut stream).await? {
+            metrics::collector().add_downloaded_bytes(rsp.content.len());
+            chunk.extend_from_slice(&rsp.content);
+        }
+        let received_hash = hex::encode(compute_chunk_hash(&chunk));
+        if received_hash != *chunk_hash {
+            return Err(AppError::Internal(format!(
+                "chunk hash mismatch: expected {chunk_hash}, got {received_hash}"
+            )));
+        }
+        file_fs
+            .write_all(&chunk)
+            .await
+            .map_err(|e| AppError::Internal(format!("{e}")))?;
+    }
+    file_fs
+        .flush()
+        .await
+        .map_err(|e| AppError::Internal(format!("{e}")))?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::daemon_server::config::{RuntimeConfigItem, RuntimeConfigSource};
+    use crate::daemon_server::handlers::edge::stub_hive::{self, StubHive};
+    use crate::daemon_server::handlers::file::sync;
+    use crate::pb::SyncWithProgressReq;
+    use crate::test_support::TempDb;
+    use crv_core::path::basic::WorkspacePath;
+    use crv_core::workspace::entity::WorkspaceConfig;
+    use tokio_stream::StreamExt;
+
+    fn request<T>(remote_addr: &str, body: T) -> Request<T> {
+        let mut runtime_config = RuntimeConfig::default();
+        runtime_config.remote_addr = RuntimeConfigItem {
+            value: remote_addr.to_string(),
+            source: RuntimeConfigSource::Override,
+        };
+        let mut req = Request::new(body);
+        req.extensions_mut().insert(runtime_config);
+        req
+    }
+
+    async fn sync_to(state: &AppState, addr: &str, workspace_root: &str, changelist_id: i64) {
+        let events: Vec<_> = sync::handle_with_progress(
+            state.clone(),
+            request(
+                addr,
+                SyncWithProgressReq {
+                    workspace_name: "ws".to_string(),
+                    paths: vec![workspace_root.to_string()],
+                    changelist_id,
+                    ..Default::default()
+                },
+            ),
+        )
+        .await
+        .unwrap()
+        .into_inner()
+        .collect()
+        .await;
+        assert!(events.iter().all(|event| event.is_ok()));
+    }
+
+    fn restore_req(addr: &str) -> Request<RestoreCheckpointReq> {
+        request(
+            addr,
+            RestoreCheckpointReq {
+                workspace_name: "ws".to_string(),
+                checkpoint_name: "cp".to_string(),
+            },
+        )
+    }
+
+    #[tokio::test]
+    async fn corrupted_download_leaves_files_and_metadata_unchanged() {
+        let changelists = vec![
+            vec![("//a.txt".to_string(), Some(b"hello".to_vec()))],
+            vec![("//a.txt".to_string(), Some(b"hello world".to_vec()))],
+        ];
+        let addr = stub_hive::spawn(StubHive {
+            changelists: changelists.clone(),
+            ..Default::default()
+        })
+        .await;
+        let corrupt_addr = stub_hive::spawn(StubHive {
+            changelists,
+            corrupt_chunks: HashSet::from([hex::encode(blake3::hash(b"hello").as_bytes())]),
+            ..Default::default()
+        })
+        .await;
+
+        let db = TempDb::new();
+        let state = db.state();
+        let workspace_root = db.root().join("ws");
+        std::fs::create_dir_all(&workspace_root).unwrap();
+        let workspace_root = format!("{}/", workspace_root.to_string_lossy());
+        let config =
+            WorkspaceConfig::from_specification("ws", &workspace_root, "//... //ws/").unwrap();
+        state
+            .db
+            .create_workspace_pending("ws".to_string(), config)
+            .unwrap();
+        state.db.confirm_workspace("ws".to_string()).unwrap();
+
+        sync_to(&state, &addr, &workspace_root, 1).await;
+        state.db.save_checkpoint("ws", "cp").unwrap();
+        sync_to(&state, &addr, &workspace_root, 0).await;
+
+        let local_path = format!("{workspace_root}a.txt");
+        let workspace_path = WorkspacePath::parse("//ws/a.txt").unwrap();
+        assert!(
+            restore(state.clone(), restore_req(&corrupt_addr))
+                .await
+                .is_err()
+        );
+        assert_eq!(std::fs::read(&local_path).unwrap(), b"hello world");
+        assert!(!recovery::partial_path(Path::new(&local_path)).exists());
+        let meta = state.db.get_file_meta(&workspace_path).unwrap().unwrap();
+        assert_eq!(meta.changelist_id, 2);
+
+        let rsp = restore(state.clone(), restore_req(&addr))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(rsp.restored_paths, vec!["//ws/a.txt".to_string()]);
+        assert_eq!(std::fs::read(&local_path).unwrap(), b"hello");
+        let meta = state.db.get_file_meta(&workspace_path).unwrap().unwrap();
+        assert_eq!(meta.changelist_id, 1);
+    }
+}
@@ -1,17 +1,29 @@
 use std::path::{self, PathBuf};
 
+use crate::daemon_server::config::RuntimeConfig;
 use crate::daemon_server::context::SessionContext;
 use crate::daemon_server::error::{AppError, AppResult};
-use crate::daemon_server::state::AppState;
+use crate::daemon_server::state::{AppState, HiveChannel};
+use crate::hive_pb::hive_service_client::HiveServiceClient;
+use crate::hive_pb::{RegisterWorkspaceReq, UnregisterWorkspaceReq};
 use crate::pb::{CreateWorkspaceReq, CreateWorkspaceRsp};
 use crv_core::workspace::entity::WorkspaceConfig;
 use tonic::{Request, Response, Status};
 
+/// 创建 workspace，采用两阶段提交保证 hive 与本地数据库一致：
+///
+/// 1. 在 hive 上登记 workspace（`RegisterWorkspace`），workspace 名称在 hive 上全局唯一，
+///    名称被占用时直接失败，本地不做任何修改；
+/// 2. 写入本地数据库，先写入 Pending 状态，再改为 Confirmed。
+///
+/// 第 2 步失败时，调用 hive 的 `UnregisterWorkspace` 撤销第 1 步的登记，并删除本地残留的
+/// Pending 记录。撤销失败时只打印日志，hive 上会残留一条没有对应本地 workspace 的登记。
 pub async fn handle(
     state: AppState,
     req: Request<CreateWorkspaceReq>,
 ) -> AppResult<Response<CreateWorkspaceRsp>> {
-    let _ctx = SessionContext::from_req(&req)?;
+    let ctx = SessionContext::from_req(&req)?;
+    let runtime_config = RuntimeConfig::from_req(&req)?;
     let req = req.into_inner();
 
     // step 0. 检查 root dir 是否存在，且为目录
@@ -41,32 +53,162 @@ pub async fn handle(
                 Status::invalid_argument(format!("Invalid workspace configuration: {}", e))
             })?;
 
-    // Step 2: 调用 create_workspace_pending 创建 Pending 状态的 workspace
+    // Step 2: 两阶段提交的第一阶段，在 hive 上登记这个 workspace
+    let channel = state
+        .hive_channel
+        .get_channel(&runtime_config.remote_addr.value)?;
+    HiveServiceClient::new(channel.clone())
+        .register_workspace(RegisterWorkspaceReq {
+            workspace_name: req.workspace_name.clone(),
+            owner: ctx.username,
+            workspace_root: root_dir,
+            workspace_mapping: req.workspace_mapping.clone(),
+        })
+        .await
+        .map_err(|e| {
+            Status::new(
+                e.code(),
+                format!("Failed to register workspace with hive: {}", e.message()),
+            )
+        })?;
+
+    // Step 3: 第二阶段，写入本地数据库，失败时撤销 hive 上的登记
+    if let Err(e) = save_workspace(&state, &req.workspace_name, workspace_config) {
+        unregister_workspace(channel, &req.workspace_name).await;
+        return Err(e);
+    }
+
+    // 返回成功响应
+    Ok(Response::new(CreateWorkspaceRsp {}))
+}
+
+/// 将 workspace 以 Pending 状态写入本地数据库后改为 Confirmed，确认失败时删除 Pending 记录
+fn save_workspace(
+    state: &AppState,
+    workspace_name: &String,
+    workspace_config: WorkspaceConfig,
+) -> AppResult<()> {
     state
         .db
-        .create_workspace_pending(req.workspace_name.clone(), workspace_config.clone())
+        .create_workspace_pending(workspace_name.clone(), workspace_config)
         .map_err(|e| Status::internal(format!("Failed to create pending workspace: {}", e)))?;
 
-    // Step 3: 调用 hive 的接口注册这个 workspace（暂时留空）
-    // TODO: 实现 hive 注册逻辑
-    // let hive_client = state.get_hive_client().await?;
-    // let register_req = RegisterWorkspaceReq {
-    //     workspace_name: req.workspace_name.clone(),
-    //     workspace_root: req.workspace_root.clone(),
-    //     workspace_mapping: req.workspace_mapping.clone(),
-    // };
-    // hive_client.register_workspace(register_req).await.map_err(|e| {
-    //     // 如果 hive 注册失败，需要回滚本地数据库中的 pending workspace
-    //     // TODO: 实现回滚逻辑
-    //     Status::internal(format!("Failed to register workspace with hive: {}", e))
-    // })?;
-
-    // Step 4: 调用 confirm_workspace 将 workspace 元数据状态改为 Confirmed
-    state
-        .db
-        .confirm_workspace(req.workspace_name.clone())
-        .map_err(|e| Status::internal(format!("Failed to confirm workspace: {}", e)))?;
+    if let Err(e) = state.db.confirm_workspace(workspace_name.clone()) {
+        if let Err(e) = state.db.discard_pending_workspace(workspace_name) {
+            eprintln!("Failed to discard pending workspace {workspace_name}: {e}");
+        }
+        return Err(AppError::from(Status::internal(format!(
+            "Failed to confirm workspace: {}",
+            e
+        ))));
+    }
+    Ok(())
+}
 
-    // 返回成功响应
-    Ok(Response::new(CreateWorkspaceRsp {}))
+/// 回滚：撤销 hive 上的登记
+async fn unregister_workspace(channel: HiveChannel, workspace_name: &str) {
+    if let Err(e) = HiveServiceClient::new(channel)
+        .unregister_workspace(UnregisterWorkspaceReq {
+            workspace_name: workspace_name.to_string(),
+        })
+        .await
+    {
+        eprintln!("Failed to unregister workspace {workspace_name} from hive: {e}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::daemon_server::config::{RuntimeConfigItem, RuntimeConfigSource};
+    use crate::daemon_server::handlers::edge::stub_hive::{self, StubHive};
+    use crate::test_support::{TempDb, TempDir};
+
+    fn request(hive_addr: &str, name: &str, root: &std::path::Path) -> Request<CreateWorkspaceReq> {
+        let mut runtime_config = RuntimeConfig::default();
+        runtime_config.remote_addr = RuntimeConfigItem {
+            value: hive_addr.to_string(),
+            source: RuntimeConfigSource::Override,
+        };
+        let mut req = Request::new(CreateWorkspaceReq {
+            workspace_name: name.to_string(),
+            workspace_root: root.to_string_lossy().to_string(),
+            workspace_mapping: String::new(),
+        });
+        req.extensions_mut().insert(runtime_config);
+        req.extensions_mut().insert(SessionContext {
+            username: "alice".to_string(),
+            token: String::new(),
+        });
+        req
+    }
+
+    #[tokio::test]
+    async fn workspace_is_registered_on_hive_and_confirmed_locally() {
+        let hive = StubHive::default();
+        let workspaces = hive.workspaces.clone();
+        let addr = stub_hive::spawn(hive).await;
+        let db = TempDb::new();
+        let state = db.state();
+        let root_dir = TempDir::new();
+        let root = root_dir.path();
+
+        handle(state.clone(), request(&addr, "ws", root))
+            .await
+            .unwrap();
+
+        assert!(workspaces.lock().unwrap().contains("ws"));
+        assert!(
+            state
+                .db
+                .get_confirmed_workspace_meta(&"ws".to_string())
+                .unwrap()
+                .is_some()
+        );
+    }
+
+    #[tokio::test]
+    async fn hive_rejection_leaves_no_local_workspace() {
+        let hive = StubHive::default();
+        hive.workspaces.lock().unwrap().insert("ws".to_string());
+        let addr = stub_hive::spawn(hive).await;
+        let db = TempDb::new();
+        let state = db.state();
+        let root_dir = TempDir::new();
+        let root = root_dir.path();
+
+        let Err(AppError::Raw(status)) = handle(state.clone(), request(&addr, "ws", root)).await
+        else {
+            panic!("create should fail when hive rejects the workspace");
+        };
+        assert_eq!(status.code(), tonic::Code::AlreadyExists);
+        assert!(state.db.get_all_workspaces().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn local_failure_rolls_back_hive_registration() {
+        let hive = StubHive::default();
+        let workspaces = hive.workspaces.clone();
+        let addr = stub_hive::spawn(hive).await;
+        let db = TempDb::new();
+        let state = db.state();
+        let root_dir = TempDir::new();
+        let root = root_dir.path();
+
+        handle(state.clone(), request(&addr, "ws", root))
+            .await
+            .unwrap();
+
+        // 根目录与已有的 workspace 冲突，hive 登记成功后本地写入失败
+        let nested = root.join("nested");
+        std::fs::create_dir(&nested).unwrap();
+        handle(state.clone(), request(&addr, "ws2", &nested))
+            .await
+            .unwrap_err();
+
+        let workspaces = workspaces.lock().unwrap();
+        assert!(workspaces.contains("ws"));
+        assert!(!workspaces.contains("ws2"));
+        assert_eq!(state.db.get_all_workspaces().unwrap(), vec!["ws"]);
+    }
 }
@@ -0,0 +1,133 @@
+use crate::daemon_server::config::RuntimeConfig;
+use crate::daemon_server::error::{AppError, AppResult};
+use crate::daemon_server::state::AppState;
+use crate::hive_pb::UnregisterWorkspaceReq;
+use crate::hive_pb::hive_service_client::HiveServiceClient;
+use crate::pb::{DeleteWorkspaceReq, DeleteWorkspaceRsp};
+use tonic::{Code, Request, Response, Status};
+
+/// 删除 workspace：先撤销 hive 上的登记，再删除本地记录及其下所有的 active file。
+///
+/// hive 上已经没有这个 workspace 的登记时（例如创建时回滚失败后又被清理）仍然删除本地记录。
+pub async fn handle(
+    state: AppState,
+    req: Request<DeleteWorkspaceReq>,
+) -> AppResult<Response<DeleteWorkspaceRsp>> {
+    let runtime_config = RuntimeConfig::from_req(&req)?;
+    let workspace_name = req.into_inner().workspace_name;
+
+    if state
+        .db
+        .get_confirmed_workspace_meta(&workspace_name)?
+        .is_none()
+    {
+        return Err(AppError::NotFound(format!(
+            "Workspace {workspace_name} not found."
+        )));
+    }
+
+    let channel = state
+        .hive_channel
+        .get_channel(&runtime_config.remote_addr.value)?;
+    match HiveServiceClient::new(channel)
+        .unregister_workspace(UnregisterWorkspaceReq {
+            workspace_name: workspace_name.clone(),
+        })
+        .await
+    {
+        Ok(_) => {}
+        Err(e) if e.code() == Code::NotFound => {}
+        Err(e) => {
+            return Err(AppError::from(Status::new(
+                e.code(),
+                format!("Failed to unregister workspace from hive: {}", e.message()),
+            )));
+        }
+    }
+
+    // workspace 与其下的 active file 一起删除，避免中途失败留下没有 workspace 的 active file
+    loop {
+        let mut transaction = state.db.begin_transaction();
+        transaction.delete_workspace(&workspace_name)?;
+        transaction.delete_active_files_for_workspace(&workspace_name)?;
+        if transaction.commit().is_ok() {
+            break;
+        }
+    }
+
+    Ok(Response::new(DeleteWorkspaceRsp {}))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::daemon_server::config::{RuntimeConfigItem, RuntimeConfigSource};
+    use crate::daemon_server::db::active_file::Action;
+    use crate::daemon_server::handlers::edge::stub_hive::{self, StubHive};
+    use crate::test_support::TempDb;
+    use crv_core::path::basic::WorkspacePath;
+    use crv_core::workspace::entity::WorkspaceConfig;
+    use std::path::Path;
+
+    fn create_workspace(state: &AppState, root: &Path, name: &str) {
+        let root = root.join(name);
+        std::fs::create_dir_all(&root).unwrap();
+        let config =
+            WorkspaceConfig::from_specification(name, &format!("{}/", root.to_string_lossy()), "")
+                .unwrap();
+        state
+            .db
+            .create_workspace_pending(name.to_string(), config)
+            .unwrap();
+        state.db.confirm_workspace(name.to_string()).unwrap();
+    }
+
+    fn request(hive_addr: &str, name: &str) -> Request<DeleteWorkspaceReq> {
+        let mut runtime_config = RuntimeConfig::default();
+        runtime_config.remote_addr = RuntimeConfigItem {
+            value: hive_addr.to_string(),
+            source: RuntimeConfigSource::Override,
+        };
+        let mut req = Request::new(DeleteWorkspaceReq {
+            workspace_name: name.to_string(),
+        });
+        req.extensions_mut().insert(runtime_config);
+        req
+    }
+
+    fn stage(state: &AppState, path: &str) -> WorkspacePath {
+        let path = WorkspacePath::parse(path).unwrap();
+        state
+            .db
+            .set_active_file_action(path.clone(), Action::Add)
+            .unwrap();
+        path
+    }
+
+    #[tokio::test]
+    async fn deleting_workspace_removes_its_active_files() {
+        let hive = StubHive::default();
+        let workspaces = hive.workspaces.clone();
+        workspaces.lock().unwrap().insert("ws".to_string());
+        let addr = stub_hive::spawn(hive).await;
+        let db = TempDb::new();
+        let state = db.state();
+        create_workspace(&state, db.root(), "ws");
+        create_workspace(&state, db.root(), "ws2");
+
+        let staged: Vec<_> = (0..100)
+            .map(|i| stage(&state, &format!("//ws/dir{}/file{i}.txt", i % 7)))
+            .collect();
+        // 名称以 ws 开头的其他 workspace 不受影响
+        let other = stage(&state, "//ws2/file.txt");
+
+        handle(state.clone(), request(&addr, "ws")).await.unwrap();
+
+        assert!(!workspaces.lock().unwrap().contains("ws"));
+        assert_eq!(state.db.get_all_workspaces().unwrap(), vec!["ws2"]);
+        for path in &staged {
+            assert!(state.db.get_active_file_action(path).unwrap().is_none());
+        }
+        assert!(state.db.get_active_file_action(&other).unwrap().is_some());
+    }
+}
@@ -0,0 +1,48 @@
+use crate::daemon_server::config::RuntimeConfig;
+use crate::daemon_server::error::AppResult;
+use crate::daemon_server::state::AppState;
+use crate::hive_pb::{self, hive_service_client::HiveServiceClient};
+use crate::pb::{DescribeWorkspaceReq, DescribeWorkspaceRsp, WorkspaceFile};
+use tonic::{Request, Response};
+
+/// 向 hive 查询 workspace 的登记信息，以及最新 changelist 中被其映射的文件
+pub async fn handle(
+    state: AppState,
+    req: Request<DescribeWorkspaceReq>,
+) -> AppResult<Response<DescribeWorkspaceRsp>> {
+    let runtime_config = RuntimeConfig::from_req(&req)?;
+    let channel = state
+        .hive_channel
+        .get_channel(&runtime_config.remote_addr.value)?;
+    let request_body = req.into_inner();
+
+    let hive_rsp = HiveServiceClient::new(channel)
+        .describe_workspace(hive_pb::DescribeWorkspaceReq {
+            workspace_name: request_body.workspace_name.clone(),
+            depot_wildcard: request_body.depot_wildcard,
+        })
+        .await?
+        .into_inner();
+
+    let workspace = hive_rsp.workspace.unwrap_or_default();
+    let files: Vec<WorkspaceFile> = hive_rsp
+        .files
+        .into_iter()
+        .map(|f| WorkspaceFile {
+            depot_path: f.depot_path,
+            local_path: f.local_path,
+            generation: f.generation,
+            revision: f.revision,
+            changelist_id: f.changelist_id,
+            size: f.size,
+        })
+        .collect();
+
+    Ok(Response::new(DescribeWorkspaceRsp {
+        workspace_name: request_body.workspace_name,
+        workspace_path: workspace.workspace_root,
+        file_paths: files.iter().map(|f| f.local_path.clone()).collect(),
+        changelist_id: hive_rsp.changelist_id,
+        files,
+    }))
+}
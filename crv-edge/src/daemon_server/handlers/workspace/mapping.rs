@@ -0,0 +1,268 @@
+use crate::daemon_server::error::{AppError, AppResult};
+use crate::daemon_server::handlers::workspace::validate::verify_mappings;
+use crate::daemon_server::state::AppState;
+use crate::pb::{
+    AddWorkspaceMappingReq, AddWorkspaceMappingRsp, ListWorkspaceMappingsReq,
+    ListWorkspaceMappingsRsp, RemoveWorkspaceMappingReq, RemoveWorkspaceMappingRsp,
+    WorkspaceMappingDoc,
+};
+use crv_core::workspace::conflict_detector_v2::FilenameFilter;
+use crv_core::workspace::entity::{WorkspaceConfig, WorkspaceMapping};
+use std::path;
+use tonic::{Request, Response, Status};
+
+fn get_config(state: &AppState, workspace_name: &String) -> AppResult<WorkspaceConfig> {
+    let workspace_meta = state
+        .db
+        .get_confirmed_workspace_meta(workspace_name)?
+        .ok_or(AppError::Raw(Status::not_found(format!(
+            "Workspace {} not found.",
+            workspace_name
+        ))))?;
+    Ok(workspace_meta.config)
+}
+
+/// 校验修改后的映射不存在冲突，然后写入数据库
+fn save_config(
+    state: &AppState,
+    workspace_name: &String,
+    config: &WorkspaceConfig,
+) -> AppResult<()> {
+    verify_mappings(workspace_name, config)?;
+    state
+        .db
+        .set_workspace_mappings(workspace_name, config.mappings.clone())?;
+    Ok(())
+}
+
+fn mapping_docs(config: &WorkspaceConfig) -> Vec<WorkspaceMappingDoc> {
+    config
+        .mapping_docs()
+        .into_iter()
+        .map(|doc| WorkspaceMappingDoc {
+            depot_wildcard: doc.depot_wildcard,
+            local_dir: doc.local_dir,
+            recursive: doc.recursive,
+            filter: match doc.filter {
+                FilenameFilter::All => String::new(),
+                FilenameFilter::Extension(ext) => ext,
+            },
+            priority: doc.priority,
+        })
+        .collect()
+}
+
+/// 添加一条映射，添加后存在冲突时拒绝修改
+pub async fn add(
+    state: AppState,
+    req: Request<AddWorkspaceMappingReq>,
+) -> AppResult<Response<AddWorkspaceMappingRsp>> {
+    let request_body = req.into_inner();
+    let mut config = get_config(&state, &request_body.workspace_name)?;
+
+    let local_dir = match request_body.local_dir.as_str() {
+        "" => None,
+        dir if dir.ends_with("/") || dir.ends_with("\\") => Some(dir.to_string()),
+        dir => Some(format!("{}{}", dir, path::MAIN_SEPARATOR)),
+    };
+    let mapping = WorkspaceMapping::from_parts(
+        &request_body.depot_wildcard,
+        local_dir.as_deref(),
+        &config.root_dir,
+    )
+    .map_err(|e| AppError::Raw(Status::invalid_argument(e.to_string())))?;
+
+    let index = match request_body.priority {
+        None => config.mappings.len(),
+        Some(priority) if priority >= 0 && priority as usize <= config.mappings.len() => {
+            priority as usize
+        }
+        Some(priority) => {
+            return Err(AppError::Raw(Status::invalid_argument(format!(
+                "Priority {} is out of range 0..={}.",
+                priority,
+                config.mappings.len()
+            ))));
+        }
+    };
+    config.mappings.insert(index, mapping);
+    save_config(&state, &request_body.workspace_name, &config)?;
+
+    Ok(Response::new(AddWorkspaceMappingRsp {
+        mappings: mapping_docs(&config),
+    }))
+}
+
+/// 按优先级删除一条映射，删除后存在冲突时拒绝修改
+pub async fn remove(
+    state: AppState,
+    req: Request<RemoveWorkspaceMappingReq>,
+) -> AppResult<Response<RemoveWorkspaceMappingRsp>> {
+    let request_body = req.into_inner();
+    let mut config = get_config(&state, &request_body.workspace_name)?;
+
+    let priority = request_body.priority;
+    if priority < 0 || priority as usize >= config.mappings.len() {
+        return Err(AppError::Raw(Status::not_found(format!(
+            "Workspace {} has no mapping with priority {}.",
+            request_body.workspace_name, priority
+        ))));
+    }
+    config.mappings.remove(priority as usize);
+    save_config(&state, &request_body.workspace_name, &config)?;
+
+    Ok(Response::new(RemoveWorkspaceMappingRsp {
+        mappings: mapping_docs(&config),
+    }))
+}
+
+pub async fn list(
+    state: AppState,
+    req: Request<ListWorkspaceMappingsReq>,
+) -> AppResult<Response<ListWorkspaceMappingsRsp>> {
+    let request_body = req.into_inner();
+    let config = get_config(&state, &request_body.workspace_name)?;
+
+    Ok(Response::new(ListWorkspaceMappingsRsp {
+        mappings: mapping_docs(&config),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDb;
+    use crv_core::path::basic::LocalDir;
+    use tonic::Code;
+
+    fn state_with_workspace(name: &str) -> (TempDb, AppState) {
+        let db = TempDb::new();
+        let state = db.state();
+        let config = WorkspaceConfig {
+            root_dir: LocalDir::parse("/root/ws/").unwrap(),
+            mappings: vec![],
+        };
+        state
+            .db
+            .create_workspace_pending(name.to_string(), config)
+            .unwrap();
+        state.db.confirm_workspace(name.to_string()).unwrap();
+        (db, state)
+    }
+
+    async fn add_mapping(
+        state: &AppState,
+        depot_wildcard: &str,
+        local_dir: &str,
+    ) -> AppResult<Vec<WorkspaceMappingDoc>> {
+        let rsp = add(
+            state.clone(),
+            Request::new(AddWorkspaceMappingReq {
+                workspace_name: "ws".to_string(),
+                depot_wildcard: depot_wildcard.to_string(),
+                local_dir: local_dir.to_string(),
+                priority: None,
+            }),
+        )
+        .await?;
+        Ok(rsp.into_inner().mappings)
+    }
+
+    #[tokio::test]
+    async fn added_mappings_are_persisted_in_priority_order() {
+        let (_db, state) = state_with_workspace("ws");
+        add_mapping(&state, "//a/b/...", "/root/ws/x")
+            .await
+            .unwrap();
+        add_mapping(&state, "//a/b/c/...", "").await.unwrap();
+
+        let rsp = list(
+            state,
+            Request::new(ListWorkspaceMappingsReq {
+                workspace_name: "ws".to_string(),
+            }),
+        )
+        .await
+        .unwrap()
+        .into_inner();
+        assert_eq!(
+            rsp.mappings,
+            vec![
+                WorkspaceMappingDoc {
+                    depot_wildcard: "//a/b/...".to_string(),
+                    local_dir: "/root/ws/x/".to_string(),
+                    recursive: true,
+                    filter: String::new(),
+                    priority: 0,
+                },
+                WorkspaceMappingDoc {
+                    depot_wildcard: "//a/b/c/...".to_string(),
+                    local_dir: String::new(),
+                    recursive: true,
+                    filter: String::new(),
+                    priority: 1,
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn conflicting_mapping_is_rejected() {
+        let (_db, state) = state_with_workspace("ws");
+        add_mapping(&state, "//a/b/...", "/root/ws/x/")
+            .await
+            .unwrap();
+
+        let err: Status = add_mapping(&state, "//a/b/c/d/...", "/root/ws/x/y/t/")
+            .await
+            .unwrap_err()
+            .into();
+        assert_eq!(err.code(), Code::FailedPrecondition);
+
+        let config = get_config(&state, &"ws".to_string()).unwrap();
+        assert_eq!(config.mappings.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn mapping_outside_root_is_rejected() {
+        let (_db, state) = state_with_workspace("ws");
+        let err: Status = add_mapping(&state, "//a/...", "/tmp/x/")
+            .await
+            .unwrap_err()
+            .into();
+        assert_eq!(err.code(), Code::InvalidArgument);
+    }
+
+    #[tokio::test]
+    async fn remove_deletes_mapping_by_priority() {
+        let (_db, state) = state_with_workspace("ws");
+        add_mapping(&state, "//a/...", "/root/ws/a/").await.unwrap();
+        add_mapping(&state, "//b/...", "/root/ws/b/").await.unwrap();
+
+        let rsp = remove(
+            state.clone(),
+            Request::new(RemoveWorkspaceMappingReq {
+                workspace_name: "ws".to_string(),
+                priority: 0,
+            }),
+        )
+        .await
+        .unwrap()
+        .into_inner();
+        assert_eq!(rsp.mappings.len(), 1);
+        assert_eq!(rsp.mappings[0].depot_wildcard, "//b/...");
+        assert_eq!(rsp.mappings[0].priority, 0);
+
+        let err: Status = remove(
+            state,
+            Request::new(RemoveWorkspaceMappingReq {
+                workspace_name: "ws".to_string(),
+                priority: 1,
+            }),
+        )
+        .await
+        .unwrap_err()
+        .into();
+        assert_eq!(err.code(), Code::NotFound);
+    }
+}
@@ -1,2 +1,8 @@
+pub mod checkpoint;
 pub mod create;
+pub mod delete;
+pub mod describe;
 pub mod list;
+pub mod mapping;
+pub mod switch;
+pub mod validate;
@@ -0,0 +1,431 @@
+use crate::daemon_server::config::RuntimeConfig;
+use crate::daemon_server::db::file::FileMeta;
+use crate::daemon_server::error::{AppError, AppResult};
+use crate::daemon_server::handlers::workspace::checkpoint::{download_file, local_chunk_hashes};
+use crate::daemon_server::handlers::workspace::validate::verify_mappings;
+use crate::daemon_server::state::{AppState, HiveChannel};
+use crate::pb::{SwitchWorkspaceReq, SwitchWorkspaceRsp};
+use crv_core::path::basic::{WorkspaceDir, WorkspacePath};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::fs;
+use tonic::{Request, Response, Status};
+
+/// 切换过程中对本地文件做过的修改，用于失败时按相反顺序回滚
+enum SwitchOp {
+    /// 从 hive 下载的文件
+    Downloaded(String),
+    /// 从原 workspace 复用（移动）过来的文件
+    Moved { from: String, to: String },
+    /// 被暂存起来的待删除或待覆盖的文件
+    Staged { original: String, staged: String },
+}
+
+#[derive(Default)]
+struct SwitchJournal {
+    ops: Vec<SwitchOp>,
+}
+
+impl SwitchJournal {
+    /// 将已存在的文件重命名到同目录下的暂存文件，切换成功后才真正删除
+    async fn stage(&mut self, local_path: &str) -> AppResult<()> {
+        if !Path::new(local_path).exists() {
+            return Ok(());
+        }
+        let staged = format!("{local_path}.crv-switch-{}", uuid::Uuid::new_v4().simple());
+        fs::rename(local_path, &staged)
+            .await
+            .map_err(|e| AppError::Internal(format!("{e}")))?;
+        self.ops.push(SwitchOp::Staged {
+            original: local_path.to_string(),
+            staged,
+        });
+        Ok(())
+    }
+
+    async fn rename(&mut self, from: &str, to: &str) -> AppResult<()> {
+        if let Some(parent) = Path::new(to).parent() {
+            fs::create_dir_all(parent)
+                .await
+                .map_err(|e| AppError::Internal(format!("{e}")))?;
+        }
+        fs::rename(from, to)
+            .await
+            .map_err(|e| AppError::Internal(format!("{e}")))?;
+        self.ops.push(SwitchOp::Moved {
+            from: from.to_string(),
+            to: to.to_string(),
+        });
+        Ok(())
+    }
+
+    async fn download(
+        &mut self,
+        channel: HiveChannel,
+        local_path: &str,
+        chunk_hashes: &[String],
+    ) -> AppResult<()> {
+        // 先记录再下载，下载到一半失败时也能清理掉残缺的文件
+        self.ops.push(SwitchOp::Downloaded(local_path.to_string()));
+        download_file(channel, local_path, chunk_hashes).await
+    }
+
+    /// 回滚是尽力而为的，单个文件恢复失败不影响其他文件的恢复
+    async fn rollback(self) {
+        for op in self.ops.into_iter().rev() {
+            let _ = match op {
+                SwitchOp::Downloaded(local_path) => fs::remove_file(local_path).await,
+                SwitchOp::Moved { from, to } => fs::rename(to, from).await,
+                SwitchOp::Staged { original, staged } => fs::rename(staged, original).await,
+            };
+        }
+    }
+
+    /// 切换成功，清理暂存文件
+    async fn commit(self) {
+        for op in self.ops {
+            if let SwitchOp::Staged { staged, .. } = op {
+                let _ = fs::remove_file(staged).await;
+            }
+        }
+    }
+}
+
+pub async fn handle(
+    state: AppState,
+    req: Request<SwitchWorkspaceReq>,
+) -> AppResult<Response<SwitchWorkspaceRsp>> {
+    let runtime_config = RuntimeConfig::from_req(&req)?;
+    let request_body = req.into_inner();
+    let target = request_body.workspace_name;
+
+    let target_meta = state
+        .db
+        .get_confirmed_workspace_meta(&target)?
+        .ok_or(AppError::Raw(Status::not_found(format!(
+            "Workspace {} not found.",
+            target
+        ))))?;
+    verify_mappings(&target, &target_meta.config)?;
+
+    let previous = state.db.get_active_workspace()?;
+    if previous.as_deref() == Some(target.as_str()) {
+        return Ok(Response::new(SwitchWorkspaceRsp {
+            previous_workspace: target,
+            ..Default::default()
+        }));
+    }
+    // 原 workspace 可能已经被删除，此时不需要保存和清理它的文件
+    let previous_alive = match &previous {
+        Some(name) if state.db.get_confirmed_workspace_meta(name)?.is_some() => Some(name.clone()),
+        _ => None,
+    };
+
+    // 1. 保存原 workspace 的 active file 状态
+    let checkpoint_name = match &previous_alive {
+        Some(name) => {
+            let timestamp = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_millis())
+                .unwrap_or_default();
+            let checkpoint_name = format!("switch-to-{target}-{timestamp}");
+            state.db.save_checkpoint(name, &checkpoint_name)?;
+            checkpoint_name
+        }
+        None => String::new(),
+    };
+
+    // 2. 激活新 workspace
+    if let Err(e) = state
+        .db
+        .switch_active_workspace(previous.as_deref(), Some(&target))
+    {
+        if let Some(name) = &previous_alive {
+            let _ = state.db.delete_checkpoint(name, &checkpoint_name);
+        }
+        return Err(e.into());
+    }
+
+    // 3 ~ 5. 计算两个 workspace 已同步文件的差异并更新本地文件，任何一步失败都回滚
+    let channel = state
+        .hive_channel
+        .get_channel(&runtime_config.remote_addr.value)?;
+    let mut journal = SwitchJournal::default();
+    let result = apply_switch(
+        &state,
+        channel,
+        previous_alive.as_deref(),
+        &target,
+        &mut journal,
+    )
+    .await;
+
+    match result {
+        Ok(mut rsp) => {
+            journal.commit().await;
+            rsp.previous_workspace = previous.unwrap_or_default();
+            rsp.checkpoint_name = checkpoint_name;
+            Ok(Response::new(rsp))
+        }
+        Err(e) => {
+            journal.rollback().await;
+            let _ = state
+                .db
+                .switch_active_workspace(Some(&target), previous.as_deref());
+            if let Some(name) = &previous_alive {
+                let _ = state.db.delete_checkpoint(name, &checkpoint_name);
+            }
+            Err(e)
+        }
+    }
+}
+
+fn workspace_root(workspace_name: &str) -> WorkspaceDir {
+    WorkspaceDir {
+        workspace_name: workspace_name.to_string(),
+        dirs: vec![],
+    }
+}
+
+fn opened_paths(state: &AppState, workspace_name: &str) -> AppResult<HashSet<String>> {
+    Ok(state
+        .db
+        .get_active_file_under_dir(&workspace_root(workspace_name))?
+        .into_iter()
+        .map(|(path, _)| path.to_custom_string())
+        .collect())
+}
+
+async fn apply_switch(
+    state: &AppState,
+    channel: HiveChannel,
+    previous: Option<&str>,
+    target: &str,
+    journal: &mut SwitchJournal,
+) -> AppResult<SwitchWorkspaceRsp> {
+    let mut rsp = SwitchWorkspaceRsp::default();
+
+    // 3. 原 workspace 中未打开的本地文件都不再属于新 workspace，内容可以按 chunk hash 复用
+    let mut removals: Vec<(WorkspacePath, String)> = vec![];
+    let mut reusable: HashMap<Vec<String>, Vec<String>> = HashMap::new();
+    if let Some(previous) = previous {
+        let previous_opened = opened_paths(state, previous)?;
+        for (path, meta) in state
+            .db
+            .get_file_meta_under_dir(&workspace_root(previous))?
+        {
+            if previous_opened.contains(&path.to_custom_string()) {
+                continue;
+            }
+            let local_path = meta.location.local_path.to_local_path_string();
+            if let Some(hashes) = local_chunk_hashes(&local_path).await {
+                reusable.entry(hashes).or_default().push(local_path.clone());
+                removals.push((path, local_path));
+            }
+        }
+    }
+
+    // 4. 物化新 workspace 的同步快照，打开中的文件保留本地内容
+    let target_opened = opened_paths(state, target)?;
+    let mut moved = HashSet::new();
+    for (path, meta) in state.db.get_file_meta_under_dir(&workspace_root(target))? {
+        if target_opened.contains(&path.to_custom_string()) {
+            continue;
+        }
+        let FileMeta {
+            location,
+            chunk_hashes,
+            ..
+        } = meta;
+        let local_path = location.local_path.to_local_path_string();
+        if local_chunk_hashes(&local_path).await.as_ref() == Some(&chunk_hashes) {
+            continue;
+        }
+
+        journal.stage(&local_path).await?;
+        match reusable.get_mut(&chunk_hashes).and_then(|x| x.pop()) {
+            Some(from) => {
+                journal.rename(&from, &local_path).await?;
+                moved.insert(from);
+                rsp.reused_paths.push(path.to_custom_string());
+            }
+            None => {
+                journal
+                    .download(channel.clone(), &local_path, &chunk_hashes)
+                    .await?;
+                rsp.downloaded_paths.push(path.to_custom_string());
+            }
+        }
+    }
+
+    // 5. 移除原 workspace 中剩下的文件
+    for (path, local_path) in removals {
+        if moved.contains(&local_path) {
+            continue;
+        }
+        journal.stage(&local_path).await?;
+        rsp.removed_paths.push(path.to_custom_string());
+    }
+
+    Ok(rsp)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::daemon_server::config::{RuntimeConfigItem, RuntimeConfigSource};
+    use crate::daemon_server::db::active_file::Action;
+    use crate::daemon_server::db::file::{FileLocation, FileRevision};
+    use crate::test_support::TempDb;
+    use crv_core::path::basic::{DepotPath, LocalDir, LocalPath};
+    use crv_core::repository::compute_chunk_hash;
+    use crv_core::workspace::entity::WorkspaceConfig;
+    use std::path::PathBuf;
+
+    struct Fixture {
+        db: TempDb,
+        state: AppState,
+    }
+
+    impl Fixture {
+        fn new() -> Self {
+            let db = TempDb::new();
+            let root = db.root();
+            let state = db.state();
+            for name in ["a", "b"] {
+                std::fs::create_dir_all(root.join(name)).unwrap();
+                let config = WorkspaceConfig {
+                    root_dir: LocalDir::parse(&format!("{}/{name}/", root.display())).unwrap(),
+                    mappings: vec![],
+                };
+                state
+                    .db
+                    .create_workspace_pending(name.to_string(), config)
+                    .unwrap();
+                state.db.confirm_workspace(name.to_string()).unwrap();
+            }
+            state.db.switch_active_workspace(None, Some("a")).unwrap();
+            Self { db, state }
+        }
+
+        fn local(&self, workspace_name: &str, file: &str) -> PathBuf {
+            self.db.root().join(workspace_name).join(file)
+        }
+
+        /// 记录一个已同步的文件，`on_disk` 为 true 时同时写入本地文件
+        fn synced(&self, workspace_name: &str, file: &str, content: &str, on_disk: bool) {
+            let local_path = self.local(workspace_name, file);
+            if on_disk {
+                std::fs::write(&local_path, content).unwrap();
+            }
+            let chunk_hashes = if content.is_empty() {
+                vec![]
+            } else {
+                vec![hex::encode(compute_chunk_hash(content.as_bytes()))]
+            };
+            let workspace_path =
+                WorkspacePath::parse(&format!("//{workspace_name}/{file}")).unwrap();
+            let meta = FileMeta {
+                location: FileLocation {
+                    local_path: LocalPath::parse(&local_path.to_string_lossy()).unwrap(),
+                    workspace_path: workspace_path.clone(),
+                    depot_path: DepotPath::parse(&format!("//depot/{file}")).unwrap(),
+                },
+                current_revision: FileRevision {
+                    generation: 1,
+                    revision: 1,
+                },
+                changelist_id: 1,
+                size: content.len() as i64,
+                chunk_hashes,
+            };
+            self.state.db.set_file_meta(workspace_path, meta).unwrap();
+        }
+
+        fn request(&self, workspace_name: &str) -> Request<SwitchWorkspaceReq> {
+            let mut runtime_config = RuntimeConfig::default();
+            // 不可达的 hive，任何下载都会失败
+            runtime_config.remote_addr = RuntimeConfigItem {
+                value: "http://127.0.0.1:1".to_string(),
+                source: RuntimeConfigSource::Override,
+            };
+            let mut req = Request::new(SwitchWorkspaceReq {
+                workspace_name: workspace_name.to_string(),
+            });
+            req.extensions_mut().insert(runtime_config);
+            req
+        }
+    }
+
+    #[tokio::test]
+    async fn switch_reuses_and_removes_files() {
+        let fixture = Fixture::new();
+        fixture.synced("a", "shared.txt", "hello", true);
+        fixture.synced("a", "old.txt", "old", true);
+        fixture.synced("a", "wip.txt", "wip", true);
+        fixture
+            .state
+            .db
+            .set_active_file_action(WorkspacePath::parse("//a/wip.txt").unwrap(), Action::Edit)
+            .unwrap();
+        fixture.synced("b", "shared.txt", "hello", false);
+
+        let rsp = handle(fixture.state.clone(), fixture.request("b"))
+            .await
+            .unwrap()
+            .into_inner();
+
+        assert_eq!(rsp.previous_workspace, "a");
+        assert_eq!(rsp.reused_paths, vec!["//b/shared.txt"]);
+        assert_eq!(rsp.removed_paths, vec!["//a/old.txt"]);
+        assert!(rsp.downloaded_paths.is_empty());
+
+        assert_eq!(
+            std::fs::read_to_string(fixture.local("b", "shared.txt")).unwrap(),
+            "hello"
+        );
+        assert!(!fixture.local("a", "shared.txt").exists());
+        assert!(!fixture.local("a", "old.txt").exists());
+        assert!(fixture.local("a", "wip.txt").exists());
+        assert_eq!(
+            fixture.state.db.get_active_workspace().unwrap().as_deref(),
+            Some("b")
+        );
+        let checkpoints = fixture.state.db.list_checkpoints("a").unwrap();
+        assert_eq!(checkpoints.len(), 1);
+        assert_eq!(checkpoints[0].0, rsp.checkpoint_name);
+        // 暂存文件已清理
+        assert_eq!(
+            std::fs::read_dir(fixture.db.root().join("a"))
+                .unwrap()
+                .count(),
+            1
+        );
+    }
+
+    #[tokio::test]
+    async fn switch_rolls_back_when_download_fails() {
+        let fixture = Fixture::new();
+        fixture.synced("a", "shared.txt", "hello", true);
+        fixture.synced("a", "old.txt", "old", true);
+        fixture.synced("b", "shared.txt", "hello", false);
+        fixture.synced("b", "unique.txt", "only in hive", false);
+
+        let result = handle(fixture.state.clone(), fixture.request("b")).await;
+        assert!(result.is_err());
+
+        assert_eq!(
+            std::fs::read_to_string(fixture.local("a", "shared.txt")).unwrap(),
+            "hello"
+        );
+        assert!(fixture.local("a", "old.txt").exists());
+        assert!(!fixture.local("b", "shared.txt").exists());
+        assert!(!fixture.local("b", "unique.txt").exists());
+        assert_eq!(
+            fixture.state.db.get_active_workspace().unwrap().as_deref(),
+            Some("a")
+        );
+        assert!(fixture.state.db.list_checkpoints("a").unwrap().is_empty());
+    }
+}
@@ -0,0 +1,162 @@
+use crate::daemon_server::error::{AppError, AppResult};
+use crate::daemon_server::state::AppState;
+use crate::pb::{ValidateWorkspaceReq, ValidateWorkspaceRsp};
+use crv_core::workspace::conflict_detector_v2::ConflictDetector;
+use crv_core::workspace::entity::WorkspaceConfig;
+use tonic::{Request, Response, Status};
+
+/// 仅执行映射冲突检测，不触发同步
+pub async fn handle(
+    state: AppState,
+    req: Request<ValidateWorkspaceReq>,
+) -> AppResult<Response<ValidateWorkspaceRsp>> {
+    let request_body = req.into_inner();
+
+    let workspace_meta = state
+        .db
+        .get_confirmed_workspace_meta(&request_body.workspace_name)?
+        .ok_or(AppError::Raw(Status::not_found(format!(
+            "Workspace {} not found.",
+            request_body.workspace_name
+        ))))?;
+
+    Ok(Response::new(ValidateWorkspaceRsp {
+        conflicting_local_paths: find_mapping_conflicts(&workspace_meta.config),
+    }))
+}
+
+/// 返回 workspace 映射中所有存在冲突的本地路径（unix 风格）
+pub fn find_mapping_conflicts(config: &WorkspaceConfig) -> Vec<String> {
+    let detector = ConflictDetector::new(config.path_mappings());
+    if detector.verify_mappings().is_ok() {
+        return vec![];
+    }
+    detector
+        .find_conflicts()
+        .into_iter()
+        .map(|local_path| format!("/{local_path}"))
+        .collect()
+}
+
+/// 同步前的校验，映射存在冲突时直接失败，并在错误中列出冲突的本地路径
+pub fn verify_mappings(workspace_name: &str, config: &WorkspaceConfig) -> AppResult<()> {
+    let conflicts = find_mapping_conflicts(config);
+    if conflicts.is_empty() {
+        return Ok(());
+    }
+    Err(AppError::Raw(Status::failed_precondition(format!(
+        "Workspace {} has conflicting mappings on local paths: {}",
+        workspace_name,
+        conflicts.join(", ")
+    ))))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::daemon_server::config::RuntimeConfig;
+    use crate::daemon_server::handlers::file::sync;
+    use crate::pb::SyncReq;
+    use crate::test_support::TempDb;
+    use crv_core::path::basic::{FilenameWildcard, LocalDir, RangeDepotWildcard};
+    use crv_core::workspace::entity::{FolderMapping, IncludeMapping, WorkspaceMapping};
+
+    fn folder(depot_dirs: &[&str], local: &str) -> WorkspaceMapping {
+        WorkspaceMapping::Include(IncludeMapping::Folder(FolderMapping {
+            depot_folder: RangeDepotWildcard {
+                dirs: depot_dirs.iter().map(|x| x.to_string()).collect(),
+                recursive: true,
+                wildcard: FilenameWildcard::All,
+            },
+            local_folder: LocalDir::parse(local).unwrap(),
+        }))
+    }
+
+    /// 直接写入一份带冲突的配置，模拟数据库中已经存在的非法映射
+    fn state_with_workspace(name: &str, mappings: Vec<WorkspaceMapping>) -> (TempDb, AppState) {
+        let db = TempDb::new();
+        let state = db.state();
+        let config = WorkspaceConfig {
+            root_dir: LocalDir::parse("/root/ws/").unwrap(),
+            mappings,
+        };
+        state
+            .db
+            .create_workspace_pending(name.to_string(), config)
+            .unwrap();
+        state.db.confirm_workspace(name.to_string()).unwrap();
+        (db, state)
+    }
+
+    #[tokio::test]
+    async fn validate_reports_conflicting_local_paths() {
+        let (_db, state) = state_with_workspace(
+            "ws",
+            vec![
+                folder(&["a", "b"], "/root/ws/x/"),
+                folder(&["a", "b", "c", "d"], "/root/ws/x/y/t/"),
+            ],
+        );
+
+        let rsp = handle(
+            state,
+            Request::new(ValidateWorkspaceReq {
+                workspace_name: "ws".to_string(),
+            }),
+        )
+        .await
+        .unwrap()
+        .into_inner();
+        assert_eq!(rsp.conflicting_local_paths, vec!["/root/ws/x/y/t/"]);
+    }
+
+    #[tokio::test]
+    async fn validate_accepts_consistent_mappings() {
+        let (_db, state) = state_with_workspace(
+            "ws",
+            vec![
+                folder(&["a", "b"], "/root/ws/x/"),
+                folder(&["a", "b", "c", "d"], "/root/ws/x/c/d/"),
+            ],
+        );
+
+        let rsp = handle(
+            state,
+            Request::new(ValidateWorkspaceReq {
+                workspace_name: "ws".to_string(),
+            }),
+        )
+        .await
+        .unwrap()
+        .into_inner();
+        assert!(rsp.conflicting_local_paths.is_empty());
+    }
+
+    #[tokio::test]
+    async fn sync_fails_fast_on_mapping_conflict() {
+        let (_db, state) = state_with_workspace(
+            "ws",
+            vec![
+                folder(&["a", "b"], "/root/ws/x/"),
+                folder(&["a", "b", "c", "d"], "/root/ws/x/y/t/"),
+            ],
+        );
+
+        // hive 地址不可达，若校验没有拦截则会在获取文件树时报出其他错误
+        let mut req = Request::new(SyncReq {
+            workspace_name: "ws".to_string(),
+            paths: vec!["//ws/...".to_string()],
+            force: false,
+            changelist_id: 0,
+            as_of_millis: 0,
+            offline: false,
+        });
+        req.extensions_mut().insert(RuntimeConfig::default());
+
+        let Err(AppError::Raw(status)) = sync::handle(state, req).await else {
+            panic!("sync should be rejected by mapping validation");
+        };
+        assert_eq!(status.code(), tonic::Code::FailedPrecondition);
+        assert!(status.message().contains("/root/ws/x/y/t/"));
+    }
+}
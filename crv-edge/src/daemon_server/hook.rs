@@ -0,0 +1,41 @@
+//! 提交前执行的本地钩子脚本
+use crate::daemon_server::error::{AppError, AppResult};
+use std::path::Path;
+use tokio::process::Command;
+
+/// 执行提交前钩子，钩子以非零状态退出时返回其 stderr 作为错误信息
+///
+/// 通过环境变量向钩子传递本次提交的信息：
+/// - `CRV_BRANCH`：提交的目标分支，为空表示默认分支；
+/// - `CRV_CHANGELIST_DESC`：提交描述；
+/// - `CRV_FILES`：待提交文件的本地路径，以换行分隔。
+pub async fn run_pre_submit_hook(
+    hook: &Path,
+    branch: &str,
+    description: &str,
+    files: &[String],
+) -> AppResult<()> {
+    let output = Command::new(hook)
+        .env("CRV_BRANCH", branch)
+        .env("CRV_CHANGELIST_DESC", description)
+        .env("CRV_FILES", files.join("\n"))
+        .kill_on_drop(true)
+        .output()
+        .await
+        .map_err(|e| {
+            AppError::Config(format!(
+                "Failed to run pre-submit hook {}: {e}",
+                hook.display()
+            ))
+        })?;
+
+    if output.status.success() {
+        return Ok(());
+    }
+    let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+    Err(AppError::PreSubmitHookFailed(if stderr.is_empty() {
+        format!("hook exited with {}", output.status)
+    } else {
+        stderr
+    }))
+}
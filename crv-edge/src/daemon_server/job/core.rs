@@ -1,3 +1,7 @@
+use crate::daemon_server::db::DbManager;
+use crate::daemon_server::db::job::JobRecord;
+use crate::daemon_server::middleware::request_id;
+use bincode::{Decode, Encode};
 use futures::future::BoxFuture;
 use prost::Message;
 use serde::{Deserialize, Serialize};
@@ -9,7 +13,7 @@ use tokio::task::JoinSet;
 
 pub type JobId = String;
 
-#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, Encode, Decode)]
 pub enum WorkerProtocol {
     /// All workers must succeed. If any fails, the job fails immediately.
     And,
@@ -17,15 +21,19 @@ pub enum WorkerProtocol {
     Or,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, Encode, Decode)]
 pub enum JobRetentionPolicy {
     /// Immediately remove job when finished.
     Immediate,
     /// Retain job for specified seconds after finished.
+    /// The persisted record is removed together with the in-memory job.
     Retain(u64),
+    /// Retain job for specified seconds after finished, surviving daemon restarts.
+    /// The persisted record is removed from RocksDB once the duration has elapsed.
+    EvictAfterSecs(u64),
 }
 
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Encode, Decode)]
 pub enum JobStatus {
     Pending,
     Running,
@@ -42,7 +50,7 @@ pub enum JobEvent {
 }
 
 /// Job 的持久化数据部分
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Encode, Decode)]
 pub struct JobData {
     pub id: JobId,
     pub status: JobStatus,
@@ -70,6 +78,7 @@ pub struct Job {
     cleanup_tx: mpsc::UnboundedSender<String>,
     retention_policy: JobRetentionPolicy,
     cancel_notify: Arc<Notify>,
+    db: Arc<DbManager>,
 }
 
 impl Job {
@@ -80,9 +89,50 @@ impl Job {
         protocol: WorkerProtocol,
         retention_policy: JobRetentionPolicy,
         cleanup_tx: mpsc::UnboundedSender<String>,
+        db: Arc<DbManager>,
     ) -> Self {
-        let (tx, _) = broadcast::channel(1024);
         let now = current_timestamp();
+        Self::from_data(
+            JobData {
+                id,
+                status: JobStatus::Pending,
+                created_at: now,
+                updated_at: now,
+                request_payload,
+            },
+            buffer_policy,
+            protocol,
+            retention_policy,
+            cleanup_tx,
+            db,
+        )
+    }
+
+    /// 从持久化的记录重建 Job，重建的 Job 没有 worker，需要重新添加 worker 后才能启动
+    pub fn restore(
+        record: JobRecord,
+        cleanup_tx: mpsc::UnboundedSender<String>,
+        db: Arc<DbManager>,
+    ) -> Self {
+        Self::from_data(
+            record.data,
+            MessageStoragePolicy::None,
+            record.protocol,
+            record.retention_policy,
+            cleanup_tx,
+            db,
+        )
+    }
+
+    fn from_data(
+        data: JobData,
+        buffer_policy: MessageStoragePolicy,
+        protocol: WorkerProtocol,
+        retention_policy: JobRetentionPolicy,
+        cleanup_tx: mpsc::UnboundedSender<String>,
+        db: Arc<DbManager>,
+    ) -> Self {
+        let (tx, _) = broadcast::channel(1024);
 
         let (message_buffer, buffer_capacity) = match buffer_policy {
             MessageStoragePolicy::None => (None, 0),
@@ -92,13 +142,7 @@ impl Job {
         };
 
         Self {
-            data: RwLock::new(JobData {
-                id,
-                status: JobStatus::Pending,
-                created_at: now,
-                updated_at: now,
-                request_payload,
-            }),
+            data: RwLock::new(data),
             tx,
             message_buffer,
             buffer_capacity,
@@ -107,6 +151,23 @@ impl Job {
             cleanup_tx,
             retention_policy,
             cancel_notify: Arc::new(Notify::new()),
+            db,
+        }
+    }
+
+    pub fn retention_policy(&self) -> JobRetentionPolicy {
+        self.retention_policy
+    }
+
+    /// 将 Job 当前的状态写入数据库，调用方需持有 `data` 的锁，保证写入的顺序与状态变化的顺序一致
+    pub(super) fn persist(&self, data: &JobData) {
+        let record = JobRecord {
+            data: data.clone(),
+            protocol: self.protocol,
+            retention_policy: self.retention_policy,
+        };
+        if let Err(e) = self.db.set_job(&record) {
+            println!("[JobManager] Failed to persist job {}: {}", data.id, e);
         }
     }
 
@@ -114,7 +175,10 @@ impl Job {
     where
         F: std::future::Future<Output = Result<(), String>> + Send + 'static,
     {
-        self.pending_workers.lock().unwrap().push(Box::pin(future));
+        self.pending_workers
+            .lock()
+            .unwrap()
+            .push(Box::pin(request_id::inherit(future)));
     }
 
     pub fn start(self: Arc<Self>) {
@@ -124,6 +188,7 @@ impl Job {
         }
         data.status = JobStatus::Running;
         data.updated_at = current_timestamp();
+        self.persist(&data);
         drop(data);
 
         let workers = std::mem::take(&mut *self.pending_workers.lock().unwrap());
@@ -232,6 +297,7 @@ impl Job {
         }
         data.status = new_status;
         data.updated_at = current_timestamp();
+        self.persist(&data);
         true
     }
 
@@ -244,7 +310,7 @@ impl Job {
             JobRetentionPolicy::Immediate => {
                 let _ = tx.send(id);
             }
-            JobRetentionPolicy::Retain(secs) => {
+            JobRetentionPolicy::Retain(secs) | JobRetentionPolicy::EvictAfterSecs(secs) => {
                 tokio::spawn(async move {
                     tokio::time::sleep(tokio::time::Duration::from_secs(secs)).await;
                     let _ = tx.send(id);
@@ -283,7 +349,7 @@ impl Job {
     }
 }
 
-fn current_timestamp() -> u64 {
+pub(super) fn current_timestamp() -> u64 {
     SystemTime::now()
         .duration_since(UNIX_EPOCH)
         .unwrap()
@@ -1,51 +1,118 @@
+use super::core::{
+    Job, JobId, JobRetentionPolicy, JobStatus, MessageStoragePolicy, WorkerProtocol,
+    current_timestamp,
+};
+use crate::daemon_server::db::DbManager;
 use std::collections::HashMap;
 use std::sync::{Arc, RwLock};
-use uuid::Uuid;
 use tokio::sync::mpsc;
-use super::core::{Job, JobId, MessageStoragePolicy, WorkerProtocol, JobRetentionPolicy};
+use uuid::Uuid;
 
 pub struct JobManager {
     jobs: Arc<RwLock<HashMap<JobId, Arc<Job>>>>,
     cleanup_tx: mpsc::UnboundedSender<String>,
+    db: Arc<DbManager>,
 }
 
 impl JobManager {
-    pub fn new() -> Self {
+    /// 创建 JobManager，并恢复数据库中持久化的 Job
+    pub fn new(db: Arc<DbManager>) -> Self {
         let jobs = Arc::new(RwLock::new(HashMap::new()));
         let jobs_clone = jobs.clone();
-        let (tx, mut rx) = mpsc::unbounded_channel();
-        
+        let db_clone = db.clone();
+        let (tx, mut rx) = mpsc::unbounded_channel::<String>();
+
         tokio::spawn(async move {
             while let Some(id) = rx.recv().await {
                 if jobs_clone.write().unwrap().remove(&id).is_some() {
                     println!("[JobManager] Auto-cleaned job: {}", id);
                 }
+                if let Err(e) = db_clone.delete_job(&id) {
+                    println!("[JobManager] Failed to delete persisted job {}: {}", id, e);
+                }
             }
         });
 
-        Self {
+        let manager = Self {
             jobs,
             cleanup_tx: tx,
+            db,
+        };
+        manager.recover();
+        manager
+    }
+
+    /// 恢复上次 daemon 退出时未完成的 Job。
+    ///
+    /// 运行中的 Job 的 worker 已经丢失，会被重置为 Pending，需要重新添加 worker 后启动；
+    /// 已结束的 Job 只有 `EvictAfterSecs` 策略且未到期的会被恢复，其余的记录直接删除。
+    fn recover(&self) {
+        let records = match self.db.list_jobs() {
+            Ok(records) => records,
+            Err(e) => {
+                println!("[JobManager] Failed to load persisted jobs: {}", e);
+                return;
+            }
+        };
+
+        let now = current_timestamp();
+        for mut record in records {
+            let id = record.data.id.clone();
+            match record.data.status {
+                JobStatus::Pending | JobStatus::Running => {
+                    record.data.status = JobStatus::Pending;
+                    record.data.updated_at = now;
+                    if let Err(e) = self.db.set_job(&record) {
+                        println!("[JobManager] Failed to requeue job {}: {}", id, e);
+                    }
+                    println!("[JobManager] Recovered job: {}", id);
+                }
+                JobStatus::Completed | JobStatus::Failed(_) | JobStatus::Cancelled => {
+                    let expire_at = match record.retention_policy {
+                        JobRetentionPolicy::EvictAfterSecs(secs) => record.data.updated_at + secs,
+                        _ => now,
+                    };
+                    if expire_at <= now {
+                        if let Err(e) = self.db.delete_job(&id) {
+                            println!("[JobManager] Failed to delete persisted job {}: {}", id, e);
+                        }
+                        continue;
+                    }
+
+                    let tx = self.cleanup_tx.clone();
+                    let cleanup_id = id.clone();
+                    let remaining = tokio::time::Duration::from_secs(expire_at - now);
+                    tokio::spawn(async move {
+                        tokio::time::sleep(remaining).await;
+                        let _ = tx.send(cleanup_id);
+                    });
+                }
+            }
+
+            let job = Job::restore(record, self.cleanup_tx.clone(), self.db.clone());
+            self.jobs.write().unwrap().insert(id, Arc::new(job));
         }
     }
 
     pub fn create_job(
-        &self, 
-        request_payload: Option<String>, 
-        buffer_policy: MessageStoragePolicy, 
+        &self,
+        request_payload: Option<String>,
+        buffer_policy: MessageStoragePolicy,
         protocol: WorkerProtocol,
         retention_policy: JobRetentionPolicy,
     ) -> Arc<Job> {
         let id = Uuid::new_v4().to_string();
         println!("[JobManager] Creating job: {}", id);
         let job = Arc::new(Job::new(
-            id.clone(), 
-            request_payload, 
-            buffer_policy, 
-            protocol, 
+            id.clone(),
+            request_payload,
+            buffer_policy,
+            protocol,
             retention_policy,
-            self.cleanup_tx.clone()
+            self.cleanup_tx.clone(),
+            self.db.clone(),
         ));
+        job.persist(&job.data.read().unwrap());
         self.jobs.write().unwrap().insert(id.clone(), job.clone());
         job
     }
@@ -53,4 +120,113 @@ impl JobManager {
     pub fn get_job(&self, id: &str) -> Option<Arc<Job>> {
         self.jobs.read().unwrap().get(id).cloned()
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDb;
+    use std::time::Duration;
+
+    async fn wait_for_status(job: &Job, status: JobStatus) {
+        for _ in 0..100 {
+            if job.data.read().unwrap().status == status {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("job did not reach status {:?}", status);
+    }
+
+    #[tokio::test]
+    async fn running_job_is_requeued_as_pending_after_crash() {
+        let db = TempDb::new();
+        let manager = JobManager::new(db.db());
+        let job = manager.create_job(
+            Some("payload".to_string()),
+            MessageStoragePolicy::None,
+            WorkerProtocol::And,
+            JobRetentionPolicy::Immediate,
+        );
+        let id = job.data.read().unwrap().id.clone();
+        job.add_worker(std::future::pending());
+        job.clone().start();
+        assert_eq!(
+            db.get_job(&id).unwrap().unwrap().data.status,
+            JobStatus::Running
+        );
+
+        // 模拟崩溃：丢弃 JobManager 后用同一个数据库重新创建
+        drop(job);
+        drop(manager);
+        let manager = JobManager::new(db.db());
+
+        let recovered = manager.get_job(&id).unwrap();
+        let data = recovered.data.read().unwrap().clone();
+        assert_eq!(data.status, JobStatus::Pending);
+        assert_eq!(data.request_payload.as_deref(), Some("payload"));
+        assert_eq!(
+            db.get_job(&id).unwrap().unwrap().data.status,
+            JobStatus::Pending
+        );
+    }
+
+    #[tokio::test]
+    async fn recovered_job_can_be_restarted_and_is_removed_when_completed() {
+        let db = TempDb::new();
+        let id = {
+            let manager = JobManager::new(db.db());
+            let job = manager.create_job(
+                None,
+                MessageStoragePolicy::None,
+                WorkerProtocol::And,
+                JobRetentionPolicy::Immediate,
+            );
+            job.data.read().unwrap().id.clone()
+        };
+
+        let manager = JobManager::new(db.db());
+        let job = manager.get_job(&id).unwrap();
+        job.add_worker(async { Ok(()) });
+        job.clone().start();
+        wait_for_status(&job, JobStatus::Completed).await;
+
+        for _ in 0..100 {
+            if manager.get_job(&id).is_none() && db.get_job(&id).unwrap().is_none() {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("completed job was not cleaned up");
+    }
+
+    #[tokio::test]
+    async fn finished_jobs_are_recovered_only_until_eviction() {
+        let db = TempDb::new();
+        let manager = JobManager::new(db.db());
+        let create = |policy| {
+            let job = manager.create_job(
+                None,
+                MessageStoragePolicy::None,
+                WorkerProtocol::And,
+                policy,
+            );
+            job.complete();
+            job.data.read().unwrap().id.clone()
+        };
+        let retained = create(JobRetentionPolicy::EvictAfterSecs(3600));
+        let expired = create(JobRetentionPolicy::EvictAfterSecs(0));
+        let in_memory_only = create(JobRetentionPolicy::Retain(3600));
+        drop(manager);
+
+        let manager = JobManager::new(db.db());
+        let job = manager.get_job(&retained).unwrap();
+        assert_eq!(job.data.read().unwrap().status, JobStatus::Completed);
+        assert!(db.get_job(&retained).unwrap().is_some());
+
+        for id in [expired, in_memory_only] {
+            assert!(manager.get_job(&id).is_none());
+            assert!(db.get_job(&id).unwrap().is_none());
+        }
+    }
+}
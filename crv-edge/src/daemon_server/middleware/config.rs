@@ -1,7 +1,11 @@
 use crate::daemon_server::config::{RuntimeConfig, RuntimeConfigOverride, RuntimeConfigSource};
 use crate::daemon_server::state::AppState;
+use std::path::Path;
 use tonic::{Request, Status};
 
+/// 调用方的工作目录，edge 会从这里向上查找 `.crvconfig`。使用二进制元数据以支持非 ASCII 路径
+pub const CWD_HEADER: &str = "x-crv-cwd-bin";
+
 pub fn call(state: AppState, mut request: Request<()>) -> Result<Request<()>, Status> {
     // 1. 【读持久化配置】 从 RocksDB 读取配置
     // 如果 DB IO 很慢，会阻塞 executor 线程，但在本地 Daemon + RocksDB 场景下通常是微秒级，可接受。
@@ -12,6 +16,17 @@ pub fn call(state: AppState, mut request: Request<()>) -> Result<Request<()>, St
         .map_err(|e| Status::internal(format!("Get error {} when load config from db.", e)))?;
     final_config.merge(runtime_config_override, RuntimeConfigSource::Set);
 
+    // 调用方工作目录中的 `.crvconfig` 只对本次请求生效
+    if let Some(cwd) = request.metadata().get_bin(CWD_HEADER) {
+        let cwd = cwd
+            .to_bytes()
+            .map_err(|e| Status::invalid_argument(format!("Invalid {CWD_HEADER}: {e}")))?;
+        let cwd = String::from_utf8_lossy(&cwd).to_string();
+        let file_override = RuntimeConfigOverride::from_crvconfig_files(Path::new(&cwd))
+            .map_err(|e| Status::invalid_argument(e.to_string()))?;
+        final_config.merge(file_override, RuntimeConfigSource::File);
+    }
+
     // 2. 【读临时覆盖】 检查 Metadata (Headers)
     // 假设 CLI 发送请求时，将覆盖参数序列化为 JSON 放在 "x-crv-config-override" 头里
     if let Some(val_bytes) = request.metadata().get("x-crv-config-override") {
@@ -32,3 +47,84 @@ pub fn call(state: AppState, mut request: Request<()>) -> Result<Request<()>, St
 
     Ok(request)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::daemon_server::config::CRVCONFIG_FILE_NAME;
+    use crate::test_support::{TempDb, TempDir};
+    use tonic::metadata::MetadataValue;
+
+    fn runtime_config(state: &AppState, cwd: Option<&Path>) -> RuntimeConfig {
+        let mut request = Request::new(());
+        if let Some(cwd) = cwd {
+            request.metadata_mut().insert_bin(
+                CWD_HEADER,
+                MetadataValue::from_bytes(cwd.to_string_lossy().as_bytes()),
+            );
+        }
+        RuntimeConfig::from_req(&call(state.clone(), request).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn crvconfig_above_cwd_overrides_stored_config_for_one_request() {
+        let db = TempDb::new();
+        let state = db.state();
+        state
+            .db
+            .set_runtime_config("remote_addr", "http://stored:34560")
+            .unwrap();
+        state.db.set_runtime_config("editor", "vim").unwrap();
+
+        let project_dir = TempDir::new();
+        let project = project_dir.path();
+        let cwd = project.join("src").join("module");
+        std::fs::create_dir_all(&cwd).unwrap();
+        std::fs::write(
+            project.join(CRVCONFIG_FILE_NAME),
+            "remote_addr = \"http://project:34560\"\neditor = \"nano\"\n",
+        )
+        .unwrap();
+        // 离工作目录更近的文件优先
+        std::fs::write(
+            project.join("src").join(CRVCONFIG_FILE_NAME),
+            "editor = \"code\"\n",
+        )
+        .unwrap();
+
+        let config = runtime_config(&state, Some(&cwd));
+        assert_eq!(config.remote_addr.value, "http://project:34560");
+        assert!(matches!(
+            config.remote_addr.source,
+            RuntimeConfigSource::File
+        ));
+        assert_eq!(config.editor.value, "code");
+        assert_eq!(config.user.value, "default");
+
+        // 没有携带工作目录的请求不受影响
+        let config = runtime_config(&state, None);
+        assert_eq!(config.remote_addr.value, "http://stored:34560");
+        assert!(matches!(
+            config.remote_addr.source,
+            RuntimeConfigSource::Set
+        ));
+        assert_eq!(config.editor.value, "vim");
+    }
+
+    #[test]
+    fn invalid_crvconfig_is_rejected() {
+        let db = TempDb::new();
+        let state = db.state();
+        let cwd_dir = TempDir::new();
+        let cwd = cwd_dir.path();
+        std::fs::write(cwd.join(CRVCONFIG_FILE_NAME), "editor = [").unwrap();
+
+        let mut request = Request::new(());
+        request.metadata_mut().insert_bin(
+            CWD_HEADER,
+            MetadataValue::from_bytes(cwd.to_string_lossy().as_bytes()),
+        );
+        let status = call(state, request).unwrap_err();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+    }
+}
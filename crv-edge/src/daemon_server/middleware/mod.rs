@@ -4,6 +4,7 @@ use tonic::{Request, Status, service::Interceptor};
 
 pub mod auth;
 pub mod config;
+pub mod request_id;
 
 #[derive(Clone)]
 pub struct CombinedInterceptor {
@@ -18,6 +19,7 @@ impl CombinedInterceptor {
 
 impl Interceptor for CombinedInterceptor {
     fn call(&mut self, request: Request<()>) -> Result<Request<()>, Status> {
+        let request = request_id::call(request)?;
         let request = config::call(self.state.clone(), request)?;
         let request = auth::call(self.state.clone(), request)?;
         Ok(request)
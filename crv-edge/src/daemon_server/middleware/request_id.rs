@@ -0,0 +1,211 @@
+//! 请求 ID：用于在 CLI、edge 与 hive 的日志中关联同一次调用
+//!
+//! 调用方可以通过 `x-request-id` 头指定请求 ID，未指定时由 edge 生成。处理请求期间该 ID
+//! 保存在 task-local 中，edge 向 hive 发出的请求会自动带上同一个 `x-request-id` 头。
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tonic::codegen::{Service, http};
+use tonic::{Request, Status};
+use tower_layer::Layer;
+
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// 请求 ID 的最大长度，超过时视为无效并重新生成
+const MAX_REQUEST_ID_LEN: usize = 128;
+
+/// 当前请求的 ID，由 [`call`] 放入 `Request::extensions`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestId(pub String);
+
+tokio::task_local! {
+    static CURRENT_REQUEST_ID: RequestId;
+}
+
+impl RequestId {
+    fn generate() -> Self {
+        Self(uuid::Uuid::new_v4().to_string())
+    }
+
+    /// 解析调用方传入的请求 ID，只接受不含空白的可见 ASCII 字符
+    fn parse(value: &str) -> Option<Self> {
+        let valid = !value.is_empty()
+            && value.len() <= MAX_REQUEST_ID_LEN
+            && value.bytes().all(|b| b.is_ascii_graphic());
+        valid.then(|| Self(value.to_string()))
+    }
+
+    /// 当前任务正在处理的请求的 ID
+    pub fn current() -> Option<Self> {
+        CURRENT_REQUEST_ID.try_with(|id| id.clone()).ok()
+    }
+
+    /// 在 `future` 执行期间将当前请求 ID 设置为 `self`
+    async fn scope<F: Future>(self, future: F) -> F::Output {
+        CURRENT_REQUEST_ID.scope(self, future).await
+    }
+}
+
+/// 把当前请求 ID 带到 `future` 中，`future` 在新启动的任务中执行时使用
+pub fn inherit<F: Future>(future: F) -> impl Future<Output = F::Output> {
+    let request_id = RequestId::current();
+    async move {
+        match request_id {
+            Some(request_id) => request_id.scope(future).await,
+            None => future.await,
+        }
+    }
+}
+
+/// 读取或生成请求 ID 并放入 extensions
+pub fn call(mut request: Request<()>) -> Result<Request<()>, Status> {
+    let request_id = request
+        .metadata()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(RequestId::parse)
+        .unwrap_or_else(RequestId::generate);
+    request.extensions_mut().insert(request_id);
+    Ok(request)
+}
+
+/// 为 edge 的每个 gRPC 调用确定请求 ID，打印日志，并在调用期间设置当前请求 ID
+#[derive(Clone, Default)]
+pub struct RequestIdLayer;
+
+impl<S> Layer<S> for RequestIdLayer {
+    type Service = RequestIdService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RequestIdService { inner }
+    }
+}
+
+#[derive(Clone)]
+pub struct RequestIdService<S> {
+    inner: S,
+}
+
+impl<S, B> Service<http::Request<B>> for RequestIdService<S>
+where
+    S: Service<http::Request<B>>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<S::Response, S::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: http::Request<B>) -> Self::Future {
+        let request_id = request
+            .headers()
+            .get(REQUEST_ID_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(RequestId::parse)
+            .unwrap_or_else(RequestId::generate);
+        // 写回请求头，保证拦截器读到的是同一个 ID
+        if let Ok(value) = http::HeaderValue::from_str(&request_id.0) {
+            request.headers_mut().insert(REQUEST_ID_HEADER, value);
+        }
+        println!("rpc {} request_id={}", request.uri().path(), request_id.0);
+
+        let response = self.inner.call(request);
+        Box::pin(request_id.scope(response))
+    }
+}
+
+/// 向发往 hive 的请求添加当前请求 ID，调用方已经设置时保持不变
+pub(crate) fn propagate<B>(request: &mut http::Request<B>) {
+    if request.headers().contains_key(REQUEST_ID_HEADER) {
+        return;
+    }
+    let Some(request_id) = RequestId::current() else {
+        return;
+    };
+    if let Ok(value) = http::HeaderValue::from_str(&request_id.0) {
+        request.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::daemon_server::handlers::edge::stub_hive::{self, StubHive};
+    use crate::daemon_server::middleware::CombinedInterceptor;
+    use crate::daemon_server::service::SystemServiceImpl;
+    use crate::pb::BonjourReq;
+    use crate::pb::system_service_client::SystemServiceClient;
+    use crate::pb::system_service_server::SystemServiceServer;
+    use crate::test_support::TempDb;
+    use std::sync::{Arc, Mutex};
+    use tokio_stream::wrappers::TcpListenerStream;
+    use tonic::transport::Server;
+
+    #[test]
+    fn invalid_request_id_is_replaced() {
+        assert_eq!(
+            RequestId::parse("cli-42"),
+            Some(RequestId("cli-42".to_string()))
+        );
+        assert_eq!(RequestId::parse(""), None);
+        assert_eq!(RequestId::parse("has space"), None);
+        assert_eq!(RequestId::parse(&"a".repeat(MAX_REQUEST_ID_LEN + 1)), None);
+
+        let request = call(Request::new(())).unwrap();
+        let generated = request.extensions().get::<RequestId>().unwrap();
+        assert!(uuid::Uuid::parse_str(&generated.0).is_ok());
+    }
+
+    #[tokio::test]
+    async fn request_id_is_forwarded_to_hive() {
+        let hive_request_ids = Arc::new(Mutex::new(vec![]));
+        let hive_addr = stub_hive::spawn(StubHive {
+            request_ids: hive_request_ids.clone(),
+            ..Default::default()
+        })
+        .await;
+
+        let db = TempDb::new();
+        let state = db.state();
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let edge_addr = listener.local_addr().unwrap();
+        tokio::spawn(
+            Server::builder()
+                .layer(RequestIdLayer)
+                .add_service(SystemServiceServer::with_interceptor(
+                    SystemServiceImpl::new(state.clone()),
+                    CombinedInterceptor::new(state),
+                ))
+                .serve_with_incoming(TcpListenerStream::new(listener)),
+        );
+
+        let mut client = SystemServiceClient::connect(format!("http://{edge_addr}"))
+            .await
+            .unwrap();
+        let config_override = serde_json::json!({ "remote_addr": hive_addr }).to_string();
+        let request = |request_id: Option<&str>| {
+            let mut request = Request::new(BonjourReq {});
+            request
+                .metadata_mut()
+                .insert("x-crv-config-override", config_override.parse().unwrap());
+            if let Some(request_id) = request_id {
+                request
+                    .metadata_mut()
+                    .insert(REQUEST_ID_HEADER, request_id.parse().unwrap());
+            }
+            request
+        };
+
+        client.bonjour_hive(request(Some("cli-42"))).await.unwrap();
+        client.bonjour_hive(request(None)).await.unwrap();
+
+        let hive_request_ids = hive_request_ids.lock().unwrap().clone();
+        assert_eq!(hive_request_ids.len(), 2);
+        assert_eq!(hive_request_ids[0].as_deref(), Some("cli-42"));
+        // 调用方未指定时由 edge 生成
+        let generated = hive_request_ids[1].as_deref().unwrap();
+        assert!(uuid::Uuid::parse_str(generated).is_ok());
+    }
+}
@@ -1,10 +1,16 @@
+pub mod circuit_breaker;
 pub mod config;
 pub mod context;
 pub mod db;
 pub mod error;
 pub mod handlers;
+pub mod hook;
 pub mod job;
 pub mod middleware;
+pub mod retry;
 pub mod service;
 pub mod startup;
 pub mod state;
+pub mod throttle;
+pub mod update;
+pub mod watcher;
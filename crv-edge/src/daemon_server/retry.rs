@@ -0,0 +1,198 @@
+//! 请求 hive 遇到暂时性错误时的重试
+//!
+//! 只有 `Unavailable` 与 `ResourceExhausted` 会被重试，重试间隔按指数退避增长。
+//! hive 限流时会在 `x-rate-limit-retry-after-ms` 中给出需要等待的时间，重试间隔不会短于该时间。
+//! 只有幂等的请求（如 `check_chunks`、建立上传流）才能使用重试，
+//! `launch_submit`、`submit` 这类请求重试可能导致重复执行，不应使用。
+use rand::Rng;
+use std::future::Future;
+use std::time::Duration;
+use tonic::{Code, Status};
+
+/// hive 拒绝被限流的请求时，在该头中给出至少需要等待多少毫秒才能重试
+pub const RETRY_AFTER_HEADER: &str = "x-rate-limit-retry-after-ms";
+
+/// 重试策略
+#[derive(Clone, Debug)]
+pub struct RetryPolicy {
+    /// 最多尝试的次数（包含第一次），为 1 时不重试
+    pub max_attempts: u32,
+    /// 第一次重试前等待的时间（毫秒），之后每次翻倍
+    pub base_delay_ms: u64,
+    /// 重试间隔的上限（毫秒）
+    pub max_delay_ms: u64,
+    /// 是否在 `[0, 间隔]` 内随机等待，避免多个 edge 同时重试
+    pub jitter: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            base_delay_ms: 200,
+            max_delay_ms: 5_000,
+            jitter: true,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// 第 `attempt` 次失败（从 1 开始）后等待的时间
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let exp = attempt.saturating_sub(1).min(31);
+        let delay = self
+            .base_delay_ms
+            .saturating_mul(1u64 << exp)
+            .min(self.max_delay_ms);
+        let delay = if self.jitter && delay > 0 {
+            rand::thread_rng().gen_range(0..=delay)
+        } else {
+            delay
+        };
+        Duration::from_millis(delay)
+    }
+}
+
+/// 是否为值得重试的暂时性错误
+pub fn is_transient(status: &Status) -> bool {
+    matches!(status.code(), Code::Unavailable | Code::ResourceExhausted)
+}
+
+/// hive 限流时要求的等待时间，没有给出时返回 None
+pub fn retry_after(status: &Status) -> Option<Duration> {
+    let millis = status
+        .metadata()
+        .get(RETRY_AFTER_HEADER)?
+        .to_str()
+        .ok()?
+        .parse()
+        .ok()?;
+    Some(Duration::from_millis(millis))
+}
+
+/// 执行 `f`，遇到暂时性错误时按 `policy` 重试，返回最后一次的结果
+pub async fn with_retry<F, Fut, T>(policy: &RetryPolicy, mut f: F) -> Result<T, Status>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, Status>>,
+{
+    let mut attempt = 1;
+    loop {
+        match f().await {
+            Err(status) if attempt < policy.max_attempts && is_transient(&status) => {
+                let delay = policy.delay(attempt);
+                let delay = retry_after(&status).map_or(delay, |wait| wait.max(delay));
+                tokio::time::sleep(delay).await;
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::daemon_server::handlers::edge::stub_hive::{self, StubHive};
+    use crate::daemon_server::state::ChannelPool;
+    use crate::hive_pb::{CheckChunksReq, hive_service_client::HiveServiceClient};
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    fn policy() -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: 3,
+            base_delay_ms: 1,
+            max_delay_ms: 10,
+            jitter: false,
+        }
+    }
+
+    #[tokio::test]
+    async fn transient_errors_are_retried_until_success() {
+        let hive = StubHive {
+            check_chunks_failures: 2,
+            ..Default::default()
+        };
+        let calls = hive.check_chunks_calls.clone();
+        let addr = stub_hive::spawn(hive).await;
+        let channel = ChannelPool::new().get_channel(&addr).unwrap();
+        let client = HiveServiceClient::new(channel);
+
+        let rsp = with_retry(&policy(), || {
+            let mut client = client.clone();
+            async move {
+                client
+                    .check_chunks(CheckChunksReq {
+                        chunk_hashes: vec!["abc".to_string()],
+                    })
+                    .await
+            }
+        })
+        .await
+        .unwrap()
+        .into_inner();
+
+        assert_eq!(rsp.missing_chunk_hashes, vec!["abc".to_string()]);
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn other_errors_are_not_retried() {
+        let calls = Arc::new(AtomicU32::new(0));
+        let result: Result<(), Status> = with_retry(&policy(), || {
+            calls.fetch_add(1, Ordering::SeqCst);
+            async { Err(Status::internal("boom")) }
+        })
+        .await;
+
+        assert_eq!(result.unwrap_err().code(), Code::Internal);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    fn rate_limited(retry_after_ms: &str) -> Status {
+        let mut status = Status::resource_exhausted("rate limit exceeded");
+        status
+            .metadata_mut()
+            .insert(RETRY_AFTER_HEADER, retry_after_ms.parse().unwrap());
+        status
+    }
+
+    #[test]
+    fn retry_after_is_parsed_from_metadata() {
+        assert_eq!(
+            retry_after(&rate_limited("250")),
+            Some(Duration::from_millis(250))
+        );
+        assert_eq!(retry_after(&rate_limited("soon")), None);
+        assert_eq!(retry_after(&Status::unavailable("down")), None);
+    }
+
+    #[tokio::test]
+    async fn rate_limited_retry_waits_for_retry_after() {
+        let calls = Arc::new(AtomicU32::new(0));
+        let started = std::time::Instant::now();
+        let result = with_retry(&policy(), || {
+            let call = calls.fetch_add(1, Ordering::SeqCst);
+            async move {
+                if call == 0 {
+                    Err(rate_limited("50"))
+                } else {
+                    Ok(())
+                }
+            }
+        })
+        .await;
+
+        assert!(result.is_ok());
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        assert!(started.elapsed() >= Duration::from_millis(50));
+    }
+
+    #[test]
+    fn delay_grows_exponentially_up_to_max() {
+        let policy = policy();
+        let delays: Vec<u128> = (1..=5).map(|i| policy.delay(i).as_millis()).collect();
+        assert_eq!(delays, vec![1, 2, 4, 8, 10]);
+    }
+}
@@ -3,14 +3,17 @@ use std::pin::Pin;
 
 use super::handlers;
 use super::state::AppState;
+use crate::pb::branch_service_server::BranchService;
 use crate::pb::file_service_server::FileService;
+use crate::pb::snapshot_service_server::SnapshotService;
 use crate::pb::system_service_server::SystemService;
+use crate::pb::tag_service_server::TagService;
+use crate::pb::user_service_server::UserService;
 use crate::pb::workspace_service_server::WorkspaceService;
 use crate::pb::*;
 use crate::{
     daemon_server::handlers::file::submit::SubmitProgressStream,
-    pb::changelist_service_server::ChangelistService,
-    pb::debug_service_server::DebugService,
+    pb::changelist_service_server::ChangelistService, pb::debug_service_server::DebugService,
 };
 use tonic::{Request, Response, Status};
 
@@ -24,7 +27,8 @@ impl DebugServiceImpl {
     }
 }
 
-type TransferBlueprintStream = handlers::job_system_debug::transfer_blueprint::TransferBlueprintStream;
+type TransferBlueprintStream =
+    handlers::job_system_debug::transfer_blueprint::TransferBlueprintStream;
 
 #[tonic::async_trait]
 impl DebugService for DebugServiceImpl {
@@ -93,7 +97,9 @@ impl ChangelistService for ChangelistServiceImpl {
         &self,
         request: Request<DeleteChangelistReq>,
     ) -> Result<Response<DeleteChangelistRsp>, Status> {
-        todo!()
+        handlers::changelist::delete::handle(self.state.clone(), request)
+            .await
+            .map_err(|e| e.into())
     }
     async fn list_changelists(
         &self,
@@ -105,7 +111,9 @@ impl ChangelistService for ChangelistServiceImpl {
         &self,
         request: Request<DescribeChangelistReq>,
     ) -> Result<Response<DescribeChangelistRsp>, Status> {
-        todo!()
+        handlers::changelist::describe::handle(self.state.clone(), request)
+            .await
+            .map_err(|e| e.into())
     }
     async fn append_changelist(
         &self,
@@ -119,6 +127,14 @@ impl ChangelistService for ChangelistServiceImpl {
     ) -> Result<Response<SubmitChangelistStream>, Status> {
         todo!()
     }
+    async fn get_changelist_chain(
+        &self,
+        request: Request<GetChangelistChainReq>,
+    ) -> Result<Response<GetChangelistChainRsp>, Status> {
+        handlers::changelist::get_chain::handle(self.state.clone(), request)
+            .await
+            .map_err(|e| e.into())
+    }
 }
 
 pub struct FileServiceImpl {
@@ -137,6 +153,7 @@ type SubmitStream = SubmitProgressStream;
 #[tonic::async_trait]
 impl FileService for FileServiceImpl {
     type SyncStream = SyncStream;
+    type SyncWithProgressStream = handlers::file::sync::SyncProgressEventStream;
     type SubmitStream = SubmitStream;
 
     async fn add(&self, request: Request<AddReq>) -> Result<Response<AddRsp>, Status> {
@@ -144,7 +161,10 @@ impl FileService for FileServiceImpl {
             .await
             .map_err(|e| e.into())
     }
-    async fn checkout(&self, request: Request<CheckoutReq>) -> Result<Response<CheckoutRsp>, Status> {
+    async fn checkout(
+        &self,
+        request: Request<CheckoutReq>,
+    ) -> Result<Response<CheckoutRsp>, Status> {
         handlers::file::checkout::handle(self.state.clone(), request)
             .await
             .map_err(|e| e.into())
@@ -154,17 +174,60 @@ impl FileService for FileServiceImpl {
             .await
             .map_err(|e| e.into())
     }
-    async fn list_active_files(&self, request: Request<ListActiveFilesReq>) -> Result<Response<ListActiveFilesRsp>, Status> {
+    async fn r#move(&self, request: Request<MoveReq>) -> Result<Response<MoveRsp>, Status> {
+        handlers::file::move_file::handle(self.state.clone(), request)
+            .await
+            .map_err(|e| e.into())
+    }
+    async fn describe(
+        &self,
+        request: Request<DescribeReq>,
+    ) -> Result<Response<DescribeRsp>, Status> {
+        handlers::file::describe::handle(self.state.clone(), request)
+            .await
+            .map_err(|e| e.into())
+    }
+    async fn list_active_files(
+        &self,
+        request: Request<ListActiveFilesReq>,
+    ) -> Result<Response<ListActiveFilesRsp>, Status> {
         handlers::file::list_active_files::handle(self.state.clone(), request)
             .await
             .map_err(|e| e.into())
     }
+    async fn get_file_content(
+        &self,
+        request: Request<GetFileContentReq>,
+    ) -> Result<Response<GetFileContentRsp>, Status> {
+        handlers::file::get_file_content::handle(self.state.clone(), request)
+            .await
+            .map_err(|e| e.into())
+    }
     async fn sync(&self, request: Request<SyncReq>) -> Result<Response<SyncStream>, Status> {
-        handlers::file::sync::handle(self.state.clone(), request).await
+        handlers::file::sync::handle(self.state.clone(), request)
+            .await
+            .map_err(|e| e.into())
+    }
+    async fn sync_with_progress(
+        &self,
+        request: Request<SyncWithProgressReq>,
+    ) -> Result<Response<handlers::file::sync::SyncProgressEventStream>, Status> {
+        handlers::file::sync::handle_with_progress(self.state.clone(), request)
+            .await
             .map_err(|e| e.into())
     }
     async fn lock(&self, request: Request<LockReq>) -> Result<Response<LockRsp>, Status> {
-        todo!()
+        handlers::file::lock::handle(self.state.clone(), request)
+            .await
+            .map_err(|e| e.into())
+    }
+    async fn list_locked_files(
+        &self,
+        request: Request<ListLockedFilesReq>,
+    ) -> Result<Response<ListLockedFilesRsp>, Status> {
+        handlers::file::list_locked_files::handle(self.state.clone(), request)
+            .await
+            .map_err(|e| e.into())
     }
     async fn revert(&self, request: Request<RevertReq>) -> Result<Response<RevertRsp>, Status> {
         todo!()
@@ -188,13 +251,19 @@ impl SystemServiceImpl {
 
 #[tonic::async_trait]
 impl SystemService for SystemServiceImpl {
+    type ExportRepositorySnapshotStream =
+        handlers::edge::export_repository_snapshot::ExportRepositorySnapshotStream;
+
     async fn bonjour(&self, request: Request<BonjourReq>) -> Result<Response<BonjourRsp>, Status> {
         handlers::edge::bonjour::handle(self.state.clone(), request)
             .await
             .map_err(|e| e.into())
     }
 
-    async fn bonjour_hive(&self, request: Request<BonjourReq>) -> Result<Response<BonjourRsp>, Status> {
+    async fn bonjour_hive(
+        &self,
+        request: Request<BonjourReq>,
+    ) -> Result<Response<BonjourRsp>, Status> {
         handlers::edge::bonjour_hive::handle(self.state.clone(), request)
             .await
             .map_err(|e| e.into())
@@ -208,6 +277,105 @@ impl SystemService for SystemServiceImpl {
             .await
             .map_err(|e| e.into())
     }
+
+    async fn set_config(
+        &self,
+        request: Request<SetConfigReq>,
+    ) -> Result<Response<SetConfigRsp>, Status> {
+        handlers::edge::set_config::handle(self.state.clone(), request)
+            .await
+            .map_err(|e| e.into())
+    }
+
+    async fn get_config(
+        &self,
+        request: Request<GetConfigReq>,
+    ) -> Result<Response<GetConfigRsp>, Status> {
+        handlers::edge::get_config::handle(self.state.clone(), request)
+            .await
+            .map_err(|e| e.into())
+    }
+
+    async fn health_check(
+        &self,
+        request: Request<HealthCheckReq>,
+    ) -> Result<Response<HealthCheckRsp>, Status> {
+        handlers::edge::health_check::handle(self.state.clone(), request)
+            .await
+            .map_err(|e| e.into())
+    }
+
+    async fn get_repository_stats(
+        &self,
+        request: Request<GetRepositoryStatsReq>,
+    ) -> Result<Response<GetRepositoryStatsRsp>, Status> {
+        handlers::edge::get_repository_stats::handle(self.state.clone(), request)
+            .await
+            .map_err(|e| e.into())
+    }
+
+    async fn rebuild_index(
+        &self,
+        request: Request<RebuildIndexReq>,
+    ) -> Result<Response<RebuildIndexRsp>, Status> {
+        handlers::edge::rebuild_index::handle(self.state.clone(), request)
+            .await
+            .map_err(|e| e.into())
+    }
+
+    async fn get_repository_layout(
+        &self,
+        request: Request<GetRepositoryLayoutReq>,
+    ) -> Result<Response<GetRepositoryLayoutRsp>, Status> {
+        handlers::edge::get_repository_layout::handle(self.state.clone(), request)
+            .await
+            .map_err(|e| e.into())
+    }
+
+    async fn export_repository_snapshot(
+        &self,
+        request: Request<ExportRepositorySnapshotReq>,
+    ) -> Result<Response<Self::ExportRepositorySnapshotStream>, Status> {
+        handlers::edge::export_repository_snapshot::handle(self.state.clone(), request)
+            .await
+            .map_err(|e| e.into())
+    }
+
+    async fn start_watch(
+        &self,
+        request: Request<StartWatchReq>,
+    ) -> Result<Response<StartWatchRsp>, Status> {
+        handlers::edge::watch::start(self.state.clone(), request)
+            .await
+            .map_err(|e| e.into())
+    }
+
+    async fn stop_watch(
+        &self,
+        request: Request<StopWatchReq>,
+    ) -> Result<Response<StopWatchRsp>, Status> {
+        handlers::edge::watch::stop(self.state.clone(), request)
+            .await
+            .map_err(|e| e.into())
+    }
+
+    async fn check_update(
+        &self,
+        request: Request<CheckUpdateReq>,
+    ) -> Result<Response<CheckUpdateRsp>, Status> {
+        handlers::edge::update::check(self.state.clone(), request)
+            .await
+            .map_err(|e| e.into())
+    }
+
+    async fn apply_update(
+        &self,
+        request: Request<ApplyUpdateReq>,
+    ) -> Result<Response<ApplyUpdateRsp>, Status> {
+        handlers::edge::update::apply(self.state.clone(), request)
+            .await
+            .map_err(|e| e.into())
+    }
 }
 
 pub struct WorkspaceServiceImpl {
@@ -234,7 +402,9 @@ impl WorkspaceService for WorkspaceServiceImpl {
         &self,
         request: Request<DeleteWorkspaceReq>,
     ) -> Result<Response<DeleteWorkspaceRsp>, Status> {
-        todo!()
+        handlers::workspace::delete::handle(self.state.clone(), request)
+            .await
+            .map_err(|e| e.into())
     }
     async fn list_workspaces(
         &self,
@@ -248,6 +418,294 @@ impl WorkspaceService for WorkspaceServiceImpl {
         &self,
         request: Request<DescribeWorkspaceReq>,
     ) -> Result<Response<DescribeWorkspaceRsp>, Status> {
-        todo!()
+        handlers::workspace::describe::handle(self.state.clone(), request)
+            .await
+            .map_err(|e| e.into())
+    }
+    async fn save_checkpoint(
+        &self,
+        request: Request<SaveCheckpointReq>,
+    ) -> Result<Response<SaveCheckpointRsp>, Status> {
+        handlers::workspace::checkpoint::save(self.state.clone(), request)
+            .await
+            .map_err(|e| e.into())
+    }
+    async fn restore_checkpoint(
+        &self,
+        request: Request<RestoreCheckpointReq>,
+    ) -> Result<Response<RestoreCheckpointRsp>, Status> {
+        handlers::workspace::checkpoint::restore(self.state.clone(), request)
+            .await
+            .map_err(|e| e.into())
+    }
+    async fn list_checkpoints(
+        &self,
+        request: Request<ListCheckpointsReq>,
+    ) -> Result<Response<ListCheckpointsRsp>, Status> {
+        handlers::workspace::checkpoint::list(self.state.clone(), request)
+            .await
+            .map_err(|e| e.into())
+    }
+    async fn delete_checkpoint(
+        &self,
+        request: Request<DeleteCheckpointReq>,
+    ) -> Result<Response<DeleteCheckpointRsp>, Status> {
+        handlers::workspace::checkpoint::delete(self.state.clone(), request)
+            .await
+            .map_err(|e| e.into())
+    }
+    async fn validate_workspace(
+        &self,
+        request: Request<ValidateWorkspaceReq>,
+    ) -> Result<Response<ValidateWorkspaceRsp>, Status> {
+        handlers::workspace::validate::handle(self.state.clone(), request)
+            .await
+            .map_err(|e| e.into())
+    }
+    async fn switch_workspace(
+        &self,
+        request: Request<SwitchWorkspaceReq>,
+    ) -> Result<Response<SwitchWorkspaceRsp>, Status> {
+        handlers::workspace::switch::handle(self.state.clone(), request)
+            .await
+            .map_err(|e| e.into())
+    }
+    async fn add_workspace_mapping(
+        &self,
+        request: Request<AddWorkspaceMappingReq>,
+    ) -> Result<Response<AddWorkspaceMappingRsp>, Status> {
+        handlers::workspace::mapping::add(self.state.clone(), request)
+            .await
+            .map_err(|e| e.into())
+    }
+    async fn remove_workspace_mapping(
+        &self,
+        request: Request<RemoveWorkspaceMappingReq>,
+    ) -> Result<Response<RemoveWorkspaceMappingRsp>, Status> {
+        handlers::workspace::mapping::remove(self.state.clone(), request)
+            .await
+            .map_err(|e| e.into())
+    }
+    async fn list_workspace_mappings(
+        &self,
+        request: Request<ListWorkspaceMappingsReq>,
+    ) -> Result<Response<ListWorkspaceMappingsRsp>, Status> {
+        handlers::workspace::mapping::list(self.state.clone(), request)
+            .await
+            .map_err(|e| e.into())
+    }
+}
+
+pub struct BranchServiceImpl {
+    pub state: AppState,
+}
+
+impl BranchServiceImpl {
+    pub fn new(state: AppState) -> Self {
+        Self { state }
+    }
+}
+
+#[tonic::async_trait]
+impl BranchService for BranchServiceImpl {
+    async fn create_branch(
+        &self,
+        request: Request<CreateBranchReq>,
+    ) -> Result<Response<CreateBranchRsp>, Status> {
+        handlers::branch::create::handle(self.state.clone(), request)
+            .await
+            .map_err(|e| e.into())
+    }
+
+    async fn list_branches(
+        &self,
+        request: Request<ListBranchesReq>,
+    ) -> Result<Response<ListBranchesRsp>, Status> {
+        handlers::branch::list::handle(self.state.clone(), request)
+            .await
+            .map_err(|e| e.into())
+    }
+
+    async fn set_branch_description_format(
+        &self,
+        request: Request<SetBranchDescriptionFormatReq>,
+    ) -> Result<Response<SetBranchDescriptionFormatRsp>, Status> {
+        handlers::branch::set_description_format::handle(self.state.clone(), request)
+            .await
+            .map_err(|e| e.into())
+    }
+
+    async fn set_branch_protection(
+        &self,
+        request: Request<SetBranchProtectionReq>,
+    ) -> Result<Response<SetBranchProtectionRsp>, Status> {
+        handlers::branch::set_protection::handle(self.state.clone(), request)
+            .await
+            .map_err(|e| e.into())
+    }
+
+    async fn cherry_pick(
+        &self,
+        request: Request<CherryPickReq>,
+    ) -> Result<Response<CherryPickRsp>, Status> {
+        handlers::branch::cherry_pick::handle(self.state.clone(), request)
+            .await
+            .map_err(|e| e.into())
+    }
+
+    async fn get_branch_diff(
+        &self,
+        request: Request<GetBranchDiffReq>,
+    ) -> Result<Response<GetBranchDiffRsp>, Status> {
+        handlers::branch::diff::handle(self.state.clone(), request)
+            .await
+            .map_err(|e| e.into())
+    }
+}
+
+pub struct UserServiceImpl {
+    pub state: AppState,
+}
+
+impl UserServiceImpl {
+    pub fn new(state: AppState) -> Self {
+        Self { state }
+    }
+}
+
+#[tonic::async_trait]
+impl UserService for UserServiceImpl {
+    async fn list_users(
+        &self,
+        request: Request<ListUsersReq>,
+    ) -> Result<Response<ListUsersRsp>, Status> {
+        handlers::user::list::handle(self.state.clone(), request)
+            .await
+            .map_err(|e| e.into())
+    }
+
+    async fn update_user_password(
+        &self,
+        request: Request<UpdateUserPasswordReq>,
+    ) -> Result<Response<UpdateUserPasswordRsp>, Status> {
+        handlers::user::update_password::handle(self.state.clone(), request)
+            .await
+            .map_err(|e| e.into())
+    }
+
+    async fn delete_user(
+        &self,
+        request: Request<DeleteUserReq>,
+    ) -> Result<Response<DeleteUserRsp>, Status> {
+        handlers::user::delete::handle(self.state.clone(), request)
+            .await
+            .map_err(|e| e.into())
+    }
+}
+
+pub struct TagServiceImpl {
+    pub state: AppState,
+}
+
+impl TagServiceImpl {
+    pub fn new(state: AppState) -> Self {
+        Self { state }
+    }
+}
+
+#[tonic::async_trait]
+impl TagService for TagServiceImpl {
+    async fn tag_changelist(
+        &self,
+        request: Request<TagChangelistReq>,
+    ) -> Result<Response<TagChangelistRsp>, Status> {
+        handlers::tag::add::handle(self.state.clone(), request)
+            .await
+            .map_err(|e| e.into())
+    }
+
+    async fn untag_changelist(
+        &self,
+        request: Request<UntagChangelistReq>,
+    ) -> Result<Response<UntagChangelistRsp>, Status> {
+        handlers::tag::remove::handle(self.state.clone(), request)
+            .await
+            .map_err(|e| e.into())
+    }
+
+    async fn get_changelist_by_tag(
+        &self,
+        request: Request<GetChangelistByTagReq>,
+    ) -> Result<Response<GetChangelistByTagRsp>, Status> {
+        handlers::tag::list::handle(self.state.clone(), request)
+            .await
+            .map_err(|e| e.into())
+    }
+
+    async fn tag_file_revision(
+        &self,
+        request: Request<TagFileRevisionReq>,
+    ) -> Result<Response<TagFileRevisionRsp>, Status> {
+        handlers::tag::revision::handle(self.state.clone(), request)
+            .await
+            .map_err(|e| e.into())
+    }
+
+    async fn list_file_revisions_by_tag(
+        &self,
+        request: Request<ListFileRevisionsByTagReq>,
+    ) -> Result<Response<ListFileRevisionsByTagRsp>, Status> {
+        handlers::tag::list_revisions::handle(self.state.clone(), request)
+            .await
+            .map_err(|e| e.into())
+    }
+}
+
+pub struct SnapshotServiceImpl {
+    pub state: AppState,
+}
+
+impl SnapshotServiceImpl {
+    pub fn new(state: AppState) -> Self {
+        Self { state }
+    }
+}
+
+#[tonic::async_trait]
+impl SnapshotService for SnapshotServiceImpl {
+    async fn create_snapshot(
+        &self,
+        request: Request<CreateSnapshotReq>,
+    ) -> Result<Response<CreateSnapshotRsp>, Status> {
+        handlers::snapshot::create::handle(self.state.clone(), request)
+            .await
+            .map_err(|e| e.into())
+    }
+
+    async fn list_snapshots(
+        &self,
+        request: Request<ListSnapshotsReq>,
+    ) -> Result<Response<ListSnapshotsRsp>, Status> {
+        handlers::snapshot::list::handle(self.state.clone(), request)
+            .await
+            .map_err(|e| e.into())
+    }
+
+    async fn delete_snapshot(
+        &self,
+        request: Request<DeleteSnapshotReq>,
+    ) -> Result<Response<DeleteSnapshotRsp>, Status> {
+        handlers::snapshot::delete::handle(self.state.clone(), request)
+            .await
+            .map_err(|e| e.into())
+    }
+
+    async fn restore_snapshot(
+        &self,
+        request: Request<RestoreSnapshotReq>,
+    ) -> Result<Response<RestoreSnapshotRsp>, Status> {
+        handlers::snapshot::restore::handle(self.state.clone(), request)
+            .await
+            .map_err(|e| e.into())
     }
 }
@@ -1,17 +1,30 @@
 //! 服务启动引导
-use super::config::BootstrapConfig;
+use super::config::{BootstrapConfig, RuntimeConfig, RuntimeConfigSource};
 use super::middleware::CombinedInterceptor;
+use super::middleware::request_id::RequestIdLayer;
 use super::service::*;
+use super::update;
 use crate::daemon_server::db::DbManager;
 use crate::daemon_server::state::AppState;
+use crate::hive_client::verify_hive_version;
+use crate::metrics::{self, MetricsLayer};
+use crate::pb::branch_service_server::BranchServiceServer;
 use crate::pb::changelist_service_server::ChangelistServiceServer;
 use crate::pb::debug_service_server::DebugServiceServer;
 use crate::pb::file_service_server::FileServiceServer;
+use crate::pb::snapshot_service_server::SnapshotServiceServer;
 use crate::pb::system_service_server::SystemServiceServer;
+use crate::pb::tag_service_server::TagServiceServer;
+use crate::pb::user_service_server::UserServiceServer;
 use crate::pb::workspace_service_server::WorkspaceServiceServer;
+use crv_core::logger::recovery::RecoveryAction;
 use std::net::SocketAddr;
+use std::path::PathBuf;
 use std::sync::Arc;
+use tokio::sync::Semaphore;
+use tokio::task::JoinHandle;
 use tonic::transport::Server;
+use tonic::transport::server::TcpIncoming;
 
 /// 启动 gRPC 服务器（优雅关闭）
 pub async fn start_server_with_shutdown<S>(shutdown: S) -> Result<(), Box<dyn std::error::Error>>
@@ -19,65 +32,124 @@ where
     S: Future<Output = ()> + Send + 'static,
 {
     let bootstrap_config = BootstrapConfig::load()?;
-    let db = DbManager::new(bootstrap_config.embedded_database_root)?;
-    let db_arc = Arc::new(db);
-    let app_state = AppState::new(db_arc.clone());
+    let app_state = prepare_app_state(&bootstrap_config).await?;
+    let restart = app_state.restart.clone();
 
-    let interceptor = CombinedInterceptor::new(app_state.clone());
-    let system_service_impl = SystemServiceImpl::new(app_state.clone());
-    let workspace_service_impl = WorkspaceServiceImpl::new(app_state.clone());
-    let file_service_impl = FileServiceImpl::new(app_state.clone());
-    let changelist_service_impl = ChangelistServiceImpl::new(app_state.clone());
-    let debug_service_impl = DebugServiceImpl::new(app_state);
-
-    let addr: SocketAddr = format!("[::1]:{}", bootstrap_config.daemon_port).parse()?;
+    let addr = daemon_addr(&bootstrap_config)?;
+    let metrics_server =
+        start_metrics_server(&bootstrap_config, addr, app_state.db.clone()).await?;
 
     println!("Starting gRPC server on {}", addr);
+    let incoming = bind(addr)?;
+    discard_update_backup();
 
-    Server::builder()
-        .add_service(SystemServiceServer::with_interceptor(
-            system_service_impl,
-            interceptor.clone(),
-        ))
-        .add_service(WorkspaceServiceServer::with_interceptor(
-            workspace_service_impl,
-            interceptor.clone(),
-        ))
-        .add_service(FileServiceServer::with_interceptor(
-            file_service_impl,
-            interceptor.clone(),
-        ))
-        .add_service(ChangelistServiceServer::with_interceptor(
-            changelist_service_impl,
-            interceptor.clone(),
-        ))
-        .add_service(DebugServiceServer::with_interceptor(
-            debug_service_impl,
-            interceptor,
-        ))
-        .serve_with_shutdown(addr, shutdown)
-        .await?;
+    let restart_requested = restart.clone();
+    serve(app_state, incoming, async move {
+        tokio::select! {
+            _ = shutdown => {}
+            _ = restart_requested.wait() => {}
+        }
+    })
+    .await?;
+
+    if let Some(metrics_server) = metrics_server {
+        metrics_server.abort();
+    }
 
+    if restart.is_requested() {
+        update::restart()?;
+    }
     Ok(())
 }
 
 /// 启动 gRPC 服务器（无关闭信号，会一直运行直至进程退出）
 pub async fn start_server() -> Result<(), Box<dyn std::error::Error>> {
     let bootstrap_config = BootstrapConfig::load()?;
-    let db = DbManager::new(bootstrap_config.embedded_database_root)?;
-    let db_arc = Arc::new(db);
-    let app_state = AppState::new(db_arc.clone());
+    let app_state = prepare_app_state(&bootstrap_config).await?;
+    let restart = app_state.restart.clone();
+
+    let addr = daemon_addr(&bootstrap_config)?;
+    let metrics_server =
+        start_metrics_server(&bootstrap_config, addr, app_state.db.clone()).await?;
+    let incoming = bind(addr)?;
+    discard_update_backup();
+
+    let restart_requested = restart.clone();
+    serve(app_state, incoming, async move {
+        restart_requested.wait().await
+    })
+    .await?;
+
+    if let Some(metrics_server) = metrics_server {
+        metrics_server.abort();
+    }
+
+    if restart.is_requested() {
+        update::restart()?;
+    }
+    Ok(())
+}
 
+/// 按启动配置创建 AppState，并完成启动前的恢复与检查
+async fn prepare_app_state(
+    bootstrap_config: &BootstrapConfig,
+) -> Result<AppState, Box<dyn std::error::Error>> {
+    let hive_client_config = bootstrap_config.hive_client_config();
+    let db = DbManager::new(&bootstrap_config.embedded_database_root)?;
+    let mut app_state = AppState::with_hive_client_config(Arc::new(db), hive_client_config);
+    app_state.pre_submit_hook = bootstrap_config.pre_submit_hook.as_ref().map(PathBuf::from);
+    app_state.update_url = bootstrap_config.update_url.clone();
+    app_state.sync_write_buffer_size = bootstrap_config.sync_write_buffer_kb.max(1) * 1024;
+    app_state.sync_open_files = Arc::new(Semaphore::new(bootstrap_config.max_open_files.max(1)));
+    recover_interrupted_writes(&app_state)?;
+    check_hive_version(&app_state).await?;
+    start_file_watcher(bootstrap_config, &app_state);
+    Ok(app_state)
+}
+
+/// daemon 监听的地址，监听所有网卡时打印警告
+fn daemon_addr(
+    bootstrap_config: &BootstrapConfig,
+) -> Result<SocketAddr, Box<dyn std::error::Error>> {
+    let addr = bootstrap_config.daemon_bind_addr()?;
+    if addr.ip().is_unspecified() {
+        // daemon 没有自己的身份校验，所有请求都以 runtime config 中的用户身份访问 hive
+        eprintln!(
+            "Warning: the daemon listens on {addr} and is exposed on all interfaces. \
+             Anyone who can reach this port can act as the configured user."
+        );
+    }
+    Ok(addr)
+}
+
+/// 绑定 daemon 监听的地址，绑定失败时 daemon 无法提供服务
+fn bind(addr: SocketAddr) -> std::io::Result<TcpIncoming> {
+    Ok(TcpIncoming::bind(addr)?.with_nodelay(Some(true)))
+}
+
+/// 在已绑定的 `incoming` 上提供 daemon 的所有 gRPC 服务，直到 `shutdown` 完成
+async fn serve<S>(
+    app_state: AppState,
+    incoming: TcpIncoming,
+    shutdown: S,
+) -> Result<(), tonic::transport::Error>
+where
+    S: Future<Output = ()> + Send + 'static,
+{
     let interceptor = CombinedInterceptor::new(app_state.clone());
     let system_service_impl = SystemServiceImpl::new(app_state.clone());
     let workspace_service_impl = WorkspaceServiceImpl::new(app_state.clone());
     let file_service_impl = FileServiceImpl::new(app_state.clone());
     let changelist_service_impl = ChangelistServiceImpl::new(app_state.clone());
+    let branch_service_impl = BranchServiceImpl::new(app_state.clone());
+    let user_service_impl = UserServiceImpl::new(app_state.clone());
+    let tag_service_impl = TagServiceImpl::new(app_state.clone());
+    let snapshot_service_impl = SnapshotServiceImpl::new(app_state.clone());
     let debug_service_impl = DebugServiceImpl::new(app_state);
 
-    let addr: SocketAddr = format!("[::1]:{}", bootstrap_config.daemon_port).parse()?;
-
     Server::builder()
+        .layer(MetricsLayer)
+        .layer(RequestIdLayer)
         .add_service(SystemServiceServer::with_interceptor(
             system_service_impl,
             interceptor.clone(),
@@ -94,12 +166,157 @@ pub async fn start_server() -> Result<(), Box<dyn std::error::Error>> {
             changelist_service_impl,
             interceptor.clone(),
         ))
+        .add_service(BranchServiceServer::with_interceptor(
+            branch_service_impl,
+            interceptor.clone(),
+        ))
+        .add_service(UserServiceServer::with_interceptor(
+            user_service_impl,
+            interceptor.clone(),
+        ))
+        .add_service(TagServiceServer::with_interceptor(
+            tag_service_impl,
+            interceptor.clone(),
+        ))
+        .add_service(SnapshotServiceServer::with_interceptor(
+            snapshot_service_impl,
+            interceptor.clone(),
+        ))
         .add_service(DebugServiceServer::with_interceptor(
             debug_service_impl,
             interceptor,
         ))
-        .serve(addr)
-        .await?;
+        .serve_with_incoming_shutdown(incoming, shutdown)
+        .await
+}
 
+/// 清理上次 daemon 异常退出时没有写完的文件
+fn recover_interrupted_writes(app_state: &AppState) -> Result<(), Box<dyn std::error::Error>> {
+    for action in app_state.recovery_log.recover()? {
+        match action {
+            RecoveryAction::RolledBack { target_path, .. } => {
+                println!("Discarded partially written {}", target_path.display())
+            }
+            RecoveryAction::Completed { target_path, .. } => {
+                println!("Completed interrupted write of {}", target_path.display())
+            }
+        }
+    }
     Ok(())
 }
+
+/// 已经绑定监听地址，新版本可以提供服务，删除上次更新时保留的旧版本可执行文件
+fn discard_update_backup() {
+    if let Ok(exe) = std::env::current_exe() {
+        update::discard_backup(&exe);
+    }
+}
+
+/// 按配置启动 Prometheus 指标服务，监听与 gRPC 服务相同的主机
+async fn start_metrics_server(
+    bootstrap_config: &BootstrapConfig,
+    daemon_addr: SocketAddr,
+    db: Arc<DbManager>,
+) -> Result<Option<JoinHandle<()>>, Box<dyn std::error::Error>> {
+    if bootstrap_config.disable_metrics {
+        return Ok(None);
+    }
+    let addr = SocketAddr::new(daemon_addr.ip(), bootstrap_config.metrics_port);
+    println!("Starting metrics server on {}", addr);
+    Ok(Some(metrics::start_server(addr, db).await?))
+}
+
+/// 按配置启动文件监听，启动失败不影响 daemon 的其他功能
+fn start_file_watcher(bootstrap_config: &BootstrapConfig, app_state: &AppState) {
+    if !bootstrap_config.auto_track_changes {
+        return;
+    }
+    match app_state.file_watcher.start(app_state.clone()) {
+        Ok(workspace_names) => println!("Watching workspaces: {}", workspace_names.join(", ")),
+        Err(e) => eprintln!("Failed to start file watcher: {e}"),
+    }
+}
+
+/// 在接受用户连接前检查已配置的 hive 的版本，不兼容时拒绝启动
+async fn check_hive_version(app_state: &AppState) -> Result<(), Box<dyn std::error::Error>> {
+    let mut runtime_config = RuntimeConfig::default();
+    runtime_config.merge(
+        app_state.db.load_runtime_config()?,
+        RuntimeConfigSource::Set,
+    );
+    verify_hive_version(app_state, &runtime_config.remote_addr.value).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pb::BonjourReq;
+    use crate::pb::system_service_client::SystemServiceClient;
+    use crate::test_support::TempDb;
+    use std::time::Duration;
+
+    #[test]
+    fn clients_connect_through_loopback_when_bound_to_all_interfaces() {
+        let config = |addr: Option<&str>| BootstrapConfig {
+            daemon_bind_addr: addr.map(str::to_string),
+            ..Default::default()
+        };
+        assert_eq!(config(None).daemon_url().unwrap(), "http://[::1]:31822");
+        assert_eq!(
+            config(Some("0.0.0.0:34562")).daemon_url().unwrap(),
+            "http://127.0.0.1:34562"
+        );
+        assert_eq!(
+            config(Some("[::]:34562")).daemon_url().unwrap(),
+            "http://[::1]:34562"
+        );
+        assert_eq!(
+            config(Some("192.168.1.10:34562")).daemon_url().unwrap(),
+            "http://192.168.1.10:34562"
+        );
+        assert!(config(Some("localhost")).daemon_bind_addr().is_err());
+    }
+
+    #[tokio::test]
+    async fn daemon_listens_on_configured_bind_addr() {
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let bootstrap_config = BootstrapConfig {
+            daemon_bind_addr: Some(format!("127.0.0.1:{port}")),
+            ..Default::default()
+        };
+        let addr = daemon_addr(&bootstrap_config).unwrap();
+        let db = TempDb::new();
+        let app_state = db.state();
+        let (shutdown_tx, shutdown_rx) = tokio::sync::oneshot::channel::<()>();
+        let incoming = bind(addr).unwrap();
+        let server = tokio::spawn(serve(app_state, incoming, async move {
+            let _ = shutdown_rx.await;
+        }));
+
+        // 与 CLI 一样按启动配置得到 daemon 地址，等待服务开始监听
+        let url = bootstrap_config.daemon_url().unwrap();
+        let mut client = None;
+        for _ in 0..100 {
+            if let Ok(c) = SystemServiceClient::connect(url.clone()).await {
+                client = Some(c);
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        let rsp = client
+            .expect("daemon should accept connections")
+            .bonjour(BonjourReq {})
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(rsp.api_level, 1);
+
+        shutdown_tx.send(()).unwrap();
+        server.await.unwrap().unwrap();
+    }
+}
@@ -1,11 +1,36 @@
 //! 服务全局状态管理
+use crate::daemon_server::config::HiveClientConfig;
 use crate::daemon_server::error::{AppError, AppResult};
+use crate::hive_client::HiveClientPool;
 
+use super::circuit_breaker::CircuitBreaker;
 use super::db::DbManager;
 use super::job::JobManager;
+use super::middleware::request_id;
+use super::retry::RetryPolicy;
+use super::throttle::TokenBucketThrottle;
+use super::update::RestartSignal;
+use super::watcher::FileWatcher;
+use crv_core::logger::recovery::RecoveryLog;
 use lru::LruCache;
+use std::path::PathBuf;
+use std::task::{Context, Poll};
+use std::time::Duration;
 use std::{num::NonZeroUsize, sync::Arc};
-use tonic::transport::{Channel, Endpoint};
+use tokio::sync::Semaphore;
+use tonic::Status;
+use tonic::body::Body;
+use tonic::codegen::{BoxFuture, Service, StdError, http};
+use tonic::transport::Channel;
+
+/// 恢复日志的文件名，位于嵌入式数据库的根目录
+const RECOVERY_LOG_FILE: &str = "recovery.log";
+
+/// 同步时写入文件的默认缓冲区大小
+pub const DEFAULT_SYNC_WRITE_BUFFER_SIZE: usize = 256 * 1024;
+
+/// 同步时默认最多同时下载的文件数量
+pub const DEFAULT_MAX_OPEN_FILES: usize = 8;
 
 /// 全局应用状态，将被注入到 gRPC Service 中
 #[derive(Clone)]
@@ -16,39 +41,80 @@ pub struct AppState {
     pub hive_channel: Arc<ChannelPool>,
     /// Job 管理器
     pub job_manager: Arc<JobManager>,
+    /// 自动跟踪文件修改的监听器
+    pub file_watcher: Arc<FileWatcher>,
+    /// 提交前执行的钩子
+    pub pre_submit_hook: Option<PathBuf>,
+    /// 更新清单的地址
+    pub update_url: Option<String>,
+    /// 安装更新后请求重启 daemon
+    pub restart: Arc<RestartSignal>,
+    /// 记录正在写入的文件，daemon 崩溃后用于清理写了一半的文件
+    pub recovery_log: Arc<RecoveryLog>,
+    /// 同步时写入文件使用的缓冲区大小（字节）
+    pub sync_write_buffer_size: usize,
+    /// 限制所有同步任务同时下载的文件数量
+    pub sync_open_files: Arc<Semaphore>,
 }
 
 /// 缓存连接
 pub struct ChannelPool {
-    channel_cache: Arc<std::sync::Mutex<LruCache<String, Channel>>>,
+    channel_cache: Arc<std::sync::Mutex<LruCache<String, HiveChannel>>>,
+    config: HiveClientConfig,
+    /// 所有连接共用的上传限速
+    upload_throttle: Option<Arc<TokenBucketThrottle>>,
 }
 
 impl ChannelPool {
     const CACHE_CAPACITY: usize = 64;
 
     pub fn new() -> Self {
+        Self::with_config(HiveClientConfig::default())
+    }
+
+    /// 所有连接都使用同一份配置建立（如 TLS）
+    pub fn with_config(config: HiveClientConfig) -> Self {
         Self {
             channel_cache: Arc::new(std::sync::Mutex::new(LruCache::new(
                 NonZeroUsize::new(Self::CACHE_CAPACITY).unwrap(),
             ))),
+            upload_throttle: config
+                .upload_bytes_per_sec
+                .map(|bytes_per_sec| Arc::new(TokenBucketThrottle::new(bytes_per_sec))),
+            config,
         }
     }
 
-    pub fn get_channel(&self, addr: &str) -> AppResult<Channel> {
+    /// 是否启用差量上传
+    pub fn delta_upload_enabled(&self) -> bool {
+        self.config.delta_upload
+    }
+
+    pub fn get_channel(&self, addr: &str) -> AppResult<HiveChannel> {
         let mut cache = self
             .channel_cache
             .lock()
             .map_err(|e| AppError::Internal(format!("{e}")))?;
 
         if let Some(channel) = cache.get(addr) {
-            return Ok(channel.clone());
+            return Ok(channel.next_connection());
         }
 
         drop(cache);
 
-        let channel = Endpoint::from_shared(addr.to_string())
-            .map_err(|e| AppError::Internal(format!("{e}")))?
-            .connect_lazy();
+        let connections = Arc::new(HiveClientPool::connect_lazy(
+            &self.config.endpoint(addr)?,
+            self.config.connection_pool_size,
+        ));
+        let channel = HiveChannel {
+            inner: connections.next_channel(),
+            connections,
+            timeout: self.config.request_timeout,
+            breaker: Arc::new(CircuitBreaker::new(self.config.circuit_breaker.clone())),
+            retry: self.config.retry.clone(),
+            delta_upload: self.config.delta_upload,
+            upload_throttle: self.upload_throttle.clone(),
+        };
 
         let mut cache = self
             .channel_cache
@@ -61,12 +127,210 @@ impl ChannelPool {
     }
 }
 
+/// 连接 hive 的通道，为每个请求附加超时，并在 hive 不可达时熔断。
+///
+/// 超时覆盖从发出请求到收到响应头的过程，对于流式响应，后续每条消息的超时由
+/// [`HiveChannel::next_message`] 控制。超时后返回 `DeadlineExceeded`。
+///
+/// 连接失败、超时等网络层面的错误计入熔断器，hive 正常返回的业务错误不计入。
+///
+/// 每次从 [`ChannelPool::get_channel`] 取得的通道使用连接池中的下一条连接，
+/// 同一个 hive 的所有连接共用一个熔断器。
+#[derive(Clone)]
+pub struct HiveChannel {
+    inner: Channel,
+    connections: Arc<HiveClientPool>,
+    timeout: Option<Duration>,
+    breaker: Arc<CircuitBreaker>,
+    retry: RetryPolicy,
+    delta_upload: bool,
+    upload_throttle: Option<Arc<TokenBucketThrottle>>,
+}
+
+impl HiveChannel {
+    /// 换用连接池中的下一条连接，其余配置不变
+    fn next_connection(&self) -> Self {
+        Self {
+            inner: self.connections.next_channel(),
+            ..self.clone()
+        }
+    }
+
+    /// 幂等请求遇到暂时性错误时的重试策略，配合 [`crate::daemon_server::retry::with_retry`] 使用
+    pub fn retry_policy(&self) -> &RetryPolicy {
+        &self.retry
+    }
+
+    /// 是否启用差量上传，启用时提交会尝试基于上一个版本的 chunk 上传差量
+    pub fn delta_upload_enabled(&self) -> bool {
+        self.delta_upload
+    }
+
+    /// 配置了上传限速时，等待至可以上传 `bytes` 字节为止
+    pub async fn throttle_upload(&self, bytes: usize) {
+        if let Some(throttle) = &self.upload_throttle {
+            throttle.acquire(bytes).await;
+        }
+    }
+
+    /// 读取流式响应中的下一条消息，超过请求超时仍未收到时返回 `DeadlineExceeded`
+    pub async fn next_message<T>(
+        &self,
+        stream: &mut tonic::Streaming<T>,
+    ) -> Result<Option<T>, Status> {
+        match self.timeout {
+            Some(timeout) => tokio::time::timeout(timeout, stream.message())
+                .await
+                .map_err(|_| deadline_exceeded(timeout))?,
+            None => stream.message().await,
+        }
+    }
+}
+
+impl Service<http::Request<Body>> for HiveChannel {
+    type Response = http::Response<Body>;
+    type Error = StdError;
+    type Future = BoxFuture<Self::Response, Self::Error>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, mut request: http::Request<Body>) -> Self::Future {
+        request_id::propagate(&mut request);
+        if let Err(status) = self.breaker.try_acquire() {
+            return Box::pin(async move { Err(status.into()) });
+        }
+
+        let timeout = self.timeout;
+        let breaker = self.breaker.clone();
+        let response = self.inner.call(request);
+        Box::pin(async move {
+            let result: Result<Self::Response, Self::Error> = match timeout {
+                Some(timeout) => match tokio::time::timeout(timeout, response).await {
+                    Ok(result) => result.map_err(Into::into),
+                    Err(_) => Err(deadline_exceeded(timeout).into()),
+                },
+                None => response.await.map_err(Into::into),
+            };
+            match &result {
+                Ok(_) => breaker.record_success(),
+                Err(_) => breaker.record_failure(),
+            }
+            result
+        })
+    }
+}
+
+fn deadline_exceeded(timeout: Duration) -> Status {
+    Status::deadline_exceeded(format!(
+        "Hive did not respond within {}ms.",
+        timeout.as_millis()
+    ))
+}
+
 impl AppState {
     pub fn new(db: Arc<DbManager>) -> Self {
+        Self::with_hive_client_config(db, HiveClientConfig::default())
+    }
+
+    pub fn with_hive_client_config(db: Arc<DbManager>, config: HiveClientConfig) -> Self {
         Self {
+            hive_channel: Arc::new(ChannelPool::with_config(config)),
+            job_manager: Arc::new(JobManager::new(db.clone())),
+            file_watcher: Arc::new(FileWatcher::default()),
+            pre_submit_hook: None,
+            update_url: None,
+            restart: Arc::new(RestartSignal::default()),
+            recovery_log: Arc::new(RecoveryLog::new(db.root().join(RECOVERY_LOG_FILE))),
+            sync_write_buffer_size: DEFAULT_SYNC_WRITE_BUFFER_SIZE,
+            sync_open_files: Arc::new(Semaphore::new(DEFAULT_MAX_OPEN_FILES)),
             db,
-            hive_channel: Arc::new(ChannelPool::new()),
-            job_manager: Arc::new(JobManager::new()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::daemon_server::circuit_breaker::CircuitBreakerConfig;
+    use crate::daemon_server::handlers::edge::stub_hive::{self, StubHive};
+    use crate::hive_pb::{BonjourReq, hive_service_client::HiveServiceClient};
+
+    #[tokio::test]
+    async fn request_times_out_when_hive_never_responds() {
+        // 只接受连接，从不回应任何数据
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            let mut sockets = vec![];
+            while let Ok((socket, _)) = listener.accept().await {
+                sockets.push(socket);
+            }
+        });
+
+        let pool = ChannelPool::with_config(HiveClientConfig {
+            request_timeout: Some(Duration::from_millis(100)),
+            ..Default::default()
+        });
+        let channel = pool
+            .get_channel(&format!("http://127.0.0.1:{port}"))
+            .unwrap();
+
+        let status = HiveServiceClient::new(channel)
+            .bonjour(BonjourReq {})
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::DeadlineExceeded);
+    }
+
+    #[tokio::test]
+    async fn circuit_opens_when_hive_is_unreachable() {
+        // 先占用一个端口再释放，保证该端口上没有服务
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        drop(listener);
+
+        let pool = ChannelPool::with_config(HiveClientConfig {
+            circuit_breaker: CircuitBreakerConfig {
+                failure_threshold: 2,
+                open_duration: Duration::from_secs(30),
+            },
+            ..Default::default()
+        });
+        let channel = pool
+            .get_channel(&format!("http://127.0.0.1:{port}"))
+            .unwrap();
+        let mut client = HiveServiceClient::new(channel);
+
+        for _ in 0..2 {
+            let status = client.bonjour(BonjourReq {}).await.unwrap_err();
+            assert_ne!(status.message(), "circuit breaker open");
+        }
+        let status = client.bonjour(BonjourReq {}).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::Unavailable);
+        assert_eq!(status.message(), "circuit breaker open");
+    }
+
+    #[tokio::test]
+    async fn pooled_connections_share_one_circuit_breaker() {
+        let addr = stub_hive::spawn(StubHive::default()).await;
+        let pool = ChannelPool::with_config(HiveClientConfig {
+            connection_pool_size: 4,
+            ..Default::default()
+        });
+
+        let channels: Vec<_> = (0..8).map(|_| pool.get_channel(&addr).unwrap()).collect();
+        assert!(
+            channels
+                .iter()
+                .all(|channel| Arc::ptr_eq(&channel.breaker, &channels[0].breaker))
+        );
+        for channel in channels {
+            HiveServiceClient::new(channel)
+                .bonjour(BonjourReq {})
+                .await
+                .unwrap();
         }
     }
 }
@@ -0,0 +1,70 @@
+//! 上传带宽限制
+//!
+//! 令牌桶的容量为一秒的流量，空闲后允许短暂地以更高的速率上传，之后按限制的速率上传。
+//! 所有上传共用同一个令牌桶，等待令牌的请求按先后顺序获得令牌。
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+
+pub struct TokenBucketThrottle {
+    bytes_per_sec: u64,
+    bucket: Mutex<Bucket>,
+}
+
+struct Bucket {
+    /// 当前可用的字节数，为负时表示已经透支
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucketThrottle {
+    pub fn new(bytes_per_sec: u64) -> Self {
+        let bytes_per_sec = bytes_per_sec.max(1);
+        Self {
+            bytes_per_sec,
+            bucket: Mutex::new(Bucket {
+                tokens: bytes_per_sec as f64,
+                last_refill: Instant::now(),
+            }),
+        }
+    }
+
+    /// 获取发送 `bytes` 字节所需的令牌，令牌不足时等待至补足为止
+    pub async fn acquire(&self, bytes: usize) {
+        let rate = self.bytes_per_sec as f64;
+        // 等待期间持有锁，后来的请求排在后面
+        let mut bucket = self.bucket.lock().await;
+        let now = Instant::now();
+        let elapsed = now.duration_since(bucket.last_refill).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * rate).min(rate);
+        bucket.last_refill = now;
+
+        bucket.tokens -= bytes as f64;
+        if bucket.tokens < 0.0 {
+            tokio::time::sleep(Duration::from_secs_f64(-bucket.tokens / rate)).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::daemon_server::handlers::file::submit::FRAME_SIZE;
+
+    #[tokio::test]
+    async fn upload_is_limited_to_configured_bandwidth() {
+        const MIB: usize = 1024 * 1024;
+        let throttle = TokenBucketThrottle::new(MIB as u64);
+        let file = vec![0u8; 10 * MIB];
+
+        let start = Instant::now();
+        for frame in file.chunks(FRAME_SIZE) {
+            throttle.acquire(frame.len()).await;
+        }
+        let elapsed = start.elapsed();
+
+        // 第一秒的流量由桶中已有的令牌提供
+        let expected = Duration::from_secs(9);
+        assert!(elapsed >= expected.mul_f64(0.8), "elapsed {elapsed:?}");
+        assert!(elapsed <= expected.mul_f64(1.2), "elapsed {elapsed:?}");
+    }
+}
@@ -0,0 +1,466 @@
+//! edge daemon 自动更新
+//!
+//! `update_url` 指向一个 JSON 清单，描述最新版本及其下载地址：
+//!
+//! ```json
+//! { "version": "0.2.0", "url": "https://example.com/crv-edge", "sha256": "..." }
+//! ```
+//!
+//! 应用更新时先把新版本下载到可执行文件旁的临时文件并校验 SHA-256，再将当前可执行文件重命名为
+//! `<exe>.old` 作为备份，把新文件移动到原位置，最后关闭服务并重新启动。新版本绑定监听地址后才删除备份，
+//! 启动失败时用备份恢复。
+use crate::daemon_server::error::{AppError, AppResult};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::Notify;
+
+/// 当前 edge 的版本
+pub const CURRENT_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// 下载清单与新版本的超时时间
+const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// `update_url` 返回的更新清单
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct UpdateManifest {
+    /// 最新版本号，形如 `1.2.3`
+    pub version: String,
+    /// 新版本可执行文件的下载地址
+    pub url: String,
+    /// 新版本可执行文件的 SHA-256（十六进制）
+    pub sha256: String,
+}
+
+/// 检查更新的结果
+#[derive(Debug, Clone)]
+pub struct UpdateCheck {
+    pub manifest: UpdateManifest,
+    /// 清单中的版本是否比当前版本新
+    pub update_available: bool,
+}
+
+/// 请求重启 daemon 的信号，服务关闭后由启动流程执行重启
+#[derive(Default)]
+pub struct RestartSignal {
+    requested: AtomicBool,
+    notify: Notify,
+}
+
+impl RestartSignal {
+    /// 请求关闭服务并重启
+    pub fn request(&self) {
+        self.requested.store(true, Ordering::SeqCst);
+        self.notify.notify_one();
+    }
+
+    /// 等待重启请求
+    pub async fn wait(&self) {
+        self.notify.notified().await;
+    }
+
+    pub fn is_requested(&self) -> bool {
+        self.requested.load(Ordering::SeqCst)
+    }
+}
+
+fn http_client() -> &'static reqwest::Client {
+    static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+    CLIENT.get_or_init(|| {
+        reqwest::Client::builder()
+            .timeout(DOWNLOAD_TIMEOUT)
+            .build()
+            .expect("failed to build update http client")
+    })
+}
+
+/// 解析 `x.y.z` 形式的版本号，允许前缀 `v` 并忽略 `-` 之后的预发布标识
+fn parse_version(version: &str) -> Option<(u64, u64, u64)> {
+    let version = version.trim().trim_start_matches('v');
+    let version = version.split(['-', '+']).next()?;
+    let mut parts = version.split('.');
+    let major = parts.next()?.parse().ok()?;
+    let minor = parts.next().unwrap_or("0").parse().ok()?;
+    let patch = parts.next().unwrap_or("0").parse().ok()?;
+    if parts.next().is_some() {
+        return None;
+    }
+    Some((major, minor, patch))
+}
+
+/// `candidate` 是否比 `current` 新，无法解析的版本号视为不新
+pub fn is_newer(candidate: &str, current: &str) -> bool {
+    match (parse_version(candidate), parse_version(current)) {
+        (Some(candidate), Some(current)) => candidate > current,
+        _ => false,
+    }
+}
+
+/// 获取更新清单并与当前版本比较
+pub async fn check_for_update(update_url: &str) -> AppResult<UpdateCheck> {
+    let body = http_client()
+        .get(update_url)
+        .send()
+        .await
+        .and_then(|rsp| rsp.error_for_status())
+        .map_err(|e| AppError::UpdateFailed(format!("failed to fetch {update_url}: {e}")))?
+        .bytes()
+        .await
+        .map_err(|e| AppError::UpdateFailed(format!("failed to fetch {update_url}: {e}")))?;
+    let manifest: UpdateManifest = serde_json::from_slice(&body)
+        .map_err(|e| AppError::UpdateFailed(format!("invalid update manifest: {e}")))?;
+    if parse_version(&manifest.version).is_none() {
+        return Err(AppError::UpdateFailed(format!(
+            "invalid version `{}` in update manifest",
+            manifest.version
+        )));
+    }
+    Ok(UpdateCheck {
+        update_available: is_newer(&manifest.version, CURRENT_VERSION),
+        manifest,
+    })
+}
+
+/// 下载新版本到 `dest` 并校验 SHA-256，校验失败时删除已下载的文件
+pub async fn download_verified(manifest: &UpdateManifest, dest: &Path) -> AppResult<()> {
+    let result = download_to(manifest, dest).await;
+    if result.is_err() {
+        let _ = tokio::fs::remove_file(dest).await;
+    }
+    result
+}
+
+async fn download_to(manifest: &UpdateManifest, dest: &Path) -> AppResult<()> {
+    let download_err = |e: reqwest::Error| {
+        AppError::UpdateFailed(format!("failed to download {}: {e}", manifest.url))
+    };
+    let io_err = |e: std::io::Error| {
+        AppError::UpdateFailed(format!("failed to write {}: {e}", dest.display()))
+    };
+
+    let mut rsp = http_client()
+        .get(&manifest.url)
+        .send()
+        .await
+        .and_then(|rsp| rsp.error_for_status())
+        .map_err(download_err)?;
+    let mut file = tokio::fs::File::create(dest).await.map_err(io_err)?;
+    let mut hasher = Sha256::new();
+    while let Some(chunk) = rsp.chunk().await.map_err(download_err)? {
+        hasher.update(&chunk);
+        file.write_all(&chunk).await.map_err(io_err)?;
+    }
+    file.sync_all().await.map_err(io_err)?;
+
+    let actual = hex::encode(hasher.finalize());
+    if !actual.eq_ignore_ascii_case(manifest.sha256.trim()) {
+        return Err(AppError::UpdateFailed(format!(
+            "checksum mismatch: expected {}, got {actual}",
+            manifest.sha256
+        )));
+    }
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        tokio::fs::set_permissions(dest, std::fs::Permissions::from_mode(0o755))
+            .await
+            .map_err(io_err)?;
+    }
+    Ok(())
+}
+
+/// 可执行文件的备份路径
+pub fn backup_path(exe: &Path) -> PathBuf {
+    let mut path = exe.as_os_str().to_owned();
+    path.push(".old");
+    PathBuf::from(path)
+}
+
+/// 下载新版本时使用的临时文件路径，与可执行文件位于同一目录以便原子替换
+fn staging_path(exe: &Path) -> PathBuf {
+    let mut path = exe.as_os_str().to_owned();
+    path.push(".download");
+    PathBuf::from(path)
+}
+
+/// 用 `new_binary` 替换 `exe`，原文件保留为备份；替换失败时恢复原文件
+pub fn install(new_binary: &Path, exe: &Path) -> AppResult<()> {
+    let backup = backup_path(exe);
+    let io_err = |e: std::io::Error| AppError::UpdateFailed(format!("failed to install: {e}"));
+    if backup.exists() {
+        std::fs::remove_file(&backup).map_err(io_err)?;
+    }
+    std::fs::rename(exe, &backup).map_err(io_err)?;
+    if let Err(e) = std::fs::rename(new_binary, exe) {
+        let _ = std::fs::rename(&backup, exe);
+        return Err(io_err(e));
+    }
+    Ok(())
+}
+
+/// 用备份恢复 `exe`，没有备份时不做任何事
+pub fn rollback(exe: &Path) -> std::io::Result<()> {
+    let backup = backup_path(exe);
+    if !backup.exists() {
+        return Ok(());
+    }
+    std::fs::rename(backup, exe)
+}
+
+/// 新版本启动成功后删除备份
+pub fn discard_backup(exe: &Path) {
+    let backup = backup_path(exe);
+    if !backup.exists() {
+        return;
+    }
+    match std::fs::remove_file(&backup) {
+        Ok(()) => println!("Removed previous binary {}", backup.display()),
+        Err(e) => eprintln!("Failed to remove previous binary {}: {e}", backup.display()),
+    }
+}
+
+/// 检查并安装更新，返回安装的版本；已是最新版本时返回 `None`
+///
+/// 安装后需要重启 daemon 才会运行新版本。
+pub async fn apply_update(update_url: &str, exe: &Path) -> AppResult<Option<String>> {
+    let check = check_for_update(update_url).await?;
+    if !check.update_available {
+        return Ok(None);
+    }
+    let staging = staging_path(exe);
+    download_verified(&check.manifest, &staging).await?;
+    if let Err(e) = install(&staging, exe) {
+        let _ = std::fs::remove_file(&staging);
+        return Err(e);
+    }
+    Ok(Some(check.manifest.version))
+}
+
+/// 以相同的参数启动新版本的可执行文件，替换当前进程
+///
+/// Unix 下通过 `exec` 原地替换当前进程，Windows 下启动新进程后退出当前进程。启动失败时用备份恢复
+/// 原可执行文件并返回错误。
+pub fn restart() -> AppResult<()> {
+    let exe = std::env::current_exe()
+        .map_err(|e| AppError::UpdateFailed(format!("failed to locate executable: {e}")))?;
+    let args: Vec<_> = std::env::args_os().skip(1).collect();
+    restart_exe(&exe, &args)
+}
+
+/// 以 `args` 启动 `exe` 替换当前进程，失败时用备份恢复 `exe`
+fn restart_exe(exe: &Path, args: &[OsString]) -> AppResult<()> {
+    println!("Restarting {}", exe.display());
+
+    #[cfg(unix)]
+    let err = {
+        use std::os::unix::process::CommandExt;
+        // exec 成功时不会返回
+        std::process::Command::new(exe).args(args).exec()
+    };
+    #[cfg(not(unix))]
+    let err = match std::process::Command::new(exe).args(args).spawn() {
+        Ok(_) => std::process::exit(0),
+        Err(e) => e,
+    };
+
+    if let Err(e) = rollback(exe) {
+        eprintln!("Failed to restore previous binary: {e}");
+    }
+    Err(AppError::UpdateFailed(format!(
+        "failed to start {}: {err}",
+        exe.display()
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::AsyncReadExt;
+    use tokio::net::TcpListener;
+
+    /// 返回固定内容的简易 HTTP 服务，按请求路径返回对应的响应体；`routes` 接收服务的根地址
+    async fn spawn_http_server(
+        routes: impl FnOnce(&str) -> Vec<(&'static str, Vec<u8>)>,
+    ) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let routes = routes(&base);
+        tokio::spawn(async move {
+            loop {
+                let Ok((mut stream, _)) = listener.accept().await else {
+                    return;
+                };
+                let routes = routes.clone();
+                tokio::spawn(async move {
+                    let mut request = Vec::new();
+                    let mut buf = [0u8; 1024];
+                    while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+                        let n = stream.read(&mut buf).await.unwrap();
+                        if n == 0 {
+                            return;
+                        }
+                        request.extend_from_slice(&buf[..n]);
+                    }
+                    let request = String::from_utf8_lossy(&request);
+                    let path = request.split_whitespace().nth(1).unwrap_or("").to_string();
+                    let (status, body) = match routes.iter().find(|(p, _)| *p == path) {
+                        Some((_, body)) => ("200 OK", body.clone()),
+                        None => ("404 Not Found", Vec::new()),
+                    };
+                    let head = format!(
+                        "HTTP/1.1 {status}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                        body.len()
+                    );
+                    stream.write_all(head.as_bytes()).await.unwrap();
+                    stream.write_all(&body).await.unwrap();
+                });
+            }
+        });
+        base
+    }
+
+    fn manifest_json(base: &str, version: &str, sha256: &str) -> Vec<u8> {
+        serde_json::json!({
+            "version": version,
+            "url": format!("{base}/crv-edge"),
+            "sha256": sha256,
+        })
+        .to_string()
+        .into_bytes()
+    }
+
+    fn temp_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("crv-edge-update-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn versions_are_compared_numerically() {
+        assert!(is_newer("0.10.0", "0.9.9"));
+        assert!(is_newer("v1.0.0", "0.1.0"));
+        assert!(is_newer("0.1.1", "0.1.0"));
+        assert!(!is_newer("0.1.0", "0.1.0"));
+        assert!(!is_newer("0.0.9", "0.1.0"));
+        assert!(!is_newer("latest", "0.1.0"));
+    }
+
+    #[tokio::test]
+    async fn update_is_downloaded_verified_and_installed() {
+        let binary = b"new crv-edge binary".to_vec();
+        let sha256 = hex::encode(Sha256::digest(&binary));
+        let base = spawn_http_server(|base| {
+            vec![
+                ("/manifest.json", manifest_json(base, "99.0.0", &sha256)),
+                ("/crv-edge", binary.clone()),
+            ]
+        })
+        .await;
+
+        let check = check_for_update(&format!("{base}/manifest.json"))
+            .await
+            .unwrap();
+        assert!(check.update_available);
+        assert_eq!(check.manifest.version, "99.0.0");
+
+        let dir = temp_dir();
+        let exe = dir.join("crv-edge");
+        std::fs::write(&exe, b"old crv-edge binary").unwrap();
+
+        let installed = apply_update(&format!("{base}/manifest.json"), &exe)
+            .await
+            .unwrap();
+        assert_eq!(installed.as_deref(), Some("99.0.0"));
+        assert_eq!(std::fs::read(&exe).unwrap(), binary);
+        assert_eq!(
+            std::fs::read(backup_path(&exe)).unwrap(),
+            b"old crv-edge binary"
+        );
+        assert!(!staging_path(&exe).exists());
+
+        // 新版本启动失败时可以恢复旧版本
+        rollback(&exe).unwrap();
+        assert_eq!(std::fs::read(&exe).unwrap(), b"old crv-edge binary");
+        assert!(!backup_path(&exe).exists());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn failed_restart_restores_previous_binary() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = temp_dir();
+        let exe = dir.join("crv-edge");
+        let new_binary = dir.join("crv-edge.new");
+        std::fs::write(&exe, b"old crv-edge binary").unwrap();
+        std::fs::write(&new_binary, b"new crv-edge binary").unwrap();
+        install(&new_binary, &exe).unwrap();
+
+        // 新版本没有执行权限，exec 一定会失败而不会替换测试进程
+        std::fs::set_permissions(&exe, std::fs::Permissions::from_mode(0o644)).unwrap();
+        let err = restart_exe(&exe, &[]).unwrap_err();
+        assert!(matches!(err, AppError::UpdateFailed(msg) if msg.contains("failed to start")));
+        assert_eq!(std::fs::read(&exe).unwrap(), b"old crv-edge binary");
+        assert!(!backup_path(&exe).exists());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn checksum_mismatch_keeps_current_binary() {
+        let base = spawn_http_server(|base| {
+            vec![
+                (
+                    "/manifest.json",
+                    manifest_json(base, "99.0.0", &"0".repeat(64)),
+                ),
+                ("/crv-edge", b"tampered binary".to_vec()),
+            ]
+        })
+        .await;
+
+        let dir = temp_dir();
+        let exe = dir.join("crv-edge");
+        std::fs::write(&exe, b"old crv-edge binary").unwrap();
+
+        let err = apply_update(&format!("{base}/manifest.json"), &exe)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, AppError::UpdateFailed(msg) if msg.contains("checksum mismatch")));
+        assert_eq!(std::fs::read(&exe).unwrap(), b"old crv-edge binary");
+        assert!(!staging_path(&exe).exists());
+        assert!(!backup_path(&exe).exists());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn current_version_needs_no_update() {
+        let base = spawn_http_server(|base| {
+            vec![(
+                "/manifest.json",
+                manifest_json(base, CURRENT_VERSION, &"0".repeat(64)),
+            )]
+        })
+        .await;
+
+        let dir = temp_dir();
+        let exe = dir.join("crv-edge");
+        std::fs::write(&exe, b"old crv-edge binary").unwrap();
+        let installed = apply_update(&format!("{base}/manifest.json"), &exe)
+            .await
+            .unwrap();
+        assert_eq!(installed, None);
+        assert!(!backup_path(&exe).exists());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,205 @@
+//! 监听工作区目录，自动将被修改的已同步文件标记为 Edit
+use crate::daemon_server::db::active_file::Action;
+use crate::daemon_server::error::{AppError, AppResult};
+use crate::daemon_server::handlers::workspace::checkpoint::local_chunk_hashes;
+use crate::daemon_server::state::AppState;
+use crv_core::path::basic::LocalPath;
+use crv_core::path::engine::PathEngine;
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+/// 收到事件后等待的时间，合并同一次写入产生的多个事件，
+/// 同时让 sync 等写入文件后再更新元数据的操作先完成
+const DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// 文件监听器，停止时丢弃底层的 watcher，处理事件的任务随之退出
+#[derive(Default)]
+pub struct FileWatcher {
+    watcher: Mutex<Option<RecommendedWatcher>>,
+}
+
+impl FileWatcher {
+    pub fn is_running(&self) -> bool {
+        self.watcher.lock().unwrap().is_some()
+    }
+
+    /// 监听所有已确认的工作区的根目录，返回被监听的工作区名称；已经在监听时重新加载工作区列表
+    pub fn start(&self, state: AppState) -> AppResult<Vec<String>> {
+        let mut workspaces = Vec::new();
+        for workspace_name in state.db.get_all_workspaces()? {
+            if let Some(meta) = state.db.get_confirmed_workspace_meta(&workspace_name)? {
+                workspaces.push((workspace_name, meta.config));
+            }
+        }
+
+        let (tx, rx) = mpsc::unbounded_channel();
+        let mut watcher = notify::recommended_watcher(move |event: notify::Result<Event>| {
+            let Ok(event) = event else {
+                return;
+            };
+            if matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+                for path in event.paths {
+                    let _ = tx.send(path);
+                }
+            }
+        })
+        .map_err(|e| AppError::Internal(format!("Failed to create file watcher: {e}")))?;
+
+        let mut workspace_names = Vec::with_capacity(workspaces.len());
+        let mut path_engines = Vec::with_capacity(workspaces.len());
+        for (workspace_name, config) in workspaces {
+            let root_dir = config.root_dir.to_local_path_string();
+            watcher
+                .watch(Path::new(&root_dir), RecursiveMode::Recursive)
+                .map_err(|e| AppError::Internal(format!("Failed to watch {root_dir}: {e}")))?;
+            path_engines.push(PathEngine::new(config, &workspace_name));
+            workspace_names.push(workspace_name);
+        }
+
+        tokio::spawn(track_changes(state, path_engines, rx));
+        *self.watcher.lock().unwrap() = Some(watcher);
+        Ok(workspace_names)
+    }
+
+    /// 停止监听，返回停止前是否在监听
+    pub fn stop(&self) -> bool {
+        self.watcher.lock().unwrap().take().is_some()
+    }
+}
+
+async fn track_changes(
+    state: AppState,
+    path_engines: Vec<PathEngine>,
+    mut rx: mpsc::UnboundedReceiver<PathBuf>,
+) {
+    while let Some(path) = rx.recv().await {
+        tokio::time::sleep(DEBOUNCE).await;
+        let mut paths = HashSet::from([path]);
+        while let Ok(path) = rx.try_recv() {
+            paths.insert(path);
+        }
+        for path in paths {
+            if let Err(e) = track_change(&state, &path_engines, &path).await {
+                eprintln!("Failed to track change of {}: {e}", path.display());
+            }
+        }
+    }
+}
+
+/// 文件已同步且内容与当前版本不一致时标记为 Edit，返回是否进行了标记
+async fn track_change(
+    state: &AppState,
+    path_engines: &[PathEngine],
+    path: &Path,
+) -> AppResult<bool> {
+    let Ok(local_path) = LocalPath::parse(&path.to_string_lossy()) else {
+        return Ok(false);
+    };
+    for path_engine in path_engines {
+        let Some(workspace_path) = path_engine.local_path_to_workspace_path(&local_path) else {
+            continue;
+        };
+        // 只跟踪已同步的文件，新文件仍需要通过 add 添加
+        let Some(file_meta) = state.db.get_file_meta(&workspace_path)? else {
+            continue;
+        };
+        if state.db.get_active_file_action(&workspace_path)?.is_some() {
+            continue;
+        }
+        let Some(chunk_hashes) = local_chunk_hashes(&local_path.to_local_path_string()).await
+        else {
+            continue;
+        };
+        if chunk_hashes == file_meta.chunk_hashes {
+            continue;
+        }
+        state
+            .db
+            .set_active_file_action(workspace_path, Action::Edit)?;
+        return Ok(true);
+    }
+    Ok(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::daemon_server::db::file::{FileLocation, FileMeta, FileRevision};
+    use crate::test_support::TempDb;
+    use crv_core::path::basic::{DepotPath, WorkspacePath};
+    use crv_core::repository::compute_chunk_hash;
+    use crv_core::workspace::entity::WorkspaceConfig;
+    use std::io::Write;
+
+    #[tokio::test]
+    async fn modified_file_is_marked_as_edit() {
+        let db = TempDb::new();
+        let workspace_root = db.root().join("ws");
+        std::fs::create_dir_all(&workspace_root).unwrap();
+        let state = db.state();
+        let workspace_root = format!("{}/", workspace_root.to_string_lossy());
+        let config =
+            WorkspaceConfig::from_specification("ws", &workspace_root, "//... //ws/").unwrap();
+        state
+            .db
+            .create_workspace_pending("ws".to_string(), config)
+            .unwrap();
+        state.db.confirm_workspace("ws".to_string()).unwrap();
+
+        let local_path = format!("{workspace_root}a.txt");
+        std::fs::write(&local_path, "hello").unwrap();
+        let workspace_path = WorkspacePath::parse("//ws/a.txt").unwrap();
+        let meta = FileMeta {
+            location: FileLocation {
+                local_path: LocalPath::parse(&local_path).unwrap(),
+                workspace_path: workspace_path.clone(),
+                depot_path: DepotPath::parse("//a.txt").unwrap(),
+            },
+            current_revision: FileRevision {
+                generation: 1,
+                revision: 1,
+            },
+            changelist_id: 1,
+            size: 5,
+            chunk_hashes: vec![hex::encode(compute_chunk_hash(b"hello"))],
+        };
+        state
+            .db
+            .set_file_meta(workspace_path.clone(), meta)
+            .unwrap();
+
+        let watcher = FileWatcher::default();
+        assert_eq!(watcher.start(state.clone()).unwrap(), vec!["ws"]);
+        // 未同步的文件不会被跟踪
+        std::fs::write(format!("{workspace_root}b.txt"), "new").unwrap();
+        std::fs::OpenOptions::new()
+            .append(true)
+            .open(&local_path)
+            .unwrap()
+            .write_all(b"!")
+            .unwrap();
+
+        let mut action = None;
+        for _ in 0..50 {
+            action = state.db.get_active_file_action(&workspace_path).unwrap();
+            if action.is_some() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        assert!(matches!(action, Some(Action::Edit)));
+        assert!(
+            state
+                .db
+                .get_active_file_action(&WorkspacePath::parse("//ws/b.txt").unwrap())
+                .unwrap()
+                .is_none()
+        );
+        assert!(watcher.stop());
+        assert!(!watcher.is_running());
+    }
+}
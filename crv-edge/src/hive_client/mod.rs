@@ -0,0 +1,186 @@
+//! 与 hive 的连接池以及连接 hive 时的版本兼容性检查
+use crate::daemon_server::error::{AppError, AppResult};
+use crate::daemon_server::state::AppState;
+use crate::hive_pb::{BonjourReq, BonjourRsp, hive_service_client::HiveServiceClient};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use tonic::transport::{Channel, Endpoint};
+
+/// 支持的最低 hive 主版本号，低于该版本时 daemon 拒绝启动
+pub const MIN_HIVE_MAJOR: u32 = 1;
+/// 建议的最低 hive 次版本号，低于该版本时仅给出警告
+pub const MIN_HIVE_MINOR: u32 = 1;
+
+/// 启动时探测 hive 的超时时间
+const HIVE_PROBE_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// 默认与每个 hive 建立的连接数
+pub const DEFAULT_HIVE_CONNECTION_POOL_SIZE: usize = 4;
+
+/// 与同一个 hive 的多条 HTTP/2 连接，请求按轮询顺序分配到各条连接上。
+///
+/// 单条连接上的并发流数量有上限，长时间的流式请求（如同步、上传）占满后会阻塞其他请求，
+/// 多条连接可以避免这种情况。连接在第一次使用时才真正建立。
+pub struct HiveClientPool {
+    channels: Vec<Channel>,
+    next: AtomicUsize,
+}
+
+impl HiveClientPool {
+    /// 为 `endpoint` 建立 `size` 条连接，`size` 为 0 时按 1 处理
+    pub fn connect_lazy(endpoint: &Endpoint, size: usize) -> Self {
+        Self {
+            channels: (0..size.max(1)).map(|_| endpoint.connect_lazy()).collect(),
+            next: AtomicUsize::new(0),
+        }
+    }
+
+    /// 池中的连接数
+    pub fn size(&self) -> usize {
+        self.channels.len()
+    }
+
+    /// 按轮询顺序取出下一条连接
+    pub fn next_channel(&self) -> Channel {
+        self.channels[self.next_index()].clone()
+    }
+
+    fn next_index(&self) -> usize {
+        self.next.fetch_add(1, Ordering::Relaxed) % self.channels.len()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HiveVersionStatus {
+    Compatible,
+    /// 主版本兼容，但次版本低于建议的版本，部分功能可能不可用
+    Outdated,
+}
+
+/// 根据 bonjour 返回的版本号判断 hive 是否兼容
+pub fn check_hive_version(rsp: &BonjourRsp) -> AppResult<HiveVersionStatus> {
+    // 版本号不会为负数，异常值按 0 处理
+    let major = u32::try_from(rsp.major_version).unwrap_or(0);
+    let minor = u32::try_from(rsp.minor_version).unwrap_or(0);
+    if major < MIN_HIVE_MAJOR {
+        return Err(AppError::HiveVersionIncompatible { major, minor });
+    }
+    if major == MIN_HIVE_MAJOR && minor < MIN_HIVE_MINOR {
+        return Ok(HiveVersionStatus::Outdated);
+    }
+    Ok(HiveVersionStatus::Compatible)
+}
+
+/// 向 hive 发送 bonjour 并检查版本，版本不兼容时返回错误
+///
+/// hive 不可达时只打印警告，daemon 仍可以离线工作，之后的请求会在连接 hive 时各自报错。
+pub async fn verify_hive_version(state: &AppState, remote_addr: &str) -> AppResult<()> {
+    let channel = match state.hive_channel.get_channel(remote_addr) {
+        Ok(channel) => channel,
+        Err(e) => {
+            eprintln!("Warning: failed to check hive version at {remote_addr}: {e}");
+            return Ok(());
+        }
+    };
+    let mut hive_client = HiveServiceClient::new(channel);
+    let probe = hive_client.bonjour(BonjourReq {});
+    let rsp = match tokio::time::timeout(HIVE_PROBE_TIMEOUT, probe).await {
+        Ok(Ok(rsp)) => rsp.into_inner(),
+        Ok(Err(status)) => {
+            eprintln!(
+                "Warning: failed to check hive version at {remote_addr}: {}",
+                status.message()
+            );
+            return Ok(());
+        }
+        Err(_) => {
+            eprintln!("Warning: failed to check hive version at {remote_addr}: timed out");
+            return Ok(());
+        }
+    };
+
+    if check_hive_version(&rsp)? == HiveVersionStatus::Outdated {
+        eprintln!(
+            "Warning: hive version {}.{} is older than {MIN_HIVE_MAJOR}.{MIN_HIVE_MINOR}, some features may be unavailable",
+            rsp.major_version, rsp.minor_version
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::daemon_server::handlers::edge::stub_hive::{self, StubHive};
+    use crate::test_support::TempDb;
+
+    fn bonjour_rsp(major_version: i32, minor_version: i32) -> BonjourRsp {
+        BonjourRsp {
+            major_version,
+            minor_version,
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn pool_hands_out_connections_round_robin() {
+        let endpoint = Endpoint::from_static("http://127.0.0.1:1");
+        let pool = HiveClientPool::connect_lazy(&endpoint, 3);
+        assert_eq!(pool.size(), 3);
+        let indexes: Vec<_> = (0..7).map(|_| pool.next_index()).collect();
+        assert_eq!(indexes, [0, 1, 2, 0, 1, 2, 0]);
+
+        let pool = HiveClientPool::connect_lazy(&endpoint, 0);
+        assert_eq!(pool.size(), 1);
+        assert_eq!(pool.next_index(), 0);
+        assert_eq!(pool.next_index(), 0);
+    }
+
+    #[test]
+    fn newer_hive_is_compatible() {
+        assert_eq!(
+            check_hive_version(&bonjour_rsp(1, 1)).unwrap(),
+            HiveVersionStatus::Compatible
+        );
+        assert_eq!(
+            check_hive_version(&bonjour_rsp(2, 0)).unwrap(),
+            HiveVersionStatus::Compatible
+        );
+    }
+
+    #[test]
+    fn older_minor_version_is_outdated() {
+        assert_eq!(
+            check_hive_version(&bonjour_rsp(1, 0)).unwrap(),
+            HiveVersionStatus::Outdated
+        );
+    }
+
+    #[test]
+    fn older_major_version_is_incompatible() {
+        assert!(matches!(
+            check_hive_version(&bonjour_rsp(0, 9)),
+            Err(AppError::HiveVersionIncompatible { major: 0, minor: 9 })
+        ));
+        assert!(matches!(
+            check_hive_version(&bonjour_rsp(-1, 0)),
+            Err(AppError::HiveVersionIncompatible { major: 0, .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn unreachable_hive_does_not_block_startup() {
+        let db = TempDb::new();
+        let state = db.state();
+
+        let addr = stub_hive::spawn(StubHive::default()).await;
+        verify_hive_version(&state, &addr).await.unwrap();
+
+        let addr = stub_hive::spawn(StubHive {
+            bonjour_error: Some(tonic::Status::unavailable("hive is down")),
+            ..Default::default()
+        })
+        .await;
+        verify_hive_version(&state, &addr).await.unwrap();
+    }
+}
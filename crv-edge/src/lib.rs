@@ -1,7 +1,12 @@
 pub mod client_manager;
 pub mod daemon_server;
+pub mod hive_client;
+pub mod metrics;
 pub mod utils;
 
+#[cfg(test)]
+pub(crate) mod test_support;
+
 pub mod pb {
     include!(concat!(env!("OUT_DIR"), "/daemon_proto.rs"));
 }
@@ -101,8 +101,8 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     runtime.spawn(async move {
         // 稍等片刻让 server 绑定端口
         tokio::time::sleep(Duration::from_millis(300)).await;
-        let endpoint = format!("http://[::1]:{}", bootstrap_config.daemon_port);
         let result = async {
+            let endpoint = bootstrap_config.daemon_url().ok()?;
             let mut client = SystemServiceClient::connect(endpoint).await.ok()?;
             let _ = client
                 .bonjour(tonic::Request::new(BonjourReq {}))
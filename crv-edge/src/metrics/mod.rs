@@ -0,0 +1,303 @@
+//! edge 的监控指标，以 Prometheus 文本格式通过 HTTP 的 `/metrics` 暴露
+//!
+//! 指标在进程内全局共享，通过 [`collector`] 获取；数据库相关的指标在每次抓取时从数据库中刷新。
+use crate::daemon_server::db::{DbError, DbManager};
+use http_body_util::Full;
+use hyper::body::{Bytes, Incoming};
+use hyper::server::conn::http1;
+use hyper::service::service_fn;
+use hyper::{Method, Request, Response, StatusCode};
+use hyper_util::rt::TokioIo;
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounterVec, IntGaugeVec, Opts, Registry, TextEncoder,
+};
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::{Arc, OnceLock};
+use std::task::{Context, Poll};
+use std::time::Instant;
+use tokio::net::TcpListener;
+use tokio::task::JoinHandle;
+use tonic::codegen::{Service, http};
+use tower_layer::Layer;
+
+pub struct MetricsCollector {
+    registry: Registry,
+    /// 按方法统计的 RPC 调用次数
+    rpc_calls_total: IntCounterVec,
+    /// 按方法统计的 RPC 耗时，流式 RPC 只统计到开始返回数据为止
+    rpc_duration_seconds: HistogramVec,
+    /// 与 hive 之间传输的 chunk 字节数，按 upload/download 区分
+    chunk_bytes_total: IntCounterVec,
+    /// 所有工作区中处于打开状态（add/edit/delete/move）的文件数
+    active_files: IntGaugeVec,
+    /// 各列族的估计数据大小
+    db_column_family_bytes: IntGaugeVec,
+}
+
+static COLLECTOR: OnceLock<MetricsCollector> = OnceLock::new();
+
+/// 全局的指标收集器
+pub fn collector() -> &'static MetricsCollector {
+    COLLECTOR.get_or_init(MetricsCollector::new)
+}
+
+impl MetricsCollector {
+    fn new() -> Self {
+        let registry = Registry::new();
+        let rpc_calls_total = IntCounterVec::new(
+            Opts::new("rpc_calls_total", "Number of gRPC calls handled by edge."),
+            &["method"],
+        )
+        .unwrap();
+        let rpc_duration_seconds = HistogramVec::new(
+            HistogramOpts::new(
+                "rpc_duration_seconds",
+                "Latency of gRPC calls handled by edge.",
+            ),
+            &["method"],
+        )
+        .unwrap();
+        let chunk_bytes_total = IntCounterVec::new(
+            Opts::new(
+                "chunk_bytes_total",
+                "Chunk bytes transferred between edge and hive.",
+            ),
+            &["direction"],
+        )
+        .unwrap();
+        let active_files = IntGaugeVec::new(
+            Opts::new(
+                "active_files",
+                "Files opened for add/edit/delete/move in all workspaces.",
+            ),
+            &["action"],
+        )
+        .unwrap();
+        let db_column_family_bytes = IntGaugeVec::new(
+            Opts::new(
+                "db_column_family_bytes",
+                "Estimated live data size of each RocksDB column family.",
+            ),
+            &["cf"],
+        )
+        .unwrap();
+
+        registry
+            .register(Box::new(rpc_calls_total.clone()))
+            .unwrap();
+        registry
+            .register(Box::new(rpc_duration_seconds.clone()))
+            .unwrap();
+        registry
+            .register(Box::new(chunk_bytes_total.clone()))
+            .unwrap();
+        registry.register(Box::new(active_files.clone())).unwrap();
+        registry
+            .register(Box::new(db_column_family_bytes.clone()))
+            .unwrap();
+
+        Self {
+            registry,
+            rpc_calls_total,
+            rpc_duration_seconds,
+            chunk_bytes_total,
+            active_files,
+            db_column_family_bytes,
+        }
+    }
+
+    pub fn observe_rpc(&self, method: &str, started_at: Instant) {
+        self.rpc_calls_total.with_label_values(&[method]).inc();
+        self.rpc_duration_seconds
+            .with_label_values(&[method])
+            .observe(started_at.elapsed().as_secs_f64());
+    }
+
+    pub fn add_uploaded_bytes(&self, bytes: usize) {
+        self.chunk_bytes_total
+            .with_label_values(&["upload"])
+            .inc_by(bytes as u64);
+    }
+
+    pub fn add_downloaded_bytes(&self, bytes: usize) {
+        self.chunk_bytes_total
+            .with_label_values(&["download"])
+            .inc_by(bytes as u64);
+    }
+
+    /// 从数据库中刷新打开的文件数和列族大小
+    pub fn refresh_db_metrics(&self, db: &DbManager) -> Result<(), DbError> {
+        self.active_files.reset();
+        for (action, count) in db.count_active_files()? {
+            self.active_files
+                .with_label_values(&[action.as_str()])
+                .set(count as i64);
+        }
+        for (cf, bytes) in db.column_family_sizes()? {
+            self.db_column_family_bytes
+                .with_label_values(&[cf])
+                .set(bytes as i64);
+        }
+        Ok(())
+    }
+
+    /// 以 Prometheus 文本格式输出所有指标
+    pub fn encode(&self) -> String {
+        let mut buffer = vec![];
+        TextEncoder::new()
+            .encode(&self.registry.gather(), &mut buffer)
+            .unwrap();
+        String::from_utf8(buffer).unwrap()
+    }
+}
+
+/// 统计 gRPC 调用次数与耗时的中间件
+#[derive(Clone, Default)]
+pub struct MetricsLayer;
+
+impl<S> Layer<S> for MetricsLayer {
+    type Service = MetricsService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        MetricsService { inner }
+    }
+}
+
+#[derive(Clone)]
+pub struct MetricsService<S> {
+    inner: S,
+}
+
+impl<S, B> Service<http::Request<B>> for MetricsService<S>
+where
+    S: Service<http::Request<B>>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<S::Response, S::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: http::Request<B>) -> Self::Future {
+        let method = request.uri().path().to_string();
+        let started_at = Instant::now();
+        let response = self.inner.call(request);
+        Box::pin(async move {
+            let response = response.await;
+            collector().observe_rpc(&method, started_at);
+            response
+        })
+    }
+}
+
+/// 在指定地址上启动 `/metrics` 服务
+pub async fn start_server(addr: SocketAddr, db: Arc<DbManager>) -> std::io::Result<JoinHandle<()>> {
+    let listener = TcpListener::bind(addr).await?;
+    Ok(tokio::spawn(serve(listener, db)))
+}
+
+async fn serve(listener: TcpListener, db: Arc<DbManager>) {
+    loop {
+        let stream = match listener.accept().await {
+            Ok((stream, _)) => stream,
+            Err(e) => {
+                eprintln!("Metrics server failed to accept connection: {e}");
+                continue;
+            }
+        };
+        let db = db.clone();
+        tokio::spawn(async move {
+            let service = service_fn(move |request| handle(request, db.clone()));
+            if let Err(e) = http1::Builder::new()
+                .serve_connection(TokioIo::new(stream), service)
+                .await
+            {
+                eprintln!("Metrics server connection error: {e}");
+            }
+        });
+    }
+}
+
+async fn handle(
+    request: Request<Incoming>,
+    db: Arc<DbManager>,
+) -> Result<Response<Full<Bytes>>, Infallible> {
+    if request.method() != Method::GET || request.uri().path() != "/metrics" {
+        let mut response = Response::new(Full::new(Bytes::from_static(b"Not Found")));
+        *response.status_mut() = StatusCode::NOT_FOUND;
+        return Ok(response);
+    }
+
+    let collector = collector();
+    if let Err(e) = collector.refresh_db_metrics(&db) {
+        eprintln!("Failed to refresh db metrics: {e}");
+    }
+    let mut response = Response::new(Full::new(Bytes::from(collector.encode())));
+    response.headers_mut().insert(
+        http::header::CONTENT_TYPE,
+        http::HeaderValue::from_static("text/plain; version=0.0.4"),
+    );
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::daemon_server::service::SystemServiceImpl;
+    use crate::pb::BonjourReq;
+    use crate::pb::system_service_client::SystemServiceClient;
+    use crate::pb::system_service_server::SystemServiceServer;
+    use crate::test_support::TempDb;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio_stream::wrappers::TcpListenerStream;
+    use tonic::transport::Server;
+
+    #[tokio::test]
+    async fn metrics_endpoint_reports_rpc_calls() {
+        let db = TempDb::new();
+        let state = db.state();
+
+        let grpc_listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let grpc_addr = grpc_listener.local_addr().unwrap();
+        tokio::spawn(
+            Server::builder()
+                .layer(MetricsLayer)
+                .add_service(SystemServiceServer::new(SystemServiceImpl::new(state)))
+                .serve_with_incoming(TcpListenerStream::new(grpc_listener)),
+        );
+        let metrics_listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let metrics_addr = metrics_listener.local_addr().unwrap();
+        tokio::spawn(serve(metrics_listener, db.db()));
+
+        let mut client = SystemServiceClient::connect(format!("http://{grpc_addr}"))
+            .await
+            .unwrap();
+        for _ in 0..3 {
+            client.bonjour(BonjourReq {}).await.unwrap();
+        }
+
+        let mut stream = tokio::net::TcpStream::connect(metrics_addr).await.unwrap();
+        stream
+            .write_all(b"GET /metrics HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+            .await
+            .unwrap();
+        let mut body = String::new();
+        stream.read_to_string(&mut body).await.unwrap();
+
+        assert!(body.starts_with("HTTP/1.1 200"));
+        let line = body
+            .lines()
+            .find(|line| {
+                line.starts_with("rpc_calls_total{method=\"/daemon_proto.SystemService/Bonjour\"}")
+            })
+            .expect("rpc_calls_total for Bonjour should be reported");
+        let count: u64 = line.rsplit(' ').next().unwrap().parse().unwrap();
+        assert!(count >= 3);
+        assert!(body.contains("db_column_family_bytes{cf=\"active_file\"}"));
+    }
+}
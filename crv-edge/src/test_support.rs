@@ -0,0 +1,65 @@
+//! 测试用的临时目录与数据库，drop 时删除目录
+
+use crate::daemon_server::db::DbManager;
+use crate::daemon_server::state::AppState;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// 系统临时目录下新建的目录，drop 时连同其中的内容一起删除
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    pub fn new() -> Self {
+        let path = std::env::temp_dir().join(format!("crv-edge-test-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&path).unwrap();
+        Self(path)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+/// 打开在临时目录 `db` 子目录中的数据库。
+///
+/// 测试可以在 [`TempDb::root`] 下创建 workspace 等文件，drop 时整个目录一起删除，
+/// 因此使用数据库的 AppState 不能比它活得更久
+pub struct TempDb {
+    db: Arc<DbManager>,
+    dir: TempDir,
+}
+
+impl TempDb {
+    pub fn new() -> Self {
+        let dir = TempDir::new();
+        let db = Arc::new(DbManager::new(dir.path().join("db")).unwrap());
+        Self { db, dir }
+    }
+
+    pub fn root(&self) -> &Path {
+        self.dir.path()
+    }
+
+    pub fn db(&self) -> Arc<DbManager> {
+        self.db.clone()
+    }
+
+    pub fn state(&self) -> AppState {
+        AppState::new(self.db())
+    }
+}
+
+impl Deref for TempDb {
+    type Target = DbManager;
+
+    fn deref(&self) -> &DbManager {
+        &self.db
+    }
+}
@@ -6,8 +6,12 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         std::env::set_var("PROTOC", &protoc);
     }
 
+    let out_dir = std::path::PathBuf::from(std::env::var("OUT_DIR")?);
+
     // 编译 proto 文件，输出到 OUT_DIR
     tonic_prost_build::configure()
+        // 同时输出 FileDescriptorSet，供 gRPC 反射服务使用
+        .file_descriptor_set_path(out_dir.join("hive_descriptor.bin"))
         // 可选配置，比如关闭生成 server、client、改变输出路径等
         // .build_server(false)
         // .out_dir("src/generated")
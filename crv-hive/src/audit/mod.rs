@@ -0,0 +1,147 @@
+//! 审计日志：记录所有修改类操作（提交、锁定文件、注册、登录）
+//!
+//! 审计记录写入数据库的 `audit_log` 表，写入失败只记录日志，不影响操作本身。
+//! 调用方应通过 `tokio::spawn(emit(event))` 异步写入，避免增加请求的延迟。
+use crate::database::dao::dao;
+use tonic::Status;
+
+/// 被审计的操作类型
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AuditOp {
+    /// 提交 changelist
+    Submit,
+    /// 为提交锁定文件
+    LaunchSubmit,
+    Register,
+    Login,
+}
+
+impl AuditOp {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuditOp::Submit => "submit",
+            AuditOp::LaunchSubmit => "launch_submit",
+            AuditOp::Register => "register",
+            AuditOp::Login => "login",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "submit" => Some(AuditOp::Submit),
+            "launch_submit" => Some(AuditOp::LaunchSubmit),
+            "register" => Some(AuditOp::Register),
+            "login" => Some(AuditOp::Login),
+            _ => None,
+        }
+    }
+}
+
+/// 操作的结果
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AuditOutcome {
+    Success,
+    Failure,
+}
+
+impl AuditOutcome {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuditOutcome::Success => "success",
+            AuditOutcome::Failure => "failure",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "success" => Some(AuditOutcome::Success),
+            "failure" => Some(AuditOutcome::Failure),
+            _ => None,
+        }
+    }
+}
+
+/// 一条审计记录
+#[derive(Clone, Debug, PartialEq)]
+pub struct AuditEvent {
+    pub timestamp_millis: i64,
+    pub username: String,
+    pub operation: AuditOp,
+    /// 被操作的资源，例如 changelist id、提交票据、用户名
+    pub resource_id: String,
+    pub outcome: AuditOutcome,
+    pub detail: serde_json::Value,
+}
+
+impl AuditEvent {
+    /// 以当前时间创建审计记录
+    pub fn new(
+        operation: AuditOp,
+        username: impl Into<String>,
+        resource_id: impl Into<String>,
+        outcome: AuditOutcome,
+        detail: serde_json::Value,
+    ) -> Self {
+        Self {
+            timestamp_millis: chrono::Utc::now().timestamp_millis(),
+            username: username.into(),
+            operation,
+            resource_id: resource_id.into(),
+            outcome,
+            detail,
+        }
+    }
+
+    /// RPC 返回错误时的审计记录，错误信息记录在 detail 中
+    pub fn failed(
+        operation: AuditOp,
+        username: impl Into<String>,
+        resource_id: impl Into<String>,
+        status: &Status,
+    ) -> Self {
+        Self::new(
+            operation,
+            username,
+            resource_id,
+            AuditOutcome::Failure,
+            serde_json::json!({
+                "code": format!("{:?}", status.code()),
+                "message": status.message(),
+            }),
+        )
+    }
+}
+
+/// 审计日志的查询条件，`None` 表示不限制
+#[derive(Clone, Debug, Default)]
+pub struct AuditQuery {
+    /// 包含该时间点（毫秒）
+    pub since_millis: Option<i64>,
+    /// 不包含该时间点（毫秒）
+    pub until_millis: Option<i64>,
+    pub username: Option<String>,
+    pub operation: Option<AuditOp>,
+    pub limit: u64,
+}
+
+impl AuditQuery {
+    pub fn matches(&self, event: &AuditEvent) -> bool {
+        self.since_millis
+            .is_none_or(|t| event.timestamp_millis >= t)
+            && self.until_millis.is_none_or(|t| event.timestamp_millis < t)
+            && self.username.as_ref().is_none_or(|u| &event.username == u)
+            && self.operation.is_none_or(|op| event.operation == op)
+    }
+}
+
+/// 写入一条审计记录
+pub async fn emit(event: AuditEvent) {
+    if let Err(e) = dao().insert_audit_event(&event).await {
+        tracing::warn!(
+            "failed to write audit log: operation={}, username={}, resource_id={}: {e}",
+            event.operation.as_str(),
+            event.username,
+            event.resource_id
+        );
+    }
+}
@@ -0,0 +1,103 @@
+//! 登录失败锁定：统计窗口内连续失败 [`MAX_LOGIN_FAILURES`] 次后锁定账号 [`LOCKOUT_SECS`] 秒，
+//! 锁定期间的登录请求不校验密码直接拒绝，到期后自动解锁。
+use crate::database::dao::{Dao, DaoResult};
+
+/// 统计窗口内允许的连续失败次数
+pub const MAX_LOGIN_FAILURES: i32 = 5;
+/// 统计窗口（秒），从窗口内第一次失败开始计算
+pub const LOGIN_FAILURE_WINDOW_SECS: i64 = 15 * 60;
+/// 锁定时长（秒）
+pub const LOCKOUT_SECS: i64 = 15 * 60;
+
+/// 账号在 `now` 时是否处于锁定状态，锁定时返回锁定截止时间
+pub async fn locked_until(dao: &dyn Dao, username: &str, now: i64) -> DaoResult<Option<i64>> {
+    Ok(dao
+        .find_login_failures(username)
+        .await?
+        .and_then(|record| record.locked_until)
+        .filter(|&until| until > now))
+}
+
+/// 记录一次登录失败，达到阈值时锁定账号并返回锁定截止时间
+pub async fn record_failure(dao: &dyn Dao, username: &str, now: i64) -> DaoResult<Option<i64>> {
+    let record = dao
+        .increment_login_failures(username, now, LOGIN_FAILURE_WINDOW_SECS)
+        .await?;
+    if record.count < MAX_LOGIN_FAILURES {
+        return Ok(None);
+    }
+    let until = now + LOCKOUT_SECS;
+    dao.lock_account(username, until).await?;
+    Ok(Some(until))
+}
+
+/// 登录成功后清空失败记录
+pub async fn reset(dao: &dyn Dao, username: &str) -> DaoResult<()> {
+    dao.reset_login_failures(username).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::dao::MockDao;
+
+    async fn fail(dao: &MockDao, username: &str, now: i64, times: usize) -> Option<i64> {
+        let mut until = None;
+        for _ in 0..times {
+            until = record_failure(dao, username, now).await.unwrap();
+        }
+        until
+    }
+
+    #[tokio::test]
+    async fn locks_after_max_failures_within_window() {
+        let dao = MockDao::default();
+        let now = 1_000_000;
+
+        assert_eq!(fail(&dao, "alice", now, 4).await, None);
+        assert_eq!(locked_until(&dao, "alice", now).await.unwrap(), None);
+
+        let until = fail(&dao, "alice", now + 60, 1).await;
+        assert_eq!(until, Some(now + 60 + LOCKOUT_SECS));
+        assert_eq!(locked_until(&dao, "alice", now + 60).await.unwrap(), until);
+        // 其他用户不受影响
+        assert_eq!(locked_until(&dao, "bob", now + 60).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn lock_expires_after_lockout_period() {
+        let dao = MockDao::default();
+        let now = 1_000_000;
+        let until = fail(&dao, "alice", now, 5).await.unwrap();
+
+        assert!(
+            locked_until(&dao, "alice", until - 1)
+                .await
+                .unwrap()
+                .is_some()
+        );
+        assert_eq!(locked_until(&dao, "alice", until).await.unwrap(), None);
+
+        // 解锁后重新开始计数
+        assert_eq!(fail(&dao, "alice", until, 4).await, None);
+        assert_eq!(locked_until(&dao, "alice", until).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn failures_outside_window_or_before_success_are_not_counted() {
+        let dao = MockDao::default();
+        let now = 1_000_000;
+
+        fail(&dao, "alice", now, 4).await;
+        // 窗口已过，重新计数
+        assert_eq!(
+            fail(&dao, "alice", now + LOGIN_FAILURE_WINDOW_SECS, 4).await,
+            None
+        );
+
+        reset(&dao, "alice").await.unwrap();
+        let later = now + LOGIN_FAILURE_WINDOW_SECS + 1;
+        assert_eq!(fail(&dao, "alice", later, 4).await, None);
+        assert!(fail(&dao, "alice", later, 1).await.is_some());
+    }
+}
@@ -1,17 +1,23 @@
 use std::sync::Arc;
 
 use chrono::Utc;
-use jsonwebtoken::{decode, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use jsonwebtoken::{Algorithm, DecodingKey, EncodingKey, Header, Validation, decode, encode};
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
-use tonic::{metadata::MetadataValue, Request, Response, Status};
 use tonic::service::Interceptor;
+use tonic::{Request, Response, Status, metadata::MetadataValue};
 
-use argon2::password_hash::{PasswordHash, PasswordVerifier};
 use argon2::Argon2;
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use rand::rngs::OsRng;
 
 use crate::config::holder::get_or_init_config;
 use crate::database::dao;
+use crate::middleware::RateLimiter;
+use crate::middleware::request_id;
+
+pub mod lockout;
+pub mod oauth2;
 
 /// 领域层的用户身份信息（与具体传输协议无关）
 #[derive(Debug, Clone)]
@@ -22,6 +28,19 @@ pub struct UserContext {
     pub source: AuthSource,
 }
 
+/// 拥有该 scope 的用户是管理员
+pub const ADMIN_SCOPE: &str = "admin";
+
+impl UserContext {
+    pub fn has_scope(&self, scope: &str) -> bool {
+        self.scopes.iter().any(|s| s == scope)
+    }
+
+    pub fn is_admin(&self) -> bool {
+        self.has_scope(ADMIN_SCOPE)
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 pub enum AuthSource {
     Jwt,
@@ -155,7 +174,9 @@ impl AuthService {
             scopes: data.claims.scopes,
             source: AuthSource::Jwt,
         };
-        let meta = TokenMeta { exp: data.claims.exp };
+        let meta = TokenMeta {
+            exp: data.claims.exp,
+        };
 
         Ok((ctx, meta))
     }
@@ -190,6 +211,20 @@ pub fn require_user<T>(req: &Request<T>) -> Result<&UserContext, Status> {
         .ok_or_else(|| Status::unauthenticated("login required"))
 }
 
+/// 获取当前登录用户，并要求其拥有指定的 scope。
+///
+/// - 如未登录，返回 `Status::unauthenticated("login required")`
+/// - 如缺少 scope，返回 `Status::permission_denied`
+pub fn require_scope<'a, T>(req: &'a Request<T>, scope: &str) -> Result<&'a UserContext, Status> {
+    let user = require_user(req)?;
+    if !user.has_scope(scope) {
+        return Err(Status::permission_denied(format!(
+            "scope '{scope}' required"
+        )));
+    }
+    Ok(user)
+}
+
 /// 统一的 gRPC 鉴权拦截函数，可在 Interceptor / tower layer 中复用。
 ///
 /// - 解析 `authorization: Bearer xxx`
@@ -200,10 +235,7 @@ pub fn enforce_jwt_on_request<T>(
     auth: &AuthService,
 ) -> Result<Request<T>, Status> {
     let md = req.metadata().clone();
-    let header_val = match md
-        .get("authorization")
-        .and_then(|v| v.to_str().ok())
-    {
+    let header_val = match md.get("authorization").and_then(|v| v.to_str().ok()) {
         // 未携带 Authorization 头时，直接放行，交由具体业务决定是否需要登录
         None => return Ok(req),
         Some(v) => v,
@@ -241,35 +273,56 @@ pub fn apply_renew_metadata<T>(req: &Request<T>, resp: &mut Response<()>) {
     }
 }
 
-/// 服务端 gRPC 鉴权拦截器实现，包装 `enforce_jwt_on_request`。
+/// 服务端 gRPC 鉴权拦截器实现，包装 `enforce_jwt_on_request`，鉴权之后可选地进行限流。
 #[derive(Clone)]
 pub struct AuthInterceptor {
     auth: Arc<AuthService>,
+    rate_limiter: Option<Arc<RateLimiter>>,
 }
 
 impl AuthInterceptor {
     pub fn new(auth: Arc<AuthService>) -> Self {
-        Self { auth }
+        Self {
+            auth,
+            rate_limiter: None,
+        }
+    }
+
+    /// 在 JWT 校验之后按用户限流
+    pub fn with_rate_limiter(mut self, rate_limiter: Arc<RateLimiter>) -> Self {
+        self.rate_limiter = Some(rate_limiter);
+        self
     }
 }
 
 impl Interceptor for AuthInterceptor {
     fn call(&mut self, req: Request<()>) -> Result<Request<()>, Status> {
-        enforce_jwt_on_request(req, &self.auth)
+        let req = request_id::call(req)?;
+        let req = enforce_jwt_on_request(req, &self.auth)?;
+        if let Some(rate_limiter) = &self.rate_limiter {
+            rate_limiter.check_request(&req)?;
+        }
+        Ok(req)
     }
 }
 
+/// 使用 Argon2 与随机盐对密码进行哈希
+pub fn hash_password(password: &str) -> Result<String, AuthError> {
+    let salt = SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|_| AuthError::Internal)
+}
+
 /// 校验用户名/密码是否合法的函数
-pub async fn validate_user_credentials(
-    username: &str,
-    password: &str,
-) -> Result<bool, AuthError> {
+pub async fn validate_user_credentials(username: &str, password: &str) -> Result<bool, AuthError> {
     // 测试环境内置测试账号：admin / admin（仅在 `cargo test` 时生效，不影响生产/开发环境运行的服务进程）
     if cfg!(test) && username == "admin" && password == "admin" {
         return Ok(true);
     }
 
-    // 尝试从 MongoDB 中读取用户信息
+    // 从数据库中读取用户信息
     let user_doc_opt = dao::find_user_by_username(username)
         .await
         // 对于 DAO 层错误，这里统一视为认证失败，而不是返回内部错误，避免泄露实现细节
@@ -280,6 +333,10 @@ pub async fn validate_user_credentials(
         None => return Ok(false),
     };
 
+    // OAuth2 创建的账号没有密码
+    if user.source == dao::USER_SOURCE_OAUTH2 {
+        return Ok(false);
+    }
     let stored = user.password;
 
     // 优先尝试将 stored 作为 argon2 密文进行验证
@@ -301,11 +358,11 @@ pub async fn validate_user_credentials(
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::auth::{AuthService, AuthInterceptor, TokenPolicy, UserContext, RenewToken};
+    use crate::auth::{AuthInterceptor, AuthService, RenewToken, TokenPolicy, UserContext};
     use crate::hive_server::CrvHiveService;
     use crate::pb::hive_service_server::HiveService;
-    use tonic::{Request, Code};
     use tonic::metadata::MetadataValue;
+    use tonic::{Code, Request};
 
     /// 缺少 Authorization 头时，应直接放行，但不注入 UserContext
     #[test]
@@ -320,7 +377,10 @@ mod tests {
         let req = Request::new(());
         let res = <AuthInterceptor as tonic::service::Interceptor>::call(&mut interceptor, req);
 
-        assert!(res.is_ok(), "request without authorization should be accepted");
+        assert!(
+            res.is_ok(),
+            "request without authorization should be accepted"
+        );
         let req = res.unwrap();
         assert!(
             req.extensions().get::<UserContext>().is_none(),
@@ -387,7 +447,10 @@ mod tests {
             .expect("RenewToken should be injected for near-expiration token");
 
         assert!(!renew.token.is_empty(), "renewed token should not be empty");
-        assert!(renew.expires_at > 0, "renewed token should have a valid exp");
+        assert!(
+            renew.expires_at > 0,
+            "renewed token should have a valid exp"
+        );
 
         // 同时确认 UserContext 仍然存在
         let ctx = req
@@ -451,7 +514,10 @@ mod tests {
             !rsp.access_token.is_empty(),
             "access_token should not be empty for admin/admin"
         );
-        assert!(rsp.expires_at > 0, "expires_at should be a positive timestamp");
+        assert!(
+            rsp.expires_at > 0,
+            "expires_at should be a positive timestamp"
+        );
     }
 
     /// 非 admin/admin 的账号应被拒绝并返回 Unauthenticated
@@ -471,6 +537,34 @@ mod tests {
         assert_eq!(status.code(), Code::Unauthenticated);
     }
 
+    /// 连续失败达到阈值后，即使密码正确也拒绝登录
+    #[tokio::test]
+    async fn login_is_locked_after_repeated_failures() {
+        use crate::database::dao::Dao;
+
+        let dao = crate::test_support::install_mock_dao();
+        let service = CrvHiveService::new(make_auth());
+        let username = format!("lockout-{}", uuid::Uuid::new_v4());
+        let password_hash = hash_password("secret").unwrap();
+        dao.insert_user(&username, &password_hash, None, "")
+            .await
+            .unwrap();
+
+        let login = |password: &str| {
+            service.login(Request::new(crate::pb::LoginReq {
+                username: username.clone(),
+                password: password.to_string(),
+            }))
+        };
+        for _ in 0..lockout::MAX_LOGIN_FAILURES {
+            let status = login("wrong").await.unwrap_err();
+            assert_eq!(status.code(), Code::Unauthenticated);
+        }
+        let status = login("secret").await.unwrap_err();
+        assert_eq!(status.code(), Code::PermissionDenied);
+        assert_eq!(status.message(), "account temporarily locked");
+    }
+
     /// require_user 在存在 UserContext 时应成功返回
     #[test]
     fn require_user_works_when_context_present() {
@@ -490,7 +584,10 @@ mod tests {
     fn require_user_fails_when_context_missing() {
         let req = Request::new(());
         let res = require_user(&req);
-        assert!(res.is_err(), "require_user should fail when context is missing");
+        assert!(
+            res.is_err(),
+            "require_user should fail when context is missing"
+        );
         let status = res.err().unwrap();
         assert_eq!(status.code(), Code::Unauthenticated);
     }
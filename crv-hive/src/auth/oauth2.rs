@@ -0,0 +1,356 @@
+//! OAuth2 登录：先返回 provider 的授权页面地址与 state，用户在浏览器中授权后，
+//! 用跳转回来的 code 向 provider 换取 access token，再读取用户信息得到 hive 用户名。
+//!
+//! 等待完成的 state 只保存在内存中，hive 重启后需要重新开始登录。
+use crate::config::entity::{ConfigEntity, OAuth2ProviderConfig};
+use crate::config::holder::get_or_init_config;
+use crate::database::dao::{Dao, USER_SOURCE_OAUTH2};
+use dashmap::DashMap;
+use oauth2::basic::BasicClient;
+use oauth2::{
+    AuthType, AuthUrl, AuthorizationCode, ClientId, ClientSecret, CsrfToken, EndpointNotSet,
+    EndpointSet, RedirectUrl, Scope, TokenResponse, TokenUrl,
+};
+use std::collections::HashMap;
+use std::sync::OnceLock;
+use tonic::Status;
+
+/// state 的有效期（秒）
+const STATE_TTL_SECS: i64 = 10 * 60;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OAuth2Provider {
+    Google,
+    GitHub,
+}
+
+impl OAuth2Provider {
+    fn name(self) -> &'static str {
+        match self {
+            OAuth2Provider::Google => "google",
+            OAuth2Provider::GitHub => "github",
+        }
+    }
+
+    fn default_auth_url(self) -> &'static str {
+        match self {
+            OAuth2Provider::Google => "https://accounts.google.com/o/oauth2/v2/auth",
+            OAuth2Provider::GitHub => "https://github.com/login/oauth/authorize",
+        }
+    }
+
+    fn default_token_url(self) -> &'static str {
+        match self {
+            OAuth2Provider::Google => "https://oauth2.googleapis.com/token",
+            OAuth2Provider::GitHub => "https://github.com/login/oauth/access_token",
+        }
+    }
+
+    fn default_userinfo_url(self) -> &'static str {
+        match self {
+            OAuth2Provider::Google => "https://openidconnect.googleapis.com/v1/userinfo",
+            OAuth2Provider::GitHub => "https://api.github.com/user",
+        }
+    }
+
+    fn scopes(self) -> &'static [&'static str] {
+        match self {
+            OAuth2Provider::Google => &["openid", "email", "profile"],
+            OAuth2Provider::GitHub => &["read:user", "user:email"],
+        }
+    }
+}
+
+/// 从 provider 读取到的用户身份
+#[derive(Debug, Clone, PartialEq)]
+pub struct OAuth2Identity {
+    /// GitHub 为 GitHub 用户名，Google 为已验证的邮箱
+    pub username: String,
+    pub email: Option<String>,
+    pub display_name: String,
+}
+
+struct PendingLogin {
+    provider: OAuth2Provider,
+    expires_at: i64,
+}
+
+type ConfiguredClient =
+    BasicClient<EndpointSet, EndpointNotSet, EndpointNotSet, EndpointNotSet, EndpointSet>;
+
+pub struct OAuth2Login {
+    providers: HashMap<OAuth2Provider, OAuth2ProviderConfig>,
+    pending: DashMap<String, PendingLogin>,
+    http: reqwest::Client,
+}
+
+static OAUTH2_LOGIN: OnceLock<OAuth2Login> = OnceLock::new();
+
+/// 按全局配置创建的 OAuth2 登录服务
+pub fn oauth2_login() -> &'static OAuth2Login {
+    OAUTH2_LOGIN.get_or_init(|| OAuth2Login::from_config(get_or_init_config()))
+}
+
+impl OAuth2Login {
+    pub fn from_config(config: &ConfigEntity) -> Self {
+        let providers = [
+            (OAuth2Provider::Google, &config.oauth2_google),
+            (OAuth2Provider::GitHub, &config.oauth2_github),
+        ]
+        .into_iter()
+        .filter_map(|(provider, config)| config.clone().map(|config| (provider, config)))
+        .collect();
+        Self::new(providers)
+    }
+
+    pub fn new(providers: HashMap<OAuth2Provider, OAuth2ProviderConfig>) -> Self {
+        // 不跟随重定向，避免 token 与用户信息请求被转发到配置以外的地址
+        let http = reqwest::Client::builder()
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .expect("failed to build oauth2 http client");
+        Self {
+            providers,
+            pending: DashMap::new(),
+            http,
+        }
+    }
+
+    /// 生成授权页面地址，返回 (auth_url, state)。
+    pub fn begin(&self, provider: OAuth2Provider) -> Result<(String, String), Status> {
+        let (_, client) = self.client(provider)?;
+        let (url, state) = client
+            .authorize_url(CsrfToken::new_random)
+            .add_scopes(provider.scopes().iter().map(|s| Scope::new(s.to_string())))
+            .url();
+
+        let now = chrono::Utc::now().timestamp();
+        self.pending.retain(|_, pending| pending.expires_at > now);
+        self.pending.insert(
+            state.secret().clone(),
+            PendingLogin {
+                provider,
+                expires_at: now + STATE_TTL_SECS,
+            },
+        );
+        Ok((url.to_string(), state.secret().clone()))
+    }
+
+    /// 校验 state 并用 code 换取用户身份，首次登录时创建用户。
+    ///
+    /// state 只能使用一次；用户名已被注册的账号占用时返回 `permission_denied`，
+    /// 避免 OAuth2 身份接管同名的密码账号。
+    pub async fn complete(
+        &self,
+        dao: &dyn Dao,
+        state: &str,
+        code: &str,
+    ) -> Result<OAuth2Identity, Status> {
+        let now = chrono::Utc::now().timestamp();
+        let provider = match self.pending.remove(state) {
+            Some((_, pending)) if pending.expires_at > now => pending.provider,
+            _ => return Err(Status::invalid_argument("unknown or expired oauth2 state")),
+        };
+
+        let (config, client) = self.client(provider)?;
+        let token = client
+            .exchange_code(AuthorizationCode::new(code.to_string()))
+            .request_async(&self.http)
+            .await
+            .map_err(|e| {
+                Status::unauthenticated(format!("failed to exchange authorization code: {e}"))
+            })?;
+        let userinfo_url = config
+            .userinfo_url
+            .as_deref()
+            .unwrap_or(provider.default_userinfo_url());
+        let identity = self
+            .fetch_identity(provider, userinfo_url, token.access_token().secret())
+            .await?;
+
+        let find_user = || async {
+            dao.find_user_by_username(&identity.username)
+                .await
+                .map_err(|e| Status::internal(format!("database error while finding user: {e}")))
+        };
+        let mut user = find_user().await?;
+        if user.is_none() {
+            // 同一用户并发首次登录时插入可能失败，重新读取后按已存在处理
+            if let Err(e) = dao
+                .insert_oauth2_user(
+                    &identity.username,
+                    identity.email.as_deref(),
+                    &identity.display_name,
+                )
+                .await
+            {
+                user = find_user().await?;
+                if user.is_none() {
+                    return Err(Status::internal(format!(
+                        "database error while inserting user: {e}"
+                    )));
+                }
+            }
+        }
+        if user.is_some_and(|user| user.source != USER_SOURCE_OAUTH2) {
+            return Err(Status::permission_denied(format!(
+                "user '{}' is a password account and can not log in with oauth2",
+                identity.username
+            )));
+        }
+        Ok(identity)
+    }
+
+    fn client(
+        &self,
+        provider: OAuth2Provider,
+    ) -> Result<(&OAuth2ProviderConfig, ConfiguredClient), Status> {
+        let config = self.providers.get(&provider).ok_or_else(|| {
+            Status::failed_precondition(format!(
+                "oauth2 provider {} is not configured",
+                provider.name()
+            ))
+        })?;
+        let invalid = |e: oauth2::url::ParseError| {
+            Status::internal(format!("invalid oauth2 config of {}: {e}", provider.name()))
+        };
+        let auth_url = AuthUrl::new(
+            config
+                .auth_url
+                .clone()
+                .unwrap_or_else(|| provider.default_auth_url().to_string()),
+        )
+        .map_err(invalid)?;
+        let token_url = TokenUrl::new(
+            config
+                .token_url
+                .clone()
+                .unwrap_or_else(|| provider.default_token_url().to_string()),
+        )
+        .map_err(invalid)?;
+        let redirect_url = RedirectUrl::new(config.redirect_uri.clone()).map_err(invalid)?;
+
+        let client = BasicClient::new(ClientId::new(config.client_id.clone()))
+            .set_client_secret(ClientSecret::new(config.client_secret.clone()))
+            // GitHub 只接受放在请求体中的 client 凭据
+            .set_auth_type(AuthType::RequestBody)
+            .set_auth_uri(auth_url)
+            .set_token_uri(token_url)
+            .set_redirect_uri(redirect_url);
+        Ok((config, client))
+    }
+
+    async fn fetch_identity(
+        &self,
+        provider: OAuth2Provider,
+        userinfo_url: &str,
+        access_token: &str,
+    ) -> Result<OAuth2Identity, Status> {
+        let rsp = self
+            .http
+            .get(userinfo_url)
+            .bearer_auth(access_token)
+            // GitHub API 要求携带 User-Agent
+            .header(reqwest::header::USER_AGENT, "crv-hive")
+            .header(reqwest::header::ACCEPT, "application/json")
+            .send()
+            .await
+            .map_err(|e| Status::unavailable(format!("failed to fetch oauth2 user info: {e}")))?;
+        let status = rsp.status();
+        if !status.is_success() {
+            return Err(Status::unauthenticated(format!(
+                "failed to fetch oauth2 user info: http {status}"
+            )));
+        }
+        let body = rsp
+            .text()
+            .await
+            .map_err(|e| Status::unavailable(format!("failed to fetch oauth2 user info: {e}")))?;
+        parse_identity(provider, &body)
+    }
+}
+
+/// 解析 provider 返回的用户信息
+fn parse_identity(provider: OAuth2Provider, body: &str) -> Result<OAuth2Identity, Status> {
+    let value: serde_json::Value = serde_json::from_str(body)
+        .map_err(|e| Status::internal(format!("invalid oauth2 user info: {e}")))?;
+    let field = |key: &str| {
+        value
+            .get(key)
+            .and_then(|v| v.as_str())
+            .map(str::trim)
+            .filter(|v| !v.is_empty())
+            .map(str::to_string)
+    };
+    let display_name = field("name").unwrap_or_default();
+
+    match provider {
+        OAuth2Provider::Google => {
+            let email = field("email")
+                .ok_or_else(|| Status::permission_denied("google account has no email"))?;
+            if value.get("email_verified").and_then(|v| v.as_bool()) != Some(true) {
+                return Err(Status::permission_denied(
+                    "google account email is not verified",
+                ));
+            }
+            Ok(OAuth2Identity {
+                username: email.clone(),
+                email: Some(email),
+                display_name,
+            })
+        }
+        OAuth2Provider::GitHub => {
+            let login =
+                field("login").ok_or_else(|| Status::internal("github user info has no login"))?;
+            Ok(OAuth2Identity {
+                username: login,
+                email: field("email"),
+                display_name,
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn identities_are_parsed_per_provider() {
+        let github = parse_identity(
+            OAuth2Provider::GitHub,
+            r#"{"login":"octocat","name":"The Octocat","email":null}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            github,
+            OAuth2Identity {
+                username: "octocat".to_string(),
+                email: None,
+                display_name: "The Octocat".to_string(),
+            }
+        );
+
+        let google = parse_identity(
+            OAuth2Provider::Google,
+            r#"{"sub":"1","email":"alice@example.com","email_verified":true}"#,
+        )
+        .unwrap();
+        assert_eq!(google.username, "alice@example.com");
+        assert_eq!(google.email.as_deref(), Some("alice@example.com"));
+
+        let err = parse_identity(
+            OAuth2Provider::Google,
+            r#"{"sub":"1","email":"alice@example.com","email_verified":false}"#,
+        )
+        .unwrap_err();
+        assert_eq!(err.code(), tonic::Code::PermissionDenied);
+    }
+
+    #[test]
+    fn unconfigured_provider_is_rejected() {
+        let login = OAuth2Login::new(HashMap::new());
+        let err = login.begin(OAuth2Provider::Google).unwrap_err();
+        assert_eq!(err.code(), tonic::Code::FailedPrecondition);
+    }
+}
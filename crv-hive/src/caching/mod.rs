@@ -1,6 +1,7 @@
 use std::fs::{self, OpenOptions};
 use std::io::{Read, Seek, SeekFrom, Write};
 use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
 
 use crate::config::holder::get_or_init_config;
 use crv_core::repository::compute_chunk_hash;
@@ -21,6 +22,15 @@ pub enum ChunkCacheError {
 
 pub type ChunkCacheResult<T> = Result<T, ChunkCacheError>;
 
+/// 一次淘汰的结果
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EvictionReport {
+    /// 删除的缓存文件数
+    pub removed_count: u64,
+    /// 释放的字节数
+    pub freed_bytes: u64,
+}
+
 /// 负责在本地临时目录中缓存用户上传的文件 chunk。
 ///
 /// - 根目录默认位于 `ConfigEntity.repository_path` 的 `cache/chunks` 子目录中；
@@ -173,6 +183,77 @@ impl ChunkCache {
         Ok(())
     }
 
+    /// 按修改时间淘汰缓存文件，直到缓存总大小不超过 `max_total_bytes`。
+    ///
+    /// 最久未写入的文件最先被删除；正在上传的 chunk 会不断追加写入，通常不会被淘汰。
+    pub fn evict_lru(&self, max_total_bytes: u64) -> ChunkCacheResult<EvictionReport> {
+        let mut files = Vec::new();
+        let mut total_bytes = 0u64;
+        if self.root.exists() {
+            for shard in fs::read_dir(&self.root)? {
+                let shard = shard?;
+                if !shard.file_type()?.is_dir() {
+                    continue;
+                }
+                for entry in fs::read_dir(shard.path())? {
+                    let entry = entry?;
+                    let metadata = entry.metadata()?;
+                    if !metadata.is_file() {
+                        continue;
+                    }
+                    let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+                    total_bytes += metadata.len();
+                    files.push((modified, metadata.len(), entry.path()));
+                }
+            }
+        }
+
+        files.sort_by_key(|(modified, _, _)| *modified);
+
+        let mut report = EvictionReport::default();
+        for (_, len, path) in files {
+            if total_bytes <= max_total_bytes {
+                break;
+            }
+            match fs::remove_file(&path) {
+                Ok(()) => {
+                    report.removed_count += 1;
+                    report.freed_bytes += len;
+                }
+                // 可能已被并发的提交流程删除
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e.into()),
+            }
+            total_bytes -= len;
+        }
+        Ok(report)
+    }
+
+    /// 在后台定期执行 [`ChunkCache::evict_lru`]，启动时立即执行一次。
+    pub fn spawn_eviction_task(
+        self,
+        max_total_bytes: u64,
+        interval: Duration,
+    ) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                let cache = self.clone();
+                match tokio::task::spawn_blocking(move || cache.evict_lru(max_total_bytes)).await {
+                    Ok(Ok(report)) if report.removed_count > 0 => tracing::info!(
+                        "chunk cache evicted {} files, freed {} bytes",
+                        report.removed_count,
+                        report.freed_bytes
+                    ),
+                    Ok(Ok(_)) => {}
+                    Ok(Err(e)) => tracing::warn!("chunk cache eviction failed: {e}"),
+                    Err(e) => tracing::warn!("chunk cache eviction task panicked: {e}"),
+                }
+            }
+        })
+    }
+
     /// 清空整个缓存目录（慎用）。
     pub fn clear_all(&self) -> ChunkCacheResult<()> {
         if self.root.exists() {
@@ -222,7 +303,9 @@ mod tests {
         assert_eq!(cache.has_chunk(&hash_hex).unwrap(), true);
 
         // read_chunk 返回原始数据并校验哈希
-        let read_back = cache.read_chunk(&hash_hex).expect("read_chunk should succeed");
+        let read_back = cache
+            .read_chunk(&hash_hex)
+            .expect("read_chunk should succeed");
         assert_eq!(read_back, data);
     }
 
@@ -245,7 +328,9 @@ mod tests {
             .expect("append part2 should succeed");
 
         assert_eq!(cache.has_chunk(&hash_hex).unwrap(), true);
-        let read_back = cache.read_chunk(&hash_hex).expect("read_chunk should succeed");
+        let read_back = cache
+            .read_chunk(&hash_hex)
+            .expect("read_chunk should succeed");
         assert_eq!(read_back, full);
     }
 
@@ -323,6 +408,64 @@ mod tests {
         cache.clear_all().expect("clear_all should succeed");
         assert_eq!(cache.has_chunk(&hash_hex).unwrap(), false);
     }
-}
 
+    fn cache_size(root: &std::path::Path) -> u64 {
+        let mut total = 0;
+        for shard in fs::read_dir(root).unwrap() {
+            for entry in fs::read_dir(shard.unwrap().path()).unwrap() {
+                total += entry.unwrap().metadata().unwrap().len();
+            }
+        }
+        total
+    }
+
+    #[test]
+    fn evict_lru_removes_oldest_files_until_below_limit() {
+        let tmp = tempdir().unwrap();
+        let cache_root = tmp.path().join("cache");
+        let cache = ChunkCache::new(&cache_root).expect("create cache");
+
+        let mut hashes = vec![];
+        for i in 0..20u8 {
+            let data = vec![i; 1024];
+            let hash_hex = hash_to_hex(&data);
+            cache
+                .append_chunk_part(&hash_hex, 0, &data)
+                .expect("append should succeed");
+            let path = cache.chunk_path_unchecked(&hash_hex).unwrap();
+            let file = OpenOptions::new().write(true).open(&path).unwrap();
+            file.set_modified(SystemTime::UNIX_EPOCH + Duration::from_secs(1_000 + i as u64))
+                .unwrap();
+            hashes.push(hash_hex);
+        }
+        assert_eq!(cache_size(&cache_root), 20 * 1024);
+
+        let report = cache.evict_lru(8 * 1024).expect("evict should succeed");
+        assert_eq!(report.removed_count, 12);
+        assert_eq!(report.freed_bytes, 12 * 1024);
+        assert!(cache_size(&cache_root) <= 8 * 1024);
+
+        // 最旧的文件被淘汰，最新的文件被保留
+        assert!(!cache.has_chunk(&hashes[0]).unwrap());
+        assert!(!cache.has_chunk(&hashes[11]).unwrap());
+        assert!(cache.has_chunk(&hashes[12]).unwrap());
+        assert!(cache.has_chunk(&hashes[19]).unwrap());
+    }
+
+    #[test]
+    fn evict_lru_keeps_cache_within_limit() {
+        let tmp = tempdir().unwrap();
+        let cache_root = tmp.path().join("cache");
+        let cache = ChunkCache::new(&cache_root).expect("create cache");
 
+        let data = b"small chunk";
+        let hash_hex = hash_to_hex(data);
+        cache
+            .append_chunk_part(&hash_hex, 0, data)
+            .expect("append should succeed");
+
+        let report = cache.evict_lru(1024).expect("evict should succeed");
+        assert_eq!(report, EvictionReport::default());
+        assert!(cache.has_chunk(&hash_hex).unwrap());
+    }
+}
@@ -1,3 +1,4 @@
+use crv_core::metadata::CompressionType;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -7,32 +8,125 @@ pub struct ConfigEntity {
     pub postgres_database: String,
     pub postgres_username: String,
     pub postgres_password: String,
-    pub postgres_port: u16, 
+    pub postgres_port: u16,
+    /// 完整的数据库连接串，设置后忽略上面的 postgres_* 字段；环境变量 CRV_DATABASE_URL 优先级更高
+    pub database_url: Option<String>,
+    /// 只读查询使用的数据库：`primary` 全部走主库，`secondary_preferred` 优先走只读副本
+    pub database_read_preference: ReadPreference,
+    /// 只读副本的连接串，`database_read_preference = "secondary_preferred"` 时使用
+    pub database_read_url: Option<String>,
 
     pub hive_address: Option<String>,
+    /// Prometheus `/metrics` 服务的监听地址，为空时不启动
+    pub metrics_address: Option<String>,
+    /// chunk 仓库的存储后端，目前只支持 `local`
+    pub repository_backend: RepositoryBackend,
     pub repository_path: String,
     pub upload_cache_path: String,
     pub jwt_secret: String,
+
+    /// 是否启用 gRPC 反射（供 grpcurl 等工具查询服务定义），debug 构建默认开启
+    pub enable_reflection: bool,
+
+    /// 上传缓存（`upload_cache_path`）的大小上限（MB），超出后按修改时间淘汰最旧的文件
+    pub chunk_cache_max_mb: u64,
+    /// 检查上传缓存大小的间隔（分钟）
+    pub chunk_cache_eviction_interval_mins: u64,
+
+    /// 是否每天将长时间未修改的 pack 转移到对象存储，本地只保留索引
+    pub chunk_offload_enabled: bool,
+    /// pack 最后修改超过该天数后才会被转移
+    pub chunk_offload_threshold_days: u32,
+    /// 提交时写入仓库的 chunk 使用的压缩方式：`none` 或 `zstd`，只影响之后新写入的 chunk
+    pub chunk_compression: CompressionType,
+
+    /// 每个用户每秒补充的请求令牌数，为 0 时不限流
+    pub rate_limit_per_sec: f64,
+    /// 每个用户最多积攒的请求令牌数，即允许的突发请求数
+    pub rate_limit_burst: u32,
+
+    /// 提交描述需要匹配的正则（如 `^\[[A-Z]+-\d+\] `），为空时不检查；分支可以单独覆盖
+    pub changelist_description_regex: Option<String>,
+
+    /// 同时打开的 WatchBranch 流的上限，超过时新的订阅返回 resource_exhausted
+    pub watch_branch_max_subscribers: usize,
+
+    /// Google OAuth2 登录的配置，未设置时不能使用 Google 登录
+    pub oauth2_google: Option<OAuth2ProviderConfig>,
+    /// GitHub OAuth2 登录的配置，未设置时不能使用 GitHub 登录
+    pub oauth2_github: Option<OAuth2ProviderConfig>,
 }
 
 impl Default for ConfigEntity {
     fn default() -> Self {
         Self {
-            // 添加 directConnection=true&w=1 用于单节点 MongoDB（非副本集）
             postgres_hostname: "127.0.0.1".to_string(),
             postgres_database: "chronoverse".to_string(),
             postgres_username: "postgres".to_string(),
             postgres_password: "postgres".to_string(),
             postgres_port: 5432,
-            
+            database_url: None,
+            database_read_preference: ReadPreference::Primary,
+            database_read_url: None,
+
             hive_address: Some("0.0.0.0:34560".to_string()),
+            metrics_address: Some("0.0.0.0:34561".to_string()),
+            repository_backend: RepositoryBackend::Local,
             repository_path: default_repository_path(),
             upload_cache_path: default_upload_cache_path(),
             jwt_secret: "dev-secret".to_string(),
+            enable_reflection: cfg!(debug_assertions),
+            chunk_cache_max_mb: 10 * 1024,
+            chunk_cache_eviction_interval_mins: 10,
+            chunk_offload_enabled: false,
+            chunk_offload_threshold_days: 30,
+            chunk_compression: CompressionType::None,
+            rate_limit_per_sec: 50.0,
+            rate_limit_burst: 200,
+            changelist_description_regex: None,
+            watch_branch_max_subscribers: 256,
+            oauth2_google: None,
+            oauth2_github: None,
         }
     }
 }
 
+/// 一个 OAuth2 provider 的配置，在 provider 处登记 hive 的应用后得到
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OAuth2ProviderConfig {
+    pub client_id: String,
+    pub client_secret: String,
+    /// 用户授权后浏览器跳转的地址，必须与登记时填写的一致
+    pub redirect_uri: String,
+    /// 授权页面、token 与用户信息的地址，为空时使用 provider 的公开地址
+    pub auth_url: Option<String>,
+    pub token_url: Option<String>,
+    pub userinfo_url: Option<String>,
+}
+
+/// 只读查询的路由方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReadPreference {
+    /// 所有查询都使用主库
+    #[default]
+    Primary,
+    /// 只读 RPC 的查询使用只读副本，未配置副本或连接失败时退回主库
+    SecondaryPreferred,
+}
+
+/// chunk 仓库的存储后端
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RepositoryBackend {
+    /// 存放在 `repository_path` 指向的本地目录
+    #[default]
+    Local,
+    /// S3 兼容的对象存储，尚未实现
+    S3,
+}
+
 fn default_repository_path() -> String {
     if cfg!(target_os = "windows") {
         if let Ok(appdata) = std::env::var("APPDATA") {
@@ -1,15 +1,21 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::{Arc, OnceLock, RwLock};
 
+use async_trait::async_trait;
+use crv_core::metadata::{BranchDoc, BranchMetadata, FileMetadata, SnapshotDoc};
+use crv_core::tree::depot_tree::LockMode;
 use sea_orm::{
-    ActiveModelTrait, ConnectionTrait, DatabaseBackend, DbErr, EntityTrait, Set, Statement,
-    TransactionTrait,
+    AccessMode, ActiveModelTrait, ColumnTrait, ConnectionTrait, DatabaseBackend, DbErr, EntityName,
+    EntityTrait, IsolationLevel, PaginatorTrait, QueryFilter, QueryOrder, QuerySelect, Set,
+    Statement, TransactionTrait,
 };
-use async_trait::async_trait;
 use thiserror::Error;
 
+use crate::audit::{AuditEvent, AuditOp, AuditOutcome, AuditQuery};
+use crate::common::depot_path::DepotPath;
 use crate::database::entities;
 use crate::database::ltree_key;
+use crate::webhook::{WebhookConfig, WebhookEvent};
 
 /// DAO 层错误类型
 #[derive(Debug, Error)]
@@ -25,6 +31,18 @@ pub enum DaoError {
 
     #[error("Ltree key error: {0}")]
     LtreeKey(#[from] ltree_key::LtreeKeyError),
+
+    /// 提交的 (path, generation, revision) 已经存在，说明 HEAD 在计算新版本后被其他提交推进
+    #[error("Revision conflict on {0}")]
+    RevisionConflict(String),
+
+    /// 批量写入的 file revision 与已有的行或同一批次中的其他行主键重复
+    #[error("Duplicate key: {path}#{generation}:{revision}")]
+    DuplicateKey {
+        path: String,
+        generation: i64,
+        revision: i64,
+    },
 }
 
 pub type DaoResult<T> = Result<T, DaoError>;
@@ -39,14 +57,42 @@ fn db() -> DaoResult<&'static sea_orm::DatabaseConnection> {
 
 #[async_trait]
 pub trait Dao: Send + Sync {
-    async fn find_user_by_username(&self, username: &str) -> DaoResult<Option<entities::users::Model>>;
-    async fn insert_user(&self, username: &str, password_hash: &str) -> DaoResult<()>;
+    async fn find_user_by_username(
+        &self,
+        username: &str,
+    ) -> DaoResult<Option<entities::users::Model>>;
+    async fn insert_user(
+        &self,
+        username: &str,
+        password_hash: &str,
+        email: Option<&str>,
+        display_name: &str,
+    ) -> DaoResult<()>;
+    async fn insert_oauth2_user(
+        &self,
+        username: &str,
+        email: Option<&str>,
+        display_name: &str,
+    ) -> DaoResult<()>;
+    async fn list_users(
+        &self,
+        offset: u64,
+        limit: u64,
+    ) -> DaoResult<(Vec<entities::users::Model>, i64)>;
+    async fn update_user_password(&self, username: &str, password_hash: &str) -> DaoResult<bool>;
+    async fn delete_user(&self, username: &str) -> DaoResult<bool>;
 
     async fn find_latest_file_revision_by_depot_path(
         &self,
         depot_path: &str,
     ) -> DaoResult<Option<entities::file_revisions::Model>>;
+    async fn find_file_by_depot_path(
+        &self,
+        depot_path: &str,
+    ) -> DaoResult<Option<entities::files::Model>>;
+    async fn add_branch_to_file(&self, depot_path: &str, branch_id: &str) -> DaoResult<bool>;
 
+    async fn next_changelist_id(&self) -> DaoResult<i64>;
     async fn insert_changelist(
         &self,
         author: &str,
@@ -63,11 +109,176 @@ pub trait Dao: Send + Sync {
         metadata: serde_json::Value,
         revisions: Vec<NewFileRevisionInput>,
     ) -> DaoResult<i64>;
+    async fn commit_submit_to_branch(
+        &self,
+        branch_id: &str,
+        author: &str,
+        description: &str,
+        committed_at: i64,
+        metadata: serde_json::Value,
+        revisions: Vec<NewFileRevisionInput>,
+    ) -> DaoResult<i64>;
+
+    async fn find_branch_by_id(&self, branch_id: &str) -> DaoResult<Option<BranchDoc>>;
+    async fn find_branch_head(&self, branch_id: &str) -> DaoResult<Option<i64>>;
+    async fn is_changelist_on_chain(&self, head: i64, changelist_id: i64) -> DaoResult<bool>;
+    async fn find_merge_base(&self, a: i64, b: i64) -> DaoResult<i64>;
+    async fn insert_branch(&self, branch: &BranchDoc) -> DaoResult<()>;
+    async fn update_branch_head(&self, branch_id: &str, changelist_id: i64) -> DaoResult<bool>;
+    async fn list_branches(
+        &self,
+        sort_by: BranchSort,
+        offset: u64,
+        limit: u64,
+    ) -> DaoResult<(Vec<BranchDoc>, i64)>;
+    async fn update_branch_metadata(
+        &self,
+        branch_id: &str,
+        metadata: &BranchMetadata,
+    ) -> DaoResult<bool>;
+    async fn set_branch_protection(&self, branch_id: &str, is_protected: bool) -> DaoResult<bool>;
+
+    async fn find_latest_changelist_before(
+        &self,
+        branch_id: &str,
+        committed_at: i64,
+    ) -> DaoResult<Option<i64>>;
+
+    async fn add_label_to_changelist(&self, changelist_id: i64, label: &str) -> DaoResult<bool>;
+    async fn remove_label_from_changelist(
+        &self,
+        changelist_id: i64,
+        label: &str,
+    ) -> DaoResult<bool>;
+    async fn find_changelists_by_label(
+        &self,
+        branch_id: &str,
+        label: &str,
+        limit: u64,
+    ) -> DaoResult<Vec<entities::changelists::Model>>;
+    async fn list_changelist_chain(
+        &self,
+        from_cl: i64,
+        to_cl: i64,
+        limit: u64,
+    ) -> DaoResult<Vec<ChangelistSummaryRecord>>;
+
+    async fn find_changelist_by_id(
+        &self,
+        changelist_id: i64,
+    ) -> DaoResult<Option<entities::changelists::Model>>;
+    async fn find_latest_changelist_id(&self) -> DaoResult<Option<i64>>;
+    async fn list_file_revisions_by_depot_path(
+        &self,
+        depot_path: &str,
+    ) -> DaoResult<Vec<entities::file_revisions::Model>>;
+    async fn update_file_revision_tags(
+        &self,
+        depot_path: &str,
+        generation: i64,
+        revision: i64,
+        tags_to_add: &[String],
+        tags_to_remove: &[String],
+    ) -> DaoResult<Option<Vec<String>>>;
+    async fn find_file_revisions_by_tag(
+        &self,
+        branch_id: &str,
+        tag: &str,
+        limit: u64,
+    ) -> DaoResult<Vec<entities::file_revisions::Model>>;
+    async fn squash_changelists(&self, input: &SquashChangelistsInput) -> DaoResult<i64>;
+    async fn find_file_revision_at(
+        &self,
+        depot_path: &str,
+        changelist_id: i64,
+    ) -> DaoResult<Option<entities::file_revisions::Model>>;
+    async fn list_file_revisions_by_changelist(
+        &self,
+        changelist_id: i64,
+    ) -> DaoResult<Vec<entities::file_revisions::Model>>;
+    async fn list_file_tree_revisions(
+        &self,
+        depot: &DepotPath,
+        changelist_id: i64,
+    ) -> DaoResult<Vec<entities::file_revisions::Model>>;
+    async fn cherry_pick(&self, input: &CherryPickInput) -> DaoResult<i64>;
+    async fn merge_branches(&self, input: &MergeInput) -> DaoResult<i64>;
+
+    async fn insert_audit_event(&self, event: &AuditEvent) -> DaoResult<()>;
+    async fn list_audit_events(&self, query: &AuditQuery) -> DaoResult<Vec<AuditEvent>>;
+
+    async fn find_workspace_by_name(
+        &self,
+        name: &str,
+    ) -> DaoResult<Option<entities::workspaces::Model>>;
+    async fn insert_workspace(&self, workspace: &entities::workspaces::Model) -> DaoResult<()>;
+    async fn delete_workspace(&self, name: &str) -> DaoResult<bool>;
+
+    async fn insert_webhook(&self, webhook: &WebhookConfig) -> DaoResult<i64>;
+    async fn delete_webhook(&self, id: i64) -> DaoResult<bool>;
+    async fn list_webhooks(&self) -> DaoResult<Vec<WebhookConfig>>;
+
+    async fn insert_snapshot(&self, snapshot: &SnapshotDoc) -> DaoResult<i64>;
+    async fn find_snapshot_by_id(&self, id: i64) -> DaoResult<Option<SnapshotDoc>>;
+    async fn list_snapshots(&self, branch_id: &str) -> DaoResult<Vec<SnapshotDoc>>;
+    async fn delete_snapshot(&self, id: i64) -> DaoResult<bool>;
+
+    async fn find_submission(
+        &self,
+        request_id: &str,
+        not_before: i64,
+    ) -> DaoResult<Option<SubmissionRecord>>;
+    async fn insert_submission(
+        &self,
+        record: &SubmissionRecord,
+        expire_before: i64,
+    ) -> DaoResult<()>;
+
+    async fn insert_submit_ticket(&self, record: &SubmitTicketRecord) -> DaoResult<()>;
+    async fn delete_submit_ticket(&self, ticket: &str) -> DaoResult<bool>;
+    async fn delete_expired_submit_tickets(&self, now: i64) -> DaoResult<u64>;
+    async fn list_submit_tickets(&self, now: i64) -> DaoResult<Vec<SubmitTicketRecord>>;
+    async fn find_submit_ticket(&self, ticket: &str) -> DaoResult<Option<SubmitTicketRecord>>;
+    async fn update_submit_ticket_status(
+        &self,
+        ticket: &str,
+        status: SubmitContextStatus,
+        branch_id: Option<&str>,
+    ) -> DaoResult<bool>;
+
+    async fn increment_login_failures(
+        &self,
+        username: &str,
+        now: i64,
+        window_secs: i64,
+    ) -> DaoResult<entities::login_failures::Model>;
+    async fn find_login_failures(
+        &self,
+        username: &str,
+    ) -> DaoResult<Option<entities::login_failures::Model>>;
+    async fn lock_account(&self, username: &str, locked_until: i64) -> DaoResult<()>;
+    async fn reset_login_failures(&self, username: &str) -> DaoResult<()>;
+
+    async fn repository_stats(&self) -> DaoResult<RepositoryStats>;
 }
 
 /// 生产实现：使用 SeaORM + 全局单例连接池（`crate::database::DB_CONN`）
+///
+/// `read_from_replica` 为 true 时查询使用只读副本，写入始终使用主库。
 #[derive(Debug, Default)]
-pub struct SeaOrmDao;
+pub struct SeaOrmDao {
+    read_from_replica: bool,
+}
+
+impl SeaOrmDao {
+    fn reader(&self) -> DaoResult<&'static sea_orm::DatabaseConnection> {
+        if self.read_from_replica {
+            crate::database::try_get_read().ok_or(DaoError::DatabaseNotInitialized)
+        } else {
+            db()
+        }
+    }
+}
 
 #[async_trait]
 impl Dao for SeaOrmDao {
@@ -75,18 +286,68 @@ impl Dao for SeaOrmDao {
         &self,
         username: &str,
     ) -> DaoResult<Option<entities::users::Model>> {
-        find_user_by_username_on(db()?, username).await
+        find_user_by_username_on(self.reader()?, username).await
+    }
+
+    async fn insert_user(
+        &self,
+        username: &str,
+        password_hash: &str,
+        email: Option<&str>,
+        display_name: &str,
+    ) -> DaoResult<()> {
+        insert_user_on(
+            db()?,
+            username,
+            password_hash,
+            email,
+            display_name,
+            USER_SOURCE_PASSWORD,
+        )
+        .await
+    }
+
+    async fn insert_oauth2_user(
+        &self,
+        username: &str,
+        email: Option<&str>,
+        display_name: &str,
+    ) -> DaoResult<()> {
+        insert_user_on(db()?, username, "", email, display_name, USER_SOURCE_OAUTH2).await
+    }
+
+    async fn list_users(
+        &self,
+        offset: u64,
+        limit: u64,
+    ) -> DaoResult<(Vec<entities::users::Model>, i64)> {
+        list_users_on(self.reader()?, offset, limit).await
+    }
+
+    async fn update_user_password(&self, username: &str, password_hash: &str) -> DaoResult<bool> {
+        update_user_password_on(db()?, username, password_hash).await
     }
 
-    async fn insert_user(&self, username: &str, password_hash: &str) -> DaoResult<()> {
-        insert_user_on(db()?, username, password_hash).await
+    async fn delete_user(&self, username: &str) -> DaoResult<bool> {
+        delete_user_on(db()?, username).await
     }
 
     async fn find_latest_file_revision_by_depot_path(
         &self,
         depot_path: &str,
     ) -> DaoResult<Option<entities::file_revisions::Model>> {
-        find_latest_file_revision_by_depot_path_on(db()?, depot_path).await
+        find_latest_file_revision_by_depot_path_on(self.reader()?, depot_path).await
+    }
+
+    async fn find_file_by_depot_path(
+        &self,
+        depot_path: &str,
+    ) -> DaoResult<Option<entities::files::Model>> {
+        find_file_by_depot_path_on(self.reader()?, depot_path).await
+    }
+
+    async fn add_branch_to_file(&self, depot_path: &str, branch_id: &str) -> DaoResult<bool> {
+        add_branch_to_file_on(db()?, depot_path, branch_id).await
     }
 
     async fn insert_changelist(
@@ -96,7 +357,11 @@ impl Dao for SeaOrmDao {
         committed_at: i64,
         metadata: serde_json::Value,
     ) -> DaoResult<i64> {
-        insert_changelist_on(db()?, author, description, committed_at, metadata).await
+        insert_changelist_on(db()?, "", author, description, committed_at, metadata).await
+    }
+
+    async fn next_changelist_id(&self) -> DaoResult<i64> {
+        next_changelist_id_on(db()?).await
     }
 
     async fn commit_submit(
@@ -107,392 +372,4413 @@ impl Dao for SeaOrmDao {
         metadata: serde_json::Value,
         revisions: Vec<NewFileRevisionInput>,
     ) -> DaoResult<i64> {
-        commit_submit_on(db()?, author, description, committed_at, metadata, revisions)
-            .await
+        commit_submit_on(
+            db()?,
+            None,
+            author,
+            description,
+            committed_at,
+            metadata,
+            revisions,
+        )
+        .await
     }
-}
 
-/// 测试实现：纯内存版本，便于本地/单测运行（不依赖 Postgres）。
-#[derive(Debug, Default)]
-pub struct MockDao {
-    inner: std::sync::Mutex<MockDaoState>,
-}
+    async fn commit_submit_to_branch(
+        &self,
+        branch_id: &str,
+        author: &str,
+        description: &str,
+        committed_at: i64,
+        metadata: serde_json::Value,
+        revisions: Vec<NewFileRevisionInput>,
+    ) -> DaoResult<i64> {
+        commit_submit_on(
+            db()?,
+            Some(branch_id),
+            author,
+            description,
+            committed_at,
+            metadata,
+            revisions,
+        )
+        .await
+    }
 
-#[derive(Debug)]
-struct MockDaoState {
-    next_changelist_id: i64,
-    users: HashMap<String, entities::users::Model>,
-    latest_revisions: HashMap<String, entities::file_revisions::Model>, // key: ltree_key
-}
+    async fn find_branch_by_id(&self, branch_id: &str) -> DaoResult<Option<BranchDoc>> {
+        find_branch_by_id_on(self.reader()?, branch_id).await
+    }
 
-impl Default for MockDaoState {
-    fn default() -> Self {
-        Self {
-            next_changelist_id: 1,
-            users: HashMap::new(),
-            latest_revisions: HashMap::new(),
-        }
+    async fn find_branch_head(&self, branch_id: &str) -> DaoResult<Option<i64>> {
+        find_branch_head_on(self.reader()?, branch_id).await
     }
-}
 
-#[async_trait]
-impl Dao for MockDao {
-    async fn find_user_by_username(
-        &self,
-        username: &str,
-    ) -> DaoResult<Option<entities::users::Model>> {
-        let g = self.inner.lock().expect("MockDao poisoned");
-        Ok(g.users.get(username).cloned())
+    async fn is_changelist_on_chain(&self, head: i64, changelist_id: i64) -> DaoResult<bool> {
+        is_changelist_on_chain_on(self.reader()?, head, changelist_id).await
     }
 
-    async fn insert_user(&self, username: &str, password_hash: &str) -> DaoResult<()> {
-        let mut g = self.inner.lock().expect("MockDao poisoned");
-        if g.users.contains_key(username) {
-            return Err(DaoError::Db(DbErr::RecordNotInserted));
-        }
-        g.users.insert(
-            username.to_string(),
-            entities::users::Model {
-                id: username.to_string(),
-                password: password_hash.to_string(),
-            },
-        );
-        Ok(())
+    async fn find_merge_base(&self, a: i64, b: i64) -> DaoResult<i64> {
+        find_merge_base_on(self.reader()?, a, b).await
     }
 
-    async fn find_latest_file_revision_by_depot_path(
+    async fn insert_branch(&self, branch: &BranchDoc) -> DaoResult<()> {
+        insert_branch_on(db()?, branch).await
+    }
+
+    async fn update_branch_head(&self, branch_id: &str, changelist_id: i64) -> DaoResult<bool> {
+        update_branch_head_on(db()?, branch_id, changelist_id).await
+    }
+
+    async fn list_branches(
         &self,
-        depot_path: &str,
-    ) -> DaoResult<Option<entities::file_revisions::Model>> {
-        let key = ltree_key::depot_path_str_to_ltree_key(depot_path)?;
-        let g = self.inner.lock().expect("MockDao poisoned");
-        Ok(g.latest_revisions.get(&key).cloned())
+        sort_by: BranchSort,
+        offset: u64,
+        limit: u64,
+    ) -> DaoResult<(Vec<BranchDoc>, i64)> {
+        list_branches_on(self.reader()?, sort_by, offset, limit).await
     }
 
-    async fn insert_changelist(
+    async fn update_branch_metadata(
         &self,
-        _author: &str,
-        _description: &str,
-        _committed_at: i64,
-        _metadata: serde_json::Value,
-    ) -> DaoResult<i64> {
-        let mut g = self.inner.lock().expect("MockDao poisoned");
-        let id = g.next_changelist_id;
-        g.next_changelist_id = g.next_changelist_id.saturating_add(1);
-        Ok(id)
+        branch_id: &str,
+        metadata: &BranchMetadata,
+    ) -> DaoResult<bool> {
+        update_branch_metadata_on(db()?, branch_id, metadata).await
     }
 
-    async fn commit_submit(
+    async fn set_branch_protection(&self, branch_id: &str, is_protected: bool) -> DaoResult<bool> {
+        set_branch_protection_on(db()?, branch_id, is_protected).await
+    }
+
+    async fn find_latest_changelist_before(
         &self,
-        author: &str,
-        description: &str,
+        branch_id: &str,
         committed_at: i64,
-        metadata: serde_json::Value,
-        revisions: Vec<NewFileRevisionInput>,
-    ) -> DaoResult<i64> {
-        let changelist_id = self
-            .insert_changelist(author, description, committed_at, metadata)
-            .await?;
+    ) -> DaoResult<Option<i64>> {
+        find_latest_changelist_before_on(self.reader()?, branch_id, committed_at).await
+    }
 
-        let mut g = self.inner.lock().expect("MockDao poisoned");
-        for r in revisions {
-            let key = ltree_key::depot_path_str_to_ltree_key(&r.depot_path)?;
+    async fn add_label_to_changelist(&self, changelist_id: i64, label: &str) -> DaoResult<bool> {
+        add_label_to_changelist_on(db()?, changelist_id, label).await
+    }
 
-            let model = entities::file_revisions::Model {
-                path: key.clone(),
-                generation: r.generation,
-                revision: r.revision,
-                changelist_id,
-                binary_id: r.binary_id,
-                size: r.size,
-                is_delete: r.is_delete,
-                created_at: r.created_at,
-                metadata: r.metadata,
-            };
+    async fn remove_label_from_changelist(
+        &self,
+        changelist_id: i64,
+        label: &str,
+    ) -> DaoResult<bool> {
+        remove_label_from_changelist_on(db()?, changelist_id, label).await
+    }
 
-            // 更新 latest：按 (generation, revision) 取最大
-            let should_replace = match g.latest_revisions.get(&key) {
-                None => true,
-                Some(existing) => {
-                    (model.generation, model.revision) > (existing.generation, existing.revision)
-                }
-            };
+    async fn find_changelists_by_label(
+        &self,
+        branch_id: &str,
+        label: &str,
+        limit: u64,
+    ) -> DaoResult<Vec<entities::changelists::Model>> {
+        find_changelists_by_label_on(self.reader()?, branch_id, label, limit).await
+    }
 
-            if should_replace {
-                g.latest_revisions.insert(key, model);
-            }
-        }
+    async fn list_changelist_chain(
+        &self,
+        from_cl: i64,
+        to_cl: i64,
+        limit: u64,
+    ) -> DaoResult<Vec<ChangelistSummaryRecord>> {
+        list_changelist_chain_on(self.reader()?, from_cl, to_cl, limit).await
+    }
 
-        Ok(changelist_id)
+    async fn find_changelist_by_id(
+        &self,
+        changelist_id: i64,
+    ) -> DaoResult<Option<entities::changelists::Model>> {
+        find_changelist_by_id_on(self.reader()?, changelist_id).await
     }
-}
 
-static DAO_INSTANCE: OnceLock<RwLock<Arc<dyn Dao>>> = OnceLock::new();
+    async fn find_latest_changelist_id(&self) -> DaoResult<Option<i64>> {
+        find_latest_changelist_id_on(self.reader()?).await
+    }
 
-fn dao_cell() -> &'static RwLock<Arc<dyn Dao>> {
-    DAO_INSTANCE.get_or_init(|| RwLock::new(Arc::new(SeaOrmDao::default())))
-}
+    async fn list_file_revisions_by_depot_path(
+        &self,
+        depot_path: &str,
+    ) -> DaoResult<Vec<entities::file_revisions::Model>> {
+        list_file_revisions_by_depot_path_on(self.reader()?, depot_path).await
+    }
 
-/// 获取当前 DAO（默认是生产实现 `SeaOrmDao`）。
-pub fn dao() -> Arc<dyn Dao> {
-    dao_cell()
-        .read()
-        .expect("dao RwLock poisoned")
-        .clone()
-}
+    async fn update_file_revision_tags(
+        &self,
+        depot_path: &str,
+        generation: i64,
+        revision: i64,
+        tags_to_add: &[String],
+        tags_to_remove: &[String],
+    ) -> DaoResult<Option<Vec<String>>> {
+        update_file_revision_tags_on(
+            db()?,
+            depot_path,
+            generation,
+            revision,
+            tags_to_add,
+            tags_to_remove,
+        )
+        .await
+    }
 
-/// 仅用于测试/本地：覆盖全局 DAO 实现（例如注入 `MockDao`）。
-///
-/// 注意：这是全局状态，建议测试串行使用（或自行加锁）。
-pub fn set_dao_for_tests(new_dao: Arc<dyn Dao>) {
-    *dao_cell().write().expect("dao RwLock poisoned") = new_dao;
-}
+    async fn find_file_revisions_by_tag(
+        &self,
+        branch_id: &str,
+        tag: &str,
+        limit: u64,
+    ) -> DaoResult<Vec<entities::file_revisions::Model>> {
+        find_file_revisions_by_tag_on(self.reader()?, branch_id, tag, limit).await
+    }
 
-async fn find_user_by_username_on<C: ConnectionTrait>(
-    conn: &C,
-    username: &str,
-) -> DaoResult<Option<entities::users::Model>> {
-    let model = entities::users::Entity::find_by_id(username.to_string())
-        .one(conn)
-        .await?;
-    Ok(model)
-}
+    async fn squash_changelists(&self, input: &SquashChangelistsInput) -> DaoResult<i64> {
+        squash_changelists_on(db()?, input).await
+    }
 
-#[derive(Debug, Clone)]
-pub struct NewFileRevisionInput {
-    pub depot_path: String,
-    pub generation: i64,
-    pub revision: i64,
-    pub binary_id: serde_json::Value,
-    pub size: i64,
-    pub is_delete: bool,
-    pub created_at: i64,
-    pub metadata: serde_json::Value,
-}
+    async fn find_file_revision_at(
+        &self,
+        depot_path: &str,
+        changelist_id: i64,
+    ) -> DaoResult<Option<entities::file_revisions::Model>> {
+        find_file_revision_at_on(self.reader()?, depot_path, changelist_id).await
+    }
 
-/// 根据用户名查找用户。
-pub async fn find_user_by_username(username: &str) -> DaoResult<Option<entities::users::Model>> {
-    dao().find_user_by_username(username).await
-}
+    async fn list_file_revisions_by_changelist(
+        &self,
+        changelist_id: i64,
+    ) -> DaoResult<Vec<entities::file_revisions::Model>> {
+        list_file_revisions_by_changelist_on(self.reader()?, changelist_id).await
+    }
 
-/// 创建新用户文档。
-///
-/// - `username` 作为主键 `id` 字段；
-/// - `password_hash` 存储为 `password` 字段，建议为 Argon2 哈希。
-pub async fn insert_user(username: &str, password_hash: &str) -> DaoResult<()> {
-    dao().insert_user(username, password_hash).await
-}
+    async fn list_file_tree_revisions(
+        &self,
+        depot: &DepotPath,
+        changelist_id: i64,
+    ) -> DaoResult<Vec<entities::file_revisions::Model>> {
+        crate::database::service::get_file_tree_revisions(depot, changelist_id).await
+    }
 
-async fn insert_user_on<C: ConnectionTrait>(
-    conn: &C,
+    async fn cherry_pick(&self, input: &CherryPickInput) -> DaoResult<i64> {
+        cherry_pick_on(db()?, input).await
+    }
+
+    async fn merge_branches(&self, input: &MergeInput) -> DaoResult<i64> {
+        merge_branches_on(db()?, input).await
+    }
+
+    async fn insert_audit_event(&self, event: &AuditEvent) -> DaoResult<()> {
+        insert_audit_event_on(db()?, event).await
+    }
+
+    async fn list_audit_events(&self, query: &AuditQuery) -> DaoResult<Vec<AuditEvent>> {
+        list_audit_events_on(self.reader()?, query).await
+    }
+
+    async fn find_workspace_by_name(
+        &self,
+        name: &str,
+    ) -> DaoResult<Option<entities::workspaces::Model>> {
+        find_workspace_by_name_on(self.reader()?, name).await
+    }
+
+    async fn insert_workspace(&self, workspace: &entities::workspaces::Model) -> DaoResult<()> {
+        insert_workspace_on(db()?, workspace).await
+    }
+
+    async fn delete_workspace(&self, name: &str) -> DaoResult<bool> {
+        delete_workspace_on(db()?, name).await
+    }
+
+    async fn insert_webhook(&self, webhook: &WebhookConfig) -> DaoResult<i64> {
+        insert_webhook_on(db()?, webhook).await
+    }
+
+    async fn delete_webhook(&self, id: i64) -> DaoResult<bool> {
+        delete_webhook_on(db()?, id).await
+    }
+
+    async fn list_webhooks(&self) -> DaoResult<Vec<WebhookConfig>> {
+        list_webhooks_on(self.reader()?).await
+    }
+
+    async fn insert_snapshot(&self, snapshot: &SnapshotDoc) -> DaoResult<i64> {
+        insert_snapshot_on(db()?, snapshot).await
+    }
+
+    async fn find_snapshot_by_id(&self, id: i64) -> DaoResult<Option<SnapshotDoc>> {
+        find_snapshot_by_id_on(self.reader()?, id).await
+    }
+
+    async fn list_snapshots(&self, branch_id: &str) -> DaoResult<Vec<SnapshotDoc>> {
+        list_snapshots_on(self.reader()?, branch_id).await
+    }
+
+    async fn delete_snapshot(&self, id: i64) -> DaoResult<bool> {
+        delete_snapshot_on(db()?, id).await
+    }
+
+    async fn find_submission(
+        &self,
+        request_id: &str,
+        not_before: i64,
+    ) -> DaoResult<Option<SubmissionRecord>> {
+        find_submission_on(self.reader()?, request_id, not_before).await
+    }
+
+    async fn insert_submission(
+        &self,
+        record: &SubmissionRecord,
+        expire_before: i64,
+    ) -> DaoResult<()> {
+        insert_submission_on(db()?, record, expire_before).await
+    }
+
+    async fn insert_submit_ticket(&self, record: &SubmitTicketRecord) -> DaoResult<()> {
+        insert_submit_ticket_on(db()?, record).await
+    }
+
+    async fn delete_submit_ticket(&self, ticket: &str) -> DaoResult<bool> {
+        delete_submit_ticket_on(db()?, ticket).await
+    }
+
+    async fn delete_expired_submit_tickets(&self, now: i64) -> DaoResult<u64> {
+        delete_expired_submit_tickets_on(db()?, now).await
+    }
+
+    async fn list_submit_tickets(&self, now: i64) -> DaoResult<Vec<SubmitTicketRecord>> {
+        list_submit_tickets_on(self.reader()?, now).await
+    }
+
+    async fn find_submit_ticket(&self, ticket: &str) -> DaoResult<Option<SubmitTicketRecord>> {
+        find_submit_ticket_on(self.reader()?, ticket).await
+    }
+
+    async fn update_submit_ticket_status(
+        &self,
+        ticket: &str,
+        status: SubmitContextStatus,
+        branch_id: Option<&str>,
+    ) -> DaoResult<bool> {
+        update_submit_ticket_status_on(db()?, ticket, status, branch_id).await
+    }
+
+    async fn increment_login_failures(
+        &self,
+        username: &str,
+        now: i64,
+        window_secs: i64,
+    ) -> DaoResult<entities::login_failures::Model> {
+        increment_login_failures_on(db()?, username, now, window_secs).await
+    }
+
+    // 锁定状态需要立即生效，不读副本
+    async fn find_login_failures(
+        &self,
+        username: &str,
+    ) -> DaoResult<Option<entities::login_failures::Model>> {
+        Ok(
+            entities::login_failures::Entity::find_by_id(username.to_string())
+                .one(db()?)
+                .await?,
+        )
+    }
+
+    async fn lock_account(&self, username: &str, locked_until: i64) -> DaoResult<()> {
+        lock_account_on(db()?, username, locked_until).await
+    }
+
+    async fn reset_login_failures(&self, username: &str) -> DaoResult<()> {
+        entities::login_failures::Entity::delete_by_id(username.to_string())
+            .exec(db()?)
+            .await?;
+        Ok(())
+    }
+
+    async fn repository_stats(&self) -> DaoResult<RepositoryStats> {
+        repository_stats_on(self.reader()?).await
+    }
+}
+
+/// 测试实现：纯内存版本，便于本地/单测运行（不依赖 Postgres）。
+#[derive(Debug, Default)]
+pub struct MockDao {
+    inner: std::sync::Mutex<MockDaoState>,
+}
+
+#[derive(Debug)]
+struct MockDaoState {
+    next_changelist_id: i64,
+    users: HashMap<String, entities::users::Model>,
+    latest_revisions: HashMap<String, entities::file_revisions::Model>, // key: ltree_key
+    files: HashMap<String, entities::files::Model>,                     // key: ltree_key
+    /// 所有写入过的 revision，按写入顺序排列
+    revisions: Vec<entities::file_revisions::Model>,
+    branches: HashMap<String, BranchDoc>,
+    changelists: HashMap<i64, entities::changelists::Model>,
+    audit_events: Vec<AuditEvent>,
+    workspaces: HashMap<String, entities::workspaces::Model>,
+    next_webhook_id: i64,
+    webhooks: Vec<WebhookConfig>,
+    next_snapshot_id: i64,
+    snapshots: Vec<SnapshotDoc>,
+    submissions: HashMap<String, SubmissionRecord>,
+    submit_tickets: HashMap<String, SubmitTicketRecord>,
+    login_failures: HashMap<String, entities::login_failures::Model>,
+    /// 所有写入过的 revision 的 size 之和（latest_revisions 只保留最新的 revision）
+    total_revision_bytes: i64,
+}
+
+impl MockDaoState {
+    fn allocate_changelist_id(&mut self) -> i64 {
+        let id = self.next_changelist_id;
+        self.next_changelist_id = self.next_changelist_id.saturating_add(1);
+        id
+    }
+
+    /// 与 `find_branch_head` 一致：默认分支的 HEAD 是提交到默认分支的最新 changelist
+    fn branch_head(&self, branch_id: &str) -> Option<i64> {
+        if branch_id.is_empty() {
+            Some(
+                self.changelists
+                    .values()
+                    .filter(|cl| cl.branch_id.is_empty())
+                    .map(|cl| cl.id)
+                    .max()
+                    .unwrap_or(0),
+            )
+        } else {
+            self.branches.get(branch_id).map(|b| b.head_changelist_id)
+        }
+    }
+
+    /// 与 `changelist_chain_cte` 一致：从 `head` 沿 parent_changelist_id 回溯的 changelist 链
+    fn chain(&self, head: i64) -> HashSet<i64> {
+        let mut chain = HashSet::new();
+        let mut current = self.changelists.get(&head);
+        while let Some(cl) = current {
+            chain.insert(cl.id);
+            current = self.changelists.get(&cl.parent_changelist_id);
+        }
+        chain
+    }
+
+    /// 与 `find_merge_base_on` 一致：`head` 及其沿 parent 与合并来源回溯能到达的所有 changelist
+    fn ancestors(&self, head: i64) -> HashSet<i64> {
+        let mut ancestors = HashSet::new();
+        let mut pending = vec![head];
+        while let Some(id) = pending.pop() {
+            let Some(cl) = self.changelists.get(&id) else {
+                continue;
+            };
+            if !ancestors.insert(id) {
+                continue;
+            }
+            pending.push(cl.parent_changelist_id);
+            if let Some((_, merged_from)) = changelist_merged_from(&cl.metadata) {
+                pending.push(merged_from);
+            }
+        }
+        ancestors
+    }
+
+    fn insert_changelist(
+        &mut self,
+        branch_id: &str,
+        parent_changelist_id: i64,
+        author: &str,
+        description: &str,
+        committed_at: i64,
+        metadata: serde_json::Value,
+    ) -> i64 {
+        let id = self.allocate_changelist_id();
+        self.changelists.insert(
+            id,
+            entities::changelists::Model {
+                id,
+                author: author.to_string(),
+                description: description.to_string(),
+                committed_at,
+                metadata,
+                branch_id: branch_id.to_string(),
+                parent_changelist_id,
+            },
+        );
+        id
+    }
+
+    /// 在 `branch_id` 的 HEAD 之后写入一个 changelist 及其 revision，并推进分支的 HEAD
+    fn commit(
+        &mut self,
+        branch_id: &str,
+        author: &str,
+        description: &str,
+        committed_at: i64,
+        metadata: serde_json::Value,
+        revisions: Vec<NewFileRevisionInput>,
+    ) -> DaoResult<i64> {
+        // 与 file_revisions 的主键一致：整个提交在任何写入前检查
+        let mut seen = HashSet::new();
+        for r in &revisions {
+            let key = ltree_key::depot_path_str_to_ltree_key(&r.depot_path)?;
+            let duplicated = self
+                .revisions
+                .iter()
+                .any(|m| m.path == key && m.generation == r.generation && m.revision == r.revision);
+            if duplicated || !seen.insert((key, r.generation, r.revision)) {
+                return Err(DaoError::RevisionConflict(r.depot_path.clone()));
+            }
+        }
+
+        let parent_changelist_id = self.branch_head(branch_id).unwrap_or(0);
+        let changelist_id = self.insert_changelist(
+            branch_id,
+            parent_changelist_id,
+            author,
+            description,
+            committed_at,
+            metadata,
+        );
+        for r in revisions {
+            let key = ltree_key::depot_path_str_to_ltree_key(&r.depot_path)?;
+            self.total_revision_bytes += r.size;
+            self.files
+                .entry(key.clone())
+                .or_insert_with(|| entities::files::Model {
+                    path: key.clone(),
+                    created_at: r.created_at,
+                    metadata: r.file_metadata.clone(),
+                });
+
+            let model = entities::file_revisions::Model {
+                path: key.clone(),
+                generation: r.generation,
+                revision: r.revision,
+                changelist_id,
+                binary_id: r.binary_id,
+                size: r.size,
+                is_delete: r.is_delete,
+                created_at: r.created_at,
+                metadata: r.metadata,
+            };
+            self.revisions.push(model.clone());
+
+            // 更新 latest：按 (generation, revision) 取最大
+            let should_replace = match self.latest_revisions.get(&key) {
+                None => true,
+                Some(existing) => {
+                    (model.generation, model.revision) > (existing.generation, existing.revision)
+                }
+            };
+
+            if should_replace {
+                self.latest_revisions.insert(key, model);
+            }
+        }
+
+        if let Some(branch) = self.branches.get_mut(branch_id) {
+            branch.head_changelist_id = changelist_id;
+        }
+        Ok(changelist_id)
+    }
+
+    fn insert_user(
+        &mut self,
+        username: &str,
+        password_hash: &str,
+        email: Option<&str>,
+        display_name: &str,
+        source: &str,
+    ) -> DaoResult<()> {
+        if self.users.contains_key(username) {
+            return Err(DaoError::Db(DbErr::RecordNotInserted));
+        }
+        self.users.insert(
+            username.to_string(),
+            entities::users::Model {
+                id: username.to_string(),
+                password: password_hash.to_string(),
+                created_at: chrono::Utc::now().timestamp_millis(),
+                email: email.map(str::to_string),
+                display_name: display_name.to_string(),
+                source: source.to_string(),
+            },
+        );
+        Ok(())
+    }
+}
+
+impl Default for MockDaoState {
+    fn default() -> Self {
+        Self {
+            next_changelist_id: 1,
+            users: HashMap::new(),
+            latest_revisions: HashMap::new(),
+            files: HashMap::new(),
+            revisions: Vec::new(),
+            branches: HashMap::new(),
+            changelists: HashMap::new(),
+            audit_events: Vec::new(),
+            workspaces: HashMap::new(),
+            next_webhook_id: 1,
+            webhooks: Vec::new(),
+            next_snapshot_id: 1,
+            snapshots: Vec::new(),
+            submissions: HashMap::new(),
+            submit_tickets: HashMap::new(),
+            login_failures: HashMap::new(),
+            total_revision_bytes: 0,
+        }
+    }
+}
+
+/// 与 `get_file_tree_revisions` 的查询范围一致：文件精确匹配，目录只含直接子文件，通配含所有后代
+fn mock_depot_matches(depot: &DepotPath, path: &str) -> bool {
+    let pattern = depot.to_string();
+    if depot.is_file() {
+        path == pattern
+    } else if depot.is_directory() {
+        path.strip_prefix(&pattern)
+            .is_some_and(|rest| !rest.contains('/'))
+    } else {
+        path.starts_with(pattern.trim_end_matches("..."))
+    }
+}
+
+#[async_trait]
+impl Dao for MockDao {
+    async fn find_user_by_username(
+        &self,
+        username: &str,
+    ) -> DaoResult<Option<entities::users::Model>> {
+        let g = self.inner.lock().expect("MockDao poisoned");
+        Ok(g.users.get(username).cloned())
+    }
+
+    async fn insert_user(
+        &self,
+        username: &str,
+        password_hash: &str,
+        email: Option<&str>,
+        display_name: &str,
+    ) -> DaoResult<()> {
+        let mut g = self.inner.lock().expect("MockDao poisoned");
+        g.insert_user(
+            username,
+            password_hash,
+            email,
+            display_name,
+            USER_SOURCE_PASSWORD,
+        )
+    }
+
+    async fn insert_oauth2_user(
+        &self,
+        username: &str,
+        email: Option<&str>,
+        display_name: &str,
+    ) -> DaoResult<()> {
+        let mut g = self.inner.lock().expect("MockDao poisoned");
+        g.insert_user(username, "", email, display_name, USER_SOURCE_OAUTH2)
+    }
+
+    async fn list_users(
+        &self,
+        offset: u64,
+        limit: u64,
+    ) -> DaoResult<(Vec<entities::users::Model>, i64)> {
+        let g = self.inner.lock().expect("MockDao poisoned");
+        let mut users: Vec<entities::users::Model> = g.users.values().cloned().collect();
+        users.sort_by(|a, b| a.id.cmp(&b.id));
+        let total = users.len() as i64;
+        let page = users
+            .into_iter()
+            .skip(offset as usize)
+            .take(limit as usize)
+            .collect();
+        Ok((page, total))
+    }
+
+    async fn update_user_password(&self, username: &str, password_hash: &str) -> DaoResult<bool> {
+        let mut g = self.inner.lock().expect("MockDao poisoned");
+        match g.users.get_mut(username) {
+            Some(user) => {
+                user.password = password_hash.to_string();
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    async fn delete_user(&self, username: &str) -> DaoResult<bool> {
+        let mut g = self.inner.lock().expect("MockDao poisoned");
+        Ok(g.users.remove(username).is_some())
+    }
+
+    async fn find_latest_file_revision_by_depot_path(
+        &self,
+        depot_path: &str,
+    ) -> DaoResult<Option<entities::file_revisions::Model>> {
+        let key = ltree_key::depot_path_str_to_ltree_key(depot_path)?;
+        let g = self.inner.lock().expect("MockDao poisoned");
+        Ok(g.latest_revisions.get(&key).cloned())
+    }
+
+    async fn find_file_by_depot_path(
+        &self,
+        depot_path: &str,
+    ) -> DaoResult<Option<entities::files::Model>> {
+        let key = ltree_key::depot_path_str_to_ltree_key(depot_path)?;
+        let g = self.inner.lock().expect("MockDao poisoned");
+        Ok(g.files.get(&key).cloned())
+    }
+
+    async fn add_branch_to_file(&self, depot_path: &str, branch_id: &str) -> DaoResult<bool> {
+        let key = ltree_key::depot_path_str_to_ltree_key(depot_path)?;
+        let mut g = self.inner.lock().expect("MockDao poisoned");
+        let Some(file) = g.files.get_mut(&key) else {
+            return Ok(false);
+        };
+        let mut branches = file_seen_on_branches(&file.metadata);
+        if branches.iter().any(|b| b == branch_id) {
+            return Ok(false);
+        }
+        branches.push(branch_id.to_string());
+        set_file_seen_on_branches(&mut file.metadata, branches);
+        Ok(true)
+    }
+
+    async fn next_changelist_id(&self) -> DaoResult<i64> {
+        let mut g = self.inner.lock().expect("MockDao poisoned");
+        Ok(g.allocate_changelist_id())
+    }
+
+    async fn insert_changelist(
+        &self,
+        author: &str,
+        description: &str,
+        committed_at: i64,
+        metadata: serde_json::Value,
+    ) -> DaoResult<i64> {
+        let mut g = self.inner.lock().expect("MockDao poisoned");
+        let parent_changelist_id = g.branch_head("").unwrap_or(0);
+        Ok(g.insert_changelist(
+            "",
+            parent_changelist_id,
+            author,
+            description,
+            committed_at,
+            metadata,
+        ))
+    }
+
+    async fn commit_submit(
+        &self,
+        author: &str,
+        description: &str,
+        committed_at: i64,
+        metadata: serde_json::Value,
+        revisions: Vec<NewFileRevisionInput>,
+    ) -> DaoResult<i64> {
+        let mut g = self.inner.lock().expect("MockDao poisoned");
+        g.commit("", author, description, committed_at, metadata, revisions)
+    }
+
+    async fn commit_submit_to_branch(
+        &self,
+        branch_id: &str,
+        author: &str,
+        description: &str,
+        committed_at: i64,
+        metadata: serde_json::Value,
+        revisions: Vec<NewFileRevisionInput>,
+    ) -> DaoResult<i64> {
+        let mut g = self.inner.lock().expect("MockDao poisoned");
+        g.commit(
+            branch_id,
+            author,
+            description,
+            committed_at,
+            metadata,
+            revisions,
+        )
+    }
+
+    async fn find_branch_by_id(&self, branch_id: &str) -> DaoResult<Option<BranchDoc>> {
+        let g = self.inner.lock().expect("MockDao poisoned");
+        Ok(g.branches.get(branch_id).cloned())
+    }
+
+    async fn find_branch_head(&self, branch_id: &str) -> DaoResult<Option<i64>> {
+        let g = self.inner.lock().expect("MockDao poisoned");
+        Ok(g.branch_head(branch_id))
+    }
+
+    async fn is_changelist_on_chain(&self, head: i64, changelist_id: i64) -> DaoResult<bool> {
+        let g = self.inner.lock().expect("MockDao poisoned");
+        Ok(g.chain(head).contains(&changelist_id))
+    }
+
+    async fn find_merge_base(&self, a: i64, b: i64) -> DaoResult<i64> {
+        let g = self.inner.lock().expect("MockDao poisoned");
+        let ancestors = g.ancestors(a);
+        Ok(g.ancestors(b)
+            .into_iter()
+            .filter(|id| ancestors.contains(id))
+            .max()
+            .unwrap_or(0))
+    }
+
+    async fn insert_branch(&self, branch: &BranchDoc) -> DaoResult<()> {
+        let mut g = self.inner.lock().expect("MockDao poisoned");
+        if g.branches.contains_key(&branch.id) {
+            return Err(DaoError::Db(DbErr::RecordNotInserted));
+        }
+        g.branches.insert(branch.id.clone(), branch.clone());
+        Ok(())
+    }
+
+    async fn update_branch_head(&self, branch_id: &str, changelist_id: i64) -> DaoResult<bool> {
+        let mut g = self.inner.lock().expect("MockDao poisoned");
+        match g.branches.get_mut(branch_id) {
+            Some(branch) => {
+                branch.head_changelist_id = changelist_id;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    async fn list_branches(
+        &self,
+        sort_by: BranchSort,
+        offset: u64,
+        limit: u64,
+    ) -> DaoResult<(Vec<BranchDoc>, i64)> {
+        let g = self.inner.lock().expect("MockDao poisoned");
+        let mut branches: Vec<BranchDoc> = g.branches.values().cloned().collect();
+        branches.sort_by(|a, b| {
+            match sort_by {
+                BranchSort::CreatedAt => b.created_at.cmp(&a.created_at),
+                BranchSort::HeadChangelistId => b.head_changelist_id.cmp(&a.head_changelist_id),
+            }
+            .then_with(|| a.id.cmp(&b.id))
+        });
+        let total = branches.len() as i64;
+        let page = branches
+            .into_iter()
+            .skip(offset as usize)
+            .take(limit as usize)
+            .collect();
+        Ok((page, total))
+    }
+
+    async fn update_branch_metadata(
+        &self,
+        branch_id: &str,
+        metadata: &BranchMetadata,
+    ) -> DaoResult<bool> {
+        let mut g = self.inner.lock().expect("MockDao poisoned");
+        match g.branches.get_mut(branch_id) {
+            Some(branch) => {
+                branch.metadata = metadata.clone();
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    async fn set_branch_protection(&self, branch_id: &str, is_protected: bool) -> DaoResult<bool> {
+        let mut g = self.inner.lock().expect("MockDao poisoned");
+        match g.branches.get_mut(branch_id) {
+            Some(branch) => {
+                branch.is_protected = is_protected;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    async fn find_latest_changelist_before(
+        &self,
+        branch_id: &str,
+        committed_at: i64,
+    ) -> DaoResult<Option<i64>> {
+        let g = self.inner.lock().expect("MockDao poisoned");
+        let head = if branch_id.is_empty() {
+            i64::MAX
+        } else {
+            match g.branches.get(branch_id) {
+                Some(branch) => branch.head_changelist_id,
+                None => return Ok(None),
+            }
+        };
+        Ok(g.changelists
+            .values()
+            .filter(|cl| cl.id <= head && cl.committed_at <= committed_at)
+            .map(|cl| cl.id)
+            .max())
+    }
+
+    async fn add_label_to_changelist(&self, changelist_id: i64, label: &str) -> DaoResult<bool> {
+        let mut g = self.inner.lock().expect("MockDao poisoned");
+        let Some(changelist) = g.changelists.get_mut(&changelist_id) else {
+            return Ok(false);
+        };
+        let mut labels = changelist_labels(&changelist.metadata);
+        if !labels.iter().any(|l| l == label) {
+            labels.push(label.to_string());
+        }
+        set_changelist_labels(&mut changelist.metadata, labels);
+        Ok(true)
+    }
+
+    async fn remove_label_from_changelist(
+        &self,
+        changelist_id: i64,
+        label: &str,
+    ) -> DaoResult<bool> {
+        let mut g = self.inner.lock().expect("MockDao poisoned");
+        let Some(changelist) = g.changelists.get_mut(&changelist_id) else {
+            return Ok(false);
+        };
+        let mut labels = changelist_labels(&changelist.metadata);
+        labels.retain(|l| l != label);
+        set_changelist_labels(&mut changelist.metadata, labels);
+        Ok(true)
+    }
+
+    async fn find_changelists_by_label(
+        &self,
+        branch_id: &str,
+        label: &str,
+        limit: u64,
+    ) -> DaoResult<Vec<entities::changelists::Model>> {
+        let g = self.inner.lock().expect("MockDao poisoned");
+        let head = if branch_id.is_empty() {
+            i64::MAX
+        } else {
+            match g.branches.get(branch_id) {
+                Some(branch) => branch.head_changelist_id,
+                None => return Ok(vec![]),
+            }
+        };
+        let mut changelists: Vec<entities::changelists::Model> = g
+            .changelists
+            .values()
+            .filter(|cl| {
+                cl.id <= head && changelist_labels(&cl.metadata).iter().any(|l| l == label)
+            })
+            .cloned()
+            .collect();
+        changelists.sort_by_key(|cl| std::cmp::Reverse(cl.id));
+        changelists.truncate(limit as usize);
+        Ok(changelists)
+    }
+
+    async fn list_changelist_chain(
+        &self,
+        from_cl: i64,
+        to_cl: i64,
+        limit: u64,
+    ) -> DaoResult<Vec<ChangelistSummaryRecord>> {
+        let g = self.inner.lock().expect("MockDao poisoned");
+        let mut changelists: Vec<&entities::changelists::Model> = Vec::new();
+        let mut current = g.changelists.get(&to_cl);
+        while let Some(cl) = current.filter(|cl| cl.id >= from_cl) {
+            if changelist_squashed_into(&cl.metadata).is_none() {
+                changelists.push(cl);
+            }
+            current = g.changelists.get(&cl.parent_changelist_id);
+        }
+        changelists.truncate(limit as usize);
+        Ok(changelists
+            .into_iter()
+            .map(|cl| ChangelistSummaryRecord {
+                id: cl.id,
+                author: cl.author.clone(),
+                description: cl.description.clone(),
+                committed_at: cl.committed_at,
+                files_count: g
+                    .revisions
+                    .iter()
+                    .filter(|r| r.changelist_id == cl.id)
+                    .count() as i64,
+            })
+            .collect())
+    }
+
+    async fn find_changelist_by_id(
+        &self,
+        changelist_id: i64,
+    ) -> DaoResult<Option<entities::changelists::Model>> {
+        let g = self.inner.lock().expect("MockDao poisoned");
+        Ok(g.changelists.get(&changelist_id).cloned())
+    }
+
+    async fn find_latest_changelist_id(&self) -> DaoResult<Option<i64>> {
+        let g = self.inner.lock().expect("MockDao poisoned");
+        Ok(g.changelists.keys().max().copied())
+    }
+
+    async fn list_file_revisions_by_depot_path(
+        &self,
+        depot_path: &str,
+    ) -> DaoResult<Vec<entities::file_revisions::Model>> {
+        let key = ltree_key::depot_path_str_to_ltree_key(depot_path)?;
+        let g = self.inner.lock().expect("MockDao poisoned");
+        let mut revisions: Vec<entities::file_revisions::Model> = g
+            .revisions
+            .iter()
+            .filter(|r| r.path == key)
+            .cloned()
+            .collect();
+        revisions.sort_by_key(|r| std::cmp::Reverse((r.generation, r.revision)));
+        Ok(revisions)
+    }
+
+    async fn update_file_revision_tags(
+        &self,
+        depot_path: &str,
+        generation: i64,
+        revision: i64,
+        tags_to_add: &[String],
+        tags_to_remove: &[String],
+    ) -> DaoResult<Option<Vec<String>>> {
+        let key = ltree_key::depot_path_str_to_ltree_key(depot_path)?;
+        let mut g = self.inner.lock().expect("MockDao poisoned");
+        let state = &mut *g;
+        let is_target = |r: &entities::file_revisions::Model| {
+            r.path == key && r.generation == generation && r.revision == revision
+        };
+        let Some(model) = state.revisions.iter_mut().find(|r| is_target(r)) else {
+            return Ok(None);
+        };
+        let mut tags: std::collections::BTreeSet<String> =
+            file_revision_tags(&model.metadata).into_iter().collect();
+        tags.extend(tags_to_add.iter().cloned());
+        tags.retain(|t| !tags_to_remove.contains(t));
+        let tags: Vec<String> = tags.into_iter().collect();
+        set_file_revision_tags(&mut model.metadata, tags.clone());
+        if let Some(latest) = state
+            .latest_revisions
+            .get_mut(&key)
+            .filter(|r| is_target(r))
+        {
+            latest.metadata = model.metadata.clone();
+        }
+        Ok(Some(tags))
+    }
+
+    async fn find_file_revisions_by_tag(
+        &self,
+        branch_id: &str,
+        tag: &str,
+        limit: u64,
+    ) -> DaoResult<Vec<entities::file_revisions::Model>> {
+        let g = self.inner.lock().expect("MockDao poisoned");
+        let head = if branch_id.is_empty() {
+            i64::MAX
+        } else {
+            match g.branches.get(branch_id) {
+                Some(branch) => branch.head_changelist_id,
+                None => return Ok(vec![]),
+            }
+        };
+        let mut revisions: Vec<entities::file_revisions::Model> = g
+            .revisions
+            .iter()
+            .filter(|r| {
+                r.changelist_id <= head && file_revision_tags(&r.metadata).iter().any(|t| t == tag)
+            })
+            .cloned()
+            .collect();
+        revisions.sort_by(|a, b| {
+            b.changelist_id
+                .cmp(&a.changelist_id)
+                .then_with(|| a.path.cmp(&b.path))
+        });
+        revisions.truncate(limit as usize);
+        Ok(revisions)
+    }
+
+    async fn squash_changelists(&self, input: &SquashChangelistsInput) -> DaoResult<i64> {
+        let mut g = self.inner.lock().expect("MockDao poisoned");
+        let state = &mut *g;
+        let (branch_id, parent_changelist_id) = state
+            .changelists
+            .get(&input.from_changelist_id)
+            .map_or((String::new(), 0), |cl| {
+                (cl.branch_id.clone(), cl.parent_changelist_id)
+            });
+        let new_id = state.insert_changelist(
+            &branch_id,
+            parent_changelist_id,
+            &input.author,
+            &input.description,
+            input.committed_at,
+            squashed_from_metadata(input),
+        );
+        let range = input.from_changelist_id..=input.to_changelist_id;
+
+        // 每个文件只保留区间内最新的 revision，并归属到新的 changelist
+        let mut last_in_range: HashMap<String, (i64, i64)> = HashMap::new();
+        for r in state
+            .revisions
+            .iter()
+            .filter(|r| range.contains(&r.changelist_id))
+        {
+            let entry = last_in_range
+                .entry(r.path.clone())
+                .or_insert((r.generation, r.revision));
+            *entry = (*entry).max((r.generation, r.revision));
+        }
+        state.revisions.retain(|r| {
+            !range.contains(&r.changelist_id)
+                || last_in_range.get(&r.path) == Some(&(r.generation, r.revision))
+        });
+        for r in state
+            .revisions
+            .iter_mut()
+            .chain(state.latest_revisions.values_mut())
+        {
+            if range.contains(&r.changelist_id) {
+                r.changelist_id = new_id;
+            }
+        }
+
+        for changelist in state.changelists.values_mut() {
+            if range.contains(&changelist.id) {
+                set_squashed_into(&mut changelist.metadata, new_id);
+            }
+        }
+        if let Some(branch) = state.branches.get_mut(&input.branch_id) {
+            branch.head_changelist_id = new_id;
+        }
+        Ok(new_id)
+    }
+
+    async fn find_file_revision_at(
+        &self,
+        depot_path: &str,
+        changelist_id: i64,
+    ) -> DaoResult<Option<entities::file_revisions::Model>> {
+        let key = ltree_key::depot_path_str_to_ltree_key(depot_path)?;
+        let g = self.inner.lock().expect("MockDao poisoned");
+        let chain = g.chain(changelist_id);
+        Ok(g.revisions
+            .iter()
+            .filter(|r| r.path == key && chain.contains(&r.changelist_id))
+            .max_by_key(|r| (r.changelist_id, r.generation, r.revision))
+            .cloned())
+    }
+
+    async fn list_file_revisions_by_changelist(
+        &self,
+        changelist_id: i64,
+    ) -> DaoResult<Vec<entities::file_revisions::Model>> {
+        let g = self.inner.lock().expect("MockDao poisoned");
+        let mut revisions: Vec<entities::file_revisions::Model> = g
+            .revisions
+            .iter()
+            .filter(|r| r.changelist_id == changelist_id)
+            .cloned()
+            .collect();
+        revisions.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(revisions)
+    }
+
+    async fn list_file_tree_revisions(
+        &self,
+        depot: &DepotPath,
+        changelist_id: i64,
+    ) -> DaoResult<Vec<entities::file_revisions::Model>> {
+        let g = self.inner.lock().expect("MockDao poisoned");
+        let head = if changelist_id > 0 {
+            changelist_id
+        } else {
+            g.branch_head("").unwrap_or(0)
+        };
+        let chain = g.chain(head);
+        let mut latest: HashMap<String, entities::file_revisions::Model> = HashMap::new();
+        for r in &g.revisions {
+            if !chain.contains(&r.changelist_id) {
+                continue;
+            }
+            let path = r.to_depot_path_string()?;
+            if !mock_depot_matches(depot, &path) {
+                continue;
+            }
+            let newer = latest.get(&path).is_none_or(|m| {
+                (r.generation, r.revision, r.changelist_id)
+                    > (m.generation, m.revision, m.changelist_id)
+            });
+            if newer {
+                latest.insert(path, r.clone());
+            }
+        }
+        let mut revisions: Vec<entities::file_revisions::Model> = latest.into_values().collect();
+        revisions.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(revisions)
+    }
+
+    async fn cherry_pick(&self, input: &CherryPickInput) -> DaoResult<i64> {
+        let mut g = self.inner.lock().expect("MockDao poisoned");
+        g.commit(
+            &input.target_branch_id,
+            &input.author,
+            &input.description,
+            input.committed_at,
+            cherry_picked_from_metadata(input),
+            input.revisions.clone(),
+        )
+    }
+
+    async fn merge_branches(&self, input: &MergeInput) -> DaoResult<i64> {
+        let mut g = self.inner.lock().expect("MockDao poisoned");
+        g.commit(
+            &input.target_branch_id,
+            &input.author,
+            &input.description,
+            input.committed_at,
+            merged_from_metadata(input),
+            input.revisions.clone(),
+        )
+    }
+
+    async fn insert_audit_event(&self, event: &AuditEvent) -> DaoResult<()> {
+        let mut g = self.inner.lock().expect("MockDao poisoned");
+        g.audit_events.push(event.clone());
+        Ok(())
+    }
+
+    async fn list_audit_events(&self, query: &AuditQuery) -> DaoResult<Vec<AuditEvent>> {
+        let g = self.inner.lock().expect("MockDao poisoned");
+        let mut events: Vec<AuditEvent> = g
+            .audit_events
+            .iter()
+            .filter(|e| query.matches(e))
+            .cloned()
+            .collect();
+        events.sort_by_key(|e| std::cmp::Reverse(e.timestamp_millis));
+        events.truncate(query.limit as usize);
+        Ok(events)
+    }
+
+    async fn find_workspace_by_name(
+        &self,
+        name: &str,
+    ) -> DaoResult<Option<entities::workspaces::Model>> {
+        let g = self.inner.lock().expect("MockDao poisoned");
+        Ok(g.workspaces.get(name).cloned())
+    }
+
+    async fn insert_workspace(&self, workspace: &entities::workspaces::Model) -> DaoResult<()> {
+        let mut g = self.inner.lock().expect("MockDao poisoned");
+        if g.workspaces.contains_key(&workspace.name) {
+            return Err(DaoError::Db(DbErr::RecordNotInserted));
+        }
+        g.workspaces
+            .insert(workspace.name.clone(), workspace.clone());
+        Ok(())
+    }
+
+    async fn delete_workspace(&self, name: &str) -> DaoResult<bool> {
+        let mut g = self.inner.lock().expect("MockDao poisoned");
+        Ok(g.workspaces.remove(name).is_some())
+    }
+
+    async fn insert_webhook(&self, webhook: &WebhookConfig) -> DaoResult<i64> {
+        let mut g = self.inner.lock().expect("MockDao poisoned");
+        let id = g.next_webhook_id;
+        g.next_webhook_id += 1;
+        g.webhooks.push(WebhookConfig {
+            id,
+            ..webhook.clone()
+        });
+        Ok(id)
+    }
+
+    async fn delete_webhook(&self, id: i64) -> DaoResult<bool> {
+        let mut g = self.inner.lock().expect("MockDao poisoned");
+        let before = g.webhooks.len();
+        g.webhooks.retain(|w| w.id != id);
+        Ok(g.webhooks.len() < before)
+    }
+
+    async fn list_webhooks(&self) -> DaoResult<Vec<WebhookConfig>> {
+        let g = self.inner.lock().expect("MockDao poisoned");
+        Ok(g.webhooks.clone())
+    }
+
+    async fn insert_snapshot(&self, snapshot: &SnapshotDoc) -> DaoResult<i64> {
+        let mut g = self.inner.lock().expect("MockDao poisoned");
+        if g.snapshots.iter().any(|s| s.name == snapshot.name) {
+            return Err(DaoError::Db(DbErr::RecordNotInserted));
+        }
+        let id = g.next_snapshot_id;
+        g.next_snapshot_id += 1;
+        g.snapshots.push(SnapshotDoc {
+            id,
+            ..snapshot.clone()
+        });
+        Ok(id)
+    }
+
+    async fn find_snapshot_by_id(&self, id: i64) -> DaoResult<Option<SnapshotDoc>> {
+        let g = self.inner.lock().expect("MockDao poisoned");
+        Ok(g.snapshots.iter().find(|s| s.id == id).cloned())
+    }
+
+    async fn list_snapshots(&self, branch_id: &str) -> DaoResult<Vec<SnapshotDoc>> {
+        let g = self.inner.lock().expect("MockDao poisoned");
+        Ok(g.snapshots
+            .iter()
+            .filter(|s| branch_id.is_empty() || s.branch_id == branch_id)
+            .cloned()
+            .collect())
+    }
+
+    async fn delete_snapshot(&self, id: i64) -> DaoResult<bool> {
+        let mut g = self.inner.lock().expect("MockDao poisoned");
+        let before = g.snapshots.len();
+        g.snapshots.retain(|s| s.id != id);
+        Ok(g.snapshots.len() < before)
+    }
+
+    async fn find_submission(
+        &self,
+        request_id: &str,
+        not_before: i64,
+    ) -> DaoResult<Option<SubmissionRecord>> {
+        let g = self.inner.lock().expect("MockDao poisoned");
+        Ok(g.submissions
+            .get(request_id)
+            .filter(|r| r.committed_at >= not_before)
+            .cloned())
+    }
+
+    async fn insert_submission(
+        &self,
+        record: &SubmissionRecord,
+        expire_before: i64,
+    ) -> DaoResult<()> {
+        let mut g = self.inner.lock().expect("MockDao poisoned");
+        g.submissions.retain(|_, r| r.committed_at >= expire_before);
+        if g.submissions.contains_key(&record.request_id) {
+            return Err(DaoError::Db(DbErr::RecordNotInserted));
+        }
+        g.submissions
+            .insert(record.request_id.clone(), record.clone());
+        Ok(())
+    }
+
+    async fn insert_submit_ticket(&self, record: &SubmitTicketRecord) -> DaoResult<()> {
+        let mut g = self.inner.lock().expect("MockDao poisoned");
+        if g.submit_tickets.contains_key(&record.ticket) {
+            return Err(DaoError::Db(DbErr::RecordNotInserted));
+        }
+        g.submit_tickets
+            .insert(record.ticket.clone(), record.clone());
+        Ok(())
+    }
+
+    async fn delete_submit_ticket(&self, ticket: &str) -> DaoResult<bool> {
+        let mut g = self.inner.lock().expect("MockDao poisoned");
+        Ok(g.submit_tickets.remove(ticket).is_some())
+    }
+
+    async fn delete_expired_submit_tickets(&self, now: i64) -> DaoResult<u64> {
+        let mut g = self.inner.lock().expect("MockDao poisoned");
+        let before = g.submit_tickets.len();
+        g.submit_tickets.retain(|_, t| t.expires_at > now);
+        Ok((before - g.submit_tickets.len()) as u64)
+    }
+
+    async fn list_submit_tickets(&self, now: i64) -> DaoResult<Vec<SubmitTicketRecord>> {
+        let g = self.inner.lock().expect("MockDao poisoned");
+        let mut tickets: Vec<SubmitTicketRecord> = g
+            .submit_tickets
+            .values()
+            .filter(|t| t.expires_at > now && t.status.is_active())
+            .cloned()
+            .collect();
+        tickets.sort_by(|a, b| a.ticket.cmp(&b.ticket));
+        Ok(tickets)
+    }
+
+    async fn find_submit_ticket(&self, ticket: &str) -> DaoResult<Option<SubmitTicketRecord>> {
+        let g = self.inner.lock().expect("MockDao poisoned");
+        Ok(g.submit_tickets.get(ticket).cloned())
+    }
+
+    async fn update_submit_ticket_status(
+        &self,
+        ticket: &str,
+        status: SubmitContextStatus,
+        branch_id: Option<&str>,
+    ) -> DaoResult<bool> {
+        let mut g = self.inner.lock().expect("MockDao poisoned");
+        let Some(record) = g.submit_tickets.get_mut(ticket) else {
+            return Ok(false);
+        };
+        record.status = status;
+        if let Some(branch_id) = branch_id {
+            record.branch_id = branch_id.to_string();
+        }
+        Ok(true)
+    }
+
+    async fn increment_login_failures(
+        &self,
+        username: &str,
+        now: i64,
+        window_secs: i64,
+    ) -> DaoResult<entities::login_failures::Model> {
+        let mut g = self.inner.lock().expect("MockDao poisoned");
+        let record = g
+            .login_failures
+            .entry(username.to_string())
+            .or_insert_with(|| entities::login_failures::Model {
+                username: username.to_string(),
+                count: 0,
+                window_started_at: now,
+                locked_until: None,
+            });
+        if record.window_started_at > now - window_secs {
+            record.count += 1;
+        } else {
+            record.count = 1;
+            record.window_started_at = now;
+        }
+        Ok(record.clone())
+    }
+
+    async fn find_login_failures(
+        &self,
+        username: &str,
+    ) -> DaoResult<Option<entities::login_failures::Model>> {
+        let g = self.inner.lock().expect("MockDao poisoned");
+        Ok(g.login_failures.get(username).cloned())
+    }
+
+    async fn lock_account(&self, username: &str, locked_until: i64) -> DaoResult<()> {
+        let mut g = self.inner.lock().expect("MockDao poisoned");
+        if let Some(record) = g.login_failures.get_mut(username) {
+            record.count = 0;
+            record.locked_until = Some(locked_until);
+        }
+        Ok(())
+    }
+
+    async fn reset_login_failures(&self, username: &str) -> DaoResult<()> {
+        let mut g = self.inner.lock().expect("MockDao poisoned");
+        g.login_failures.remove(username);
+        Ok(())
+    }
+
+    async fn repository_stats(&self) -> DaoResult<RepositoryStats> {
+        let g = self.inner.lock().expect("MockDao poisoned");
+        Ok(RepositoryStats {
+            total_files: g.latest_revisions.len() as i64,
+            total_changelists: g.changelists.len() as i64,
+            total_branches: g.branches.len() as i64,
+            total_revision_bytes: g.total_revision_bytes,
+            oldest_changelist_at: g.changelists.values().map(|cl| cl.committed_at).min(),
+            newest_changelist_at: g.changelists.values().map(|cl| cl.committed_at).max(),
+        })
+    }
+}
+
+static DAO_INSTANCE: OnceLock<RwLock<Arc<dyn Dao>>> = OnceLock::new();
+static READ_DAO_INSTANCE: OnceLock<RwLock<Arc<dyn Dao>>> = OnceLock::new();
+
+fn dao_cell() -> &'static RwLock<Arc<dyn Dao>> {
+    DAO_INSTANCE.get_or_init(|| RwLock::new(Arc::new(SeaOrmDao::default())))
+}
+
+fn read_dao_cell() -> &'static RwLock<Arc<dyn Dao>> {
+    READ_DAO_INSTANCE.get_or_init(|| {
+        RwLock::new(Arc::new(SeaOrmDao {
+            read_from_replica: true,
+        }))
+    })
+}
+
+/// 获取当前 DAO（默认是生产实现 `SeaOrmDao`）。
+pub fn dao() -> Arc<dyn Dao> {
+    dao_cell().read().expect("dao RwLock poisoned").clone()
+}
+
+/// 获取只读 RPC 使用的 DAO：配置了 `secondary_preferred` 时查询走只读副本。
+///
+/// 副本存在复制延迟，提交、合并等需要读取最新状态再写入的流程必须使用 [`dao`]。
+pub fn read_dao() -> Arc<dyn Dao> {
+    read_dao_cell().read().expect("dao RwLock poisoned").clone()
+}
+
+/// 仅用于测试/本地：覆盖全局 DAO 实现（例如注入 `MockDao`），[`dao`] 与 [`read_dao`] 都会返回它。
+///
+/// 注意：这是全局状态，建议测试串行使用（或自行加锁）。
+pub fn set_dao_for_tests(new_dao: Arc<dyn Dao>) {
+    *dao_cell().write().expect("dao RwLock poisoned") = new_dao.clone();
+    *read_dao_cell().write().expect("dao RwLock poisoned") = new_dao;
+}
+
+async fn find_user_by_username_on<C: ConnectionTrait>(
+    conn: &C,
+    username: &str,
+) -> DaoResult<Option<entities::users::Model>> {
+    let model = entities::users::Entity::find_by_id(username.to_string())
+        .one(conn)
+        .await?;
+    Ok(model)
+}
+
+#[derive(Debug, Clone)]
+pub struct NewFileRevisionInput {
+    pub depot_path: String,
+    pub generation: i64,
+    pub revision: i64,
+    pub binary_id: serde_json::Value,
+    pub size: i64,
+    pub is_delete: bool,
+    pub created_at: i64,
+    pub metadata: serde_json::Value,
+    /// 文件第一次提交时写入 `files.metadata` 的内容，见 [`new_file_metadata`]
+    pub file_metadata: serde_json::Value,
+}
+
+/// 已完成的提交，用于 Submit 按 request_id 去重
+#[derive(Debug, Clone, PartialEq)]
+pub struct SubmissionRecord {
+    pub request_id: String,
+    pub changelist_id: i64,
+    /// 提交时间（秒），超过保留期的记录视为过期
+    pub committed_at: i64,
+    /// 提交后各文件的最新 revision，用于重放原始响应
+    pub latest_revisions: serde_json::Value,
+}
+
+/// 提交及其锁定的文件，持久化后 hive 重启时可以恢复进行中的提交
+#[derive(Debug, Clone, PartialEq)]
+pub struct SubmitTicketRecord {
+    pub ticket: String,
+    pub submitting_by: String,
+    /// 提交到的分支，开始提交时才确定，之前为空
+    pub branch_id: String,
+    /// launch_submit 的时间（秒）
+    pub started_at: i64,
+    /// 过期时间（秒），过期的 ticket 不再恢复，并在清理时删除
+    pub expires_at: i64,
+    pub status: SubmitContextStatus,
+    pub files: Vec<FileLockRecord>,
+}
+
+/// 提交的进展，结束（提交、失败或取消）后 ticket 持有的锁都已释放
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SubmitContextStatus {
+    /// 已锁定文件，等待上传与提交；提交失败但 ticket 仍然有效时也回到该状态
+    #[default]
+    Launched,
+    Submitting,
+    Committed,
+    /// 提交失败且 ticket 已失效
+    Failed,
+    Cancelled,
+}
+
+impl SubmitContextStatus {
+    /// ticket 是否仍然持有锁，只有这些 ticket 会在重启时恢复
+    pub fn is_active(self) -> bool {
+        matches!(self, Self::Launched | Self::Submitting)
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Launched => "launched",
+            Self::Submitting => "submitting",
+            Self::Committed => "committed",
+            Self::Failed => "failed",
+            Self::Cancelled => "cancelled",
+        }
+    }
+
+    fn parse(status: &str) -> Self {
+        match status {
+            "submitting" => Self::Submitting,
+            "committed" => Self::Committed,
+            "failed" => Self::Failed,
+            "cancelled" => Self::Cancelled,
+            _ => Self::Launched,
+        }
+    }
+}
+
+/// ticket 锁定的单个文件
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileLockRecord {
+    pub depot_path: String,
+    pub mode: LockMode,
+    pub locked_generation: Option<i64>,
+    pub locked_revision: Option<i64>,
+}
+
+/// 仓库的统计信息
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RepositoryStats {
+    pub total_files: i64,
+    pub total_changelists: i64,
+    pub total_branches: i64,
+    /// 所有 revision 的文件大小之和（去重前）
+    pub total_revision_bytes: i64,
+    /// 最早 / 最晚的 changelist 提交时间（秒），没有 changelist 时为 None
+    pub oldest_changelist_at: Option<i64>,
+    pub newest_changelist_at: Option<i64>,
+}
+
+/// 根据用户名查找用户。
+pub async fn find_user_by_username(username: &str) -> DaoResult<Option<entities::users::Model>> {
+    dao().find_user_by_username(username).await
+}
+
+/// 创建新用户文档。
+///
+/// - `username` 作为主键 `id` 字段；
+/// - `password_hash` 存储为 `password` 字段，建议为 Argon2 哈希；
+/// - `display_name` 为空时展示用户名。
+pub async fn insert_user(
+    username: &str,
+    password_hash: &str,
+    email: Option<&str>,
+    display_name: &str,
+) -> DaoResult<()> {
+    dao()
+        .insert_user(username, password_hash, email, display_name)
+        .await
+}
+
+/// 注册的账号，`users.source` 的默认值
+pub const USER_SOURCE_PASSWORD: &str = "password";
+/// OAuth2 登录时创建的账号，没有密码，只能通过 OAuth2 登录
+pub const USER_SOURCE_OAUTH2: &str = "oauth2";
+
+/// 创建 OAuth2 登录的用户，`password` 留空，用户名已存在时返回数据库错误。
+pub async fn insert_oauth2_user(
+    username: &str,
+    email: Option<&str>,
+    display_name: &str,
+) -> DaoResult<()> {
+    dao()
+        .insert_oauth2_user(username, email, display_name)
+        .await
+}
+
+async fn insert_user_on<C: ConnectionTrait>(
+    conn: &C,
+    username: &str,
+    password_hash: &str,
+    email: Option<&str>,
+    display_name: &str,
+    source: &str,
+) -> DaoResult<()> {
+    let am = entities::users::ActiveModel {
+        id: Set(username.to_string()),
+        password: Set(password_hash.to_string()),
+        created_at: Set(chrono::Utc::now().timestamp_millis()),
+        email: Set(email.map(str::to_string)),
+        display_name: Set(display_name.to_string()),
+        source: Set(source.to_string()),
+    };
+    am.insert(conn).await?;
+    Ok(())
+}
+
+/// 按用户名排序分页列出用户，同时返回用户总数。
+pub async fn list_users(offset: u64, limit: u64) -> DaoResult<(Vec<entities::users::Model>, i64)> {
+    dao().list_users(offset, limit).await
+}
+
+async fn list_users_on<C: ConnectionTrait>(
+    conn: &C,
+    offset: u64,
+    limit: u64,
+) -> DaoResult<(Vec<entities::users::Model>, i64)> {
+    use entities::users::{Column, Entity};
+
+    let total = Entity::find().count(conn).await?;
+    let users = Entity::find()
+        .order_by_asc(Column::Id)
+        .offset(offset)
+        .limit(limit)
+        .all(conn)
+        .await?;
+    Ok((users, total as i64))
+}
+
+/// 更新用户的密码哈希，用户不存在时返回 false。
+pub async fn update_user_password(username: &str, password_hash: &str) -> DaoResult<bool> {
+    dao().update_user_password(username, password_hash).await
+}
+
+async fn update_user_password_on<C: ConnectionTrait>(
+    conn: &C,
     username: &str,
     password_hash: &str,
+) -> DaoResult<bool> {
+    use entities::users::{Column, Entity};
+
+    let res = Entity::update_many()
+        .col_expr(
+            Column::Password,
+            sea_orm::sea_query::Expr::value(password_hash),
+        )
+        .filter(Column::Id.eq(username))
+        .exec(conn)
+        .await?;
+    Ok(res.rows_affected > 0)
+}
+
+/// 删除用户，用户不存在时返回 false。
+pub async fn delete_user(username: &str) -> DaoResult<bool> {
+    dao().delete_user(username).await
+}
+
+async fn delete_user_on<C: ConnectionTrait>(conn: &C, username: &str) -> DaoResult<bool> {
+    let res = entities::users::Entity::delete_by_id(username.to_string())
+        .exec(conn)
+        .await?;
+    Ok(res.rows_affected > 0)
+}
+
+/// 按 depot path 查询该文件的最新 revision（如果存在）。
+///
+/// 返回值为 `file_revisions` 的一条记录：按 `(generation desc, revision desc)` 取最大。
+pub async fn find_latest_file_revision_by_depot_path(
+    depot_path: &str,
+) -> DaoResult<Option<entities::file_revisions::Model>> {
+    dao()
+        .find_latest_file_revision_by_depot_path(depot_path)
+        .await
+}
+
+async fn find_latest_file_revision_by_depot_path_on<C: ConnectionTrait>(
+    conn: &C,
+    depot_path: &str,
+) -> DaoResult<Option<entities::file_revisions::Model>> {
+    let key = ltree_key::depot_path_str_to_ltree_key(depot_path)?;
+
+    // `file_revisions.path` 是 Postgres `ltree`，而 SeaORM 这里字段类型用 `String`。
+    // 在某些 Postgres 版本/配置下，`ltree = text` 不会隐式 cast，导致查询报错。
+    // 这里用 raw SQL 显式 `$1::ltree`，避免类型不匹配。
+    let stmt = Statement::from_sql_and_values(
+        DatabaseBackend::Postgres,
+        r#"
+        SELECT
+            path::text AS path,
+            generation,
+            revision,
+            changelist_id,
+            binary_id,
+            size,
+            is_delete,
+            created_at,
+            metadata
+        FROM file_revisions
+        WHERE path = $1::ltree
+        ORDER BY generation DESC, revision DESC
+        LIMIT 1
+        "#,
+        [key.into()].to_vec(),
+    );
+
+    let model = entities::file_revisions::Entity::find()
+        .from_raw_sql(stmt)
+        .one(conn)
+        .await?;
+
+    Ok(model)
+}
+
+/// 按 depot path 查询 `files` 行。
+pub async fn find_file_by_depot_path(
+    depot_path: &str,
+) -> DaoResult<Option<entities::files::Model>> {
+    dao().find_file_by_depot_path(depot_path).await
+}
+
+async fn find_file_by_depot_path_on<C: ConnectionTrait>(
+    conn: &C,
+    depot_path: &str,
+) -> DaoResult<Option<entities::files::Model>> {
+    let key = ltree_key::depot_path_str_to_ltree_key(depot_path)?;
+    let stmt = Statement::from_sql_and_values(
+        DatabaseBackend::Postgres,
+        r#"
+        SELECT path::text AS path, created_at, metadata
+        FROM files
+        WHERE path = $1::ltree
+        "#,
+        [key.into()].to_vec(),
+    );
+
+    let model = entities::files::Entity::find()
+        .from_raw_sql(stmt)
+        .one(conn)
+        .await?;
+
+    Ok(model)
+}
+
+/// 新文件的 `files.metadata`：记录第一次提交该文件的用户，以及提交时所在的分支
+pub fn new_file_metadata(first_introduced_by: &str, branch_id: &str) -> serde_json::Value {
+    let mut metadata = serde_json::to_value(FileMetadata {
+        first_introduced_by: first_introduced_by.to_string(),
+    })
+    .unwrap_or_else(|_| serde_json::json!({}));
+    set_file_seen_on_branches(&mut metadata, vec![branch_id.to_string()]);
+    metadata
+}
+
+/// 读取 `files.metadata.seen_on_branches` 中的分支，"" 代表默认分支，缺失时为空。
+pub fn file_seen_on_branches(metadata: &serde_json::Value) -> Vec<String> {
+    metadata
+        .get("seen_on_branches")
+        .and_then(|branches| branches.as_array())
+        .map(|branches| {
+            branches
+                .iter()
+                .filter_map(|b| b.as_str().map(|s| s.to_string()))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn set_file_seen_on_branches(metadata: &mut serde_json::Value, branches: Vec<String>) {
+    if !metadata.is_object() {
+        *metadata = serde_json::json!({});
+    }
+    metadata["seen_on_branches"] = serde_json::json!(branches);
+}
+
+/// 将分支加入文件的 `seen_on_branches`，已存在时不重复添加。
+///
+/// 返回是否实际添加了分支；文件不存在或分支已存在时返回 false。
+pub async fn add_branch_to_file(depot_path: &str, branch_id: &str) -> DaoResult<bool> {
+    dao().add_branch_to_file(depot_path, branch_id).await
+}
+
+async fn add_branch_to_file_on<C: ConnectionTrait>(
+    conn: &C,
+    depot_path: &str,
+    branch_id: &str,
+) -> DaoResult<bool> {
+    let key = ltree_key::depot_path_str_to_ltree_key(depot_path)?;
+
+    // 判断与追加在同一条 UPDATE 中完成，并发提交到同一分支时不会重复添加
+    let result = conn
+        .execute(Statement::from_sql_and_values(
+            DatabaseBackend::Postgres,
+            r#"
+            UPDATE files
+            SET metadata = jsonb_set(
+                metadata,
+                '{seen_on_branches}',
+                COALESCE(metadata->'seen_on_branches', '[]'::jsonb) || jsonb_build_array($2::text)
+            )
+            WHERE path = $1::ltree
+              AND NOT COALESCE(metadata->'seen_on_branches', '[]'::jsonb) @> jsonb_build_array($2::text)
+            "#,
+            vec![key.into(), branch_id.into()],
+        ))
+        .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// 原子地分配下一个 changelist id。
+///
+/// id 来自 changelists 表的自增序列，多个 hive 实例共享同一个数据库时也不会重复；
+/// 分配后未使用的 id（如事务回滚）会留下空洞，不会被复用。
+pub async fn next_changelist_id() -> DaoResult<i64> {
+    dao().next_changelist_id().await
+}
+
+async fn next_changelist_id_on<C: ConnectionTrait>(conn: &C) -> DaoResult<i64> {
+    let row = conn
+        .query_one(Statement::from_string(
+            DatabaseBackend::Postgres,
+            "SELECT nextval(pg_get_serial_sequence('changelists', 'id')) AS id",
+        ))
+        .await?
+        .ok_or_else(|| {
+            DaoError::Db(DbErr::RecordNotFound(
+                "failed to allocate changelist id".to_string(),
+            ))
+        })?;
+    Ok(row.try_get("", "id")?)
+}
+
+/// 创建一个 changelist，并返回其自增 id。
+pub async fn insert_changelist(
+    author: &str,
+    description: &str,
+    committed_at: i64,
+    metadata: serde_json::Value,
+) -> DaoResult<i64> {
+    dao()
+        .insert_changelist(author, description, committed_at, metadata)
+        .await
+}
+
+/// 在 `branch_id` 上创建 changelist，新的 changelist 接在分支当前的 HEAD 之后。
+///
+/// 具名分支的记录会被锁住直到事务结束，并发提交到同一分支时不会基于同一个 HEAD。
+async fn insert_changelist_on<C: ConnectionTrait>(
+    conn: &C,
+    branch_id: &str,
+    author: &str,
+    description: &str,
+    committed_at: i64,
+    metadata: serde_json::Value,
+) -> DaoResult<i64> {
+    let parent_changelist_id = if branch_id.is_empty() {
+        find_branch_head_on(conn, branch_id).await?.unwrap_or(0)
+    } else {
+        let row = conn
+            .query_one(Statement::from_sql_and_values(
+                DatabaseBackend::Postgres,
+                r#"
+                SELECT head_changelist_id FROM branches WHERE id = $1 FOR UPDATE
+                "#,
+                vec![branch_id.into()],
+            ))
+            .await?;
+        match row {
+            Some(row) => row.try_get("", "head_changelist_id")?,
+            None => 0,
+        }
+    };
+    insert_changelist_with_parent_on(
+        conn,
+        branch_id,
+        parent_changelist_id,
+        author,
+        description,
+        committed_at,
+        metadata,
+    )
+    .await
+}
+
+async fn insert_changelist_with_parent_on<C: ConnectionTrait>(
+    conn: &C,
+    branch_id: &str,
+    parent_changelist_id: i64,
+    author: &str,
+    description: &str,
+    committed_at: i64,
+    metadata: serde_json::Value,
+) -> DaoResult<i64> {
+    let changelist_id = next_changelist_id_on(conn).await?;
+    let row = conn
+        .query_one(Statement::from_sql_and_values(
+            DatabaseBackend::Postgres,
+            r#"
+            INSERT INTO changelists
+                (id, author, description, committed_at, metadata, branch_id, parent_changelist_id)
+            VALUES ($1, $2, $3, $4, $5::jsonb, $6, $7)
+            RETURNING id
+            "#,
+            vec![
+                changelist_id.into(),
+                author.to_string().into(),
+                description.to_string().into(),
+                committed_at.into(),
+                metadata.to_string().into(),
+                branch_id.to_string().into(),
+                parent_changelist_id.into(),
+            ],
+        ))
+        .await?;
+
+    let row = row.ok_or_else(|| {
+        DaoError::Db(DbErr::RecordNotFound(
+            "failed to insert changelist".to_string(),
+        ))
+    })?;
+    Ok(row.try_get("", "id")?)
+}
+
+/// 单条 INSERT 语句最多写入的行数，避免超出 Postgres 单条语句 65535 个参数的限制
+const BATCH_INSERT_ROWS: usize = 1000;
+
+/// 批量确保 files 行存在，已存在的文件不受影响
+async fn ensure_files_exist_on<C: ConnectionTrait>(
+    conn: &C,
+    inputs: &[NewFileRevisionInput],
 ) -> DaoResult<()> {
-    let am = entities::users::ActiveModel {
-        id: Set(username.to_string()),
-        password: Set(password_hash.to_string()),
+    for chunk in inputs.chunks(BATCH_INSERT_ROWS) {
+        let mut rows = Vec::with_capacity(chunk.len());
+        let mut values: Vec<sea_orm::Value> = Vec::with_capacity(chunk.len() * 3);
+        for (i, r) in chunk.iter().enumerate() {
+            let p = i * 3;
+            rows.push(format!(
+                "(${}::ltree, ${}, ${}::jsonb)",
+                p + 1,
+                p + 2,
+                p + 3
+            ));
+            values.push(ltree_key::depot_path_str_to_ltree_key(&r.depot_path)?.into());
+            values.push(r.created_at.into());
+            values.push(r.file_metadata.to_string().into());
+        }
+        conn.execute(Statement::from_sql_and_values(
+            DatabaseBackend::Postgres,
+            format!(
+                r#"
+                INSERT INTO files (path, created_at, metadata)
+                VALUES {}
+                ON CONFLICT (path) DO NOTHING
+                "#,
+                rows.join(", ")
+            ),
+            values,
+        ))
+        .await?;
+    }
+    Ok(())
+}
+
+/// 批量写入 file_revisions，每 `BATCH_INSERT_ROWS` 行一条 INSERT 语句。
+///
+/// 与已有的行或同一批次中的其他行主键重复时返回 [`DaoError::DuplicateKey`]，
+/// 此时部分行可能已经写入，调用方需要回滚事务。
+async fn insert_file_revisions_on<C: ConnectionTrait>(
+    conn: &C,
+    inputs: &[NewFileRevisionInput],
+    changelist_id: i64,
+) -> DaoResult<()> {
+    for chunk in inputs.chunks(BATCH_INSERT_ROWS) {
+        let mut keys = Vec::with_capacity(chunk.len());
+        let mut rows = Vec::with_capacity(chunk.len());
+        let mut values: Vec<sea_orm::Value> = Vec::with_capacity(chunk.len() * 9);
+        for (i, r) in chunk.iter().enumerate() {
+            let key = ltree_key::depot_path_str_to_ltree_key(&r.depot_path)?;
+            let p = i * 9;
+            rows.push(format!(
+                "(${}::ltree, ${}, ${}, ${}, ${}::jsonb, ${}, ${}, ${}, ${}::jsonb)",
+                p + 1,
+                p + 2,
+                p + 3,
+                p + 4,
+                p + 5,
+                p + 6,
+                p + 7,
+                p + 8,
+                p + 9
+            ));
+            values.extend([
+                key.clone().into(),
+                r.generation.into(),
+                r.revision.into(),
+                changelist_id.into(),
+                r.binary_id.to_string().into(),
+                r.size.into(),
+                r.is_delete.into(),
+                r.created_at.into(),
+                r.metadata.to_string().into(),
+            ]);
+            keys.push(key);
+        }
+
+        // 主键重复的行不会写入也不会返回，据此找出重复的 revision
+        let mut inserted = conn
+            .query_all(Statement::from_sql_and_values(
+                DatabaseBackend::Postgres,
+                format!(
+                    r#"
+                    INSERT INTO file_revisions
+                        (path, generation, revision, changelist_id, binary_id, size, is_delete, created_at, metadata)
+                    VALUES {}
+                    ON CONFLICT DO NOTHING
+                    RETURNING path::text AS path, generation, revision
+                    "#,
+                    rows.join(", ")
+                ),
+                values,
+            ))
+            .await?
+            .into_iter()
+            .map(|row| {
+                Ok((
+                    row.try_get::<String>("", "path")?,
+                    row.try_get::<i64>("", "generation")?,
+                    row.try_get::<i64>("", "revision")?,
+                ))
+            })
+            .collect::<DaoResult<HashSet<_>>>()?;
+        for (key, r) in keys.into_iter().zip(chunk) {
+            if !inserted.remove(&(key, r.generation, r.revision)) {
+                return Err(DaoError::DuplicateKey {
+                    path: r.depot_path.clone(),
+                    generation: r.generation,
+                    revision: r.revision,
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+/// 原子提交：
+/// - 创建 changelist
+/// - 确保 files 行存在
+/// - 写入每个文件的 file_revisions
+pub async fn commit_submit(
+    author: &str,
+    description: &str,
+    committed_at: i64,
+    metadata: serde_json::Value,
+    revisions: Vec<NewFileRevisionInput>,
+) -> DaoResult<i64> {
+    dao()
+        .commit_submit(author, description, committed_at, metadata, revisions)
+        .await
+}
+
+/// 与 [`commit_submit`] 相同，并在同一个事务中将 `branch_id` 的 HEAD 更新为新的 changelist
+pub async fn commit_submit_to_branch(
+    branch_id: &str,
+    author: &str,
+    description: &str,
+    committed_at: i64,
+    metadata: serde_json::Value,
+    revisions: Vec<NewFileRevisionInput>,
+) -> DaoResult<i64> {
+    dao()
+        .commit_submit_to_branch(
+            branch_id,
+            author,
+            description,
+            committed_at,
+            metadata,
+            revisions,
+        )
+        .await
+}
+
+async fn commit_submit_on(
+    conn: &sea_orm::DatabaseConnection,
+    branch_id: Option<&str>,
+    author: &str,
+    description: &str,
+    committed_at: i64,
+    metadata: serde_json::Value,
+    revisions: Vec<NewFileRevisionInput>,
+) -> DaoResult<i64> {
+    let txn = conn.begin().await?;
+
+    // 复用 DAO 的插入逻辑（只是在事务里执行）
+    let changelist_id = insert_changelist_on(
+        &txn,
+        branch_id.unwrap_or(""),
+        author,
+        description,
+        committed_at,
+        metadata,
+    )
+    .await?;
+
+    ensure_files_exist_on(&txn, &revisions).await?;
+    // 并发提交基于同一 HEAD 计算出相同的版本号时，由主键冲突保证只有一个能落库
+    match insert_file_revisions_on(&txn, &revisions, changelist_id).await {
+        Err(DaoError::DuplicateKey { path, .. }) => return Err(DaoError::RevisionConflict(path)),
+        other => other?,
+    }
+    if let Some(branch_id) = branch_id {
+        update_branch_head_on(&txn, branch_id, changelist_id).await?;
+    }
+
+    txn.commit().await?;
+    Ok(changelist_id)
+}
+
+/// 按 id 查询分支。
+pub async fn find_branch_by_id(branch_id: &str) -> DaoResult<Option<BranchDoc>> {
+    dao().find_branch_by_id(branch_id).await
+}
+
+/// 默认分支 HEAD 的 SQL 表达式：默认分支没有 branches 记录，其 HEAD 是提交到默认分支的最新 changelist
+pub(crate) const DEFAULT_BRANCH_HEAD_SQL: &str =
+    "(SELECT COALESCE(MAX(id), 0) FROM changelists WHERE branch_id = '')";
+
+/// 以 SQL 表达式 `head` 为起点，沿 `parent_changelist_id` 回溯得到的 changelist 链（含 `head` 本身），
+/// 作为 CTE `chain(id, parent_changelist_id)` 放在查询的最前面。
+pub(crate) fn changelist_chain_cte(head: &str) -> String {
+    format!(
+        r#"
+        WITH RECURSIVE chain AS (
+            SELECT id, parent_changelist_id FROM changelists WHERE id = {head}
+            UNION ALL
+            SELECT c.id, c.parent_changelist_id
+            FROM changelists c
+            JOIN chain ON c.id = chain.parent_changelist_id
+        )
+        "#
+    )
+}
+
+/// 查询分支的 HEAD，`branch_id` 为空时返回默认分支的 HEAD（没有 changelist 时为 0），分支不存在时返回 None。
+pub async fn find_branch_head(branch_id: &str) -> DaoResult<Option<i64>> {
+    dao().find_branch_head(branch_id).await
+}
+
+async fn find_branch_head_on<C: ConnectionTrait>(
+    conn: &C,
+    branch_id: &str,
+) -> DaoResult<Option<i64>> {
+    if !branch_id.is_empty() {
+        return Ok(find_branch_by_id_on(conn, branch_id)
+            .await?
+            .map(|branch| branch.head_changelist_id));
+    }
+    let row = conn
+        .query_one(Statement::from_string(
+            DatabaseBackend::Postgres,
+            format!("SELECT {DEFAULT_BRANCH_HEAD_SQL} AS head"),
+        ))
+        .await?;
+    match row {
+        Some(row) => Ok(Some(row.try_get("", "head")?)),
+        None => Ok(Some(0)),
+    }
+}
+
+/// 检查 `changelist_id` 是否位于从 `head` 回溯的 changelist 链上，即 `head` 处是否已经包含该 changelist。
+pub async fn is_changelist_on_chain(head: i64, changelist_id: i64) -> DaoResult<bool> {
+    dao().is_changelist_on_chain(head, changelist_id).await
+}
+
+async fn is_changelist_on_chain_on<C: ConnectionTrait>(
+    conn: &C,
+    head: i64,
+    changelist_id: i64,
+) -> DaoResult<bool> {
+    let row = conn
+        .query_one(Statement::from_sql_and_values(
+            DatabaseBackend::Postgres,
+            format!(
+                r#"
+                {}
+                SELECT EXISTS (SELECT 1 FROM chain WHERE id = $2) AS found
+                "#,
+                changelist_chain_cte("$1")
+            ),
+            vec![head.into(), changelist_id.into()],
+        ))
+        .await?;
+    match row {
+        Some(row) => Ok(row.try_get("", "found")?),
+        None => Ok(false),
+    }
+}
+
+/// 两个 changelist 最近的公共祖先，没有公共祖先时返回 0。
+///
+/// 祖先沿 parent 与 `metadata.merged_from` 记录的合并来源回溯，因此合并过的分支以上次合并的来源为基准；
+/// changelist id 全局递增，公共祖先中 id 最大的一个不会是其他公共祖先的祖先。
+pub async fn find_merge_base(a: i64, b: i64) -> DaoResult<i64> {
+    dao().find_merge_base(a, b).await
+}
+
+async fn find_merge_base_on<C: ConnectionTrait>(conn: &C, a: i64, b: i64) -> DaoResult<i64> {
+    let row = conn
+        .query_one(Statement::from_sql_and_values(
+            DatabaseBackend::Postgres,
+            r#"
+            WITH RECURSIVE ancestors(id, side) AS (
+                SELECT id, 1 FROM changelists WHERE id = $1
+                UNION
+                SELECT id, 2 FROM changelists WHERE id = $2
+                UNION
+                SELECT p.id, ancestors.side
+                FROM ancestors
+                JOIN changelists c ON c.id = ancestors.id
+                JOIN changelists p
+                  ON p.id = c.parent_changelist_id
+                  OR p.id = (c.metadata -> 'merged_from' ->> 'changelist_id')::bigint
+            )
+            SELECT COALESCE(MAX(id), 0) AS base
+            FROM (
+                SELECT id FROM ancestors GROUP BY id HAVING COUNT(DISTINCT side) = 2
+            ) common
+            "#,
+            vec![a.into(), b.into()],
+        ))
+        .await?;
+    match row {
+        Some(row) => Ok(row.try_get("", "base")?),
+        None => Ok(0),
+    }
+}
+
+async fn find_branch_by_id_on<C: ConnectionTrait>(
+    conn: &C,
+    branch_id: &str,
+) -> DaoResult<Option<BranchDoc>> {
+    let model = entities::branches::Entity::find_by_id(branch_id.to_string())
+        .one(conn)
+        .await?;
+
+    let Some(model) = model else {
+        return Ok(None);
+    };
+    Ok(Some(BranchDoc {
+        id: model.id,
+        created_at: model.created_at,
+        created_by: model.created_by,
+        head_changelist_id: model.head_changelist_id,
+        is_protected: model.is_protected,
+        metadata: serde_json::from_value(model.metadata)?,
+    }))
+}
+
+/// 创建新分支，分支 id 已存在时返回数据库错误。
+pub async fn insert_branch(branch: &BranchDoc) -> DaoResult<()> {
+    dao().insert_branch(branch).await
+}
+
+async fn insert_branch_on<C: ConnectionTrait>(conn: &C, branch: &BranchDoc) -> DaoResult<()> {
+    let am = entities::branches::ActiveModel {
+        id: Set(branch.id.clone()),
+        created_at: Set(branch.created_at),
+        created_by: Set(branch.created_by.clone()),
+        head_changelist_id: Set(branch.head_changelist_id),
+        is_protected: Set(branch.is_protected),
+        metadata: Set(serde_json::to_value(&branch.metadata)?),
+    };
+    am.insert(conn).await?;
+    Ok(())
+}
+
+/// 将分支的 HEAD 移动到指定的 changelist，返回分支是否存在。
+pub async fn update_branch_head(branch_id: &str, changelist_id: i64) -> DaoResult<bool> {
+    dao().update_branch_head(branch_id, changelist_id).await
+}
+
+async fn update_branch_head_on<C: ConnectionTrait>(
+    conn: &C,
+    branch_id: &str,
+    changelist_id: i64,
+) -> DaoResult<bool> {
+    let res = conn
+        .execute(Statement::from_sql_and_values(
+            DatabaseBackend::Postgres,
+            r#"
+            UPDATE branches SET head_changelist_id = $2 WHERE id = $1
+            "#,
+            vec![branch_id.into(), changelist_id.into()],
+        ))
+        .await?;
+    Ok(res.rows_affected() > 0)
+}
+
+/// 分支列表的排序方式，均为降序，相同时按分支 id 排序。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BranchSort {
+    CreatedAt,
+    HeadChangelistId,
+}
+
+/// 按指定方式排序分页列出分支，同时返回分支总数。
+pub async fn list_branches(
+    sort_by: BranchSort,
+    offset: u64,
+    limit: u64,
+) -> DaoResult<(Vec<BranchDoc>, i64)> {
+    dao().list_branches(sort_by, offset, limit).await
+}
+
+async fn list_branches_on<C: ConnectionTrait>(
+    conn: &C,
+    sort_by: BranchSort,
+    offset: u64,
+    limit: u64,
+) -> DaoResult<(Vec<BranchDoc>, i64)> {
+    use entities::branches::{Column, Entity};
+
+    let total = Entity::find().count(conn).await?;
+    let sort_column = match sort_by {
+        BranchSort::CreatedAt => Column::CreatedAt,
+        BranchSort::HeadChangelistId => Column::HeadChangelistId,
+    };
+    let models = Entity::find()
+        .order_by_desc(sort_column)
+        .order_by_asc(Column::Id)
+        .offset(offset)
+        .limit(limit)
+        .all(conn)
+        .await?;
+
+    let mut branches = Vec::with_capacity(models.len());
+    for model in models {
+        branches.push(BranchDoc {
+            id: model.id,
+            created_at: model.created_at,
+            created_by: model.created_by,
+            head_changelist_id: model.head_changelist_id,
+            is_protected: model.is_protected,
+            metadata: serde_json::from_value(model.metadata)?,
+        });
+    }
+    Ok((branches, total as i64))
+}
+
+/// 替换分支的附加元信息，返回分支是否存在。
+pub async fn update_branch_metadata(branch_id: &str, metadata: &BranchMetadata) -> DaoResult<bool> {
+    dao().update_branch_metadata(branch_id, metadata).await
+}
+
+async fn update_branch_metadata_on<C: ConnectionTrait>(
+    conn: &C,
+    branch_id: &str,
+    metadata: &BranchMetadata,
+) -> DaoResult<bool> {
+    let res = conn
+        .execute(Statement::from_sql_and_values(
+            DatabaseBackend::Postgres,
+            r#"
+            UPDATE branches SET metadata = $2 WHERE id = $1
+            "#,
+            vec![branch_id.into(), serde_json::to_value(metadata)?.into()],
+        ))
+        .await?;
+    Ok(res.rows_affected() > 0)
+}
+
+/// 设置分支是否受保护（禁止直接提交），返回分支是否存在。
+pub async fn set_branch_protection(branch_id: &str, is_protected: bool) -> DaoResult<bool> {
+    dao().set_branch_protection(branch_id, is_protected).await
+}
+
+async fn set_branch_protection_on<C: ConnectionTrait>(
+    conn: &C,
+    branch_id: &str,
+    is_protected: bool,
+) -> DaoResult<bool> {
+    let res = conn
+        .execute(Statement::from_sql_and_values(
+            DatabaseBackend::Postgres,
+            r#"
+            UPDATE branches SET is_protected = $2 WHERE id = $1
+            "#,
+            vec![branch_id.into(), is_protected.into()],
+        ))
+        .await?;
+    Ok(res.rows_affected() > 0)
+}
+
+/// 查询分支 HEAD（含）之前最后一个提交时间（秒）不晚于 `committed_at` 的 changelist。
+///
+/// changelist id 单调递增，从 HEAD 沿提交历史回溯找到的第一个满足条件的 changelist，
+/// 就是 id 不超过 HEAD 且满足时间条件的最大 id。`branch_id` 为空时在所有 changelist 中查找。
+pub async fn find_latest_changelist_before(
+    branch_id: &str,
+    committed_at: i64,
+) -> DaoResult<Option<i64>> {
+    dao()
+        .find_latest_changelist_before(branch_id, committed_at)
+        .await
+}
+
+async fn find_latest_changelist_before_on<C: ConnectionTrait>(
+    conn: &C,
+    branch_id: &str,
+    committed_at: i64,
+) -> DaoResult<Option<i64>> {
+    // 依赖 (committed_at, id) 复合索引：idx_changelists_committed_at_id
+    let row = conn
+        .query_one(Statement::from_sql_and_values(
+            DatabaseBackend::Postgres,
+            r#"
+            SELECT c.id
+            FROM changelists c
+            WHERE c.committed_at <= $2
+              AND ($1 = '' OR c.id <= (SELECT b.head_changelist_id FROM branches b WHERE b.id = $1))
+            ORDER BY c.id DESC
+            LIMIT 1
+            "#,
+            vec![branch_id.to_string().into(), committed_at.into()],
+        ))
+        .await?;
+
+    match row {
+        Some(row) => Ok(Some(row.try_get("", "id")?)),
+        None => Ok(None),
+    }
+}
+
+/// 读取 changelist `metadata.labels` 中的标签，缺失时为空。
+pub fn changelist_labels(metadata: &serde_json::Value) -> Vec<String> {
+    metadata
+        .get("labels")
+        .and_then(|labels| labels.as_array())
+        .map(|labels| {
+            labels
+                .iter()
+                .filter_map(|l| l.as_str().map(|s| s.to_string()))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn set_changelist_labels(metadata: &mut serde_json::Value, labels: Vec<String>) {
+    if !metadata.is_object() {
+        *metadata = serde_json::json!({});
+    }
+    metadata["labels"] = serde_json::json!(labels);
+}
+
+/// 为 changelist 添加标签，标签已存在时不重复添加；changelist 不存在时返回 false。
+pub async fn add_label_to_changelist(changelist_id: i64, label: &str) -> DaoResult<bool> {
+    dao().add_label_to_changelist(changelist_id, label).await
+}
+
+async fn add_label_to_changelist_on<C: ConnectionTrait>(
+    conn: &C,
+    changelist_id: i64,
+    label: &str,
+) -> DaoResult<bool> {
+    // 在 jsonb 数组末尾追加标签，已包含该标签的行不会被更新
+    let res = conn
+        .execute(Statement::from_sql_and_values(
+            DatabaseBackend::Postgres,
+            r#"
+            UPDATE changelists
+            SET metadata = jsonb_set(
+                metadata,
+                '{labels}',
+                COALESCE(metadata->'labels', '[]'::jsonb) || jsonb_build_array($2::text)
+            )
+            WHERE id = $1
+              AND NOT COALESCE(metadata->'labels', '[]'::jsonb) @> jsonb_build_array($2::text)
+            "#,
+            vec![changelist_id.into(), label.to_string().into()],
+        ))
+        .await?;
+    if res.rows_affected() > 0 {
+        return Ok(true);
+    }
+
+    let exists = entities::changelists::Entity::find_by_id(changelist_id)
+        .one(conn)
+        .await?
+        .is_some();
+    Ok(exists)
+}
+
+/// 移除 changelist 的标签，标签不存在时不做修改；changelist 不存在时返回 false。
+pub async fn remove_label_from_changelist(changelist_id: i64, label: &str) -> DaoResult<bool> {
+    dao()
+        .remove_label_from_changelist(changelist_id, label)
+        .await
+}
+
+async fn remove_label_from_changelist_on<C: ConnectionTrait>(
+    conn: &C,
+    changelist_id: i64,
+    label: &str,
+) -> DaoResult<bool> {
+    let res = conn
+        .execute(Statement::from_sql_and_values(
+            DatabaseBackend::Postgres,
+            r#"
+            UPDATE changelists
+            SET metadata = jsonb_set(
+                metadata,
+                '{labels}',
+                COALESCE(
+                    (
+                        SELECT jsonb_agg(l)
+                        FROM jsonb_array_elements(metadata->'labels') AS l
+                        WHERE l <> to_jsonb($2::text)
+                    ),
+                    '[]'::jsonb
+                )
+            )
+            WHERE id = $1
+            "#,
+            vec![changelist_id.into(), label.to_string().into()],
+        ))
+        .await?;
+    Ok(res.rows_affected() > 0)
+}
+
+/// 查询带有指定标签的 changelist，按 id 从新到旧排列。
+///
+/// `branch_id` 不为空时只返回该分支 HEAD 及之前的 changelist，分支不存在时返回空列表。
+pub async fn find_changelists_by_label(
+    branch_id: &str,
+    label: &str,
+    limit: u64,
+) -> DaoResult<Vec<entities::changelists::Model>> {
+    dao()
+        .find_changelists_by_label(branch_id, label, limit)
+        .await
+}
+
+async fn find_changelists_by_label_on<C: ConnectionTrait>(
+    conn: &C,
+    branch_id: &str,
+    label: &str,
+    limit: u64,
+) -> DaoResult<Vec<entities::changelists::Model>> {
+    // 依赖 GIN 索引：idx_changelists_metadata
+    let stmt = Statement::from_sql_and_values(
+        DatabaseBackend::Postgres,
+        r#"
+        SELECT c.id, c.author, c.description, c.committed_at, c.metadata,
+               c.branch_id, c.parent_changelist_id
+        FROM changelists c
+        WHERE c.metadata @> jsonb_build_object('labels', jsonb_build_array($2::text))
+          AND ($1 = '' OR c.id <= (SELECT b.head_changelist_id FROM branches b WHERE b.id = $1))
+        ORDER BY c.id DESC
+        LIMIT $3
+        "#,
+        vec![
+            branch_id.to_string().into(),
+            label.to_string().into(),
+            (limit as i64).into(),
+        ],
+    );
+    let models = entities::changelists::Entity::find()
+        .from_raw_sql(stmt)
+        .all(conn)
+        .await?;
+    Ok(models)
+}
+
+/// changelist 的概要及其包含的文件数
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChangelistSummaryRecord {
+    pub id: i64,
+    pub author: String,
+    pub description: String,
+    pub committed_at: i64,
+    pub files_count: i64,
+}
+
+/// 从 `to_cl` 沿 parent 回溯，从新到旧列出链上 id 不小于 `from_cl` 的 changelist，已被压缩的 changelist 不包含在内。
+pub async fn list_changelist_chain(
+    from_cl: i64,
+    to_cl: i64,
+    limit: u64,
+) -> DaoResult<Vec<ChangelistSummaryRecord>> {
+    dao().list_changelist_chain(from_cl, to_cl, limit).await
+}
+
+async fn list_changelist_chain_on<C: ConnectionTrait>(
+    conn: &C,
+    from_cl: i64,
+    to_cl: i64,
+    limit: u64,
+) -> DaoResult<Vec<ChangelistSummaryRecord>> {
+    // parent 的 id 总是小于 changelist 本身，回溯到 `from_cl` 之前即可停止
+    let stmt = Statement::from_sql_and_values(
+        DatabaseBackend::Postgres,
+        r#"
+        WITH RECURSIVE chain AS (
+            SELECT id, parent_changelist_id FROM changelists WHERE id = $2 AND id >= $1
+            UNION ALL
+            SELECT c.id, c.parent_changelist_id
+            FROM changelists c
+            JOIN chain ON c.id = chain.parent_changelist_id
+            WHERE c.id >= $1
+        )
+        SELECT c.id, c.author, c.description, c.committed_at,
+               (SELECT COUNT(*) FROM file_revisions fr WHERE fr.changelist_id = c.id) AS files_count
+        FROM chain
+        JOIN changelists c ON c.id = chain.id
+        WHERE c.metadata -> 'squashed_into' IS NULL
+        ORDER BY c.id DESC
+        LIMIT $3
+        "#,
+        vec![from_cl.into(), to_cl.into(), (limit as i64).into()],
+    );
+    let rows = conn.query_all(stmt).await?;
+    rows.iter()
+        .map(|row| {
+            Ok(ChangelistSummaryRecord {
+                id: row.try_get("", "id")?,
+                author: row.try_get("", "author")?,
+                description: row.try_get("", "description")?,
+                committed_at: row.try_get("", "committed_at")?,
+                files_count: row.try_get("", "files_count")?,
+            })
+        })
+        .collect()
+}
+
+/// 按 id 查询 changelist。
+pub async fn find_changelist_by_id(
+    changelist_id: i64,
+) -> DaoResult<Option<entities::changelists::Model>> {
+    dao().find_changelist_by_id(changelist_id).await
+}
+
+async fn find_changelist_by_id_on<C: ConnectionTrait>(
+    conn: &C,
+    changelist_id: i64,
+) -> DaoResult<Option<entities::changelists::Model>> {
+    let model = entities::changelists::Entity::find_by_id(changelist_id)
+        .one(conn)
+        .await?;
+    Ok(model)
+}
+
+/// 查询最新（id 最大）的 changelist，没有任何 changelist 时返回 None。
+pub async fn find_latest_changelist_id() -> DaoResult<Option<i64>> {
+    dao().find_latest_changelist_id().await
+}
+
+async fn find_latest_changelist_id_on<C: ConnectionTrait>(conn: &C) -> DaoResult<Option<i64>> {
+    let model = entities::changelists::Entity::find()
+        .order_by_desc(entities::changelists::Column::Id)
+        .one(conn)
+        .await?;
+    Ok(model.map(|m| m.id))
+}
+
+/// 查询文件的所有 revision，按 (generation, revision) 从新到旧排列。
+pub async fn list_file_revisions_by_depot_path(
+    depot_path: &str,
+) -> DaoResult<Vec<entities::file_revisions::Model>> {
+    dao().list_file_revisions_by_depot_path(depot_path).await
+}
+
+async fn list_file_revisions_by_depot_path_on<C: ConnectionTrait>(
+    conn: &C,
+    depot_path: &str,
+) -> DaoResult<Vec<entities::file_revisions::Model>> {
+    let key = ltree_key::depot_path_str_to_ltree_key(depot_path)?;
+    let models = entities::file_revisions::Entity::find()
+        .filter(entities::file_revisions::Column::Path.eq(key))
+        .order_by_desc(entities::file_revisions::Column::Generation)
+        .order_by_desc(entities::file_revisions::Column::Revision)
+        .all(conn)
+        .await?;
+    Ok(models)
+}
+
+/// 读取 file revision `metadata.tags` 中的标签，缺失时为空。
+pub fn file_revision_tags(metadata: &serde_json::Value) -> Vec<String> {
+    metadata
+        .get("tags")
+        .and_then(|tags| tags.as_array())
+        .map(|tags| {
+            tags.iter()
+                .filter_map(|t| t.as_str().map(|s| s.to_string()))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn set_file_revision_tags(metadata: &mut serde_json::Value, tags: Vec<String>) {
+    if !metadata.is_object() {
+        *metadata = serde_json::json!({});
+    }
+    metadata["tags"] = serde_json::json!(tags);
+}
+
+/// 为 file revision 添加和移除标签，返回更新后的标签（按字典序排列、不含重复项）；
+/// revision 不存在时返回 None。
+pub async fn update_file_revision_tags(
+    depot_path: &str,
+    generation: i64,
+    revision: i64,
+    tags_to_add: &[String],
+    tags_to_remove: &[String],
+) -> DaoResult<Option<Vec<String>>> {
+    dao()
+        .update_file_revision_tags(
+            depot_path,
+            generation,
+            revision,
+            tags_to_add,
+            tags_to_remove,
+        )
+        .await
+}
+
+async fn update_file_revision_tags_on<C: ConnectionTrait>(
+    conn: &C,
+    depot_path: &str,
+    generation: i64,
+    revision: i64,
+    tags_to_add: &[String],
+    tags_to_remove: &[String],
+) -> DaoResult<Option<Vec<String>>> {
+    let key = ltree_key::depot_path_str_to_ltree_key(depot_path)?;
+
+    // 在同一条 UPDATE 中合并原有标签与待添加标签、去掉待移除标签，并发修改不会互相覆盖
+    let row = conn
+        .query_one(Statement::from_sql_and_values(
+            DatabaseBackend::Postgres,
+            r#"
+            UPDATE file_revisions
+            SET metadata = jsonb_set(
+                metadata,
+                '{tags}',
+                COALESCE(
+                    (
+                        SELECT jsonb_agg(t ORDER BY t)
+                        FROM (
+                            SELECT jsonb_array_elements_text(COALESCE(metadata->'tags', '[]'::jsonb)) AS t
+                            UNION
+                            SELECT jsonb_array_elements_text($4::jsonb)
+                        ) AS tags
+                        WHERE t NOT IN (SELECT jsonb_array_elements_text($5::jsonb))
+                    ),
+                    '[]'::jsonb
+                )
+            )
+            WHERE path = $1::ltree AND generation = $2 AND revision = $3
+            RETURNING metadata
+            "#,
+            vec![
+                key.into(),
+                generation.into(),
+                revision.into(),
+                serde_json::json!(tags_to_add).to_string().into(),
+                serde_json::json!(tags_to_remove).to_string().into(),
+            ],
+        ))
+        .await?;
+
+    match row {
+        Some(row) => {
+            let metadata: serde_json::Value = row.try_get("", "metadata")?;
+            Ok(Some(file_revision_tags(&metadata)))
+        }
+        None => Ok(None),
+    }
+}
+
+/// 查询带有指定标签的 file revision，按 changelist id 从新到旧排列。
+///
+/// `branch_id` 不为空时只返回该分支 HEAD 及之前的 revision，分支不存在时返回空列表。
+pub async fn find_file_revisions_by_tag(
+    branch_id: &str,
+    tag: &str,
+    limit: u64,
+) -> DaoResult<Vec<entities::file_revisions::Model>> {
+    dao()
+        .find_file_revisions_by_tag(branch_id, tag, limit)
+        .await
+}
+
+async fn find_file_revisions_by_tag_on<C: ConnectionTrait>(
+    conn: &C,
+    branch_id: &str,
+    tag: &str,
+    limit: u64,
+) -> DaoResult<Vec<entities::file_revisions::Model>> {
+    // 依赖 GIN 索引：idx_file_revisions_metadata
+    let stmt = Statement::from_sql_and_values(
+        DatabaseBackend::Postgres,
+        r#"
+        SELECT
+            r.path::text AS path,
+            r.generation,
+            r.revision,
+            r.changelist_id,
+            r.binary_id,
+            r.size,
+            r.is_delete,
+            r.created_at,
+            r.metadata
+        FROM file_revisions r
+        WHERE r.metadata @> jsonb_build_object('tags', jsonb_build_array($2::text))
+          AND ($1 = '' OR r.changelist_id <= (SELECT b.head_changelist_id FROM branches b WHERE b.id = $1))
+        ORDER BY r.changelist_id DESC, r.path
+        LIMIT $3
+        "#,
+        vec![
+            branch_id.to_string().into(),
+            tag.to_string().into(),
+            (limit as i64).into(),
+        ],
+    );
+    let models = entities::file_revisions::Entity::find()
+        .from_raw_sql(stmt)
+        .all(conn)
+        .await?;
+    Ok(models)
+}
+
+/// 压缩 changelist 的参数
+#[derive(Debug, Clone)]
+pub struct SquashChangelistsInput {
+    /// 为空表示默认分支，否则压缩完成后将该分支的 HEAD 更新为新的 changelist
+    pub branch_id: String,
+    pub from_changelist_id: i64,
+    pub to_changelist_id: i64,
+    pub author: String,
+    pub description: String,
+    pub committed_at: i64,
+}
+
+/// 读取 changelist `metadata.squashed_into`：该 changelist 被压缩进的新 changelist。
+pub fn changelist_squashed_into(metadata: &serde_json::Value) -> Option<i64> {
+    metadata.get("squashed_into").and_then(|id| id.as_i64())
+}
+
+fn set_squashed_into(metadata: &mut serde_json::Value, changelist_id: i64) {
+    if !metadata.is_object() {
+        *metadata = serde_json::json!({});
+    }
+    metadata["squashed_into"] = serde_json::json!(changelist_id);
+}
+
+fn squashed_from_metadata(input: &SquashChangelistsInput) -> serde_json::Value {
+    serde_json::json!({
+        "squashed_from": [input.from_changelist_id, input.to_changelist_id],
+    })
+}
+
+/// 原子地将 `[from, to]` 区间内的 changelist 压缩为一个新的 changelist，返回新的 id：
+/// - 新的 changelist 与区间位于同一分支，父 changelist 是 `from` 的父 changelist；
+/// - 每个文件只保留区间内最新的 revision，并将其归属到新的 changelist，其余 revision 被删除；
+/// - 旧的 changelist 保留，并在 `metadata.squashed_into` 中记录新的 changelist；
+/// - `branch_id` 不为空时更新该分支的 HEAD。
+///
+/// 调用方需要持有 `SUBMIT_LOCK` 的写锁，并保证 `to` 是最新的 changelist、区间是同一分支上连续的一段历史。
+pub async fn squash_changelists(input: &SquashChangelistsInput) -> DaoResult<i64> {
+    dao().squash_changelists(input).await
+}
+
+async fn squash_changelists_on(
+    conn: &sea_orm::DatabaseConnection,
+    input: &SquashChangelistsInput,
+) -> DaoResult<i64> {
+    let txn = conn.begin().await?;
+
+    // 新的 changelist 在区间所在的分支上取代整个区间，接在区间第一个 changelist 的父 changelist 之后
+    let (branch_id, parent_changelist_id) =
+        find_changelist_by_id_on(&txn, input.from_changelist_id)
+            .await?
+            .map_or((String::new(), 0), |changelist| {
+                (changelist.branch_id, changelist.parent_changelist_id)
+            });
+    let new_id = insert_changelist_with_parent_on(
+        &txn,
+        &branch_id,
+        parent_changelist_id,
+        &input.author,
+        &input.description,
+        input.committed_at,
+        squashed_from_metadata(input),
+    )
+    .await?;
+
+    let range = vec![
+        input.from_changelist_id.into(),
+        input.to_changelist_id.into(),
+    ];
+    txn.execute(Statement::from_sql_and_values(
+        DatabaseBackend::Postgres,
+        r#"
+        DELETE FROM file_revisions r
+        WHERE r.changelist_id BETWEEN $1 AND $2
+          AND EXISTS (
+              SELECT 1 FROM file_revisions n
+              WHERE n.path = r.path
+                AND n.changelist_id BETWEEN $1 AND $2
+                AND (n.generation, n.revision) > (r.generation, r.revision)
+          )
+        "#,
+        range.clone(),
+    ))
+    .await?;
+
+    let mut values = range.clone();
+    values.push(new_id.into());
+    txn.execute(Statement::from_sql_and_values(
+        DatabaseBackend::Postgres,
+        r#"
+        UPDATE file_revisions
+        SET changelist_id = $3
+        WHERE changelist_id BETWEEN $1 AND $2
+        "#,
+        values.clone(),
+    ))
+    .await?;
+
+    txn.execute(Statement::from_sql_and_values(
+        DatabaseBackend::Postgres,
+        r#"
+        UPDATE changelists
+        SET metadata = jsonb_set(metadata, '{squashed_into}', to_jsonb($3::bigint))
+        WHERE id BETWEEN $1 AND $2
+        "#,
+        values,
+    ))
+    .await?;
+
+    if !input.branch_id.is_empty() {
+        txn.execute(Statement::from_sql_and_values(
+            DatabaseBackend::Postgres,
+            r#"
+            UPDATE branches SET head_changelist_id = $2 WHERE id = $1
+            "#,
+            vec![input.branch_id.clone().into(), new_id.into()],
+        ))
+        .await?;
+    }
+
+    txn.commit().await?;
+    Ok(new_id)
+}
+
+/// 查询文件在 `changelist_id` 所在的 changelist 链上最新的 revision，即该 changelist 处的文件版本。
+pub async fn find_file_revision_at(
+    depot_path: &str,
+    changelist_id: i64,
+) -> DaoResult<Option<entities::file_revisions::Model>> {
+    dao().find_file_revision_at(depot_path, changelist_id).await
+}
+
+async fn find_file_revision_at_on<C: ConnectionTrait>(
+    conn: &C,
+    depot_path: &str,
+    changelist_id: i64,
+) -> DaoResult<Option<entities::file_revisions::Model>> {
+    let key = ltree_key::depot_path_str_to_ltree_key(depot_path)?;
+    let stmt = Statement::from_sql_and_values(
+        DatabaseBackend::Postgres,
+        format!(
+            r#"
+            {}
+            SELECT
+                path::text AS path,
+                generation,
+                revision,
+                changelist_id,
+                binary_id,
+                size,
+                is_delete,
+                created_at,
+                metadata
+            FROM file_revisions
+            WHERE path = $1::ltree AND changelist_id IN (SELECT id FROM chain)
+            ORDER BY changelist_id DESC, generation DESC, revision DESC
+            LIMIT 1
+            "#,
+            changelist_chain_cte("$2")
+        ),
+        vec![key.into(), changelist_id.into()],
+    );
+
+    let model = entities::file_revisions::Entity::find()
+        .from_raw_sql(stmt)
+        .one(conn)
+        .await?;
+
+    Ok(model)
+}
+
+/// 查询某个 changelist 写入的所有 revision，按路径排列。
+pub async fn list_file_revisions_by_changelist(
+    changelist_id: i64,
+) -> DaoResult<Vec<entities::file_revisions::Model>> {
+    dao().list_file_revisions_by_changelist(changelist_id).await
+}
+
+async fn list_file_revisions_by_changelist_on<C: ConnectionTrait>(
+    conn: &C,
+    changelist_id: i64,
+) -> DaoResult<Vec<entities::file_revisions::Model>> {
+    let models = entities::file_revisions::Entity::find()
+        .filter(entities::file_revisions::Column::ChangelistId.eq(changelist_id))
+        .order_by_asc(entities::file_revisions::Column::Path)
+        .all(conn)
+        .await?;
+    Ok(models)
+}
+
+/// 查询 `depot` 范围内各文件在 `changelist_id` 时的最新 revision（含删除），`changelist_id <= 0` 表示默认分支的 HEAD
+pub async fn list_file_tree_revisions(
+    depot: &DepotPath,
+    changelist_id: i64,
+) -> DaoResult<Vec<entities::file_revisions::Model>> {
+    dao().list_file_tree_revisions(depot, changelist_id).await
+}
+
+/// Cherry-pick 的写入参数：将 `revisions` 作为新的 changelist 写入，并更新目标分支的 HEAD
+#[derive(Debug, Clone)]
+pub struct CherryPickInput {
+    pub source_branch_id: String,
+    pub source_changelist_id: i64,
+    pub target_branch_id: String,
+    pub author: String,
+    pub description: String,
+    pub committed_at: i64,
+    pub revisions: Vec<NewFileRevisionInput>,
+}
+
+/// 读取 changelist `metadata.cherry_picked_from`：该 changelist 复制自哪个 changelist。
+pub fn changelist_cherry_picked_from(metadata: &serde_json::Value) -> Option<i64> {
+    metadata
+        .get("cherry_picked_from")
+        .and_then(|from| from.get("changelist_id"))
+        .and_then(|id| id.as_i64())
+}
+
+fn cherry_picked_from_metadata(input: &CherryPickInput) -> serde_json::Value {
+    serde_json::json!({
+        "cherry_picked_from": {
+            "branch_id": input.source_branch_id,
+            "changelist_id": input.source_changelist_id,
+        },
+    })
+}
+
+/// 原子地写入 cherry-pick 产生的 changelist 与 revision，并将目标分支的 HEAD 更新为新的 changelist，
+/// 返回新的 changelist id。
+///
+/// 调用方需要持有 `SUBMIT_LOCK` 的写锁，并已经检查过冲突。
+pub async fn cherry_pick(input: &CherryPickInput) -> DaoResult<i64> {
+    dao().cherry_pick(input).await
+}
+
+async fn cherry_pick_on(
+    conn: &sea_orm::DatabaseConnection,
+    input: &CherryPickInput,
+) -> DaoResult<i64> {
+    commit_to_branch_on(
+        conn,
+        &input.target_branch_id,
+        &input.author,
+        &input.description,
+        input.committed_at,
+        cherry_picked_from_metadata(input),
+        &input.revisions,
+    )
+    .await
+}
+
+/// 在一个事务中写入新的 changelist 与 revision，并将 `branch_id` 的 HEAD 更新为新的 changelist
+async fn commit_to_branch_on(
+    conn: &sea_orm::DatabaseConnection,
+    branch_id: &str,
+    author: &str,
+    description: &str,
+    committed_at: i64,
+    metadata: serde_json::Value,
+    revisions: &[NewFileRevisionInput],
+) -> DaoResult<i64> {
+    let txn = conn.begin().await?;
+
+    let new_id =
+        insert_changelist_on(&txn, branch_id, author, description, committed_at, metadata).await?;
+
+    ensure_files_exist_on(&txn, revisions).await?;
+    insert_file_revisions_on(&txn, revisions, new_id).await?;
+
+    txn.execute(Statement::from_sql_and_values(
+        DatabaseBackend::Postgres,
+        r#"
+        UPDATE branches SET head_changelist_id = $2 WHERE id = $1
+        "#,
+        vec![branch_id.into(), new_id.into()],
+    ))
+    .await?;
+
+    txn.commit().await?;
+    Ok(new_id)
+}
+
+/// 合并分支的写入参数：将 `revisions` 作为新的 changelist 写入，并更新目标分支的 HEAD
+#[derive(Debug, Clone)]
+pub struct MergeInput {
+    pub source_branch_id: String,
+    /// 合并时源分支的 HEAD
+    pub source_changelist_id: i64,
+    pub target_branch_id: String,
+    pub author: String,
+    pub description: String,
+    pub committed_at: i64,
+    pub revisions: Vec<NewFileRevisionInput>,
+}
+
+/// 读取 changelist `metadata.merged_from`：该 changelist 合并自哪个分支的哪个 changelist。
+pub fn changelist_merged_from(metadata: &serde_json::Value) -> Option<(String, i64)> {
+    let from = metadata.get("merged_from")?;
+    let branch_id = from.get("branch_id")?.as_str()?;
+    let changelist_id = from.get("changelist_id")?.as_i64()?;
+    Some((branch_id.to_string(), changelist_id))
+}
+
+fn merged_from_metadata(input: &MergeInput) -> serde_json::Value {
+    serde_json::json!({
+        "merged_from": {
+            "branch_id": input.source_branch_id,
+            "changelist_id": input.source_changelist_id,
+        },
+    })
+}
+
+/// 原子地写入合并产生的 changelist 与 revision，并将目标分支的 HEAD 更新为新的 changelist，
+/// 返回新的 changelist id。
+///
+/// 调用方需要持有 `SUBMIT_LOCK` 的写锁，并已经处理过冲突。
+pub async fn merge_branches(input: &MergeInput) -> DaoResult<i64> {
+    dao().merge_branches(input).await
+}
+
+async fn merge_branches_on(
+    conn: &sea_orm::DatabaseConnection,
+    input: &MergeInput,
+) -> DaoResult<i64> {
+    commit_to_branch_on(
+        conn,
+        &input.target_branch_id,
+        &input.author,
+        &input.description,
+        input.committed_at,
+        merged_from_metadata(input),
+        &input.revisions,
+    )
+    .await
+}
+
+/// 写入一条审计记录。
+pub async fn insert_audit_event(event: &AuditEvent) -> DaoResult<()> {
+    dao().insert_audit_event(event).await
+}
+
+async fn insert_audit_event_on<C: ConnectionTrait>(conn: &C, event: &AuditEvent) -> DaoResult<()> {
+    let am = entities::audit_log::ActiveModel {
+        timestamp_millis: Set(event.timestamp_millis),
+        username: Set(event.username.clone()),
+        operation: Set(event.operation.as_str().to_string()),
+        resource_id: Set(event.resource_id.clone()),
+        outcome: Set(event.outcome.as_str().to_string()),
+        detail: Set(event.detail.clone()),
+        ..Default::default()
     };
     am.insert(conn).await?;
     Ok(())
 }
 
-/// 按 depot path 查询该文件的最新 revision（如果存在）。
-///
-/// 返回值为 `file_revisions` 的一条记录：按 `(generation desc, revision desc)` 取最大。
-pub async fn find_latest_file_revision_by_depot_path(
-    depot_path: &str,
-) -> DaoResult<Option<entities::file_revisions::Model>> {
-    dao().find_latest_file_revision_by_depot_path(depot_path).await
+/// 按条件查询审计记录，按时间从新到旧排列，最多返回 `query.limit` 条。
+pub async fn list_audit_events(query: &AuditQuery) -> DaoResult<Vec<AuditEvent>> {
+    dao().list_audit_events(query).await
+}
+
+async fn list_audit_events_on<C: ConnectionTrait>(
+    conn: &C,
+    query: &AuditQuery,
+) -> DaoResult<Vec<AuditEvent>> {
+    use entities::audit_log::{Column, Entity};
+
+    let mut select = Entity::find();
+    if let Some(since) = query.since_millis {
+        select = select.filter(Column::TimestampMillis.gte(since));
+    }
+    if let Some(until) = query.until_millis {
+        select = select.filter(Column::TimestampMillis.lt(until));
+    }
+    if let Some(username) = &query.username {
+        select = select.filter(Column::Username.eq(username.clone()));
+    }
+    if let Some(operation) = query.operation {
+        select = select.filter(Column::Operation.eq(operation.as_str()));
+    }
+
+    let models = select
+        .order_by_desc(Column::TimestampMillis)
+        .order_by_desc(Column::Id)
+        .limit(query.limit)
+        .all(conn)
+        .await?;
+
+    // 无法识别的 operation / outcome 说明记录来自更新版本的 hive，直接跳过
+    Ok(models
+        .into_iter()
+        .filter_map(|m| {
+            Some(AuditEvent {
+                timestamp_millis: m.timestamp_millis,
+                username: m.username,
+                operation: AuditOp::parse(&m.operation)?,
+                resource_id: m.resource_id,
+                outcome: AuditOutcome::parse(&m.outcome)?,
+                detail: m.detail,
+            })
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod dao_trait_tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn mock_dao_insert_and_find_user() {
+        // 注意：这是全局覆盖，测试尽量保持简单。
+        crate::test_support::install_mock_dao();
+
+        insert_user("alice", "hash", Some("alice@example.com"), "Alice")
+            .await
+            .expect("insert user");
+        let u = find_user_by_username("alice")
+            .await
+            .expect("find user")
+            .expect("user should exist");
+        assert_eq!(u.id, "alice");
+        assert_eq!(u.password, "hash");
+        assert_eq!(u.email.as_deref(), Some("alice@example.com"));
+        assert_eq!(u.display_name, "Alice");
+    }
+}
+
+/// 按名称查询 workspace。
+pub async fn find_workspace_by_name(name: &str) -> DaoResult<Option<entities::workspaces::Model>> {
+    dao().find_workspace_by_name(name).await
+}
+
+async fn find_workspace_by_name_on<C: ConnectionTrait>(
+    conn: &C,
+    name: &str,
+) -> DaoResult<Option<entities::workspaces::Model>> {
+    Ok(entities::workspaces::Entity::find_by_id(name.to_string())
+        .one(conn)
+        .await?)
+}
+
+/// 登记新的 workspace，名称已存在时返回数据库错误。
+pub async fn insert_workspace(workspace: &entities::workspaces::Model) -> DaoResult<()> {
+    dao().insert_workspace(workspace).await
+}
+
+async fn insert_workspace_on<C: ConnectionTrait>(
+    conn: &C,
+    workspace: &entities::workspaces::Model,
+) -> DaoResult<()> {
+    let am: entities::workspaces::ActiveModel = workspace.clone().into();
+    am.insert(conn).await?;
+    Ok(())
+}
+
+/// 删除 workspace，返回其是否存在。
+pub async fn delete_workspace(name: &str) -> DaoResult<bool> {
+    dao().delete_workspace(name).await
+}
+
+async fn delete_workspace_on<C: ConnectionTrait>(conn: &C, name: &str) -> DaoResult<bool> {
+    let res = entities::workspaces::Entity::delete_by_id(name.to_string())
+        .exec(conn)
+        .await?;
+    Ok(res.rows_affected > 0)
+}
+
+/// 登记 webhook，返回其 id（忽略 `webhook.id`）。
+pub async fn insert_webhook(webhook: &WebhookConfig) -> DaoResult<i64> {
+    dao().insert_webhook(webhook).await
+}
+
+async fn insert_webhook_on<C: ConnectionTrait>(
+    conn: &C,
+    webhook: &WebhookConfig,
+) -> DaoResult<i64> {
+    let events: Vec<&str> = webhook.events.iter().map(|e| e.as_str()).collect();
+    let am = entities::webhooks::ActiveModel {
+        url: Set(webhook.url.clone()),
+        secret: Set(webhook.secret.clone()),
+        events: Set(serde_json::json!(events)),
+        created_by: Set(webhook.created_by.clone()),
+        created_at: Set(webhook.created_at),
+        ..Default::default()
+    };
+    let model = am.insert(conn).await?;
+    Ok(model.id)
+}
+
+/// 删除 webhook，返回其是否存在。
+pub async fn delete_webhook(id: i64) -> DaoResult<bool> {
+    dao().delete_webhook(id).await
+}
+
+async fn delete_webhook_on<C: ConnectionTrait>(conn: &C, id: i64) -> DaoResult<bool> {
+    let res = entities::webhooks::Entity::delete_by_id(id)
+        .exec(conn)
+        .await?;
+    Ok(res.rows_affected > 0)
+}
+
+/// 按登记顺序列出所有 webhook。
+pub async fn list_webhooks() -> DaoResult<Vec<WebhookConfig>> {
+    dao().list_webhooks().await
+}
+
+async fn list_webhooks_on<C: ConnectionTrait>(conn: &C) -> DaoResult<Vec<WebhookConfig>> {
+    use entities::webhooks::{Column, Entity};
+
+    let models = Entity::find().order_by_asc(Column::Id).all(conn).await?;
+
+    // 无法识别的事件说明记录来自更新版本的 hive，直接忽略
+    Ok(models
+        .into_iter()
+        .map(|m| WebhookConfig {
+            id: m.id,
+            url: m.url,
+            secret: m.secret,
+            events: m
+                .events
+                .as_array()
+                .map(|events| {
+                    events
+                        .iter()
+                        .filter_map(|e| e.as_str().and_then(WebhookEvent::parse))
+                        .collect()
+                })
+                .unwrap_or_default(),
+            created_by: m.created_by,
+            created_at: m.created_at,
+        })
+        .collect())
+}
+
+/// 创建快照，返回其 id（忽略 `snapshot.id`）。名称已存在时返回数据库的唯一约束错误。
+pub async fn insert_snapshot(snapshot: &SnapshotDoc) -> DaoResult<i64> {
+    dao().insert_snapshot(snapshot).await
+}
+
+async fn insert_snapshot_on<C: ConnectionTrait>(
+    conn: &C,
+    snapshot: &SnapshotDoc,
+) -> DaoResult<i64> {
+    let am = entities::snapshots::ActiveModel {
+        name: Set(snapshot.name.clone()),
+        branch_id: Set(snapshot.branch_id.clone()),
+        changelist_id: Set(snapshot.changelist_id),
+        created_by: Set(snapshot.created_by.clone()),
+        created_at: Set(snapshot.created_at),
+        description: Set(snapshot.description.clone()),
+        ..Default::default()
+    };
+    let model = am.insert(conn).await?;
+    Ok(model.id)
+}
+
+fn snapshot_from_model(m: entities::snapshots::Model) -> SnapshotDoc {
+    SnapshotDoc {
+        id: m.id,
+        name: m.name,
+        branch_id: m.branch_id,
+        changelist_id: m.changelist_id,
+        created_by: m.created_by,
+        created_at: m.created_at,
+        description: m.description,
+    }
+}
+
+/// 按 id 查询快照。
+pub async fn find_snapshot_by_id(id: i64) -> DaoResult<Option<SnapshotDoc>> {
+    dao().find_snapshot_by_id(id).await
+}
+
+async fn find_snapshot_by_id_on<C: ConnectionTrait>(
+    conn: &C,
+    id: i64,
+) -> DaoResult<Option<SnapshotDoc>> {
+    let model = entities::snapshots::Entity::find_by_id(id)
+        .one(conn)
+        .await?;
+    Ok(model.map(snapshot_from_model))
+}
+
+/// 按创建顺序列出分支上的快照，`branch_id` 为空时列出所有快照。
+pub async fn list_snapshots(branch_id: &str) -> DaoResult<Vec<SnapshotDoc>> {
+    dao().list_snapshots(branch_id).await
+}
+
+async fn list_snapshots_on<C: ConnectionTrait>(
+    conn: &C,
+    branch_id: &str,
+) -> DaoResult<Vec<SnapshotDoc>> {
+    use entities::snapshots::{Column, Entity};
+
+    let mut query = Entity::find();
+    if !branch_id.is_empty() {
+        query = query.filter(Column::BranchId.eq(branch_id));
+    }
+    let models = query.order_by_asc(Column::Id).all(conn).await?;
+    Ok(models.into_iter().map(snapshot_from_model).collect())
+}
+
+/// 删除快照，返回其是否存在。
+pub async fn delete_snapshot(id: i64) -> DaoResult<bool> {
+    dao().delete_snapshot(id).await
+}
+
+async fn delete_snapshot_on<C: ConnectionTrait>(conn: &C, id: i64) -> DaoResult<bool> {
+    let res = entities::snapshots::Entity::delete_by_id(id)
+        .exec(conn)
+        .await?;
+    Ok(res.rows_affected > 0)
+}
+
+/// 按 request_id 查询提交记录，`committed_at` 早于 `not_before` 的记录视为已过期。
+pub async fn find_submission(
+    request_id: &str,
+    not_before: i64,
+) -> DaoResult<Option<SubmissionRecord>> {
+    dao().find_submission(request_id, not_before).await
+}
+
+async fn find_submission_on<C: ConnectionTrait>(
+    conn: &C,
+    request_id: &str,
+    not_before: i64,
+) -> DaoResult<Option<SubmissionRecord>> {
+    let model = entities::submission_cache::Entity::find_by_id(request_id.to_string())
+        .one(conn)
+        .await?;
+    Ok(model
+        .filter(|m| m.committed_at >= not_before)
+        .map(|m| SubmissionRecord {
+            request_id: m.request_id,
+            changelist_id: m.changelist_id,
+            committed_at: m.committed_at,
+            latest_revisions: m.latest_revisions,
+        }))
+}
+
+/// 记录一次已完成的提交，同时清理 `committed_at` 早于 `expire_before` 的过期记录。
+pub async fn insert_submission(record: &SubmissionRecord, expire_before: i64) -> DaoResult<()> {
+    dao().insert_submission(record, expire_before).await
+}
+
+async fn insert_submission_on<C: ConnectionTrait>(
+    conn: &C,
+    record: &SubmissionRecord,
+    expire_before: i64,
+) -> DaoResult<()> {
+    use entities::submission_cache::{ActiveModel, Column, Entity};
+
+    Entity::delete_many()
+        .filter(Column::CommittedAt.lt(expire_before))
+        .exec(conn)
+        .await?;
+    ActiveModel {
+        request_id: Set(record.request_id.clone()),
+        changelist_id: Set(record.changelist_id),
+        committed_at: Set(record.committed_at),
+        latest_revisions: Set(record.latest_revisions.clone()),
+    }
+    .insert(conn)
+    .await?;
+    Ok(())
+}
+
+fn lock_mode_to_str(mode: LockMode) -> &'static str {
+    match mode {
+        LockMode::Read => "read",
+        LockMode::Write => "write",
+    }
+}
+
+fn lock_mode_from_str(mode: &str) -> LockMode {
+    match mode {
+        "read" => LockMode::Read,
+        _ => LockMode::Write,
+    }
+}
+
+/// 记录一个进行中的提交及其锁定的文件。
+pub async fn insert_submit_ticket(record: &SubmitTicketRecord) -> DaoResult<()> {
+    dao().insert_submit_ticket(record).await
+}
+
+async fn insert_submit_ticket_on(
+    conn: &sea_orm::DatabaseConnection,
+    record: &SubmitTicketRecord,
+) -> DaoResult<()> {
+    let txn = conn.begin().await?;
+
+    entities::submit_tickets::ActiveModel {
+        ticket: Set(record.ticket.clone()),
+        submitting_by: Set(record.submitting_by.clone()),
+        expires_at: Set(record.expires_at),
+        branch_id: Set(record.branch_id.clone()),
+        started_at: Set(record.started_at),
+        status: Set(record.status.as_str().to_string()),
+    }
+    .insert(&txn)
+    .await?;
+    for f in &record.files {
+        entities::file_locks::ActiveModel {
+            ticket: Set(record.ticket.clone()),
+            path: Set(f.depot_path.clone()),
+            mode: Set(lock_mode_to_str(f.mode).to_string()),
+            locked_generation: Set(f.locked_generation),
+            locked_revision: Set(f.locked_revision),
+        }
+        .insert(&txn)
+        .await?;
+    }
+
+    txn.commit().await?;
+    Ok(())
+}
+
+/// 删除 ticket 及其持有的文件锁，ticket 不存在时返回 false。
+pub async fn delete_submit_ticket(ticket: &str) -> DaoResult<bool> {
+    dao().delete_submit_ticket(ticket).await
+}
+
+async fn delete_submit_ticket_on(
+    conn: &sea_orm::DatabaseConnection,
+    ticket: &str,
+) -> DaoResult<bool> {
+    let txn = conn.begin().await?;
+
+    entities::file_locks::Entity::delete_many()
+        .filter(entities::file_locks::Column::Ticket.eq(ticket))
+        .exec(&txn)
+        .await?;
+    let res = entities::submit_tickets::Entity::delete_by_id(ticket.to_string())
+        .exec(&txn)
+        .await?;
+
+    txn.commit().await?;
+    Ok(res.rows_affected > 0)
+}
+
+/// 删除 `expires_at` 不晚于 `now` 的 ticket 及其文件锁，返回删除的 ticket 数量。
+pub async fn delete_expired_submit_tickets(now: i64) -> DaoResult<u64> {
+    dao().delete_expired_submit_tickets(now).await
+}
+
+async fn delete_expired_submit_tickets_on(
+    conn: &sea_orm::DatabaseConnection,
+    now: i64,
+) -> DaoResult<u64> {
+    use entities::submit_tickets::{Column, Entity};
+
+    let txn = conn.begin().await?;
+
+    let expired: Vec<String> = Entity::find()
+        .filter(Column::ExpiresAt.lte(now))
+        .all(&txn)
+        .await?
+        .into_iter()
+        .map(|m| m.ticket)
+        .collect();
+    if !expired.is_empty() {
+        entities::file_locks::Entity::delete_many()
+            .filter(entities::file_locks::Column::Ticket.is_in(expired.clone()))
+            .exec(&txn)
+            .await?;
+        Entity::delete_many()
+            .filter(Column::Ticket.is_in(expired.clone()))
+            .exec(&txn)
+            .await?;
+    }
+
+    txn.commit().await?;
+    Ok(expired.len() as u64)
+}
+
+/// 列出 `now` 时尚未过期且仍然持有锁的 ticket 及其锁定的文件，按 ticket 排序。
+pub async fn list_submit_tickets(now: i64) -> DaoResult<Vec<SubmitTicketRecord>> {
+    dao().list_submit_tickets(now).await
 }
 
-async fn find_latest_file_revision_by_depot_path_on<C: ConnectionTrait>(
+async fn list_submit_tickets_on<C: ConnectionTrait>(
     conn: &C,
-    depot_path: &str,
-) -> DaoResult<Option<entities::file_revisions::Model>> {
-    let key = ltree_key::depot_path_str_to_ltree_key(depot_path)?;
+    now: i64,
+) -> DaoResult<Vec<SubmitTicketRecord>> {
+    use entities::submit_tickets::{Column, Entity};
 
-    // `file_revisions.path` 是 Postgres `ltree`，而 SeaORM 这里字段类型用 `String`。
-    // 在某些 Postgres 版本/配置下，`ltree = text` 不会隐式 cast，导致查询报错。
-    // 这里用 raw SQL 显式 `$1::ltree`，避免类型不匹配。
+    let active = [
+        SubmitContextStatus::Launched,
+        SubmitContextStatus::Submitting,
+    ];
+    let tickets = Entity::find()
+        .filter(Column::ExpiresAt.gt(now))
+        .filter(Column::Status.is_in(active.map(SubmitContextStatus::as_str)))
+        .order_by_asc(Column::Ticket)
+        .all(conn)
+        .await?;
+    submit_ticket_records(conn, tickets).await
+}
+
+/// 按 ticket 查找提交记录，已经结束但尚未过期清理的记录也会返回。
+pub async fn find_submit_ticket(ticket: &str) -> DaoResult<Option<SubmitTicketRecord>> {
+    dao().find_submit_ticket(ticket).await
+}
+
+async fn find_submit_ticket_on<C: ConnectionTrait>(
+    conn: &C,
+    ticket: &str,
+) -> DaoResult<Option<SubmitTicketRecord>> {
+    let Some(model) = entities::submit_tickets::Entity::find_by_id(ticket.to_string())
+        .one(conn)
+        .await?
+    else {
+        return Ok(None);
+    };
+    Ok(submit_ticket_records(conn, vec![model]).await?.pop())
+}
+
+/// 更新提交的状态，`branch_id` 不为 None 时同时记录提交到的分支；ticket 不存在时返回 false。
+pub async fn update_submit_ticket_status(
+    ticket: &str,
+    status: SubmitContextStatus,
+    branch_id: Option<&str>,
+) -> DaoResult<bool> {
+    dao()
+        .update_submit_ticket_status(ticket, status, branch_id)
+        .await
+}
+
+async fn update_submit_ticket_status_on(
+    conn: &sea_orm::DatabaseConnection,
+    ticket: &str,
+    status: SubmitContextStatus,
+    branch_id: Option<&str>,
+) -> DaoResult<bool> {
+    use entities::submit_tickets::{Column, Entity};
+
+    let mut update = Entity::update_many()
+        .col_expr(
+            Column::Status,
+            sea_orm::sea_query::Expr::value(status.as_str()),
+        )
+        .filter(Column::Ticket.eq(ticket));
+    if let Some(branch_id) = branch_id {
+        update = update.col_expr(Column::BranchId, sea_orm::sea_query::Expr::value(branch_id));
+    }
+    let res = update.exec(conn).await?;
+    Ok(res.rows_affected > 0)
+}
+
+/// 记录一次登录失败并返回最新的记录；距上次统计窗口开始超过 `window_secs` 秒时重新开始计数。
+pub async fn increment_login_failures(
+    username: &str,
+    now: i64,
+    window_secs: i64,
+) -> DaoResult<entities::login_failures::Model> {
+    dao()
+        .increment_login_failures(username, now, window_secs)
+        .await
+}
+
+async fn increment_login_failures_on(
+    conn: &sea_orm::DatabaseConnection,
+    username: &str,
+    now: i64,
+    window_secs: i64,
+) -> DaoResult<entities::login_failures::Model> {
+    // 并发的失败请求都要计入，在一条语句中完成读取与累加
     let stmt = Statement::from_sql_and_values(
         DatabaseBackend::Postgres,
         r#"
-        SELECT
-            path::text AS path,
-            generation,
-            revision,
-            changelist_id,
-            binary_id,
-            size,
-            is_delete,
-            created_at,
-            metadata
-        FROM file_revisions
-        WHERE path = $1::ltree
-        ORDER BY generation DESC, revision DESC
-        LIMIT 1
+        INSERT INTO login_failures (username, count, window_started_at, locked_until)
+        VALUES ($1, 1, $2, NULL)
+        ON CONFLICT (username) DO UPDATE SET
+            count = CASE
+                WHEN login_failures.window_started_at > $3 THEN login_failures.count + 1
+                ELSE 1
+            END,
+            window_started_at = CASE
+                WHEN login_failures.window_started_at > $3 THEN login_failures.window_started_at
+                ELSE $2
+            END
+        RETURNING username, count, window_started_at, locked_until
         "#,
-        [key.into()].to_vec(),
+        vec![
+            username.to_string().into(),
+            now.into(),
+            (now - window_secs).into(),
+        ],
     );
 
-    let model = entities::file_revisions::Entity::find()
+    entities::login_failures::Entity::find()
         .from_raw_sql(stmt)
         .one(conn)
-        .await?;
+        .await?
+        .ok_or_else(|| {
+            DaoError::Db(DbErr::RecordNotFound(
+                "failed to record login failure".to_string(),
+            ))
+        })
+}
 
-    Ok(model)
+/// 按用户名查询登录失败记录。
+pub async fn find_login_failures(
+    username: &str,
+) -> DaoResult<Option<entities::login_failures::Model>> {
+    dao().find_login_failures(username).await
 }
 
-/// 创建一个 changelist，并返回其自增 id。
-pub async fn insert_changelist(
-    author: &str,
-    description: &str,
-    committed_at: i64,
-    metadata: serde_json::Value,
-) -> DaoResult<i64> {
-    dao()
-        .insert_changelist(author, description, committed_at, metadata)
-        .await
+/// 锁定账号到 `locked_until`（秒）并清零失败次数，用户没有失败记录时什么也不做。
+pub async fn lock_account(username: &str, locked_until: i64) -> DaoResult<()> {
+    dao().lock_account(username, locked_until).await
 }
 
-async fn insert_changelist_on<C: ConnectionTrait>(
+async fn lock_account_on(
+    conn: &sea_orm::DatabaseConnection,
+    username: &str,
+    locked_until: i64,
+) -> DaoResult<()> {
+    use entities::login_failures::{Column, Entity};
+
+    Entity::update_many()
+        .col_expr(Column::Count, sea_orm::sea_query::Expr::value(0))
+        .col_expr(
+            Column::LockedUntil,
+            sea_orm::sea_query::Expr::value(locked_until),
+        )
+        .filter(Column::Username.eq(username))
+        .exec(conn)
+        .await?;
+    Ok(())
+}
+
+/// 删除用户的登录失败记录，登录成功后调用。
+pub async fn reset_login_failures(username: &str) -> DaoResult<()> {
+    dao().reset_login_failures(username).await
+}
+
+/// 为 ticket 补上各自锁定的文件
+async fn submit_ticket_records<C: ConnectionTrait>(
     conn: &C,
-    author: &str,
-    description: &str,
-    committed_at: i64,
-    metadata: serde_json::Value,
-) -> DaoResult<i64> {
+    tickets: Vec<entities::submit_tickets::Model>,
+) -> DaoResult<Vec<SubmitTicketRecord>> {
+    if tickets.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let mut locks: HashMap<String, Vec<FileLockRecord>> = HashMap::new();
+    let models = entities::file_locks::Entity::find()
+        .filter(
+            entities::file_locks::Column::Ticket.is_in(tickets.iter().map(|t| t.ticket.clone())),
+        )
+        .order_by_asc(entities::file_locks::Column::Path)
+        .all(conn)
+        .await?;
+    for m in models {
+        locks.entry(m.ticket).or_default().push(FileLockRecord {
+            depot_path: m.path,
+            mode: lock_mode_from_str(&m.mode),
+            locked_generation: m.locked_generation,
+            locked_revision: m.locked_revision,
+        });
+    }
+
+    Ok(tickets
+        .into_iter()
+        .map(|t| SubmitTicketRecord {
+            files: locks.remove(&t.ticket).unwrap_or_default(),
+            ticket: t.ticket,
+            submitting_by: t.submitting_by,
+            branch_id: t.branch_id,
+            started_at: t.started_at,
+            expires_at: t.expires_at,
+            status: SubmitContextStatus::parse(&t.status),
+        })
+        .collect())
+}
+
+/// 统计仓库中的文件、changelist、分支数量与 revision 总大小。
+pub async fn repository_stats() -> DaoResult<RepositoryStats> {
+    dao().repository_stats().await
+}
+
+async fn repository_stats_on<C: ConnectionTrait>(conn: &C) -> DaoResult<RepositoryStats> {
     let row = conn
-        .query_one(Statement::from_sql_and_values(
+        .query_one(Statement::from_string(
             DatabaseBackend::Postgres,
             r#"
-            INSERT INTO changelists (author, description, committed_at, metadata)
-            VALUES ($1, $2, $3, $4::jsonb)
-            RETURNING id
+            SELECT
+                (SELECT COUNT(*) FROM files) AS total_files,
+                (SELECT COUNT(*) FROM changelists) AS total_changelists,
+                (SELECT COUNT(*) FROM branches) AS total_branches,
+                (SELECT COALESCE(SUM(size), 0)::BIGINT FROM file_revisions) AS total_revision_bytes,
+                (SELECT MIN(committed_at) FROM changelists) AS oldest_changelist_at,
+                (SELECT MAX(committed_at) FROM changelists) AS newest_changelist_at
             "#,
-            vec![
-                author.to_string().into(),
-                description.to_string().into(),
-                committed_at.into(),
-                metadata.to_string().into(),
-            ],
         ))
         .await?;
 
-    let row = row.ok_or_else(|| {
-        DaoError::Db(DbErr::RecordNotFound(
-            "failed to insert changelist".to_string(),
-        ))
-    })?;
-    Ok(row.try_get("", "id")?)
+    let Some(row) = row else {
+        return Ok(RepositoryStats::default());
+    };
+    Ok(RepositoryStats {
+        total_files: row.try_get("", "total_files")?,
+        total_changelists: row.try_get("", "total_changelists")?,
+        total_branches: row.try_get("", "total_branches")?,
+        total_revision_bytes: row.try_get("", "total_revision_bytes")?,
+        oldest_changelist_at: row.try_get("", "oldest_changelist_at")?,
+        newest_changelist_at: row.try_get("", "newest_changelist_at")?,
+    })
+}
+
+/// 备份中包含的表，按外键依赖排序：被引用的表在前
+fn metadata_tables() -> [&'static str; 12] {
+    [
+        entities::users::Entity.table_name(),
+        entities::branches::Entity.table_name(),
+        entities::changelists::Entity.table_name(),
+        entities::files::Entity.table_name(),
+        entities::file_revisions::Entity.table_name(),
+        entities::file_locks::Entity.table_name(),
+        entities::workspaces::Entity.table_name(),
+        entities::snapshots::Entity.table_name(),
+        entities::webhooks::Entity.table_name(),
+        entities::audit_log::Entity.table_name(),
+        entities::submission_cache::Entity.table_name(),
+        entities::submit_tickets::Entity.table_name(),
+    ]
+}
+
+/// 导出元数据库中所有表的全部行，结果以表名为键、各行的 JSON 数组为值。
+///
+/// 在只读的可重复读事务中逐表查询，所有表的数据来自同一个快照。
+pub async fn export_metadata() -> DaoResult<serde_json::Value> {
+    let txn = db()?
+        .begin_with_config(
+            Some(IsolationLevel::RepeatableRead),
+            Some(AccessMode::ReadOnly),
+        )
+        .await?;
+    let mut export = serde_json::Map::new();
+    for table in metadata_tables() {
+        export.insert(table.to_string(), export_table_on(&txn, table).await?);
+    }
+    txn.commit().await?;
+    Ok(serde_json::Value::Object(export))
+}
+
+/// 导入 [`export_metadata`] 导出的数据，返回导入的行数。
+///
+/// 按主键 upsert：已存在的行被备份中的数据覆盖，重复导入同一份备份不会产生重复的行。
+/// 只导入已知的表，备份中缺少的表保持不变；导入后自增序列会推进到各表的最大 id 之后。
+pub async fn import_metadata(metadata: &serde_json::Value) -> DaoResult<u64> {
+    let txn = db()?.begin().await?;
+    let mut imported = 0;
+    for table in metadata_tables() {
+        let Some(serde_json::Value::Array(rows)) = metadata.get(table) else {
+            continue;
+        };
+        if rows.is_empty() {
+            continue;
+        }
+        import_table_on(&txn, table, rows).await?;
+        imported += rows.len() as u64;
+    }
+    txn.commit().await?;
+    Ok(imported)
 }
 
-async fn ensure_file_exists_on<C: ConnectionTrait>(
+/// 由 Postgres 将整张表序列化为 JSON 数组，`ltree` 等自定义类型的列以文本形式导出
+async fn export_table_on<C: ConnectionTrait>(
     conn: &C,
-    depot_path: &str,
-    created_at: i64,
-    metadata: &serde_json::Value,
-) -> DaoResult<()> {
-    let key = ltree_key::depot_path_str_to_ltree_key(depot_path)?;
-    conn.execute(Statement::from_sql_and_values(
-        DatabaseBackend::Postgres,
-        r#"
-        INSERT INTO files (path, created_at, metadata)
-        VALUES ($1::ltree, $2, $3::jsonb)
-        ON CONFLICT (path) DO NOTHING
-        "#,
-        vec![key.into(), created_at.into(), metadata.to_string().into()],
-    ))
-    .await?;
-    Ok(())
+    table: &str,
+) -> DaoResult<serde_json::Value> {
+    let row = conn
+        .query_one(Statement::from_string(
+            DatabaseBackend::Postgres,
+            format!(r#"SELECT COALESCE(json_agg(t), '[]'::json) AS rows FROM "{table}" t"#),
+        ))
+        .await?;
+    match row {
+        Some(row) => Ok(row.try_get("", "rows")?),
+        None => Ok(serde_json::Value::Array(Vec::new())),
+    }
 }
 
-async fn insert_file_revision_on<C: ConnectionTrait>(
+/// 用 `json_populate_recordset` 将 JSON 行转换为表的行类型后按主键 upsert，并推进自增序列
+async fn import_table_on<C: ConnectionTrait>(
     conn: &C,
-    input: &NewFileRevisionInput,
-    changelist_id: i64,
+    table: &str,
+    rows: &[serde_json::Value],
 ) -> DaoResult<()> {
-    let key = ltree_key::depot_path_str_to_ltree_key(&input.depot_path)?;
+    // 列名取自数据库本身，不使用备份中的字段名拼接 SQL
+    let columns = conn
+        .query_all(Statement::from_sql_and_values(
+            DatabaseBackend::Postgres,
+            r#"
+            SELECT column_name::text AS name,
+                   COALESCE(column_default LIKE 'nextval(%', false) AS is_serial
+            FROM information_schema.columns
+            WHERE table_schema = current_schema() AND table_name = $1
+            ORDER BY ordinal_position
+            "#,
+            [table.into()],
+        ))
+        .await?
+        .into_iter()
+        .map(|row| {
+            Ok((
+                row.try_get::<String>("", "name")?,
+                row.try_get::<bool>("", "is_serial")?,
+            ))
+        })
+        .collect::<DaoResult<Vec<_>>>()?;
 
+    let names = columns
+        .iter()
+        .map(|(name, _)| format!(r#""{name}""#))
+        .collect::<Vec<_>>();
+    let excluded = columns
+        .iter()
+        .map(|(name, _)| format!(r#"EXCLUDED."{name}""#))
+        .collect::<Vec<_>>();
     conn.execute(Statement::from_sql_and_values(
         DatabaseBackend::Postgres,
-        r#"
-        INSERT INTO file_revisions
-            (path, generation, revision, changelist_id, binary_id, size, is_delete, created_at, metadata)
-        VALUES
-            ($1::ltree, $2, $3, $4, $5::jsonb, $6, $7, $8, $9::jsonb)
-        "#,
-        vec![
-            key.into(),
-            input.generation.into(),
-            input.revision.into(),
-            changelist_id.into(),
-            input.binary_id.to_string().into(),
-            input.size.into(),
-            input.is_delete.into(),
-            input.created_at.into(),
-            input.metadata.to_string().into(),
-        ],
+        format!(
+            r#"
+            INSERT INTO "{table}" ({columns})
+            SELECT {columns} FROM json_populate_recordset(NULL::"{table}", $1::json)
+            ON CONFLICT ON CONSTRAINT "{table}_pkey"
+            DO UPDATE SET ({columns}) = ROW({excluded})
+            "#,
+            columns = names.join(", "),
+            excluded = excluded.join(", "),
+        ),
+        [serde_json::Value::Array(rows.to_vec()).into()],
     ))
     .await?;
 
+    for (name, _) in columns.iter().filter(|(_, is_serial)| *is_serial) {
+        conn.execute(Statement::from_string(
+            DatabaseBackend::Postgres,
+            format!(
+                r#"
+                SELECT setval(
+                    pg_get_serial_sequence('"{table}"', '{name}'),
+                    COALESCE((SELECT MAX("{name}") FROM "{table}"), 0) + 1,
+                    false
+                )
+                "#
+            ),
+        ))
+        .await?;
+    }
     Ok(())
 }
 
-/// 原子提交：
-/// - 创建 changelist
-/// - 确保 files 行存在
-/// - 写入每个文件的 file_revisions
-pub async fn commit_submit(
-    author: &str,
-    description: &str,
-    committed_at: i64,
-    metadata: serde_json::Value,
-    revisions: Vec<NewFileRevisionInput>,
-) -> DaoResult<i64> {
-    dao()
-        .commit_submit(author, description, committed_at, metadata, revisions)
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn revision_input(depot_path: &str, generation: i64, revision: i64) -> NewFileRevisionInput {
+        NewFileRevisionInput {
+            depot_path: depot_path.to_string(),
+            generation,
+            revision,
+            binary_id: serde_json::json!([]),
+            size: 0,
+            is_delete: false,
+            created_at: 0,
+            metadata: serde_json::json!({}),
+            file_metadata: serde_json::json!({}),
+        }
+    }
+
+    #[tokio::test]
+    async fn mock_commit_submit_rejects_duplicate_revisions_in_batch() {
+        let dao = MockDao::default();
+        let path = "//tests/batch/dup.txt";
+        let err = dao
+            .commit_submit(
+                "alice",
+                "dup",
+                0,
+                serde_json::json!({}),
+                vec![revision_input(path, 1, 1), revision_input(path, 1, 1)],
+            )
+            .await
+            .unwrap_err();
+        assert!(matches!(err, DaoError::RevisionConflict(p) if p == path));
+        assert_eq!(dao.find_latest_changelist_id().await.unwrap(), None);
+    }
+
+    /// 与 submit service 的 DB 测试一样，用共享 runtime 的单一 harness 串行执行。
+    #[test]
+    #[ignore = "requires external Postgres; enable with CRV_RUN_HIVE_DB_TESTS=1"]
+    fn batch_insert_tests_harness() {
+        crate::test_support::run_hive_db_test(|| async {
+            batch_insert_reports_duplicate_key().await;
+            commit_submit_with_duplicate_revision_persists_nothing().await;
+            commit_submit_to_branch_advances_head().await;
+            interleaved_branch_commits_resolve_separate_trees().await;
+            merge_base_follows_merged_from().await;
+            batch_insert_writes_every_row_once().await;
+        });
+    }
+
+    async fn batch_insert_reports_duplicate_key() {
+        let db = crate::database::get();
+        let dir = format!("//tests/batch/{}", uuid::Uuid::new_v4().simple());
+        let changelist_id =
+            insert_changelist_on(db, "", "alice", "batch", 0, serde_json::json!({}))
+                .await
+                .unwrap();
+
+        // 同一批次中重复
+        let txn = db.begin().await.unwrap();
+        let inputs = vec![
+            revision_input(&format!("{dir}/a.txt"), 1, 1),
+            revision_input(&format!("{dir}/b.txt"), 1, 1),
+            revision_input(&format!("{dir}/a.txt"), 1, 1),
+        ];
+        ensure_files_exist_on(&txn, &inputs).await.unwrap();
+        let err = insert_file_revisions_on(&txn, &inputs, changelist_id)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            DaoError::DuplicateKey { ref path, generation: 1, revision: 1 }
+                if *path == format!("{dir}/a.txt")
+        ));
+        txn.rollback().await.unwrap();
+
+        // 与已有的行重复
+        let existing = vec![revision_input(&format!("{dir}/c.txt"), 1, 1)];
+        ensure_files_exist_on(db, &existing).await.unwrap();
+        insert_file_revisions_on(db, &existing, changelist_id)
+            .await
+            .unwrap();
+        let txn = db.begin().await.unwrap();
+        let inputs = vec![
+            revision_input(&format!("{dir}/c.txt"), 1, 2),
+            revision_input(&format!("{dir}/c.txt"), 1, 1),
+        ];
+        ensure_files_exist_on(&txn, &inputs).await.unwrap();
+        let err = insert_file_revisions_on(&txn, &inputs, changelist_id)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, DaoError::DuplicateKey { revision: 1, .. }));
+        txn.rollback().await.unwrap();
+    }
+
+    async fn commit_submit_with_duplicate_revision_persists_nothing() {
+        let db = crate::database::get();
+        let path = format!("//tests/batch/{}/dup.txt", uuid::Uuid::new_v4().simple());
+        let latest = find_latest_changelist_id_on(db).await.unwrap();
+
+        let err = commit_submit_on(
+            db,
+            None,
+            "alice",
+            "dup",
+            0,
+            serde_json::json!({}),
+            vec![revision_input(&path, 1, 1), revision_input(&path, 1, 1)],
+        )
         .await
-}
+        .unwrap_err();
+        assert!(matches!(err, DaoError::RevisionConflict(ref p) if *p == path));
+        assert_eq!(find_latest_changelist_id_on(db).await.unwrap(), latest);
+        let row = db
+            .query_one(Statement::from_sql_and_values(
+                DatabaseBackend::Postgres,
+                "SELECT COUNT(*) AS count FROM file_revisions WHERE path = $1::ltree",
+                [ltree_key::depot_path_str_to_ltree_key(&path)
+                    .unwrap()
+                    .into()],
+            ))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(row.try_get::<i64>("", "count").unwrap(), 0);
+    }
 
-async fn commit_submit_on(
-    conn: &sea_orm::DatabaseConnection,
-    author: &str,
-    description: &str,
-    committed_at: i64,
-    metadata: serde_json::Value,
-    revisions: Vec<NewFileRevisionInput>,
-) -> DaoResult<i64> {
-    let txn = conn.begin().await?;
+    async fn commit_submit_to_branch_advances_head() {
+        let db = crate::database::get();
+        let branch_id = format!("submit-{}", uuid::Uuid::new_v4().simple());
+        insert_branch_on(
+            db,
+            &BranchDoc {
+                id: branch_id.clone(),
+                created_at: 0,
+                created_by: "alice".to_string(),
+                head_changelist_id: 0,
+                is_protected: false,
+                metadata: BranchMetadata {
+                    description: branch_id.clone(),
+                    owners: vec![],
+                    description_regex: None,
+                },
+            },
+        )
+        .await
+        .unwrap();
+        let path = format!("//tests/branch/{}/a.txt", uuid::Uuid::new_v4().simple());
 
-    // 复用 DAO 的插入逻辑（只是在事务里执行）
-    let changelist_id =
-        insert_changelist_on(&txn, author, description, committed_at, metadata).await?;
+        let changelist_id = commit_submit_on(
+            db,
+            Some(&branch_id),
+            "alice",
+            "advance",
+            0,
+            serde_json::json!({}),
+            vec![revision_input(&path, 1, 1)],
+        )
+        .await
+        .unwrap();
+        let branch = find_branch_by_id_on(db, &branch_id).await.unwrap().unwrap();
+        assert_eq!(branch.head_changelist_id, changelist_id);
+    }
+
+    /// 两个分支交替提交：沿各自的 parent 链解析出的文件树互不包含对方的提交。
+    async fn interleaved_branch_commits_resolve_separate_trees() {
+        let db = crate::database::get();
+        let run = uuid::Uuid::new_v4().simple();
+        let branches = [format!("main-{run}"), format!("dev-{run}")];
+        for branch_id in &branches {
+            insert_branch_on(
+                db,
+                &BranchDoc {
+                    id: branch_id.clone(),
+                    created_at: 0,
+                    created_by: "alice".to_string(),
+                    head_changelist_id: 0,
+                    is_protected: false,
+                    metadata: BranchMetadata {
+                        description: branch_id.clone(),
+                        owners: vec![],
+                        description_regex: None,
+                    },
+                },
+            )
+            .await
+            .unwrap();
+        }
+        let dir = format!("//tests/interleaved/{run}/");
+        let shared = format!("{dir}shared.txt");
+        let mut commits = Vec::new();
+        for (branch_id, path, revision) in [
+            (&branches[0], shared.clone(), 1),
+            (&branches[1], shared.clone(), 2),
+            (&branches[1], format!("{dir}dev.txt"), 1),
+            (&branches[0], shared.clone(), 3),
+        ] {
+            let changelist_id = commit_submit_on(
+                db,
+                Some(branch_id),
+                "alice",
+                "interleaved",
+                0,
+                serde_json::json!({}),
+                vec![revision_input(&path, 1, revision)],
+            )
+            .await
+            .unwrap();
+            commits.push(changelist_id);
+        }
 
-    for r in &revisions {
-        ensure_file_exists_on(&txn, &r.depot_path, r.created_at, &r.metadata).await?;
-        insert_file_revision_on(&txn, r, changelist_id).await?;
+        let depot = DepotPath::parse(&dir).unwrap();
+        let depot = &depot;
+        let tree = move |head| async move {
+            crate::database::service::get_file_tree_revisions(depot, head)
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|r| (r.to_depot_path_string().unwrap(), r.revision))
+                .collect::<Vec<_>>()
+        };
+        let main_head = find_branch_head_on(db, &branches[0])
+            .await
+            .unwrap()
+            .unwrap();
+        let dev_head = find_branch_head_on(db, &branches[1])
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!((main_head, dev_head), (commits[3], commits[2]));
+        assert_eq!(tree(main_head).await, vec![(shared.clone(), 3)]);
+        assert_eq!(
+            tree(dev_head).await,
+            vec![(format!("{dir}dev.txt"), 1), (shared.clone(), 2)]
+        );
+        assert!(tree(0).await.is_empty());
+        assert!(
+            is_changelist_on_chain_on(db, main_head, commits[0])
+                .await
+                .unwrap()
+        );
+        assert!(
+            !is_changelist_on_chain_on(db, main_head, commits[1])
+                .await
+                .unwrap()
+        );
+        let history = |from, to| async move {
+            list_changelist_chain_on(db, from, to, 100)
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|cl| cl.id)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(history(1, main_head).await, vec![commits[3], commits[0]]);
+        assert_eq!(
+            history(commits[1], dev_head).await,
+            vec![commits[2], commits[1]]
+        );
+        assert_eq!(history(commits[1], main_head).await, vec![commits[3]]);
     }
 
-    txn.commit().await?;
-    Ok(changelist_id)
-}
+    /// 分叉后的公共祖先是分叉点；合并之后以合并来源为新的公共祖先。
+    async fn merge_base_follows_merged_from() {
+        let db = crate::database::get();
+        let run = uuid::Uuid::new_v4().simple();
+        let (main, dev) = (format!("main-{run}"), format!("dev-{run}"));
+        let path = format!("//tests/merge/{run}/a.txt");
+        let commit = |branch_id: String, revision: i64| {
+            let path = path.clone();
+            async move {
+                commit_submit_on(
+                    db,
+                    Some(&branch_id),
+                    "alice",
+                    "merge base",
+                    0,
+                    serde_json::json!({}),
+                    vec![revision_input(&path, 1, revision)],
+                )
+                .await
+                .unwrap()
+            }
+        };
+        let insert_branch = |branch_id: String, head_changelist_id: i64| async move {
+            insert_branch_on(
+                db,
+                &BranchDoc {
+                    id: branch_id.clone(),
+                    created_at: 0,
+                    created_by: "alice".to_string(),
+                    head_changelist_id,
+                    is_protected: false,
+                    metadata: BranchMetadata {
+                        description: branch_id,
+                        owners: vec![],
+                        description_regex: None,
+                    },
+                },
+            )
+            .await
+            .unwrap();
+        };
 
-#[cfg(test)]
-mod dao_trait_tests {
-    use super::*;
+        insert_branch(main.clone(), 0).await;
+        let fork = commit(main.clone(), 1).await;
+        insert_branch(dev.clone(), fork).await;
+        let on_dev = commit(dev.clone(), 2).await;
+        let on_main = commit(main.clone(), 3).await;
+        assert_eq!(find_merge_base_on(db, on_dev, on_main).await.unwrap(), fork);
+        assert_eq!(find_merge_base_on(db, on_main, on_dev).await.unwrap(), fork);
+
+        let merged = merge_branches_on(
+            db,
+            &MergeInput {
+                source_branch_id: dev.clone(),
+                source_changelist_id: on_dev,
+                target_branch_id: main.clone(),
+                author: "alice".to_string(),
+                description: "merge".to_string(),
+                committed_at: 0,
+                revisions: vec![revision_input(&path, 1, 4)],
+            },
+        )
+        .await
+        .unwrap();
+        let next_on_dev = commit(dev.clone(), 5).await;
+        assert_eq!(
+            find_merge_base_on(db, next_on_dev, merged).await.unwrap(),
+            on_dev
+        );
+        assert_eq!(
+            find_merge_base_on(db, on_dev, merged).await.unwrap(),
+            on_dev
+        );
+    }
+
+    /// 批量写入 500 个 revision：每一行都落库一次，重复确保 files 行存在不会报错或产生重复行。
+    /// 在回滚的事务中执行。
+    async fn batch_insert_writes_every_row_once() {
+        let db = crate::database::get();
+        let changelist_id =
+            insert_changelist_on(db, "", "alice", "batch", 0, serde_json::json!({}))
+                .await
+                .unwrap();
+        let dir = format!("//tests/batch/{}/", uuid::Uuid::new_v4().simple());
+        let inputs: Vec<NewFileRevisionInput> = (0..500)
+            .map(|i| revision_input(&format!("{dir}f{i}.txt"), 1, 1))
+            .collect();
+
+        let txn = db.begin().await.unwrap();
+        ensure_files_exist_on(&txn, &inputs).await.unwrap();
+        ensure_files_exist_on(&txn, &inputs).await.unwrap();
+        insert_file_revisions_on(&txn, &inputs, changelist_id)
+            .await
+            .unwrap();
+
+        let prefix = ltree_key::depot_dir_or_wildcard_to_ltree_prefix(&dir).unwrap();
+        let files = "SELECT COUNT(*) AS count FROM files WHERE path <@ $1::ltree";
+        assert_eq!(count_rows(&txn, files, prefix.clone().into()).await, 500);
+        let revisions = "SELECT COUNT(*) AS count FROM file_revisions WHERE path <@ $1::ltree";
+        assert_eq!(count_rows(&txn, revisions, prefix.into()).await, 500);
+        let by_changelist = "SELECT COUNT(*) AS count FROM file_revisions WHERE changelist_id = $1";
+        assert_eq!(
+            count_rows(&txn, by_changelist, changelist_id.into()).await,
+            500
+        );
+        txn.rollback().await.unwrap();
+    }
+
+    async fn count_rows<C: ConnectionTrait>(conn: &C, sql: &str, value: sea_orm::Value) -> i64 {
+        conn.query_one(Statement::from_sql_and_values(
+            DatabaseBackend::Postgres,
+            sql,
+            [value],
+        ))
+        .await
+        .unwrap()
+        .unwrap()
+        .try_get::<i64>("", "count")
+        .unwrap()
+    }
 
     #[tokio::test]
-    async fn mock_dao_insert_and_find_user() {
-        // 注意：这是全局覆盖，测试尽量保持简单。
-        set_dao_for_tests(Arc::new(MockDao::default()));
+    async fn concurrent_changelist_ids_are_unique() {
+        let dao = Arc::new(MockDao::default());
+        let tasks: Vec<_> = (0..10)
+            .map(|_| {
+                let dao = dao.clone();
+                tokio::spawn(async move { dao.next_changelist_id().await.unwrap() })
+            })
+            .collect();
 
-        insert_user("alice", "hash").await.expect("insert user");
-        let u = find_user_by_username("alice")
+        let mut ids = HashSet::new();
+        for task in tasks {
+            assert!(ids.insert(task.await.unwrap()));
+        }
+        assert_eq!(ids.len(), 10);
+
+        // 之后创建的 changelist 不会复用已分配的 id
+        let id = dao
+            .insert_changelist("alice", "", 0, serde_json::json!({}))
             .await
-            .expect("find user")
-            .expect("user should exist");
-        assert_eq!(u.id, "alice");
-        assert_eq!(u.password, "hash");
+            .unwrap();
+        assert!(!ids.contains(&id));
     }
 }
@@ -0,0 +1,21 @@
+use sea_orm::entity::prelude::*;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+#[sea_orm(table_name = "audit_log")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = true)]
+    pub id: i64,
+    pub timestamp_millis: i64,
+    pub username: String,
+    /// `crate::audit::AuditOp` 的字符串形式
+    pub operation: String,
+    pub resource_id: String,
+    /// `crate::audit::AuditOutcome` 的字符串形式
+    pub outcome: String,
+    pub detail: Json,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -1,5 +1,5 @@
-use sea_orm::entity::prelude::*;
 use super::file_revisions;
+use sea_orm::entity::prelude::*;
 
 #[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
 #[sea_orm(table_name = "changelists")]
@@ -12,6 +12,10 @@ pub struct Model {
     pub description: String,
     pub committed_at: i64,
     pub metadata: Json,
+    /// changelist 提交到的分支，空字符串表示默认分支
+    pub branch_id: String,
+    /// 分支上的前一个 changelist，0 表示分支的第一个 changelist
+    pub parent_changelist_id: i64,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -0,0 +1,20 @@
+use sea_orm::entity::prelude::*;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+#[sea_orm(table_name = "file_locks")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub ticket: String,
+    /// 原始 depot path，不是 ltree key
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub path: String,
+    /// `read` 或 `write`
+    pub mode: String,
+    pub locked_generation: Option<i64>,
+    pub locked_revision: Option<i64>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,19 @@
+use sea_orm::entity::prelude::*;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+#[sea_orm(table_name = "login_failures")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub username: String,
+    /// 当前统计窗口内连续登录失败的次数
+    pub count: i32,
+    /// 统计窗口开始的时间（秒），即窗口内第一次失败的时间
+    pub window_started_at: i64,
+    /// 锁定截止时间（秒），为 None 或早于当前时间时未锁定
+    pub locked_until: Option<i64>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -1,5 +1,13 @@
+pub mod audit_log;
+pub mod branches;
 pub mod changelists;
+pub mod file_locks;
 pub mod file_revisions;
 pub mod files;
+pub mod login_failures;
+pub mod snapshots;
+pub mod submission_cache;
+pub mod submit_tickets;
 pub mod users;
-
+pub mod webhooks;
+pub mod workspaces;
@@ -6,11 +6,16 @@ pub struct Model {
     #[sea_orm(primary_key, auto_increment = false)]
     pub id: String,
     pub password: String,
+    /// 注册时间（毫秒时间戳）
+    pub created_at: i64,
+    pub email: Option<String>,
+    /// 显示名称，为空时使用用户名
+    pub display_name: String,
+    /// 账号来源：`password` 为注册的账号，`oauth2` 为 OAuth2 登录时创建的账号（没有密码）
+    pub source: String,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {}
 
 impl ActiveModelBehavior for ActiveModel {}
-
-
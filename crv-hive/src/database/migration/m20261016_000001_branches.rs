@@ -0,0 +1,52 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // 创建 branches 表，metadata 中保存描述与所有者（见 `crv_core::metadata::BranchMetadata`）
+        manager
+            .create_table(
+                Table::create()
+                    .table(Branches::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Branches::Id)
+                            .string()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(Branches::CreatedAt).big_integer().not_null())
+                    .col(ColumnDef::new(Branches::CreatedBy).string().not_null())
+                    .col(
+                        ColumnDef::new(Branches::HeadChangelistId)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(Branches::Metadata).json_binary().not_null())
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Branches::Table).if_exists().to_owned())
+            .await?;
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Branches {
+    Table,
+    Id,
+    CreatedAt,
+    CreatedBy,
+    HeadChangelistId,
+    Metadata,
+}
@@ -0,0 +1,44 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // 按时间点查询 changelist 时使用（见 `dao::find_latest_changelist_before`）
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_changelists_committed_at_id")
+                    .table(Changelists::Table)
+                    .col(Changelists::CommittedAt)
+                    .col(Changelists::Id)
+                    .if_not_exists()
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx_changelists_committed_at_id")
+                    .table(Changelists::Table)
+                    .if_exists()
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Changelists {
+    Table,
+    Id,
+    CommittedAt,
+}
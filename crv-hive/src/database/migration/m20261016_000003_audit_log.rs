@@ -0,0 +1,69 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // 创建 audit_log 表，记录所有修改类操作（见 `crate::audit`）
+        manager
+            .create_table(
+                Table::create()
+                    .table(AuditLog::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(AuditLog::Id)
+                            .big_integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(AuditLog::TimestampMillis)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(AuditLog::Username).string().not_null())
+                    .col(ColumnDef::new(AuditLog::Operation).string().not_null())
+                    .col(ColumnDef::new(AuditLog::ResourceId).string().not_null())
+                    .col(ColumnDef::new(AuditLog::Outcome).string().not_null())
+                    .col(ColumnDef::new(AuditLog::Detail).json_binary().not_null())
+                    .to_owned(),
+            )
+            .await?;
+
+        // ListAuditLog 按时间范围查询并按时间倒序返回
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_audit_log_timestamp_millis")
+                    .table(AuditLog::Table)
+                    .col(AuditLog::TimestampMillis)
+                    .if_not_exists()
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(AuditLog::Table).if_exists().to_owned())
+            .await?;
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum AuditLog {
+    Table,
+    Id,
+    TimestampMillis,
+    Username,
+    Operation,
+    ResourceId,
+    Outcome,
+    Detail,
+}
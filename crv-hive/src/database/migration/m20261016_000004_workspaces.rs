@@ -0,0 +1,57 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // 创建 workspaces 表，记录 edge 上创建的 workspace，名称全局唯一
+        manager
+            .create_table(
+                Table::create()
+                    .table(Workspaces::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Workspaces::Name)
+                            .string()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(Workspaces::Owner).string().not_null())
+                    .col(ColumnDef::new(Workspaces::RootPath).string().not_null())
+                    .col(ColumnDef::new(Workspaces::Mapping).text().not_null())
+                    .col(
+                        ColumnDef::new(Workspaces::CreatedAt)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(
+                Table::drop()
+                    .table(Workspaces::Table)
+                    .if_exists()
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Workspaces {
+    Table,
+    Name,
+    Owner,
+    RootPath,
+    Mapping,
+    CreatedAt,
+}
@@ -0,0 +1,44 @@
+use sea_orm::Statement;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // 按标签查询 changelist 时使用（见 `dao::find_changelists_by_label`）
+        manager
+            .get_connection()
+            .execute(Statement::from_string(
+                manager.get_database_backend(),
+                format!(
+                    "CREATE INDEX IF NOT EXISTS idx_changelists_metadata ON {} USING GIN ({} jsonb_path_ops)",
+                    Changelists::Table.to_string(),
+                    Changelists::Metadata.to_string(),
+                ),
+            ))
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx_changelists_metadata")
+                    .table(Changelists::Table)
+                    .if_exists()
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Changelists {
+    Table,
+    Metadata,
+}
@@ -0,0 +1,78 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // 创建 snapshots 表，记录将分支固定在某个 changelist 上的命名快照
+        manager
+            .create_table(
+                Table::create()
+                    .table(Snapshots::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Snapshots::Id)
+                            .big_integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(Snapshots::Name)
+                            .string()
+                            .not_null()
+                            .unique_key(),
+                    )
+                    .col(ColumnDef::new(Snapshots::BranchId).string().not_null())
+                    .col(
+                        ColumnDef::new(Snapshots::ChangelistId)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(Snapshots::CreatedBy).string().not_null())
+                    .col(
+                        ColumnDef::new(Snapshots::CreatedAt)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(Snapshots::Description).text().not_null())
+                    .to_owned(),
+            )
+            .await?;
+
+        // SquashChangelists 需要检查区间内的 changelist 是否被快照引用
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_snapshots_changelist_id")
+                    .table(Snapshots::Table)
+                    .col(Snapshots::ChangelistId)
+                    .if_not_exists()
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Snapshots::Table).if_exists().to_owned())
+            .await?;
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Snapshots {
+    Table,
+    Id,
+    Name,
+    BranchId,
+    ChangelistId,
+    CreatedBy,
+    CreatedAt,
+    Description,
+}
@@ -0,0 +1,75 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // 创建 submission_cache 表，记录已完成的提交，用于 Submit 按 request_id 去重
+        manager
+            .create_table(
+                Table::create()
+                    .table(SubmissionCache::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(SubmissionCache::RequestId)
+                            .string()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(SubmissionCache::ChangelistId)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(SubmissionCache::CommittedAt)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(SubmissionCache::LatestRevisions)
+                            .json_binary()
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // 过期记录按 committed_at 清理
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_submission_cache_committed_at")
+                    .table(SubmissionCache::Table)
+                    .col(SubmissionCache::CommittedAt)
+                    .if_not_exists()
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(
+                Table::drop()
+                    .table(SubmissionCache::Table)
+                    .if_exists()
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum SubmissionCache {
+    Table,
+    RequestId,
+    ChangelistId,
+    CommittedAt,
+    LatestRevisions,
+}
@@ -0,0 +1,44 @@
+use sea_orm::Statement;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // 按标签查询 file revision 时使用（见 `dao::find_file_revisions_by_tag`）
+        manager
+            .get_connection()
+            .execute(Statement::from_string(
+                manager.get_database_backend(),
+                format!(
+                    "CREATE INDEX IF NOT EXISTS idx_file_revisions_metadata ON {} USING GIN ({} jsonb_path_ops)",
+                    FileRevisions::Table.to_string(),
+                    FileRevisions::Metadata.to_string(),
+                ),
+            ))
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx_file_revisions_metadata")
+                    .table(FileRevisions::Table)
+                    .if_exists()
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum FileRevisions {
+    Table,
+    Metadata,
+}
@@ -0,0 +1,109 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // 创建 submit_tickets 表，记录进行中的提交，hive 重启后据此恢复 ticket
+        manager
+            .create_table(
+                Table::create()
+                    .table(SubmitTickets::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(SubmitTickets::Ticket)
+                            .string()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(SubmitTickets::SubmittingBy)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(SubmitTickets::ExpiresAt)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // 过期的 ticket 按 expires_at 清理
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_submit_tickets_expires_at")
+                    .table(SubmitTickets::Table)
+                    .col(SubmitTickets::ExpiresAt)
+                    .if_not_exists()
+                    .to_owned(),
+            )
+            .await?;
+
+        // 创建 file_locks 表，记录各 ticket 锁定的文件
+        manager
+            .create_table(
+                Table::create()
+                    .table(FileLocks::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(FileLocks::Ticket).string().not_null())
+                    .col(ColumnDef::new(FileLocks::Path).string().not_null())
+                    .col(ColumnDef::new(FileLocks::Mode).string().not_null())
+                    .col(ColumnDef::new(FileLocks::LockedGeneration).big_integer())
+                    .col(ColumnDef::new(FileLocks::LockedRevision).big_integer())
+                    .primary_key(Index::create().col(FileLocks::Ticket).col(FileLocks::Path))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_file_locks_path")
+                    .table(FileLocks::Table)
+                    .col(FileLocks::Path)
+                    .if_not_exists()
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(FileLocks::Table).if_exists().to_owned())
+            .await?;
+        manager
+            .drop_table(
+                Table::drop()
+                    .table(SubmitTickets::Table)
+                    .if_exists()
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum SubmitTickets {
+    Table,
+    Ticket,
+    SubmittingBy,
+    ExpiresAt,
+}
+
+#[derive(DeriveIden)]
+enum FileLocks {
+    Table,
+    Ticket,
+    Path,
+    Mode,
+    LockedGeneration,
+    LockedRevision,
+}
@@ -0,0 +1,47 @@
+use sea_orm::Statement;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // 之前提交的 revision 都没有压缩，补上 compressionType = "none"
+        manager
+            .get_connection()
+            .execute(Statement::from_string(
+                manager.get_database_backend(),
+                format!(
+                    r#"UPDATE {table} SET {metadata} = jsonb_set({metadata}, '{{compressionType}}', '"none"') WHERE NOT {metadata} ? 'compressionType'"#,
+                    table = FileRevisions::Table.to_string(),
+                    metadata = FileRevisions::Metadata.to_string(),
+                ),
+            ))
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // 只移除未压缩的记录，压缩过的 revision 仍然需要该字段才能被正确读取
+        manager
+            .get_connection()
+            .execute(Statement::from_string(
+                manager.get_database_backend(),
+                format!(
+                    r#"UPDATE {table} SET {metadata} = {metadata} - 'compressionType' WHERE {metadata} ->> 'compressionType' = 'none'"#,
+                    table = FileRevisions::Table.to_string(),
+                    metadata = FileRevisions::Metadata.to_string(),
+                ),
+            ))
+            .await?;
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum FileRevisions {
+    Table,
+    Metadata,
+}
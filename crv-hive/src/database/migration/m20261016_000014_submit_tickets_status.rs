@@ -0,0 +1,60 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // 提交结束后保留记录与状态，已有的记录都是进行中的提交
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(SubmitTickets::Table)
+                    .add_column_if_not_exists(
+                        ColumnDef::new(SubmitTickets::BranchId)
+                            .string()
+                            .not_null()
+                            .default(""),
+                    )
+                    .add_column_if_not_exists(
+                        ColumnDef::new(SubmitTickets::StartedAt)
+                            .big_integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .add_column_if_not_exists(
+                        ColumnDef::new(SubmitTickets::Status)
+                            .string()
+                            .not_null()
+                            .default("launched"),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(SubmitTickets::Table)
+                    .drop_column(SubmitTickets::BranchId)
+                    .drop_column(SubmitTickets::StartedAt)
+                    .drop_column(SubmitTickets::Status)
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum SubmitTickets {
+    Table,
+    BranchId,
+    StartedAt,
+    Status,
+}
@@ -0,0 +1,90 @@
+use sea_orm::Statement;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // changelist 所属的分支，空字符串表示默认分支
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Changelists::Table)
+                    .add_column_if_not_exists(
+                        ColumnDef::new(Changelists::BranchId)
+                            .string()
+                            .not_null()
+                            .default(""),
+                    )
+                    .add_column_if_not_exists(
+                        ColumnDef::new(Changelists::ParentChangelistId)
+                            .big_integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // 已有的 changelist 构成一条线性历史，父 changelist 就是前一个 changelist
+        manager
+            .get_connection()
+            .execute(Statement::from_string(
+                manager.get_database_backend(),
+                format!(
+                    "UPDATE {table} SET {parent} = COALESCE((SELECT MAX(p.{id}) FROM {table} p WHERE p.{id} < {table}.{id}), 0)",
+                    table = Changelists::Table.to_string(),
+                    parent = Changelists::ParentChangelistId.to_string(),
+                    id = Changelists::Id.to_string(),
+                ),
+            ))
+            .await?;
+
+        // 查询默认分支的 HEAD 时使用（见 `dao::find_branch_head`）
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_changelists_branch_id_id")
+                    .table(Changelists::Table)
+                    .col(Changelists::BranchId)
+                    .col(Changelists::Id)
+                    .if_not_exists()
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx_changelists_branch_id_id")
+                    .table(Changelists::Table)
+                    .if_exists()
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Changelists::Table)
+                    .drop_column(Changelists::ParentChangelistId)
+                    .drop_column(Changelists::BranchId)
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Changelists {
+    Table,
+    Id,
+    BranchId,
+    ParentChangelistId,
+}
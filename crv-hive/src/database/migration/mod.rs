@@ -1,6 +1,24 @@
 use sea_orm_migration::prelude::*;
 
 mod m20251224_000001_init;
+mod m20261016_000001_branches;
+mod m20261016_000002_changelists_committed_at;
+mod m20261016_000003_audit_log;
+mod m20261016_000004_workspaces;
+mod m20261016_000005_users_created_at;
+mod m20261016_000006_changelists_metadata_index;
+mod m20261016_000007_webhooks;
+mod m20261016_000008_snapshots;
+mod m20261016_000009_submission_cache;
+mod m20261016_000010_users_profile;
+mod m20261016_000011_file_revisions_metadata_index;
+mod m20261016_000012_submit_tickets;
+mod m20261016_000013_file_revisions_compression_type;
+mod m20261016_000014_submit_tickets_status;
+mod m20261016_000015_login_failures;
+mod m20261016_000016_branches_is_protected;
+mod m20261016_000017_users_source;
+mod m20261016_000018_changelists_parent;
 
 pub struct Migrator;
 
@@ -9,8 +27,24 @@ impl MigratorTrait for Migrator {
     fn migrations() -> Vec<Box<dyn MigrationTrait>> {
         vec![
             Box::new(m20251224_000001_init::Migration),
+            Box::new(m20261016_000001_branches::Migration),
+            Box::new(m20261016_000002_changelists_committed_at::Migration),
+            Box::new(m20261016_000003_audit_log::Migration),
+            Box::new(m20261016_000004_workspaces::Migration),
+            Box::new(m20261016_000005_users_created_at::Migration),
+            Box::new(m20261016_000006_changelists_metadata_index::Migration),
+            Box::new(m20261016_000007_webhooks::Migration),
+            Box::new(m20261016_000008_snapshots::Migration),
+            Box::new(m20261016_000009_submission_cache::Migration),
+            Box::new(m20261016_000010_users_profile::Migration),
+            Box::new(m20261016_000011_file_revisions_metadata_index::Migration),
+            Box::new(m20261016_000012_submit_tickets::Migration),
+            Box::new(m20261016_000013_file_revisions_compression_type::Migration),
+            Box::new(m20261016_000014_submit_tickets_status::Migration),
+            Box::new(m20261016_000015_login_failures::Migration),
+            Box::new(m20261016_000016_branches_is_protected::Migration),
+            Box::new(m20261016_000017_users_source::Migration),
+            Box::new(m20261016_000018_changelists_parent::Migration),
         ]
     }
 }
-
-
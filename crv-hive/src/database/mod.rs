@@ -6,13 +6,79 @@ pub mod service;
 
 use anyhow::Result;
 use once_cell::sync::OnceCell;
-use urlencoding::encode;
 use sea_orm::{ConnectionTrait, Database, DatabaseConnection};
 use sea_orm_migration::MigratorTrait;
+use urlencoding::encode;
 
-use crate::config::{entity::ConfigEntity, holder::get_or_init_config};
+use crate::config::{
+    entity::{ConfigEntity, ReadPreference},
+    holder::get_or_init_config,
+};
+
+/// 主库与只读副本的连接池，未使用只读副本时 `read` 与 `write` 是同一个连接池
+pub struct DualConnection {
+    pub read: DatabaseConnection,
+    pub write: DatabaseConnection,
+}
 
-static DB_CONN: OnceCell<DatabaseConnection> = OnceCell::new();
+static DB_CONN: OnceCell<DualConnection> = OnceCell::new();
+
+/// 覆盖数据库连接串的环境变量，优先级高于配置文件
+pub const DATABASE_URL_ENV: &str = "CRV_DATABASE_URL";
+
+/// 依次使用环境变量、配置中的 `database_url`、由 postgres_* 字段拼接的连接串
+fn connection_url(config: &ConfigEntity) -> Result<String> {
+    let url = std::env::var(DATABASE_URL_ENV)
+        .ok()
+        .filter(|url| !url.is_empty())
+        .or_else(|| config.database_url.clone());
+    let Some(url) = url else {
+        return Ok(postgres_connection_url(config));
+    };
+    // 连接串可能包含密码，错误信息中不输出完整内容
+    if !url.starts_with("postgres://") && !url.starts_with("postgresql://") {
+        anyhow::bail!("invalid database url: must start with postgres:// or postgresql://");
+    }
+    Ok(url)
+}
+
+/// `secondary_preferred` 时只读副本的连接串，其余情况返回 None
+fn read_connection_url(config: &ConfigEntity) -> Result<Option<String>> {
+    if config.database_read_preference != ReadPreference::SecondaryPreferred {
+        return Ok(None);
+    }
+    let Some(url) = config
+        .database_read_url
+        .clone()
+        .filter(|url| !url.is_empty())
+    else {
+        tracing::warn!("database_read_url is not set, read-only queries use the primary");
+        return Ok(None);
+    };
+    if !url.starts_with("postgres://") && !url.starts_with("postgresql://") {
+        anyhow::bail!("invalid database read url: must start with postgres:// or postgresql://");
+    }
+    Ok(Some(url))
+}
+
+/// 连接只读副本，连接失败时退回主库，不影响 hive 启动
+async fn connect_read_replica(
+    config: &ConfigEntity,
+    write: &DatabaseConnection,
+) -> Result<DatabaseConnection> {
+    let Some(url) = read_connection_url(config)? else {
+        return Ok(write.clone());
+    };
+    match Database::connect(&url).await {
+        Ok(conn) => Ok(conn),
+        Err(e) => {
+            tracing::warn!(
+                "failed to connect to read replica, read-only queries use the primary: {e}"
+            );
+            Ok(write.clone())
+        }
+    }
+}
 
 fn postgres_connection_url(config: &ConfigEntity) -> String {
     let username = encode(&config.postgres_username);
@@ -29,8 +95,16 @@ fn postgres_connection_url(config: &ConfigEntity) -> String {
 }
 
 pub async fn init() -> Result<()> {
-    let config = get_or_init_config();
-    let conn = Database::connect(&postgres_connection_url(config)).await?;
+    init_from_config(get_or_init_config()).await
+}
+
+/// 使用指定的配置连接数据库并执行 migration
+pub async fn init_from_config(config: &ConfigEntity) -> Result<()> {
+    let mut conn = Database::connect(&connection_url(config)?).await?;
+    let mut read = connect_read_replica(config, &conn).await?;
+    for c in [&mut conn, &mut read] {
+        c.set_metric_callback(|info| crate::metrics::metrics().observe_db_query(info.elapsed));
+    }
 
     // 使用 advisory lock 串行化 migration，避免多进程并发导致扩展/类型冲突
     let _ = conn
@@ -53,7 +127,7 @@ pub async fn init() -> Result<()> {
     migrate_result?;
 
     DB_CONN
-        .set(conn)
+        .set(DualConnection { read, write: conn })
         .map_err(|_| anyhow::anyhow!("Database already initialized"))?;
 
     Ok(())
@@ -63,16 +137,80 @@ pub async fn shutdown() -> Result<()> {
     let conn = DB_CONN
         .get()
         .ok_or(anyhow::anyhow!("Database not initialized"))?;
-    conn.clone().close().await?;
+    // 未使用只读副本时两者是同一个连接池，重复关闭没有影响
+    conn.read.clone().close().await?;
+    conn.write.clone().close().await?;
     Ok(())
 }
 
-/// 获取数据库连接（全局单例）
+/// 获取主库连接（全局单例）
 pub fn get() -> &'static DatabaseConnection {
-    DB_CONN.get().expect("Database not initialized")
+    &DB_CONN.get().expect("Database not initialized").write
 }
 
-/// 获取数据库连接（可选）
+/// 获取主库连接（可选）
 pub fn try_get() -> Option<&'static DatabaseConnection> {
-    DB_CONN.get()
+    DB_CONN.get().map(|conn| &conn.write)
+}
+
+/// 获取只读查询使用的连接（可选），未配置只读副本时就是主库连接
+pub fn try_get_read() -> Option<&'static DatabaseConnection> {
+    DB_CONN.get().map(|conn| &conn.read)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn database_url_env_overrides_config() {
+        let mut config = ConfigEntity::default();
+        assert!(
+            connection_url(&config)
+                .unwrap()
+                .starts_with("postgresql://postgres:postgres@")
+        );
+
+        config.database_url = Some("postgres://alice@db.example.com/crv".to_string());
+        assert_eq!(
+            connection_url(&config).unwrap(),
+            "postgres://alice@db.example.com/crv"
+        );
+
+        // 环境变量为进程级别，所有相关断言放在同一个测试中
+        unsafe { std::env::set_var(DATABASE_URL_ENV, "postgresql://bob@127.0.0.1:6543/test") };
+        let url = connection_url(&config);
+
+        unsafe { std::env::set_var(DATABASE_URL_ENV, "mongodb://127.0.0.1:27017") };
+        let invalid = connection_url(&config);
+        // init 使用环境变量中的连接串，无效时在连接数据库之前报错
+        let init_err = init_from_config(&config).await.unwrap_err();
+        unsafe { std::env::remove_var(DATABASE_URL_ENV) };
+
+        assert_eq!(url.unwrap(), "postgresql://bob@127.0.0.1:6543/test");
+        assert!(invalid.is_err());
+        assert!(init_err.to_string().contains("invalid database url"));
+        assert!(!init_err.to_string().contains("27017"));
+    }
+
+    #[test]
+    fn read_replica_is_only_used_when_secondary_preferred() {
+        let mut config = ConfigEntity::default();
+        config.database_read_url = Some("postgres://replica.example.com/crv".to_string());
+        assert_eq!(read_connection_url(&config).unwrap(), None);
+
+        config.database_read_preference = ReadPreference::SecondaryPreferred;
+        assert_eq!(
+            read_connection_url(&config).unwrap().as_deref(),
+            Some("postgres://replica.example.com/crv")
+        );
+
+        config.database_read_url = None;
+        assert_eq!(read_connection_url(&config).unwrap(), None);
+
+        config.database_read_url = Some("mongodb://127.0.0.1:27017".to_string());
+        let err = read_connection_url(&config).unwrap_err();
+        assert!(err.to_string().contains("invalid database read url"));
+        assert!(!err.to_string().contains("27017"));
+    }
 }
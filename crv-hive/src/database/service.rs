@@ -1,6 +1,6 @@
 use crate::common::depot_path::DepotPath;
 use crate::database::{
-    dao::DaoError,
+    dao::{DEFAULT_BRANCH_HEAD_SQL, DaoError, changelist_chain_cte},
     entities::{changelists, file_revisions},
     ltree_key,
 };
@@ -10,7 +10,6 @@ use sea_orm::{
 };
 use std::collections::HashMap;
 
-
 fn db() -> Result<&'static sea_orm::DatabaseConnection, DaoError> {
     crate::database::try_get().ok_or(DaoError::DatabaseNotInitialized)
 }
@@ -124,7 +123,8 @@ fn ltree_depth(prefix: &str) -> i64 {
     }
 }
 
-/// 获取指定 depot path 的文件树（按 changelist_id 截止）。
+/// 获取指定 depot path 在 `changelist_id` 处的文件树，只包含该 changelist 所在的 changelist 链上的 revision；
+/// `changelist_id <= 0` 表示默认分支的 HEAD。
 ///
 /// - 文件路径：返回该文件最新 revision（若存在）。
 /// - 目录路径：仅返回该目录“直接子级文件”的最新 revision。
@@ -136,26 +136,35 @@ pub async fn get_file_tree_revisions(
     let txn = db()?.begin().await?;
 
     let changelist_id_value = changelist_id;
+    let chain = |param: &str| {
+        changelist_chain_cte(&format!(
+            "CASE WHEN {param}::bigint > 0 THEN {param} ELSE {DEFAULT_BRANCH_HEAD_SQL} END"
+        ))
+    };
     let models = if depot.is_file() {
         let key = ltree_key::depot_path_str_to_ltree_key(&depot.to_string())?;
         let stmt = Statement::from_sql_and_values(
             DatabaseBackend::Postgres,
-            r#"
-            SELECT DISTINCT ON (path)
-                path::text AS path,
-                generation,
-                revision,
-                changelist_id,
-                binary_id,
-                size,
-                is_delete,
-                created_at,
-                metadata
-            FROM file_revisions
-            WHERE path = $1::ltree
-              AND ($2::bigint <= 0 OR changelist_id <= $2)
-            ORDER BY path, generation DESC, revision DESC, changelist_id DESC
-            "#,
+            format!(
+                r#"
+                {}
+                SELECT DISTINCT ON (path)
+                    path::text AS path,
+                    generation,
+                    revision,
+                    changelist_id,
+                    binary_id,
+                    size,
+                    is_delete,
+                    created_at,
+                    metadata
+                FROM file_revisions
+                WHERE path = $1::ltree
+                  AND changelist_id IN (SELECT id FROM chain)
+                ORDER BY path, generation DESC, revision DESC, changelist_id DESC
+                "#,
+                chain("$2")
+            ),
             [key.into(), changelist_id_value.into()].to_vec(),
         );
         file_revisions::Entity::find()
@@ -167,35 +176,9 @@ pub async fn get_file_tree_revisions(
         let depth = ltree_depth(&prefix) + 1;
         let stmt = Statement::from_sql_and_values(
             DatabaseBackend::Postgres,
-            r#"
-            SELECT DISTINCT ON (path)
-                path::text AS path,
-                generation,
-                revision,
-                changelist_id,
-                binary_id,
-                size,
-                is_delete,
-                created_at,
-                metadata
-            FROM file_revisions
-            WHERE path <@ $1::ltree
-              AND ($2::bigint <= 0 OR changelist_id <= $2)
-              AND nlevel(path) = $3
-            ORDER BY path, generation DESC, revision DESC, changelist_id DESC
-            "#,
-            [prefix.into(), changelist_id_value.into(), depth.into()].to_vec(),
-        );
-        file_revisions::Entity::find()
-            .from_raw_sql(stmt)
-            .all(&txn)
-            .await?
-    } else {
-        let prefix = depot_dir_or_wildcard_to_ltree_prefix(&depot.to_string())?;
-        if prefix.is_empty() {
-            let stmt = Statement::from_sql_and_values(
-                DatabaseBackend::Postgres,
+            format!(
                 r#"
+                {}
                 SELECT DISTINCT ON (path)
                     path::text AS path,
                     generation,
@@ -207,9 +190,43 @@ pub async fn get_file_tree_revisions(
                     created_at,
                     metadata
                 FROM file_revisions
-                WHERE ($1::bigint <= 0 OR changelist_id <= $1)
+                WHERE path <@ $1::ltree
+                  AND changelist_id IN (SELECT id FROM chain)
+                  AND nlevel(path) = $3
                 ORDER BY path, generation DESC, revision DESC, changelist_id DESC
                 "#,
+                chain("$2")
+            ),
+            [prefix.into(), changelist_id_value.into(), depth.into()].to_vec(),
+        );
+        file_revisions::Entity::find()
+            .from_raw_sql(stmt)
+            .all(&txn)
+            .await?
+    } else {
+        let prefix = depot_dir_or_wildcard_to_ltree_prefix(&depot.to_string())?;
+        if prefix.is_empty() {
+            let stmt = Statement::from_sql_and_values(
+                DatabaseBackend::Postgres,
+                format!(
+                    r#"
+                    {}
+                    SELECT DISTINCT ON (path)
+                        path::text AS path,
+                        generation,
+                        revision,
+                        changelist_id,
+                        binary_id,
+                        size,
+                        is_delete,
+                        created_at,
+                        metadata
+                    FROM file_revisions
+                    WHERE changelist_id IN (SELECT id FROM chain)
+                    ORDER BY path, generation DESC, revision DESC, changelist_id DESC
+                    "#,
+                    chain("$1")
+                ),
                 [changelist_id_value.into()].to_vec(),
             );
             let models = file_revisions::Entity::find()
@@ -221,22 +238,26 @@ pub async fn get_file_tree_revisions(
         }
         let stmt = Statement::from_sql_and_values(
             DatabaseBackend::Postgres,
-            r#"
-            SELECT DISTINCT ON (path)
-                path::text AS path,
-                generation,
-                revision,
-                changelist_id,
-                binary_id,
-                size,
-                is_delete,
-                created_at,
-                metadata
-            FROM file_revisions
-            WHERE path <@ $1::ltree
-              AND ($2::bigint <= 0 OR changelist_id <= $2)
-            ORDER BY path, generation DESC, revision DESC, changelist_id DESC
-            "#,
+            format!(
+                r#"
+                {}
+                SELECT DISTINCT ON (path)
+                    path::text AS path,
+                    generation,
+                    revision,
+                    changelist_id,
+                    binary_id,
+                    size,
+                    is_delete,
+                    created_at,
+                    metadata
+                FROM file_revisions
+                WHERE path <@ $1::ltree
+                  AND changelist_id IN (SELECT id FROM chain)
+                ORDER BY path, generation DESC, revision DESC, changelist_id DESC
+                "#,
+                chain("$2")
+            ),
             [prefix.into(), changelist_id_value.into()].to_vec(),
         );
         file_revisions::Entity::find()
@@ -247,4 +268,4 @@ pub async fn get_file_tree_revisions(
 
     txn.commit().await?;
     Ok(models)
-}
\ No newline at end of file
+}
@@ -0,0 +1,268 @@
+use crate::audit::{AuditOp, AuditQuery};
+use crate::auth::{UserContext, require_user};
+use crate::database::dao::{Dao, read_dao};
+use crate::logging::HiveLog;
+use crate::pb::{AuditLogEntry, ListAuditLogReq, ListAuditLogRsp};
+use tonic::{Request, Response, Status};
+
+/// 未指定 limit 时最多返回的条数
+const DEFAULT_LIMIT: u32 = 100;
+/// 单次查询最多返回的条数
+const MAX_LIMIT: u32 = 1000;
+
+pub async fn handle_list_audit_log(
+    log: HiveLog,
+    r: Request<ListAuditLogReq>,
+) -> Result<Response<ListAuditLogRsp>, Status> {
+    let user = require_user(&r)?.clone();
+    let log = log.with_user(&user.username);
+    let _g = log.enter();
+
+    let request = r.into_inner();
+    log.info(&format!(
+        "list_audit_log received: since={}, until={}, username={}, operation={}",
+        request.since_millis, request.until_millis, request.username, request.operation
+    ));
+
+    let rsp = list_audit_log(read_dao().as_ref(), &user, request).await?;
+    Ok(Response::new(rsp))
+}
+
+/// 查询审计日志，仅管理员可用。
+pub(crate) async fn list_audit_log(
+    dao: &dyn Dao,
+    user: &UserContext,
+    request: ListAuditLogReq,
+) -> Result<ListAuditLogRsp, Status> {
+    if !user.is_admin() {
+        return Err(Status::permission_denied(
+            "only admins can list the audit log",
+        ));
+    }
+
+    let operation = request.operation.trim();
+    let operation = if operation.is_empty() {
+        None
+    } else {
+        Some(AuditOp::parse(operation).ok_or_else(|| {
+            Status::invalid_argument(format!("unknown audit operation '{operation}'"))
+        })?)
+    };
+    let username = request.username.trim();
+
+    let query = AuditQuery {
+        since_millis: (request.since_millis != 0).then_some(request.since_millis),
+        until_millis: (request.until_millis != 0).then_some(request.until_millis),
+        username: (!username.is_empty()).then(|| username.to_string()),
+        operation,
+        limit: match request.limit {
+            0 => DEFAULT_LIMIT,
+            limit => limit.min(MAX_LIMIT),
+        } as u64,
+    };
+
+    let events = dao
+        .list_audit_events(&query)
+        .await
+        .map_err(|e| Status::internal(format!("database error while listing audit log: {e}")))?;
+
+    Ok(ListAuditLogRsp {
+        entries: events
+            .into_iter()
+            .map(|e| AuditLogEntry {
+                timestamp_millis: e.timestamp_millis,
+                username: e.username,
+                operation: e.operation.as_str().to_string(),
+                resource_id: e.resource_id,
+                outcome: e.outcome.as_str().to_string(),
+                detail_json: e.detail.to_string(),
+            })
+            .collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audit::{AuditEvent, AuditOutcome};
+    use crate::auth::{ADMIN_SCOPE, AuthService, AuthSource, TokenPolicy};
+    use crate::config::{entity::ConfigEntity, holder::try_set_config};
+    use crate::database::dao::MockDao;
+    use crate::hive_server::CrvHiveService;
+    use crate::pb::hive_service_server::HiveService;
+    use crate::pb::{FileChunk, FileToLock, LaunchSubmitReq, LockMode, SubmitReq};
+    use crv_core::repository::{blake3_hash_to_hex, compute_chunk_hash};
+    use std::sync::{Arc, OnceLock};
+    use std::time::Duration;
+    use tonic::Code;
+
+    static TEST_DIR: OnceLock<tempfile::TempDir> = OnceLock::new();
+
+    fn user(username: &str, scopes: &[&str]) -> UserContext {
+        UserContext {
+            username: username.to_string(),
+            scopes: scopes.iter().map(|x| x.to_string()).collect(),
+            source: AuthSource::Jwt,
+        }
+    }
+
+    fn event(timestamp_millis: i64, username: &str, operation: AuditOp) -> AuditEvent {
+        AuditEvent {
+            timestamp_millis,
+            username: username.to_string(),
+            operation,
+            resource_id: timestamp_millis.to_string(),
+            outcome: AuditOutcome::Success,
+            detail: serde_json::json!({}),
+        }
+    }
+
+    #[tokio::test]
+    async fn list_audit_log_requires_admin() {
+        let dao = MockDao::default();
+        let err = list_audit_log(&dao, &user("alice", &[]), ListAuditLogReq::default())
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::PermissionDenied);
+    }
+
+    #[tokio::test]
+    async fn list_audit_log_applies_filters() {
+        let dao = MockDao::default();
+        for e in [
+            event(1_000, "alice", AuditOp::Login),
+            event(2_000, "alice", AuditOp::Submit),
+            event(3_000, "bob", AuditOp::Submit),
+            event(4_000, "alice", AuditOp::Submit),
+        ] {
+            dao.insert_audit_event(&e).await.unwrap();
+        }
+        let admin = user("root", &[ADMIN_SCOPE]);
+
+        let rsp = list_audit_log(
+            &dao,
+            &admin,
+            ListAuditLogReq {
+                since_millis: 1_500,
+                until_millis: 4_000,
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+        let timestamps: Vec<i64> = rsp.entries.iter().map(|e| e.timestamp_millis).collect();
+        assert_eq!(timestamps, vec![3_000, 2_000]);
+
+        let rsp = list_audit_log(
+            &dao,
+            &admin,
+            ListAuditLogReq {
+                username: "alice".to_string(),
+                operation: "submit".to_string(),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+        let timestamps: Vec<i64> = rsp.entries.iter().map(|e| e.timestamp_millis).collect();
+        assert_eq!(timestamps, vec![4_000, 2_000]);
+
+        let err = list_audit_log(
+            &dao,
+            &admin,
+            ListAuditLogReq {
+                operation: "delete".to_string(),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(err.code(), Code::InvalidArgument);
+    }
+
+    #[tokio::test]
+    async fn submit_is_recorded_in_audit_log() {
+        TEST_DIR.get_or_init(|| {
+            let dir = tempfile::tempdir().expect("create temp dir");
+            let mut cfg = ConfigEntity::default();
+            cfg.repository_path = dir.path().join("repo").to_string_lossy().into_owned();
+            cfg.upload_cache_path = dir.path().join("cache").to_string_lossy().into_owned();
+            let _ = try_set_config(cfg);
+            dir
+        });
+        let dao = crate::test_support::install_mock_dao();
+        let service = CrvHiveService::new(Arc::new(AuthService::new(
+            b"test-secret",
+            TokenPolicy::default(),
+        )));
+
+        let path = format!("//tests/audit/{}/a.txt", uuid::Uuid::new_v4());
+        let rsp = service
+            .launch_submit(Request::new(LaunchSubmitReq {
+                files: vec![FileToLock {
+                    path: path.clone(),
+                    expected_file_generation: None,
+                    expected_file_revision: None,
+                    mode: LockMode::Write as i32,
+                }],
+                branch_id: String::new(),
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(rsp.success);
+
+        let data = format!("audit {path}");
+        let chunk_hash = blake3_hash_to_hex(&compute_chunk_hash(data.as_bytes()));
+        crate::hive_server::submit::cache_service()
+            .append_chunk_part(&chunk_hash, 0, data.as_bytes())
+            .unwrap();
+
+        let rsp = service
+            .submit(Request::new(SubmitReq {
+                ticket: rsp.ticket,
+                description: "audit".to_string(),
+                file_chunks: vec![FileChunk {
+                    path,
+                    binary_id: vec![chunk_hash],
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(rsp.success, "submit failed: {}", rsp.message);
+
+        // 审计记录是异步写入的
+        let changelist_id = rsp.changelist_id.to_string();
+        let admin = user("root", &[ADMIN_SCOPE]);
+        let mut entry = None;
+        for _ in 0..50 {
+            let rsp = list_audit_log(
+                dao.as_ref(),
+                &admin,
+                ListAuditLogReq {
+                    operation: "submit".to_string(),
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+            entry = rsp
+                .entries
+                .into_iter()
+                .find(|e| e.resource_id == changelist_id);
+            if entry.is_some() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+
+        let entry = entry.expect("submit should be recorded in audit log");
+        assert_eq!(entry.username, "admin");
+        assert_eq!(entry.outcome, "success");
+        let detail: serde_json::Value = serde_json::from_str(&entry.detail_json).unwrap();
+        assert_eq!(detail["files"], 1);
+    }
+}
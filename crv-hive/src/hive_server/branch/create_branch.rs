@@ -0,0 +1,484 @@
+use crate::auth::{UserContext, require_user};
+use crate::database::dao::{Dao, dao};
+use crate::hive_server::branch::set_protection::check_branch_owner;
+use crate::logging::HiveLog;
+use crate::pb::{CreateBranchReq, CreateBranchRsp};
+use crv_core::metadata::{BranchDoc, BranchMetadata};
+use tonic::{Request, Response, Status};
+
+pub async fn handle_create_branch(
+    log: HiveLog,
+    r: Request<CreateBranchReq>,
+) -> Result<Response<CreateBranchRsp>, Status> {
+    let user = require_user(&r)?.clone();
+    let log = log.with_user(&user.username);
+    let _g = log.enter();
+
+    let request = r.into_inner();
+    log.info(&format!(
+        "create_branch received: branch={}, base={}, at_cl={}",
+        request.branch_id, request.base_branch_id, request.at_changelist_id
+    ));
+
+    let rsp = create_branch(
+        dao().as_ref(),
+        &user,
+        request,
+        chrono::Utc::now().timestamp_millis(),
+    )
+    .await?;
+    Ok(Response::new(rsp))
+}
+
+/// 创建分支的业务逻辑，新分支的 HEAD 默认与基准分支当前的 HEAD 相同。
+///
+/// - 指定 `at_changelist_id` 时从基准分支的历史 changelist 分叉；
+/// - 基于受保护分支（有所有者的分支）创建时，要求当前用户是其所有者或管理员；
+/// - 不指定基准分支时创建根分支，仅管理员可用；
+/// - 未指定所有者时，创建者为唯一所有者。
+pub(crate) async fn create_branch(
+    dao: &dyn Dao,
+    user: &UserContext,
+    request: CreateBranchReq,
+    now: i64,
+) -> Result<CreateBranchRsp, Status> {
+    let branch_id = request.branch_id.trim();
+    if branch_id.is_empty() {
+        return Err(Status::invalid_argument("branch_id is required"));
+    }
+
+    if request.at_changelist_id < 0 {
+        return Err(Status::invalid_argument(
+            "at_changelist_id must not be negative",
+        ));
+    }
+
+    let base_branch_id = request.base_branch_id.trim();
+    let head_changelist_id = if base_branch_id.is_empty() {
+        if request.at_changelist_id > 0 {
+            return Err(Status::invalid_argument(
+                "at_changelist_id requires base_branch_id",
+            ));
+        }
+        if !user.is_admin() {
+            return Err(Status::permission_denied(
+                "only admins can create a branch without base branch",
+            ));
+        }
+        0
+    } else {
+        let base = dao
+            .find_branch_by_id(base_branch_id)
+            .await
+            .map_err(|e| Status::internal(format!("database error while finding branch: {e}")))?
+            .ok_or_else(|| {
+                Status::not_found(format!("base branch '{base_branch_id}' not found"))
+            })?;
+        check_branch_owner(&base, user)?;
+        if request.at_changelist_id == 0 {
+            base.head_changelist_id
+        } else {
+            changelist_on_branch(
+                dao,
+                base_branch_id,
+                base.head_changelist_id,
+                request.at_changelist_id,
+            )
+            .await?
+        }
+    };
+
+    match dao.find_branch_by_id(branch_id).await {
+        Ok(Some(_)) => {
+            return Ok(CreateBranchRsp {
+                success: false,
+                message: "branch already exists".to_string(),
+            });
+        }
+        Ok(None) => {}
+        Err(e) => {
+            return Err(Status::internal(format!(
+                "database error while finding branch: {e}"
+            )));
+        }
+    }
+
+    let mut owners: Vec<String> = vec![];
+    for owner in request.owners.iter().map(|x| x.trim()) {
+        if !owner.is_empty() && !owners.iter().any(|x| x == owner) {
+            owners.push(owner.to_string());
+        }
+    }
+    if owners.is_empty() {
+        owners.push(user.username.clone());
+    }
+
+    let branch = BranchDoc {
+        id: branch_id.to_string(),
+        created_at: now,
+        created_by: user.username.clone(),
+        head_changelist_id,
+        is_protected: false,
+        metadata: BranchMetadata {
+            description: request.description,
+            owners,
+            description_regex: None,
+        },
+    };
+    dao.insert_branch(&branch)
+        .await
+        .map_err(|e| Status::internal(format!("database error while inserting branch: {e}")))?;
+
+    Ok(CreateBranchRsp {
+        success: true,
+        message: String::from("created"),
+    })
+}
+
+/// 检查 changelist 存在且位于分支 HEAD 的历史中。
+///
+/// 分支的历史是从 HEAD 沿 parent 回溯的 changelist 链，其他分支在这之间的提交不在链上；
+/// 新分支以该 changelist 为 HEAD，之后的提交接在它后面，文件树也沿这条链解析。
+async fn changelist_on_branch(
+    dao: &dyn Dao,
+    branch_id: &str,
+    head_changelist_id: i64,
+    changelist_id: i64,
+) -> Result<i64, Status> {
+    dao.find_changelist_by_id(changelist_id)
+        .await
+        .map_err(|e| Status::internal(format!("database error while finding changelist: {e}")))?
+        .ok_or_else(|| Status::not_found(format!("changelist {changelist_id} not found")))?;
+    let on_branch = dao
+        .is_changelist_on_chain(head_changelist_id, changelist_id)
+        .await
+        .map_err(|e| Status::internal(format!("database error while walking history: {e}")))?;
+    if !on_branch {
+        return Err(Status::failed_precondition(format!(
+            "changelist {changelist_id} is not on branch '{branch_id}' (HEAD is {head_changelist_id})"
+        )));
+    }
+    Ok(changelist_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::{ADMIN_SCOPE, AuthSource};
+    use crate::common::depot_path::DepotPath;
+    use crate::database::dao::{MockDao, NewFileRevisionInput};
+    use crate::hive_server::branch::get_branch_diff::{get_branch_diff, snapshot_at};
+    use crate::pb::GetBranchDiffReq;
+    use tonic::Code;
+
+    fn user(username: &str, scopes: &[&str]) -> UserContext {
+        UserContext {
+            username: username.to_string(),
+            scopes: scopes.iter().map(|x| x.to_string()).collect(),
+            source: AuthSource::Jwt,
+        }
+    }
+
+    fn request(branch_id: &str, base_branch_id: &str, owners: &[&str]) -> CreateBranchReq {
+        CreateBranchReq {
+            branch_id: branch_id.to_string(),
+            description: format!("{branch_id} branch"),
+            base_branch_id: base_branch_id.to_string(),
+            owners: owners.iter().map(|x| x.to_string()).collect(),
+            at_changelist_id: 0,
+        }
+    }
+
+    async fn dao_with_main() -> MockDao {
+        let dao = MockDao::default();
+        dao.insert_branch(&BranchDoc {
+            id: "main".to_string(),
+            created_at: 0,
+            created_by: "alice".to_string(),
+            head_changelist_id: 42,
+            is_protected: false,
+            metadata: BranchMetadata {
+                description: "main".to_string(),
+                owners: vec!["alice".to_string()],
+                description_regex: None,
+            },
+        })
+        .await
+        .unwrap();
+        dao
+    }
+
+    #[tokio::test]
+    async fn non_owner_cannot_branch_off_protected_branch() {
+        let dao = dao_with_main().await;
+
+        let err = create_branch(&dao, &user("bob", &[]), request("dev", "main", &[]), 1)
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::PermissionDenied);
+        assert!(dao.find_branch_by_id("dev").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn owner_and_admin_can_branch_off_protected_branch() {
+        let dao = dao_with_main().await;
+
+        let rsp = create_branch(&dao, &user("alice", &[]), request("dev", "main", &[]), 1)
+            .await
+            .unwrap();
+        assert!(rsp.success);
+        let dev = dao.find_branch_by_id("dev").await.unwrap().unwrap();
+        assert_eq!(dev.head_changelist_id, 42);
+        assert_eq!(dev.created_by, "alice");
+        assert_eq!(dev.metadata.owners, vec!["alice"]);
+
+        let rsp = create_branch(
+            &dao,
+            &user("carol", &[ADMIN_SCOPE]),
+            request("release", "main", &["dave", "erin", "dave"]),
+            2,
+        )
+        .await
+        .unwrap();
+        assert!(rsp.success);
+        let release = dao.find_branch_by_id("release").await.unwrap().unwrap();
+        assert_eq!(release.metadata.owners, vec!["dave", "erin"]);
+
+        // 分支已存在
+        let rsp = create_branch(&dao, &user("alice", &[]), request("dev", "main", &[]), 3)
+            .await
+            .unwrap();
+        assert!(!rsp.success);
+    }
+
+    fn revision(depot_path: &str, revision: i64, is_delete: bool) -> NewFileRevisionInput {
+        NewFileRevisionInput {
+            depot_path: depot_path.to_string(),
+            generation: 1,
+            revision,
+            binary_id: serde_json::json!([]),
+            size: 0,
+            is_delete,
+            created_at: 0,
+            metadata: serde_json::json!({}),
+            file_metadata: serde_json::json!({}),
+        }
+    }
+
+    fn request_at(branch_id: &str, base_branch_id: &str, at_changelist_id: i64) -> CreateBranchReq {
+        CreateBranchReq {
+            at_changelist_id,
+            ..request(branch_id, base_branch_id, &[])
+        }
+    }
+
+    /// 三个 changelist，main 指向 3，release 停留在 1
+    async fn dao_with_history() -> MockDao {
+        let dao = MockDao::default();
+        let submits = [
+            vec![
+                revision("//src/a.rs", 1, false),
+                revision("//src/b.rs", 1, false),
+            ],
+            vec![
+                revision("//src/a.rs", 2, false),
+                revision("//src/c.rs", 1, false),
+            ],
+            vec![
+                revision("//src/b.rs", 2, true),
+                revision("//src/d.rs", 1, false),
+            ],
+        ];
+        for revisions in submits {
+            dao.commit_submit("alice", "", 0, serde_json::json!({}), revisions)
+                .await
+                .unwrap();
+        }
+        for (branch_id, head_changelist_id) in [("main", 3), ("release", 1)] {
+            dao.insert_branch(&BranchDoc {
+                id: branch_id.to_string(),
+                created_at: 0,
+                created_by: "alice".to_string(),
+                head_changelist_id,
+                is_protected: false,
+                metadata: BranchMetadata {
+                    description: branch_id.to_string(),
+                    owners: vec![],
+                    description_regex: None,
+                },
+            })
+            .await
+            .unwrap();
+        }
+        dao
+    }
+
+    #[tokio::test]
+    async fn branch_at_changelist_has_the_tree_of_that_changelist() {
+        let dao = dao_with_history().await;
+
+        let rsp = create_branch(&dao, &user("bob", &[]), request_at("feature", "main", 2), 1)
+            .await
+            .unwrap();
+        assert!(rsp.success);
+        let feature = dao.find_branch_by_id("feature").await.unwrap().unwrap();
+        assert_eq!(feature.head_changelist_id, 2);
+
+        let depot = DepotPath::parse("//...").unwrap();
+        let mut files: Vec<_> = snapshot_at(&dao, feature.head_changelist_id, &depot)
+            .await
+            .unwrap()
+            .into_iter()
+            .filter(|(_, m)| !m.is_delete)
+            .map(|(path, m)| (path, m.revision))
+            .collect();
+        files.sort();
+        assert_eq!(
+            files,
+            vec![
+                ("//src/a.rs".to_string(), 2),
+                ("//src/b.rs".to_string(), 1),
+                ("//src/c.rs".to_string(), 1),
+            ]
+        );
+
+        // 与 main 相比缺少 changelist 3 的改动
+        let diff = get_branch_diff(
+            &dao,
+            GetBranchDiffReq {
+                branch_a_id: "feature".to_string(),
+                branch_b_id: "main".to_string(),
+                depot_wildcard: String::new(),
+            },
+        )
+        .await
+        .unwrap();
+        assert_eq!(diff.only_in_a, vec!["//src/b.rs"]);
+        assert_eq!(diff.only_in_b, vec!["//src/d.rs"]);
+        assert!(diff.modified.is_empty());
+    }
+
+    #[tokio::test]
+    async fn branch_at_changelist_ignores_later_commits_of_other_branches() {
+        let dao = dao_with_history().await;
+
+        let rsp = create_branch(&dao, &user("bob", &[]), request_at("feature", "main", 2), 1)
+            .await
+            .unwrap();
+        assert!(rsp.success);
+        // 分叉之后 main 与 feature 各自继续提交
+        let on_main = dao
+            .commit_submit_to_branch(
+                "main",
+                "alice",
+                "",
+                0,
+                serde_json::json!({}),
+                vec![revision("//src/a.rs", 3, false)],
+            )
+            .await
+            .unwrap();
+        let on_feature = dao
+            .commit_submit_to_branch(
+                "feature",
+                "bob",
+                "",
+                0,
+                serde_json::json!({}),
+                vec![revision("//src/e.rs", 1, false)],
+            )
+            .await
+            .unwrap();
+        assert!(on_main < on_feature);
+
+        let feature = dao.find_branch_by_id("feature").await.unwrap().unwrap();
+        assert_eq!(feature.head_changelist_id, on_feature);
+        let depot = DepotPath::parse("//...").unwrap();
+        let mut files: Vec<_> = snapshot_at(&dao, feature.head_changelist_id, &depot)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|(path, m)| (path, m.revision))
+            .collect();
+        files.sort();
+        assert_eq!(
+            files,
+            vec![
+                ("//src/a.rs".to_string(), 2),
+                ("//src/b.rs".to_string(), 1),
+                ("//src/c.rs".to_string(), 1),
+                ("//src/e.rs".to_string(), 1),
+            ]
+        );
+
+        // main 的新提交不在 feature 上，不能以它为分叉点
+        let err = create_branch(
+            &dao,
+            &user("bob", &[]),
+            request_at("hotfix", "feature", on_main),
+            1,
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(err.code(), Code::FailedPrecondition);
+    }
+
+    #[tokio::test]
+    async fn branch_at_changelist_must_be_on_base_branch() {
+        let dao = dao_with_history().await;
+        let bob = user("bob", &[]);
+
+        let err = create_branch(&dao, &bob, request_at("feature", "release", 3), 1)
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::FailedPrecondition);
+        let err = create_branch(&dao, &bob, request_at("feature", "main", 4), 1)
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::NotFound);
+        let err = create_branch(&dao, &bob, request_at("feature", "main", -1), 1)
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::InvalidArgument);
+        let err = create_branch(
+            &dao,
+            &user("carol", &[ADMIN_SCOPE]),
+            request_at("root", "", 1),
+            1,
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(err.code(), Code::InvalidArgument);
+        assert!(dao.find_branch_by_id("feature").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn root_branch_requires_admin() {
+        let dao = MockDao::default();
+
+        let err = create_branch(&dao, &user("bob", &[]), request("main", "", &[]), 1)
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::PermissionDenied);
+
+        let rsp = create_branch(
+            &dao,
+            &user("carol", &[ADMIN_SCOPE]),
+            request("main", "", &[]),
+            1,
+        )
+        .await
+        .unwrap();
+        assert!(rsp.success);
+
+        let err = create_branch(
+            &dao,
+            &user("carol", &[ADMIN_SCOPE]),
+            request("dev", "nope", &[]),
+            1,
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(err.code(), Code::NotFound);
+    }
+}
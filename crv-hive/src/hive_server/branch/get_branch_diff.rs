@@ -0,0 +1,278 @@
+use std::collections::HashMap;
+
+use crate::auth::require_user;
+use crate::common::depot_path::DepotPath;
+use crate::database::dao::{Dao, read_dao};
+use crate::database::entities::file_revisions;
+use crate::logging::HiveLog;
+use crate::pb::{GetBranchDiffReq, GetBranchDiffRsp};
+use crv_core::path::basic::DepotPath as CoreDepotPath;
+use crv_core::tree::{FileTree, FileTreeNode, diff_trees};
+use tonic::{Request, Response, Status};
+
+pub async fn handle_get_branch_diff(
+    log: HiveLog,
+    r: Request<GetBranchDiffReq>,
+) -> Result<Response<GetBranchDiffRsp>, Status> {
+    let user = require_user(&r)?.clone();
+    let log = log.with_user(&user.username);
+    let _g = log.enter();
+
+    let request = r.into_inner();
+    log.info(&format!(
+        "get_branch_diff received: a={}, b={}, path={}",
+        request.branch_a_id, request.branch_b_id, request.depot_wildcard
+    ));
+
+    let rsp = get_branch_diff(read_dao().as_ref(), request).await?;
+    log.info(&format!(
+        "get_branch_diff finished: only_in_a={}, only_in_b={}, modified={}",
+        rsp.only_in_a.len(),
+        rsp.only_in_b.len(),
+        rsp.modified.len()
+    ));
+    Ok(Response::new(rsp))
+}
+
+/// `depot` 范围内各文件在 changelist `head` 时的最新 revision，包含删除记录，键为 depot 路径
+pub(crate) async fn snapshot_at(
+    dao: &dyn Dao,
+    head: i64,
+    depot: &DepotPath,
+) -> Result<HashMap<String, file_revisions::Model>, Status> {
+    // 查询时 changelist_id <= 0 表示默认分支的 HEAD，空分支需要单独处理
+    if head <= 0 {
+        return Ok(HashMap::new());
+    }
+
+    let models = dao
+        .list_file_tree_revisions(depot, head)
+        .await
+        .map_err(|e| Status::internal(format!("database error while listing files: {e}")))?;
+    let mut files = HashMap::with_capacity(models.len());
+    for m in models {
+        let path = m
+            .to_depot_path_string()
+            .map_err(|e| Status::internal(format!("failed to decode ltree path: {e}")))?;
+        files.insert(path, m);
+    }
+    Ok(files)
+}
+
+/// 分支 HEAD 上 `depot` 范围内的文件树，已删除的文件不包含在内。
+///
+/// 树中使用完整的 depot 路径，文件节点的 revision_id 为 `{generation}#{revision}`
+async fn branch_tree(
+    dao: &dyn Dao,
+    branch_id: &str,
+    depot: &DepotPath,
+) -> Result<FileTree, Status> {
+    let branch = dao
+        .find_branch_by_id(branch_id)
+        .await
+        .map_err(|e| Status::internal(format!("database error while finding branch: {e}")))?
+        .ok_or_else(|| Status::not_found(format!("branch '{branch_id}' not found")))?;
+
+    let mut files = Vec::new();
+    for (path, m) in snapshot_at(dao, branch.head_changelist_id, depot).await? {
+        if m.is_delete {
+            continue;
+        }
+        let depot_path = CoreDepotPath::parse(&path)
+            .map_err(|e| Status::internal(format!("invalid depot path '{path}': {e}")))?;
+        files.push((
+            depot_path.dirs,
+            FileTreeNode::File {
+                name: depot_path.file,
+                file_id: path,
+                reivision_id: format!("{}#{}", m.generation, m.revision),
+                changelist_id: m.changelist_id,
+                binary_id: serde_json::from_value(m.binary_id).unwrap_or_default(),
+                size: m.size,
+                revision_created_at: m.created_at,
+            },
+        ));
+    }
+    Ok(FileTree::from_files(files))
+}
+
+/// 比较两个分支 HEAD 上的文件，分别列出只在一侧存在以及两侧版本不同的路径。
+pub(crate) async fn get_branch_diff(
+    dao: &dyn Dao,
+    request: GetBranchDiffReq,
+) -> Result<GetBranchDiffRsp, Status> {
+    let branch_a_id = request.branch_a_id.trim();
+    let branch_b_id = request.branch_b_id.trim();
+    if branch_a_id.is_empty() || branch_b_id.is_empty() {
+        return Err(Status::invalid_argument(
+            "branch_a_id and branch_b_id are required",
+        ));
+    }
+    let depot_wildcard = match request.depot_wildcard.trim() {
+        "" => "//...",
+        depot_wildcard => depot_wildcard,
+    };
+    let depot = DepotPath::parse(depot_wildcard).map_err(|e| {
+        Status::invalid_argument(format!("invalid depot_wildcard '{depot_wildcard}': {e}"))
+    })?;
+
+    let tree_a = branch_tree(dao, branch_a_id, &depot).await?;
+    let tree_b = branch_tree(dao, branch_b_id, &depot).await?;
+    let diff = diff_trees(&tree_a, &tree_b);
+
+    let strings = |paths: Vec<CoreDepotPath>| -> Vec<String> {
+        paths.iter().map(CoreDepotPath::to_custom_string).collect()
+    };
+    let mut rsp = GetBranchDiffRsp {
+        only_in_a: strings(diff.removed),
+        only_in_b: strings(diff.added),
+        modified: strings(diff.modified.into_iter().map(|f| f.path).collect()),
+    };
+    // 文件树中目录排在文件之前，按完整路径重新排序
+    rsp.only_in_a.sort();
+    rsp.only_in_b.sort();
+    rsp.modified.sort();
+    Ok(rsp)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::dao::{MockDao, NewFileRevisionInput};
+    use crv_core::metadata::{BranchDoc, BranchMetadata};
+    use tonic::Code;
+
+    fn revision(depot_path: &str, revision: i64, is_delete: bool) -> NewFileRevisionInput {
+        NewFileRevisionInput {
+            depot_path: depot_path.to_string(),
+            generation: 1,
+            revision,
+            binary_id: serde_json::json!([]),
+            size: 0,
+            is_delete,
+            created_at: 0,
+            metadata: serde_json::json!({}),
+            file_metadata: serde_json::json!({}),
+        }
+    }
+
+    fn request(branch_a_id: &str, branch_b_id: &str, depot_wildcard: &str) -> GetBranchDiffReq {
+        GetBranchDiffReq {
+            branch_a_id: branch_a_id.to_string(),
+            branch_b_id: branch_b_id.to_string(),
+            depot_wildcard: depot_wildcard.to_string(),
+        }
+    }
+
+    /// changelist 1 添加 a、b、c 与 //docs/readme.md，release 分支停留在 1；
+    /// changelist 2 修改 a、删除 c 并添加 d，main 分支指向 2；empty 分支没有 changelist
+    async fn dao_with_branches() -> MockDao {
+        let dao = MockDao::default();
+        let submits = [
+            vec![
+                revision("//assets/a.png", 1, false),
+                revision("//assets/b.png", 1, false),
+                revision("//assets/ui/c.png", 1, false),
+                revision("//docs/readme.md", 1, false),
+            ],
+            vec![
+                revision("//assets/a.png", 2, false),
+                revision("//assets/ui/c.png", 2, true),
+                revision("//assets/ui/d.png", 1, false),
+            ],
+        ];
+        for revisions in submits {
+            dao.commit_submit("alice", "", 0, serde_json::json!({}), revisions)
+                .await
+                .unwrap();
+        }
+        for (branch_id, head_changelist_id) in [("main", 2), ("release", 1), ("empty", 0)] {
+            dao.insert_branch(&BranchDoc {
+                id: branch_id.to_string(),
+                created_at: 0,
+                created_by: "alice".to_string(),
+                head_changelist_id,
+                is_protected: false,
+                metadata: BranchMetadata {
+                    description: branch_id.to_string(),
+                    owners: vec![],
+                    description_regex: None,
+                },
+            })
+            .await
+            .unwrap();
+        }
+        dao
+    }
+
+    #[tokio::test]
+    async fn diff_lists_added_removed_and_modified_files() {
+        let dao = dao_with_branches().await;
+
+        let rsp = get_branch_diff(&dao, request("release", "main", "//assets/..."))
+            .await
+            .unwrap();
+        assert_eq!(rsp.only_in_a, vec!["//assets/ui/c.png"]);
+        assert_eq!(rsp.only_in_b, vec!["//assets/ui/d.png"]);
+        assert_eq!(rsp.modified, vec!["//assets/a.png"]);
+
+        // 交换两侧
+        let rsp = get_branch_diff(&dao, request("main", "release", ""))
+            .await
+            .unwrap();
+        assert_eq!(rsp.only_in_a, vec!["//assets/ui/d.png"]);
+        assert_eq!(rsp.only_in_b, vec!["//assets/ui/c.png"]);
+        assert_eq!(rsp.modified, vec!["//assets/a.png"]);
+
+        // 目录只比较直接子文件
+        let rsp = get_branch_diff(&dao, request("release", "main", "//assets/ui/"))
+            .await
+            .unwrap();
+        assert_eq!(rsp.only_in_a, vec!["//assets/ui/c.png"]);
+        assert_eq!(rsp.only_in_b, vec!["//assets/ui/d.png"]);
+        assert!(rsp.modified.is_empty());
+    }
+
+    #[tokio::test]
+    async fn diff_against_empty_branch_lists_every_file() {
+        let dao = dao_with_branches().await;
+
+        let rsp = get_branch_diff(&dao, request("empty", "release", "//..."))
+            .await
+            .unwrap();
+        assert!(rsp.only_in_a.is_empty());
+        assert!(rsp.modified.is_empty());
+        assert_eq!(
+            rsp.only_in_b,
+            vec![
+                "//assets/a.png",
+                "//assets/b.png",
+                "//assets/ui/c.png",
+                "//docs/readme.md"
+            ]
+        );
+
+        let rsp = get_branch_diff(&dao, request("main", "main", "//..."))
+            .await
+            .unwrap();
+        assert_eq!(rsp, GetBranchDiffRsp::default());
+    }
+
+    #[tokio::test]
+    async fn rejects_invalid_requests() {
+        let dao = dao_with_branches().await;
+
+        let err = get_branch_diff(&dao, request("main", "", "//..."))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::InvalidArgument);
+        let err = get_branch_diff(&dao, request("main", "release", "assets"))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::InvalidArgument);
+        let err = get_branch_diff(&dao, request("main", "missing", "//..."))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::NotFound);
+    }
+}
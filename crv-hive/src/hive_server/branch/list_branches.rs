@@ -0,0 +1,135 @@
+use crate::auth::require_user;
+use crate::database::dao::{BranchSort, Dao, read_dao};
+use crate::logging::HiveLog;
+use crate::pb::{BranchSortField, BranchSummary, ListBranchesReq, ListBranchesRsp};
+use tonic::{Request, Response, Status};
+
+/// 未指定 page_size 时每页的条数
+const DEFAULT_PAGE_SIZE: u32 = 50;
+/// 每页最多的条数
+const MAX_PAGE_SIZE: u32 = 1000;
+
+pub async fn handle_list_branches(
+    log: HiveLog,
+    r: Request<ListBranchesReq>,
+) -> Result<Response<ListBranchesRsp>, Status> {
+    let user = require_user(&r)?.clone();
+    let log = log.with_user(&user.username);
+    let _g = log.enter();
+
+    let request = r.into_inner();
+    log.info(&format!(
+        "list_branches received: page={}, page_size={}, sort_by={:?}",
+        request.page,
+        request.page_size,
+        request.sort_by()
+    ));
+
+    let rsp = list_branches(read_dao().as_ref(), request).await?;
+    Ok(Response::new(rsp))
+}
+
+/// 按创建时间或 HEAD changelist 降序分页列出分支。
+pub(crate) async fn list_branches(
+    dao: &dyn Dao,
+    request: ListBranchesReq,
+) -> Result<ListBranchesRsp, Status> {
+    let page_size = match request.page_size {
+        0 => DEFAULT_PAGE_SIZE,
+        page_size => page_size.min(MAX_PAGE_SIZE),
+    } as u64;
+    let sort_by = match request.sort_by() {
+        BranchSortField::CreatedAt => BranchSort::CreatedAt,
+        BranchSortField::HeadChangelistId => BranchSort::HeadChangelistId,
+    };
+
+    let (branches, total) = dao
+        .list_branches(sort_by, request.page as u64 * page_size, page_size)
+        .await
+        .map_err(|e| Status::internal(format!("database error while listing branches: {e}")))?;
+
+    Ok(ListBranchesRsp {
+        branches: branches
+            .into_iter()
+            .map(|b| BranchSummary {
+                id: b.id,
+                description: b.metadata.description,
+                head_changelist_id: b.head_changelist_id,
+                created_by: b.created_by,
+                created_at: b.created_at,
+            })
+            .collect(),
+        total_count: total as u64,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::dao::MockDao;
+    use crv_core::metadata::{BranchDoc, BranchMetadata};
+
+    async fn insert_branch(dao: &MockDao, id: &str, created_at: i64, head_changelist_id: i64) {
+        dao.insert_branch(&BranchDoc {
+            id: id.to_string(),
+            created_at,
+            created_by: "alice".to_string(),
+            head_changelist_id,
+            is_protected: false,
+            metadata: BranchMetadata {
+                description: format!("{id} branch"),
+                owners: vec![],
+                description_regex: None,
+            },
+        })
+        .await
+        .unwrap();
+    }
+
+    fn ids(rsp: &ListBranchesRsp) -> Vec<&str> {
+        rsp.branches.iter().map(|b| b.id.as_str()).collect()
+    }
+
+    #[tokio::test]
+    async fn list_branches_is_sorted_and_paginated() {
+        let dao = MockDao::default();
+        insert_branch(&dao, "main", 1000, 40).await;
+        insert_branch(&dao, "dev", 3000, 10).await;
+        insert_branch(&dao, "release", 2000, 30).await;
+        insert_branch(&dao, "hotfix", 4000, 30).await;
+
+        let rsp = list_branches(&dao, ListBranchesReq::default())
+            .await
+            .unwrap();
+        assert_eq!(ids(&rsp), vec!["hotfix", "dev", "release", "main"]);
+        assert_eq!(rsp.total_count, 4);
+        assert_eq!(rsp.branches[0].description, "hotfix branch");
+        assert_eq!(rsp.branches[0].created_by, "alice");
+
+        // HEAD 相同时按分支 id 排序
+        let rsp = list_branches(
+            &dao,
+            ListBranchesReq {
+                sort_by: BranchSortField::HeadChangelistId as i32,
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+        assert_eq!(ids(&rsp), vec!["main", "hotfix", "release", "dev"]);
+        assert_eq!(rsp.branches[0].head_changelist_id, 40);
+
+        let rsp = list_branches(
+            &dao,
+            ListBranchesReq {
+                page: 1,
+                page_size: 3,
+                sort_by: BranchSortField::HeadChangelistId as i32,
+            },
+        )
+        .await
+        .unwrap();
+        assert_eq!(ids(&rsp), vec!["dev"]);
+        assert_eq!(rsp.total_count, 4);
+    }
+}
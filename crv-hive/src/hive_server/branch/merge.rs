@@ -0,0 +1,575 @@
+use std::collections::BTreeSet;
+
+use crate::auth::{UserContext, require_user};
+use crate::common::depot_path::DepotPath;
+use crate::database::dao::{Dao, MergeInput, NewFileRevisionInput, dao, new_file_metadata};
+use crate::database::entities::file_revisions;
+use crate::hive_server::branch::get_branch_diff::snapshot_at;
+use crate::hive_server::branch::set_protection::check_branch_accepts_merge;
+use crate::hive_server::submit::SUBMIT_LOCK;
+use crate::logging::HiveLog;
+use crate::pb::{MergeReq, MergeRsp, MergeStrategy};
+use tonic::{Request, Response, Status};
+
+pub async fn handle_merge(
+    log: HiveLog,
+    r: Request<MergeReq>,
+) -> Result<Response<MergeRsp>, Status> {
+    let user = require_user(&r)?.clone();
+    let log = log.with_user(&user.username);
+    let _g = log.enter();
+
+    let request = r.into_inner();
+    log.info(&format!(
+        "merge received: source_branch={}, target_branch={}, strategy={:?}",
+        request.source_branch_id,
+        request.target_branch_id,
+        request.strategy()
+    ));
+
+    let rsp = merge(
+        dao().as_ref(),
+        &user,
+        request,
+        chrono::Utc::now().timestamp(),
+    )
+    .await?;
+    log.info(&format!(
+        "merge finished: new_changelist_id={}, auto_resolved={}, manual_conflicts={}",
+        rsp.new_changelist_id,
+        rsp.auto_resolved.len(),
+        rsp.manual_conflicts.len()
+    ));
+    Ok(Response::new(rsp))
+}
+
+/// 文件内容（chunk 列表），文件不存在或已删除时为 None。
+///
+/// 合并写入的 revision 会重新编号，因此按内容而不是 (generation, revision) 判断两侧是否相同
+fn content(model: Option<&file_revisions::Model>) -> Option<&serde_json::Value> {
+    model.map(|m| &m.binary_id)
+}
+
+/// 将源分支 HEAD 上的文件合并到目标分支，作为目标分支上的新 changelist。
+///
+/// 以两个 HEAD 的公共祖先为基准逐个文件做三方比较：只有源分支修改过的文件采用源分支的版本，
+/// 只有目标分支修改过的文件保持不变，两侧都修改且结果不同的文件视为冲突并按 `strategy` 处理。
+/// 存在需要手动解决的冲突时只返回冲突列表，不写入任何数据。整个过程持有 `SUBMIT_LOCK` 的写锁。
+pub(crate) async fn merge(
+    dao: &dyn Dao,
+    user: &UserContext,
+    request: MergeReq,
+    now: i64,
+) -> Result<MergeRsp, Status> {
+    let strategy = request.strategy();
+    let source_branch_id = request.source_branch_id.trim();
+    let target_branch_id = request.target_branch_id.trim();
+    if source_branch_id.is_empty() || target_branch_id.is_empty() {
+        return Err(Status::invalid_argument(
+            "source_branch_id and target_branch_id are required",
+        ));
+    }
+    if source_branch_id == target_branch_id {
+        return Err(Status::invalid_argument(
+            "cannot merge a branch into itself",
+        ));
+    }
+
+    let _submit_guard = SUBMIT_LOCK.write().await;
+
+    let find_branch = |branch_id: &'static str, id: &str| {
+        let id = id.to_string();
+        async move {
+            dao.find_branch_by_id(&id)
+                .await
+                .map_err(|e| Status::internal(format!("database error while finding branch: {e}")))?
+                .ok_or_else(|| Status::not_found(format!("{branch_id} '{id}' not found")))
+        }
+    };
+    let source = find_branch("source branch", source_branch_id).await?;
+    let target = find_branch("target branch", target_branch_id).await?;
+    check_branch_accepts_merge(&target, user)?;
+
+    let merge_base = dao
+        .find_merge_base(source.head_changelist_id, target.head_changelist_id)
+        .await
+        .map_err(|e| Status::internal(format!("database error while finding merge base: {e}")))?;
+    let depot = DepotPath::parse("//...").expect("root wildcard is valid");
+    let base_files = snapshot_at(dao, merge_base, &depot).await?;
+    let source_files = snapshot_at(dao, source.head_changelist_id, &depot).await?;
+    let target_files = snapshot_at(dao, target.head_changelist_id, &depot).await?;
+    let paths: BTreeSet<&String> = source_files.keys().chain(target_files.keys()).collect();
+
+    let mut auto_resolved = Vec::new();
+    let mut manual_conflicts = Vec::new();
+    let mut revisions = Vec::new();
+    for path in paths {
+        let base_file = base_files.get(path).filter(|m| !m.is_delete);
+        let source_file = source_files.get(path).filter(|m| !m.is_delete);
+        let target_file = target_files.get(path).filter(|m| !m.is_delete);
+        // 两侧相同，或只有目标分支修改过
+        if content(source_file) == content(target_file)
+            || content(source_file) == content(base_file)
+        {
+            continue;
+        }
+
+        let conflict = content(target_file) != content(base_file);
+        let desired = if !conflict {
+            source_file
+        } else {
+            match strategy {
+                MergeStrategy::AutoResolveSourceWins => {
+                    auto_resolved.push(path.clone());
+                    source_file
+                }
+                // 一侧已删除的冲突按删除处理
+                MergeStrategy::AutoResolveDeleteWins
+                    if source_file.is_none() || target_file.is_none() =>
+                {
+                    auto_resolved.push(path.clone());
+                    None
+                }
+                MergeStrategy::AutoResolveDeleteWins => {
+                    manual_conflicts.push(path.clone());
+                    continue;
+                }
+            }
+        };
+        if desired.is_none() && target_file.is_none() {
+            continue;
+        }
+
+        // revision 在所有分支间统一编号，新的 revision 接在该文件最新的 revision 之后
+        let latest = dao
+            .find_latest_file_revision_by_depot_path(path)
+            .await
+            .map_err(|e| Status::internal(format!("database error while finding {path}: {e}")))?;
+        let (generation, revision) = match latest {
+            Some(m) => (m.generation, m.revision.saturating_add(1)),
+            None => (1, 1),
+        };
+        let (binary_id, size, is_delete, metadata) = match desired {
+            Some(source_file) => {
+                // 流水线标签属于源 revision，不随之复制
+                let mut metadata = source_file.metadata.clone();
+                if let Some(metadata) = metadata.as_object_mut() {
+                    metadata.remove("tags");
+                }
+                (
+                    source_file.binary_id.clone(),
+                    source_file.size,
+                    false,
+                    metadata,
+                )
+            }
+            None => (serde_json::json!([]), 0, true, serde_json::json!({})),
+        };
+        revisions.push(NewFileRevisionInput {
+            depot_path: path.clone(),
+            generation,
+            revision,
+            binary_id,
+            size,
+            is_delete,
+            created_at: now,
+            metadata,
+            file_metadata: new_file_metadata(&user.username, target_branch_id),
+        });
+    }
+
+    if !manual_conflicts.is_empty() {
+        return Ok(MergeRsp {
+            new_changelist_id: 0,
+            auto_resolved,
+            manual_conflicts,
+        });
+    }
+    if revisions.is_empty() {
+        return Err(Status::failed_precondition(format!(
+            "branch '{target_branch_id}' is already up to date with '{source_branch_id}'"
+        )));
+    }
+
+    let description = match request.description.trim() {
+        "" => format!("Merge {source_branch_id} into {target_branch_id}"),
+        description => description.to_string(),
+    };
+    let paths: Vec<String> = revisions.iter().map(|r| r.depot_path.clone()).collect();
+    let new_changelist_id = dao
+        .merge_branches(&MergeInput {
+            source_branch_id: source_branch_id.to_string(),
+            source_changelist_id: source.head_changelist_id,
+            target_branch_id: target_branch_id.to_string(),
+            author: user.username.clone(),
+            description,
+            committed_at: now,
+            revisions,
+        })
+        .await
+        .map_err(|e| Status::internal(format!("database error while merging: {e}")))?;
+
+    // changelist 已落库，记录失败不影响本次结果
+    for depot_path in &paths {
+        let _ = dao.add_branch_to_file(depot_path, target_branch_id).await;
+    }
+
+    Ok(MergeRsp {
+        new_changelist_id,
+        auto_resolved,
+        manual_conflicts: vec![],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::AuthSource;
+    use crate::database::dao::{MockDao, changelist_merged_from};
+    use crv_core::metadata::{BranchDoc, BranchMetadata};
+    use tonic::Code;
+
+    fn revision(depot_path: &str, revision: i64, is_delete: bool) -> NewFileRevisionInput {
+        NewFileRevisionInput {
+            depot_path: depot_path.to_string(),
+            generation: 1,
+            revision,
+            binary_id: if is_delete {
+                serde_json::json!([])
+            } else {
+                serde_json::json!([format!("{depot_path}#{revision}")])
+            },
+            size: if is_delete { 0 } else { revision * 10 },
+            is_delete,
+            created_at: 0,
+            metadata: serde_json::json!({}),
+            file_metadata: serde_json::json!({}),
+        }
+    }
+
+    fn user(username: &str) -> UserContext {
+        UserContext {
+            username: username.to_string(),
+            scopes: vec![],
+            source: AuthSource::Jwt,
+        }
+    }
+
+    fn request(
+        source_branch_id: &str,
+        target_branch_id: &str,
+        strategy: MergeStrategy,
+    ) -> MergeReq {
+        MergeReq {
+            source_branch_id: source_branch_id.to_string(),
+            target_branch_id: target_branch_id.to_string(),
+            description: String::new(),
+            strategy: strategy as i32,
+        }
+    }
+
+    /// changelist 1 添加 a、b、c，main 与 release 都从 1 分叉；
+    /// changelist 2 在 main 上修改 a、删除 b 并添加 d
+    async fn dao_with_branches() -> MockDao {
+        let dao = MockDao::default();
+        dao.commit_submit(
+            "alice",
+            "",
+            0,
+            serde_json::json!({}),
+            vec![
+                revision("//src/a.txt", 1, false),
+                revision("//src/b.txt", 1, false),
+                revision("//src/c.txt", 1, false),
+            ],
+        )
+        .await
+        .unwrap();
+        for branch_id in ["main", "release"] {
+            dao.insert_branch(&BranchDoc {
+                id: branch_id.to_string(),
+                created_at: 0,
+                created_by: "alice".to_string(),
+                head_changelist_id: 1,
+                is_protected: false,
+                metadata: BranchMetadata {
+                    description: branch_id.to_string(),
+                    owners: vec!["alice".to_string()],
+                    description_regex: None,
+                },
+            })
+            .await
+            .unwrap();
+        }
+        commit(
+            &dao,
+            "main",
+            vec![
+                revision("//src/a.txt", 2, false),
+                revision("//src/b.txt", 2, true),
+                revision("//src/d.txt", 1, false),
+            ],
+        )
+        .await;
+        dao
+    }
+
+    /// changelist 3 在 release 上修改 a、b、c 并添加 e
+    async fn diverge_release(dao: &MockDao) {
+        commit(
+            dao,
+            "release",
+            vec![
+                revision("//src/a.txt", 3, false),
+                revision("//src/b.txt", 3, false),
+                revision("//src/c.txt", 2, false),
+                revision("//src/e.txt", 1, false),
+            ],
+        )
+        .await;
+    }
+
+    async fn commit(dao: &MockDao, branch_id: &str, revisions: Vec<NewFileRevisionInput>) -> i64 {
+        dao.commit_submit_to_branch(branch_id, "alice", "", 0, serde_json::json!({}), revisions)
+            .await
+            .unwrap()
+    }
+
+    /// 新 changelist 中各文件的 (路径, 是否删除, chunk)
+    async fn changes(dao: &MockDao, changelist_id: i64) -> Vec<(String, bool, serde_json::Value)> {
+        dao.list_file_revisions_by_changelist(changelist_id)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|m| {
+                let path = m.to_depot_path_string().unwrap();
+                (path, m.is_delete, m.binary_id)
+            })
+            .collect()
+    }
+
+    async fn head(dao: &MockDao, branch_id: &str) -> i64 {
+        dao.find_branch_by_id(branch_id)
+            .await
+            .unwrap()
+            .unwrap()
+            .head_changelist_id
+    }
+
+    #[tokio::test]
+    async fn merge_without_conflicts_brings_target_to_source_state() {
+        let dao = dao_with_branches().await;
+        // 受保护分支仍然接受所有者的 merge
+        dao.set_branch_protection("release", true).await.unwrap();
+
+        let rsp = merge(
+            &dao,
+            &user("alice"),
+            request("main", "release", MergeStrategy::AutoResolveDeleteWins),
+            1000,
+        )
+        .await
+        .unwrap();
+        assert_eq!(rsp.new_changelist_id, 3);
+        assert!(rsp.auto_resolved.is_empty());
+        assert!(rsp.manual_conflicts.is_empty());
+        assert_eq!(head(&dao, "release").await, 3);
+
+        let changelist = dao.find_changelist_by_id(3).await.unwrap().unwrap();
+        assert_eq!(changelist.description, "Merge main into release");
+        assert_eq!(
+            changelist_merged_from(&changelist.metadata),
+            Some(("main".to_string(), 2))
+        );
+        assert_eq!(
+            changes(&dao, 3).await,
+            vec![
+                (
+                    "//src/a.txt".to_string(),
+                    false,
+                    serde_json::json!(["//src/a.txt#2"])
+                ),
+                ("//src/b.txt".to_string(), true, serde_json::json!([])),
+                (
+                    "//src/d.txt".to_string(),
+                    false,
+                    serde_json::json!(["//src/d.txt#1"])
+                ),
+            ]
+        );
+
+        // 合并后两个分支没有差异
+        let err = merge(
+            &dao,
+            &user("alice"),
+            request("main", "release", MergeStrategy::AutoResolveDeleteWins),
+            1000,
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(err.code(), Code::FailedPrecondition);
+    }
+
+    #[tokio::test]
+    async fn source_wins_resolves_files_changed_on_both_sides() {
+        let dao = dao_with_branches().await;
+        diverge_release(&dao).await;
+
+        // a、b 在两个分支上都修改过；c、e 只在 release 上修改，d 只在 main 上修改
+        let rsp = merge(
+            &dao,
+            &user("alice"),
+            request("release", "main", MergeStrategy::AutoResolveSourceWins),
+            1000,
+        )
+        .await
+        .unwrap();
+        assert_eq!(rsp.new_changelist_id, 4);
+        assert_eq!(rsp.auto_resolved, vec!["//src/a.txt", "//src/b.txt"]);
+        assert!(rsp.manual_conflicts.is_empty());
+        assert_eq!(head(&dao, "main").await, 4);
+        assert_eq!(
+            changes(&dao, 4).await,
+            vec![
+                (
+                    "//src/a.txt".to_string(),
+                    false,
+                    serde_json::json!(["//src/a.txt#3"])
+                ),
+                (
+                    "//src/b.txt".to_string(),
+                    false,
+                    serde_json::json!(["//src/b.txt#3"])
+                ),
+                (
+                    "//src/c.txt".to_string(),
+                    false,
+                    serde_json::json!(["//src/c.txt#2"])
+                ),
+                (
+                    "//src/e.txt".to_string(),
+                    false,
+                    serde_json::json!(["//src/e.txt#1"])
+                ),
+            ]
+        );
+
+        // 再次合并以上次合并的来源为基准：只带入 release 之后的修改，main 之后的修改保持不变
+        commit(&dao, "release", vec![revision("//src/c.txt", 4, false)]).await;
+        commit(&dao, "main", vec![revision("//src/a.txt", 5, false)]).await;
+        let rsp = merge(
+            &dao,
+            &user("alice"),
+            request("release", "main", MergeStrategy::AutoResolveDeleteWins),
+            1000,
+        )
+        .await
+        .unwrap();
+        assert_eq!(rsp.new_changelist_id, 7);
+        assert!(rsp.auto_resolved.is_empty());
+        assert!(rsp.manual_conflicts.is_empty());
+        assert_eq!(
+            changes(&dao, 7).await,
+            vec![(
+                "//src/c.txt".to_string(),
+                false,
+                serde_json::json!(["//src/c.txt#4"])
+            )]
+        );
+    }
+
+    #[tokio::test]
+    async fn merging_an_ancestor_changes_nothing() {
+        let dao = dao_with_branches().await;
+
+        // release 的 HEAD 是 main 的祖先，main 上的修改不是冲突
+        let err = merge(
+            &dao,
+            &user("alice"),
+            request("release", "main", MergeStrategy::AutoResolveSourceWins),
+            1000,
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(err.code(), Code::FailedPrecondition);
+        assert_eq!(head(&dao, "main").await, 2);
+    }
+
+    #[tokio::test]
+    async fn delete_wins_leaves_modify_conflicts_for_manual_resolution() {
+        let dao = dao_with_branches().await;
+        diverge_release(&dao).await;
+
+        let rsp = merge(
+            &dao,
+            &user("alice"),
+            request("release", "main", MergeStrategy::AutoResolveDeleteWins),
+            1000,
+        )
+        .await
+        .unwrap();
+        assert_eq!(rsp.new_changelist_id, 0);
+        assert_eq!(rsp.auto_resolved, vec!["//src/b.txt"]);
+        assert_eq!(rsp.manual_conflicts, vec!["//src/a.txt"]);
+        // 有需要手动解决的冲突时不写入任何数据
+        assert_eq!(head(&dao, "main").await, 2);
+        assert!(dao.find_changelist_by_id(4).await.unwrap().is_none());
+
+        // 在 main 上删除 a 后没有两侧都修改的文件，删除优先的冲突直接写入
+        commit(&dao, "main", vec![revision("//src/a.txt", 4, true)]).await;
+        let rsp = merge(
+            &dao,
+            &user("alice"),
+            request("release", "main", MergeStrategy::AutoResolveDeleteWins),
+            1000,
+        )
+        .await
+        .unwrap();
+        assert_eq!(rsp.new_changelist_id, 5);
+        assert_eq!(rsp.auto_resolved, vec!["//src/a.txt", "//src/b.txt"]);
+        assert_eq!(
+            changes(&dao, 5).await,
+            vec![
+                (
+                    "//src/c.txt".to_string(),
+                    false,
+                    serde_json::json!(["//src/c.txt#2"])
+                ),
+                (
+                    "//src/e.txt".to_string(),
+                    false,
+                    serde_json::json!(["//src/e.txt#1"])
+                ),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn rejects_invalid_merges() {
+        let dao = dao_with_branches().await;
+        let strategy = MergeStrategy::AutoResolveSourceWins;
+
+        let err = merge(&dao, &user("alice"), request("main", "main", strategy), 0)
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::InvalidArgument);
+        let err = merge(
+            &dao,
+            &user("alice"),
+            request("missing", "main", strategy),
+            0,
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(err.code(), Code::NotFound);
+        let err = merge(&dao, &user("bob"), request("main", "release", strategy), 0)
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::PermissionDenied);
+        dao.set_branch_protection("release", true).await.unwrap();
+        let err = merge(&dao, &user("bob"), request("main", "release", strategy), 0)
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::PermissionDenied);
+    }
+}
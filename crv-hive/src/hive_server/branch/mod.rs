@@ -0,0 +1,7 @@
+pub mod create_branch;
+pub mod get_branch_diff;
+pub mod list_branches;
+pub mod merge;
+pub mod set_description_format;
+pub mod set_protection;
+pub mod watch_branch;
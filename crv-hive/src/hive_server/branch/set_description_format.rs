@@ -0,0 +1,117 @@
+use crate::auth::{ADMIN_SCOPE, require_scope};
+use crate::database::dao::{Dao, dao};
+use crate::logging::HiveLog;
+use crate::pb::{SetBranchDescriptionFormatReq, SetBranchDescriptionFormatRsp};
+use regex::Regex;
+use tonic::{Request, Response, Status};
+
+pub async fn handle_set_branch_description_format(
+    log: HiveLog,
+    r: Request<SetBranchDescriptionFormatReq>,
+) -> Result<Response<SetBranchDescriptionFormatRsp>, Status> {
+    let user = require_scope(&r, ADMIN_SCOPE)?.clone();
+    let log = log.with_user(&user.username);
+    let _g = log.enter();
+
+    let request = r.into_inner();
+    log.info(&format!(
+        "set_branch_description_format received: branch={}, regex={}",
+        request.branch_id, request.regex
+    ));
+
+    let rsp = set_branch_description_format(dao().as_ref(), request).await?;
+    Ok(Response::new(rsp))
+}
+
+/// 设置分支的提交描述正则，正则为空时清除覆盖，改用全局配置。
+pub(crate) async fn set_branch_description_format(
+    dao: &dyn Dao,
+    request: SetBranchDescriptionFormatReq,
+) -> Result<SetBranchDescriptionFormatRsp, Status> {
+    let branch_id = request.branch_id.trim();
+    if branch_id.is_empty() {
+        return Err(Status::invalid_argument("branch_id is required"));
+    }
+    let description_regex = if request.regex.is_empty() {
+        None
+    } else {
+        Regex::new(&request.regex)
+            .map_err(|e| Status::invalid_argument(format!("invalid regex: {e}")))?;
+        Some(request.regex)
+    };
+
+    let mut branch = dao
+        .find_branch_by_id(branch_id)
+        .await
+        .map_err(|e| Status::internal(format!("database error while finding branch: {e}")))?
+        .ok_or_else(|| Status::not_found(format!("branch {branch_id} not found")))?;
+    branch.metadata.description_regex = description_regex;
+
+    let updated = dao
+        .update_branch_metadata(branch_id, &branch.metadata)
+        .await
+        .map_err(|e| Status::internal(format!("database error while updating branch: {e}")))?;
+    if !updated {
+        return Err(Status::not_found(format!("branch {branch_id} not found")));
+    }
+    Ok(SetBranchDescriptionFormatRsp {})
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::dao::MockDao;
+    use crv_core::metadata::{BranchDoc, BranchMetadata};
+
+    fn request(branch_id: &str, regex: &str) -> SetBranchDescriptionFormatReq {
+        SetBranchDescriptionFormatReq {
+            branch_id: branch_id.to_string(),
+            regex: regex.to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn description_format_is_stored_on_branch() {
+        let dao = MockDao::default();
+        dao.insert_branch(&BranchDoc {
+            id: "main".to_string(),
+            created_at: 0,
+            created_by: "alice".to_string(),
+            head_changelist_id: 0,
+            is_protected: false,
+            metadata: BranchMetadata {
+                description: "main".to_string(),
+                owners: vec!["alice".to_string()],
+                description_regex: None,
+            },
+        })
+        .await
+        .unwrap();
+
+        set_branch_description_format(&dao, request("main", r"^\[[A-Z]+-\d+\] "))
+            .await
+            .unwrap();
+        let branch = dao.find_branch_by_id("main").await.unwrap().unwrap();
+        assert_eq!(
+            branch.metadata.description_regex.as_deref(),
+            Some(r"^\[[A-Z]+-\d+\] ")
+        );
+        assert_eq!(branch.metadata.owners, vec!["alice"]);
+
+        let err = set_branch_description_format(&dao, request("main", "[unclosed"))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), tonic::Code::InvalidArgument);
+
+        set_branch_description_format(&dao, request("main", ""))
+            .await
+            .unwrap();
+        let branch = dao.find_branch_by_id("main").await.unwrap().unwrap();
+        assert_eq!(branch.metadata.description_regex, None);
+
+        let err = set_branch_description_format(&dao, request("dev", ""))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), tonic::Code::NotFound);
+    }
+}
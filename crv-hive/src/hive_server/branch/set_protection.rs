@@ -0,0 +1,187 @@
+use crate::auth::{ADMIN_SCOPE, UserContext, require_scope};
+use crate::database::dao::{Dao, dao};
+use crate::logging::HiveLog;
+use crate::pb::{SetBranchProtectionReq, SetBranchProtectionRsp};
+use crv_core::metadata::BranchDoc;
+use tonic::{Request, Response, Status};
+
+pub async fn handle_set_branch_protection(
+    log: HiveLog,
+    r: Request<SetBranchProtectionReq>,
+) -> Result<Response<SetBranchProtectionRsp>, Status> {
+    let user = require_scope(&r, ADMIN_SCOPE)?.clone();
+    let log = log.with_user(&user.username);
+    let _g = log.enter();
+
+    let request = r.into_inner();
+    log.info(&format!(
+        "set_branch_protection received: branch={}, is_protected={}",
+        request.branch_id, request.is_protected
+    ));
+
+    let rsp = set_branch_protection(dao().as_ref(), request).await?;
+    Ok(Response::new(rsp))
+}
+
+/// 设置分支是否受保护。
+pub(crate) async fn set_branch_protection(
+    dao: &dyn Dao,
+    request: SetBranchProtectionReq,
+) -> Result<SetBranchProtectionRsp, Status> {
+    let branch_id = request.branch_id.trim();
+    if branch_id.is_empty() {
+        return Err(Status::invalid_argument("branch_id is required"));
+    }
+
+    let updated = dao
+        .set_branch_protection(branch_id, request.is_protected)
+        .await
+        .map_err(|e| Status::internal(format!("database error while updating branch: {e}")))?;
+    if !updated {
+        return Err(Status::not_found(format!("branch {branch_id} not found")));
+    }
+    Ok(SetBranchProtectionRsp {})
+}
+
+/// 受保护的分支只能通过 Merge 合入变更，直接提交时返回 `permission_denied`。
+///
+/// `branch_id` 为空表示默认分支，默认分支没有分支记录，不受保护。
+pub(crate) async fn check_branch_accepts_submit(
+    dao: &dyn Dao,
+    branch_id: &str,
+) -> Result<(), Status> {
+    if branch_id.is_empty() {
+        return Ok(());
+    }
+    let branch = dao
+        .find_branch_by_id(branch_id)
+        .await
+        .map_err(|e| Status::internal(format!("database error while finding branch: {e}")))?
+        .ok_or_else(|| Status::not_found(format!("branch {branch_id} not found")))?;
+    check_branch_accepts_direct_write(&branch)
+}
+
+/// 提交、cherry-pick、压缩 changelist 与恢复快照都是对分支的直接写入，受保护的分支全部拒绝
+pub(crate) fn check_branch_accepts_direct_write(branch: &BranchDoc) -> Result<(), Status> {
+    if branch.is_protected {
+        return Err(Status::permission_denied(format!(
+            "branch {} is protected, changes must be merged into it",
+            branch.id
+        )));
+    }
+    Ok(())
+}
+
+/// Merge 是受保护分支唯一接受的写入方式，只有分支所有者或管理员可以合入受保护分支
+pub(crate) fn check_branch_accepts_merge(
+    branch: &BranchDoc,
+    user: &UserContext,
+) -> Result<(), Status> {
+    if branch.is_protected && !branch.metadata.is_owned_by(&user.username) && !user.is_admin() {
+        return Err(Status::permission_denied(format!(
+            "branch {} is protected, only its owners or admins can merge into it",
+            branch.id
+        )));
+    }
+    check_branch_owner(branch, user)
+}
+
+/// 有所有者的分支只有所有者或管理员才能写入或基于它创建新分支
+pub(crate) fn check_branch_owner(branch: &BranchDoc, user: &UserContext) -> Result<(), Status> {
+    if branch.metadata.has_owners()
+        && !branch.metadata.is_owned_by(&user.username)
+        && !user.is_admin()
+    {
+        return Err(Status::permission_denied(format!(
+            "user '{}' is not an owner of protected branch '{}'",
+            user.username, branch.id
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::{AuthSource, UserContext};
+    use crate::database::dao::MockDao;
+    use crv_core::metadata::{BranchDoc, BranchMetadata};
+    use tonic::Code;
+
+    fn request(user: &str, scopes: &[&str], is_protected: bool) -> Request<SetBranchProtectionReq> {
+        let mut req = Request::new(SetBranchProtectionReq {
+            branch_id: "main".to_string(),
+            is_protected,
+        });
+        req.extensions_mut().insert(UserContext {
+            username: user.to_string(),
+            scopes: scopes.iter().map(|s| s.to_string()).collect(),
+            source: AuthSource::Jwt,
+        });
+        req
+    }
+
+    #[tokio::test]
+    async fn only_admin_can_toggle_protection() {
+        let dao = crate::test_support::install_mock_dao();
+        let branch_id = format!("protected-{}", uuid::Uuid::new_v4());
+        dao.insert_branch(&BranchDoc {
+            id: branch_id.clone(),
+            created_at: 0,
+            created_by: "alice".to_string(),
+            head_changelist_id: 0,
+            is_protected: false,
+            metadata: BranchMetadata {
+                description: branch_id.clone(),
+                owners: vec![],
+                description_regex: None,
+            },
+        })
+        .await
+        .unwrap();
+        let with_branch = |mut req: Request<SetBranchProtectionReq>| {
+            req.get_mut().branch_id = branch_id.clone();
+            req
+        };
+        let log = || HiveLog::new("SetBranchProtection(test)");
+
+        let err = handle_set_branch_protection(log(), with_branch(request("bob", &[], true)))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::PermissionDenied);
+        check_branch_accepts_submit(dao.as_ref(), &branch_id)
+            .await
+            .unwrap();
+
+        handle_set_branch_protection(log(), with_branch(request("root", &[ADMIN_SCOPE], true)))
+            .await
+            .unwrap();
+        let err = check_branch_accepts_submit(dao.as_ref(), &branch_id)
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::PermissionDenied);
+
+        handle_set_branch_protection(log(), with_branch(request("root", &[ADMIN_SCOPE], false)))
+            .await
+            .unwrap();
+        check_branch_accepts_submit(dao.as_ref(), &branch_id)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn unknown_branch_is_not_found() {
+        let dao = MockDao::default();
+        let err = set_branch_protection(
+            &dao,
+            SetBranchProtectionReq {
+                branch_id: "missing".to_string(),
+                is_protected: true,
+            },
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(err.code(), Code::NotFound);
+        check_branch_accepts_submit(&dao, "").await.unwrap();
+    }
+}
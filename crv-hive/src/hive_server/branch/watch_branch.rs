@@ -0,0 +1,166 @@
+use std::sync::LazyLock;
+
+use tokio::sync::{broadcast, mpsc};
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{Request, Response, Status};
+
+use crate::auth::require_user;
+use crate::config::holder::get_or_init_config;
+use crate::database::dao::read_dao;
+use crate::logging::HiveLog;
+use crate::pb::{BranchEvent, WatchBranchReq};
+
+pub type WatchBranchStream = ReceiverStream<Result<BranchEvent, Status>>;
+
+/// 广播通道中最多缓存的提交数，订阅者落后更多时流以错误结束
+const EVENT_BUFFER: usize = 1024;
+
+/// 分支上新提交的广播通道，所有分支共用，每个 WatchBranch 流只转发自己订阅的分支
+pub struct BranchEvents {
+    sender: broadcast::Sender<(String, BranchEvent)>,
+}
+
+impl BranchEvents {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            sender: broadcast::channel(capacity).0,
+        }
+    }
+
+    /// 通知订阅者 `branch_id` 上有新的提交，没有订阅者时直接丢弃
+    pub fn publish(&self, branch_id: &str, event: BranchEvent) {
+        let _ = self.sender.send((branch_id.to_string(), event));
+    }
+
+    /// 订阅所有分支的提交，已有 `max_subscribers` 个订阅者时返回 resource_exhausted
+    pub fn subscribe(
+        &self,
+        max_subscribers: usize,
+    ) -> Result<broadcast::Receiver<(String, BranchEvent)>, Status> {
+        if self.sender.receiver_count() >= max_subscribers {
+            return Err(Status::resource_exhausted(format!(
+                "too many branch watchers (max {max_subscribers})"
+            )));
+        }
+        Ok(self.sender.subscribe())
+    }
+}
+
+static BRANCH_EVENTS: LazyLock<BranchEvents> = LazyLock::new(|| BranchEvents::new(EVENT_BUFFER));
+
+/// 全局的分支提交广播，提交成功后由 `SubmitService` 发送
+pub fn branch_events() -> &'static BranchEvents {
+    &BRANCH_EVENTS
+}
+
+pub async fn handle_watch_branch(
+    log: HiveLog,
+    r: Request<WatchBranchReq>,
+) -> Result<Response<WatchBranchStream>, Status> {
+    let user = require_user(&r)?.clone();
+    let log = log.with_user(&user.username);
+    let _g = log.enter();
+
+    let branch_id = r.into_inner().branch_id.trim().to_string();
+    log.info(&format!("watch_branch received: branch_id={branch_id}"));
+
+    // 默认分支没有对应的分支记录
+    if !branch_id.is_empty() {
+        let branch = read_dao()
+            .find_branch_by_id(&branch_id)
+            .await
+            .map_err(|e| Status::internal(format!("database error while finding branch: {e}")))?;
+        if branch.is_none() {
+            return Err(Status::not_found(format!("branch not found: {branch_id}")));
+        }
+    }
+
+    let events = branch_events().subscribe(get_or_init_config().watch_branch_max_subscribers)?;
+    let (tx, rx) = mpsc::channel::<Result<BranchEvent, Status>>(32);
+    tokio::spawn(forward_branch_events(log.clone(), branch_id, events, tx));
+
+    Ok(Response::new(ReceiverStream::new(rx)))
+}
+
+/// 把 `branch_id` 上的提交转发给客户端，直到客户端断开连接
+async fn forward_branch_events(
+    log: HiveLog,
+    branch_id: String,
+    mut events: broadcast::Receiver<(String, BranchEvent)>,
+    tx: mpsc::Sender<Result<BranchEvent, Status>>,
+) {
+    let _g = log.enter();
+    loop {
+        // 客户端断开时立即释放订阅，不必等到下一次提交
+        let event = tokio::select! {
+            _ = tx.closed() => break,
+            event = events.recv() => event,
+        };
+        match event {
+            Ok((id, event)) if id == branch_id => {
+                if tx.send(Ok(event)).await.is_err() {
+                    break;
+                }
+            }
+            Ok(_) => {}
+            Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                let _ = tx
+                    .send(Err(Status::data_loss(format!(
+                        "watcher fell behind, {skipped} changelists were dropped"
+                    ))))
+                    .await;
+                break;
+            }
+            Err(broadcast::error::RecvError::Closed) => break,
+        }
+    }
+    log.info("watch_branch stream closed");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio_stream::StreamExt;
+
+    fn event(changelist_id: i64) -> BranchEvent {
+        BranchEvent {
+            changelist_id,
+            author: "alice".to_string(),
+            committed_at: 1_700_000_000,
+            files_count: 1,
+        }
+    }
+
+    #[tokio::test]
+    async fn forwards_only_events_of_watched_branch() {
+        let events = BranchEvents::new(16);
+        let (tx, rx) = mpsc::channel(8);
+        let receiver = events.subscribe(1).unwrap();
+        let log = HiveLog::new("WatchBranch(test_forwards_only_events_of_watched_branch)");
+        tokio::spawn(forward_branch_events(log, "main".to_string(), receiver, tx));
+
+        events.publish("release", event(1));
+        events.publish("main", event(2));
+        events.publish("", event(3));
+        events.publish("main", event(4));
+
+        let mut stream = ReceiverStream::new(rx);
+        assert_eq!(stream.next().await.unwrap().unwrap().changelist_id, 2);
+        assert_eq!(stream.next().await.unwrap().unwrap().changelist_id, 4);
+    }
+
+    #[tokio::test]
+    async fn subscribers_are_limited_and_released_on_disconnect() {
+        let events = BranchEvents::new(16);
+        let (tx, rx) = mpsc::channel(8);
+        let receiver = events.subscribe(1).unwrap();
+        let err = events.subscribe(1).unwrap_err();
+        assert_eq!(err.code(), tonic::Code::ResourceExhausted);
+
+        let log = HiveLog::new("WatchBranch(test_subscribers_are_limited)");
+        let task = tokio::spawn(forward_branch_events(log, "main".to_string(), receiver, tx));
+        drop(rx);
+        task.await.unwrap();
+        assert!(events.subscribe(1).is_ok());
+    }
+}
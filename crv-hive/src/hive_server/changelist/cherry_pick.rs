@@ -0,0 +1,407 @@
+use crate::auth::{UserContext, require_user};
+use crate::database::dao::{
+    CherryPickInput, Dao, NewFileRevisionInput, changelist_squashed_into, dao, new_file_metadata,
+};
+use crate::database::entities::file_revisions;
+use crate::hive_server::branch::set_protection::{
+    check_branch_accepts_direct_write, check_branch_owner,
+};
+use crate::hive_server::submit::SUBMIT_LOCK;
+use crate::logging::HiveLog;
+use crate::pb::{CherryPickReq, CherryPickRsp, SubmitConflict};
+use tonic::{Request, Response, Status};
+
+pub async fn handle_cherry_pick(
+    log: HiveLog,
+    r: Request<CherryPickReq>,
+) -> Result<Response<CherryPickRsp>, Status> {
+    let user = require_user(&r)?.clone();
+    let log = log.with_user(&user.username);
+    let _g = log.enter();
+
+    let request = r.into_inner();
+    log.info(&format!(
+        "cherry_pick received: source_branch={}, changelist_id={}, target_branch={}",
+        request.source_branch_id, request.changelist_id, request.target_branch_id
+    ));
+
+    let rsp = cherry_pick(
+        dao().as_ref(),
+        &user,
+        request,
+        chrono::Utc::now().timestamp(),
+    )
+    .await?;
+    log.info(&format!(
+        "cherry_pick finished: new_changelist_id={}, conflicts={}",
+        rsp.new_changelist_id,
+        rsp.conflicts.len()
+    ));
+    Ok(Response::new(rsp))
+}
+
+/// 文件版本 (generation, revision)，文件不存在时为 (0, 0)
+fn version(model: &Option<file_revisions::Model>) -> (i64, i64) {
+    model
+        .as_ref()
+        .map_or((0, 0), |m| (m.generation, m.revision))
+}
+
+/// 将源分支上 `changelist_id` 的文件变更复制为目标分支上的新 changelist。
+///
+/// 分支的 HEAD 指向一个 changelist，分支上的文件是从 HEAD 沿父 changelist 回溯的链上各文件最新的 revision。
+/// 对源 changelist 修改的每个文件，目标分支 HEAD 上的版本必须与该 changelist 的父 changelist 处的版本相同，
+/// 否则视为冲突，不写入任何数据。没有冲突时新的 changelist 复用源 revision 的 chunk，
+/// 并将目标分支的 HEAD 更新为新的 changelist。整个过程持有 `SUBMIT_LOCK` 的写锁。
+pub(crate) async fn cherry_pick(
+    dao: &dyn Dao,
+    user: &UserContext,
+    request: CherryPickReq,
+    now: i64,
+) -> Result<CherryPickRsp, Status> {
+    let changelist_id = request.changelist_id;
+    if changelist_id <= 0 {
+        return Err(Status::invalid_argument(format!(
+            "invalid changelist id {changelist_id}"
+        )));
+    }
+    let target_branch_id = request.target_branch_id.trim();
+    if target_branch_id.is_empty() {
+        return Err(Status::invalid_argument("target_branch_id is required"));
+    }
+
+    let _submit_guard = SUBMIT_LOCK.write().await;
+
+    let changelist = dao
+        .find_changelist_by_id(changelist_id)
+        .await
+        .map_err(|e| Status::internal(format!("database error while finding changelist: {e}")))?
+        .ok_or_else(|| Status::not_found(format!("changelist {changelist_id} not found")))?;
+    if let Some(squashed_into) = changelist_squashed_into(&changelist.metadata) {
+        return Err(Status::failed_precondition(format!(
+            "changelist {changelist_id} is squashed into {squashed_into}"
+        )));
+    }
+
+    let source_branch_id = request.source_branch_id.trim();
+    if !source_branch_id.is_empty() {
+        let source = dao
+            .find_branch_by_id(source_branch_id)
+            .await
+            .map_err(|e| Status::internal(format!("database error while finding branch: {e}")))?
+            .ok_or_else(|| Status::not_found(format!("branch '{source_branch_id}' not found")))?;
+        let on_source = dao
+            .is_changelist_on_chain(source.head_changelist_id, changelist_id)
+            .await
+            .map_err(|e| Status::internal(format!("database error while checking branch: {e}")))?;
+        if !on_source {
+            return Err(Status::failed_precondition(format!(
+                "changelist {changelist_id} is not on branch '{source_branch_id}'"
+            )));
+        }
+    }
+
+    let target = dao
+        .find_branch_by_id(target_branch_id)
+        .await
+        .map_err(|e| Status::internal(format!("database error while finding branch: {e}")))?
+        .ok_or_else(|| Status::not_found(format!("branch '{target_branch_id}' not found")))?;
+    check_branch_owner(&target, user)?;
+    check_branch_accepts_direct_write(&target)?;
+    let on_target = dao
+        .is_changelist_on_chain(target.head_changelist_id, changelist_id)
+        .await
+        .map_err(|e| Status::internal(format!("database error while checking branch: {e}")))?;
+    if on_target {
+        return Err(Status::failed_precondition(format!(
+            "branch '{target_branch_id}' already contains changelist {changelist_id}"
+        )));
+    }
+
+    let sources = dao
+        .list_file_revisions_by_changelist(changelist_id)
+        .await
+        .map_err(|e| Status::internal(format!("database error while listing revisions: {e}")))?;
+    if sources.is_empty() {
+        return Err(Status::failed_precondition(format!(
+            "changelist {changelist_id} has no file changes"
+        )));
+    }
+
+    let mut conflicts = Vec::new();
+    let mut revisions = Vec::new();
+    for source in sources {
+        let depot_path = source
+            .to_depot_path_string()
+            .map_err(|e| Status::internal(format!("invalid revision path: {e}")))?;
+        let db_error =
+            |e| Status::internal(format!("database error while finding {depot_path}: {e}"));
+
+        let base = dao
+            .find_file_revision_at(&depot_path, changelist.parent_changelist_id)
+            .await
+            .map_err(db_error)?;
+        let current = dao
+            .find_file_revision_at(&depot_path, target.head_changelist_id)
+            .await
+            .map_err(db_error)?;
+        if version(&base) != version(&current) {
+            let (expected_generation, expected_revision) = version(&base);
+            let (current_generation, current_revision) = version(&current);
+            conflicts.push(SubmitConflict {
+                path: depot_path,
+                expected_file_generation: expected_generation,
+                expected_file_revision: expected_revision,
+                current_file_generation: current_generation,
+                current_file_revision: current_revision,
+            });
+            continue;
+        }
+
+        // revision 在所有分支间统一编号，新的 revision 接在该文件最新的 revision 之后
+        let latest = dao
+            .find_latest_file_revision_by_depot_path(&depot_path)
+            .await
+            .map_err(db_error)?;
+        let (generation, revision) = match latest {
+            Some(m) => (m.generation, m.revision.saturating_add(1)),
+            None => (1, 1),
+        };
+        // 流水线标签属于源 revision，不随之复制
+        let mut metadata = source.metadata;
+        if let Some(metadata) = metadata.as_object_mut() {
+            metadata.remove("tags");
+        }
+        revisions.push(NewFileRevisionInput {
+            depot_path,
+            generation,
+            revision,
+            binary_id: source.binary_id,
+            size: source.size,
+            is_delete: source.is_delete,
+            created_at: now,
+            metadata,
+            file_metadata: new_file_metadata(&user.username, target_branch_id),
+        });
+    }
+    if !conflicts.is_empty() {
+        return Ok(CherryPickRsp {
+            new_changelist_id: 0,
+            conflicts,
+        });
+    }
+
+    let description = match request.description.trim() {
+        "" => changelist.description,
+        description => description.to_string(),
+    };
+    let paths: Vec<String> = revisions.iter().map(|r| r.depot_path.clone()).collect();
+    let new_changelist_id = dao
+        .cherry_pick(&CherryPickInput {
+            source_branch_id: source_branch_id.to_string(),
+            source_changelist_id: changelist_id,
+            target_branch_id: target_branch_id.to_string(),
+            author: user.username.clone(),
+            description,
+            committed_at: now,
+            revisions,
+        })
+        .await
+        .map_err(|e| Status::internal(format!("database error while cherry-picking: {e}")))?;
+
+    // changelist 已落库，记录失败不影响本次结果
+    for depot_path in &paths {
+        let _ = dao.add_branch_to_file(depot_path, target_branch_id).await;
+    }
+
+    Ok(CherryPickRsp {
+        new_changelist_id,
+        conflicts: vec![],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::AuthSource;
+    use crate::database::dao::{MockDao, changelist_cherry_picked_from};
+    use crv_core::metadata::{BranchDoc, BranchMetadata};
+    use tonic::Code;
+
+    fn revision(depot_path: &str, revision: i64) -> NewFileRevisionInput {
+        NewFileRevisionInput {
+            depot_path: depot_path.to_string(),
+            generation: 1,
+            revision,
+            binary_id: serde_json::json!([format!("{depot_path}#{revision}")]),
+            size: revision * 10,
+            is_delete: false,
+            created_at: revision * 100,
+            metadata: serde_json::json!({ "tags": ["approved"] }),
+            file_metadata: serde_json::json!({}),
+        }
+    }
+
+    fn user(username: &str) -> UserContext {
+        UserContext {
+            username: username.to_string(),
+            scopes: vec![],
+            source: AuthSource::Jwt,
+        }
+    }
+
+    fn request(
+        source_branch_id: &str,
+        changelist_id: i64,
+        target_branch_id: &str,
+    ) -> CherryPickReq {
+        CherryPickReq {
+            source_branch_id: source_branch_id.to_string(),
+            changelist_id,
+            target_branch_id: target_branch_id.to_string(),
+            description: String::new(),
+        }
+    }
+
+    /// changelist 1 添加 a.txt 与 b.txt，release 分支停留在 1；
+    /// main 分支上 changelist 2 修改 a.txt，3 修改 b.txt，4 再次修改 a.txt
+    async fn dao_with_branches() -> MockDao {
+        let dao = MockDao::default();
+        let submits = [
+            (
+                "add a and b",
+                vec![revision("//src/a.txt", 1), revision("//src/b.txt", 1)],
+            ),
+            ("edit a", vec![revision("//src/a.txt", 2)]),
+            ("fix b", vec![revision("//src/b.txt", 2)]),
+            ("edit a again", vec![revision("//src/a.txt", 3)]),
+        ];
+        for (i, (description, revisions)) in submits.into_iter().enumerate() {
+            dao.commit_submit(
+                "alice",
+                description,
+                (i as i64 + 1) * 100,
+                serde_json::json!({}),
+                revisions,
+            )
+            .await
+            .unwrap();
+        }
+        for (branch_id, head_changelist_id) in [("main", 4), ("release", 1)] {
+            dao.insert_branch(&BranchDoc {
+                id: branch_id.to_string(),
+                created_at: 0,
+                created_by: "alice".to_string(),
+                head_changelist_id,
+                is_protected: false,
+                metadata: BranchMetadata {
+                    description: branch_id.to_string(),
+                    owners: vec!["alice".to_string()],
+                    description_regex: None,
+                },
+            })
+            .await
+            .unwrap();
+        }
+        dao
+    }
+
+    #[tokio::test]
+    async fn clean_cherry_pick_creates_changelist_on_target() {
+        let dao = dao_with_branches().await;
+
+        let rsp = cherry_pick(&dao, &user("alice"), request("main", 3, "release"), 1000)
+            .await
+            .unwrap();
+        assert!(rsp.conflicts.is_empty());
+        assert_eq!(rsp.new_changelist_id, 5);
+
+        let changelist = dao.find_changelist_by_id(5).await.unwrap().unwrap();
+        assert_eq!(changelist.description, "fix b");
+        assert_eq!(changelist.author, "alice");
+        assert_eq!(changelist_cherry_picked_from(&changelist.metadata), Some(3));
+        assert_eq!(
+            dao.find_branch_by_id("release")
+                .await
+                .unwrap()
+                .unwrap()
+                .head_changelist_id,
+            5
+        );
+
+        // 新的 revision 复用源 revision 的 chunk，编号接在最新的 revision 之后
+        let revisions = dao.list_file_revisions_by_changelist(5).await.unwrap();
+        assert_eq!(revisions.len(), 1);
+        let picked = &revisions[0];
+        assert_eq!(picked.to_depot_path_string().unwrap(), "//src/b.txt");
+        assert_eq!((picked.generation, picked.revision), (1, 3));
+        assert_eq!(picked.binary_id, serde_json::json!(["//src/b.txt#2"]));
+        assert_eq!(picked.size, 20);
+        assert_eq!(picked.metadata, serde_json::json!({}));
+    }
+
+    #[tokio::test]
+    async fn conflicting_cherry_pick_writes_nothing() {
+        let dao = dao_with_branches().await;
+
+        // release 上的 a.txt 仍是 revision 1，而 changelist 4 基于 revision 2 修改
+        let rsp = cherry_pick(&dao, &user("alice"), request("main", 4, "release"), 1000)
+            .await
+            .unwrap();
+        assert_eq!(rsp.new_changelist_id, 0);
+        assert_eq!(
+            rsp.conflicts,
+            vec![SubmitConflict {
+                path: "//src/a.txt".to_string(),
+                expected_file_generation: 1,
+                expected_file_revision: 2,
+                current_file_generation: 1,
+                current_file_revision: 1,
+            }]
+        );
+        assert_eq!(dao.find_latest_changelist_id().await.unwrap(), Some(4));
+        assert_eq!(
+            dao.find_branch_by_id("release")
+                .await
+                .unwrap()
+                .unwrap()
+                .head_changelist_id,
+            1
+        );
+    }
+
+    #[tokio::test]
+    async fn rejects_invalid_cherry_picks() {
+        let dao = dao_with_branches().await;
+
+        // 目标分支已经包含该 changelist
+        let err = cherry_pick(&dao, &user("alice"), request("", 1, "release"), 1000)
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::FailedPrecondition);
+
+        // changelist 不在源分支上
+        let err = cherry_pick(&dao, &user("alice"), request("release", 3, "main"), 1000)
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::FailedPrecondition);
+
+        let err = cherry_pick(&dao, &user("alice"), request("main", 3, "dev"), 1000)
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::NotFound);
+
+        // 有所有者的分支只有所有者可以写入
+        let err = cherry_pick(&dao, &user("bob"), request("main", 3, "release"), 1000)
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::PermissionDenied);
+
+        // 受保护分支只接受 merge，所有者也不能 cherry-pick
+        dao.set_branch_protection("release", true).await.unwrap();
+        let err = cherry_pick(&dao, &user("alice"), request("main", 3, "release"), 1000)
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::PermissionDenied);
+    }
+}
@@ -0,0 +1,251 @@
+use crate::database::dao::{Dao, read_dao};
+use crate::logging::HiveLog;
+use crate::pb::{ChangelistSummary, GetChangelistChainReq, GetChangelistChainRsp};
+use tonic::{Request, Response, Status};
+
+/// 单次最多返回的 changelist 数量
+const MAX_CHAIN_LEN: usize = 10_000;
+
+pub async fn handle_get_changelist_chain(
+    log: HiveLog,
+    r: Request<GetChangelistChainReq>,
+) -> Result<Response<GetChangelistChainRsp>, Status> {
+    let _g = log.enter();
+
+    let request = r.into_inner();
+    log.info(&format!(
+        "get_changelist_chain received: branch={}, from={}, to={}",
+        request.branch_id, request.from_cl, request.to_cl
+    ));
+
+    let rsp = get_changelist_chain(read_dao().as_ref(), request).await?;
+    log.info(&format!(
+        "get_changelist_chain finished: count={}, truncated={}",
+        rsp.changelists.len(),
+        rsp.truncated
+    ));
+    Ok(Response::new(rsp))
+}
+
+/// 从 `to_cl` 向前列出分支上直到 `from_cl` 的 changelist，返回时按从旧到新排列。
+///
+/// 分支的历史是从 HEAD 沿 parent 回溯的 changelist 链，其他分支的提交不包含在内；
+/// `to_cl` 必须位于这条链上，超过 `MAX_CHAIN_LEN` 条时只保留最接近 `to_cl` 的部分。
+pub(crate) async fn get_changelist_chain(
+    dao: &dyn Dao,
+    request: GetChangelistChainReq,
+) -> Result<GetChangelistChainRsp, Status> {
+    let branch_id = request.branch_id.trim();
+    let head = dao
+        .find_branch_head(branch_id)
+        .await
+        .map_err(|e| Status::internal(format!("database error while finding branch: {e}")))?
+        .ok_or_else(|| Status::not_found(format!("branch '{branch_id}' not found")))?;
+
+    let to = match request.to_cl {
+        0 => head,
+        to => to,
+    };
+    let from = request.from_cl.max(1);
+    if request.from_cl < 0 || to < 0 || from > to {
+        return Err(Status::invalid_argument(format!(
+            "invalid changelist range [{}, {}]",
+            request.from_cl, request.to_cl
+        )));
+    }
+    let on_branch = dao
+        .is_changelist_on_chain(head, to)
+        .await
+        .map_err(|e| Status::internal(format!("database error while walking history: {e}")))?;
+    if !on_branch {
+        return Err(Status::invalid_argument(format!(
+            "changelist {to} is not on branch '{branch_id}'"
+        )));
+    }
+
+    let mut changelists = dao
+        .list_changelist_chain(from, to, MAX_CHAIN_LEN as u64 + 1)
+        .await
+        .map_err(|e| Status::internal(format!("database error while listing changelists: {e}")))?;
+    let truncated = changelists.len() > MAX_CHAIN_LEN;
+    changelists.truncate(MAX_CHAIN_LEN);
+    changelists.reverse();
+
+    Ok(GetChangelistChainRsp {
+        changelists: changelists
+            .into_iter()
+            .map(|cl| ChangelistSummary {
+                changelist_id: cl.id,
+                author: cl.author,
+                description: cl.description,
+                committed_at: cl.committed_at,
+                files_count: cl.files_count.max(0) as u64,
+            })
+            .collect(),
+        truncated,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::dao::MockDao;
+    use crv_core::metadata::{BranchDoc, BranchMetadata};
+    use tonic::Code;
+
+    async fn dao_with_changelists(count: i64) -> MockDao {
+        let dao = MockDao::default();
+        for i in 1..=count {
+            dao.insert_changelist("alice", &format!("cl {i}"), i, serde_json::json!({}))
+                .await
+                .unwrap();
+        }
+        dao
+    }
+
+    async fn chain(dao: &MockDao, branch_id: &str, from_cl: i64, to_cl: i64) -> Vec<i64> {
+        let rsp = get_changelist_chain(
+            dao,
+            GetChangelistChainReq {
+                branch_id: branch_id.to_string(),
+                from_cl,
+                to_cl,
+            },
+        )
+        .await
+        .unwrap();
+        assert!(!rsp.truncated);
+        rsp.changelists.iter().map(|cl| cl.changelist_id).collect()
+    }
+
+    #[tokio::test]
+    async fn ranges_are_returned_oldest_first() {
+        let dao = dao_with_changelists(1_000).await;
+
+        assert_eq!(chain(&dao, "", 7, 7).await, vec![7]);
+        assert_eq!(chain(&dao, "", 11, 20).await, (11..=20).collect::<Vec<_>>());
+        assert_eq!(
+            chain(&dao, "", 1, 1_000).await,
+            (1..=1_000).collect::<Vec<_>>()
+        );
+        // 0 表示从第一个 changelist 开始 / 到 HEAD 为止
+        assert_eq!(chain(&dao, "", 0, 0).await.len(), 1_000);
+
+        let rsp = get_changelist_chain(
+            &dao,
+            GetChangelistChainReq {
+                branch_id: String::new(),
+                from_cl: 3,
+                to_cl: 3,
+            },
+        )
+        .await
+        .unwrap();
+        assert_eq!(rsp.changelists[0].author, "alice");
+        assert_eq!(rsp.changelists[0].description, "cl 3");
+        assert_eq!(rsp.changelists[0].committed_at, 3);
+        assert_eq!(rsp.changelists[0].files_count, 0);
+    }
+
+    #[tokio::test]
+    async fn branch_chain_stops_at_head() {
+        let dao = dao_with_changelists(10).await;
+        dao.insert_branch(&BranchDoc {
+            id: "main".to_string(),
+            created_at: 0,
+            created_by: "alice".to_string(),
+            head_changelist_id: 6,
+            is_protected: false,
+            metadata: BranchMetadata {
+                description: String::new(),
+                owners: vec![],
+                description_regex: None,
+            },
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(chain(&dao, "main", 4, 0).await, vec![4, 5, 6]);
+        for (branch_id, from_cl, to_cl, code) in [
+            ("main", 4, 8, Code::InvalidArgument),
+            ("main", 5, 4, Code::InvalidArgument),
+            ("dev", 1, 2, Code::NotFound),
+        ] {
+            let err = get_changelist_chain(
+                &dao,
+                GetChangelistChainReq {
+                    branch_id: branch_id.to_string(),
+                    from_cl,
+                    to_cl,
+                },
+            )
+            .await
+            .unwrap_err();
+            assert_eq!(err.code(), code);
+        }
+    }
+
+    #[tokio::test]
+    async fn chain_skips_commits_of_other_branches() {
+        let dao = dao_with_changelists(3).await;
+        for (branch_id, head_changelist_id) in [("main", 3), ("dev", 2)] {
+            dao.insert_branch(&BranchDoc {
+                id: branch_id.to_string(),
+                created_at: 0,
+                created_by: "alice".to_string(),
+                head_changelist_id,
+                is_protected: false,
+                metadata: BranchMetadata {
+                    description: String::new(),
+                    owners: vec![],
+                    description_regex: None,
+                },
+            })
+            .await
+            .unwrap();
+        }
+        // 两个分支交替提交：main 得到 4、6，dev 得到 5
+        for branch_id in ["main", "dev", "main"] {
+            dao.commit_submit_to_branch(branch_id, "alice", "", 0, serde_json::json!({}), vec![])
+                .await
+                .unwrap();
+        }
+
+        assert_eq!(chain(&dao, "main", 0, 0).await, vec![1, 2, 3, 4, 6]);
+        assert_eq!(chain(&dao, "main", 3, 6).await, vec![3, 4, 6]);
+        assert_eq!(chain(&dao, "dev", 0, 0).await, vec![1, 2, 5]);
+        assert_eq!(chain(&dao, "", 0, 0).await, vec![1, 2, 3]);
+        let err = get_changelist_chain(
+            &dao,
+            GetChangelistChainReq {
+                branch_id: "dev".to_string(),
+                from_cl: 0,
+                to_cl: 4,
+            },
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(err.code(), Code::InvalidArgument);
+    }
+
+    #[tokio::test]
+    async fn long_chains_are_truncated_near_to_cl() {
+        let count = MAX_CHAIN_LEN as i64 + 5;
+        let dao = dao_with_changelists(count).await;
+
+        let rsp = get_changelist_chain(
+            &dao,
+            GetChangelistChainReq {
+                branch_id: String::new(),
+                from_cl: 1,
+                to_cl: count,
+            },
+        )
+        .await
+        .unwrap();
+        assert!(rsp.truncated);
+        assert_eq!(rsp.changelists.len(), MAX_CHAIN_LEN);
+        assert_eq!(rsp.changelists[0].changelist_id, 6);
+        assert_eq!(rsp.changelists.last().unwrap().changelist_id, count);
+    }
+}
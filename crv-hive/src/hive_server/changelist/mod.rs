@@ -0,0 +1,31 @@
+pub mod cherry_pick;
+pub mod get_changelist_chain;
+pub mod squash_changelists;
+
+use crate::database::dao::{Dao, changelist_squashed_into};
+use tonic::Status;
+
+/// 跟随 `squashed_into` 的次数上限，防止元数据异常时陷入死循环
+const MAX_SQUASH_REDIRECTS: usize = 64;
+
+/// 将请求中引用的 changelist id 重定向到其被压缩进的 changelist。
+///
+/// 未被压缩或不存在的 changelist 原样返回，由调用方决定如何处理。
+pub(crate) async fn resolve_changelist_id(
+    dao: &dyn Dao,
+    changelist_id: i64,
+) -> Result<i64, Status> {
+    let mut current = changelist_id;
+    for _ in 0..MAX_SQUASH_REDIRECTS {
+        let changelist = dao.find_changelist_by_id(current).await.map_err(|e| {
+            Status::internal(format!("database error while finding changelist: {e}"))
+        })?;
+        match changelist.and_then(|cl| changelist_squashed_into(&cl.metadata)) {
+            Some(next) => current = next,
+            None => return Ok(current),
+        }
+    }
+    Err(Status::internal(format!(
+        "too many squash redirects while resolving changelist {changelist_id}"
+    )))
+}
@@ -0,0 +1,346 @@
+use crate::auth::{ADMIN_SCOPE, require_scope};
+use crate::database::dao::{Dao, SquashChangelistsInput, changelist_squashed_into, dao};
+use crate::hive_server::branch::set_protection::check_branch_accepts_direct_write;
+use crate::hive_server::submit::SUBMIT_LOCK;
+use crate::logging::HiveLog;
+use crate::pb::{SquashChangelistsReq, SquashChangelistsRsp};
+use tonic::{Request, Response, Status};
+
+pub async fn handle_squash_changelists(
+    log: HiveLog,
+    r: Request<SquashChangelistsReq>,
+) -> Result<Response<SquashChangelistsRsp>, Status> {
+    let user = require_scope(&r, ADMIN_SCOPE)?.clone();
+    let log = log.with_user(&user.username);
+    let _g = log.enter();
+
+    let request = r.into_inner();
+    log.info(&format!(
+        "squash_changelists received: branch={}, from={}, to={}",
+        request.branch_id, request.from_cl, request.to_cl
+    ));
+
+    let rsp = squash_changelists(
+        dao().as_ref(),
+        request,
+        &user.username,
+        chrono::Utc::now().timestamp(),
+    )
+    .await?;
+    log.info(&format!(
+        "squash_changelists finished: new_changelist_id={}",
+        rsp.new_changelist_id
+    ));
+    Ok(Response::new(rsp))
+}
+
+/// 将 `[from_cl, to_cl]` 区间内的 changelist 压缩为一个新的 changelist。
+///
+/// 只能压缩最新的一段历史：`to_cl` 必须是最新的 changelist，指定分支时还必须是该分支的 HEAD；
+/// 区间内的 changelist 必须是同一分支上首尾相接的一段历史，不能夹杂其他分支的提交。
+/// 整个过程持有 `SUBMIT_LOCK` 的写锁，期间不会有新的提交写入。
+pub(crate) async fn squash_changelists(
+    dao: &dyn Dao,
+    request: SquashChangelistsReq,
+    author: &str,
+    now: i64,
+) -> Result<SquashChangelistsRsp, Status> {
+    let (from, to) = (request.from_cl, request.to_cl);
+    if from <= 0 || from > to {
+        return Err(Status::invalid_argument(format!(
+            "invalid changelist range [{from}, {to}]"
+        )));
+    }
+
+    let _submit_guard = SUBMIT_LOCK.write().await;
+
+    let latest = dao
+        .find_latest_changelist_id()
+        .await
+        .map_err(|e| Status::internal(format!("database error while finding changelist: {e}")))?;
+    if latest != Some(to) {
+        return Err(Status::failed_precondition(format!(
+            "changelist {to} is not the latest changelist"
+        )));
+    }
+
+    let branch_id = request.branch_id.trim();
+    if !branch_id.is_empty() {
+        let branch = dao
+            .find_branch_by_id(branch_id)
+            .await
+            .map_err(|e| Status::internal(format!("database error while finding branch: {e}")))?
+            .ok_or_else(|| Status::not_found(format!("branch '{branch_id}' not found")))?;
+        check_branch_accepts_direct_write(&branch)?;
+        if branch.head_changelist_id != to {
+            return Err(Status::failed_precondition(format!(
+                "changelist {to} is not the head of branch '{branch_id}'"
+            )));
+        }
+    }
+
+    // 快照创建后不可修改，其 changelist 不能被压缩
+    let snapshots = dao
+        .list_snapshots("")
+        .await
+        .map_err(|e| Status::internal(format!("database error while listing snapshots: {e}")))?;
+    if let Some(snapshot) = snapshots
+        .iter()
+        .find(|s| (from..=to).contains(&s.changelist_id))
+    {
+        return Err(Status::failed_precondition(format!(
+            "changelist {} is pinned by snapshot '{}'",
+            snapshot.changelist_id, snapshot.name
+        )));
+    }
+
+    let mut descriptions = Vec::new();
+    let mut range_branch_id: Option<String> = None;
+    for changelist_id in from..=to {
+        let changelist = dao
+            .find_changelist_by_id(changelist_id)
+            .await
+            .map_err(|e| Status::internal(format!("database error while finding changelist: {e}")))?
+            .ok_or_else(|| Status::not_found(format!("changelist {changelist_id} not found")))?;
+        if let Some(squashed_into) = changelist_squashed_into(&changelist.metadata) {
+            return Err(Status::failed_precondition(format!(
+                "changelist {changelist_id} is already squashed into {squashed_into}"
+            )));
+        }
+        let range_branch_id = range_branch_id.get_or_insert_with(|| changelist.branch_id.clone());
+        if changelist_id > from
+            && (changelist.parent_changelist_id != changelist_id - 1
+                || changelist.branch_id != *range_branch_id)
+        {
+            return Err(Status::failed_precondition(format!(
+                "changelists [{from}, {to}] are not a contiguous history of one branch"
+            )));
+        }
+        if !changelist.description.is_empty() {
+            descriptions.push(changelist.description);
+        }
+    }
+
+    let description = match request.new_description.trim() {
+        "" => descriptions.join("\n"),
+        description => description.to_string(),
+    };
+    let new_changelist_id = dao
+        .squash_changelists(&SquashChangelistsInput {
+            branch_id: branch_id.to_string(),
+            from_changelist_id: from,
+            to_changelist_id: to,
+            author: author.to_string(),
+            description,
+            committed_at: now,
+        })
+        .await
+        .map_err(|e| {
+            Status::internal(format!("database error while squashing changelists: {e}"))
+        })?;
+
+    Ok(SquashChangelistsRsp { new_changelist_id })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::dao::{MockDao, NewFileRevisionInput};
+    use crate::hive_server::changelist::resolve_changelist_id;
+    use crate::hive_server::fetch::get_file_history::get_file_history;
+    use crate::pb::GetFileHistoryReq;
+    use crv_core::metadata::{BranchDoc, BranchMetadata, SnapshotDoc};
+    use tonic::Code;
+
+    fn revision(depot_path: &str, revision: i64, size: i64) -> NewFileRevisionInput {
+        NewFileRevisionInput {
+            depot_path: depot_path.to_string(),
+            generation: 1,
+            revision,
+            binary_id: serde_json::json!([format!("chunk-{revision}")]),
+            size,
+            is_delete: false,
+            created_at: revision * 100,
+            metadata: serde_json::json!({}),
+            file_metadata: serde_json::json!({}),
+        }
+    }
+
+    fn request(branch_id: &str, from_cl: i64, to_cl: i64) -> SquashChangelistsReq {
+        SquashChangelistsReq {
+            branch_id: branch_id.to_string(),
+            from_cl,
+            to_cl,
+            new_description: String::new(),
+        }
+    }
+
+    /// changelist 1/2/3 依次修改 a.txt，changelist 2 还修改了 b.txt，main 分支 HEAD 为 3
+    async fn dao_with_history() -> MockDao {
+        let dao = MockDao::default();
+        let submits = [
+            ("add a", vec![revision("//src/a.txt", 1, 10)]),
+            (
+                "edit a, add b",
+                vec![
+                    revision("//src/a.txt", 2, 20),
+                    revision("//src/b.txt", 1, 5),
+                ],
+            ),
+            ("edit a again", vec![revision("//src/a.txt", 3, 30)]),
+        ];
+        for (i, (description, revisions)) in submits.into_iter().enumerate() {
+            dao.commit_submit(
+                "alice",
+                description,
+                (i as i64 + 1) * 100,
+                serde_json::json!({}),
+                revisions,
+            )
+            .await
+            .unwrap();
+        }
+        dao.insert_branch(&BranchDoc {
+            id: "main".to_string(),
+            created_at: 0,
+            created_by: "alice".to_string(),
+            head_changelist_id: 3,
+            is_protected: false,
+            metadata: BranchMetadata {
+                description: "main".to_string(),
+                owners: vec![],
+                description_regex: None,
+            },
+        })
+        .await
+        .unwrap();
+        dao
+    }
+
+    fn history_of(path: &str) -> GetFileHistoryReq {
+        GetFileHistoryReq {
+            path: path.to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn squashed_history_is_browsable() {
+        let dao = dao_with_history().await;
+
+        let rsp = squash_changelists(&dao, request("main", 2, 3), "admin", 1000)
+            .await
+            .unwrap();
+        assert_eq!(rsp.new_changelist_id, 4);
+
+        let squashed = dao.find_changelist_by_id(4).await.unwrap().unwrap();
+        assert_eq!(squashed.description, "edit a, add b\nedit a again");
+        assert_eq!(squashed.author, "admin");
+        assert_eq!(
+            dao.find_branch_by_id("main")
+                .await
+                .unwrap()
+                .unwrap()
+                .head_changelist_id,
+            4
+        );
+
+        // a.txt 在区间内的两个 revision 合并为一个，归属到新的 changelist
+        let history = get_file_history(&dao, history_of("//src/a.txt"))
+            .await
+            .unwrap();
+        let entries: Vec<(i64, i64, i64)> = history
+            .entries
+            .iter()
+            .map(|e| (e.revision, e.changelist_id, e.size))
+            .collect();
+        assert_eq!(entries, vec![(3, 4, 30), (1, 1, 10)]);
+        assert_eq!(
+            history.entries[0].description,
+            "edit a, add b\nedit a again"
+        );
+        assert_eq!(history.entries[1].author, "alice");
+
+        let history = get_file_history(&dao, history_of("//src/b.txt"))
+            .await
+            .unwrap();
+        assert_eq!(history.entries.len(), 1);
+        assert_eq!(history.entries[0].changelist_id, 4);
+
+        // 引用旧 changelist 的请求被重定向
+        assert_eq!(resolve_changelist_id(&dao, 2).await.unwrap(), 4);
+        assert_eq!(resolve_changelist_id(&dao, 3).await.unwrap(), 4);
+        assert_eq!(resolve_changelist_id(&dao, 1).await.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn rejects_invalid_ranges() {
+        let dao = dao_with_history().await;
+
+        let err = squash_changelists(&dao, request("", 3, 2), "admin", 1000)
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::InvalidArgument);
+
+        // 只能压缩到最新的 changelist
+        let err = squash_changelists(&dao, request("", 1, 2), "admin", 1000)
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::FailedPrecondition);
+
+        let err = squash_changelists(&dao, request("dev", 1, 3), "admin", 1000)
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::NotFound);
+
+        // 受保护分支只接受 merge
+        dao.set_branch_protection("main", true).await.unwrap();
+        let err = squash_changelists(&dao, request("main", 2, 3), "admin", 1000)
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::PermissionDenied);
+        dao.set_branch_protection("main", false).await.unwrap();
+
+        // 已经被压缩的 changelist 不能再次压缩
+        squash_changelists(&dao, request("", 2, 3), "admin", 1000)
+            .await
+            .unwrap();
+        let err = squash_changelists(&dao, request("", 3, 4), "admin", 1000)
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::FailedPrecondition);
+    }
+
+    #[tokio::test]
+    async fn snapshot_changelists_cannot_be_squashed() {
+        let dao = dao_with_history().await;
+        dao.insert_snapshot(&SnapshotDoc {
+            id: 0,
+            name: "release".to_string(),
+            branch_id: "main".to_string(),
+            changelist_id: 2,
+            created_by: "alice".to_string(),
+            created_at: 0,
+            description: String::new(),
+        })
+        .await
+        .unwrap();
+
+        let err = squash_changelists(&dao, request("main", 1, 3), "admin", 1000)
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::FailedPrecondition);
+        assert!(err.message().contains("release"));
+        assert_eq!(
+            dao.find_changelist_by_id(2)
+                .await
+                .unwrap()
+                .and_then(|cl| changelist_squashed_into(&cl.metadata)),
+            None
+        );
+
+        // 快照之后的 changelist 仍然可以压缩
+        squash_changelists(&dao, request("main", 3, 3), "admin", 1000)
+            .await
+            .unwrap();
+    }
+}
@@ -5,7 +5,8 @@ use tonic::{Request, Response, Status};
 use crate::hive_server::repository_manager;
 use crate::logging::HiveLog;
 use crate::pb::{DownloadFileChunkReq, DownloadFileChunkResp};
-use crv_core::repository::{blake3_hex_to_hash, RepositoryError};
+use crv_core::metadata::CompressionType;
+use crv_core::repository::{RepositoryError, blake3_hex_to_hash};
 
 pub type DownloadFileChunkStream = ReceiverStream<Result<DownloadFileChunkResp, Status>>;
 
@@ -20,6 +21,10 @@ pub async fn handle_download_file_chunk(
     if req.chunk_hashes.is_empty() {
         return Err(Status::invalid_argument("chunk_hashes is empty"));
     }
+    // chunk 在仓库中的存储方式与传输无关，按请求的方式重新编码后发送
+    let compression_type = CompressionType::parse(&req.compression).ok_or_else(|| {
+        Status::invalid_argument(format!("unsupported compression: {}", req.compression))
+    })?;
 
     let repo = repository_manager()?;
     let (tx, rx) = mpsc::channel::<Result<DownloadFileChunkResp, Status>>(32);
@@ -33,9 +38,7 @@ pub async fn handle_download_file_chunk(
     if packet_size as usize > MAX_PACKET_SIZE {
         packet_size = MAX_PACKET_SIZE as i64;
     }
-    let packet_size = packet_size
-        .try_into()
-        .unwrap_or(MAX_PACKET_SIZE);
+    let packet_size = packet_size.try_into().unwrap_or(MAX_PACKET_SIZE);
 
     tokio::spawn(async move {
         let _g = log_spawn.enter();
@@ -68,18 +71,31 @@ pub async fn handle_download_file_chunk(
                 }
                 Err(e) => {
                     let _ = tx
-                        .send(Err(Status::internal(format!(
-                            "read chunk failed: {}",
-                            e
-                        ))))
+                        .send(Err(Status::internal(format!("read chunk failed: {}", e))))
                         .await;
                     break;
                 }
             };
 
+            let uncompressed_size = data.len() as u32;
+            let data = match compression_type {
+                CompressionType::None => data,
+                CompressionType::Zstd => match compression_type.compress(&data) {
+                    Ok(data) => data,
+                    Err(e) => {
+                        let _ = tx
+                            .send(Err(Status::internal(format!(
+                                "compress chunk failed: {}",
+                                e
+                            ))))
+                            .await;
+                        break;
+                    }
+                },
+            };
+
             let total_len = data.len();
             let total_len_u64 = total_len as u64;
-            let total_len_u32 = total_len as u32;
 
             if total_len == 0 {
                 let rsp = DownloadFileChunkResp {
@@ -87,8 +103,8 @@ pub async fn handle_download_file_chunk(
                     offset: 0,
                     content: Vec::new(),
                     size: total_len_u64,
-                    compression: "none".to_string(),
-                    uncompressed_size: total_len_u32,
+                    compression: compression_type.as_str().to_string(),
+                    uncompressed_size,
                 };
                 if tx.send(Ok(rsp)).await.is_err() {
                     break;
@@ -103,9 +119,7 @@ pub async fn handle_download_file_chunk(
                 let offset_i64 = match i64::try_from(offset) {
                     Ok(v) => v,
                     Err(_) => {
-                        let _ = tx
-                            .send(Err(Status::internal("offset overflow")))
-                            .await;
+                        let _ = tx.send(Err(Status::internal("offset overflow"))).await;
                         return;
                     }
                 };
@@ -115,8 +129,8 @@ pub async fn handle_download_file_chunk(
                     offset: offset_i64,
                     content,
                     size: total_len_u64,
-                    compression: "none".to_string(),
-                    uncompressed_size: total_len_u32,
+                    compression: compression_type.as_str().to_string(),
+                    uncompressed_size,
                 };
 
                 if tx.send(Ok(rsp)).await.is_err() {
@@ -137,7 +151,9 @@ mod tests {
     use super::*;
     use crate::config::{entity::ConfigEntity, holder::try_set_config};
     use crate::logging::init_logging;
-    use crv_core::repository::{blake3_hash_to_hex, compute_chunk_hash, Compression, RepositoryError};
+    use crv_core::repository::{
+        Compression, RepositoryError, blake3_hash_to_hex, compute_chunk_hash,
+    };
     use std::sync::OnceLock;
     use tokio_stream::StreamExt;
 
@@ -184,6 +200,7 @@ mod tests {
         let req = DownloadFileChunkReq {
             chunk_hashes: vec![hash_hex.clone()],
             packet_size: 4,
+            compression: String::new(),
         };
 
         let log = HiveLog::new("DownloadFileChunk(test_download_single_chunk_split)");
@@ -220,6 +237,7 @@ mod tests {
         let req = DownloadFileChunkReq {
             chunk_hashes: vec!["invalid-hash".to_string()],
             packet_size: 0,
+            compression: String::new(),
         };
         let log = HiveLog::new("DownloadFileChunk(test_download_invalid_chunk_hash)");
         let resp = handle_download_file_chunk(log, Request::new(req))
@@ -240,6 +258,7 @@ mod tests {
         let req = DownloadFileChunkReq {
             chunk_hashes: vec![],
             packet_size: 0,
+            compression: String::new(),
         };
         let log = HiveLog::new("DownloadFileChunk(test_download_empty_hashes)");
         let err = handle_download_file_chunk(log, Request::new(req))
@@ -267,6 +286,7 @@ mod tests {
         let req = DownloadFileChunkReq {
             chunk_hashes: vec![hash_hex.clone()],
             packet_size: (4 * 1024 * 1024) as i64,
+            compression: String::new(),
         };
 
         let log = HiveLog::new("DownloadFileChunk(test_download_large_chunk_17mb)");
@@ -322,6 +342,7 @@ mod tests {
         let req = DownloadFileChunkReq {
             chunk_hashes: vec![hash_a_hex.clone(), hash_b_hex.clone()],
             packet_size: 0,
+            compression: String::new(),
         };
 
         let log = HiveLog::new("DownloadFileChunk(test_download_multiple_chunks_in_one_request)");
@@ -347,4 +368,71 @@ mod tests {
         assert_eq!(got_a, data_a);
         assert_eq!(got_b, data_b);
     }
+
+    #[tokio::test]
+    async fn test_download_chunk_in_requested_compression() {
+        let _g = test_mutex().lock().await;
+        init_test_repo();
+
+        let repo = crate::hive_server::repository_manager().expect("repository_manager");
+        // 一个以原始内容存储，一个以 zstd 存储，下载时都按请求的方式编码
+        let raw_data = b"stored without compression ".repeat(256);
+        let zstd_data = b"stored with zstd ".repeat(256);
+        for (data, compression) in [
+            (&raw_data, Compression::None),
+            (&zstd_data, Compression::Zstd),
+        ] {
+            match repo.write_chunk(data, compression) {
+                Ok(_) => {}
+                Err(RepositoryError::DuplicateHash { .. }) => {}
+                Err(e) => panic!("write_chunk failed: {e}"),
+            }
+        }
+
+        for compression_type in [CompressionType::None, CompressionType::Zstd] {
+            for data in [&raw_data, &zstd_data] {
+                let hash_hex = blake3_hash_to_hex(&compute_chunk_hash(data));
+                let req = DownloadFileChunkReq {
+                    chunk_hashes: vec![hash_hex],
+                    packet_size: 64,
+                    compression: compression_type.as_str().to_string(),
+                };
+                let log =
+                    HiveLog::new("DownloadFileChunk(test_download_chunk_in_requested_compression)");
+                let mut stream = handle_download_file_chunk(log, Request::new(req))
+                    .await
+                    .expect("handle_download_file_chunk")
+                    .into_inner();
+
+                let mut received = Vec::new();
+                while let Some(item) = stream.next().await {
+                    let rsp = item.expect("stream item ok");
+                    assert_eq!(rsp.compression, compression_type.as_str());
+                    assert_eq!(rsp.uncompressed_size, data.len() as u32);
+                    received.extend_from_slice(&rsp.content);
+                }
+                assert_eq!(compression_type.decompress(&received).unwrap(), *data);
+                if compression_type == CompressionType::Zstd {
+                    assert!(received.len() < data.len());
+                }
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_download_unknown_compression() {
+        let _g = test_mutex().lock().await;
+        init_test_repo();
+
+        let req = DownloadFileChunkReq {
+            chunk_hashes: vec![blake3_hash_to_hex(&compute_chunk_hash(b"any"))],
+            packet_size: 0,
+            compression: "lz4".to_string(),
+        };
+        let log = HiveLog::new("DownloadFileChunk(test_download_unknown_compression)");
+        let err = handle_download_file_chunk(log, Request::new(req))
+            .await
+            .expect_err("should reject unknown compression");
+        assert_eq!(err.code(), tonic::Code::InvalidArgument);
+    }
 }
\ No newline at end of file
@@ -0,0 +1,128 @@
+use crv_core::metadata::derive_file_id_from_path;
+use tonic::{Request, Response, Status};
+
+use crate::database::dao::{Dao, file_seen_on_branches, read_dao};
+use crate::logging::HiveLog;
+use crate::pb::{FileInfo, FindFileByPathReq, FindFileByPathRsp};
+
+pub async fn handle_find_file_by_path(
+    log: HiveLog,
+    request: Request<FindFileByPathReq>,
+) -> Result<Response<FindFileByPathRsp>, Status> {
+    let _g = log.enter();
+    let req = request.into_inner();
+
+    log.info(&format!("find_file_by_path: path={}", req.path));
+
+    let rsp = find_file_by_path(read_dao().as_ref(), req).await?;
+    log.info(&format!("file found: {}", rsp.file.is_some()));
+    Ok(Response::new(rsp))
+}
+
+/// 按 depot path 查找文件，同时返回由路径计算出的文件 ID。
+///
+/// `files` 表以 depot path 为主键，因此直接按路径查询，不需要先计算文件 ID。
+pub(crate) async fn find_file_by_path(
+    dao: &dyn Dao,
+    req: FindFileByPathReq,
+) -> Result<FindFileByPathRsp, Status> {
+    let path = crv_core::path::basic::DepotPath::parse(&req.path)
+        .map_err(|e| Status::invalid_argument(format!("invalid path '{}': {e}", req.path)))?;
+    let depot_path = path.to_custom_string();
+
+    let file = dao
+        .find_file_by_depot_path(&depot_path)
+        .await
+        .map_err(|e| Status::internal(format!("database error while finding file: {e}")))?;
+
+    Ok(FindFileByPathRsp {
+        file_id: derive_file_id_from_path(&path),
+        file: file.map(|f| FileInfo {
+            path: depot_path,
+            created_at: f.created_at,
+            first_introduced_by: f.metadata["first_introduced_by"]
+                .as_str()
+                .unwrap_or_default()
+                .to_string(),
+            branches: file_seen_on_branches(&f.metadata),
+        }),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::dao::{MockDao, NewFileRevisionInput, new_file_metadata};
+    use crv_core::path::basic::DepotPath;
+    use tonic::Code;
+
+    fn request(path: &str) -> FindFileByPathReq {
+        FindFileByPathReq {
+            path: path.to_string(),
+        }
+    }
+
+    async fn dao_with_file(depot_path: &str) -> MockDao {
+        let dao = MockDao::default();
+        dao.commit_submit(
+            "alice",
+            "add file",
+            100,
+            serde_json::json!({}),
+            vec![NewFileRevisionInput {
+                depot_path: depot_path.to_string(),
+                generation: 1,
+                revision: 1,
+                binary_id: serde_json::json!([]),
+                size: 0,
+                is_delete: false,
+                created_at: 100,
+                metadata: serde_json::json!({}),
+                file_metadata: new_file_metadata("alice", "main"),
+            }],
+        )
+        .await
+        .unwrap();
+        dao
+    }
+
+    #[tokio::test]
+    async fn finds_submitted_file_with_its_id() {
+        let dao = dao_with_file("//src/module/a.cpp").await;
+
+        let rsp = find_file_by_path(&dao, request("//src/module/a.cpp"))
+            .await
+            .unwrap();
+        let expected_id =
+            derive_file_id_from_path(&DepotPath::parse("//src/module/a.cpp").unwrap());
+        assert_eq!(rsp.file_id, expected_id);
+
+        let file = rsp.file.unwrap();
+        assert_eq!(file.path, "//src/module/a.cpp");
+        assert_eq!(file.created_at, 100);
+        assert_eq!(file.first_introduced_by, "alice");
+        assert_eq!(file.branches, vec!["main".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn missing_file_still_returns_its_id() {
+        let dao = dao_with_file("//src/module/a.cpp").await;
+
+        let rsp = find_file_by_path(&dao, request("//src/module/b.cpp"))
+            .await
+            .unwrap();
+        assert!(rsp.file.is_none());
+        assert_eq!(
+            rsp.file_id,
+            derive_file_id_from_path(&DepotPath::parse("//src/module/b.cpp").unwrap())
+        );
+    }
+
+    #[tokio::test]
+    async fn invalid_path_is_rejected() {
+        let err = find_file_by_path(&MockDao::default(), request("src/a.cpp"))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::InvalidArgument);
+    }
+}
@@ -0,0 +1,128 @@
+use tonic::{Request, Response, Status};
+
+use crate::database::dao::{Dao, read_dao};
+use crate::logging::HiveLog;
+use crate::pb::{GetChangelistAtTimeReq, GetChangelistAtTimeRsp};
+
+pub async fn get_changelist_at_time(
+    log: HiveLog,
+    request: Request<GetChangelistAtTimeReq>,
+) -> Result<Response<GetChangelistAtTimeRsp>, Status> {
+    let _g = log.enter();
+    let req = request.into_inner();
+
+    log.info(&format!(
+        "get_changelist_at_time: branch={}, timestamp_millis={}",
+        req.branch_id, req.timestamp_millis
+    ));
+
+    let rsp = resolve_changelist_at_time(read_dao().as_ref(), req).await?;
+    Ok(Response::new(rsp))
+}
+
+/// 将时间点解析为 changelist：分支 HEAD 之前最后一个提交时间不晚于该时间点的 changelist。
+///
+/// changelist 的提交时间精度为秒，请求中的毫秒时间戳向下取整到秒后比较。
+pub(crate) async fn resolve_changelist_at_time(
+    dao: &dyn Dao,
+    req: GetChangelistAtTimeReq,
+) -> Result<GetChangelistAtTimeRsp, Status> {
+    let branch_id = req.branch_id.trim();
+    if !branch_id.is_empty() {
+        dao.find_branch_by_id(branch_id)
+            .await
+            .map_err(|e| Status::internal(format!("database error while finding branch: {e}")))?
+            .ok_or_else(|| Status::not_found(format!("branch '{branch_id}' not found")))?;
+    }
+
+    let committed_at = req.timestamp_millis.div_euclid(1000);
+    let changelist_id = dao
+        .find_latest_changelist_before(branch_id, committed_at)
+        .await
+        .map_err(|e| Status::internal(format!("database error while finding changelist: {e}")))?
+        .ok_or_else(|| {
+            Status::not_found(format!(
+                "no changelist committed at or before {}",
+                req.timestamp_millis
+            ))
+        })?;
+
+    Ok(GetChangelistAtTimeRsp { changelist_id })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::dao::MockDao;
+    use crv_core::metadata::{BranchDoc, BranchMetadata};
+    use tonic::Code;
+
+    fn request(branch_id: &str, timestamp_millis: i64) -> GetChangelistAtTimeReq {
+        GetChangelistAtTimeReq {
+            branch_id: branch_id.to_string(),
+            timestamp_millis,
+        }
+    }
+
+    /// changelist 1/2/3 分别在第 100/200/300 秒提交，main 分支 HEAD 为 2
+    async fn dao_with_history() -> MockDao {
+        let dao = MockDao::default();
+        for committed_at in [100, 200, 300] {
+            dao.insert_changelist("alice", "", committed_at, serde_json::json!({}))
+                .await
+                .unwrap();
+        }
+        dao.insert_branch(&BranchDoc {
+            id: "main".to_string(),
+            created_at: 0,
+            created_by: "alice".to_string(),
+            head_changelist_id: 2,
+            is_protected: false,
+            metadata: BranchMetadata {
+                description: "main".to_string(),
+                owners: vec![],
+                description_regex: None,
+            },
+        })
+        .await
+        .unwrap();
+        dao
+    }
+
+    #[tokio::test]
+    async fn resolves_latest_changelist_before_timestamp() {
+        let dao = dao_with_history().await;
+
+        let rsp = resolve_changelist_at_time(&dao, request("", 250_999))
+            .await
+            .unwrap();
+        assert_eq!(rsp.changelist_id, 2);
+
+        // 恰好等于提交时间
+        let rsp = resolve_changelist_at_time(&dao, request("", 300_000))
+            .await
+            .unwrap();
+        assert_eq!(rsp.changelist_id, 3);
+
+        // 分支 HEAD 之后的 changelist 不可见
+        let rsp = resolve_changelist_at_time(&dao, request("main", 400_000))
+            .await
+            .unwrap();
+        assert_eq!(rsp.changelist_id, 2);
+    }
+
+    #[tokio::test]
+    async fn reports_not_found_before_history_or_unknown_branch() {
+        let dao = dao_with_history().await;
+
+        let err = resolve_changelist_at_time(&dao, request("", 99_999))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::NotFound);
+
+        let err = resolve_changelist_at_time(&dao, request("dev", 400_000))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::NotFound);
+    }
+}
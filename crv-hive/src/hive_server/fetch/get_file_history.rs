@@ -0,0 +1,70 @@
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+
+use tonic::{Request, Response, Status};
+
+use crate::database::dao::{Dao, read_dao};
+use crate::logging::HiveLog;
+use crate::pb::{FileHistoryEntry, GetFileHistoryReq, GetFileHistoryRsp};
+
+pub async fn handle_get_file_history(
+    log: HiveLog,
+    request: Request<GetFileHistoryReq>,
+) -> Result<Response<GetFileHistoryRsp>, Status> {
+    let _g = log.enter();
+    let req = request.into_inner();
+
+    log.info(&format!("get_file_history: path={}", req.path));
+
+    let rsp = get_file_history(read_dao().as_ref(), req).await?;
+    log.info(&format!("file history entries: {}", rsp.entries.len()));
+    Ok(Response::new(rsp))
+}
+
+/// 列出文件的所有 revision 及其所属 changelist 的信息，从新到旧排列。
+pub(crate) async fn get_file_history(
+    dao: &dyn Dao,
+    req: GetFileHistoryReq,
+) -> Result<GetFileHistoryRsp, Status> {
+    let path = crv_core::path::basic::DepotPath::parse(&req.path)
+        .map_err(|e| Status::invalid_argument(format!("invalid path '{}': {e}", req.path)))?;
+
+    let revisions = dao
+        .list_file_revisions_by_depot_path(&path.to_custom_string())
+        .await
+        .map_err(|e| Status::internal(format!("database error while get_file_history: {e}")))?;
+
+    let mut changelists = HashMap::new();
+    for r in &revisions {
+        if let Entry::Vacant(entry) = changelists.entry(r.changelist_id) {
+            let changelist = dao
+                .find_changelist_by_id(r.changelist_id)
+                .await
+                .map_err(|e| {
+                    Status::internal(format!("database error while finding changelist: {e}"))
+                })?;
+            entry.insert(changelist);
+        }
+    }
+
+    let mut entries = Vec::with_capacity(revisions.len());
+    for r in revisions {
+        let (author, description) = match &changelists[&r.changelist_id] {
+            Some(cl) => (cl.author.clone(), cl.description.clone()),
+            None => (String::new(), String::new()),
+        };
+
+        entries.push(FileHistoryEntry {
+            generation: r.generation,
+            revision: r.revision,
+            changelist_id: r.changelist_id,
+            size: r.size,
+            is_delete: r.is_delete,
+            created_at: r.created_at,
+            author,
+            description,
+        });
+    }
+
+    Ok(GetFileHistoryRsp { entries })
+}
@@ -1,9 +1,12 @@
 use tonic::{Request, Response, Status};
 
 use crate::common::depot_path::DepotPath;
+use crate::database::dao::read_dao;
 use crate::database::service as db_service;
+use crate::hive_server::changelist::resolve_changelist_id;
 use crate::logging::HiveLog;
 use crate::pb::{FileRevision as PbFileRevision, GetFileTreeReq, GetFileTreeRsp};
+use crv_core::metadata::CompressionType;
 
 pub async fn get_file_tree(
     log: HiveLog,
@@ -25,19 +28,40 @@ pub async fn get_file_tree(
     }
 
     log.info(&format!(
-        "get_file_tree: path={}, changelist_id={}",
-        depot, req.changelist_id
+        "get_file_tree: path={}, changelist_id={}, branch={}",
+        depot, req.changelist_id, req.branch_id
     ));
 
-    let models = db_service::get_file_tree_revisions(&depot, req.changelist_id)
+    // 已被压缩的 changelist 重定向到压缩后的 changelist；未指定 changelist 时使用分支的 HEAD
+    let branch_id = req.branch_id.trim();
+    let changelist_id = if req.changelist_id > 0 {
+        resolve_changelist_id(read_dao().as_ref(), req.changelist_id).await?
+    } else if branch_id.is_empty() {
+        req.changelist_id
+    } else {
+        let branch = read_dao()
+            .find_branch_by_id(branch_id)
+            .await
+            .map_err(|e| Status::internal(format!("database error while finding branch: {e}")))?
+            .ok_or_else(|| Status::not_found(format!("branch '{branch_id}' not found")))?;
+        // 分支还没有任何 changelist
+        if branch.head_changelist_id <= 0 {
+            return Ok(Response::new(GetFileTreeRsp {
+                file_revisions: vec![],
+            }));
+        }
+        branch.head_changelist_id
+    };
+
+    let models = db_service::get_file_tree_revisions(&depot, changelist_id)
         .await
         .map_err(|e| Status::internal(format!("database error while get_file_tree: {e}")))?;
 
     let mut file_revisions = Vec::with_capacity(models.len());
     for m in models {
-        let path = m.to_depot_path_string().map_err(|e| {
-            Status::internal(format!("failed to decode ltree path: {e}"))
-        })?;
+        let path = m
+            .to_depot_path_string()
+            .map_err(|e| Status::internal(format!("failed to decode ltree path: {e}")))?;
 
         let binary_id = m
             .binary_id
@@ -55,6 +79,18 @@ pub async fn get_file_tree(
             binary_id,
             size: m.size,
             revision_created_at: m.created_at,
+            content_hash: m
+                .metadata
+                .get("hash")
+                .and_then(|h| h.as_str())
+                .unwrap_or_default()
+                .to_string(),
+            compression_type: m
+                .metadata
+                .get("compressionType")
+                .and_then(|c| c.as_str())
+                .unwrap_or(CompressionType::None.as_str())
+                .to_string(),
         });
     }
 
@@ -117,7 +153,10 @@ mod tests {
     }
 
     fn unique_depot_dir(name: &str) -> String {
-        format!("//tests/get_file_tree/{}/", uuid::Uuid::new_v4().to_string() + name)
+        format!(
+            "//tests/get_file_tree/{}/",
+            uuid::Uuid::new_v4().to_string() + name
+        )
     }
 
     async fn file_tree_file_with_changelist_cutoff() {
@@ -130,6 +169,7 @@ mod tests {
         let req = GetFileTreeReq {
             depot_wildcard: path.clone(),
             changelist_id: cl1,
+            branch_id: String::new(),
         };
         let log = HiveLog::new("GetFileTree(test_file_cutoff)");
         let resp = get_file_tree(log, Request::new(req))
@@ -142,6 +182,7 @@ mod tests {
         let req_latest = GetFileTreeReq {
             depot_wildcard: path,
             changelist_id: 0,
+            branch_id: String::new(),
         };
         let log = HiveLog::new("GetFileTree(test_file_latest)");
         let resp = get_file_tree(log, Request::new(req_latest))
@@ -165,6 +206,7 @@ mod tests {
         let wildcard_req = GetFileTreeReq {
             depot_wildcard: format!("{}...", base),
             changelist_id: 0,
+            branch_id: String::new(),
         };
         let log = HiveLog::new("GetFileTree(test_wildcard_descendants)");
         let resp = get_file_tree(log, Request::new(wildcard_req))
@@ -191,6 +233,7 @@ mod tests {
         let req = GetFileTreeReq {
             depot_wildcard: "//...".to_string(),
             changelist_id: 0,
+            branch_id: String::new(),
         };
         let log = HiveLog::new("GetFileTree(test_root_wildcard)");
         let resp = get_file_tree(log, Request::new(req))
@@ -209,6 +252,7 @@ mod tests {
         let req = GetFileTreeReq {
             depot_wildcard: "invalid".to_string(),
             changelist_id: 0,
+            branch_id: String::new(),
         };
         let log = HiveLog::new("GetFileTree(test_invalid)");
         let err = get_file_tree(log, Request::new(req))
@@ -222,6 +266,7 @@ mod tests {
         let req = GetFileTreeReq {
             depot_wildcard: "//a/b/".to_string(),
             changelist_id: 0,
+            branch_id: String::new(),
         };
         let log = HiveLog::new("GetFileTree(test_dir_reject)");
         let err = get_file_tree(log, Request::new(req))
@@ -240,4 +285,3 @@ mod tests {
         });
     }
 }
-
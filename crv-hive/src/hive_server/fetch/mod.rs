@@ -1,2 +1,5 @@
 pub mod download;
-pub mod get_file_tree;
\ No newline at end of file
+pub mod find_file_by_path;
+pub mod get_changelist_at_time;
+pub mod get_file_history;
+pub mod get_file_tree;
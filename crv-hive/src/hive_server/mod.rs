@@ -1,27 +1,62 @@
-use crate::auth::{AuthInterceptor, AuthService};
+use crate::audit::{AuditEvent, AuditOp, AuditOutcome};
+use crate::auth::{AuthInterceptor, AuthService, lockout};
 use crate::hive_server::fetch::download;
 use crate::logging::HiveLog;
+use crate::middleware::{RateLimitHeadersLayer, RateLimiter};
 use crate::pb::{
-    BonjourReq, BonjourRsp, CheckChunksReq, CheckChunksRsp, DownloadFileChunkReq,
-    GetFileTreeReq, GetFileTreeRsp, LaunchSubmitReq, LaunchSubmitRsp, LoginReq, LoginRsp, RegisterReq,
-    RegisterRsp, SubmitReq, SubmitRsp, UploadFileChunkReq,
+    BeginOauth2LoginReq, BeginOauth2LoginRsp, BonjourReq, BonjourRsp, CancelSubmitContextReq,
+    CancelSubmitContextRsp, CheckChunksReq, CheckChunksRsp, CherryPickReq, CherryPickRsp,
+    CompleteOauth2LoginReq, CompleteOauth2LoginRsp, CreateBranchReq, CreateBranchRsp,
+    CreateSnapshotReq, CreateSnapshotRsp, DeleteSnapshotReq, DeleteSnapshotRsp, DeleteUserReq,
+    DeleteUserRsp, DeltaUploadReq, DeltaUploadRsp, DescribeWorkspaceReq, DescribeWorkspaceRsp,
+    DownloadFileChunkReq, ExportRepositorySnapshotReq, FindFileByPathReq, FindFileByPathRsp,
+    GetBranchDiffReq, GetBranchDiffRsp, GetChangelistAtTimeReq, GetChangelistAtTimeRsp,
+    GetChangelistByTagReq, GetChangelistByTagRsp, GetChangelistChainReq, GetChangelistChainRsp,
+    GetFileHistoryReq, GetFileHistoryRsp, GetFileTreeReq, GetFileTreeRsp, GetRepositoryLayoutReq,
+    GetRepositoryLayoutRsp, GetRepositoryStatsReq, GetRepositoryStatsRsp, GetSubmitContextReq,
+    GetSubmitContextRsp, GetUserProfileReq, GetUserProfileRsp, ImportRepositorySnapshotReq,
+    ImportRepositorySnapshotRsp, LaunchSubmitReq, LaunchSubmitRsp, ListAuditLogReq,
+    ListAuditLogRsp, ListBranchesReq, ListBranchesRsp, ListFileRevisionsByTagReq,
+    ListFileRevisionsByTagRsp, ListLockedFilesReq, ListLockedFilesRsp, ListSnapshotsReq,
+    ListSnapshotsRsp, ListUsersReq, ListUsersRsp, ListWebhooksReq, ListWebhooksRsp, LoginReq,
+    LoginRsp, MergeReq, MergeRsp, RebuildIndexReq, RebuildIndexRsp, RegisterReq, RegisterRsp,
+    RegisterWebhookReq, RegisterWebhookRsp, RegisterWorkspaceReq, RegisterWorkspaceRsp,
+    RestoreSnapshotReq, RestoreSnapshotRsp, SetBranchDescriptionFormatReq,
+    SetBranchDescriptionFormatRsp, SetBranchProtectionReq, SetBranchProtectionRsp,
+    SquashChangelistsReq, SquashChangelistsRsp, SubmitReq, SubmitRsp, TagChangelistReq,
+    TagChangelistRsp, TagFileRevisionReq, TagFileRevisionRsp, UnregisterWebhookReq,
+    UnregisterWebhookRsp, UnregisterWorkspaceReq, UnregisterWorkspaceRsp, UntagChangelistReq,
+    UntagChangelistRsp, UpdateUserPasswordReq, UpdateUserPasswordRsp, UploadFileChunkReq,
+    WatchBranchReq,
     hive_service_server::{HiveService, HiveServiceServer},
 };
-use argon2::password_hash::SaltString;
-use argon2::{Argon2, PasswordHasher};
-use http::header::{HeaderName, ACCEPT, AUTHORIZATION, CONTENT_TYPE};
+use crv_core::repository::Repository;
 use http::Method;
-use crv_core::repository::{
-    Repository
-};
-use rand::rngs::OsRng;
+use http::header::{ACCEPT, AUTHORIZATION, CONTENT_TYPE, HeaderName};
 use std::sync::{Arc, OnceLock};
+use std::time::{Duration, SystemTime};
 use tonic::{Request, Response, Status, transport::Server};
+use tonic_reflection::pb::v1::server_reflection_server::{
+    ServerReflection, ServerReflectionServer,
+};
+use tonic_reflection::pb::v1alpha::server_reflection_server::{
+    ServerReflection as ServerReflectionV1Alpha,
+    ServerReflectionServer as ServerReflectionServerV1Alpha,
+};
 use tonic_web::GrpcWebLayer;
 use tower_http::cors::{Any, CorsLayer};
 
+mod audit;
+mod branch;
+mod changelist;
 mod fetch;
-mod submit;
+mod snapshot;
+mod stats;
+pub(crate) mod submit;
+mod tag;
+mod user;
+mod webhook;
+mod workspace;
 
 pub struct CrvHiveService {
     auth: Arc<AuthService>,
@@ -33,6 +68,7 @@ impl CrvHiveService {
     }
 }
 
+use crate::config::entity::RepositoryBackend;
 use crate::config::holder::get_or_init_config;
 
 /// 全局 RepositoryManager 实例，用于访问底层 chunk 仓库。
@@ -44,9 +80,20 @@ pub(crate) fn repository_manager() -> Result<&'static Repository, Status> {
     let cfg = get_or_init_config();
     let repo_root = cfg.repository_path.clone();
 
-    let res = REPOSITORY_MANAGER.get_or_init(|| {
-        Repository::new(&repo_root)
-            .map_err(|e| format!("failed to open repository at {repo_root}: {e}"))
+    let res = REPOSITORY_MANAGER.get_or_init(|| match cfg.repository_backend {
+        RepositoryBackend::Local => {
+            let repo = Repository::new(&repo_root)
+                .map_err(|e| format!("failed to open repository at {repo_root}: {e}"))?;
+            if cfg.chunk_offload_enabled {
+                // 转移 pack 需要 S3 客户端作为对象存储后端
+                return Err(
+                    "chunk_offload_enabled requires an S3 client, which is not supported yet"
+                        .to_string(),
+                );
+            }
+            Ok(repo)
+        }
+        RepositoryBackend::S3 => Err("repository_backend 's3' is not supported yet".to_string()),
     });
 
     match res {
@@ -76,26 +123,81 @@ fn build_cors_layer() -> CorsLayer {
         ])
 }
 
-#[tonic::async_trait]
-impl HiveService for CrvHiveService {
-    async fn bonjour(&self, _request: Request<BonjourReq>) -> Result<Response<BonjourRsp>, Status> {
-        let log = HiveLog::from_request("Bonjour", &_request);
-        let _g = log.enter();
-        log.info("rpc start");
-        let rsp = BonjourRsp {
-            major_version: 1,
-            minor_version: 1,
-            api_implementation: "crv-hive".to_string(),
-            platform: "rust".to_string(),
-            os: std::env::consts::OS.to_string(),
-            architecture: std::env::consts::ARCH.to_string(),
-        };
-        let out = Ok(Response::new(rsp));
-        log.finish_ok();
-        out
+/// 构建 gRPC 反射服务（v1 与 v1alpha），未启用时返回 `None`
+///
+/// 同时提供 v1alpha 是为了兼容只支持旧版本协议的 grpcurl 等工具。
+fn build_reflection_services() -> Result<
+    (
+        Option<ServerReflectionServer<impl ServerReflection>>,
+        Option<ServerReflectionServerV1Alpha<impl ServerReflectionV1Alpha>>,
+    ),
+    tonic_reflection::server::Error,
+> {
+    if !get_or_init_config().enable_reflection {
+        return Ok((None, None));
     }
 
-    async fn login(&self, request: Request<LoginReq>) -> Result<Response<LoginRsp>, Status> {
+    let v1 = tonic_reflection::server::Builder::configure()
+        .register_encoded_file_descriptor_set(crate::pb::FILE_DESCRIPTOR_SET)
+        .build_v1()?;
+    let v1alpha = tonic_reflection::server::Builder::configure()
+        .register_encoded_file_descriptor_set(crate::pb::FILE_DESCRIPTOR_SET)
+        .build_v1alpha()?;
+    Ok((Some(v1), Some(v1alpha)))
+}
+
+/// 从数据库恢复重启前进行中的提交，否则它们持有的文件锁会丢失
+async fn restore_submit_tickets() {
+    match submit::submit_service().restore().await {
+        Ok(0) => {}
+        Ok(restored) => println!("Restored {restored} in-progress submit ticket(s)"),
+        Err(e) => eprintln!("failed to restore submit tickets: {e}"),
+    }
+}
+
+/// 在后台定期淘汰上传缓存，避免未完成的上传无限占用磁盘
+fn spawn_chunk_cache_eviction() -> tokio::task::JoinHandle<()> {
+    let cfg = get_or_init_config();
+    submit::cache_service().clone().spawn_eviction_task(
+        cfg.chunk_cache_max_mb.saturating_mul(1024 * 1024),
+        std::time::Duration::from_secs(cfg.chunk_cache_eviction_interval_mins.max(1) * 60),
+    )
+}
+
+/// 每天将超过 `chunk_offload_threshold_days` 未修改的 pack 转移到对象存储，未启用时返回 `None`
+fn spawn_chunk_offload() -> Option<tokio::task::JoinHandle<()>> {
+    let cfg = get_or_init_config();
+    if !cfg.chunk_offload_enabled {
+        return None;
+    }
+    let threshold = Duration::from_secs(u64::from(cfg.chunk_offload_threshold_days) * 24 * 60 * 60);
+    Some(tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(Duration::from_secs(24 * 60 * 60));
+        loop {
+            ticker.tick().await;
+            let result = tokio::task::spawn_blocking(move || {
+                let repo = repository_manager().map_err(|s| s.message().to_string())?;
+                let older_than = SystemTime::now() - threshold;
+                repo.offload_sealed_packs(older_than)
+                    .map_err(|e| e.to_string())
+            })
+            .await;
+            match result {
+                Ok(Ok(report)) if report.packs > 0 => tracing::info!(
+                    "offloaded {} packs ({} bytes) to object storage",
+                    report.packs,
+                    report.bytes
+                ),
+                Ok(Ok(_)) => {}
+                Ok(Err(e)) => tracing::warn!("chunk offload failed: {e}"),
+                Err(e) => tracing::warn!("chunk offload task panicked: {e}"),
+            }
+        }
+    }))
+}
+
+impl CrvHiveService {
+    async fn handle_login(&self, request: Request<LoginReq>) -> Result<Response<LoginRsp>, Status> {
         let log = HiveLog::from_request("Login", &request);
         let _g = log.enter();
         log.info("rpc start");
@@ -103,13 +205,25 @@ impl HiveService for CrvHiveService {
         log.debug(&format!("login attempt username={}", req.username));
 
         if req.username.trim().is_empty() || req.password.is_empty() {
-            let e = Status::invalid_argument(
-                "username and password are required",
-            );
+            let e = Status::invalid_argument("username and password are required");
             log.finish_err(&e);
             return Err(e);
         }
 
+        // 锁定状态读取失败时不阻止登录，数据库不可用时密码校验本身也会失败
+        let dao = crate::database::dao::dao();
+        let now = chrono::Utc::now().timestamp();
+        match lockout::locked_until(dao.as_ref(), &req.username, now).await {
+            Ok(Some(until)) => {
+                log.warn(&format!("account locked until {until}"));
+                let e = Status::permission_denied("account temporarily locked");
+                log.finish_err(&e);
+                return Err(e);
+            }
+            Ok(None) => {}
+            Err(e) => log.warn(&format!("failed to check account lock: {e}")),
+        }
+
         // 抽象出的用户名/密码校验逻辑，当前实现总是返回 false，
         // 你可以在后续替换为真实的数据库或其他身份源查询。
         let is_valid = crate::auth::validate_user_credentials(&req.username, &req.password)
@@ -117,10 +231,18 @@ impl HiveService for CrvHiveService {
             .map_err(Status::from)?;
 
         if !is_valid {
+            match lockout::record_failure(dao.as_ref(), &req.username, now).await {
+                Ok(Some(until)) => log.warn(&format!("account locked until {until}")),
+                Ok(None) => {}
+                Err(e) => log.warn(&format!("failed to record login failure: {e}")),
+            }
             let e = Status::unauthenticated("invalid username or password");
             log.finish_err(&e);
             return Err(e);
         }
+        if let Err(e) = lockout::reset(dao.as_ref(), &req.username).await {
+            log.warn(&format!("failed to reset login failures: {e}"));
+        }
 
         let (token, exp) = self
             .auth
@@ -137,7 +259,7 @@ impl HiveService for CrvHiveService {
         out
     }
 
-    async fn register(
+    async fn handle_register(
         &self,
         request: Request<RegisterReq>,
     ) -> Result<Response<RegisterRsp>, Status> {
@@ -148,27 +270,33 @@ impl HiveService for CrvHiveService {
 
         let username = req.username.trim();
         let password = req.password;
+        let email = req
+            .email
+            .as_deref()
+            .map(str::trim)
+            .filter(|email| !email.is_empty());
+        let display_name = req.display_name.trim();
 
         if username.is_empty() || password.is_empty() {
-            let e = Status::invalid_argument(
-                "username and password are required",
-            );
+            let e = Status::invalid_argument("username and password are required");
             log.finish_err(&e);
             return Err(e);
         }
 
         if username.len() < 3 {
-            let e = Status::invalid_argument(
-                "username must be at least 3 characters",
-            );
+            let e = Status::invalid_argument("username must be at least 3 characters");
             log.finish_err(&e);
             return Err(e);
         }
 
         if password.len() < 6 {
-            let e = Status::invalid_argument(
-                "password must be at least 6 characters",
-            );
+            let e = Status::invalid_argument("password must be at least 6 characters");
+            log.finish_err(&e);
+            return Err(e);
+        }
+
+        if email.is_some_and(|email| !user::is_valid_email(email)) {
+            let e = Status::invalid_argument("email is not valid");
             log.finish_err(&e);
             return Err(e);
         }
@@ -183,26 +311,20 @@ impl HiveService for CrvHiveService {
             }
             Ok(None) => {}
             Err(e) => {
-                let s = Status::internal(format!(
-                    "database error while checking user: {e}"
-                ));
+                let s = Status::internal(format!("database error while checking user: {e}"));
                 log.finish_err(&s);
                 return Err(s);
             }
         }
 
         // 使用 Argon2 对密码进行哈希
-        let salt = SaltString::generate(&mut OsRng);
-        let argon = Argon2::default();
-        let password_hash = argon
-            .hash_password(password.as_bytes(), &salt)
-            .map_err(|_| Status::internal("failed to hash password"))?
-            .to_string();
-
-        if let Err(e) = crate::database::dao::insert_user(username, &password_hash).await {
-            let s = Status::internal(format!(
-                "database error while inserting user: {e}"
-            ));
+        let password_hash = crate::auth::hash_password(&password)
+            .map_err(|_| Status::internal("failed to hash password"))?;
+
+        if let Err(e) =
+            crate::database::dao::insert_user(username, &password_hash, email, display_name).await
+        {
+            let s = Status::internal(format!("database error while inserting user: {e}"));
             log.finish_err(&s);
             return Err(s);
         }
@@ -214,10 +336,115 @@ impl HiveService for CrvHiveService {
         log.finish_ok();
         out
     }
+}
+
+#[tonic::async_trait]
+impl HiveService for CrvHiveService {
+    async fn bonjour(&self, _request: Request<BonjourReq>) -> Result<Response<BonjourRsp>, Status> {
+        let log = HiveLog::from_request("Bonjour", &_request);
+        let _g = log.enter();
+        log.info("rpc start");
+        let rsp = BonjourRsp {
+            major_version: 1,
+            minor_version: 1,
+            api_implementation: "crv-hive".to_string(),
+            platform: "rust".to_string(),
+            os: std::env::consts::OS.to_string(),
+            architecture: std::env::consts::ARCH.to_string(),
+        };
+        let out = Ok(Response::new(rsp));
+        log.finish_ok();
+        out
+    }
+
+    async fn login(&self, request: Request<LoginReq>) -> Result<Response<LoginRsp>, Status> {
+        let username = request.get_ref().username.trim().to_string();
+        let out = self.handle_login(request).await;
+        let event = match &out {
+            Ok(_) => AuditEvent::new(
+                AuditOp::Login,
+                &username,
+                &username,
+                AuditOutcome::Success,
+                serde_json::json!({}),
+            ),
+            Err(e) => AuditEvent::failed(AuditOp::Login, &username, &username, e),
+        };
+        tokio::spawn(crate::audit::emit(event));
+        out
+    }
+
+    async fn register(
+        &self,
+        request: Request<RegisterReq>,
+    ) -> Result<Response<RegisterRsp>, Status> {
+        let username = request.get_ref().username.trim().to_string();
+        let out = self.handle_register(request).await;
+        let event = match &out {
+            Ok(rsp) => AuditEvent::new(
+                AuditOp::Register,
+                &username,
+                &username,
+                if rsp.get_ref().success {
+                    AuditOutcome::Success
+                } else {
+                    AuditOutcome::Failure
+                },
+                serde_json::json!({ "message": rsp.get_ref().message }),
+            ),
+            Err(e) => AuditEvent::failed(AuditOp::Register, &username, &username, e),
+        };
+        tokio::spawn(crate::audit::emit(event));
+        out
+    }
+
+    async fn begin_oauth2_login(
+        &self,
+        request: Request<BeginOauth2LoginReq>,
+    ) -> Result<Response<BeginOauth2LoginRsp>, Status> {
+        let log = HiveLog::from_request("BeginOauth2Login", &request);
+        let _g = log.enter();
+        log.info("rpc start");
+        let out = user::oauth2_login::handle_begin_oauth2_login(log.clone(), request).await;
+        match &out {
+            Ok(_) => log.finish_ok(),
+            Err(e) => log.finish_err(e),
+        }
+        out
+    }
 
+    async fn complete_oauth2_login(
+        &self,
+        request: Request<CompleteOauth2LoginReq>,
+    ) -> Result<Response<CompleteOauth2LoginRsp>, Status> {
+        let log = HiveLog::from_request("CompleteOauth2Login", &request);
+        let _g = log.enter();
+        log.info("rpc start");
+        let out =
+            user::oauth2_login::handle_complete_oauth2_login(log.clone(), &self.auth, request)
+                .await;
+        match &out {
+            Ok(rsp) => {
+                log.finish_ok();
+                let username = &rsp.get_ref().username;
+                tokio::spawn(crate::audit::emit(AuditEvent::new(
+                    AuditOp::Login,
+                    username,
+                    username,
+                    AuditOutcome::Success,
+                    serde_json::json!({ "source": "oauth2" }),
+                )));
+            }
+            Err(e) => log.finish_err(e),
+        }
+        out
+    }
 
     type DownloadFileChunkStream = download::DownloadFileChunkStream;
     type UploadFileChunkStream = submit::submit::UploadFileChunkStream;
+    type ExportRepositorySnapshotStream =
+        stats::export_repository_snapshot::ExportRepositorySnapshotStream;
+    type WatchBranchStream = branch::watch_branch::WatchBranchStream;
 
     async fn download_file_chunk(
         &self,
@@ -241,11 +468,44 @@ impl HiveService for CrvHiveService {
         let log = HiveLog::from_request("LaunchSubmit", &request);
         let _g = log.enter();
         log.info("rpc start");
+        let username = submit::submitting_user(&request);
+        let paths: Vec<String> = request
+            .get_ref()
+            .files
+            .iter()
+            .map(|f| f.path.clone())
+            .collect();
         let out = submit::launch_submit::handle_launch_submit(log.clone(), request).await;
         match &out {
             Ok(_) => log.finish_ok(),
             Err(e) => log.finish_err(e),
         }
+        let event = match &out {
+            Ok(rsp) if rsp.get_ref().success => AuditEvent::new(
+                AuditOp::LaunchSubmit,
+                &username,
+                &rsp.get_ref().ticket,
+                AuditOutcome::Success,
+                serde_json::json!({ "files": paths }),
+            ),
+            Ok(rsp) => AuditEvent::new(
+                AuditOp::LaunchSubmit,
+                &username,
+                "",
+                AuditOutcome::Failure,
+                serde_json::json!({
+                    "files": paths,
+                    "unable_to_lock": rsp
+                        .get_ref()
+                        .file_unable_to_lock
+                        .iter()
+                        .map(|f| f.path.as_str())
+                        .collect::<Vec<_>>(),
+                }),
+            ),
+            Err(e) => AuditEvent::failed(AuditOp::LaunchSubmit, &username, "", e),
+        };
+        tokio::spawn(crate::audit::emit(event));
         out
     }
 
@@ -280,18 +540,39 @@ impl HiveService for CrvHiveService {
         out
     }
 
-    async fn submit(
+    async fn delta_upload(
         &self,
-        _request: Request<SubmitReq>,
-    ) -> Result<Response<SubmitRsp>, Status> {
+        request: Request<DeltaUploadReq>,
+    ) -> Result<Response<DeltaUploadRsp>, Status> {
+        let log = HiveLog::from_request("DeltaUpload", &request);
+        let _g = log.enter();
+        log.info("rpc start");
+        let out = submit::delta_upload::handle_delta_upload(log.clone(), request).await;
+        match &out {
+            Ok(_) => log.finish_ok(),
+            Err(e) => log.finish_err(e),
+        }
+        out
+    }
+
+    async fn submit(&self, _request: Request<SubmitReq>) -> Result<Response<SubmitRsp>, Status> {
         let log = HiveLog::from_request("Submit", &_request);
         let _g = log.enter();
         log.info("rpc start");
+        let username = submit::submitting_user(&_request);
+        let summary = submit::submit::SubmitSummary::of(_request.get_ref());
         let out = submit::submit::submit(log.clone(), _request).await;
         match &out {
             Ok(_) => log.finish_ok(),
             Err(e) => log.finish_err(e),
         }
+        let (event, notification) = summary.events(&username, &out);
+        if let Some(event) = event {
+            tokio::spawn(crate::audit::emit(event));
+        }
+        if let Some(notification) = notification {
+            tokio::spawn(crate::webhook::notify_submit(notification));
+        }
         out
     }
 
@@ -309,47 +590,787 @@ impl HiveService for CrvHiveService {
         }
         out
     }
-}
 
-/// 启动 gRPC 服务器（优雅关闭）
-pub async fn start_server_with_shutdown<S>(
-    addr: std::net::SocketAddr,
-    shutdown: S,
-) -> Result<(), Box<dyn std::error::Error>>
-where
-    S: std::future::Future<Output = ()> + Send + 'static,
-{
-    // 基于全局配置初始化 AuthService，并构建 gRPC 拦截器
-    let auth = AuthService::from_config();
-    let service = CrvHiveService::new(Arc::clone(&auth));
-    let interceptor = AuthInterceptor::new(Arc::clone(&auth));
-    let cors = build_cors_layer();
+    async fn get_changelist_at_time(
+        &self,
+        request: Request<GetChangelistAtTimeReq>,
+    ) -> Result<Response<GetChangelistAtTimeRsp>, Status> {
+        let log = HiveLog::from_request("GetChangelistAtTime", &request);
+        let _g = log.enter();
+        log.info("rpc start");
+        let out = fetch::get_changelist_at_time::get_changelist_at_time(log.clone(), request).await;
+        match &out {
+            Ok(_) => log.finish_ok(),
+            Err(e) => log.finish_err(e),
+        }
+        out
+    }
 
-    Server::builder()
-        .accept_http1(true)
-        .layer(cors)
-        .layer(GrpcWebLayer::new())
-        .add_service(HiveServiceServer::with_interceptor(service, interceptor))
-        .serve_with_shutdown(addr, shutdown)
-        .await?;
+    async fn get_file_history(
+        &self,
+        request: Request<GetFileHistoryReq>,
+    ) -> Result<Response<GetFileHistoryRsp>, Status> {
+        let log = HiveLog::from_request("GetFileHistory", &request);
+        let _g = log.enter();
+        log.info("rpc start");
+        let out = fetch::get_file_history::handle_get_file_history(log.clone(), request).await;
+        match &out {
+            Ok(_) => log.finish_ok(),
+            Err(e) => log.finish_err(e),
+        }
+        out
+    }
 
-    Ok(())
-}
+    async fn find_file_by_path(
+        &self,
+        request: Request<FindFileByPathReq>,
+    ) -> Result<Response<FindFileByPathRsp>, Status> {
+        let log = HiveLog::from_request("FindFileByPath", &request);
+        let _g = log.enter();
+        log.info("rpc start");
+        let out = fetch::find_file_by_path::handle_find_file_by_path(log.clone(), request).await;
+        match &out {
+            Ok(_) => log.finish_ok(),
+            Err(e) => log.finish_err(e),
+        }
+        out
+    }
 
-/// 启动 gRPC 服务器（无关闭信号，会一直运行直至进程退出）
-pub async fn start_server(addr: std::net::SocketAddr) -> Result<(), Box<dyn std::error::Error>> {
-    let auth = AuthService::from_config();
-    let service = CrvHiveService::new(Arc::clone(&auth));
-    let interceptor = AuthInterceptor::new(auth);
-    let cors = build_cors_layer();
+    async fn create_branch(
+        &self,
+        request: Request<CreateBranchReq>,
+    ) -> Result<Response<CreateBranchRsp>, Status> {
+        let log = HiveLog::from_request("CreateBranch", &request);
+        let _g = log.enter();
+        log.info("rpc start");
+        let out = branch::create_branch::handle_create_branch(log.clone(), request).await;
+        match &out {
+            Ok(_) => log.finish_ok(),
+            Err(e) => log.finish_err(e),
+        }
+        out
+    }
 
-    Server::builder()
-        .accept_http1(true)
-        .layer(cors)
-        .layer(GrpcWebLayer::new())
-        .add_service(HiveServiceServer::with_interceptor(service, interceptor))
-        .serve(addr)
-        .await?;
+    async fn list_branches(
+        &self,
+        request: Request<ListBranchesReq>,
+    ) -> Result<Response<ListBranchesRsp>, Status> {
+        let log = HiveLog::from_request("ListBranches", &request);
+        let _g = log.enter();
+        log.info("rpc start");
+        let out = branch::list_branches::handle_list_branches(log.clone(), request).await;
+        match &out {
+            Ok(_) => log.finish_ok(),
+            Err(e) => log.finish_err(e),
+        }
+        out
+    }
 
-    Ok(())
+    async fn set_branch_description_format(
+        &self,
+        request: Request<SetBranchDescriptionFormatReq>,
+    ) -> Result<Response<SetBranchDescriptionFormatRsp>, Status> {
+        let log = HiveLog::from_request("SetBranchDescriptionFormat", &request);
+        let _g = log.enter();
+        log.info("rpc start");
+        let out = branch::set_description_format::handle_set_branch_description_format(
+            log.clone(),
+            request,
+        )
+        .await;
+        match &out {
+            Ok(_) => log.finish_ok(),
+            Err(e) => log.finish_err(e),
+        }
+        out
+    }
+
+    async fn set_branch_protection(
+        &self,
+        request: Request<SetBranchProtectionReq>,
+    ) -> Result<Response<SetBranchProtectionRsp>, Status> {
+        let log = HiveLog::from_request("SetBranchProtection", &request);
+        let _g = log.enter();
+        log.info("rpc start");
+        let out = branch::set_protection::handle_set_branch_protection(log.clone(), request).await;
+        match &out {
+            Ok(_) => log.finish_ok(),
+            Err(e) => log.finish_err(e),
+        }
+        out
+    }
+
+    async fn get_branch_diff(
+        &self,
+        request: Request<GetBranchDiffReq>,
+    ) -> Result<Response<GetBranchDiffRsp>, Status> {
+        let log = HiveLog::from_request("GetBranchDiff", &request);
+        let _g = log.enter();
+        log.info("rpc start");
+        let out = branch::get_branch_diff::handle_get_branch_diff(log.clone(), request).await;
+        match &out {
+            Ok(_) => log.finish_ok(),
+            Err(e) => log.finish_err(e),
+        }
+        out
+    }
+
+    async fn watch_branch(
+        &self,
+        request: Request<WatchBranchReq>,
+    ) -> Result<Response<Self::WatchBranchStream>, Status> {
+        let log = HiveLog::from_request("WatchBranch", &request);
+        let _g = log.enter();
+        log.info("rpc start");
+        let out = branch::watch_branch::handle_watch_branch(log.clone(), request).await;
+        match &out {
+            Ok(_) => log.info("rpc accepted (stream opened)"),
+            Err(e) => log.finish_err(e),
+        }
+        out
+    }
+
+    async fn list_audit_log(
+        &self,
+        request: Request<ListAuditLogReq>,
+    ) -> Result<Response<ListAuditLogRsp>, Status> {
+        let log = HiveLog::from_request("ListAuditLog", &request);
+        let _g = log.enter();
+        log.info("rpc start");
+        let out = audit::list_audit_log::handle_list_audit_log(log.clone(), request).await;
+        match &out {
+            Ok(_) => log.finish_ok(),
+            Err(e) => log.finish_err(e),
+        }
+        out
+    }
+
+    async fn register_workspace(
+        &self,
+        request: Request<RegisterWorkspaceReq>,
+    ) -> Result<Response<RegisterWorkspaceRsp>, Status> {
+        let log = HiveLog::from_request("RegisterWorkspace", &request);
+        let _g = log.enter();
+        log.info("rpc start");
+        let out =
+            workspace::register_workspace::handle_register_workspace(log.clone(), request).await;
+        match &out {
+            Ok(_) => log.finish_ok(),
+            Err(e) => log.finish_err(e),
+        }
+        out
+    }
+
+    async fn unregister_workspace(
+        &self,
+        request: Request<UnregisterWorkspaceReq>,
+    ) -> Result<Response<UnregisterWorkspaceRsp>, Status> {
+        let log = HiveLog::from_request("UnregisterWorkspace", &request);
+        let _g = log.enter();
+        log.info("rpc start");
+        let out =
+            workspace::unregister_workspace::handle_unregister_workspace(log.clone(), request)
+                .await;
+        match &out {
+            Ok(_) => log.finish_ok(),
+            Err(e) => log.finish_err(e),
+        }
+        out
+    }
+
+    async fn describe_workspace(
+        &self,
+        request: Request<DescribeWorkspaceReq>,
+    ) -> Result<Response<DescribeWorkspaceRsp>, Status> {
+        let log = HiveLog::from_request("DescribeWorkspace", &request);
+        let _g = log.enter();
+        log.info("rpc start");
+        let out =
+            workspace::describe_workspace::handle_describe_workspace(log.clone(), request).await;
+        match &out {
+            Ok(_) => log.finish_ok(),
+            Err(e) => log.finish_err(e),
+        }
+        out
+    }
+
+    async fn get_repository_stats(
+        &self,
+        request: Request<GetRepositoryStatsReq>,
+    ) -> Result<Response<GetRepositoryStatsRsp>, Status> {
+        let log = HiveLog::from_request("GetRepositoryStats", &request);
+        let _g = log.enter();
+        log.info("rpc start");
+        let out =
+            stats::get_repository_stats::handle_get_repository_stats(log.clone(), request).await;
+        match &out {
+            Ok(_) => log.finish_ok(),
+            Err(e) => log.finish_err(e),
+        }
+        out
+    }
+
+    async fn rebuild_index(
+        &self,
+        request: Request<RebuildIndexReq>,
+    ) -> Result<Response<RebuildIndexRsp>, Status> {
+        let log = HiveLog::from_request("RebuildIndex", &request);
+        let _g = log.enter();
+        log.info("rpc start");
+        let out = stats::rebuild_index::handle_rebuild_index(log.clone(), request).await;
+        match &out {
+            Ok(_) => log.finish_ok(),
+            Err(e) => log.finish_err(e),
+        }
+        out
+    }
+
+    async fn get_repository_layout(
+        &self,
+        request: Request<GetRepositoryLayoutReq>,
+    ) -> Result<Response<GetRepositoryLayoutRsp>, Status> {
+        let log = HiveLog::from_request("GetRepositoryLayout", &request);
+        let _g = log.enter();
+        log.info("rpc start");
+        let out =
+            stats::get_repository_layout::handle_get_repository_layout(log.clone(), request).await;
+        match &out {
+            Ok(_) => log.finish_ok(),
+            Err(e) => log.finish_err(e),
+        }
+        out
+    }
+
+    async fn export_repository_snapshot(
+        &self,
+        request: Request<ExportRepositorySnapshotReq>,
+    ) -> Result<Response<Self::ExportRepositorySnapshotStream>, Status> {
+        let log = HiveLog::from_request("ExportRepositorySnapshot", &request);
+        let _g = log.enter();
+        log.info("rpc start");
+        let out = stats::export_repository_snapshot::handle_export_repository_snapshot(
+            log.clone(),
+            request,
+        )
+        .await;
+        match &out {
+            Ok(_) => log.info("rpc accepted (stream opened)"),
+            Err(e) => log.finish_err(e),
+        }
+        out
+    }
+
+    async fn import_repository_snapshot(
+        &self,
+        request: Request<tonic::Streaming<ImportRepositorySnapshotReq>>,
+    ) -> Result<Response<ImportRepositorySnapshotRsp>, Status> {
+        let log = HiveLog::from_request("ImportRepositorySnapshot", &request);
+        let _g = log.enter();
+        log.info("rpc start (stream)");
+        let out = stats::import_repository_snapshot::handle_import_repository_snapshot(
+            log.clone(),
+            request,
+        )
+        .await;
+        match &out {
+            Ok(_) => log.finish_ok(),
+            Err(e) => log.finish_err(e),
+        }
+        out
+    }
+
+    async fn list_users(
+        &self,
+        request: Request<ListUsersReq>,
+    ) -> Result<Response<ListUsersRsp>, Status> {
+        let log = HiveLog::from_request("ListUsers", &request);
+        let _g = log.enter();
+        log.info("rpc start");
+        let out = user::list_users::handle_list_users(log.clone(), request).await;
+        match &out {
+            Ok(_) => log.finish_ok(),
+            Err(e) => log.finish_err(e),
+        }
+        out
+    }
+
+    async fn update_user_password(
+        &self,
+        request: Request<UpdateUserPasswordReq>,
+    ) -> Result<Response<UpdateUserPasswordRsp>, Status> {
+        let log = HiveLog::from_request("UpdateUserPassword", &request);
+        let _g = log.enter();
+        log.info("rpc start");
+        let out =
+            user::update_user_password::handle_update_user_password(log.clone(), request).await;
+        match &out {
+            Ok(_) => log.finish_ok(),
+            Err(e) => log.finish_err(e),
+        }
+        out
+    }
+
+    async fn delete_user(
+        &self,
+        request: Request<DeleteUserReq>,
+    ) -> Result<Response<DeleteUserRsp>, Status> {
+        let log = HiveLog::from_request("DeleteUser", &request);
+        let _g = log.enter();
+        log.info("rpc start");
+        let out = user::delete_user::handle_delete_user(log.clone(), request).await;
+        match &out {
+            Ok(_) => log.finish_ok(),
+            Err(e) => log.finish_err(e),
+        }
+        out
+    }
+
+    async fn get_user_profile(
+        &self,
+        request: Request<GetUserProfileReq>,
+    ) -> Result<Response<GetUserProfileRsp>, Status> {
+        let log = HiveLog::from_request("GetUserProfile", &request);
+        let _g = log.enter();
+        log.info("rpc start");
+        let out = user::get_user_profile::handle_get_user_profile(log.clone(), request).await;
+        match &out {
+            Ok(_) => log.finish_ok(),
+            Err(e) => log.finish_err(e),
+        }
+        out
+    }
+
+    async fn tag_changelist(
+        &self,
+        request: Request<TagChangelistReq>,
+    ) -> Result<Response<TagChangelistRsp>, Status> {
+        let log = HiveLog::from_request("TagChangelist", &request);
+        let _g = log.enter();
+        log.info("rpc start");
+        let out = tag::tag_changelist::handle_tag_changelist(log.clone(), request).await;
+        match &out {
+            Ok(_) => log.finish_ok(),
+            Err(e) => log.finish_err(e),
+        }
+        out
+    }
+
+    async fn untag_changelist(
+        &self,
+        request: Request<UntagChangelistReq>,
+    ) -> Result<Response<UntagChangelistRsp>, Status> {
+        let log = HiveLog::from_request("UntagChangelist", &request);
+        let _g = log.enter();
+        log.info("rpc start");
+        let out = tag::untag_changelist::handle_untag_changelist(log.clone(), request).await;
+        match &out {
+            Ok(_) => log.finish_ok(),
+            Err(e) => log.finish_err(e),
+        }
+        out
+    }
+
+    async fn get_changelist_by_tag(
+        &self,
+        request: Request<GetChangelistByTagReq>,
+    ) -> Result<Response<GetChangelistByTagRsp>, Status> {
+        let log = HiveLog::from_request("GetChangelistByTag", &request);
+        let _g = log.enter();
+        log.info("rpc start");
+        let out =
+            tag::get_changelist_by_tag::handle_get_changelist_by_tag(log.clone(), request).await;
+        match &out {
+            Ok(_) => log.finish_ok(),
+            Err(e) => log.finish_err(e),
+        }
+        out
+    }
+
+    async fn tag_file_revision(
+        &self,
+        request: Request<TagFileRevisionReq>,
+    ) -> Result<Response<TagFileRevisionRsp>, Status> {
+        let log = HiveLog::from_request("TagFileRevision", &request);
+        let _g = log.enter();
+        log.info("rpc start");
+        let out = tag::tag_file_revision::handle_tag_file_revision(log.clone(), request).await;
+        match &out {
+            Ok(_) => log.finish_ok(),
+            Err(e) => log.finish_err(e),
+        }
+        out
+    }
+
+    async fn list_file_revisions_by_tag(
+        &self,
+        request: Request<ListFileRevisionsByTagReq>,
+    ) -> Result<Response<ListFileRevisionsByTagRsp>, Status> {
+        let log = HiveLog::from_request("ListFileRevisionsByTag", &request);
+        let _g = log.enter();
+        log.info("rpc start");
+        let out = tag::list_file_revisions_by_tag::handle_list_file_revisions_by_tag(
+            log.clone(),
+            request,
+        )
+        .await;
+        match &out {
+            Ok(_) => log.finish_ok(),
+            Err(e) => log.finish_err(e),
+        }
+        out
+    }
+
+    async fn register_webhook(
+        &self,
+        request: Request<RegisterWebhookReq>,
+    ) -> Result<Response<RegisterWebhookRsp>, Status> {
+        let log = HiveLog::from_request("RegisterWebhook", &request);
+        let _g = log.enter();
+        log.info("rpc start");
+        let out = webhook::register_webhook::handle_register_webhook(log.clone(), request).await;
+        match &out {
+            Ok(_) => log.finish_ok(),
+            Err(e) => log.finish_err(e),
+        }
+        out
+    }
+
+    async fn unregister_webhook(
+        &self,
+        request: Request<UnregisterWebhookReq>,
+    ) -> Result<Response<UnregisterWebhookRsp>, Status> {
+        let log = HiveLog::from_request("UnregisterWebhook", &request);
+        let _g = log.enter();
+        log.info("rpc start");
+        let out =
+            webhook::unregister_webhook::handle_unregister_webhook(log.clone(), request).await;
+        match &out {
+            Ok(_) => log.finish_ok(),
+            Err(e) => log.finish_err(e),
+        }
+        out
+    }
+
+    async fn list_webhooks(
+        &self,
+        request: Request<ListWebhooksReq>,
+    ) -> Result<Response<ListWebhooksRsp>, Status> {
+        let log = HiveLog::from_request("ListWebhooks", &request);
+        let _g = log.enter();
+        log.info("rpc start");
+        let out = webhook::list_webhooks::handle_list_webhooks(log.clone(), request).await;
+        match &out {
+            Ok(_) => log.finish_ok(),
+            Err(e) => log.finish_err(e),
+        }
+        out
+    }
+
+    async fn list_locked_files(
+        &self,
+        request: Request<ListLockedFilesReq>,
+    ) -> Result<Response<ListLockedFilesRsp>, Status> {
+        let log = HiveLog::from_request("ListLockedFiles", &request);
+        let _g = log.enter();
+        log.info("rpc start");
+        let out = submit::list_locked_files::handle_list_locked_files(log.clone(), request).await;
+        match &out {
+            Ok(_) => log.finish_ok(),
+            Err(e) => log.finish_err(e),
+        }
+        out
+    }
+
+    async fn get_submit_context(
+        &self,
+        request: Request<GetSubmitContextReq>,
+    ) -> Result<Response<GetSubmitContextRsp>, Status> {
+        let log = HiveLog::from_request("GetSubmitContext", &request);
+        let _g = log.enter();
+        log.info("rpc start");
+        let out = submit::submit_context::handle_get_submit_context(log.clone(), request).await;
+        match &out {
+            Ok(_) => log.finish_ok(),
+            Err(e) => log.finish_err(e),
+        }
+        out
+    }
+
+    async fn cancel_submit_context(
+        &self,
+        request: Request<CancelSubmitContextReq>,
+    ) -> Result<Response<CancelSubmitContextRsp>, Status> {
+        let log = HiveLog::from_request("CancelSubmitContext", &request);
+        let _g = log.enter();
+        log.info("rpc start");
+        let out = submit::submit_context::handle_cancel_submit_context(log.clone(), request).await;
+        match &out {
+            Ok(_) => log.finish_ok(),
+            Err(e) => log.finish_err(e),
+        }
+        out
+    }
+
+    async fn squash_changelists(
+        &self,
+        request: Request<SquashChangelistsReq>,
+    ) -> Result<Response<SquashChangelistsRsp>, Status> {
+        let log = HiveLog::from_request("SquashChangelists", &request);
+        let _g = log.enter();
+        log.info("rpc start");
+        let out =
+            changelist::squash_changelists::handle_squash_changelists(log.clone(), request).await;
+        match &out {
+            Ok(_) => log.finish_ok(),
+            Err(e) => log.finish_err(e),
+        }
+        out
+    }
+
+    async fn get_changelist_chain(
+        &self,
+        request: Request<GetChangelistChainReq>,
+    ) -> Result<Response<GetChangelistChainRsp>, Status> {
+        let log = HiveLog::from_request("GetChangelistChain", &request);
+        let _g = log.enter();
+        log.info("rpc start");
+        let out =
+            changelist::get_changelist_chain::handle_get_changelist_chain(log.clone(), request)
+                .await;
+        match &out {
+            Ok(_) => log.finish_ok(),
+            Err(e) => log.finish_err(e),
+        }
+        out
+    }
+
+    async fn cherry_pick(
+        &self,
+        request: Request<CherryPickReq>,
+    ) -> Result<Response<CherryPickRsp>, Status> {
+        let log = HiveLog::from_request("CherryPick", &request);
+        let _g = log.enter();
+        log.info("rpc start");
+        let out = changelist::cherry_pick::handle_cherry_pick(log.clone(), request).await;
+        match &out {
+            Ok(_) => log.finish_ok(),
+            Err(e) => log.finish_err(e),
+        }
+        out
+    }
+
+    async fn merge(&self, request: Request<MergeReq>) -> Result<Response<MergeRsp>, Status> {
+        let log = HiveLog::from_request("Merge", &request);
+        let _g = log.enter();
+        log.info("rpc start");
+        let out = branch::merge::handle_merge(log.clone(), request).await;
+        match &out {
+            Ok(_) => log.finish_ok(),
+            Err(e) => log.finish_err(e),
+        }
+        out
+    }
+
+    async fn create_snapshot(
+        &self,
+        request: Request<CreateSnapshotReq>,
+    ) -> Result<Response<CreateSnapshotRsp>, Status> {
+        let log = HiveLog::from_request("CreateSnapshot", &request);
+        let _g = log.enter();
+        log.info("rpc start");
+        let out = snapshot::create_snapshot::handle_create_snapshot(log.clone(), request).await;
+        match &out {
+            Ok(_) => log.finish_ok(),
+            Err(e) => log.finish_err(e),
+        }
+        out
+    }
+
+    async fn list_snapshots(
+        &self,
+        request: Request<ListSnapshotsReq>,
+    ) -> Result<Response<ListSnapshotsRsp>, Status> {
+        let log = HiveLog::from_request("ListSnapshots", &request);
+        let _g = log.enter();
+        log.info("rpc start");
+        let out = snapshot::list_snapshots::handle_list_snapshots(log.clone(), request).await;
+        match &out {
+            Ok(_) => log.finish_ok(),
+            Err(e) => log.finish_err(e),
+        }
+        out
+    }
+
+    async fn delete_snapshot(
+        &self,
+        request: Request<DeleteSnapshotReq>,
+    ) -> Result<Response<DeleteSnapshotRsp>, Status> {
+        let log = HiveLog::from_request("DeleteSnapshot", &request);
+        let _g = log.enter();
+        log.info("rpc start");
+        let out = snapshot::delete_snapshot::handle_delete_snapshot(log.clone(), request).await;
+        match &out {
+            Ok(_) => log.finish_ok(),
+            Err(e) => log.finish_err(e),
+        }
+        out
+    }
+
+    async fn restore_snapshot(
+        &self,
+        request: Request<RestoreSnapshotReq>,
+    ) -> Result<Response<RestoreSnapshotRsp>, Status> {
+        let log = HiveLog::from_request("RestoreSnapshot", &request);
+        let _g = log.enter();
+        log.info("rpc start");
+        let out = snapshot::restore_snapshot::handle_restore_snapshot(log.clone(), request).await;
+        match &out {
+            Ok(_) => log.finish_ok(),
+            Err(e) => log.finish_err(e),
+        }
+        out
+    }
+}
+
+/// 构建鉴权拦截器，并按配置启用限流
+fn build_interceptor(auth: Arc<AuthService>) -> AuthInterceptor {
+    let interceptor = AuthInterceptor::new(auth);
+    match RateLimiter::from_config() {
+        Some(rate_limiter) => interceptor.with_rate_limiter(rate_limiter),
+        None => interceptor,
+    }
+}
+
+/// 启动 gRPC 服务器（优雅关闭）
+pub async fn start_server_with_shutdown<S>(
+    addr: std::net::SocketAddr,
+    shutdown: S,
+) -> Result<(), Box<dyn std::error::Error>>
+where
+    S: std::future::Future<Output = ()> + Send + 'static,
+{
+    // 基于全局配置初始化 AuthService，并构建 gRPC 拦截器
+    let auth = AuthService::from_config();
+    let service = CrvHiveService::new(Arc::clone(&auth));
+    let interceptor = build_interceptor(Arc::clone(&auth));
+    let cors = build_cors_layer();
+    let (reflection_v1, reflection_v1alpha) = build_reflection_services()?;
+    submit::description::init_description_regex()?;
+    restore_submit_tickets().await;
+    let eviction = spawn_chunk_cache_eviction();
+    let offload = spawn_chunk_offload();
+
+    let result = Server::builder()
+        .accept_http1(true)
+        .layer(cors)
+        .layer(GrpcWebLayer::new())
+        .layer(RateLimitHeadersLayer)
+        .add_service(HiveServiceServer::with_interceptor(service, interceptor))
+        .add_optional_service(reflection_v1)
+        .add_optional_service(reflection_v1alpha)
+        .serve_with_shutdown(addr, shutdown)
+        .await;
+    eviction.abort();
+    if let Some(offload) = offload {
+        offload.abort();
+    }
+    result?;
+
+    Ok(())
+}
+
+/// 启动 gRPC 服务器（无关闭信号，会一直运行直至进程退出）
+pub async fn start_server(addr: std::net::SocketAddr) -> Result<(), Box<dyn std::error::Error>> {
+    let auth = AuthService::from_config();
+    let service = CrvHiveService::new(Arc::clone(&auth));
+    let interceptor = build_interceptor(auth);
+    let cors = build_cors_layer();
+    let (reflection_v1, reflection_v1alpha) = build_reflection_services()?;
+    submit::description::init_description_regex()?;
+    restore_submit_tickets().await;
+    let eviction = spawn_chunk_cache_eviction();
+    let offload = spawn_chunk_offload();
+
+    let result = Server::builder()
+        .accept_http1(true)
+        .layer(cors)
+        .layer(GrpcWebLayer::new())
+        .layer(RateLimitHeadersLayer)
+        .add_service(HiveServiceServer::with_interceptor(service, interceptor))
+        .add_optional_service(reflection_v1)
+        .add_optional_service(reflection_v1alpha)
+        .serve(addr)
+        .await;
+    eviction.abort();
+    if let Some(offload) = offload {
+        offload.abort();
+    }
+    result?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tonic_reflection::pb::v1::ServerReflectionRequest;
+    use tonic_reflection::pb::v1::server_reflection_client::ServerReflectionClient;
+    use tonic_reflection::pb::v1::server_reflection_request::MessageRequest;
+    use tonic_reflection::pb::v1::server_reflection_response::MessageResponse;
+
+    #[tokio::test]
+    async fn reflection_lists_hive_service() {
+        // 先占用一个空闲端口再释放，交给服务器监听
+        let addr = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let (tx, rx) = tokio::sync::oneshot::channel::<()>();
+        let server = tokio::spawn(async move {
+            start_server_with_shutdown(addr, async {
+                let _ = rx.await;
+            })
+            .await
+            .map_err(|e| e.to_string())
+        });
+
+        let endpoint = tonic::transport::Endpoint::from_shared(format!("http://{addr}")).unwrap();
+        let mut channel = None;
+        for _ in 0..50 {
+            if let Ok(c) = endpoint.connect().await {
+                channel = Some(c);
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+        let mut client =
+            ServerReflectionClient::new(channel.expect("hive server should accept connections"));
+
+        let request = ServerReflectionRequest {
+            host: String::new(),
+            message_request: Some(MessageRequest::ListServices(String::new())),
+        };
+        let mut stream = client
+            .server_reflection_info(tokio_stream::iter(vec![request]))
+            .await
+            .unwrap()
+            .into_inner();
+        let rsp = stream
+            .message()
+            .await
+            .unwrap()
+            .expect("reflection response");
+
+        let Some(MessageResponse::ListServicesResponse(list)) = rsp.message_response else {
+            panic!("unexpected reflection response: {:?}", rsp.message_response);
+        };
+        let services: Vec<String> = list.service.into_iter().map(|s| s.name).collect();
+        assert!(
+            services.iter().any(|s| s == "hive_proto.HiveService"),
+            "services: {services:?}"
+        );
+
+        let _ = tx.send(());
+        server.await.unwrap().unwrap();
+    }
 }
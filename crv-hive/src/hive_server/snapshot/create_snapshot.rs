@@ -0,0 +1,211 @@
+use crate::auth::require_user;
+use crate::database::dao::{Dao, dao};
+use crate::hive_server::changelist::resolve_changelist_id;
+use crate::hive_server::snapshot::snapshot_to_pb;
+use crate::logging::HiveLog;
+use crate::pb::{CreateSnapshotReq, CreateSnapshotRsp};
+use crv_core::metadata::SnapshotDoc;
+use tonic::{Request, Response, Status};
+
+pub async fn handle_create_snapshot(
+    log: HiveLog,
+    r: Request<CreateSnapshotReq>,
+) -> Result<Response<CreateSnapshotRsp>, Status> {
+    let user = require_user(&r)?.clone();
+    let log = log.with_user(&user.username);
+    let _g = log.enter();
+
+    let request = r.into_inner();
+    log.info(&format!(
+        "create_snapshot received: name={}, branch={}, changelist={}",
+        request.name, request.branch_id, request.changelist_id
+    ));
+
+    let rsp = create_snapshot(
+        dao().as_ref(),
+        request,
+        &user.username,
+        chrono::Utc::now().timestamp_millis(),
+    )
+    .await?;
+    Ok(Response::new(rsp))
+}
+
+/// 创建快照：将分支固定在 HEAD（含）之前的某个 changelist 上，快照名称全局唯一。
+///
+/// 引用已被压缩的 changelist 时，快照固定在压缩后的 changelist 上。
+pub(crate) async fn create_snapshot(
+    dao: &dyn Dao,
+    request: CreateSnapshotReq,
+    created_by: &str,
+    now: i64,
+) -> Result<CreateSnapshotRsp, Status> {
+    let name = request.name.trim();
+    if name.is_empty() {
+        return Err(Status::invalid_argument("snapshot name is required"));
+    }
+    let branch_id = request.branch_id.trim();
+    if branch_id.is_empty() {
+        return Err(Status::invalid_argument("branch_id is required"));
+    }
+
+    let branch = dao
+        .find_branch_by_id(branch_id)
+        .await
+        .map_err(|e| Status::internal(format!("database error while finding branch: {e}")))?
+        .ok_or_else(|| Status::not_found(format!("branch '{branch_id}' not found")))?;
+
+    let changelist_id = if request.changelist_id == 0 {
+        branch.head_changelist_id
+    } else {
+        let changelist_id = resolve_changelist_id(dao, request.changelist_id).await?;
+        dao.find_changelist_by_id(changelist_id)
+            .await
+            .map_err(|e| Status::internal(format!("database error while finding changelist: {e}")))?
+            .ok_or_else(|| Status::not_found(format!("changelist {changelist_id} not found")))?;
+        let on_branch = dao
+            .is_changelist_on_chain(branch.head_changelist_id, changelist_id)
+            .await
+            .map_err(|e| Status::internal(format!("database error while checking branch: {e}")))?;
+        if !on_branch {
+            return Err(Status::failed_precondition(format!(
+                "changelist {changelist_id} is not on branch '{branch_id}'"
+            )));
+        }
+        changelist_id
+    };
+
+    let exists = dao
+        .list_snapshots("")
+        .await
+        .map_err(|e| Status::internal(format!("database error while listing snapshots: {e}")))?
+        .iter()
+        .any(|s| s.name == name);
+    if exists {
+        return Err(Status::already_exists(format!(
+            "snapshot '{name}' already exists"
+        )));
+    }
+
+    let mut snapshot = SnapshotDoc {
+        id: 0,
+        name: name.to_string(),
+        branch_id: branch_id.to_string(),
+        changelist_id,
+        created_by: created_by.to_string(),
+        created_at: now,
+        description: request.description,
+    };
+    snapshot.id = dao
+        .insert_snapshot(&snapshot)
+        .await
+        .map_err(|e| Status::internal(format!("database error while creating snapshot: {e}")))?;
+
+    Ok(CreateSnapshotRsp {
+        snapshot: Some(snapshot_to_pb(snapshot)),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::dao::MockDao;
+    use crate::hive_server::snapshot::list_snapshots::list_snapshots;
+    use crate::pb::ListSnapshotsReq;
+    use crv_core::metadata::{BranchDoc, BranchMetadata};
+    use tonic::Code;
+
+    /// changelist 1/2/3，main 分支 HEAD 为 2
+    async fn dao_with_branch() -> MockDao {
+        let dao = MockDao::default();
+        for committed_at in [100, 200, 300] {
+            dao.insert_changelist("alice", "", committed_at, serde_json::json!({}))
+                .await
+                .unwrap();
+        }
+        dao.insert_branch(&BranchDoc {
+            id: "main".to_string(),
+            created_at: 0,
+            created_by: "alice".to_string(),
+            head_changelist_id: 2,
+            is_protected: false,
+            metadata: BranchMetadata {
+                description: "main".to_string(),
+                owners: vec![],
+                description_regex: None,
+            },
+        })
+        .await
+        .unwrap();
+        dao
+    }
+
+    fn request(name: &str, changelist_id: i64) -> CreateSnapshotReq {
+        CreateSnapshotReq {
+            branch_id: "main".to_string(),
+            changelist_id,
+            name: name.to_string(),
+            description: String::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn creates_and_lists_snapshots() {
+        let dao = dao_with_branch().await;
+
+        let head = create_snapshot(&dao, request("release-1.0", 0), "alice", 1000)
+            .await
+            .unwrap()
+            .snapshot
+            .unwrap();
+        assert_eq!(head.changelist_id, 2);
+        assert_eq!(head.created_by, "alice");
+
+        let pinned = create_snapshot(&dao, request("beta", 1), "bob", 2000)
+            .await
+            .unwrap()
+            .snapshot
+            .unwrap();
+        assert_eq!(pinned.changelist_id, 1);
+
+        let rsp = list_snapshots(
+            &dao,
+            ListSnapshotsReq {
+                branch_id: "main".to_string(),
+            },
+        )
+        .await
+        .unwrap();
+        let names: Vec<&str> = rsp.snapshots.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, vec!["release-1.0", "beta"]);
+    }
+
+    #[tokio::test]
+    async fn rejects_duplicate_names_and_invalid_changelists() {
+        let dao = dao_with_branch().await;
+        create_snapshot(&dao, request("release", 0), "alice", 1000)
+            .await
+            .unwrap();
+
+        let err = create_snapshot(&dao, request("release", 1), "alice", 1000)
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::AlreadyExists);
+
+        // changelist 3 在 main 分支 HEAD 之后
+        let err = create_snapshot(&dao, request("future", 3), "alice", 1000)
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::FailedPrecondition);
+
+        let err = create_snapshot(&dao, request("missing", 42), "alice", 1000)
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::NotFound);
+
+        let err = create_snapshot(&dao, request(" ", 0), "alice", 1000)
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::InvalidArgument);
+    }
+}
@@ -0,0 +1,121 @@
+use crate::auth::{UserContext, require_user};
+use crate::database::dao::{Dao, dao};
+use crate::logging::HiveLog;
+use crate::pb::{DeleteSnapshotReq, DeleteSnapshotRsp};
+use tonic::{Request, Response, Status};
+
+pub async fn handle_delete_snapshot(
+    log: HiveLog,
+    r: Request<DeleteSnapshotReq>,
+) -> Result<Response<DeleteSnapshotRsp>, Status> {
+    let user = require_user(&r)?.clone();
+    let log = log.with_user(&user.username);
+    let _g = log.enter();
+
+    let request = r.into_inner();
+    log.info(&format!(
+        "delete_snapshot received: id={}",
+        request.snapshot_id
+    ));
+
+    let rsp = delete_snapshot(dao().as_ref(), &user, request).await?;
+    Ok(Response::new(rsp))
+}
+
+/// 删除快照，仅快照的创建者或管理员可用，不存在时返回 `NotFound`。
+pub(crate) async fn delete_snapshot(
+    dao: &dyn Dao,
+    user: &UserContext,
+    request: DeleteSnapshotReq,
+) -> Result<DeleteSnapshotRsp, Status> {
+    let snapshot = dao
+        .find_snapshot_by_id(request.snapshot_id)
+        .await
+        .map_err(|e| Status::internal(format!("database error while finding snapshot: {e}")))?
+        .ok_or_else(|| Status::not_found(format!("snapshot {} not found", request.snapshot_id)))?;
+    if snapshot.created_by != user.username && !user.is_admin() {
+        return Err(Status::permission_denied(format!(
+            "user '{}' is not the creator of snapshot '{}'",
+            user.username, snapshot.name
+        )));
+    }
+
+    dao.delete_snapshot(snapshot.id)
+        .await
+        .map_err(|e| Status::internal(format!("database error while deleting snapshot: {e}")))?;
+    Ok(DeleteSnapshotRsp {})
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::{ADMIN_SCOPE, AuthSource};
+    use crate::database::dao::MockDao;
+    use crv_core::metadata::SnapshotDoc;
+    use tonic::Code;
+
+    fn user(username: &str, scopes: &[&str]) -> UserContext {
+        UserContext {
+            username: username.to_string(),
+            scopes: scopes.iter().map(|s| s.to_string()).collect(),
+            source: AuthSource::Jwt,
+        }
+    }
+
+    async fn insert_snapshot(dao: &MockDao, name: &str, created_by: &str) -> i64 {
+        dao.insert_snapshot(&SnapshotDoc {
+            id: 0,
+            name: name.to_string(),
+            branch_id: "main".to_string(),
+            changelist_id: 1,
+            created_by: created_by.to_string(),
+            created_at: 0,
+            description: String::new(),
+        })
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn only_creator_or_admin_can_delete() {
+        let dao = MockDao::default();
+        let first = insert_snapshot(&dao, "first", "alice").await;
+        let second = insert_snapshot(&dao, "second", "alice").await;
+
+        let err = delete_snapshot(
+            &dao,
+            &user("bob", &[]),
+            DeleteSnapshotReq { snapshot_id: first },
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(err.code(), Code::PermissionDenied);
+
+        delete_snapshot(
+            &dao,
+            &user("alice", &[]),
+            DeleteSnapshotReq { snapshot_id: first },
+        )
+        .await
+        .unwrap();
+        delete_snapshot(
+            &dao,
+            &user("root", &[ADMIN_SCOPE]),
+            DeleteSnapshotReq {
+                snapshot_id: second,
+            },
+        )
+        .await
+        .unwrap();
+        assert!(dao.list_snapshots("").await.unwrap().is_empty());
+
+        let err = delete_snapshot(
+            &dao,
+            &user("alice", &[]),
+            DeleteSnapshotReq { snapshot_id: first },
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(err.code(), Code::NotFound);
+    }
+}
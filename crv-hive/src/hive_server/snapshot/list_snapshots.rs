@@ -0,0 +1,39 @@
+use crate::auth::require_user;
+use crate::database::dao::{Dao, read_dao};
+use crate::hive_server::snapshot::snapshot_to_pb;
+use crate::logging::HiveLog;
+use crate::pb::{ListSnapshotsReq, ListSnapshotsRsp};
+use tonic::{Request, Response, Status};
+
+pub async fn handle_list_snapshots(
+    log: HiveLog,
+    r: Request<ListSnapshotsReq>,
+) -> Result<Response<ListSnapshotsRsp>, Status> {
+    let user = require_user(&r)?.clone();
+    let log = log.with_user(&user.username);
+    let _g = log.enter();
+
+    let request = r.into_inner();
+    log.info(&format!(
+        "list_snapshots received: branch={}",
+        request.branch_id
+    ));
+
+    let rsp = list_snapshots(read_dao().as_ref(), request).await?;
+    log.info(&format!("snapshots: {}", rsp.snapshots.len()));
+    Ok(Response::new(rsp))
+}
+
+/// 按创建顺序列出分支上的快照，未指定分支时列出所有快照。
+pub(crate) async fn list_snapshots(
+    dao: &dyn Dao,
+    request: ListSnapshotsReq,
+) -> Result<ListSnapshotsRsp, Status> {
+    let snapshots = dao
+        .list_snapshots(request.branch_id.trim())
+        .await
+        .map_err(|e| Status::internal(format!("database error while listing snapshots: {e}")))?;
+    Ok(ListSnapshotsRsp {
+        snapshots: snapshots.into_iter().map(snapshot_to_pb).collect(),
+    })
+}
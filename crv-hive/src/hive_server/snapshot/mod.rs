@@ -0,0 +1,19 @@
+pub mod create_snapshot;
+pub mod delete_snapshot;
+pub mod list_snapshots;
+pub mod restore_snapshot;
+
+use crate::pb::SnapshotInfo;
+use crv_core::metadata::SnapshotDoc;
+
+fn snapshot_to_pb(snapshot: SnapshotDoc) -> SnapshotInfo {
+    SnapshotInfo {
+        id: snapshot.id,
+        name: snapshot.name,
+        branch_id: snapshot.branch_id,
+        changelist_id: snapshot.changelist_id,
+        created_by: snapshot.created_by,
+        created_at: snapshot.created_at,
+        description: snapshot.description,
+    }
+}
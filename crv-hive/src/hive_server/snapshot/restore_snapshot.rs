@@ -0,0 +1,118 @@
+use crate::auth::{ADMIN_SCOPE, require_scope};
+use crate::database::dao::{Dao, dao};
+use crate::hive_server::branch::set_protection::check_branch_accepts_submit;
+use crate::hive_server::submit::SUBMIT_LOCK;
+use crate::logging::HiveLog;
+use crate::pb::{RestoreSnapshotReq, RestoreSnapshotRsp};
+use tonic::{Request, Response, Status};
+
+pub async fn handle_restore_snapshot(
+    log: HiveLog,
+    r: Request<RestoreSnapshotReq>,
+) -> Result<Response<RestoreSnapshotRsp>, Status> {
+    let user = require_scope(&r, ADMIN_SCOPE)?.clone();
+    let log = log.with_user(&user.username);
+    let _g = log.enter();
+
+    let request = r.into_inner();
+    log.info(&format!(
+        "restore_snapshot received: id={}",
+        request.snapshot_id
+    ));
+
+    let rsp = restore_snapshot(dao().as_ref(), request).await?;
+    log.info(&format!(
+        "branch '{}' restored to changelist {}",
+        rsp.branch_id, rsp.changelist_id
+    ));
+    Ok(Response::new(rsp))
+}
+
+/// 将快照所在分支的 HEAD 移动到快照的 changelist，期间持有 `SUBMIT_LOCK` 的写锁。
+pub(crate) async fn restore_snapshot(
+    dao: &dyn Dao,
+    request: RestoreSnapshotReq,
+) -> Result<RestoreSnapshotRsp, Status> {
+    let _submit_guard = SUBMIT_LOCK.write().await;
+
+    let snapshot = dao
+        .find_snapshot_by_id(request.snapshot_id)
+        .await
+        .map_err(|e| Status::internal(format!("database error while finding snapshot: {e}")))?
+        .ok_or_else(|| Status::not_found(format!("snapshot {} not found", request.snapshot_id)))?;
+    check_branch_accepts_submit(dao, &snapshot.branch_id).await?;
+
+    let updated = dao
+        .update_branch_head(&snapshot.branch_id, snapshot.changelist_id)
+        .await
+        .map_err(|e| Status::internal(format!("database error while updating branch: {e}")))?;
+    if !updated {
+        return Err(Status::not_found(format!(
+            "branch '{}' not found",
+            snapshot.branch_id
+        )));
+    }
+
+    Ok(RestoreSnapshotRsp {
+        branch_id: snapshot.branch_id,
+        changelist_id: snapshot.changelist_id,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::dao::MockDao;
+    use crv_core::metadata::{BranchDoc, BranchMetadata, SnapshotDoc};
+
+    #[tokio::test]
+    async fn restore_moves_branch_head_to_snapshot() {
+        let dao = MockDao::default();
+        dao.insert_branch(&BranchDoc {
+            id: "main".to_string(),
+            created_at: 0,
+            created_by: "alice".to_string(),
+            head_changelist_id: 3,
+            is_protected: false,
+            metadata: BranchMetadata {
+                description: "main".to_string(),
+                owners: vec![],
+                description_regex: None,
+            },
+        })
+        .await
+        .unwrap();
+        let snapshot_id = dao
+            .insert_snapshot(&SnapshotDoc {
+                id: 0,
+                name: "before-refactor".to_string(),
+                branch_id: "main".to_string(),
+                changelist_id: 1,
+                created_by: "alice".to_string(),
+                created_at: 0,
+                description: String::new(),
+            })
+            .await
+            .unwrap();
+
+        let rsp = restore_snapshot(&dao, RestoreSnapshotReq { snapshot_id })
+            .await
+            .unwrap();
+        assert_eq!(rsp.branch_id, "main");
+        assert_eq!(rsp.changelist_id, 1);
+        let branch = dao.find_branch_by_id("main").await.unwrap().unwrap();
+        assert_eq!(branch.head_changelist_id, 1);
+
+        let err = restore_snapshot(&dao, RestoreSnapshotReq { snapshot_id: 42 })
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), tonic::Code::NotFound);
+
+        // 受保护分支只接受 merge，不能通过恢复快照移动 HEAD
+        dao.set_branch_protection("main", true).await.unwrap();
+        let err = restore_snapshot(&dao, RestoreSnapshotReq { snapshot_id })
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), tonic::Code::PermissionDenied);
+    }
+}
@@ -0,0 +1,204 @@
+use std::io::{self, Write};
+
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{Request, Response, Status};
+
+use crate::auth::{ADMIN_SCOPE, require_scope};
+use crate::database::dao;
+use crate::hive_server::repository_manager;
+use crate::logging::HiveLog;
+use crate::pb::{ExportRepositorySnapshotReq, ExportRepositorySnapshotRsp};
+use crv_core::repository::Repository;
+
+pub type ExportRepositorySnapshotStream =
+    ReceiverStream<Result<ExportRepositorySnapshotRsp, Status>>;
+
+/// 每条响应消息携带的 tar 归档字节数上限
+const SNAPSHOT_CHUNK_SIZE: usize = 4 * 1024 * 1024;
+
+/// 归档中元数据导出文件的名称
+pub(crate) const METADATA_ENTRY: &str = "metadata.json";
+
+/// 归档中 pack 文件所在的目录，其下保持仓库内的相对路径
+pub(crate) const REPOSITORY_DIR: &str = "repository";
+
+/// 将整个 chunk 仓库与元数据库导出为一个 tar 归档，分段流式返回，用于灾难恢复
+pub async fn handle_export_repository_snapshot(
+    log: HiveLog,
+    r: Request<ExportRepositorySnapshotReq>,
+) -> Result<Response<ExportRepositorySnapshotStream>, Status> {
+    let user = require_scope(&r, ADMIN_SCOPE)?.clone();
+    let log = log.with_user(&user.username);
+    let _g = log.enter();
+
+    let repo = repository_manager()?;
+    let metadata = dao::export_metadata()
+        .await
+        .map_err(|e| Status::internal(format!("database error while exporting metadata: {e}")))?;
+
+    let (tx, rx) = mpsc::channel(4);
+    let log_spawn = log.clone();
+    // tar 的写入与 pack 文件的读取都是阻塞 IO，放到阻塞线程中执行
+    tokio::task::spawn_blocking(move || {
+        let _g = log_spawn.enter();
+        let mut writer = ChunkSender::new(tx.clone());
+        let result = write_snapshot_archive(repo, &metadata, &mut writer)
+            .and_then(|()| writer.flush())
+            .map_err(|e| Status::internal(format!("failed to export repository snapshot: {e}")));
+        match result {
+            Ok(()) => log_spawn.info(&format!(
+                "repository snapshot exported: bytes={}",
+                writer.total_bytes
+            )),
+            Err(status) => {
+                log_spawn.finish_err(&status);
+                let _ = tx.blocking_send(Err(status));
+            }
+        }
+    });
+
+    Ok(Response::new(ReceiverStream::new(rx)))
+}
+
+/// 写出快照归档：先写 `metadata.json`，再写 `repository/` 下的所有 pack 文件
+pub(crate) fn write_snapshot_archive<W: Write>(
+    repo: &Repository,
+    metadata: &serde_json::Value,
+    out: W,
+) -> io::Result<()> {
+    let mut builder = tar::Builder::new(out);
+
+    let metadata = serde_json::to_vec_pretty(metadata)?;
+    let mut header = tar::Header::new_gnu();
+    header.set_size(metadata.len() as u64);
+    header.set_mode(0o644);
+    header.set_mtime(chrono::Utc::now().timestamp().max(0) as u64);
+    builder.append_data(&mut header, METADATA_ENTRY, metadata.as_slice())?;
+
+    repo.visit_pack_files(|relative, path| {
+        let name = std::path::Path::new(REPOSITORY_DIR).join(relative);
+        builder.append_path_with_name(path, name)?;
+        Ok(())
+    })
+    .map_err(io::Error::other)?;
+
+    builder.into_inner()?.flush()
+}
+
+/// 将写入的字节按 `SNAPSHOT_CHUNK_SIZE` 分段发送到响应流；客户端断开后写入返回错误
+struct ChunkSender {
+    tx: mpsc::Sender<Result<ExportRepositorySnapshotRsp, Status>>,
+    buffer: Vec<u8>,
+    total_bytes: u64,
+}
+
+impl ChunkSender {
+    fn new(tx: mpsc::Sender<Result<ExportRepositorySnapshotRsp, Status>>) -> Self {
+        Self {
+            tx,
+            buffer: Vec::with_capacity(SNAPSHOT_CHUNK_SIZE),
+            total_bytes: 0,
+        }
+    }
+
+    fn send_buffer(&mut self) -> io::Result<()> {
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+        let data = std::mem::replace(&mut self.buffer, Vec::with_capacity(SNAPSHOT_CHUNK_SIZE));
+        self.total_bytes += data.len() as u64;
+        self.tx
+            .blocking_send(Ok(ExportRepositorySnapshotRsp { data }))
+            .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "client disconnected"))
+    }
+}
+
+impl Write for ChunkSender {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = buf.len().min(SNAPSHOT_CHUNK_SIZE - self.buffer.len());
+        self.buffer.extend_from_slice(&buf[..n]);
+        if self.buffer.len() == SNAPSHOT_CHUNK_SIZE {
+            self.send_buffer()?;
+        }
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.send_buffer()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crv_core::repository::{Compression, RepositoryLayout};
+    use std::io::Read;
+
+    #[test]
+    fn archive_contains_metadata_and_pack_files() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let repo = Repository::new(temp_dir.path()).unwrap();
+        let record = repo
+            .write_chunk(b"backup chunk", Compression::None)
+            .unwrap();
+        repo.seal_all().unwrap();
+        let metadata = serde_json::json!({
+            "branches": [{ "id": "main", "head_changelist_id": 1 }],
+            "users": [],
+        });
+
+        let mut archive = Vec::new();
+        write_snapshot_archive(&repo, &metadata, &mut archive).unwrap();
+
+        let base = format!(
+            "{REPOSITORY_DIR}/{}/{}",
+            RepositoryLayout::shard_dir_name(record.hash[0]),
+            RepositoryLayout::pack_base_name(1)
+        );
+        let (dat_path, _) = repo.layout().pack_paths(record.hash[0], 1).unwrap();
+        let mut entries = Vec::new();
+        let mut reader = tar::Archive::new(archive.as_slice());
+        for entry in reader.entries().unwrap() {
+            let mut entry = entry.unwrap();
+            let name = entry.path().unwrap().to_string_lossy().replace('\\', "/");
+            let mut content = Vec::new();
+            entry.read_to_end(&mut content).unwrap();
+            if name == METADATA_ENTRY {
+                let exported: serde_json::Value = serde_json::from_slice(&content).unwrap();
+                assert_eq!(exported, metadata);
+            } else if name == format!("{base}.dat") {
+                assert_eq!(content, std::fs::read(&dat_path).unwrap());
+            }
+            entries.push(name);
+        }
+        assert_eq!(
+            entries,
+            vec![
+                METADATA_ENTRY.to_string(),
+                format!("{base}.dat"),
+                format!("{base}.idx"),
+            ]
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn archive_is_split_into_bounded_messages() {
+        let (tx, mut rx) = mpsc::channel(8);
+        let handle = tokio::task::spawn_blocking(move || {
+            let mut writer = ChunkSender::new(tx);
+            writer
+                .write_all(&vec![7u8; SNAPSHOT_CHUNK_SIZE + 10])
+                .unwrap();
+            writer.flush().unwrap();
+            writer.total_bytes
+        });
+
+        let mut sizes = Vec::new();
+        while let Some(message) = rx.recv().await {
+            sizes.push(message.unwrap().data.len());
+        }
+        assert_eq!(handle.await.unwrap(), SNAPSHOT_CHUNK_SIZE as u64 + 10);
+        assert_eq!(sizes, vec![SNAPSHOT_CHUNK_SIZE, 10]);
+    }
+}
@@ -0,0 +1,84 @@
+use crate::auth::{ADMIN_SCOPE, require_scope};
+use crate::hive_server::repository_manager;
+use crate::logging::HiveLog;
+use crate::pb::{BundleInfo, GetRepositoryLayoutReq, GetRepositoryLayoutRsp};
+use crv_core::repository::{PackHealth, Repository, RepositoryLayout};
+use tonic::{Request, Response, Status};
+
+/// 检查 chunk 仓库中每个 pack 的完整性，供管理员排查损坏的 pack
+pub async fn handle_get_repository_layout(
+    log: HiveLog,
+    r: Request<GetRepositoryLayoutReq>,
+) -> Result<Response<GetRepositoryLayoutRsp>, Status> {
+    let user = require_scope(&r, ADMIN_SCOPE)?.clone();
+    let log = log.with_user(&user.username);
+    let _g = log.enter();
+
+    // 需要扫描所有 pack 文件，放到阻塞线程中执行
+    let repo = repository_manager()?;
+    let rsp = tokio::task::spawn_blocking(move || get_repository_layout(repo))
+        .await
+        .map_err(|e| Status::internal(format!("failed to inspect repository: {e}")))??;
+    let invalid = rsp.bundles.iter().filter(|b| !b.is_valid).count();
+    log.info(&format!(
+        "repository inspected: bundles={}, invalid={invalid}",
+        rsp.bundles.len()
+    ));
+
+    Ok(Response::new(rsp))
+}
+
+pub(crate) fn get_repository_layout(repo: &Repository) -> Result<GetRepositoryLayoutRsp, Status> {
+    let packs = repo
+        .inspect_packs()
+        .map_err(|e| Status::internal(format!("failed to inspect repository: {e}")))?;
+    Ok(GetRepositoryLayoutRsp {
+        bundles: packs.into_iter().map(bundle_info).collect(),
+    })
+}
+
+fn bundle_info(pack: PackHealth) -> BundleInfo {
+    BundleInfo {
+        id: format!(
+            "{}/{}",
+            RepositoryLayout::shard_dir_name(pack.shard),
+            RepositoryLayout::pack_base_name(pack.pack_id)
+        ),
+        chunk_count: pack.chunk_count,
+        size_bytes: pack.size_bytes,
+        is_valid: pack.is_valid,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crv_core::repository::{Compression, PACK_TRAILER_SIZE};
+
+    #[test]
+    fn corrupted_bundle_is_reported_invalid() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let repo = Repository::new(temp_dir.path()).unwrap();
+        let record = repo
+            .write_chunk(b"layout chunk", Compression::None)
+            .unwrap();
+        repo.seal_all().unwrap();
+
+        let id = format!("shard-{:02x}/pack-000001", record.hash[0]);
+        let rsp = get_repository_layout(&repo).unwrap();
+        assert_eq!(rsp.bundles.len(), 1);
+        assert_eq!(rsp.bundles[0].id, id);
+        assert_eq!(rsp.bundles[0].chunk_count, 1);
+        assert!(rsp.bundles[0].is_valid);
+
+        let (dat_path, _) = repo.layout().pack_paths(record.hash[0], 1).unwrap();
+        let mut data = std::fs::read(&dat_path).unwrap();
+        let last_payload = data.len() - PACK_TRAILER_SIZE as usize - 1;
+        data[last_payload] ^= 0xFF;
+        std::fs::write(&dat_path, &data).unwrap();
+
+        let rsp = get_repository_layout(&repo).unwrap();
+        assert_eq!(rsp.bundles[0].id, id);
+        assert!(!rsp.bundles[0].is_valid);
+    }
+}
@@ -0,0 +1,127 @@
+use crate::database::dao::{Dao, read_dao};
+use crate::hive_server::repository_manager;
+use crate::logging::HiveLog;
+use crate::pb::{GetRepositoryStatsReq, GetRepositoryStatsRsp};
+use tonic::{Request, Response, Status};
+
+pub async fn handle_get_repository_stats(
+    log: HiveLog,
+    r: Request<GetRepositoryStatsReq>,
+) -> Result<Response<GetRepositoryStatsRsp>, Status> {
+    let _g = log.enter();
+    let _request = r.into_inner();
+
+    // 遍历所有 shard 目录统计 pack 文件大小，放到阻塞线程中执行
+    let repo = repository_manager()?;
+    let chunk_bytes = tokio::task::spawn_blocking(move || repo.disk_usage())
+        .await
+        .map_err(|e| Status::internal(format!("failed to compute repository disk usage: {e}")))?
+        .map_err(|e| Status::internal(format!("failed to compute repository disk usage: {e}")))?;
+    log.info(&format!("repository chunk bytes on disk: {chunk_bytes}"));
+
+    let rsp = get_repository_stats(read_dao().as_ref(), chunk_bytes).await?;
+    Ok(Response::new(rsp))
+}
+
+/// 汇总仓库的统计信息，`chunk_bytes` 为 chunk 仓库实际占用的磁盘空间。
+///
+/// `dedup_ratio` 为所有 revision 的文件大小之和与实际占用空间之比，越大说明去重与压缩节省的空间越多。
+pub(crate) async fn get_repository_stats(
+    dao: &dyn Dao,
+    chunk_bytes: u64,
+) -> Result<GetRepositoryStatsRsp, Status> {
+    let stats = dao.repository_stats().await.map_err(|e| {
+        Status::internal(format!(
+            "database error while collecting repository stats: {e}"
+        ))
+    })?;
+
+    let dedup_ratio = if chunk_bytes == 0 {
+        0.0
+    } else {
+        stats.total_revision_bytes as f64 / chunk_bytes as f64
+    };
+
+    Ok(GetRepositoryStatsRsp {
+        total_files: stats.total_files,
+        total_changelists: stats.total_changelists,
+        total_branches: stats.total_branches,
+        total_chunk_bytes: chunk_bytes as i64,
+        dedup_ratio,
+        oldest_cl_timestamp: stats.oldest_changelist_at.unwrap_or(0),
+        newest_cl_timestamp: stats.newest_changelist_at.unwrap_or(0),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::dao::{MockDao, NewFileRevisionInput};
+    use crv_core::metadata::{BranchDoc, BranchMetadata};
+
+    fn revision(depot_path: &str, revision: i64, size: i64) -> NewFileRevisionInput {
+        NewFileRevisionInput {
+            depot_path: depot_path.to_string(),
+            generation: 1,
+            revision,
+            binary_id: serde_json::json!([]),
+            size,
+            is_delete: false,
+            created_at: 0,
+            metadata: serde_json::json!({}),
+            file_metadata: serde_json::json!({}),
+        }
+    }
+
+    #[tokio::test]
+    async fn empty_repository_has_zero_stats() {
+        let rsp = get_repository_stats(&MockDao::default(), 0).await.unwrap();
+        assert_eq!(rsp, GetRepositoryStatsRsp::default());
+    }
+
+    #[tokio::test]
+    async fn stats_aggregate_changelists_files_and_branches() {
+        let dao = MockDao::default();
+        dao.commit_submit(
+            "alice",
+            "first",
+            100,
+            serde_json::json!({}),
+            vec![revision("//a/x.txt", 1, 300), revision("//a/y.txt", 1, 200)],
+        )
+        .await
+        .unwrap();
+        dao.commit_submit(
+            "bob",
+            "second",
+            200,
+            serde_json::json!({}),
+            vec![revision("//a/x.txt", 2, 500)],
+        )
+        .await
+        .unwrap();
+        dao.insert_branch(&BranchDoc {
+            id: "main".to_string(),
+            created_at: 0,
+            created_by: "alice".to_string(),
+            head_changelist_id: 2,
+            is_protected: false,
+            metadata: BranchMetadata {
+                description: String::new(),
+                owners: vec![],
+                description_regex: None,
+            },
+        })
+        .await
+        .unwrap();
+
+        let rsp = get_repository_stats(&dao, 250).await.unwrap();
+        assert_eq!(rsp.total_files, 2);
+        assert_eq!(rsp.total_changelists, 2);
+        assert_eq!(rsp.total_branches, 1);
+        assert_eq!(rsp.total_chunk_bytes, 250);
+        assert_eq!(rsp.dedup_ratio, 4.0);
+        assert_eq!(rsp.oldest_cl_timestamp, 100);
+        assert_eq!(rsp.newest_cl_timestamp, 200);
+    }
+}
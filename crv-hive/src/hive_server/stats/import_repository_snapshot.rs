@@ -0,0 +1,271 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use tokio::io::AsyncWriteExt;
+use tokio_stream::StreamExt;
+use tonic::{Request, Response, Status, Streaming};
+
+use crate::auth::{ADMIN_SCOPE, require_scope};
+use crate::database::dao;
+use crate::hive_server::repository_manager;
+use crate::hive_server::stats::export_repository_snapshot::{METADATA_ENTRY, REPOSITORY_DIR};
+use crate::logging::HiveLog;
+use crate::pb::{ImportRepositorySnapshotReq, ImportRepositorySnapshotRsp};
+use crv_core::repository::{PACK_DATA_SUFFIX, Repository};
+
+/// 从备份归档恢复 chunk 仓库与元数据库，可以重复导入同一个归档
+pub async fn handle_import_repository_snapshot(
+    log: HiveLog,
+    r: Request<Streaming<ImportRepositorySnapshotReq>>,
+) -> Result<Response<ImportRepositorySnapshotRsp>, Status> {
+    let user = require_scope(&r, ADMIN_SCOPE)?.clone();
+    let log = log.with_user(&user.username);
+    let _g = log.enter();
+
+    let repo = repository_manager()?;
+    // 解包目录放在仓库根目录下，与仓库位于同一文件系统；不以 shard 前缀命名，不会被当作 shard
+    let work_dir = repo
+        .layout()
+        .root()
+        .join(format!(".import-{}", uuid::Uuid::new_v4()));
+    let result = import_from_stream(repo, r.into_inner(), &work_dir).await;
+    if let Err(e) = tokio::fs::remove_dir_all(&work_dir).await {
+        log.warn(&format!("failed to remove import directory: {e}"));
+    }
+    let rsp = result?;
+    log.info(&format!(
+        "repository snapshot imported: bundles={}, chunks={}, rows={}",
+        rsp.bundles, rsp.chunks, rsp.rows
+    ));
+
+    Ok(Response::new(rsp))
+}
+
+async fn import_from_stream(
+    repo: &'static Repository,
+    mut stream: Streaming<ImportRepositorySnapshotReq>,
+    work_dir: &Path,
+) -> Result<ImportRepositorySnapshotRsp, Status> {
+    let archive_path = work_dir.join("snapshot.tar");
+    let extract_dir = work_dir.join("extracted");
+    tokio::fs::create_dir_all(&extract_dir)
+        .await
+        .map_err(|e| Status::internal(format!("failed to create import directory: {e}")))?;
+
+    let mut file = tokio::fs::File::create(&archive_path)
+        .await
+        .map_err(|e| Status::internal(format!("failed to create archive file: {e}")))?;
+    while let Some(message) = stream.next().await {
+        file.write_all(&message?.data)
+            .await
+            .map_err(|e| Status::internal(format!("failed to write archive file: {e}")))?;
+    }
+    file.flush()
+        .await
+        .map_err(|e| Status::internal(format!("failed to write archive file: {e}")))?;
+    drop(file);
+
+    // 解包与校验 pack 都是阻塞 IO，放到阻塞线程中执行
+    let imported = tokio::task::spawn_blocking(move || {
+        let archive = fs::File::open(&archive_path)
+            .map_err(|e| Status::internal(format!("failed to open archive file: {e}")))?;
+        import_snapshot_archive(repo, archive, &extract_dir)
+    })
+    .await
+    .map_err(|e| Status::internal(format!("failed to import repository snapshot: {e}")))??;
+
+    let rows = dao::import_metadata(&imported.metadata)
+        .await
+        .map_err(|e| Status::internal(format!("database error while importing metadata: {e}")))?;
+
+    Ok(ImportRepositorySnapshotRsp {
+        bundles: imported.bundles,
+        chunks: imported.chunks,
+        rows,
+    })
+}
+
+/// 归档中 pack 部分的导入结果，以及待导入数据库的元数据
+#[derive(Debug)]
+pub(crate) struct ImportedSnapshot {
+    pub bundles: u64,
+    pub chunks: u64,
+    pub metadata: serde_json::Value,
+}
+
+/// 将归档解包到 `work_dir`，校验所有 pack 后再把其中缺少的 chunk 写入仓库
+///
+/// 任意一个 pack 校验失败时不写入任何 chunk。已经存在的 chunk 会被跳过，因此重复导入是安全的。
+pub(crate) fn import_snapshot_archive<R: std::io::Read>(
+    repo: &Repository,
+    archive: R,
+    work_dir: &Path,
+) -> Result<ImportedSnapshot, Status> {
+    tar::Archive::new(archive)
+        .unpack(work_dir)
+        .map_err(|e| Status::invalid_argument(format!("invalid snapshot archive: {e}")))?;
+
+    let metadata = fs::read(work_dir.join(METADATA_ENTRY)).map_err(|e| {
+        Status::invalid_argument(format!("snapshot archive has no {METADATA_ENTRY}: {e}"))
+    })?;
+    let metadata: serde_json::Value = serde_json::from_slice(&metadata)
+        .map_err(|e| Status::invalid_argument(format!("invalid {METADATA_ENTRY}: {e}")))?;
+    if !metadata.is_object() {
+        return Err(Status::invalid_argument(format!(
+            "{METADATA_ENTRY} must be an object keyed by table name"
+        )));
+    }
+
+    let repository_dir = work_dir.join(REPOSITORY_DIR);
+    let packs = collect_pack_files(&repository_dir)
+        .map_err(|e| Status::invalid_argument(format!("invalid snapshot archive: {e}")))?;
+    for path in &packs {
+        Repository::verify_pack_file(path).map_err(|e| {
+            Status::data_loss(format!(
+                "bundle {} is corrupted: {e}",
+                path.strip_prefix(&repository_dir).unwrap_or(path).display()
+            ))
+        })?;
+    }
+
+    let mut chunks = 0;
+    for path in &packs {
+        chunks += repo
+            .import_pack_file(path)
+            .map_err(|e| Status::internal(format!("failed to import bundle: {e}")))?;
+    }
+
+    Ok(ImportedSnapshot {
+        bundles: packs.len() as u64,
+        chunks,
+        metadata,
+    })
+}
+
+/// 列出 `repository/shard-xx/` 下的所有 pack 数据文件；已转移到对象存储的 pack 没有数据文件，不会被导入
+fn collect_pack_files(repository_dir: &Path) -> std::io::Result<Vec<PathBuf>> {
+    let mut packs = Vec::new();
+    if !repository_dir.exists() {
+        return Ok(packs);
+    }
+    for shard in fs::read_dir(repository_dir)? {
+        let shard = shard?;
+        if !shard.file_type()?.is_dir() {
+            continue;
+        }
+        for entry in fs::read_dir(shard.path())? {
+            let path = entry?.path();
+            if path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.ends_with(PACK_DATA_SUFFIX))
+            {
+                packs.push(path);
+            }
+        }
+    }
+    packs.sort();
+    Ok(packs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hive_server::stats::export_repository_snapshot::write_snapshot_archive;
+    use crv_core::repository::{Compression, RepositoryLayout};
+
+    fn backup_with_three_files() -> (tempfile::TempDir, Vec<[u8; 32]>, Vec<u8>) {
+        let source_dir = tempfile::tempdir().unwrap();
+        let source = Repository::new(source_dir.path()).unwrap();
+        let hashes = ["a.txt", "b.txt", "c.txt"]
+            .iter()
+            .map(|name| {
+                let content = format!("content of {name}");
+                source
+                    .write_chunk(content.as_bytes(), Compression::None)
+                    .unwrap()
+                    .hash
+            })
+            .collect::<Vec<_>>();
+        // 只封存一部分，活跃 pack 也需要能被导入
+        source.seal_shard(hashes[0][0]).unwrap();
+
+        let metadata = serde_json::json!({ "users": [] });
+        let mut archive = Vec::new();
+        write_snapshot_archive(&source, &metadata, &mut archive).unwrap();
+        (source_dir, hashes, archive)
+    }
+
+    #[test]
+    fn restored_repository_has_every_chunk() {
+        let (_source_dir, hashes, archive) = backup_with_three_files();
+
+        let target_dir = tempfile::tempdir().unwrap();
+        let target = Repository::new(target_dir.path().join("repo")).unwrap();
+        let work_dir = target_dir.path().join("work");
+        let imported = import_snapshot_archive(&target, archive.as_slice(), &work_dir).unwrap();
+        assert_eq!(imported.chunks, 3);
+        assert_eq!(imported.metadata, serde_json::json!({ "users": [] }));
+
+        for (hash, name) in hashes.iter().zip(["a.txt", "b.txt", "c.txt"]) {
+            assert!(target.locate_chunk(hash).unwrap().is_some());
+            assert_eq!(
+                target.read_chunk(hash).unwrap(),
+                format!("content of {name}").into_bytes()
+            );
+        }
+
+        // 重复导入不会写入重复的 chunk
+        let work_dir = target_dir.path().join("work-again");
+        let imported = import_snapshot_archive(&target, archive.as_slice(), &work_dir).unwrap();
+        assert_eq!(imported.chunks, 0);
+        assert_eq!(
+            target
+                .inspect_packs()
+                .unwrap()
+                .iter()
+                .map(|p| p.chunk_count)
+                .sum::<u64>(),
+            3
+        );
+    }
+
+    #[test]
+    fn corrupted_bundle_is_rejected_before_import() {
+        let (_source_dir, hashes, archive) = backup_with_three_files();
+
+        // 修改已封存 pack 的 payload，再重新打包
+        let extract_dir = tempfile::tempdir().unwrap();
+        tar::Archive::new(archive.as_slice())
+            .unpack(extract_dir.path())
+            .unwrap();
+        let dat_path = extract_dir
+            .path()
+            .join(REPOSITORY_DIR)
+            .join(RepositoryLayout::shard_dir_name(hashes[0][0]))
+            .join(format!(
+                "{}{PACK_DATA_SUFFIX}",
+                RepositoryLayout::pack_base_name(1)
+            ));
+        let mut data = fs::read(&dat_path).unwrap();
+        let last_payload = data.len() - crv_core::repository::PACK_TRAILER_SIZE as usize - 1;
+        data[last_payload] ^= 0xFF;
+        fs::write(&dat_path, &data).unwrap();
+        let mut builder = tar::Builder::new(Vec::new());
+        builder.append_dir_all(".", extract_dir.path()).unwrap();
+        let corrupted = builder.into_inner().unwrap();
+
+        let target_dir = tempfile::tempdir().unwrap();
+        let target = Repository::new(target_dir.path().join("repo")).unwrap();
+        let err = import_snapshot_archive(
+            &target,
+            corrupted.as_slice(),
+            &target_dir.path().join("work"),
+        )
+        .unwrap_err();
+        assert_eq!(err.code(), tonic::Code::DataLoss);
+        for hash in &hashes {
+            assert!(target.locate_chunk(hash).unwrap().is_none());
+        }
+    }
+}
@@ -0,0 +1,5 @@
+pub mod export_repository_snapshot;
+pub mod get_repository_layout;
+pub mod get_repository_stats;
+pub mod import_repository_snapshot;
+pub mod rebuild_index;
@@ -0,0 +1,31 @@
+use crate::auth::{ADMIN_SCOPE, require_scope};
+use crate::hive_server::repository_manager;
+use crate::logging::HiveLog;
+use crate::pb::{RebuildIndexReq, RebuildIndexRsp};
+use tonic::{Request, Response, Status};
+
+/// 从 pack 数据文件重建所有非活跃 pack 的索引，用于索引文件丢失或损坏后的恢复
+pub async fn handle_rebuild_index(
+    log: HiveLog,
+    r: Request<RebuildIndexReq>,
+) -> Result<Response<RebuildIndexRsp>, Status> {
+    let user = require_scope(&r, ADMIN_SCOPE)?.clone();
+    let log = log.with_user(&user.username);
+    let _g = log.enter();
+
+    // 需要扫描所有 pack 文件，放到阻塞线程中执行
+    let repo = repository_manager()?;
+    let report = tokio::task::spawn_blocking(move || repo.rebuild_index_from_packs())
+        .await
+        .map_err(|e| Status::internal(format!("failed to rebuild repository index: {e}")))?
+        .map_err(|e| Status::internal(format!("failed to rebuild repository index: {e}")))?;
+    log.info(&format!(
+        "repository index rebuilt: packs={}, chunks={}",
+        report.packs, report.chunks
+    ));
+
+    Ok(Response::new(RebuildIndexRsp {
+        packs: report.packs,
+        chunks: report.chunks,
+    }))
+}
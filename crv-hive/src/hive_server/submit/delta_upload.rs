@@ -0,0 +1,195 @@
+use crate::caching::ChunkCacheError;
+use crate::hive_server::repository_manager;
+use crate::hive_server::submit::service::{SubmitService, UploadFileChunkResult};
+use crate::hive_server::submit::{cache_service, submit_service};
+use crate::logging::HiveLog;
+use crate::pb::{DeltaUploadReq, DeltaUploadRsp};
+use crv_core::repository::{RepositoryError, apply_delta, blake3_hex_to_hash, compute_chunk_hash};
+use tonic::{Request, Response, Status};
+
+/// 差量重建出的 chunk 的大小上限，避免恶意的差量数据占用大量内存
+const MAX_TARGET_CHUNK_SIZE: usize = 64 * 1024 * 1024;
+
+pub async fn handle_delta_upload(
+    log: HiveLog,
+    r: Request<DeltaUploadReq>,
+) -> Result<Response<DeltaUploadRsp>, Status> {
+    let _g = log.enter();
+
+    let request = r.into_inner();
+    log.info(&format!(
+        "delta_upload received: base={}, target={}, delta_size={}",
+        request.base_chunk_hash,
+        request.target_chunk_hash,
+        request.delta_data.len()
+    ));
+
+    let rsp = delta_upload(submit_service(), request)?;
+    log.info(&format!(
+        "delta_upload finished: chunk_hash={}, already_exists={}",
+        rsp.chunk_hash, rsp.already_exists
+    ));
+    Ok(Response::new(rsp))
+}
+
+/// 基于 base chunk 应用差量数据，校验重建出的 chunk 的哈希后写入缓存，
+/// 效果与通过 UploadFileChunk 完整上传该 chunk 相同
+pub(crate) fn delta_upload(
+    service: &SubmitService,
+    request: DeltaUploadReq,
+) -> Result<DeltaUploadRsp, Status> {
+    let ticket = uuid::Uuid::parse_str(&request.ticket)
+        .map_err(|e| Status::invalid_argument(format!("invalid ticket format: {}", e)))?;
+
+    let cache = cache_service();
+    if let Ok(true) = cache.has_chunk(&request.target_chunk_hash) {
+        return Ok(DeltaUploadRsp {
+            chunk_hash: request.target_chunk_hash,
+            already_exists: true,
+        });
+    }
+
+    let base = read_base_chunk(&request.base_chunk_hash)?;
+    let target = apply_delta(&base, &request.delta_data, MAX_TARGET_CHUNK_SIZE)
+        .map_err(|e| Status::invalid_argument(format!("apply delta failed: {}", e)))?;
+
+    let actual_hash = hex::encode(compute_chunk_hash(&target));
+    if actual_hash != request.target_chunk_hash.trim().to_lowercase() {
+        return Err(Status::invalid_argument(format!(
+            "target chunk hash mismatch: expected {}, actual {}",
+            request.target_chunk_hash, actual_hash
+        )));
+    }
+
+    match service.upload_file_chunk(
+        &ticket,
+        &request.target_chunk_hash,
+        0,
+        target.len() as i64,
+        &target,
+    ) {
+        Ok(UploadFileChunkResult::FileUploadFinished) => Ok(DeltaUploadRsp {
+            chunk_hash: request.target_chunk_hash,
+            already_exists: false,
+        }),
+        Ok(UploadFileChunkResult::FileAppended) => Err(Status::internal(format!(
+            "chunk {} is incomplete after applying delta",
+            request.target_chunk_hash
+        ))),
+        Err(e) => Err(Status::failed_precondition(e.message)),
+    }
+}
+
+/// 优先从上传缓存中读取 base chunk，缓存中不存在时从仓库中读取
+fn read_base_chunk(chunk_hash: &str) -> Result<Vec<u8>, Status> {
+    match cache_service().read_chunk(chunk_hash) {
+        Ok(data) => return Ok(data),
+        Err(ChunkCacheError::InvalidChunkHash(msg)) => {
+            return Err(Status::invalid_argument(format!(
+                "invalid base chunk hash: {}",
+                msg
+            )));
+        }
+        Err(_) => {}
+    }
+
+    let hash = blake3_hex_to_hash(chunk_hash).ok_or_else(|| {
+        Status::invalid_argument(format!("invalid base chunk hash: {}", chunk_hash))
+    })?;
+    match repository_manager()?.read_chunk(&hash) {
+        Ok(data) => Ok(data),
+        Err(RepositoryError::ChunkNotFound { .. }) => Err(Status::not_found(format!(
+            "base chunk not found: {}",
+            chunk_hash
+        ))),
+        Err(e) => Err(Status::internal(format!("read base chunk failed: {}", e))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crv_core::repository::compute_delta;
+    use std::sync::OnceLock;
+
+    static TEST_CACHE_DIR: OnceLock<tempfile::TempDir> = OnceLock::new();
+
+    fn init_test_cache() {
+        TEST_CACHE_DIR.get_or_init(|| {
+            let dir = tempfile::tempdir().expect("create temp dir");
+            let cache =
+                crate::caching::ChunkCache::new(dir.path().join("cache")).expect("create cache");
+            let _ = crate::hive_server::submit::CACHE_SERVICE.set(cache);
+            dir
+        });
+    }
+
+    fn chunk_hash(data: &[u8]) -> String {
+        hex::encode(compute_chunk_hash(data))
+    }
+
+    fn pseudo_random(len: usize) -> Vec<u8> {
+        let mut state = 0x2545_f491_4f6c_dd1du64;
+        (0..len)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state as u8
+            })
+            .collect()
+    }
+
+    #[test]
+    fn one_byte_change_uploads_small_delta() {
+        init_test_cache();
+        let service = SubmitService::new();
+        let ticket = uuid::Uuid::new_v4();
+        service.insert_test_context(ticket);
+
+        let base = pseudo_random(1024 * 1024);
+        let base_hash = chunk_hash(&base);
+        cache_service()
+            .append_chunk_part(&base_hash, 0, &base)
+            .unwrap();
+
+        let mut target = base.clone();
+        target[300_000] = target[300_000].wrapping_add(1);
+        let target_hash = chunk_hash(&target);
+        let delta = compute_delta(&base, &target);
+        assert!(
+            delta.len() * 1000 < target.len(),
+            "delta size {}",
+            delta.len()
+        );
+
+        let req = DeltaUploadReq {
+            ticket: ticket.to_string(),
+            base_chunk_hash: base_hash.clone(),
+            delta_data: delta.clone(),
+            target_chunk_hash: target_hash.clone(),
+        };
+        let rsp = delta_upload(&service, req.clone()).unwrap();
+        assert_eq!(rsp.chunk_hash, target_hash);
+        assert!(!rsp.already_exists);
+        assert_eq!(cache_service().read_chunk(&target_hash).unwrap(), target);
+
+        // 再次上传时 target chunk 已经存在
+        assert!(delta_upload(&service, req).unwrap().already_exists);
+
+        // 声明的哈希与重建结果不一致时拒绝
+        let mut other = target.clone();
+        other[0] ^= 0xff;
+        let status = delta_upload(
+            &service,
+            DeltaUploadReq {
+                ticket: ticket.to_string(),
+                base_chunk_hash: base_hash,
+                delta_data: delta,
+                target_chunk_hash: chunk_hash(&other),
+            },
+        )
+        .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+    }
+}
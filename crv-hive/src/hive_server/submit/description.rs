@@ -0,0 +1,153 @@
+use crate::config::holder::get_or_init_config;
+use crate::database::dao::Dao;
+use regex::Regex;
+use std::sync::OnceLock;
+use tonic::Status;
+
+/// 全局配置的提交描述正则，启动时编译一次；未配置时保持为空
+static DESCRIPTION_REGEX: OnceLock<Regex> = OnceLock::new();
+
+/// 编译配置中的 `changelist_description_regex`，正则无效时返回错误，hive 拒绝启动。
+pub fn init_description_regex() -> Result<(), regex::Error> {
+    let Some(pattern) = get_or_init_config()
+        .changelist_description_regex
+        .as_deref()
+        .filter(|p| !p.is_empty())
+    else {
+        return Ok(());
+    };
+    let regex = Regex::new(pattern)?;
+    let _ = DESCRIPTION_REGEX.set(regex);
+    Ok(())
+}
+
+/// 检查提交描述是否符合目标分支要求的格式，不符合时返回 `invalid_argument`。
+pub(crate) async fn check_description(
+    dao: &dyn Dao,
+    branch_id: &str,
+    description: &str,
+) -> Result<(), Status> {
+    check_description_with(dao, DESCRIPTION_REGEX.get(), branch_id, description).await
+}
+
+/// 分支设置了 `description_regex` 时使用分支的正则，否则使用全局正则；都没有时不检查。
+async fn check_description_with(
+    dao: &dyn Dao,
+    global: Option<&Regex>,
+    branch_id: &str,
+    description: &str,
+) -> Result<(), Status> {
+    let branch_regex = if branch_id.is_empty() {
+        None
+    } else {
+        dao.find_branch_by_id(branch_id)
+            .await
+            .map_err(|e| Status::internal(format!("database error while finding branch: {e}")))?
+            .ok_or_else(|| Status::not_found(format!("branch {branch_id} not found")))?
+            .metadata
+            .description_regex
+    };
+
+    match branch_regex {
+        Some(pattern) => {
+            let regex = Regex::new(&pattern).map_err(|e| {
+                Status::internal(format!(
+                    "invalid description regex of branch {branch_id}: {e}"
+                ))
+            })?;
+            require_match(&regex, description)
+        }
+        None => match global {
+            Some(regex) => require_match(regex, description),
+            None => Ok(()),
+        },
+    }
+}
+
+fn require_match(regex: &Regex, description: &str) -> Result<(), Status> {
+    if regex.is_match(description) {
+        Ok(())
+    } else {
+        Err(Status::invalid_argument(format!(
+            "changelist description does not match the required format `{}`",
+            regex.as_str()
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::dao::MockDao;
+    use crv_core::metadata::{BranchDoc, BranchMetadata};
+
+    async fn insert_branch(dao: &MockDao, id: &str, description_regex: Option<&str>) {
+        dao.insert_branch(&BranchDoc {
+            id: id.to_string(),
+            created_at: 0,
+            created_by: "alice".to_string(),
+            head_changelist_id: 0,
+            is_protected: false,
+            metadata: BranchMetadata {
+                description: id.to_string(),
+                owners: vec![],
+                description_regex: description_regex.map(str::to_string),
+            },
+        })
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn description_is_checked_against_global_regex() {
+        let dao = MockDao::default();
+        let global = Regex::new(r"^\[[A-Z]+-\d+\] \S").unwrap();
+
+        check_description_with(&dao, Some(&global), "", "[TICKET-123] fix crash")
+            .await
+            .unwrap();
+
+        let err = check_description_with(&dao, Some(&global), "", "fix crash")
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), tonic::Code::InvalidArgument);
+
+        // 旧客户端不填描述时同样被拒绝
+        let err = check_description_with(&dao, Some(&global), "", "")
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), tonic::Code::InvalidArgument);
+
+        // 未配置正则时不检查
+        check_description_with(&dao, None, "", "").await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn branch_regex_overrides_global_regex() {
+        let dao = MockDao::default();
+        insert_branch(&dao, "release", Some(r"^RELEASE: ")).await;
+        insert_branch(&dao, "dev", None).await;
+        let global = Regex::new(r"^\[[A-Z]+-\d+\] ").unwrap();
+
+        check_description_with(&dao, Some(&global), "release", "RELEASE: 1.2.0")
+            .await
+            .unwrap();
+        let err = check_description_with(&dao, Some(&global), "release", "[TICKET-1] fix")
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), tonic::Code::InvalidArgument);
+
+        // 没有覆盖的分支使用全局正则
+        check_description_with(&dao, Some(&global), "dev", "[TICKET-1] fix")
+            .await
+            .unwrap();
+        check_description_with(&dao, Some(&global), "dev", "RELEASE: 1.2.0")
+            .await
+            .unwrap_err();
+
+        let err = check_description_with(&dao, Some(&global), "missing", "[TICKET-1] fix")
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), tonic::Code::NotFound);
+    }
+}
@@ -1,24 +1,28 @@
-use crate::auth::require_user;
 use crate::common::depot_path::DepotPath;
 use crate::hive_server::submit::service::LockedFile;
-use crate::hive_server::submit::submit_service;
+use crate::hive_server::submit::{submit_service, submitting_user};
 use crate::logging::HiveLog;
-use crate::pb::{FileUnableToLock, LaunchSubmitReq, LaunchSubmitRsp};
+use crate::pb::{FileUnableToLock, LaunchSubmitReq, LaunchSubmitRsp, LockMode};
 use tonic::{Request, Response, Status};
 
 pub async fn handle_launch_submit(
     log: HiveLog,
     r: Request<LaunchSubmitReq>,
 ) -> Result<Response<LaunchSubmitRsp>, Status> {
-    // let user = require_user(&r)?;
-    let submitting_by = "admin".to_string();
+    let submitting_by = submitting_user(&r);
     let log = log.with_user(&submitting_by);
     let _g = log.enter();
 
     let request = r.into_inner();
     log.info(&format!(
-        "launch_submit received: files={}",
-        request.files.len()
+        "launch_submit received: branch={}, files={}, read_locks={}",
+        request.branch_id,
+        request.files.len(),
+        request
+            .files
+            .iter()
+            .filter(|f| f.mode() == LockMode::Read)
+            .count()
     ));
 
     let locked_files: Vec<LockedFile> = request
@@ -33,12 +37,17 @@ pub async fn handle_launch_submit(
                 path,
                 locked_generation: file.expected_file_generation,
                 locked_revision: file.expected_file_revision,
+                mode: match file.mode() {
+                    LockMode::Read => crv_core::tree::depot_tree::LockMode::Read,
+                    LockMode::Write => crv_core::tree::depot_tree::LockMode::Write,
+                },
             })
         })
         .collect::<Result<Vec<_>, Status>>()?;
 
     let result = submit_service()
         .launch_submit(
+            request.branch_id.trim(),
             &locked_files,
             submitting_by,
             // 目前默认允许提交 2 小时
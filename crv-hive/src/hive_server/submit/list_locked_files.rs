@@ -0,0 +1,112 @@
+use crate::hive_server::submit::service::SubmitService;
+use crate::hive_server::submit::submit_service;
+use crate::logging::HiveLog;
+use crate::pb::{ListLockedFilesReq, ListLockedFilesRsp, LockMode, LockedFile};
+use crv_core::metadata::derive_file_id_from_path;
+use crv_core::tree::depot_tree::{self, DepotTree};
+use tonic::{Request, Response, Status};
+
+/// 提交尚未区分分支，所有的锁都在默认分支上
+const DEFAULT_BRANCH: &str = "";
+
+pub async fn handle_list_locked_files(
+    log: HiveLog,
+    r: Request<ListLockedFilesReq>,
+) -> Result<Response<ListLockedFilesRsp>, Status> {
+    let _g = log.enter();
+
+    let request = r.into_inner();
+    log.info(&format!(
+        "list_locked_files received: branch={}",
+        request.branch_id
+    ));
+
+    let rsp = list_locked_files(submit_service(), request.branch_id.trim());
+    log.info(&format!("locked files: {}", rsp.locked_files.len()));
+    Ok(Response::new(rsp))
+}
+
+/// 列出指定分支上被锁定的文件，按路径排序；同一文件上的多个读锁合并为一条
+pub(crate) fn list_locked_files(service: &SubmitService, branch_id: &str) -> ListLockedFilesRsp {
+    if branch_id != DEFAULT_BRANCH {
+        return ListLockedFilesRsp::default();
+    }
+
+    // 以持有锁的用户作为 ticket 重建锁状态，便于合并同一文件上的多个读锁
+    let mut tree = DepotTree::new();
+    for (path, locked_by, mode) in service.list_locked_files() {
+        let path = path.to_string();
+        let file_id = crv_core::path::basic::DepotPath::parse(&path)
+            .map(|p| derive_file_id_from_path(&p))
+            .unwrap_or_else(|_| path.clone());
+        tree.try_lock_files_with_mode(DEFAULT_BRANCH, [(file_id, path)], mode, &locked_by);
+    }
+
+    ListLockedFilesRsp {
+        locked_files: tree
+            .list_locked_files(DEFAULT_BRANCH)
+            .into_iter()
+            .map(|f| LockedFile {
+                locked_by: f.holders.join(", "),
+                mode: match f.mode {
+                    depot_tree::LockMode::Read => LockMode::Read,
+                    depot_tree::LockMode::Write => LockMode::Write,
+                } as i32,
+                file_id: f.file_id,
+                path: f.path,
+            })
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lists_files_locked_by_pending_submits() {
+        let service = SubmitService::new();
+        service.insert_test_locks(
+            uuid::Uuid::new_v4(),
+            "alice",
+            &["//src/b.cpp", "//src/a.cpp"],
+        );
+        service.insert_test_locks(uuid::Uuid::new_v4(), "bob", &["//assets/c.png"]);
+
+        let rsp = list_locked_files(&service, "");
+        let files: Vec<(&str, &str)> = rsp
+            .locked_files
+            .iter()
+            .map(|f| (f.path.as_str(), f.locked_by.as_str()))
+            .collect();
+        assert_eq!(
+            files,
+            vec![
+                ("//assets/c.png", "bob"),
+                ("//src/a.cpp", "alice"),
+                ("//src/b.cpp", "alice"),
+            ]
+        );
+        assert!(rsp.locked_files.iter().all(|f| !f.file_id.is_empty()));
+
+        assert!(list_locked_files(&service, "main").locked_files.is_empty());
+    }
+
+    #[test]
+    fn merges_read_locks_on_the_same_file() {
+        let service = SubmitService::new();
+        for user in ["bob", "alice"] {
+            service.insert_test_locks_with_mode(
+                uuid::Uuid::new_v4(),
+                user,
+                &["//src/shared.h"],
+                depot_tree::LockMode::Read,
+            );
+        }
+
+        let rsp = list_locked_files(&service, "");
+        assert_eq!(rsp.locked_files.len(), 1);
+        assert_eq!(rsp.locked_files[0].locked_by, "alice, bob");
+        assert_eq!(rsp.locked_files[0].mode(), LockMode::Read);
+    }
+}
@@ -1,5 +1,10 @@
-use std::sync::OnceLock;
+use std::ops::Deref;
+use std::sync::{Arc, LazyLock, OnceLock};
 
+use dashmap::DashMap;
+use tonic::Request;
+
+use crate::auth::require_user;
 use crate::{caching::ChunkCache, hive_server::submit::service::SubmitService};
 
 pub static SUBMIT_SERVICE: OnceLock<SubmitService> = OnceLock::new();
@@ -12,6 +17,56 @@ pub fn submit_service() -> &'static SubmitService {
     SUBMIT_SERVICE.get_or_init(SubmitService::new)
 }
 
+/// 全局提交锁：普通提交持有读锁，再由 [`branch_submit_lock`] 按分支串行化；
+/// 压缩、合并、cherry-pick 与恢复快照等跨分支的操作持有写锁，期间没有任何提交写入。
+pub static SUBMIT_LOCK: tokio::sync::RwLock<()> = tokio::sync::RwLock::const_new(());
+
+static BRANCH_SUBMIT_LOCKS: LazyLock<DashMap<String, Arc<tokio::sync::Mutex<()>>>> =
+    LazyLock::new(DashMap::new);
+
+/// 分支的提交锁：同一分支上的提交串行执行，不同分支上的提交可以并发。
+///
+/// 调用方需要先持有 `SUBMIT_LOCK` 的读锁，避免与跨分支的操作交错。
+pub fn branch_submit_lock(branch_id: &str) -> BranchSubmitLock {
+    let lock = BRANCH_SUBMIT_LOCKS
+        .entry(branch_id.to_string())
+        .or_default()
+        .clone();
+    BranchSubmitLock {
+        branch_id: branch_id.to_string(),
+        lock: Some(lock),
+    }
+}
+
+/// [`branch_submit_lock`] 返回的分支锁句柄，最后一个句柄 drop 时从全局表中移除该分支，
+/// 避免表随分支数量无限增长
+pub struct BranchSubmitLock {
+    branch_id: String,
+    lock: Option<Arc<tokio::sync::Mutex<()>>>,
+}
+
+impl Deref for BranchSubmitLock {
+    type Target = tokio::sync::Mutex<()>;
+
+    fn deref(&self) -> &Self::Target {
+        self.lock.as_ref().expect("lock is only taken on drop")
+    }
+}
+
+impl Drop for BranchSubmitLock {
+    fn drop(&mut self) {
+        drop(self.lock.take());
+        // 只剩表中的引用时没有其他句柄在等待或持有该锁；remove_if 与 entry 持有同一分片的写锁，
+        // 不会移除其他调用方刚取出的锁
+        BRANCH_SUBMIT_LOCKS.remove_if(&self.branch_id, |_, lock| Arc::strong_count(lock) == 1);
+    }
+}
+
+#[cfg(test)]
+pub(crate) fn branch_submit_lock_registered(branch_id: &str) -> bool {
+    BRANCH_SUBMIT_LOCKS.contains_key(branch_id)
+}
+
 pub static CACHE_SERVICE: OnceLock<ChunkCache> = OnceLock::new();
 
 pub fn cache_service() -> &'static ChunkCache {
@@ -20,7 +75,20 @@ pub fn cache_service() -> &'static ChunkCache {
     })
 }
 
+/// 提交者的用户名。
+///
+/// 已登录时为当前用户；提交流程尚未强制鉴权，未登录的请求仍记为 admin。
+pub(crate) fn submitting_user<T>(r: &Request<T>) -> String {
+    require_user(r)
+        .map(|user| user.username.clone())
+        .unwrap_or_else(|_| "admin".to_string())
+}
+
+pub mod delta_upload;
+pub mod description;
 pub mod launch_submit;
-pub mod submit;
+pub mod list_locked_files;
 pub mod service;
+pub mod submit;
+pub mod submit_context;
 pub mod upload_file_chunk;
\ No newline at end of file
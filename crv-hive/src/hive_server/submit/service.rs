@@ -3,11 +3,22 @@ use std::{
     sync::{Arc, RwLock},
 };
 
+use crate::caching::ChunkCacheError;
 use crate::common::depot_path::DepotPath;
-use crate::hive_server::submit::cache_service;
+use crate::config::holder::get_or_init_config;
+use crate::database::dao::SubmitContextStatus;
 use crate::hive_server::repository_manager;
-use crate::caching::ChunkCacheError;
-use crv_core::repository::{Compression, RepositoryError};
+use crate::hive_server::submit::cache_service;
+use crv_core::metadata::CompressionType;
+use crv_core::repository::{Repository, RepositoryError, blake3_hash_to_hex, blake3_hex_to_hash};
+use crv_core::tree::depot_tree::{LockEntry, LockMode};
+use serde::{Deserialize, Serialize};
+
+/// 提交去重记录的保留时长，超过后相同 request_id 的提交会被重新执行
+const SUBMISSION_CACHE_TTL: chrono::Duration = chrono::Duration::hours(24);
+
+/// 版本号被其他分支的并发提交占用时，重新编号并落库的最多次数
+const MAX_COMMIT_ATTEMPTS: u32 = 3;
 
 #[derive(Clone, Debug)]
 pub struct LockedFile {
@@ -17,6 +28,8 @@ pub struct LockedFile {
     pub locked_generation: Option<i64>,
     /// locked file revision, when file not exists, this should be None
     pub locked_revision: Option<i64>,
+    /// lock mode, read locks only block other submits and can not be submitted
+    pub mode: LockMode,
 }
 
 #[derive(Clone, Debug)]
@@ -43,8 +56,8 @@ pub struct SubmitContext {
 }
 
 pub struct SubmitService {
-    /// locked files's paths
-    locked_paths: RwLock<HashMap<DepotPath, uuid::Uuid>>,
+    /// locked files's paths, lock entries are held by ticket
+    locked_paths: RwLock<HashMap<DepotPath, LockEntry>>,
     /// contexts of submitting
     contexts: RwLock<HashMap<uuid::Uuid, Arc<SubmitContext>>>,
 }
@@ -59,7 +72,7 @@ pub struct LaunchSubmitFailure {
     pub file_unable_to_lock: Vec<LockedFile>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct FileRevision {
     pub path: String,
     pub generation: i64,
@@ -67,6 +80,19 @@ pub struct FileRevision {
     pub binary_id: Vec<String>,
     pub size: i64,
     pub revision_created_at: i64,
+    /// 整个文件内容的 Blake3 哈希，删除时为空
+    pub content_hash: String,
+    /// 内容的压缩方式，提交去重缓存中的旧记录没有该字段
+    #[serde(default)]
+    pub compression_type: CompressionType,
+}
+
+/// 由移动/重命名产生的文件所记录的来源版本
+#[derive(Debug, Clone)]
+pub struct RenameSource {
+    pub from_path: String,
+    pub from_generation: i64,
+    pub from_revision: i64,
 }
 
 #[derive(Debug)]
@@ -76,6 +102,8 @@ pub struct SubmitSuccess {
 
     pub latest_revisions: Vec<FileRevision>,
     pub message: String,
+    /// 是否为按 request_id 去重后重放的首次提交结果
+    pub replayed: bool,
 }
 
 #[derive(Debug)]
@@ -98,12 +126,12 @@ pub struct SubmitFailure {
 #[derive(Debug)]
 pub enum UploadFileChunkResult {
     FileUploadFinished,
-    FileAppended
+    FileAppended,
 }
 
 #[derive(Debug)]
 pub struct UploadFileChunkError {
-    pub message: String
+    pub message: String,
 }
 
 impl SubmitService {
@@ -134,11 +162,199 @@ impl SubmitService {
         contexts.insert(ticket, ctx);
     }
 
-    fn unlock_context(&self, ticket: &uuid::Uuid) {
+    /// 仅用于单元测试：注入一个锁定了指定路径的 context。
+    #[cfg(test)]
+    pub(crate) fn insert_test_locks(
+        &self,
+        ticket: uuid::Uuid,
+        submitting_by: &str,
+        paths: &[&str],
+    ) {
+        self.insert_test_locks_with_mode(ticket, submitting_by, paths, LockMode::Write);
+    }
+
+    /// 仅用于单元测试：注入一个以指定模式锁定了路径的 context，不检查锁冲突。
+    #[cfg(test)]
+    pub(crate) fn insert_test_locks_with_mode(
+        &self,
+        ticket: uuid::Uuid,
+        submitting_by: &str,
+        paths: &[&str],
+        mode: LockMode,
+    ) {
+        let deadline = chrono::Utc::now() + chrono::Duration::minutes(10);
+        let files: Vec<LockedFile> = paths
+            .iter()
+            .map(|p| LockedFile {
+                path: DepotPath::parse(p).expect("valid depot path"),
+                locked_generation: None,
+                locked_revision: None,
+                mode,
+            })
+            .collect();
+
+        let mut locked = self
+            .locked_paths
+            .write()
+            .expect("submit service locked_paths poisoned");
+        for f in &files {
+            lock_entry(&mut locked, &f.path).grant(mode, &ticket.to_string());
+        }
+
+        let ctx = Arc::new(SubmitContext {
+            ticket,
+            submitting_by: submitting_by.to_string(),
+            timeout_deadline: deadline,
+            files,
+            chunks_uploaded: RwLock::new(Vec::new()),
+            chunks_in_progress: RwLock::new(HashSet::new()),
+        });
+        self.contexts
+            .write()
+            .expect("submit service contexts poisoned")
+            .insert(ticket, ctx);
+    }
+
+    /// 从数据库恢复尚未过期的 ticket 及其文件锁，返回恢复的 ticket 数量。
+    ///
+    /// hive 启动时调用：内存中的锁表只是数据库的写穿缓存，重启后需要据此重建，
+    /// 否则进行中的提交会丢失 ticket，而其他用户也看不到这些文件仍被锁定。
+    pub async fn restore(&self) -> crate::database::dao::DaoResult<usize> {
+        let now = chrono::Utc::now();
+        let records = crate::database::dao::list_submit_tickets(now.timestamp()).await?;
+
+        let mut locked = self
+            .locked_paths
+            .write()
+            .expect("submit service locked_paths poisoned");
+        let mut contexts = self
+            .contexts
+            .write()
+            .expect("submit service contexts poisoned");
+        let mut restored = 0;
+        for record in records {
+            let Ok(ticket) = uuid::Uuid::parse_str(&record.ticket) else {
+                continue;
+            };
+            let files: Vec<LockedFile> = record
+                .files
+                .iter()
+                .filter_map(|f| {
+                    Some(LockedFile {
+                        path: DepotPath::parse(&f.depot_path).ok()?,
+                        locked_generation: f.locked_generation,
+                        locked_revision: f.locked_revision,
+                        mode: f.mode,
+                    })
+                })
+                .collect();
+            // 数据库中的锁在写入前已经过冲突检查，这里直接授予
+            for f in &files {
+                lock_entry(&mut locked, &f.path).grant(f.mode, &record.ticket);
+            }
+            let timeout_deadline =
+                chrono::DateTime::from_timestamp(record.expires_at, 0).unwrap_or(now);
+            contexts.insert(
+                ticket,
+                Arc::new(SubmitContext {
+                    ticket,
+                    submitting_by: record.submitting_by,
+                    timeout_deadline,
+                    files,
+                    chunks_uploaded: RwLock::new(Vec::new()),
+                    chunks_in_progress: RwLock::new(HashSet::new()),
+                }),
+            );
+            restored += 1;
+        }
+        Ok(restored)
+    }
+
+    /// 列出当前被锁定的文件、持有锁的用户及锁模式，会先清理超时的 ticket。
+    ///
+    /// 同一文件上的多个读锁会各自列出一条。
+    pub fn list_locked_files(&self) -> Vec<(DepotPath, String, LockMode)> {
+        self.expire_tickets();
+
+        let locked = self
+            .locked_paths
+            .read()
+            .expect("submit service locked_paths poisoned");
+        let contexts = self
+            .contexts
+            .read()
+            .expect("submit service contexts poisoned");
+        let user_of = |ticket: &String| {
+            uuid::Uuid::parse_str(ticket)
+                .ok()
+                .and_then(|t| contexts.get(&t))
+                .map(|ctx| ctx.submitting_by.clone())
+                .unwrap_or_default()
+        };
+        locked
+            .iter()
+            .flat_map(|(path, entry)| {
+                let writer = entry
+                    .write_ticket
+                    .iter()
+                    .map(|t| (path.clone(), user_of(t), LockMode::Write));
+                let readers = entry
+                    .read_tickets
+                    .iter()
+                    .map(|t| (path.clone(), user_of(t), LockMode::Read));
+                writer.chain(readers).collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    /// 当前被锁定的文件数，同一文件上的多个读锁只计一次，会先清理超时的 ticket。
+    pub fn locked_file_count(&self) -> usize {
+        self.expire_tickets();
+        self.locked_paths
+            .read()
+            .expect("submit service locked_paths poisoned")
+            .len()
+    }
+
+    /// ticket 是否仍然有效（持有锁且未过期清理）
+    fn has_context(&self, ticket: &uuid::Uuid) -> bool {
+        self.contexts
+            .read()
+            .expect("submit service contexts poisoned")
+            .contains_key(ticket)
+    }
+
+    /// 取消尚未提交的 ticket：释放其持有的锁与上下文，并将记录标记为已取消。
+    ///
+    /// ticket 不存在或已经结束时返回 false。
+    pub async fn cancel_ticket(&self, ticket: &uuid::Uuid) -> bool {
+        if !self.has_context(ticket) {
+            return false;
+        }
+        self.release_context(ticket);
+        // 记录更新失败时 hive 重启后会恢复这些锁，直到 ticket 过期
+        let _ = crate::database::dao::update_submit_ticket_status(
+            &ticket.to_string(),
+            SubmitContextStatus::Cancelled,
+            None,
+        )
+        .await;
+        true
+    }
+
+    /// 释放 ticket 持有的锁与上下文，并删除数据库中的记录
+    async fn unlock_context(&self, ticket: &uuid::Uuid) {
+        self.release_context(ticket);
+        // 删除失败时记录会在过期后被清理，重启时也不会再恢复
+        let _ = crate::database::dao::delete_submit_ticket(&ticket.to_string()).await;
+    }
+
+    /// 只释放内存中 ticket 持有的锁与上下文
+    fn release_context(&self, ticket: &uuid::Uuid) {
         // 这里不依赖 contexts 里的 file 列表做定向删除，而是直接按 ticket 清除锁：
         // - 更稳健：即便 contexts 因异常路径缺失，也不会导致锁泄漏；
         // - 安全：只移除 value==ticket 的条目，不会误删其他并发 ticket 的锁。
-        
+
         // 先获取需要清理的 chunk 列表（包括已上传和上传中的）
         let chunks_to_cleanup: HashSet<String> = {
             let contexts = self
@@ -147,23 +363,27 @@ impl SubmitService {
                 .expect("submit service contexts poisoned");
             if let Some(ctx) = contexts.get(ticket) {
                 let mut chunks = HashSet::new();
-                
+
                 // 添加已上传的 chunk
-                let chunks_uploaded = ctx.chunks_uploaded.read()
+                let chunks_uploaded = ctx
+                    .chunks_uploaded
+                    .read()
                     .expect("submit service chunks_uploaded poisoned");
                 chunks.extend(chunks_uploaded.iter().cloned());
-                
+
                 // 添加上传中的 chunk（可能包含未完成的）
-                let chunks_in_progress = ctx.chunks_in_progress.read()
+                let chunks_in_progress = ctx
+                    .chunks_in_progress
+                    .read()
                     .expect("submit service chunks_in_progress poisoned");
                 chunks.extend(chunks_in_progress.iter().cloned());
-                
+
                 chunks
             } else {
                 HashSet::new()
             }
         };
-        
+
         // 清理所有相关的 chunk cache（包括已上传和上传中的）
         let cache = cache_service();
         for chunk_hash in &chunks_to_cleanup {
@@ -171,12 +391,16 @@ impl SubmitService {
             // 这些都是缓存文件，清理是安全的
             let _ = cache.remove_chunk(chunk_hash);
         }
-        
+
         let mut locked = self
             .locked_paths
             .write()
             .expect("submit service locked_paths poisoned");
-        locked.retain(|_, v| v != ticket);
+        let ticket_str = ticket.to_string();
+        locked.retain(|_, entry| {
+            entry.release(&ticket_str);
+            !entry.is_free()
+        });
 
         let mut context = self
             .contexts
@@ -185,9 +409,16 @@ impl SubmitService {
         context.remove(ticket);
     }
 
-    fn cleanup_expired_tickets(&self) {
-        // 注意：这里绝不能在持有 `contexts` 写锁时调用 `unlock_context`，
-        // 否则会在 `unlock_context` 内部二次申请 `contexts` 写锁导致自我死锁。
+    async fn cleanup_expired_tickets(&self) {
+        self.expire_tickets();
+        let now = chrono::Utc::now().timestamp();
+        let _ = crate::database::dao::delete_expired_submit_tickets(now).await;
+    }
+
+    /// 只清理内存中超时的 ticket，数据库中过期的记录不会被恢复，由 `cleanup_expired_tickets` 删除
+    fn expire_tickets(&self) {
+        // 注意：这里绝不能在持有 `contexts` 写锁时调用 `release_context`，
+        // 否则会在 `release_context` 内部二次申请 `contexts` 写锁导致自我死锁。
         //
         // 目前的解决方案：先在读锁下收集过期 ticket，释放锁后再逐个解锁。
         let now = chrono::Utc::now();
@@ -209,18 +440,20 @@ impl SubmitService {
         };
 
         for ticket in expired {
-            self.unlock_context(&ticket);
+            self.release_context(&ticket);
         }
     }
 
+    /// 锁定准备提交到 `branch_id` 的文件，期望版本与文件在该分支上当前的版本比较
     pub async fn launch_submit(
         &self,
+        branch_id: &str,
         files: &Vec<LockedFile>,
         submitting_by: String,
         timeout: chrono::Duration,
     ) -> Result<LaunchSubmitSuccess, LaunchSubmitFailure> {
         // 进行周边工作，清理超时的 ticket
-        self.cleanup_expired_tickets();
+        self.cleanup_expired_tickets().await;
 
         let ticket = uuid::Uuid::new_v4();
 
@@ -235,7 +468,8 @@ impl SubmitService {
             });
         }
 
-        let deadline = chrono::Utc::now() + timeout;
+        let started_at = chrono::Utc::now();
+        let deadline = started_at + timeout;
 
         // 0) 去重
         let mut unique_paths: HashSet<DepotPath> = HashSet::new();
@@ -265,10 +499,11 @@ impl SubmitService {
                 .write()
                 .expect("submit service contexts poisoned");
 
+            // 读锁之间可以共存，写锁与任何已有的锁冲突
             let mut conflicted = Vec::new();
-            for p in &unique_paths {
-                if locked.contains_key(p) {
-                    conflicted.push(files.iter().find(|f| f.path == *p).unwrap().clone());
+            for f in files.iter() {
+                if locked.get(&f.path).is_some_and(|e| !e.can_grant(f.mode)) {
+                    conflicted.push(f.clone());
                 }
             }
 
@@ -278,14 +513,15 @@ impl SubmitService {
                 });
             }
 
-            for p in &unique_paths {
-                locked.insert(p.clone(), ticket);
+            let ticket_str = ticket.to_string();
+            for f in files.iter() {
+                lock_entry(&mut locked, &f.path).grant(f.mode, &ticket_str);
             }
 
             // 2) 写入上下文
             let ctx = Arc::new(SubmitContext {
                 ticket,
-                submitting_by,
+                submitting_by: submitting_by.clone(),
                 timeout_deadline: deadline,
                 files: files.clone(),
                 chunks_uploaded: RwLock::new(Vec::new()),
@@ -295,7 +531,16 @@ impl SubmitService {
         }
 
         {
-            // 2) 读数据库，对比最新版本是否和预期的锁定版本一致
+            // 2) 读数据库，对比文件在分支上的版本是否和预期的锁定版本一致
+            let head = match crate::database::dao::find_branch_head(branch_id).await {
+                Ok(head) => head.unwrap_or(0),
+                Err(_) => {
+                    self.release_context(&ticket);
+                    return Err(LaunchSubmitFailure {
+                        file_unable_to_lock: files.clone(),
+                    });
+                }
+            };
             let mut conflicted = Vec::new();
 
             for p in &unique_paths {
@@ -311,11 +556,7 @@ impl SubmitService {
                     }
                 };
 
-                let current = match crate::database::dao::find_latest_file_revision_by_depot_path(
-                    &p.to_string(),
-                )
-                .await
-                {
+                let current = match find_revision_on_branch(head, &p.to_string()).await {
                     Ok(latest) => latest.and_then(|m| {
                         if m.is_delete {
                             None
@@ -328,9 +569,9 @@ impl SubmitService {
                         if expected.is_none()
                             && matches!(
                                 e,
-                                crate::database::dao::DaoError::Db(
-                                    sea_orm::DbErr::RecordNotFound(_)
-                                )
+                                crate::database::dao::DaoError::Db(sea_orm::DbErr::RecordNotFound(
+                                    _
+                                ))
                             )
                         {
                             None
@@ -348,7 +589,7 @@ impl SubmitService {
 
             if !conflicted.is_empty() {
                 // 回滚：释放本次 ticket 占用的锁与上下文
-                self.unlock_context(&ticket);
+                self.release_context(&ticket);
 
                 return Err(LaunchSubmitFailure {
                     file_unable_to_lock: conflicted,
@@ -356,46 +597,92 @@ impl SubmitService {
             }
         }
 
+        // 3) 写穿到数据库，hive 重启后可以恢复 ticket 与锁
+        let record = crate::database::dao::SubmitTicketRecord {
+            ticket: ticket.to_string(),
+            submitting_by,
+            branch_id: branch_id.to_string(),
+            started_at: started_at.timestamp(),
+            expires_at: deadline.timestamp(),
+            status: SubmitContextStatus::Launched,
+            files: files
+                .iter()
+                .map(|f| crate::database::dao::FileLockRecord {
+                    depot_path: f.path.to_string(),
+                    mode: f.mode,
+                    locked_generation: f.locked_generation,
+                    locked_revision: f.locked_revision,
+                })
+                .collect(),
+        };
+        if crate::database::dao::insert_submit_ticket(&record)
+            .await
+            .is_err()
+        {
+            self.release_context(&ticket);
+            return Err(LaunchSubmitFailure {
+                file_unable_to_lock: files.clone(),
+            });
+        }
+
         Ok(LaunchSubmitSuccess { ticket: ticket })
     }
 
-    pub fn upload_file_chunk(&self, ticket: &uuid::Uuid, chunk_hash: &String, offset: i64, chunk_size: i64, bytes: &[u8]) -> Result<UploadFileChunkResult, UploadFileChunkError> {
-        let contexts = self.contexts.read().expect("submit service contexts poisoned");
+    pub fn upload_file_chunk(
+        &self,
+        ticket: &uuid::Uuid,
+        chunk_hash: &String,
+        offset: i64,
+        chunk_size: i64,
+        bytes: &[u8],
+    ) -> Result<UploadFileChunkResult, UploadFileChunkError> {
+        let contexts = self
+            .contexts
+            .read()
+            .expect("submit service contexts poisoned");
         let context = contexts.get(ticket);
 
         match context {
             Some(context_inner) => {
-                let mut chunks_uploaded = context_inner.chunks_uploaded.write().expect("submit service chunks_uploaded poisoned");
-                let mut chunks_in_progress = context_inner.chunks_in_progress.write().expect("submit service chunks_in_progress poisoned");
-                
+                let mut chunks_uploaded = context_inner
+                    .chunks_uploaded
+                    .write()
+                    .expect("submit service chunks_uploaded poisoned");
+                let mut chunks_in_progress = context_inner
+                    .chunks_in_progress
+                    .write()
+                    .expect("submit service chunks_in_progress poisoned");
+
                 // 将 chunk_hash 添加到正在进行的列表中（无论是否完成）
                 chunks_in_progress.insert(chunk_hash.clone());
-                
+
                 // 使用 mod.rs 中的 CACHE_SERVICE 处理上传逻辑
                 let cache = cache_service();
-                
+
                 // 将 offset 从 i64 转换为 u64
                 let offset_u64 = offset.try_into().map_err(|_| UploadFileChunkError {
                     message: format!("invalid offset: {}", offset),
                 })?;
-                
+
                 // 调用缓存服务写入 chunk 数据
-                cache.append_chunk_part(chunk_hash, offset_u64, bytes).map_err(|e| {
-                    UploadFileChunkError {
+                cache
+                    .append_chunk_part(chunk_hash, offset_u64, bytes)
+                    .map_err(|e| UploadFileChunkError {
                         message: match e {
-                            ChunkCacheError::InvalidChunkHash(msg) => format!("invalid chunk hash: {}", msg),
+                            ChunkCacheError::InvalidChunkHash(msg) => {
+                                format!("invalid chunk hash: {}", msg)
+                            }
                             ChunkCacheError::Io(io_err) => format!("io error: {}", io_err),
                             ChunkCacheError::HashMismatch { expected, actual } => {
                                 format!("hash mismatch: expected {}, actual {}", expected, actual)
                             }
                         },
-                    }
-                })?;
-                
+                    })?;
+
                 // 判断当前写入是否已完成整个 chunk
                 let bytes_written = bytes.len() as i64;
                 let current_total_size = offset + bytes_written;
-                
+
                 // 检查是否超出预期大小
                 if current_total_size > chunk_size {
                     return Err(UploadFileChunkError {
@@ -405,13 +692,12 @@ impl SubmitService {
                         ),
                     });
                 }
-                
+
                 // 判断是否已完成整个 chunk
                 let is_chunk_complete = current_total_size == chunk_size;
-                
+
                 // 如果 chunk 已完成，验证整个 chunk 的哈希值
                 if is_chunk_complete {
-                    
                     // 验证整个 chunk 的哈希值
                     match cache.has_chunk(chunk_hash) {
                         Ok(true) => {
@@ -425,7 +711,10 @@ impl SubmitService {
                         Ok(false) => {
                             // chunk 文件不存在（不应该发生，因为刚刚写入）
                             return Err(UploadFileChunkError {
-                                message: format!("chunk file not found after write: {}", chunk_hash),
+                                message: format!(
+                                    "chunk file not found after write: {}",
+                                    chunk_hash
+                                ),
                             });
                         }
                         Err(e) => {
@@ -454,7 +743,7 @@ impl SubmitService {
                 }
             }
             None => {
-                return Result::Err(UploadFileChunkError{
+                return Result::Err(UploadFileChunkError {
                     message: "context not found".to_string(),
                 });
             }
@@ -464,144 +753,91 @@ impl SubmitService {
     /// ticket 是进行提交的上下文
     /// description 是提交的描述
     /// validations 是用于提交的验证，其中，key 是 depot path，value 是期望该文件在 cache 中已经完成上传的 chunk 的 hash 形成列表
+    /// renames 记录由移动产生的文件及其来源，会写入对应 file revision 的 metadata 中
+    /// request_id 用于幂等去重，相同 request_id 的重复提交直接返回首次提交的结果，为空则不去重
+    ///
+    /// 提交的进展会写入 ticket 的记录，供 GetSubmitContext 查询。
     pub async fn submit(
         &self,
         ticket: &uuid::Uuid,
         description: String,
         validations: HashMap<DepotPath, Vec<String>>,
+        renames: HashMap<DepotPath, RenameSource>,
+        request_id: &str,
+        branch_id: &str,
     ) -> Result<SubmitSuccess, SubmitFailure> {
-        // 清理超时票据，避免长期占用锁
-        self.cleanup_expired_tickets();
-
-        let ctx: Arc<SubmitContext> = {
-            let contexts = self
-                .contexts
-                .read()
-                .expect("submit service contexts poisoned");
-            let Some(ctx) = contexts.get(ticket) else {
-                return Err(SubmitFailure {
-                    context_not_found: true,
-                    conflicts: vec![],
-                    missing_chunks: vec![],
-                    message: "context not found".to_string(),
-                });
-            };
-            Arc::clone(ctx)
-        };
-
-        // 0) 检查 validations 覆盖了本次锁定的所有文件
-        for f in &ctx.files {
-            if !validations.contains_key(&f.path) {
-                return Err(SubmitFailure {
-                    context_not_found: false,
-                    conflicts: vec![],
-                    missing_chunks: vec![],
-                    message: format!("missing validations for path: {}", f.path),
-                });
-            }
+        let ticket_str = ticket.to_string();
+        let tracked = self.has_context(ticket);
+        // 状态只用于查询，记录失败不影响提交
+        if tracked {
+            let _ = crate::database::dao::update_submit_ticket_status(
+                &ticket_str,
+                SubmitContextStatus::Submitting,
+                Some(branch_id),
+            )
+            .await;
         }
 
-        // 1) 再次检查版本冲突（即使 launch_submit 已检查过，也要防止跨实例/外部写入）
-        let mut conflicts: Vec<SubmitConflict> = Vec::new();
-        for f in &ctx.files {
-            let expected_visible = match (f.locked_generation, f.locked_revision) {
-                (Some(g), Some(r)) => Some((g, r)),
-                (None, None) => None,
-                _ => {
-                    conflicts.push(SubmitConflict {
-                        path: f.path.to_string(),
-                        expected_generation: -1,
-                        expected_revision: -1,
-                        current_generation: -1,
-                        current_revision: -1,
-                    });
-                    continue;
-                }
+        let result = self
+            .try_submit(
+                ticket,
+                description,
+                validations,
+                renames,
+                request_id,
+                branch_id,
+            )
+            .await;
+
+        if tracked {
+            // ticket 仍然存在说明没有被消耗（检查未通过或按 request_id 重放），可以再次提交
+            let status = match &result {
+                _ if self.has_context(ticket) => SubmitContextStatus::Launched,
+                Ok(_) => SubmitContextStatus::Committed,
+                Err(_) => SubmitContextStatus::Failed,
             };
+            let _ =
+                crate::database::dao::update_submit_ticket_status(&ticket_str, status, None).await;
+        }
+        result
+    }
 
-            let latest = match crate::database::dao::find_latest_file_revision_by_depot_path(
-                &f.path.to_string(),
-            )
-            .await
-            {
-                Ok(m) => m,
+    async fn try_submit(
+        &self,
+        ticket: &uuid::Uuid,
+        description: String,
+        validations: HashMap<DepotPath, Vec<String>>,
+        renames: HashMap<DepotPath, RenameSource>,
+        request_id: &str,
+        branch_id: &str,
+    ) -> Result<SubmitSuccess, SubmitFailure> {
+        // 同一分支上的提交串行执行，并避免与 SquashChangelists 等跨分支的操作交错；
+        // 幂等检查也在锁内进行，防止相同 request_id 的并发提交重复落库
+        let _submit_guard = super::SUBMIT_LOCK.read().await;
+        let branch_lock = super::branch_submit_lock(branch_id);
+        let _branch_guard = branch_lock.lock().await;
+
+        if !request_id.is_empty() {
+            let not_before = (chrono::Utc::now() - SUBMISSION_CACHE_TTL).timestamp();
+            match crate::database::dao::find_submission(request_id, not_before).await {
+                Ok(Some(record)) => return Ok(cached_submit_success(record)),
+                Ok(None) => {}
                 Err(e) => {
                     return Err(SubmitFailure {
                         context_not_found: false,
                         conflicts: vec![],
                         missing_chunks: vec![],
-                        message: format!("database error while checking conflicts: {e}"),
+                        message: format!("database error while checking submission cache: {e}"),
                     });
                 }
-            };
-
-            // “可见版本”：如果 latest 是 delete，则视为文件不存在（与 launch_submit 一致）
-            let current_visible = latest.as_ref().and_then(|m| {
-                if m.is_delete {
-                    None
-                } else {
-                    Some((m.generation, m.revision))
-                }
-            });
-
-            if expected_visible != current_visible {
-                let (cur_g, cur_r) = latest
-                    .as_ref()
-                    .map(|m| (m.generation, m.revision))
-                    .unwrap_or((0, 0));
-                let (exp_g, exp_r) = expected_visible.unwrap_or((0, 0));
-                conflicts.push(SubmitConflict {
-                    path: f.path.to_string(),
-                    expected_generation: exp_g,
-                    expected_revision: exp_r,
-                    current_generation: cur_g,
-                    current_revision: cur_r,
-                });
             }
         }
 
-        if !conflicts.is_empty() {
-            return Err(SubmitFailure {
-                context_not_found: false,
-                conflicts,
-                missing_chunks: vec![],
-                message: "submit conflict".to_string(),
-            });
-        }
+        // 清理超时票据，避免长期占用锁
+        self.cleanup_expired_tickets().await;
 
-        // 2) 检查 validations 描述的所有 chunk 都已完整存在于 cache（并通过 hash 校验）
+        let (ctx, unique_chunks) = self.check_submit(ticket, &validations, branch_id).await?;
         let cache = cache_service();
-        let mut missing_chunks: Vec<String> = Vec::new();
-        let mut unique_chunks: HashSet<String> = HashSet::new();
-
-        for (_path, chunks) in validations.iter() {
-            // 约定：空列表表示“删除该文件”，无需任何 chunk
-            if chunks.is_empty() {
-                continue;
-            }
-            for h in chunks {
-                unique_chunks.insert(h.clone());
-                match cache.has_chunk(h) {
-                    Ok(true) => {}
-                    Ok(false) => missing_chunks.push(h.clone()),
-                    Err(_e) => {
-                        // HashMismatch / IO 等都算“不可用”，直接按 missing 返回
-                        missing_chunks.push(h.clone())
-                    }
-                }
-            }
-        }
-
-        if !missing_chunks.is_empty() {
-            missing_chunks.sort();
-            missing_chunks.dedup();
-            return Err(SubmitFailure {
-                context_not_found: false,
-                conflicts: vec![],
-                missing_chunks,
-                message: "missing chunks".to_string(),
-            });
-        }
 
         // 3) 将 chunk 写入 repository（写入成功或已存在都算通过）。
         //    同时记录每个 chunk 的长度，用于后续计算文件 size。
@@ -617,6 +853,8 @@ impl SubmitService {
             }
         };
 
+        // 已经存在的 chunk 保持原来的存储方式，下载时 hive 会按 revision 记录的方式重新编码
+        let compression_type = get_or_init_config().chunk_compression;
         let mut chunk_sizes: HashMap<String, i64> = HashMap::new();
         for h in unique_chunks.iter() {
             let data = match cache.read_chunk(h) {
@@ -632,7 +870,7 @@ impl SubmitService {
             };
             chunk_sizes.insert(h.clone(), data.len() as i64);
 
-            match repo.write_chunk(&data, Compression::None) {
+            match repo.write_chunk(&data, compression_type.into()) {
                 Ok(_record) => {}
                 Err(RepositoryError::DuplicateHash { .. }) => {
                     // repo 已存在该 chunk：视为 OK
@@ -650,108 +888,472 @@ impl SubmitService {
 
         // 4) 落库（changelist + file_revisions），成功后再删除 ticket/清理 cache
         let committed_at = chrono::Utc::now().timestamp();
-        let author = ctx.submitting_by.clone();
-
-        // 计算每个文件的新 generation/revision 与 size
-        let mut revisions_to_insert: Vec<crate::database::dao::NewFileRevisionInput> = Vec::new();
-        let mut latest_revisions: Vec<FileRevision> = Vec::new();
+        let author = changelist_author(&ctx.submitting_by).await;
+
+        // 版本号在所有分支间统一编号，提交到不同分支的同一文件可能算出相同的版本号，
+        // 由主键冲突保证只有一个能落库，另一个重新编号后再次落库
+        let mut attempt = 0;
+        let (changelist_id, latest_revisions, existing_files) = loop {
+            attempt += 1;
+
+            // 计算每个文件的新 generation/revision 与 size
+            let mut revisions_to_insert: Vec<crate::database::dao::NewFileRevisionInput> =
+                Vec::new();
+            let mut latest_revisions: Vec<FileRevision> = Vec::new();
+            // 已经提交过的文件，提交后需要把本次的分支加入其 seen_on_branches
+            let mut existing_files: Vec<String> = Vec::new();
+
+            // 写入 chunk 期间 HEAD 可能已被其他实例或锁过期后的提交推进，
+            // 新版本必须基于锁定时的版本计算，因此在落库前重新校验
+            let heads = validate_revision_chain(branch_id, &ctx.files).await?;
+
+            for (locked_file, latest) in ctx.files.iter().zip(heads) {
+                let depot_path = locked_file.path.to_string();
+                let chunks = validations
+                    .get(&locked_file.path)
+                    .cloned()
+                    .unwrap_or_default();
+                let is_delete = chunks.is_empty();
+
+                if latest.is_some() {
+                    existing_files.push(depot_path.clone());
+                }
+                let (new_generation, new_revision) = match latest {
+                    Some(m) => (m.generation, m.revision.saturating_add(1)),
+                    None => (1, 1),
+                };
 
-        for locked_file in &ctx.files {
-            let depot_path = locked_file.path.to_string();
-            let chunks = validations
-                .get(&locked_file.path)
-                .cloned()
-                .unwrap_or_default();
-            let is_delete = chunks.is_empty();
+                let size: i64 = if is_delete {
+                    0
+                } else {
+                    let mut size: i64 = 0;
+                    for h in &chunks {
+                        if let Some(len) = chunk_sizes.get(h) {
+                            size = size.saturating_add(*len);
+                        }
+                    }
+                    size
+                };
 
-            let latest = crate::database::dao::find_latest_file_revision_by_depot_path(&depot_path)
-                .await
-                .map_err(|e| SubmitFailure {
-                    context_not_found: false,
-                    conflicts: vec![],
-                    missing_chunks: vec![],
-                    message: format!("database error while preparing revisions: {e}"),
-                })?;
+                let content_hash = if is_delete {
+                    String::new()
+                } else {
+                    file_content_hash(repo, &chunks).map_err(|e| SubmitFailure {
+                        context_not_found: false,
+                        conflicts: vec![],
+                        missing_chunks: vec![],
+                        message: format!("failed to compute content hash of {depot_path}: {e}"),
+                    })?
+                };
+                let metadata = revision_metadata(
+                    renames.get(&locked_file.path),
+                    &content_hash,
+                    compression_type,
+                );
+
+                let binary_id_json = serde_json::json!(chunks);
+                revisions_to_insert.push(crate::database::dao::NewFileRevisionInput {
+                    depot_path: depot_path.clone(),
+                    generation: new_generation,
+                    revision: new_revision,
+                    binary_id: binary_id_json.clone(),
+                    size,
+                    is_delete,
+                    created_at: committed_at,
+                    metadata,
+                    file_metadata: crate::database::dao::new_file_metadata(
+                        &ctx.submitting_by,
+                        branch_id,
+                    ),
+                });
 
-            let (new_generation, new_revision) = match latest {
-                Some(m) => (m.generation, m.revision.saturating_add(1)),
-                None => (1, 1),
-            };
+                latest_revisions.push(FileRevision {
+                    path: depot_path,
+                    generation: new_generation,
+                    revision: new_revision,
+                    binary_id: binary_id_json
+                        .as_array()
+                        .unwrap_or(&Vec::new())
+                        .iter()
+                        .filter_map(|v| v.as_str().map(|s| s.to_string()))
+                        .collect(),
+                    size,
+                    revision_created_at: committed_at,
+                    content_hash,
+                    compression_type,
+                });
+            }
 
-            let size: i64 = if is_delete {
-                0
-            } else {
-                let mut size: i64 = 0;
-                for h in &chunks {
-                    if let Some(len) = chunk_sizes.get(h) {
-                        size = size.saturating_add(*len);
+            // 分支 HEAD 与 changelist 在同一个事务中推进，WatchBranch 收到事件时 HEAD 已经是新的 changelist
+            match crate::database::dao::commit_submit_to_branch(
+                branch_id,
+                &author,
+                &description,
+                committed_at,
+                serde_json::json!({}),
+                revisions_to_insert,
+            )
+            .await
+            {
+                Ok(id) => break (id, latest_revisions, existing_files),
+                Err(crate::database::dao::DaoError::RevisionConflict(path)) => {
+                    // 校验之后 HEAD 又被其他提交推进，重新读取以返回具体的冲突版本；
+                    // 本分支上的版本仍然一致时，是其他分支的提交占用了同一个版本号，重新编号
+                    match validate_revision_chain(branch_id, &ctx.files).await {
+                        Ok(_) if attempt < MAX_COMMIT_ATTEMPTS => continue,
+                        result => {
+                            self.release_context(ticket);
+                            return Err(match result {
+                                Err(failure) => failure,
+                                Ok(_) => SubmitFailure {
+                                    context_not_found: false,
+                                    conflicts: vec![],
+                                    missing_chunks: vec![],
+                                    message: format!("revision conflict on {path}"),
+                                },
+                            });
+                        }
                     }
                 }
-                size
-            };
+                Err(e) => {
+                    // P0 修复：落库失败必须释放锁/上下文，否则会导致该 ticket 占用的文件锁长期不释放，
+                    // 后续提交会持续冲突（直到下一次触发 cleanup）。
+                    // 记录保留到过期，由 `submit` 标记为失败
+                    self.release_context(ticket);
+                    return Err(SubmitFailure {
+                        context_not_found: false,
+                        conflicts: vec![],
+                        missing_chunks: vec![],
+                        message: format!("database error while committing submit: {e}"),
+                    });
+                }
+            }
+        };
 
-            let binary_id_json = serde_json::json!(chunks);
-            revisions_to_insert.push(crate::database::dao::NewFileRevisionInput {
-                depot_path: depot_path.clone(),
-                generation: new_generation,
-                revision: new_revision,
-                binary_id: binary_id_json.clone(),
-                size,
-                is_delete,
-                created_at: committed_at,
-                metadata: serde_json::json!({}),
-            });
+        // 5) 提交完成：清理 cache/释放锁，记录保留到过期，由 `submit` 标记为已提交
+        self.release_context(ticket);
 
-            latest_revisions.push(FileRevision {
-                path: depot_path,
-                generation: new_generation,
-                revision: new_revision,
-                binary_id: binary_id_json
-                    .as_array()
-                    .unwrap_or(&Vec::new())
-                    .iter()
-                    .filter_map(|v| v.as_str().map(|s| s.to_string()))
-                    .collect(),
-                size,
-                revision_created_at: committed_at,
-            });
+        // 新文件在插入时已经记录了分支；changelist 已落库，记录失败不影响本次提交结果
+        for depot_path in &existing_files {
+            let _ = crate::database::dao::add_branch_to_file(depot_path, branch_id).await;
         }
 
-        let changelist_id = match crate::database::dao::commit_submit(
-            &author,
-            &description,
+        // 通知订阅了该分支的 WatchBranch 流
+        crate::hive_server::branch::watch_branch::branch_events().publish(
+            branch_id,
+            crate::pb::BranchEvent {
+                changelist_id,
+                author: author.clone(),
+                committed_at,
+                files_count: latest_revisions.len() as u64,
+            },
+        );
+
+        // 6) 记录本次提交供 request_id 去重；changelist 已落库，记录失败不影响本次提交结果
+        if !request_id.is_empty() {
+            let record = crate::database::dao::SubmissionRecord {
+                request_id: request_id.to_string(),
+                changelist_id,
+                committed_at,
+                latest_revisions: serde_json::to_value(&latest_revisions)
+                    .unwrap_or_else(|_| serde_json::json!([])),
+            };
+            let expire_before = (chrono::Utc::now() - SUBMISSION_CACHE_TTL).timestamp();
+            let _ = crate::database::dao::insert_submission(&record, expire_before).await;
+        }
+
+        Ok(SubmitSuccess {
+            changelist_id,
             committed_at,
-            serde_json::json!({}),
-            revisions_to_insert,
-        )
-        .await
+            latest_revisions,
+            message: "success".to_string(),
+            replayed: false,
+        })
+    }
+
+    /// 提交前的检查：ticket 有效、持有所需的锁、validations 覆盖所有锁定的文件、没有版本冲突、
+    /// 所有 chunk 都已上传到 cache。通过时返回提交上下文与本次提交涉及的所有 chunk
+    async fn check_submit(
+        &self,
+        ticket: &uuid::Uuid,
+        validations: &HashMap<DepotPath, Vec<String>>,
+        branch_id: &str,
+    ) -> Result<(Arc<SubmitContext>, HashSet<String>), SubmitFailure> {
+        let ctx: Arc<SubmitContext> = {
+            let contexts = self
+                .contexts
+                .read()
+                .expect("submit service contexts poisoned");
+            let Some(ctx) = contexts.get(ticket) else {
+                return Err(SubmitFailure {
+                    context_not_found: true,
+                    conflicts: vec![],
+                    missing_chunks: vec![],
+                    message: "context not found".to_string(),
+                });
+            };
+            Arc::clone(ctx)
+        };
+
+        // 读锁只用于阻止他人提交，持有读锁的文件不能被提交；
+        // 写锁必须由本 ticket 独占，不能与任何读锁共存
         {
-            Ok(id) => id,
-            Err(e) => {
-                // P0 修复：落库失败必须释放锁/上下文，否则会导致该 ticket 占用的文件锁长期不释放，
-                // 后续提交会持续冲突（直到下一次触发 cleanup）。
-                self.unlock_context(ticket);
+            let locked = self
+                .locked_paths
+                .read()
+                .expect("submit service locked_paths poisoned");
+            let ticket_str = ticket.to_string();
+            for f in &ctx.files {
+                let exclusive = locked.get(&f.path).is_some_and(|e| {
+                    e.write_ticket.as_deref() == Some(ticket_str.as_str())
+                        && e.read_tickets.is_empty()
+                });
+                let message = if f.mode == LockMode::Read {
+                    format!("path is read-locked and can not be submitted: {}", f.path)
+                } else if !exclusive {
+                    format!("conflicting read lock on path: {}", f.path)
+                } else {
+                    continue;
+                };
                 return Err(SubmitFailure {
                     context_not_found: false,
                     conflicts: vec![],
                     missing_chunks: vec![],
-                    message: format!("database error while committing submit: {e}"),
+                    message,
                 });
             }
-        };
+        }
 
-        // 5) 提交完成：删除 ticket 并清理 cache/释放锁
-        self.unlock_context(ticket);
+        // 0) 检查 validations 覆盖了本次锁定的所有文件
+        for f in &ctx.files {
+            if !validations.contains_key(&f.path) {
+                return Err(SubmitFailure {
+                    context_not_found: false,
+                    conflicts: vec![],
+                    missing_chunks: vec![],
+                    message: format!("missing validations for path: {}", f.path),
+                });
+            }
+        }
+
+        // 1) 再次检查版本冲突（即使 launch_submit 已检查过，也要防止跨实例/外部写入）
+        validate_revision_chain(branch_id, &ctx.files).await?;
+
+        // 2) 检查 validations 描述的所有 chunk 都已完整存在于 cache（并通过 hash 校验）
+        let cache = cache_service();
+        let mut missing_chunks: Vec<String> = Vec::new();
+        let mut unique_chunks: HashSet<String> = HashSet::new();
+
+        for (_path, chunks) in validations.iter() {
+            // 约定：空列表表示“删除该文件”，无需任何 chunk
+            if chunks.is_empty() {
+                continue;
+            }
+            for h in chunks {
+                unique_chunks.insert(h.clone());
+                match cache.has_chunk(h) {
+                    Ok(true) => {}
+                    Ok(false) => missing_chunks.push(h.clone()),
+                    Err(_e) => {
+                        // HashMismatch / IO 等都算“不可用”，直接按 missing 返回
+                        missing_chunks.push(h.clone())
+                    }
+                }
+            }
+        }
+
+        if !missing_chunks.is_empty() {
+            missing_chunks.sort();
+            missing_chunks.dedup();
+            return Err(SubmitFailure {
+                context_not_found: false,
+                conflicts: vec![],
+                missing_chunks,
+                message: "missing chunks".to_string(),
+            });
+        }
+
+        Ok((ctx, unique_chunks))
+    }
+
+    /// 只执行提交前的检查，不写入 repository 与数据库，检查通过时返回 changelist_id 为 0 的结果。
+    ///
+    /// 预检之后 ticket 即失效：释放其持有的锁并清理已上传的 chunk，真正提交时需要重新 launch_submit。
+    pub async fn dry_run_submit(
+        &self,
+        ticket: &uuid::Uuid,
+        validations: &HashMap<DepotPath, Vec<String>>,
+        branch_id: &str,
+    ) -> Result<SubmitSuccess, SubmitFailure> {
+        // 预检不写入 changelist，不需要分支锁
+        let _submit_guard = super::SUBMIT_LOCK.read().await;
+        self.cleanup_expired_tickets().await;
+
+        let result = self.check_submit(ticket, validations, branch_id).await;
+        self.unlock_context(ticket).await;
+        result?;
 
         Ok(SubmitSuccess {
-            changelist_id,
-            committed_at,
-            latest_revisions,
-            message: "success".to_string(),
+            changelist_id: 0,
+            committed_at: 0,
+            latest_revisions: vec![],
+            message: "dry run succeeded".to_string(),
+            replayed: false,
         })
     }
 }
 
+/// 文件在分支上的版本：分支 HEAD 所在的 changelist 链上最新的 revision，分支还没有 changelist 时为 None
+async fn find_revision_on_branch(
+    head: i64,
+    depot_path: &str,
+) -> crate::database::dao::DaoResult<Option<crate::database::entities::file_revisions::Model>> {
+    if head <= 0 {
+        return Ok(None);
+    }
+    crate::database::dao::find_file_revision_at(depot_path, head).await
+}
+
+/// 重新读取锁定文件在 `branch_id` 上的版本，确认它们仍是 `launch_submit` 时锁定的版本。
+///
+/// 全部一致时按 `files` 的顺序返回各文件在所有分支中最新的 revision，新的版本号基于它计算，
+/// 保证版本号在分支之间不重复；否则返回包含所有冲突文件的 `SubmitFailure`。
+/// 与 `launch_submit` 一致，分支上的版本为删除时视为文件不存在。
+async fn validate_revision_chain(
+    branch_id: &str,
+    files: &[LockedFile],
+) -> Result<Vec<Option<crate::database::entities::file_revisions::Model>>, SubmitFailure> {
+    let db_error = |e: crate::database::dao::DaoError| SubmitFailure {
+        context_not_found: false,
+        conflicts: vec![],
+        missing_chunks: vec![],
+        message: format!("database error while checking conflicts: {e}"),
+    };
+    let head = crate::database::dao::find_branch_head(branch_id)
+        .await
+        .map_err(db_error)?
+        .unwrap_or(0);
+    let mut heads = Vec::with_capacity(files.len());
+    let mut conflicts: Vec<SubmitConflict> = Vec::new();
+    for f in files {
+        let depot_path = f.path.to_string();
+        let latest = crate::database::dao::find_latest_file_revision_by_depot_path(&depot_path)
+            .await
+            .map_err(db_error)?;
+        let on_branch = find_revision_on_branch(head, &depot_path)
+            .await
+            .map_err(db_error)?;
+
+        let expected_visible = match (f.locked_generation, f.locked_revision) {
+            (Some(g), Some(r)) => Some((g, r)),
+            (None, None) => None,
+            _ => {
+                conflicts.push(SubmitConflict {
+                    path: f.path.to_string(),
+                    expected_generation: -1,
+                    expected_revision: -1,
+                    current_generation: -1,
+                    current_revision: -1,
+                });
+                heads.push(latest);
+                continue;
+            }
+        };
+        let current_visible = on_branch
+            .as_ref()
+            .filter(|m| !m.is_delete)
+            .map(|m| (m.generation, m.revision));
+
+        if expected_visible != current_visible {
+            let (cur_g, cur_r) = on_branch
+                .as_ref()
+                .map(|m| (m.generation, m.revision))
+                .unwrap_or((0, 0));
+            let (exp_g, exp_r) = expected_visible.unwrap_or((0, 0));
+            conflicts.push(SubmitConflict {
+                path: f.path.to_string(),
+                expected_generation: exp_g,
+                expected_revision: exp_r,
+                current_generation: cur_g,
+                current_revision: cur_r,
+            });
+        }
+        heads.push(latest);
+    }
+
+    if !conflicts.is_empty() {
+        return Err(SubmitFailure {
+            context_not_found: false,
+            conflicts,
+            missing_chunks: vec![],
+            message: "submit conflict".to_string(),
+        });
+    }
+    Ok(heads)
+}
+
+/// 由去重记录还原首次提交的结果
+fn cached_submit_success(record: crate::database::dao::SubmissionRecord) -> SubmitSuccess {
+    SubmitSuccess {
+        changelist_id: record.changelist_id,
+        committed_at: record.committed_at,
+        latest_revisions: serde_json::from_value(record.latest_revisions).unwrap_or_default(),
+        message: "success".to_string(),
+        replayed: true,
+    }
+}
+
+/// changelist 的作者：用户设置了显示名称时使用显示名称，否则（包括查询失败时）使用用户名
+async fn changelist_author(username: &str) -> String {
+    match crate::database::dao::find_user_by_username(username).await {
+        Ok(Some(user)) if !user.display_name.is_empty() => user.display_name,
+        _ => username.to_string(),
+    }
+}
+
+/// 按顺序读取仓库中文件的所有 chunk，计算整个文件内容的哈希
+pub(crate) fn file_content_hash(repo: &Repository, chunks: &[String]) -> Result<String, String> {
+    let hashes = chunks
+        .iter()
+        .map(|h| blake3_hex_to_hash(h).ok_or_else(|| format!("invalid chunk hash: {h}")))
+        .collect::<Result<Vec<_>, _>>()?;
+    let hash = repo
+        .compute_content_hash(&hashes)
+        .map_err(|e| e.to_string())?;
+    Ok(blake3_hash_to_hex(&hash))
+}
+
+/// 获取路径上的锁记录，不存在时创建一个空记录
+fn lock_entry<'a>(
+    locked: &'a mut HashMap<DepotPath, LockEntry>,
+    path: &DepotPath,
+) -> &'a mut LockEntry {
+    locked.entry(path.clone()).or_insert_with(|| LockEntry {
+        path: path.to_string(),
+        ..Default::default()
+    })
+}
+
+/// file revision 的 metadata：内容哈希（删除时没有）、内容的压缩方式以及移动产生的文件的来源版本
+fn revision_metadata(
+    rename: Option<&RenameSource>,
+    content_hash: &str,
+    compression_type: CompressionType,
+) -> serde_json::Value {
+    let mut metadata = serde_json::json!({ "compressionType": compression_type });
+    if !content_hash.is_empty() {
+        metadata["hash"] = serde_json::json!(content_hash);
+    }
+    if let Some(source) = rename {
+        metadata["renamedFrom"] = serde_json::json!({
+            "path": source.from_path,
+            "generation": source.from_generation,
+            "revision": source.from_revision,
+        });
+    }
+    metadata
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -832,10 +1434,16 @@ mod tests {
             path: p,
             locked_generation: None,
             locked_revision: None,
+            mode: LockMode::Write,
         }];
 
         let r = svc
-            .launch_submit(&files, "alice".to_string(), chrono::Duration::minutes(10))
+            .launch_submit(
+                "",
+                &files,
+                "alice".to_string(),
+                chrono::Duration::minutes(10),
+            )
             .await;
 
         assert!(r.is_ok(), "expected Ok, got: {:?}", r.err());
@@ -853,16 +1461,23 @@ mod tests {
                 path: p.clone(),
                 locked_generation: None,
                 locked_revision: None,
+                mode: LockMode::Write,
             },
             LockedFile {
                 path: p.clone(),
                 locked_generation: None,
                 locked_revision: None,
+                mode: LockMode::Write,
             },
         ];
 
         let r = svc
-            .launch_submit(&files, "alice".to_string(), chrono::Duration::minutes(10))
+            .launch_submit(
+                "",
+                &files,
+                "alice".to_string(),
+                chrono::Duration::minutes(10),
+            )
             .await;
 
         assert!(r.is_err(), "expected Err");
@@ -882,15 +1497,21 @@ mod tests {
             path: p.clone(),
             locked_generation: None,
             locked_revision: None,
+            mode: LockMode::Write,
         }];
 
         let first = svc
-            .launch_submit(&files, "alice".to_string(), chrono::Duration::minutes(10))
+            .launch_submit(
+                "",
+                &files,
+                "alice".to_string(),
+                chrono::Duration::minutes(10),
+            )
             .await;
         assert!(first.is_ok(), "first should succeed");
 
         let second = svc
-            .launch_submit(&files, "bob".to_string(), chrono::Duration::minutes(10))
+            .launch_submit("", &files, "bob".to_string(), chrono::Duration::minutes(10))
             .await;
         assert!(second.is_err(), "second should conflict");
         let e = second.err().unwrap();
@@ -921,9 +1542,10 @@ mod tests {
             path: p.clone(),
             locked_generation: Some(1),
             locked_revision: Some(1),
+            mode: LockMode::Write,
         }];
         let r1 = svc
-            .launch_submit(&bad, "alice".to_string(), chrono::Duration::minutes(10))
+            .launch_submit("", &bad, "alice".to_string(), chrono::Duration::minutes(10))
             .await;
         assert!(r1.is_err(), "expected mismatch to fail");
         assert!(
@@ -939,9 +1561,15 @@ mod tests {
             path: p.clone(),
             locked_generation: Some(1),
             locked_revision: Some(2),
+            mode: LockMode::Write,
         }];
         let r2 = svc
-            .launch_submit(&good, "alice".to_string(), chrono::Duration::minutes(10))
+            .launch_submit(
+                "",
+                &good,
+                "alice".to_string(),
+                chrono::Duration::minutes(10),
+            )
             .await;
         assert!(
             r2.is_ok(),
@@ -964,13 +1592,49 @@ mod tests {
             path: p.clone(),
             locked_generation: None,
             locked_revision: None,
+            mode: LockMode::Write,
         }];
         let r1 = svc
-            .launch_submit(&expected_none, "alice".to_string(), chrono::Duration::minutes(10))
+            .launch_submit(
+                "",
+                &expected_none,
+                "alice".to_string(),
+                chrono::Duration::minutes(10),
+            )
             .await;
         assert!(r1.is_ok());
     }
 
+    #[test]
+    fn content_hash_of_two_chunk_file_covers_all_chunks() {
+        use crv_core::repository::Compression;
+
+        let dir = tempfile::tempdir().expect("create temp dir");
+        let repo = Repository::new(dir.path()).expect("open repository");
+        let first = repo
+            .write_chunk(b"first half of the file, ", Compression::None)
+            .expect("write first chunk");
+        // 压缩存储的 chunk 按原始内容参与哈希
+        let second = repo
+            .write_chunk(b"second half of the file", Compression::Zstd)
+            .expect("write second chunk");
+        let chunks = vec![
+            blake3_hash_to_hex(&first.hash),
+            blake3_hash_to_hex(&second.hash),
+        ];
+
+        let content_hash = file_content_hash(&repo, &chunks).expect("compute content hash");
+        let metadata = revision_metadata(None, &content_hash, CompressionType::Zstd);
+
+        let expected = blake3::hash(b"first half of the file, second half of the file");
+        assert_eq!(metadata["hash"], expected.to_hex().as_str());
+        assert_ne!(metadata["hash"], chunks[0].as_str());
+        assert_eq!(metadata["compressionType"], "zstd");
+        let deleted = revision_metadata(None, "", CompressionType::None);
+        assert!(deleted.get("hash").is_none());
+        assert_eq!(deleted["compressionType"], "none");
+    }
+
     /// 这些测试依赖全局单例数据库连接池（`crate::database::DB_CONN`），而 `#[tokio::test]`
     /// 默认会为每个测试创建并销毁一个独立 runtime，导致连接池跨 runtime 复用时出现
     /// “Tokio context ... is being shutdown”。
@@ -987,4 +1651,340 @@ mod tests {
             launch_submit_treats_deleted_latest_as_nonexistent().await;
         });
     }
+
+    fn read_lock(path: &str) -> Vec<LockedFile> {
+        vec![LockedFile {
+            path: DepotPath::new(path).unwrap(),
+            locked_generation: None,
+            locked_revision: None,
+            mode: LockMode::Read,
+        }]
+    }
+
+    #[tokio::test]
+    async fn concurrent_read_locks_succeed() {
+        crate::test_support::install_mock_dao();
+        let svc = SubmitService::new();
+        let files = read_lock("//read_lock/concurrent.txt");
+
+        for user in ["alice", "bob"] {
+            let r = svc
+                .launch_submit("", &files, user.to_string(), chrono::Duration::minutes(10))
+                .await;
+            assert!(
+                r.is_ok(),
+                "read lock for {user} should succeed: {:?}",
+                r.err()
+            );
+        }
+
+        let mut locks = svc.list_locked_files();
+        locks.sort_by(|a, b| a.1.cmp(&b.1));
+        let holders: Vec<(&str, LockMode)> = locks
+            .iter()
+            .map(|(_, user, mode)| (user.as_str(), *mode))
+            .collect();
+        assert_eq!(
+            holders,
+            vec![("alice", LockMode::Read), ("bob", LockMode::Read)]
+        );
+    }
+
+    #[tokio::test]
+    async fn write_lock_rejected_while_read_lock_held() {
+        crate::test_support::install_mock_dao();
+        let svc = SubmitService::new();
+        let path = "//read_lock/blocked.txt";
+        svc.launch_submit(
+            "",
+            &read_lock(path),
+            "alice".to_string(),
+            chrono::Duration::minutes(10),
+        )
+        .await
+        .expect("read lock");
+
+        let write = vec![LockedFile {
+            mode: LockMode::Write,
+            ..read_lock(path).remove(0)
+        }];
+        let e = svc
+            .launch_submit("", &write, "bob".to_string(), chrono::Duration::minutes(10))
+            .await
+            .expect_err("write lock should conflict with read lock");
+        assert_eq!(e.file_unable_to_lock.len(), 1);
+        assert_eq!(e.file_unable_to_lock[0].path.to_string(), path);
+    }
+
+    #[tokio::test]
+    async fn submit_rejects_read_locked_files() {
+        crate::test_support::install_mock_dao();
+        let svc = SubmitService::new();
+        let path = "//read_lock/not_submittable.txt";
+        let ticket = svc
+            .launch_submit(
+                "",
+                &read_lock(path),
+                "alice".to_string(),
+                chrono::Duration::minutes(10),
+            )
+            .await
+            .expect("read lock")
+            .ticket;
+
+        let validations = HashMap::from([(DepotPath::new(path).unwrap(), Vec::new())]);
+        let e = svc
+            .submit(
+                &ticket,
+                "desc".to_string(),
+                validations,
+                HashMap::new(),
+                "",
+                "",
+            )
+            .await
+            .expect_err("read-locked file must not be submitted");
+        assert!(e.message.contains("read-locked"), "{}", e.message);
+    }
+
+    #[tokio::test]
+    async fn tickets_survive_restart() {
+        crate::test_support::install_mock_dao();
+        let path = format!("//restart/{}.txt", uuid::Uuid::new_v4());
+        let expired_path = format!("//restart/{}.txt", uuid::Uuid::new_v4());
+        let write_lock = |path: &str| {
+            vec![LockedFile {
+                mode: LockMode::Write,
+                ..read_lock(path).remove(0)
+            }]
+        };
+        let svc = SubmitService::new();
+        let ticket = svc
+            .launch_submit(
+                "",
+                &write_lock(&path),
+                "alice".to_string(),
+                chrono::Duration::minutes(10),
+            )
+            .await
+            .expect("write lock")
+            .ticket;
+        svc.launch_submit(
+            "",
+            &write_lock(&expired_path),
+            "alice".to_string(),
+            chrono::Duration::seconds(-1),
+        )
+        .await
+        .expect("expired write lock");
+
+        // 模拟重启：新的 service 只能从数据库恢复状态
+        let restarted = SubmitService::new();
+        restarted.restore().await.unwrap();
+        let locks = restarted.list_locked_files();
+        assert!(locks.contains(&(
+            DepotPath::new(&path).unwrap(),
+            "alice".to_string(),
+            LockMode::Write
+        )));
+        assert!(locks.iter().all(|(p, _, _)| p.to_string() != expired_path));
+        restarted
+            .launch_submit(
+                "",
+                &write_lock(&path),
+                "bob".to_string(),
+                chrono::Duration::minutes(10),
+            )
+            .await
+            .expect_err("restored lock should still block other submits");
+
+        // 恢复的 ticket 仍然可以用于提交
+        let validations = HashMap::from([(DepotPath::new(&path).unwrap(), Vec::new())]);
+        restarted
+            .dry_run_submit(&ticket, &validations, "")
+            .await
+            .expect("restored ticket should be valid");
+
+        // ticket 释放后记录随之删除，再次重启不会恢复
+        let restarted_again = SubmitService::new();
+        restarted_again.restore().await.unwrap();
+        assert!(
+            restarted_again
+                .list_locked_files()
+                .iter()
+                .all(|(p, _, _)| p.to_string() != path)
+        );
+    }
+
+    /// 两个 hive 实例（或锁过期后重新加锁的客户端）同时持有同一文件的写锁并发提交：
+    /// 只能有一个提交落库，另一个在提交锁内重新校验 HEAD 时发现冲突
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn concurrent_submits_on_same_head_commit_once() {
+        use crate::database::dao::Dao;
+        use crv_core::repository::compute_chunk_hash;
+
+        let dao = crate::test_support::install_mock_dao();
+        let path = format!("//tests/revision_chain/{}.txt", uuid::Uuid::new_v4());
+        let files = vec![LockedFile {
+            mode: LockMode::Write,
+            ..read_lock(&path).remove(0)
+        }];
+
+        let mut submits = Vec::new();
+        for i in 0..2 {
+            let svc = Arc::new(SubmitService::new());
+            let ticket = svc
+                .launch_submit(
+                    "",
+                    &files,
+                    format!("user{i}"),
+                    chrono::Duration::minutes(10),
+                )
+                .await
+                .expect("each instance locks the file on its own")
+                .ticket;
+            let data = format!("content {i} of {path}");
+            let chunk = blake3_hash_to_hex(&compute_chunk_hash(data.as_bytes()));
+            cache_service()
+                .append_chunk_part(&chunk, 0, data.as_bytes())
+                .unwrap();
+            let validations = HashMap::from([(DepotPath::new(&path).unwrap(), vec![chunk])]);
+            submits.push(tokio::spawn(async move {
+                svc.submit(
+                    &ticket,
+                    format!("race {i}"),
+                    validations,
+                    HashMap::new(),
+                    "",
+                    "",
+                )
+                .await
+            }));
+        }
+
+        let mut committed = Vec::new();
+        for submit in submits {
+            match submit.await.unwrap() {
+                Ok(success) => committed.push(success),
+                Err(failure) => {
+                    assert_eq!(failure.conflicts.len(), 1, "{}", failure.message);
+                    assert_eq!(failure.conflicts[0].path, path);
+                    assert_eq!(
+                        (
+                            failure.conflicts[0].current_generation,
+                            failure.conflicts[0].current_revision
+                        ),
+                        (1, 1)
+                    );
+                }
+            }
+        }
+        assert_eq!(committed.len(), 1);
+
+        let revisions = dao.list_file_revisions_by_depot_path(&path).await.unwrap();
+        assert_eq!(revisions.len(), 1);
+        assert_eq!(revisions[0].changelist_id, committed[0].changelist_id);
+        assert_eq!((revisions[0].generation, revisions[0].revision), (1, 1));
+    }
+
+    /// 两个分支并发添加同一个文件：分支之间互不冲突，两个提交都落库，
+    /// 算出相同版本号的一方重新编号，每个分支只看到自己提交的内容
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn concurrent_submits_to_different_branches_both_commit() {
+        use crate::database::dao::Dao;
+        use crv_core::repository::compute_chunk_hash;
+
+        let dao = crate::test_support::install_mock_dao();
+        let path = format!("//tests/revision_chain/{}.txt", uuid::Uuid::new_v4());
+        let files = vec![LockedFile {
+            mode: LockMode::Write,
+            ..read_lock(&path).remove(0)
+        }];
+
+        let mut submits = Vec::new();
+        for i in 0..2 {
+            let branch_id = format!("branch-{i}-{}", uuid::Uuid::new_v4());
+            dao.insert_branch(&crv_core::metadata::BranchDoc {
+                id: branch_id.clone(),
+                created_at: 0,
+                created_by: "alice".to_string(),
+                head_changelist_id: 0,
+                is_protected: false,
+                metadata: crv_core::metadata::BranchMetadata {
+                    description: String::new(),
+                    owners: vec![],
+                    description_regex: None,
+                },
+            })
+            .await
+            .unwrap();
+            let svc = Arc::new(SubmitService::new());
+            let ticket = svc
+                .launch_submit(
+                    &branch_id,
+                    &files,
+                    format!("user{i}"),
+                    chrono::Duration::minutes(10),
+                )
+                .await
+                .expect("the file does not exist on either branch")
+                .ticket;
+            let data = format!("content {i} of {path}");
+            let chunk = blake3_hash_to_hex(&compute_chunk_hash(data.as_bytes()));
+            cache_service()
+                .append_chunk_part(&chunk, 0, data.as_bytes())
+                .unwrap();
+            let validations =
+                HashMap::from([(DepotPath::new(&path).unwrap(), vec![chunk.clone()])]);
+            submits.push(tokio::spawn(async move {
+                let result = svc
+                    .submit(
+                        &ticket,
+                        format!("add on branch {i}"),
+                        validations,
+                        HashMap::new(),
+                        "",
+                        &branch_id,
+                    )
+                    .await;
+                (branch_id, chunk, result)
+            }));
+        }
+
+        let mut revisions = Vec::new();
+        for submit in submits {
+            let (branch_id, chunk, result) = submit.await.unwrap();
+            let success = result.expect("submits on different branches do not conflict");
+            assert_eq!(
+                dao.find_branch_head(&branch_id).await.unwrap(),
+                Some(success.changelist_id)
+            );
+            let tree = dao
+                .list_file_tree_revisions(&DepotPath::new(&path).unwrap(), success.changelist_id)
+                .await
+                .unwrap();
+            assert_eq!(tree.len(), 1);
+            assert_eq!(tree[0].binary_id, serde_json::json!([chunk]));
+            revisions.push(success.latest_revisions[0].revision);
+        }
+        revisions.sort();
+        assert_eq!(revisions, vec![1, 2]);
+    }
+
+    #[tokio::test]
+    async fn changelist_author_prefers_display_name() {
+        crate::test_support::install_mock_dao();
+        let named = format!("user-{}", uuid::Uuid::new_v4());
+        let unnamed = format!("user-{}", uuid::Uuid::new_v4());
+        crate::database::dao::insert_user(&named, "hash", None, "Alice Liddell")
+            .await
+            .unwrap();
+        crate::database::dao::insert_user(&unnamed, "hash", None, "")
+            .await
+            .unwrap();
+
+        assert_eq!(changelist_author(&named).await, "Alice Liddell");
+        assert_eq!(changelist_author(&unnamed).await, unnamed);
+        assert_eq!(changelist_author("nobody").await, "nobody");
+    }
 }
\ No newline at end of file
@@ -1,20 +1,97 @@
-use crate::auth::require_user;
+use crate::audit::{AuditEvent, AuditOp, AuditOutcome};
 use crate::common::depot_path::DepotPath;
-use crate::hive_server::submit::submit_service;
+use crate::database::dao::dao;
+use crate::hive_server::branch::set_protection::check_branch_accepts_submit;
+use crate::hive_server::submit::description::check_description;
+use crate::hive_server::submit::service::RenameSource;
+use crate::hive_server::submit::{submit_service, submitting_user};
 use crate::logging::HiveLog;
-use crate::pb::{FileRevision as PbFileRevision, SubmitConflict as PbSubmitConflict, SubmitReq, SubmitRsp, UploadFileChunkRsp};
+use crate::metrics::metrics;
+use crate::pb::{
+    FileRevision as PbFileRevision, SubmitConflict as PbSubmitConflict, SubmitReq, SubmitRsp,
+    UploadFileChunkRsp,
+};
+use crate::webhook::SubmitNotification;
 use tokio_stream::wrappers::ReceiverStream;
 use tonic::{Request, Response, Status};
 
 pub type UploadFileChunkStream = ReceiverStream<Result<UploadFileChunkRsp, Status>>;
 
-pub async fn submit(
-    log: HiveLog,
-    r: Request<SubmitReq>,
-) -> Result<Response<SubmitRsp>, Status> {
-    // let user = require_user(&r)?;
-    let submitting_by = "admin".to_string();
-    // let submitting_by = user.username.clone();
+/// 响应扩展标记：本次响应是按 request_id 去重后重放的首次提交结果
+#[derive(Clone, Copy, Debug)]
+pub struct SubmitReplayed;
+
+/// 记录审计与通知 webhook 所需的请求信息，在请求被消费之前取出
+#[derive(Clone, Debug)]
+pub struct SubmitSummary {
+    ticket: String,
+    files: usize,
+    branch_id: String,
+    dry_run: bool,
+}
+
+impl SubmitSummary {
+    pub fn of(request: &SubmitReq) -> Self {
+        Self {
+            ticket: request.ticket.clone(),
+            files: request.file_chunks.len(),
+            branch_id: request.branch_id.trim().to_string(),
+            dry_run: request.dry_run,
+        }
+    }
+
+    /// 提交结束后要记录的审计事件与要发送的 webhook 通知。
+    ///
+    /// 预检不写入任何数据，按 request_id 去重重放的提交在首次提交时已经记录并通知过，两者都不会再产生；
+    /// 只有真正写入 changelist 的提交才通知 webhook。
+    pub fn events(
+        &self,
+        username: &str,
+        out: &Result<Response<SubmitRsp>, Status>,
+    ) -> (Option<AuditEvent>, Option<SubmitNotification>) {
+        let rsp = match out {
+            _ if self.dry_run => return (None, None),
+            Ok(rsp) if rsp.extensions().get::<SubmitReplayed>().is_some() => return (None, None),
+            Ok(rsp) => rsp.get_ref(),
+            Err(e) => {
+                let event = AuditEvent::failed(AuditOp::Submit, username, &self.ticket, e);
+                return (Some(event), None);
+            }
+        };
+        if !rsp.success {
+            let event = AuditEvent::new(
+                AuditOp::Submit,
+                username,
+                &self.ticket,
+                AuditOutcome::Failure,
+                serde_json::json!({
+                    "message": rsp.message,
+                    "conflicts": rsp.conflicts.len(),
+                    "missing_chunks": rsp.missing_chunks.len(),
+                }),
+            );
+            return (Some(event), None);
+        }
+        let event = AuditEvent::new(
+            AuditOp::Submit,
+            username,
+            rsp.changelist_id.to_string(),
+            AuditOutcome::Success,
+            serde_json::json!({ "ticket": self.ticket, "files": self.files }),
+        );
+        let notification = SubmitNotification {
+            branch_id: self.branch_id.clone(),
+            changelist_id: rsp.changelist_id,
+            author: username.to_string(),
+            files_count: rsp.latest_revisions.len() as u64,
+            committed_at: rsp.committed_at,
+        };
+        (Some(event), Some(notification))
+    }
+}
+
+pub async fn submit(log: HiveLog, r: Request<SubmitReq>) -> Result<Response<SubmitRsp>, Status> {
+    let submitting_by = submitting_user(&r);
     let log = log.with_user(&submitting_by);
     let _g = log.enter();
     let request = r.into_inner();
@@ -27,30 +104,82 @@ pub async fn submit(
     let mut validations: std::collections::HashMap<DepotPath, Vec<String>> =
         std::collections::HashMap::new();
 
+    let mut renames: std::collections::HashMap<DepotPath, RenameSource> =
+        std::collections::HashMap::new();
+
     for fc in &request.file_chunks {
-        let path = DepotPath::new(&fc.path)
-            .map_err(|e| Status::invalid_argument(format!("invalid depot path '{}': {e}", fc.path)))?;
+        let path = DepotPath::new(&fc.path).map_err(|e| {
+            Status::invalid_argument(format!("invalid depot path '{}': {e}", fc.path))
+        })?;
+        if fc.is_rename {
+            DepotPath::new(&fc.rename_from_path).map_err(|e| {
+                Status::invalid_argument(format!(
+                    "invalid rename source depot path '{}': {e}",
+                    fc.rename_from_path
+                ))
+            })?;
+            renames.insert(
+                path.clone(),
+                RenameSource {
+                    from_path: fc.rename_from_path.clone(),
+                    from_generation: fc.rename_from_generation,
+                    from_revision: fc.rename_from_revision,
+                },
+            );
+        }
         validations.insert(path, fc.binary_id.clone());
     }
 
     log.info(&format!(
-        "submit received: ticket={}, files={}, description_len={}",
+        "submit received: ticket={}, request_id={}, files={}, renames={}, description_len={}, dry_run={}",
         ticket_uuid,
+        request.request_id,
         request.file_chunks.len(),
-        request.description.len()
+        renames.len(),
+        request.description.len(),
+        request.dry_run
     ));
 
-    let result = service
-        .submit(&ticket_uuid, request.description.clone(), validations)
-        .await;
+    // 被拒绝时 ticket 仍然有效，锁随 ticket 过期或取消释放
+    check_branch_accepts_submit(dao().as_ref(), request.branch_id.trim()).await?;
+    // 描述不符合格式时 ticket 仍然有效，客户端修改描述后可以重新提交
+    check_description(dao().as_ref(), &request.branch_id, &request.description).await?;
+
+    // 预检不落库，也不参与 request_id 去重
+    let result = if request.dry_run {
+        service
+            .dry_run_submit(&ticket_uuid, &validations, request.branch_id.trim())
+            .await
+    } else {
+        service
+            .submit(
+                &ticket_uuid,
+                request.description.clone(),
+                validations,
+                renames,
+                request.request_id.trim(),
+                request.branch_id.trim(),
+            )
+            .await
+    };
+
+    if !request.dry_run {
+        match &result {
+            Ok(_) => metrics().observe_submit(true, false),
+            Err(failure) => metrics().observe_submit(false, !failure.conflicts.is_empty()),
+        }
+    }
 
+    let mut replayed = false;
     let rsp = match result {
         Ok(success) => {
             let changelist_id = success.changelist_id;
+            replayed = success.replayed;
             log.info(&format!(
-                "submit success: changelist_id={}, latest_revisions={}",
+                "submit success: changelist_id={}, latest_revisions={}, replayed={}",
                 changelist_id,
-                success.latest_revisions.len()
+                success.latest_revisions.len(),
+                replayed
             ));
             SubmitRsp {
                 success: true,
@@ -69,9 +198,15 @@ pub async fn submit(
                         binary_id: r.binary_id,
                         size: r.size,
                         revision_created_at: r.revision_created_at,
+                        content_hash: r.content_hash,
+                        compression_type: r.compression_type.as_str().to_string(),
                     })
                     .collect(),
-                message: format!("submitted by {}", submitting_by),
+                message: if request.dry_run {
+                    format!("dry run by {}", submitting_by)
+                } else {
+                    format!("submitted by {}", submitting_by)
+                },
             }
         }
         Err(failure) => {
@@ -101,5 +236,777 @@ pub async fn submit(
             }
         }
     };
-    Ok(Response::new(rsp))
-}
\ No newline at end of file
+    let mut rsp = Response::new(rsp);
+    if replayed {
+        rsp.extensions_mut().insert(SubmitReplayed);
+    }
+    Ok(rsp)
+}
+#[cfg(test)]
+mod tests {
+    use super::{SubmitReplayed, SubmitSummary, submit};
+    use crate::audit::AuditOutcome;
+    use crate::auth::{AuthService, AuthSource, TokenPolicy, UserContext};
+    use crate::common::depot_path::DepotPath;
+    use crate::config::{entity::ConfigEntity, holder::try_set_config};
+    use crate::database::dao::{Dao, file_seen_on_branches};
+    use crate::hive_server::CrvHiveService;
+    use crate::hive_server::submit::branch_submit_lock_registered;
+    use crate::logging::HiveLog;
+    use crate::pb::hive_service_server::HiveService;
+    use crate::pb::{
+        CancelSubmitContextReq, FileChunk, FileRevision as PbFileRevision, FileToLock,
+        GetSubmitContextReq, LaunchSubmitReq, LockMode, SubmitContextStatus, SubmitReq, SubmitRsp,
+        WatchBranchReq,
+    };
+    use crate::webhook::SubmitNotification;
+    use crv_core::metadata::{BranchDoc, BranchMetadata};
+    use crv_core::repository::{blake3_hash_to_hex, compute_chunk_hash};
+    use std::sync::{Arc, OnceLock};
+    use tokio_stream::StreamExt;
+    use tonic::{Request, Response};
+
+    static TEST_DIR: OnceLock<tempfile::TempDir> = OnceLock::new();
+
+    fn test_service() -> CrvHiveService {
+        TEST_DIR.get_or_init(|| {
+            let dir = tempfile::tempdir().expect("create temp dir");
+            let _ = try_set_config(ConfigEntity {
+                repository_path: dir.path().join("repo").to_string_lossy().into_owned(),
+                upload_cache_path: dir.path().join("cache").to_string_lossy().into_owned(),
+                ..Default::default()
+            });
+            dir
+        });
+        CrvHiveService::new(Arc::new(AuthService::new(
+            b"test-secret",
+            TokenPolicy::default(),
+        )))
+    }
+
+    /// 锁定新文件 `path` 并将内容上传到 cache，返回 ticket 与 chunk hash
+    async fn launch_and_upload(service: &CrvHiveService, path: &str) -> (String, String) {
+        let launch = Request::new(lock_request(path, None));
+        launch_and_upload_with(service, launch, &format!("content of {path}")).await
+    }
+
+    /// 锁定 `path`，`expected` 为已提交文件当前的 (generation, revision)
+    fn lock_request(path: &str, expected: Option<(i64, i64)>) -> LaunchSubmitReq {
+        LaunchSubmitReq {
+            files: vec![FileToLock {
+                path: path.to_string(),
+                expected_file_generation: expected.map(|(generation, _)| generation),
+                expected_file_revision: expected.map(|(_, revision)| revision),
+                mode: LockMode::Write as i32,
+            }],
+            branch_id: String::new(),
+        }
+    }
+
+    async fn launch_and_upload_with(
+        service: &CrvHiveService,
+        launch: Request<LaunchSubmitReq>,
+        data: &str,
+    ) -> (String, String) {
+        let launched = service.launch_submit(launch).await.unwrap().into_inner();
+        assert!(launched.success);
+
+        let chunk_hash = blake3_hash_to_hex(&compute_chunk_hash(data.as_bytes()));
+        crate::hive_server::submit::cache_service()
+            .append_chunk_part(&chunk_hash, 0, data.as_bytes())
+            .unwrap();
+        (launched.ticket, chunk_hash)
+    }
+
+    #[tokio::test]
+    async fn submit_with_same_request_id_is_applied_once() {
+        let dao = crate::test_support::install_mock_dao();
+        let service = test_service();
+
+        let path = format!("//tests/idempotent/{}/a.txt", uuid::Uuid::new_v4());
+        let (ticket, chunk_hash) = launch_and_upload(&service, &path).await;
+
+        let request = SubmitReq {
+            ticket,
+            description: "idempotent".to_string(),
+            file_chunks: vec![FileChunk {
+                path: path.clone(),
+                binary_id: vec![chunk_hash],
+                ..Default::default()
+            }],
+            request_id: uuid::Uuid::new_v4().to_string(),
+            dry_run: false,
+            ..Default::default()
+        };
+        let first = service
+            .submit(Request::new(request.clone()))
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(first.success, "submit failed: {}", first.message);
+
+        // 重试时 ticket 已经被首次提交消耗，仍应直接返回首次提交的结果
+        let second = service
+            .submit(Request::new(request))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(second, first);
+
+        let revisions = dao.list_file_revisions_by_depot_path(&path).await.unwrap();
+        assert_eq!(revisions.len(), 1);
+        assert_eq!(revisions[0].changelist_id, first.changelist_id);
+    }
+
+    #[tokio::test]
+    async fn only_committed_submits_are_audited_and_notified() {
+        crate::test_support::install_mock_dao();
+        let service = test_service();
+        let path = format!("//tests/dry_run/{}/notify.txt", uuid::Uuid::new_v4());
+        let (ticket, chunk_hash) = launch_and_upload(&service, &path).await;
+        let request = SubmitReq {
+            ticket,
+            description: "notify".to_string(),
+            file_chunks: vec![FileChunk {
+                path: path.clone(),
+                binary_id: vec![chunk_hash],
+                ..Default::default()
+            }],
+            dry_run: true,
+            ..Default::default()
+        };
+
+        // 预检通过也不记录审计、不通知 webhook
+        let summary = SubmitSummary::of(&request);
+        let out = submit(HiveLog::new("Submit"), Request::new(request.clone())).await;
+        assert!(out.as_ref().unwrap().get_ref().success);
+        let (event, notification) = summary.events("alice", &out);
+        assert!(event.is_none());
+        assert!(notification.is_none());
+
+        // 真正的提交记录成功的审计，并带着提交的分支通知 webhook
+        let committed = SubmitRsp {
+            success: true,
+            changelist_id: 7,
+            committed_at: 100,
+            latest_revisions: vec![PbFileRevision::default()],
+            ..Default::default()
+        };
+        let summary = SubmitSummary::of(&SubmitReq {
+            branch_id: " release ".to_string(),
+            dry_run: false,
+            ..request
+        });
+        let (event, notification) = summary.events("alice", &Ok(Response::new(committed.clone())));
+        let event = event.unwrap();
+        assert_eq!(event.outcome, AuditOutcome::Success);
+        assert_eq!(event.resource_id, "7");
+        assert_eq!(
+            notification,
+            Some(SubmitNotification {
+                branch_id: "release".to_string(),
+                changelist_id: 7,
+                author: "alice".to_string(),
+                files_count: 1,
+                committed_at: 100,
+            })
+        );
+
+        // 按 request_id 重放的结果已经在首次提交时记录过
+        let mut replayed = Response::new(committed);
+        replayed.extensions_mut().insert(SubmitReplayed);
+        let (event, notification) = summary.events("alice", &Ok(replayed));
+        assert!(event.is_none());
+        assert!(notification.is_none());
+
+        // 提交失败只记录审计
+        let failed = SubmitRsp {
+            success: false,
+            message: "conflict".to_string(),
+            ..Default::default()
+        };
+        let (event, notification) = summary.events("alice", &Ok(Response::new(failed)));
+        assert_eq!(event.unwrap().outcome, AuditOutcome::Failure);
+        assert!(notification.is_none());
+    }
+
+    #[tokio::test]
+    async fn dry_run_submit_checks_without_writing() {
+        let dao = crate::test_support::install_mock_dao();
+        let service = test_service();
+
+        let path = format!("//tests/dry_run/{}/a.txt", uuid::Uuid::new_v4());
+        let (ticket, chunk_hash) = launch_and_upload(&service, &path).await;
+        let request = SubmitReq {
+            ticket,
+            description: "dry run".to_string(),
+            file_chunks: vec![FileChunk {
+                path: path.clone(),
+                binary_id: vec![chunk_hash.clone()],
+                ..Default::default()
+            }],
+            request_id: uuid::Uuid::new_v4().to_string(),
+            dry_run: true,
+            ..Default::default()
+        };
+        let rsp = service
+            .submit(Request::new(request.clone()))
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(rsp.success, "dry run failed: {}", rsp.message);
+        assert_eq!(rsp.changelist_id, 0);
+        assert!(rsp.latest_revisions.is_empty());
+
+        assert!(
+            dao.list_file_revisions_by_depot_path(&path)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+        // chunk 仍只在 cache 中，没有写入 repository
+        let content = format!("content of {path}");
+        assert!(
+            crate::hive_server::repository_manager()
+                .unwrap()
+                .locate_chunk(&compute_chunk_hash(content.as_bytes()))
+                .unwrap()
+                .is_none()
+        );
+        // 预检后 ticket 失效，锁已释放
+        assert!(
+            crate::hive_server::submit::submit_service()
+                .list_locked_files()
+                .iter()
+                .all(|(locked, _, _)| locked.to_string() != path)
+        );
+        let rsp = service
+            .submit(Request::new(SubmitReq {
+                dry_run: false,
+                ..request
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(!rsp.success);
+
+        // 缺少 chunk 时返回 missing_chunks，同样不写入数据
+        let path = format!("//tests/dry_run/{}/b.txt", uuid::Uuid::new_v4());
+        let (ticket, _) = launch_and_upload(&service, &path).await;
+        let rsp = service
+            .submit(Request::new(SubmitReq {
+                ticket,
+                file_chunks: vec![FileChunk {
+                    path: path.clone(),
+                    binary_id: vec!["0".repeat(64)],
+                    ..Default::default()
+                }],
+                dry_run: true,
+                ..Default::default()
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(!rsp.success);
+        assert_eq!(rsp.missing_chunks, vec!["0".repeat(64)]);
+        assert!(
+            dao.list_file_revisions_by_depot_path(&path)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[tokio::test]
+    async fn submit_records_author_and_branches_on_file() {
+        let dao = crate::test_support::install_mock_dao();
+        let service = test_service();
+        let branch_id = format!("release-{}", uuid::Uuid::new_v4());
+        dao.insert_branch(&BranchDoc {
+            id: branch_id.clone(),
+            created_at: 0,
+            created_by: "alice".to_string(),
+            head_changelist_id: 0,
+            is_protected: false,
+            metadata: BranchMetadata {
+                description: "release".to_string(),
+                owners: vec![],
+                description_regex: None,
+            },
+        })
+        .await
+        .unwrap();
+
+        let path = format!("//tests/branches/{}/a.txt", uuid::Uuid::new_v4());
+        let alice = UserContext {
+            username: "alice".to_string(),
+            scopes: vec![],
+            source: AuthSource::Jwt,
+        };
+
+        // 同一分支重复提交时不会重复记录；文件在各分支上的版本相互独立
+        let mut expected = std::collections::HashMap::new();
+        for branch in ["", branch_id.as_str(), branch_id.as_str()] {
+            let expected_on_branch = expected.get(branch).copied();
+            let mut launch = Request::new(lock_request(&path, expected_on_branch));
+            launch.get_mut().branch_id = branch.to_string();
+            launch.extensions_mut().insert(alice.clone());
+            let data = format!("content of {path} on '{branch}' after {expected_on_branch:?}");
+            let (ticket, chunk_hash) = launch_and_upload_with(&service, launch, &data).await;
+            let mut request = Request::new(SubmitReq {
+                ticket,
+                description: "branches".to_string(),
+                file_chunks: vec![FileChunk {
+                    path: path.clone(),
+                    binary_id: vec![chunk_hash],
+                    ..Default::default()
+                }],
+                branch_id: branch.to_string(),
+                ..Default::default()
+            });
+            request.extensions_mut().insert(alice.clone());
+            let rsp = service.submit(request).await.unwrap().into_inner();
+            assert!(rsp.success, "submit failed: {}", rsp.message);
+            assert_eq!(rsp.message, "submitted by alice");
+            let latest = &rsp.latest_revisions[0];
+            expected.insert(branch, (latest.generation, latest.revision));
+        }
+
+        let file = dao.find_file_by_depot_path(&path).await.unwrap().unwrap();
+        assert_eq!(file.metadata["first_introduced_by"], "alice");
+        assert_eq!(
+            file_seen_on_branches(&file.metadata),
+            vec![String::new(), branch_id]
+        );
+    }
+
+    #[tokio::test]
+    async fn interleaved_submits_keep_branch_trees_apart() {
+        let dao = crate::test_support::install_mock_dao();
+        let service = test_service();
+        let run = uuid::Uuid::new_v4();
+        let branches = [format!("main-{run}"), format!("dev-{run}")];
+        for branch_id in &branches {
+            dao.insert_branch(&BranchDoc {
+                id: branch_id.clone(),
+                created_at: 0,
+                created_by: "alice".to_string(),
+                head_changelist_id: 0,
+                is_protected: false,
+                metadata: BranchMetadata {
+                    description: branch_id.clone(),
+                    owners: vec![],
+                    description_regex: None,
+                },
+            })
+            .await
+            .unwrap();
+        }
+        let dir = format!("//tests/interleaved/{run}/");
+        let (main, dev) = (branches[0].as_str(), branches[1].as_str());
+
+        // 两个分支交替提交，各分支只应看到自己的提交
+        let mut expected = std::collections::HashMap::new();
+        let mut trees: std::collections::HashMap<&str, std::collections::BTreeMap<String, String>> =
+            std::collections::HashMap::new();
+        for (branch, name) in [
+            (main, "shared.txt"),
+            (dev, "shared.txt"),
+            (main, "main.txt"),
+            (dev, "dev.txt"),
+            (main, "shared.txt"),
+        ] {
+            let path = format!("{dir}{name}");
+            let expected_on_branch = expected.get(&(branch, name)).copied();
+            let mut launch = Request::new(lock_request(&path, expected_on_branch));
+            launch.get_mut().branch_id = branch.to_string();
+            let data = format!("{name} on {branch} after {expected_on_branch:?}");
+            let (ticket, chunk_hash) = launch_and_upload_with(&service, launch, &data).await;
+            let rsp = service
+                .submit(Request::new(SubmitReq {
+                    ticket,
+                    description: "interleaved".to_string(),
+                    file_chunks: vec![FileChunk {
+                        path: path.clone(),
+                        binary_id: vec![chunk_hash.clone()],
+                        ..Default::default()
+                    }],
+                    branch_id: branch.to_string(),
+                    ..Default::default()
+                }))
+                .await
+                .unwrap()
+                .into_inner();
+            assert!(rsp.success, "submit failed: {}", rsp.message);
+            let latest = &rsp.latest_revisions[0];
+            expected.insert((branch, name), (latest.generation, latest.revision));
+            trees.entry(branch).or_default().insert(path, chunk_hash);
+        }
+
+        for branch in [main, dev] {
+            let head = dao.find_branch_head(branch).await.unwrap().unwrap();
+            let tree: std::collections::BTreeMap<String, String> = dao
+                .list_file_tree_revisions(&DepotPath::parse(&dir).unwrap(), head)
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|r| {
+                    let path = r.to_depot_path_string().unwrap();
+                    let chunks: Vec<String> = serde_json::from_value(r.binary_id).unwrap();
+                    (path, chunks[0].clone())
+                })
+                .collect();
+            assert_eq!(tree, trees[branch], "tree of {branch}");
+        }
+    }
+
+    #[tokio::test]
+    async fn direct_submit_to_protected_branch_is_rejected() {
+        let dao = crate::test_support::install_mock_dao();
+        let service = test_service();
+        let branch_id = format!("protected-{}", uuid::Uuid::new_v4());
+        dao.insert_branch(&BranchDoc {
+            id: branch_id.clone(),
+            created_at: 0,
+            created_by: "alice".to_string(),
+            head_changelist_id: 0,
+            is_protected: true,
+            metadata: BranchMetadata {
+                description: "protected".to_string(),
+                owners: vec![],
+                description_regex: None,
+            },
+        })
+        .await
+        .unwrap();
+
+        let path = format!("//tests/protected/{}/a.txt", uuid::Uuid::new_v4());
+        let (ticket, chunk_hash) = launch_and_upload(&service, &path).await;
+        let request = SubmitReq {
+            ticket,
+            description: "direct".to_string(),
+            file_chunks: vec![FileChunk {
+                path: path.clone(),
+                binary_id: vec![chunk_hash],
+                ..Default::default()
+            }],
+            branch_id: branch_id.clone(),
+            ..Default::default()
+        };
+        let err = service
+            .submit(Request::new(request.clone()))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), tonic::Code::PermissionDenied);
+        assert!(
+            dao.list_file_revisions_by_depot_path(&path)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+
+        // 取消保护后，同一个 ticket 仍然可以提交
+        dao.set_branch_protection(&branch_id, false).await.unwrap();
+        let rsp = service
+            .submit(Request::new(request))
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(rsp.success, "submit failed: {}", rsp.message);
+    }
+
+    #[tokio::test]
+    async fn branch_submit_locks_are_independent_per_branch() {
+        let branch_a = format!("lock-a-{}", uuid::Uuid::new_v4());
+        let branch_b = format!("lock-b-{}", uuid::Uuid::new_v4());
+
+        let lock_a = crate::hive_server::submit::branch_submit_lock(&branch_a);
+        let guard = lock_a.lock().await;
+        // 同一分支复用同一把锁，其他分支不受影响
+        assert!(
+            crate::hive_server::submit::branch_submit_lock(&branch_a)
+                .try_lock()
+                .is_err()
+        );
+        assert!(
+            crate::hive_server::submit::branch_submit_lock(&branch_b)
+                .try_lock()
+                .is_ok()
+        );
+
+        // 没有句柄的分支锁会从全局表中移除
+        assert!(!branch_submit_lock_registered(&branch_b));
+        assert!(branch_submit_lock_registered(&branch_a));
+        drop(guard);
+        drop(lock_a);
+        assert!(!branch_submit_lock_registered(&branch_a));
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn concurrent_submits_to_two_branches() {
+        let dao = crate::test_support::install_mock_dao();
+        let service = Arc::new(test_service());
+        let run = uuid::Uuid::new_v4();
+        let branches = [format!("main-{run}"), format!("release-{run}")];
+        for branch_id in &branches {
+            dao.insert_branch(&BranchDoc {
+                id: branch_id.clone(),
+                created_at: 0,
+                created_by: "alice".to_string(),
+                head_changelist_id: 0,
+                is_protected: false,
+                metadata: BranchMetadata {
+                    description: branch_id.clone(),
+                    owners: vec![],
+                    description_regex: None,
+                },
+            })
+            .await
+            .unwrap();
+        }
+        let mut events = crate::hive_server::branch::watch_branch::branch_events()
+            .subscribe(usize::MAX)
+            .unwrap();
+
+        let mut tasks = Vec::new();
+        for branch_id in &branches {
+            for i in 0..10 {
+                let service = service.clone();
+                let branch_id = branch_id.clone();
+                tasks.push(tokio::spawn(async move {
+                    let path = format!("//tests/concurrent/{run}/{branch_id}/{i}.txt");
+                    let (ticket, chunk_hash) = launch_and_upload(&service, &path).await;
+                    let rsp = service
+                        .submit(Request::new(SubmitReq {
+                            ticket,
+                            description: "concurrent".to_string(),
+                            file_chunks: vec![FileChunk {
+                                path: path.clone(),
+                                binary_id: vec![chunk_hash],
+                                ..Default::default()
+                            }],
+                            branch_id: branch_id.clone(),
+                            ..Default::default()
+                        }))
+                        .await
+                        .unwrap()
+                        .into_inner();
+                    assert!(rsp.success, "submit failed: {}", rsp.message);
+                    (branch_id, path, rsp.changelist_id)
+                }));
+            }
+        }
+
+        let results = tokio::time::timeout(
+            std::time::Duration::from_secs(60),
+            futures::future::join_all(tasks),
+        )
+        .await
+        .expect("concurrent submits deadlocked");
+
+        let mut changelist_ids = std::collections::HashSet::new();
+        for result in results {
+            let (branch_id, path, changelist_id) = result.unwrap();
+            assert!(changelist_ids.insert(changelist_id));
+            // 每个文件都记录在本次提交的 changelist 与分支上
+            let revisions = dao.list_file_revisions_by_depot_path(&path).await.unwrap();
+            assert_eq!(revisions.len(), 1);
+            assert_eq!(revisions[0].changelist_id, changelist_id);
+            let file = dao.find_file_by_depot_path(&path).await.unwrap().unwrap();
+            assert_eq!(file_seen_on_branches(&file.metadata), vec![branch_id]);
+        }
+        assert_eq!(changelist_ids.len(), 20);
+
+        // 同一分支上的提交串行执行：事件按 changelist 递增的顺序发布，HEAD 停在最后一次提交
+        let mut published: std::collections::HashMap<String, Vec<i64>> =
+            std::collections::HashMap::new();
+        while let Ok((branch_id, event)) = events.try_recv() {
+            if branches.contains(&branch_id) {
+                published
+                    .entry(branch_id)
+                    .or_default()
+                    .push(event.changelist_id);
+            }
+        }
+        for branch_id in &branches {
+            let ids = &published[branch_id];
+            assert_eq!(ids.len(), 10);
+            assert!(ids.windows(2).all(|w| w[0] < w[1]), "{branch_id}: {ids:?}");
+            let branch = dao.find_branch_by_id(branch_id).await.unwrap().unwrap();
+            assert_eq!(branch.head_changelist_id, *ids.last().unwrap());
+            assert!(!branch_submit_lock_registered(branch_id));
+        }
+    }
+
+    #[tokio::test]
+    async fn watch_branch_receives_concurrent_submit() {
+        let dao = crate::test_support::install_mock_dao();
+        let service = Arc::new(test_service());
+        let branch_id = format!("watched-{}", uuid::Uuid::new_v4());
+        dao.insert_branch(&BranchDoc {
+            id: branch_id.clone(),
+            created_at: 0,
+            created_by: "alice".to_string(),
+            head_changelist_id: 0,
+            is_protected: false,
+            metadata: BranchMetadata {
+                description: branch_id.clone(),
+                owners: vec![],
+                description_regex: None,
+            },
+        })
+        .await
+        .unwrap();
+
+        let mut watch = Request::new(WatchBranchReq {
+            branch_id: branch_id.clone(),
+        });
+        watch.extensions_mut().insert(UserContext {
+            username: "ci".to_string(),
+            scopes: vec![],
+            source: AuthSource::Jwt,
+        });
+        let mut stream = service.watch_branch(watch).await.unwrap().into_inner();
+
+        let submitter = service.clone();
+        let submitted_branch = branch_id.clone();
+        let submit = tokio::spawn(async move {
+            let path = format!("//tests/watch/{submitted_branch}/a.txt");
+            let (ticket, chunk_hash) = launch_and_upload(&submitter, &path).await;
+            let rsp = submitter
+                .submit(Request::new(SubmitReq {
+                    ticket,
+                    description: "watched".to_string(),
+                    file_chunks: vec![FileChunk {
+                        path,
+                        binary_id: vec![chunk_hash],
+                        ..Default::default()
+                    }],
+                    branch_id: submitted_branch,
+                    ..Default::default()
+                }))
+                .await
+                .unwrap()
+                .into_inner();
+            assert!(rsp.success, "submit failed: {}", rsp.message);
+            rsp
+        });
+
+        let event = tokio::time::timeout(std::time::Duration::from_secs(10), stream.next())
+            .await
+            .expect("no branch event received")
+            .expect("watch stream ended")
+            .expect("watch stream error");
+        let rsp = submit.await.unwrap();
+        assert_eq!(event.changelist_id, rsp.changelist_id);
+        assert_eq!(event.committed_at, rsp.committed_at);
+        assert_eq!(event.files_count, 1);
+        assert!(!event.author.is_empty());
+        let branch = dao.find_branch_by_id(&branch_id).await.unwrap().unwrap();
+        assert_eq!(branch.head_changelist_id, rsp.changelist_id);
+    }
+
+    #[tokio::test]
+    async fn submit_context_follows_submit_lifecycle() {
+        let _dao = crate::test_support::install_mock_dao();
+        let service = test_service();
+        let path = format!("//tests/submit_context/{}/a.txt", uuid::Uuid::new_v4());
+        let (ticket, chunk_hash) = launch_and_upload(&service, &path).await;
+
+        let context = |ticket: String| {
+            let service = &service;
+            async move {
+                service
+                    .get_submit_context(Request::new(GetSubmitContextReq { ticket }))
+                    .await
+                    .unwrap()
+                    .into_inner()
+                    .context
+                    .unwrap()
+            }
+        };
+        let launched = context(ticket.clone()).await;
+        assert_eq!(launched.status(), SubmitContextStatus::Launched);
+        assert_eq!(launched.files.len(), 1);
+        assert_eq!(launched.files[0].path, path);
+        assert!(launched.branch_id.is_empty());
+
+        let rsp = service
+            .submit(Request::new(SubmitReq {
+                ticket: ticket.clone(),
+                description: "context".to_string(),
+                file_chunks: vec![FileChunk {
+                    path,
+                    binary_id: vec![chunk_hash],
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(rsp.success, "submit failed: {}", rsp.message);
+
+        let committed = context(ticket.clone()).await;
+        assert_eq!(committed.status(), SubmitContextStatus::Committed);
+        assert_eq!(committed.started_at, launched.started_at);
+
+        // 已提交的 ticket 不能取消
+        let err = service
+            .cancel_submit_context(Request::new(CancelSubmitContextReq { ticket }))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), tonic::Code::FailedPrecondition);
+    }
+
+    /// 从 `/metrics` 的输出中读取 `hive_submit_total{result="success"}`
+    async fn scrape_submit_success(addr: std::net::SocketAddr) -> u64 {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(b"GET /metrics HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+            .await
+            .unwrap();
+        let mut body = String::new();
+        stream.read_to_string(&mut body).await.unwrap();
+        assert!(body.starts_with("HTTP/1.1 200"), "{body}");
+        assert!(body.contains("# TYPE hive_active_file_locks gauge"));
+
+        body.lines()
+            .find_map(|line| line.strip_prefix("hive_submit_total{result=\"success\"} "))
+            .map_or(0, |count| count.parse().unwrap())
+    }
+
+    #[tokio::test]
+    async fn successful_submit_is_reported_in_metrics() {
+        let _dao = crate::test_support::install_mock_dao();
+        let service = test_service();
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(crate::metrics::serve(listener));
+
+        // 计数器在进程内全局共享，并发运行的其他测试也会提交，只检查本次提交被计入
+        let before = scrape_submit_success(addr).await;
+        let path = format!("//tests/metrics/{}/a.txt", uuid::Uuid::new_v4());
+        let (ticket, chunk_hash) = launch_and_upload(&service, &path).await;
+        let rsp = service
+            .submit(Request::new(SubmitReq {
+                ticket,
+                description: "metrics".to_string(),
+                file_chunks: vec![FileChunk {
+                    path,
+                    binary_id: vec![chunk_hash],
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(rsp.success, "submit failed: {}", rsp.message);
+
+        let after = scrape_submit_success(addr).await;
+        assert!(after > before, "before={before}, after={after}");
+    }
+}
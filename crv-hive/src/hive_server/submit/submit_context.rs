@@ -0,0 +1,278 @@
+use crate::auth::require_user;
+use crate::database::dao::{Dao, SubmitContextStatus, SubmitTicketRecord, dao, read_dao};
+use crate::hive_server::submit::service::SubmitService;
+use crate::hive_server::submit::{submit_service, submitting_user};
+use crate::logging::HiveLog;
+use crate::pb::{
+    self, CancelSubmitContextReq, CancelSubmitContextRsp, GetSubmitContextReq, GetSubmitContextRsp,
+    LockMode, SubmitContextFile,
+};
+use crv_core::tree::depot_tree;
+use tonic::{Request, Response, Status};
+
+pub async fn handle_get_submit_context(
+    log: HiveLog,
+    r: Request<GetSubmitContextReq>,
+) -> Result<Response<GetSubmitContextRsp>, Status> {
+    let username = submitting_user(&r);
+    let log = log.with_user(&username);
+    let _g = log.enter();
+
+    let request = r.into_inner();
+    log.info(&format!(
+        "get_submit_context received: ticket={}",
+        request.ticket
+    ));
+
+    let context = find_submit_context(read_dao().as_ref(), &request.ticket).await?;
+    log.info(&format!(
+        "submit context status: {}",
+        context.status.as_str()
+    ));
+    Ok(Response::new(GetSubmitContextRsp {
+        context: Some(to_pb(context)),
+    }))
+}
+
+pub async fn handle_cancel_submit_context(
+    log: HiveLog,
+    r: Request<CancelSubmitContextReq>,
+) -> Result<Response<CancelSubmitContextRsp>, Status> {
+    let username = submitting_user(&r);
+    let is_admin = require_user(&r).is_ok_and(|user| user.is_admin());
+    let log = log.with_user(&username);
+    let _g = log.enter();
+
+    let request = r.into_inner();
+    log.info(&format!(
+        "cancel_submit_context received: ticket={}",
+        request.ticket
+    ));
+
+    let rsp = cancel_submit_context(
+        dao().as_ref(),
+        submit_service(),
+        &username,
+        is_admin,
+        &request.ticket,
+    )
+    .await?;
+    Ok(Response::new(rsp))
+}
+
+/// 查找尚未过期的提交记录，不存在或已过期时返回 `NotFound`。
+async fn find_submit_context(dao: &dyn Dao, ticket: &str) -> Result<SubmitTicketRecord, Status> {
+    let now = chrono::Utc::now().timestamp();
+    dao.find_submit_ticket(ticket.trim())
+        .await
+        .map_err(|e| Status::internal(format!("database error while finding ticket: {e}")))?
+        .filter(|record| record.expires_at > now)
+        .ok_or_else(|| Status::not_found(format!("ticket {ticket} not found")))
+}
+
+/// 取消尚未提交的 ticket 并释放其持有的锁，仅提交者本人或管理员可用。
+pub(crate) async fn cancel_submit_context(
+    dao: &dyn Dao,
+    service: &SubmitService,
+    username: &str,
+    is_admin: bool,
+    ticket: &str,
+) -> Result<CancelSubmitContextRsp, Status> {
+    let mut context = find_submit_context(dao, ticket).await?;
+    if context.submitting_by != username && !is_admin {
+        return Err(Status::permission_denied(format!(
+            "user '{username}' is not the submitter of ticket {}",
+            context.ticket
+        )));
+    }
+    // 提交过程中释放锁会让提交写入未加锁的文件
+    if context.status != SubmitContextStatus::Launched {
+        return Err(Status::failed_precondition(format!(
+            "ticket {} can not be cancelled: {}",
+            context.ticket,
+            context.status.as_str()
+        )));
+    }
+
+    let uuid = uuid::Uuid::parse_str(&context.ticket)
+        .map_err(|e| Status::invalid_argument(format!("invalid ticket format: {e}")))?;
+    if !service.cancel_ticket(&uuid).await {
+        return Err(Status::failed_precondition(format!(
+            "ticket {} has expired",
+            context.ticket
+        )));
+    }
+    context.status = SubmitContextStatus::Cancelled;
+    Ok(CancelSubmitContextRsp {
+        context: Some(to_pb(context)),
+    })
+}
+
+fn to_pb(record: SubmitTicketRecord) -> pb::SubmitContext {
+    pb::SubmitContext {
+        status: match record.status {
+            SubmitContextStatus::Launched => pb::SubmitContextStatus::Launched,
+            SubmitContextStatus::Submitting => pb::SubmitContextStatus::Submitting,
+            SubmitContextStatus::Committed => pb::SubmitContextStatus::Committed,
+            SubmitContextStatus::Failed => pb::SubmitContextStatus::Failed,
+            SubmitContextStatus::Cancelled => pb::SubmitContextStatus::Cancelled,
+        } as i32,
+        ticket: record.ticket,
+        submitting_by: record.submitting_by,
+        branch_id: record.branch_id,
+        files: record
+            .files
+            .into_iter()
+            .map(|f| SubmitContextFile {
+                path: f.depot_path,
+                mode: match f.mode {
+                    depot_tree::LockMode::Read => LockMode::Read,
+                    depot_tree::LockMode::Write => LockMode::Write,
+                } as i32,
+                locked_generation: f.locked_generation,
+                locked_revision: f.locked_revision,
+            })
+            .collect(),
+        started_at: record.started_at,
+        expires_at: record.expires_at,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::depot_path::DepotPath;
+    use crate::hive_server::submit::service::LockedFile;
+    use std::collections::HashMap;
+    use tonic::Code;
+
+    async fn launch(service: &SubmitService, path: &str, user: &str) -> String {
+        let files = vec![LockedFile {
+            path: DepotPath::new(path).unwrap(),
+            locked_generation: None,
+            locked_revision: None,
+            mode: depot_tree::LockMode::Write,
+        }];
+        service
+            .launch_submit("", &files, user.to_string(), chrono::Duration::minutes(10))
+            .await
+            .expect("lock new file")
+            .ticket
+            .to_string()
+    }
+
+    fn is_locked(service: &SubmitService, path: &str) -> bool {
+        service
+            .list_locked_files()
+            .iter()
+            .any(|(p, _, _)| p.to_string() == path)
+    }
+
+    #[tokio::test]
+    async fn cancel_releases_locks_and_records_status() {
+        let dao = crate::test_support::install_mock_dao();
+        let service = SubmitService::new();
+        let path = format!("//tests/cancel/{}.txt", uuid::Uuid::new_v4());
+        let ticket = launch(&service, &path, "alice").await;
+
+        let context = find_submit_context(dao.as_ref(), &ticket).await.unwrap();
+        assert_eq!(context.status, SubmitContextStatus::Launched);
+        assert_eq!(context.submitting_by, "alice");
+        assert_eq!(context.files.len(), 1);
+        assert_eq!(context.files[0].depot_path, path);
+        assert!(context.started_at > 0 && context.started_at < context.expires_at);
+
+        let err = cancel_submit_context(dao.as_ref(), &service, "bob", false, &ticket)
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::PermissionDenied);
+        assert!(is_locked(&service, &path));
+
+        let rsp = cancel_submit_context(dao.as_ref(), &service, "alice", false, &ticket)
+            .await
+            .unwrap();
+        assert_eq!(
+            rsp.context.unwrap().status(),
+            pb::SubmitContextStatus::Cancelled
+        );
+        assert!(!is_locked(&service, &path));
+        assert_eq!(
+            find_submit_context(dao.as_ref(), &ticket)
+                .await
+                .unwrap()
+                .status,
+            SubmitContextStatus::Cancelled
+        );
+
+        // 取消后的 ticket 不能再提交，也不会在重启时恢复
+        let validations = HashMap::from([(DepotPath::new(&path).unwrap(), Vec::new())]);
+        let failure = service
+            .submit(
+                &uuid::Uuid::parse_str(&ticket).unwrap(),
+                "cancelled".to_string(),
+                validations,
+                HashMap::new(),
+                "",
+                "",
+            )
+            .await
+            .unwrap_err();
+        assert!(failure.context_not_found);
+        let restarted = SubmitService::new();
+        restarted.restore().await.unwrap();
+        assert!(!is_locked(&restarted, &path));
+
+        let err = cancel_submit_context(dao.as_ref(), &service, "alice", false, &ticket)
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::FailedPrecondition);
+    }
+
+    #[tokio::test]
+    async fn admin_can_cancel_and_unknown_ticket_is_not_found() {
+        let dao = crate::test_support::install_mock_dao();
+        let service = SubmitService::new();
+        let path = format!("//tests/cancel/{}.txt", uuid::Uuid::new_v4());
+        let ticket = launch(&service, &path, "alice").await;
+
+        cancel_submit_context(dao.as_ref(), &service, "root", true, &ticket)
+            .await
+            .unwrap();
+        assert!(!is_locked(&service, &path));
+
+        let unknown = uuid::Uuid::new_v4().to_string();
+        let err = find_submit_context(dao.as_ref(), &unknown)
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::NotFound);
+        let err = cancel_submit_context(dao.as_ref(), &service, "alice", false, &unknown)
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::NotFound);
+    }
+
+    #[tokio::test]
+    async fn failed_check_keeps_ticket_launched() {
+        let dao = crate::test_support::install_mock_dao();
+        let service = SubmitService::new();
+        let path = format!("//tests/cancel/{}.txt", uuid::Uuid::new_v4());
+        let ticket = launch(&service, &path, "alice").await;
+
+        // 没有为锁定的文件提供 validations，检查不通过，ticket 仍然有效
+        service
+            .submit(
+                &uuid::Uuid::parse_str(&ticket).unwrap(),
+                "incomplete".to_string(),
+                HashMap::new(),
+                HashMap::new(),
+                "",
+                "main",
+            )
+            .await
+            .unwrap_err();
+        let context = find_submit_context(dao.as_ref(), &ticket).await.unwrap();
+        assert_eq!(context.status, SubmitContextStatus::Launched);
+        assert_eq!(context.branch_id, "main");
+        assert!(is_locked(&service, &path));
+    }
+}
@@ -1,8 +1,9 @@
 use tonic::{Request, Response, Status};
 
 use crate::{
-    hive_server::submit::{submit_service, cache_service, submit::UploadFileChunkStream},
+    hive_server::submit::{cache_service, submit::UploadFileChunkStream, submit_service},
     logging::HiveLog,
+    metrics::metrics,
     pb::{UploadFileChunkReq, UploadFileChunkRsp},
 };
 
@@ -12,8 +13,8 @@ where
 {
     use std::collections::HashSet;
     use tokio::sync::mpsc;
-    use tokio_stream::wrappers::ReceiverStream;
     use tokio_stream::StreamExt;
+    use tokio_stream::wrappers::ReceiverStream;
 
     let (tx, rx) = mpsc::channel::<Result<UploadFileChunkRsp, Status>>(32);
 
@@ -141,8 +142,10 @@ where
             ) {
                 Ok(result) => {
                     use crate::hive_server::submit::service::UploadFileChunkResult;
+                    metrics().add_uploaded_bytes(item.content.len());
                     match result {
                         UploadFileChunkResult::FileUploadFinished => {
+                            metrics().inc_chunk_uploads();
                             log.info(&format!("chunk upload finished: {}", item.chunk_hash));
                             responded_chunks.insert(item.chunk_hash.clone());
 
@@ -267,7 +270,7 @@ mod tests {
         let input = tokio_stream::iter(vec![Ok(req)]);
         let mut stream = spawn_upload_file_chunk_handler(log, input);
         let mut responses = Vec::new();
-        
+
         while let Some(result) = stream.next().await {
             match result {
                 Ok(rsp) => responses.push(rsp),
@@ -286,7 +289,8 @@ mod tests {
     async fn test_upload_multiple_chunks_success() {
         let _g = test_mutex().lock().await;
         init_test_globals();
-        let log = crate::logging::HiveLog::new("UploadFileChunk(test_upload_multiple_chunks_success)");
+        let log =
+            crate::logging::HiveLog::new("UploadFileChunk(test_upload_multiple_chunks_success)");
         let ticket = uuid::Uuid::new_v4();
         ensure_ticket(ticket);
 
@@ -321,7 +325,7 @@ mod tests {
         let input = tokio_stream::iter(reqs.into_iter().map(Ok));
         let mut stream = spawn_upload_file_chunk_handler(log, input);
         let mut responses = Vec::new();
-        
+
         while let Some(result) = stream.next().await {
             match result {
                 Ok(rsp) => responses.push(rsp),
@@ -353,7 +357,7 @@ mod tests {
         let input = tokio_stream::iter(vec![Ok(req)]);
         let mut stream = spawn_upload_file_chunk_handler(log, input);
         let result = stream.next().await;
-        
+
         assert!(result.is_some());
         match result.unwrap() {
             Ok(_) => panic!("should return error for invalid ticket"),
@@ -410,7 +414,7 @@ mod tests {
         let mut stream = spawn_upload_file_chunk_handler(log, input);
         let mut responses: Vec<UploadFileChunkRsp> = Vec::new();
         let mut errors: Vec<tonic::Status> = Vec::new();
-        
+
         // 收集所有响应和错误
         while let Some(result) = stream.next().await {
             match result {
@@ -421,7 +425,7 @@ mod tests {
                 }
             }
         }
-        
+
         // 应该有一个错误（chunks_amount 不匹配）
         assert!(!errors.is_empty() || responses.iter().any(|r| !r.success));
         if let Some(err) = errors.first() {
@@ -527,7 +531,7 @@ mod tests {
         let input = tokio_stream::iter(vec![Ok(req)]);
         let mut stream = spawn_upload_file_chunk_handler(log, input);
         let result = stream.next().await;
-        
+
         assert!(result.is_some());
         match result.unwrap() {
             Ok(rsp) => {
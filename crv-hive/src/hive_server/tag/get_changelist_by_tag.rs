@@ -0,0 +1,201 @@
+use crate::database::dao::{Dao, changelist_labels, read_dao};
+use crate::hive_server::tag::normalize_label;
+use crate::logging::HiveLog;
+use crate::pb::{GetChangelistByTagReq, GetChangelistByTagRsp, TaggedChangelist};
+use tonic::{Request, Response, Status};
+
+/// 未指定 limit 时最多返回的条数
+const DEFAULT_LIMIT: u32 = 100;
+/// 单次查询最多返回的条数
+const MAX_LIMIT: u32 = 1000;
+
+pub async fn handle_get_changelist_by_tag(
+    log: HiveLog,
+    r: Request<GetChangelistByTagReq>,
+) -> Result<Response<GetChangelistByTagRsp>, Status> {
+    let _g = log.enter();
+
+    let request = r.into_inner();
+    log.info(&format!(
+        "get_changelist_by_tag received: branch={}, label={}, limit={}",
+        request.branch_id, request.label, request.limit
+    ));
+
+    let rsp = get_changelist_by_tag(read_dao().as_ref(), request).await?;
+    Ok(Response::new(rsp))
+}
+
+/// 查询带有指定标签的 changelist，指定分支时只返回该分支 HEAD 及之前的 changelist。
+pub(crate) async fn get_changelist_by_tag(
+    dao: &dyn Dao,
+    request: GetChangelistByTagReq,
+) -> Result<GetChangelistByTagRsp, Status> {
+    let label = normalize_label(&request.label)?;
+    let branch_id = request.branch_id.trim();
+    if !branch_id.is_empty() {
+        dao.find_branch_by_id(branch_id)
+            .await
+            .map_err(|e| Status::internal(format!("database error while finding branch: {e}")))?
+            .ok_or_else(|| Status::not_found(format!("branch '{branch_id}' not found")))?;
+    }
+
+    let limit = match request.limit {
+        0 => DEFAULT_LIMIT,
+        limit => limit.min(MAX_LIMIT),
+    } as u64;
+    let changelists = dao
+        .find_changelists_by_label(branch_id, label, limit)
+        .await
+        .map_err(|e| Status::internal(format!("database error while querying changelists: {e}")))?;
+
+    Ok(GetChangelistByTagRsp {
+        changelists: changelists
+            .into_iter()
+            .map(|cl| TaggedChangelist {
+                changelist_id: cl.id,
+                author: cl.author,
+                description: cl.description,
+                committed_at: cl.committed_at,
+                labels: changelist_labels(&cl.metadata),
+            })
+            .collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::dao::MockDao;
+    use crate::hive_server::tag::tag_changelist::tag_changelist;
+    use crate::hive_server::tag::untag_changelist::untag_changelist;
+    use crate::pb::{TagChangelistReq, UntagChangelistReq};
+    use crv_core::metadata::{BranchDoc, BranchMetadata};
+    use tonic::Code;
+
+    async fn dao_with_changelists(count: i64) -> MockDao {
+        let dao = MockDao::default();
+        for i in 1..=count {
+            dao.insert_changelist("alice", &format!("cl {i}"), i, serde_json::json!({}))
+                .await
+                .unwrap();
+        }
+        dao
+    }
+
+    async fn tag(dao: &MockDao, changelist_id: i64, label: &str) -> Result<(), Status> {
+        tag_changelist(
+            dao,
+            TagChangelistReq {
+                changelist_id,
+                label: label.to_string(),
+            },
+        )
+        .await
+        .map(|_| ())
+    }
+
+    async fn query(dao: &MockDao, branch_id: &str, label: &str) -> Vec<TaggedChangelist> {
+        get_changelist_by_tag(
+            dao,
+            GetChangelistByTagReq {
+                branch_id: branch_id.to_string(),
+                label: label.to_string(),
+                limit: 0,
+            },
+        )
+        .await
+        .unwrap()
+        .changelists
+    }
+
+    #[tokio::test]
+    async fn tagged_changelists_can_be_queried_by_label() {
+        let dao = dao_with_changelists(3).await;
+        tag(&dao, 1, "release/v1.2").await.unwrap();
+        tag(&dao, 3, " release/v1.2 ").await.unwrap();
+        tag(&dao, 3, "release/v1.2").await.unwrap();
+        tag(&dao, 3, "hotfix").await.unwrap();
+
+        let changelists = query(&dao, "", "release/v1.2").await;
+        let ids: Vec<i64> = changelists.iter().map(|cl| cl.changelist_id).collect();
+        assert_eq!(ids, vec![3, 1]);
+        assert_eq!(changelists[0].labels, vec!["release/v1.2", "hotfix"]);
+        assert_eq!(changelists[0].description, "cl 3");
+        assert!(query(&dao, "", "release").await.is_empty());
+
+        untag_changelist(
+            &dao,
+            UntagChangelistReq {
+                changelist_id: 3,
+                label: "release/v1.2".to_string(),
+            },
+        )
+        .await
+        .unwrap();
+        let ids: Vec<i64> = query(&dao, "", "release/v1.2")
+            .await
+            .iter()
+            .map(|cl| cl.changelist_id)
+            .collect();
+        assert_eq!(ids, vec![1]);
+        assert_eq!(query(&dao, "", "hotfix").await.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn query_by_branch_only_returns_changelists_up_to_head() {
+        let dao = dao_with_changelists(3).await;
+        dao.insert_branch(&BranchDoc {
+            id: "main".to_string(),
+            created_at: 0,
+            created_by: "alice".to_string(),
+            head_changelist_id: 2,
+            is_protected: false,
+            metadata: BranchMetadata {
+                description: String::new(),
+                owners: vec![],
+                description_regex: None,
+            },
+        })
+        .await
+        .unwrap();
+        for id in 1..=3 {
+            tag(&dao, id, "nightly").await.unwrap();
+        }
+
+        let ids: Vec<i64> = query(&dao, "main", "nightly")
+            .await
+            .iter()
+            .map(|cl| cl.changelist_id)
+            .collect();
+        assert_eq!(ids, vec![2, 1]);
+
+        let err = get_changelist_by_tag(
+            &dao,
+            GetChangelistByTagReq {
+                branch_id: "dev".to_string(),
+                label: "nightly".to_string(),
+                limit: 0,
+            },
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(err.code(), Code::NotFound);
+    }
+
+    #[tokio::test]
+    async fn invalid_label_or_missing_changelist_is_rejected() {
+        let dao = dao_with_changelists(1).await;
+        assert_eq!(
+            tag(&dao, 1, "  ").await.unwrap_err().code(),
+            Code::InvalidArgument
+        );
+        assert_eq!(
+            tag(&dao, 1, "two words").await.unwrap_err().code(),
+            Code::InvalidArgument
+        );
+        assert_eq!(
+            tag(&dao, 42, "release").await.unwrap_err().code(),
+            Code::NotFound
+        );
+    }
+}
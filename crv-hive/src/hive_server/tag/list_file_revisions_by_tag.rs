@@ -0,0 +1,264 @@
+use crate::database::dao::{Dao, file_revision_tags, read_dao};
+use crate::hive_server::tag::normalize_label;
+use crate::logging::HiveLog;
+use crate::pb::{ListFileRevisionsByTagReq, ListFileRevisionsByTagRsp, TaggedFileRevision};
+use tonic::{Request, Response, Status};
+
+/// 未指定 limit 时最多返回的条数
+const DEFAULT_LIMIT: u32 = 100;
+/// 单次查询最多返回的条数
+const MAX_LIMIT: u32 = 1000;
+
+pub async fn handle_list_file_revisions_by_tag(
+    log: HiveLog,
+    r: Request<ListFileRevisionsByTagReq>,
+) -> Result<Response<ListFileRevisionsByTagRsp>, Status> {
+    let _g = log.enter();
+
+    let request = r.into_inner();
+    log.info(&format!(
+        "list_file_revisions_by_tag received: branch={}, tag={}, limit={}",
+        request.branch_id, request.tag, request.limit
+    ));
+
+    let rsp = list_file_revisions_by_tag(read_dao().as_ref(), request).await?;
+    Ok(Response::new(rsp))
+}
+
+/// 查询带有指定标签的 file revision，指定分支时只返回该分支 HEAD 及之前提交的 revision。
+pub(crate) async fn list_file_revisions_by_tag(
+    dao: &dyn Dao,
+    request: ListFileRevisionsByTagReq,
+) -> Result<ListFileRevisionsByTagRsp, Status> {
+    let tag = normalize_label(&request.tag)?;
+    let branch_id = request.branch_id.trim();
+    if !branch_id.is_empty() {
+        dao.find_branch_by_id(branch_id)
+            .await
+            .map_err(|e| Status::internal(format!("database error while finding branch: {e}")))?
+            .ok_or_else(|| Status::not_found(format!("branch '{branch_id}' not found")))?;
+    }
+
+    let limit = match request.limit {
+        0 => DEFAULT_LIMIT,
+        limit => limit.min(MAX_LIMIT),
+    } as u64;
+    let revisions = dao
+        .find_file_revisions_by_tag(branch_id, tag, limit)
+        .await
+        .map_err(|e| {
+            Status::internal(format!("database error while querying file revisions: {e}"))
+        })?;
+
+    let revisions = revisions
+        .into_iter()
+        .map(|r| {
+            let path = r
+                .to_depot_path_string()
+                .map_err(|e| Status::internal(format!("invalid depot path in database: {e}")))?;
+            Ok(TaggedFileRevision {
+                path,
+                generation: r.generation,
+                revision: r.revision,
+                changelist_id: r.changelist_id,
+                size: r.size,
+                created_at: r.created_at,
+                tags: file_revision_tags(&r.metadata),
+            })
+        })
+        .collect::<Result<Vec<_>, Status>>()?;
+    Ok(ListFileRevisionsByTagRsp { revisions })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::dao::{MockDao, NewFileRevisionInput};
+    use crate::hive_server::tag::tag_file_revision::tag_file_revision;
+    use crate::pb::TagFileRevisionReq;
+    use crv_core::metadata::{BranchDoc, BranchMetadata};
+    use tonic::Code;
+
+    fn revision(depot_path: &str, revision: i64) -> NewFileRevisionInput {
+        NewFileRevisionInput {
+            depot_path: depot_path.to_string(),
+            generation: 1,
+            revision,
+            binary_id: serde_json::json!([]),
+            size: 10,
+            is_delete: false,
+            created_at: 0,
+            metadata: serde_json::json!({ "fileMode": "644" }),
+            file_metadata: serde_json::json!({}),
+        }
+    }
+
+    async fn dao_with_revisions() -> MockDao {
+        let dao = MockDao::default();
+        dao.commit_submit(
+            "alice",
+            "first",
+            1,
+            serde_json::json!({}),
+            vec![
+                revision("//art/hero.png", 1),
+                revision("//art/villain.png", 1),
+            ],
+        )
+        .await
+        .unwrap();
+        dao.commit_submit(
+            "alice",
+            "second",
+            2,
+            serde_json::json!({}),
+            vec![revision("//art/hero.png", 2)],
+        )
+        .await
+        .unwrap();
+        dao
+    }
+
+    async fn tag(
+        dao: &MockDao,
+        path: &str,
+        revision: i64,
+        add: &[&str],
+        remove: &[&str],
+    ) -> Result<Vec<String>, Status> {
+        tag_file_revision(
+            dao,
+            TagFileRevisionReq {
+                path: path.to_string(),
+                generation: 1,
+                revision,
+                tags_to_add: add.iter().map(|t| t.to_string()).collect(),
+                tags_to_remove: remove.iter().map(|t| t.to_string()).collect(),
+            },
+        )
+        .await
+        .map(|rsp| rsp.tags)
+    }
+
+    async fn list(dao: &MockDao, branch_id: &str, tag: &str) -> Vec<TaggedFileRevision> {
+        list_file_revisions_by_tag(
+            dao,
+            ListFileRevisionsByTagReq {
+                branch_id: branch_id.to_string(),
+                tag: tag.to_string(),
+                limit: 0,
+            },
+        )
+        .await
+        .unwrap()
+        .revisions
+    }
+
+    #[tokio::test]
+    async fn tagged_revisions_can_be_listed_by_tag() {
+        let dao = dao_with_revisions().await;
+        let tags = tag(
+            &dao,
+            "//art/hero.png",
+            1,
+            &["needs-review", "approved"],
+            &[],
+        )
+        .await
+        .unwrap();
+        assert_eq!(tags, vec!["approved", "needs-review"]);
+        tag(&dao, "//art/hero.png", 2, &["approved", " approved "], &[])
+            .await
+            .unwrap();
+
+        let revisions = list(&dao, "", "approved").await;
+        let found: Vec<(&str, i64)> = revisions
+            .iter()
+            .map(|r| (r.path.as_str(), r.revision))
+            .collect();
+        assert_eq!(found, vec![("//art/hero.png", 2), ("//art/hero.png", 1)]);
+        assert_eq!(revisions[0].tags, vec!["approved"]);
+        assert_eq!(revisions[1].changelist_id, 1);
+        assert!(list(&dao, "", "deprecated").await.is_empty());
+
+        let tags = tag(&dao, "//art/hero.png", 1, &["deprecated"], &["approved"])
+            .await
+            .unwrap();
+        assert_eq!(tags, vec!["deprecated", "needs-review"]);
+        assert_eq!(list(&dao, "", "approved").await.len(), 1);
+        assert_eq!(list(&dao, "", "deprecated").await[0].revision, 1);
+
+        // 其他元信息保持不变
+        let history = dao
+            .list_file_revisions_by_depot_path("//art/hero.png")
+            .await
+            .unwrap();
+        assert_eq!(history[1].metadata["fileMode"], "644");
+    }
+
+    #[tokio::test]
+    async fn list_by_branch_only_returns_revisions_up_to_head() {
+        let dao = dao_with_revisions().await;
+        dao.insert_branch(&BranchDoc {
+            id: "main".to_string(),
+            created_at: 0,
+            created_by: "alice".to_string(),
+            head_changelist_id: 1,
+            is_protected: false,
+            metadata: BranchMetadata {
+                description: String::new(),
+                owners: vec![],
+                description_regex: None,
+            },
+        })
+        .await
+        .unwrap();
+        tag(&dao, "//art/hero.png", 1, &["approved"], &[])
+            .await
+            .unwrap();
+        tag(&dao, "//art/hero.png", 2, &["approved"], &[])
+            .await
+            .unwrap();
+
+        let revisions: Vec<i64> = list(&dao, "main", "approved")
+            .await
+            .iter()
+            .map(|r| r.revision)
+            .collect();
+        assert_eq!(revisions, vec![1]);
+
+        let err = list_file_revisions_by_tag(
+            &dao,
+            ListFileRevisionsByTagReq {
+                branch_id: "dev".to_string(),
+                tag: "approved".to_string(),
+                limit: 0,
+            },
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(err.code(), Code::NotFound);
+    }
+
+    #[tokio::test]
+    async fn invalid_tags_or_missing_revision_are_rejected() {
+        let dao = dao_with_revisions().await;
+        let code = |r: Result<Vec<String>, Status>| r.unwrap_err().code();
+        assert_eq!(
+            code(tag(&dao, "//art/hero.png", 1, &[], &[]).await),
+            Code::InvalidArgument
+        );
+        assert_eq!(
+            code(tag(&dao, "//art/hero.png", 1, &["two words"], &[]).await),
+            Code::InvalidArgument
+        );
+        assert_eq!(
+            code(tag(&dao, "//art/hero.png", 1, &["approved"], &["approved"]).await),
+            Code::InvalidArgument
+        );
+        assert_eq!(
+            code(tag(&dao, "//art/hero.png", 3, &["approved"], &[]).await),
+            Code::NotFound
+        );
+    }
+}
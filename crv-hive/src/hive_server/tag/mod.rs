@@ -0,0 +1,29 @@
+pub mod get_changelist_by_tag;
+pub mod list_file_revisions_by_tag;
+pub mod tag_changelist;
+pub mod tag_file_revision;
+pub mod untag_changelist;
+
+use tonic::Status;
+
+/// 标签的最大长度
+const MAX_LABEL_LEN: usize = 255;
+
+/// 校验并规范化标签：去掉首尾空白，不能为空或包含空白字符
+pub(crate) fn normalize_label(label: &str) -> Result<&str, Status> {
+    let label = label.trim();
+    if label.is_empty() {
+        return Err(Status::invalid_argument("label is required"));
+    }
+    if label.len() > MAX_LABEL_LEN {
+        return Err(Status::invalid_argument(format!(
+            "label must be at most {MAX_LABEL_LEN} bytes"
+        )));
+    }
+    if label.chars().any(char::is_whitespace) {
+        return Err(Status::invalid_argument(format!(
+            "label '{label}' must not contain whitespace"
+        )));
+    }
+    Ok(label)
+}
@@ -0,0 +1,45 @@
+use crate::auth::require_user;
+use crate::database::dao::{Dao, dao};
+use crate::hive_server::changelist::resolve_changelist_id;
+use crate::hive_server::tag::normalize_label;
+use crate::logging::HiveLog;
+use crate::pb::{TagChangelistReq, TagChangelistRsp};
+use tonic::{Request, Response, Status};
+
+pub async fn handle_tag_changelist(
+    log: HiveLog,
+    r: Request<TagChangelistReq>,
+) -> Result<Response<TagChangelistRsp>, Status> {
+    let user = require_user(&r)?.clone();
+    let log = log.with_user(&user.username);
+    let _g = log.enter();
+
+    let request = r.into_inner();
+    log.info(&format!(
+        "tag_changelist received: changelist={}, label={}",
+        request.changelist_id, request.label
+    ));
+
+    let rsp = tag_changelist(dao().as_ref(), request).await?;
+    Ok(Response::new(rsp))
+}
+
+/// 为 changelist 添加标签，重复添加同一标签不会产生重复项。
+pub(crate) async fn tag_changelist(
+    dao: &dyn Dao,
+    request: TagChangelistReq,
+) -> Result<TagChangelistRsp, Status> {
+    let label = normalize_label(&request.label)?;
+    let changelist_id = resolve_changelist_id(dao, request.changelist_id).await?;
+    let found = dao
+        .add_label_to_changelist(changelist_id, label)
+        .await
+        .map_err(|e| Status::internal(format!("database error while tagging changelist: {e}")))?;
+    if !found {
+        return Err(Status::not_found(format!(
+            "changelist {} not found",
+            request.changelist_id
+        )));
+    }
+    Ok(TagChangelistRsp {})
+}
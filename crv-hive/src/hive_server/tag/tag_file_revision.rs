@@ -0,0 +1,73 @@
+use crate::auth::require_user;
+use crate::database::dao::{Dao, dao};
+use crate::hive_server::tag::normalize_label;
+use crate::logging::HiveLog;
+use crate::pb::{TagFileRevisionReq, TagFileRevisionRsp};
+use tonic::{Request, Response, Status};
+
+pub async fn handle_tag_file_revision(
+    log: HiveLog,
+    r: Request<TagFileRevisionReq>,
+) -> Result<Response<TagFileRevisionRsp>, Status> {
+    let user = require_user(&r)?.clone();
+    let log = log.with_user(&user.username);
+    let _g = log.enter();
+
+    let request = r.into_inner();
+    log.info(&format!(
+        "tag_file_revision received: path={}, revision={}:{}, add={:?}, remove={:?}",
+        request.path,
+        request.generation,
+        request.revision,
+        request.tags_to_add,
+        request.tags_to_remove
+    ));
+
+    let rsp = tag_file_revision(dao().as_ref(), request).await?;
+    Ok(Response::new(rsp))
+}
+
+/// 为 file revision 添加和移除标签，重复添加同一标签不会产生重复项。
+pub(crate) async fn tag_file_revision(
+    dao: &dyn Dao,
+    request: TagFileRevisionReq,
+) -> Result<TagFileRevisionRsp, Status> {
+    let path = crv_core::path::basic::DepotPath::parse(&request.path)
+        .map_err(|e| Status::invalid_argument(format!("invalid path '{}': {e}", request.path)))?;
+    let tags_to_add = normalize_tags(&request.tags_to_add)?;
+    let tags_to_remove = normalize_tags(&request.tags_to_remove)?;
+    if tags_to_add.is_empty() && tags_to_remove.is_empty() {
+        return Err(Status::invalid_argument(
+            "at least one tag to add or remove is required",
+        ));
+    }
+    if let Some(tag) = tags_to_add.iter().find(|t| tags_to_remove.contains(t)) {
+        return Err(Status::invalid_argument(format!(
+            "tag '{tag}' cannot be both added and removed"
+        )));
+    }
+
+    let tags = dao
+        .update_file_revision_tags(
+            &path.to_custom_string(),
+            request.generation,
+            request.revision,
+            &tags_to_add,
+            &tags_to_remove,
+        )
+        .await
+        .map_err(|e| Status::internal(format!("database error while tagging file revision: {e}")))?
+        .ok_or_else(|| {
+            Status::not_found(format!(
+                "file revision {}#{}:{} not found",
+                request.path, request.generation, request.revision
+            ))
+        })?;
+    Ok(TagFileRevisionRsp { tags })
+}
+
+fn normalize_tags(tags: &[String]) -> Result<Vec<String>, Status> {
+    tags.iter()
+        .map(|tag| normalize_label(tag).map(str::to_string))
+        .collect()
+}
@@ -0,0 +1,43 @@
+use crate::auth::require_user;
+use crate::database::dao::{Dao, dao};
+use crate::hive_server::tag::normalize_label;
+use crate::logging::HiveLog;
+use crate::pb::{UntagChangelistReq, UntagChangelistRsp};
+use tonic::{Request, Response, Status};
+
+pub async fn handle_untag_changelist(
+    log: HiveLog,
+    r: Request<UntagChangelistReq>,
+) -> Result<Response<UntagChangelistRsp>, Status> {
+    let user = require_user(&r)?.clone();
+    let log = log.with_user(&user.username);
+    let _g = log.enter();
+
+    let request = r.into_inner();
+    log.info(&format!(
+        "untag_changelist received: changelist={}, label={}",
+        request.changelist_id, request.label
+    ));
+
+    let rsp = untag_changelist(dao().as_ref(), request).await?;
+    Ok(Response::new(rsp))
+}
+
+/// 移除 changelist 的标签，标签不存在时不做修改。
+pub(crate) async fn untag_changelist(
+    dao: &dyn Dao,
+    request: UntagChangelistReq,
+) -> Result<UntagChangelistRsp, Status> {
+    let label = normalize_label(&request.label)?;
+    let found = dao
+        .remove_label_from_changelist(request.changelist_id, label)
+        .await
+        .map_err(|e| Status::internal(format!("database error while untagging changelist: {e}")))?;
+    if !found {
+        return Err(Status::not_found(format!(
+            "changelist {} not found",
+            request.changelist_id
+        )));
+    }
+    Ok(UntagChangelistRsp {})
+}
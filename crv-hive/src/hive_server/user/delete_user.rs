@@ -0,0 +1,76 @@
+use crate::auth::{ADMIN_SCOPE, require_scope};
+use crate::database::dao::{Dao, dao};
+use crate::logging::HiveLog;
+use crate::pb::{DeleteUserReq, DeleteUserRsp};
+use tonic::{Request, Response, Status};
+
+pub async fn handle_delete_user(
+    log: HiveLog,
+    r: Request<DeleteUserReq>,
+) -> Result<Response<DeleteUserRsp>, Status> {
+    let user = require_scope(&r, ADMIN_SCOPE)?.clone();
+    let log = log.with_user(&user.username);
+    let _g = log.enter();
+
+    let request = r.into_inner();
+    log.info(&format!(
+        "delete_user received: username={}",
+        request.username
+    ));
+
+    let rsp = delete_user(dao().as_ref(), request).await?;
+    Ok(Response::new(rsp))
+}
+
+/// 删除用户，用户不存在时返回 `NotFound`。
+pub(crate) async fn delete_user(
+    dao: &dyn Dao,
+    request: DeleteUserReq,
+) -> Result<DeleteUserRsp, Status> {
+    let username = request.username.trim();
+    if username.is_empty() {
+        return Err(Status::invalid_argument("username is required"));
+    }
+
+    let deleted = dao
+        .delete_user(username)
+        .await
+        .map_err(|e| Status::internal(format!("database error while deleting user: {e}")))?;
+    if !deleted {
+        return Err(Status::not_found(format!("user '{username}' not found")));
+    }
+    Ok(DeleteUserRsp {})
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::dao::MockDao;
+    use tonic::Code;
+
+    #[tokio::test]
+    async fn delete_user_removes_existing_user() {
+        let dao = MockDao::default();
+        dao.insert_user("alice", "hash", None, "").await.unwrap();
+
+        delete_user(
+            &dao,
+            DeleteUserReq {
+                username: "alice".to_string(),
+            },
+        )
+        .await
+        .unwrap();
+        assert!(dao.find_user_by_username("alice").await.unwrap().is_none());
+
+        let err = delete_user(
+            &dao,
+            DeleteUserReq {
+                username: "alice".to_string(),
+            },
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(err.code(), Code::NotFound);
+    }
+}
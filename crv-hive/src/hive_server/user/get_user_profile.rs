@@ -0,0 +1,84 @@
+use crate::auth::require_user;
+use crate::database::dao::{Dao, read_dao};
+use crate::logging::HiveLog;
+use crate::pb::{GetUserProfileReq, GetUserProfileRsp};
+use tonic::{Request, Response, Status};
+
+pub async fn handle_get_user_profile(
+    log: HiveLog,
+    r: Request<GetUserProfileReq>,
+) -> Result<Response<GetUserProfileRsp>, Status> {
+    let user = require_user(&r)?.clone();
+    let log = log.with_user(&user.username);
+    let _g = log.enter();
+
+    let request = r.into_inner();
+    log.info(&format!(
+        "get_user_profile received: username={}",
+        request.username
+    ));
+
+    let rsp = get_user_profile(read_dao().as_ref(), request).await?;
+    Ok(Response::new(rsp))
+}
+
+/// 查询用户的公开资料，用户不存在时返回 `NotFound`。
+pub(crate) async fn get_user_profile(
+    dao: &dyn Dao,
+    request: GetUserProfileReq,
+) -> Result<GetUserProfileRsp, Status> {
+    let username = request.username.trim();
+    if username.is_empty() {
+        return Err(Status::invalid_argument("username is required"));
+    }
+
+    let user = dao
+        .find_user_by_username(username)
+        .await
+        .map_err(|e| Status::internal(format!("database error while finding user: {e}")))?
+        .ok_or_else(|| Status::not_found(format!("user '{username}' not found")))?;
+    Ok(GetUserProfileRsp {
+        username: user.id,
+        email: user.email,
+        display_name: user.display_name,
+        created_at: user.created_at,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::dao::MockDao;
+    use tonic::Code;
+
+    #[tokio::test]
+    async fn profile_is_returned_without_password() {
+        let dao = MockDao::default();
+        dao.insert_user("alice", "hash", Some("alice@example.com"), "Alice")
+            .await
+            .unwrap();
+
+        let rsp = get_user_profile(
+            &dao,
+            GetUserProfileReq {
+                username: "alice".to_string(),
+            },
+        )
+        .await
+        .unwrap();
+        assert_eq!(rsp.username, "alice");
+        assert_eq!(rsp.email.as_deref(), Some("alice@example.com"));
+        assert_eq!(rsp.display_name, "Alice");
+        assert!(rsp.created_at > 0);
+
+        let err = get_user_profile(
+            &dao,
+            GetUserProfileReq {
+                username: "bob".to_string(),
+            },
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(err.code(), Code::NotFound);
+    }
+}
@@ -0,0 +1,98 @@
+use crate::auth::{ADMIN_SCOPE, require_scope};
+use crate::database::dao::{Dao, read_dao};
+use crate::logging::HiveLog;
+use crate::pb::{ListUsersReq, ListUsersRsp, UserSummary};
+use tonic::{Request, Response, Status};
+
+/// 未指定 page_size 时每页的条数
+const DEFAULT_PAGE_SIZE: u32 = 50;
+/// 每页最多的条数
+const MAX_PAGE_SIZE: u32 = 1000;
+
+pub async fn handle_list_users(
+    log: HiveLog,
+    r: Request<ListUsersReq>,
+) -> Result<Response<ListUsersRsp>, Status> {
+    let user = require_scope(&r, ADMIN_SCOPE)?.clone();
+    let log = log.with_user(&user.username);
+    let _g = log.enter();
+
+    let request = r.into_inner();
+    log.info(&format!(
+        "list_users received: page={}, page_size={}",
+        request.page, request.page_size
+    ));
+
+    let rsp = list_users(read_dao().as_ref(), request).await?;
+    Ok(Response::new(rsp))
+}
+
+/// 按用户名排序分页列出用户。
+pub(crate) async fn list_users(
+    dao: &dyn Dao,
+    request: ListUsersReq,
+) -> Result<ListUsersRsp, Status> {
+    let page_size = match request.page_size {
+        0 => DEFAULT_PAGE_SIZE,
+        page_size => page_size.min(MAX_PAGE_SIZE),
+    } as u64;
+
+    let (users, total) = dao
+        .list_users(request.page as u64 * page_size, page_size)
+        .await
+        .map_err(|e| Status::internal(format!("database error while listing users: {e}")))?;
+
+    Ok(ListUsersRsp {
+        users: users
+            .into_iter()
+            .map(|u| UserSummary {
+                username: u.id,
+                created_at: u.created_at,
+            })
+            .collect(),
+        total,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::dao::MockDao;
+
+    #[tokio::test]
+    async fn list_users_is_paginated_by_username() {
+        let dao = MockDao::default();
+        for username in ["carol", "alice", "erin", "bob", "dave"] {
+            dao.insert_user(username, "hash", None, "").await.unwrap();
+        }
+
+        let rsp = list_users(
+            &dao,
+            ListUsersReq {
+                page: 1,
+                page_size: 2,
+            },
+        )
+        .await
+        .unwrap();
+        let usernames: Vec<&str> = rsp.users.iter().map(|u| u.username.as_str()).collect();
+        assert_eq!(usernames, vec!["carol", "dave"]);
+        assert_eq!(rsp.total, 5);
+        assert!(rsp.users.iter().all(|u| u.created_at > 0));
+
+        let rsp = list_users(&dao, ListUsersReq::default()).await.unwrap();
+        assert_eq!(rsp.users.len(), 5);
+
+        let rsp = list_users(
+            &dao,
+            ListUsersReq {
+                page: 3,
+                page_size: 2,
+            },
+        )
+        .await
+        .unwrap();
+        assert!(rsp.users.is_empty());
+        assert_eq!(rsp.total, 5);
+    }
+}
@@ -0,0 +1,31 @@
+pub mod delete_user;
+pub mod get_user_profile;
+pub mod list_users;
+pub mod oauth2_login;
+pub mod update_user_password;
+
+use regex::Regex;
+use std::sync::OnceLock;
+
+/// 简单校验邮箱格式：`local@domain.tld`，不允许空白字符
+pub(crate) fn is_valid_email(email: &str) -> bool {
+    static EMAIL: OnceLock<Regex> = OnceLock::new();
+    EMAIL
+        .get_or_init(|| Regex::new(r"^[^@\s]+@[^@\s]+\.[^@\s]+$").unwrap())
+        .is_match(email)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn email_must_have_local_part_and_domain() {
+        assert!(is_valid_email("alice@example.com"));
+        assert!(is_valid_email("a.b+c@mail.example.org"));
+        assert!(!is_valid_email("alice"));
+        assert!(!is_valid_email("alice@example"));
+        assert!(!is_valid_email("@example.com"));
+        assert!(!is_valid_email("alice @example.com"));
+    }
+}
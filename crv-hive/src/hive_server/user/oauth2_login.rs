@@ -0,0 +1,248 @@
+use crate::auth::AuthService;
+use crate::auth::oauth2::{OAuth2Login, OAuth2Provider, oauth2_login};
+use crate::database::dao::{Dao, dao};
+use crate::logging::HiveLog;
+use crate::pb::{
+    BeginOauth2LoginReq, BeginOauth2LoginRsp, CompleteOauth2LoginReq, CompleteOauth2LoginRsp,
+    Oauth2Provider,
+};
+use tonic::{Request, Response, Status};
+
+pub async fn handle_begin_oauth2_login(
+    log: HiveLog,
+    r: Request<BeginOauth2LoginReq>,
+) -> Result<Response<BeginOauth2LoginRsp>, Status> {
+    let _g = log.enter();
+
+    let request = r.into_inner();
+    log.info(&format!(
+        "begin_oauth2_login received: provider={}",
+        request.provider().as_str_name()
+    ));
+
+    let rsp = begin_oauth2_login(oauth2_login(), request)?;
+    Ok(Response::new(rsp))
+}
+
+pub async fn handle_complete_oauth2_login(
+    log: HiveLog,
+    auth: &AuthService,
+    r: Request<CompleteOauth2LoginReq>,
+) -> Result<Response<CompleteOauth2LoginRsp>, Status> {
+    let _g = log.enter();
+
+    let request = r.into_inner();
+    log.info("complete_oauth2_login received");
+
+    let rsp = complete_oauth2_login(dao().as_ref(), oauth2_login(), auth, request).await?;
+    log.info(&format!(
+        "oauth2 login succeeded: username={}",
+        rsp.username
+    ));
+    Ok(Response::new(rsp))
+}
+
+/// 开始 OAuth2 登录，返回授权页面地址与之后完成登录所需的 state。
+pub(crate) fn begin_oauth2_login(
+    login: &OAuth2Login,
+    request: BeginOauth2LoginReq,
+) -> Result<BeginOauth2LoginRsp, Status> {
+    let provider = match request.provider() {
+        Oauth2Provider::Google => OAuth2Provider::Google,
+        Oauth2Provider::Github => OAuth2Provider::GitHub,
+        Oauth2Provider::Unspecified => {
+            return Err(Status::invalid_argument("oauth2 provider is required"));
+        }
+    };
+    let (auth_url, state) = login.begin(provider)?;
+    Ok(BeginOauth2LoginRsp { auth_url, state })
+}
+
+/// 用授权后得到的 code 完成 OAuth2 登录并签发 access token。
+pub(crate) async fn complete_oauth2_login(
+    dao: &dyn Dao,
+    login: &OAuth2Login,
+    auth: &AuthService,
+    request: CompleteOauth2LoginReq,
+) -> Result<CompleteOauth2LoginRsp, Status> {
+    let state = request.state.trim();
+    let code = request.code.trim();
+    if state.is_empty() || code.is_empty() {
+        return Err(Status::invalid_argument("state and code are required"));
+    }
+
+    let identity = login.complete(dao, state, code).await?;
+    let (access_token, expires_at) = auth
+        .issue_token(&identity.username, &[])
+        .map_err(Status::from)?;
+    Ok(CompleteOauth2LoginRsp {
+        access_token,
+        expires_at,
+        username: identity.username,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::TokenPolicy;
+    use crate::config::entity::OAuth2ProviderConfig;
+    use crate::database::dao::{MockDao, USER_SOURCE_OAUTH2, USER_SOURCE_PASSWORD};
+    use axum::Router;
+    use axum::http::{HeaderMap, StatusCode, header};
+    use axum::response::IntoResponse;
+    use axum::routing::{get, post};
+    use std::collections::HashMap;
+    use tonic::Code;
+
+    fn json(status: StatusCode, body: String) -> impl IntoResponse {
+        (status, [(header::CONTENT_TYPE, "application/json")], body)
+    }
+
+    /// 模拟 GitHub：token 与 code 相同，用户名与 token 相同，code 为 `bad` 时拒绝授权
+    async fn spawn_provider() -> String {
+        let app = Router::new()
+            .route(
+                "/token",
+                post(|body: String| async move {
+                    let code = body
+                        .split('&')
+                        .find_map(|pair| pair.strip_prefix("code="))
+                        .unwrap_or_default()
+                        .to_string();
+                    if code == "bad" {
+                        return json(
+                            StatusCode::BAD_REQUEST,
+                            r#"{"error":"invalid_grant"}"#.to_string(),
+                        );
+                    }
+                    json(
+                        StatusCode::OK,
+                        format!(r#"{{"access_token":"{code}","token_type":"bearer"}}"#),
+                    )
+                }),
+            )
+            .route(
+                "/user",
+                get(|headers: HeaderMap| async move {
+                    let login = headers
+                        .get(header::AUTHORIZATION)
+                        .and_then(|v| v.to_str().ok())
+                        .and_then(|v| v.strip_prefix("Bearer "))
+                        .unwrap_or_default()
+                        .to_string();
+                    json(
+                        StatusCode::OK,
+                        format!(r#"{{"login":"{login}","name":"Octo Cat","email":null}}"#),
+                    )
+                }),
+            );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        format!("http://{addr}")
+    }
+
+    async fn github_login() -> OAuth2Login {
+        let base = spawn_provider().await;
+        OAuth2Login::new(HashMap::from([(
+            OAuth2Provider::GitHub,
+            OAuth2ProviderConfig {
+                client_id: "client".to_string(),
+                client_secret: "secret".to_string(),
+                redirect_uri: "http://localhost/callback".to_string(),
+                auth_url: Some(format!("{base}/authorize")),
+                token_url: Some(format!("{base}/token")),
+                userinfo_url: Some(format!("{base}/user")),
+            },
+        )]))
+    }
+
+    fn begin(login: &OAuth2Login) -> String {
+        let rsp = begin_oauth2_login(
+            login,
+            BeginOauth2LoginReq {
+                provider: Oauth2Provider::Github as i32,
+            },
+        )
+        .unwrap();
+        assert!(rsp.auth_url.contains(&format!("state={}", rsp.state)));
+        assert!(rsp.auth_url.contains("client_id=client"));
+        rsp.state
+    }
+
+    fn complete(state: &str, code: &str) -> CompleteOauth2LoginReq {
+        CompleteOauth2LoginReq {
+            state: state.to_string(),
+            code: code.to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn first_login_creates_oauth2_user_and_issues_token() {
+        let dao = crate::test_support::install_mock_dao();
+        let dao = dao.as_ref();
+        let login = github_login().await;
+        let auth = AuthService::new(b"test-secret", TokenPolicy::default());
+        let username = format!("octocat-{}", uuid::Uuid::new_v4().simple());
+        let state = begin(&login);
+
+        let err = complete_oauth2_login(dao, &login, &auth, complete("forged", &username))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::InvalidArgument);
+
+        let rsp = complete_oauth2_login(dao, &login, &auth, complete(&state, &username))
+            .await
+            .unwrap();
+        assert_eq!(rsp.username, username);
+        let (user, _) = auth.verify_token(&rsp.access_token).unwrap();
+        assert_eq!(user.username, username);
+        let record = dao.find_user_by_username(&username).await.unwrap().unwrap();
+        assert_eq!(record.source, USER_SOURCE_OAUTH2);
+        assert_eq!(record.password, "");
+        assert_eq!(record.display_name, "Octo Cat");
+        // OAuth2 用户不能用密码登录
+        assert!(
+            !crate::auth::validate_user_credentials(&username, "")
+                .await
+                .unwrap()
+        );
+
+        // state 只能使用一次
+        let err = complete_oauth2_login(dao, &login, &auth, complete(&state, &username))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::InvalidArgument);
+
+        // 再次登录使用已有的用户
+        let state = begin(&login);
+        complete_oauth2_login(dao, &login, &auth, complete(&state, &username))
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn oauth2_can_not_take_over_password_account() {
+        let dao = MockDao::default();
+        dao.insert_user("alice", "hash", None, "Alice")
+            .await
+            .unwrap();
+        let login = github_login().await;
+        let auth = AuthService::new(b"test-secret", TokenPolicy::default());
+
+        let state = begin(&login);
+        let err = complete_oauth2_login(&dao, &login, &auth, complete(&state, "alice"))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::PermissionDenied);
+        let record = dao.find_user_by_username("alice").await.unwrap().unwrap();
+        assert_eq!(record.source, USER_SOURCE_PASSWORD);
+
+        let state = begin(&login);
+        let err = complete_oauth2_login(&dao, &login, &auth, complete(&state, "bad"))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::Unauthenticated);
+    }
+}
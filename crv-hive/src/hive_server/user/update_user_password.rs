@@ -0,0 +1,217 @@
+use crate::auth::{ADMIN_SCOPE, hash_password, require_scope};
+use crate::database::dao::{Dao, dao};
+use crate::logging::HiveLog;
+use crate::pb::{UpdateUserPasswordReq, UpdateUserPasswordRsp};
+use tonic::{Request, Response, Status};
+
+pub async fn handle_update_user_password(
+    log: HiveLog,
+    r: Request<UpdateUserPasswordReq>,
+) -> Result<Response<UpdateUserPasswordRsp>, Status> {
+    let user = require_scope(&r, ADMIN_SCOPE)?.clone();
+    let log = log.with_user(&user.username);
+    let _g = log.enter();
+
+    let request = r.into_inner();
+    log.info(&format!(
+        "update_user_password received: username={}",
+        request.username
+    ));
+
+    let rsp = update_user_password(dao().as_ref(), request).await?;
+    Ok(Response::new(rsp))
+}
+
+/// 使用 Argon2 重新哈希新密码并更新，用户不存在时返回 `NotFound`。
+pub(crate) async fn update_user_password(
+    dao: &dyn Dao,
+    request: UpdateUserPasswordReq,
+) -> Result<UpdateUserPasswordRsp, Status> {
+    let username = request.username.trim();
+    if username.is_empty() {
+        return Err(Status::invalid_argument("username is required"));
+    }
+    if request.new_password.len() < 6 {
+        return Err(Status::invalid_argument(
+            "password must be at least 6 characters",
+        ));
+    }
+
+    let password_hash = hash_password(&request.new_password)
+        .map_err(|_| Status::internal("failed to hash password"))?;
+    let updated = dao
+        .update_user_password(username, &password_hash)
+        .await
+        .map_err(|e| Status::internal(format!("database error while updating user: {e}")))?;
+    if !updated {
+        return Err(Status::not_found(format!("user '{username}' not found")));
+    }
+    Ok(UpdateUserPasswordRsp {})
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::{AuthService, AuthSource, TokenPolicy, UserContext};
+    use crate::database::dao::MockDao;
+    use crate::hive_server::CrvHiveService;
+    use crate::pb::hive_service_server::HiveService;
+    use crate::pb::{DeleteUserReq, GetUserProfileReq, ListUsersReq, LoginReq, RegisterReq};
+    use std::sync::Arc;
+    use tonic::Code;
+
+    fn request<T>(message: T, scopes: &[&str]) -> Request<T> {
+        let mut req = Request::new(message);
+        req.extensions_mut().insert(UserContext {
+            username: "root".to_string(),
+            scopes: scopes.iter().map(|x| x.to_string()).collect(),
+            source: AuthSource::Jwt,
+        });
+        req
+    }
+
+    #[tokio::test]
+    async fn update_user_password_rehashes_password() {
+        let dao = MockDao::default();
+        dao.insert_user("alice", "old", None, "").await.unwrap();
+
+        update_user_password(
+            &dao,
+            UpdateUserPasswordReq {
+                username: "alice".to_string(),
+                new_password: "new-secret".to_string(),
+            },
+        )
+        .await
+        .unwrap();
+        let stored = dao.find_user_by_username("alice").await.unwrap().unwrap();
+        assert!(stored.password.starts_with("$argon2"));
+
+        let err = update_user_password(
+            &dao,
+            UpdateUserPasswordReq {
+                username: "bob".to_string(),
+                new_password: "new-secret".to_string(),
+            },
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(err.code(), Code::NotFound);
+
+        let err = update_user_password(
+            &dao,
+            UpdateUserPasswordReq {
+                username: "alice".to_string(),
+                new_password: "short".to_string(),
+            },
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(err.code(), Code::InvalidArgument);
+    }
+
+    #[tokio::test]
+    async fn user_management_rpcs_require_admin_scope() {
+        crate::test_support::install_mock_dao();
+        let service = CrvHiveService::new(Arc::new(AuthService::new(
+            b"test-secret",
+            TokenPolicy::default(),
+        )));
+        let username = format!("user-{}", uuid::Uuid::new_v4());
+        let rsp = service
+            .register(Request::new(RegisterReq {
+                username: username.clone(),
+                password: "old-secret".to_string(),
+                email: Some("user@example.com".to_string()),
+                display_name: "Test User".to_string(),
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(rsp.success);
+        let err = service
+            .register(Request::new(RegisterReq {
+                username: format!("user-{}", uuid::Uuid::new_v4()),
+                password: "old-secret".to_string(),
+                email: Some("not-an-email".to_string()),
+                display_name: String::new(),
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::InvalidArgument);
+
+        // 任何登录用户都能查看资料
+        let profile = service
+            .get_user_profile(request(
+                GetUserProfileReq {
+                    username: username.clone(),
+                },
+                &[],
+            ))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(profile.email.as_deref(), Some("user@example.com"));
+        assert_eq!(profile.display_name, "Test User");
+
+        // 未登录与非管理员都不能管理用户
+        let err = service
+            .list_users(Request::new(ListUsersReq::default()))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::Unauthenticated);
+        let err = service
+            .delete_user(request(
+                DeleteUserReq {
+                    username: username.clone(),
+                },
+                &[],
+            ))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::PermissionDenied);
+
+        let rsp = service
+            .list_users(request(
+                ListUsersReq {
+                    page: 0,
+                    page_size: 1000,
+                },
+                &[ADMIN_SCOPE],
+            ))
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(rsp.users.iter().any(|u| u.username == username));
+
+        service
+            .update_user_password(request(
+                UpdateUserPasswordReq {
+                    username: username.clone(),
+                    new_password: "new-secret".to_string(),
+                },
+                &[ADMIN_SCOPE],
+            ))
+            .await
+            .unwrap();
+        let login = |password: &str| {
+            service.login(Request::new(LoginReq {
+                username: username.clone(),
+                password: password.to_string(),
+            }))
+        };
+        assert!(login("old-secret").await.is_err());
+        assert!(login("new-secret").await.is_ok());
+
+        service
+            .delete_user(request(
+                DeleteUserReq {
+                    username: username.clone(),
+                },
+                &[ADMIN_SCOPE],
+            ))
+            .await
+            .unwrap();
+        assert!(login("new-secret").await.is_err());
+    }
+}
@@ -0,0 +1,47 @@
+use crate::auth::{ADMIN_SCOPE, require_scope};
+use crate::database::dao::{Dao, read_dao};
+use crate::hive_server::webhook::event_to_pb;
+use crate::logging::HiveLog;
+use crate::pb::{ListWebhooksReq, ListWebhooksRsp, WebhookInfo};
+use tonic::{Request, Response, Status};
+
+pub async fn handle_list_webhooks(
+    log: HiveLog,
+    r: Request<ListWebhooksReq>,
+) -> Result<Response<ListWebhooksRsp>, Status> {
+    let user = require_scope(&r, ADMIN_SCOPE)?.clone();
+    let log = log.with_user(&user.username);
+    let _g = log.enter();
+
+    let request = r.into_inner();
+    let rsp = list_webhooks(read_dao().as_ref(), request).await?;
+    Ok(Response::new(rsp))
+}
+
+/// 按登记顺序列出所有 webhook，不返回 secret。
+pub(crate) async fn list_webhooks(
+    dao: &dyn Dao,
+    _request: ListWebhooksReq,
+) -> Result<ListWebhooksRsp, Status> {
+    let webhooks = dao
+        .list_webhooks()
+        .await
+        .map_err(|e| Status::internal(format!("database error while listing webhooks: {e}")))?;
+
+    Ok(ListWebhooksRsp {
+        webhooks: webhooks
+            .into_iter()
+            .map(|w| WebhookInfo {
+                id: w.id,
+                url: w.url,
+                events: w
+                    .events
+                    .into_iter()
+                    .map(|e| event_to_pb(e).into())
+                    .collect(),
+                created_by: w.created_by,
+                created_at: w.created_at,
+            })
+            .collect(),
+    })
+}
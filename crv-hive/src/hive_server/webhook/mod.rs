@@ -0,0 +1,19 @@
+pub mod list_webhooks;
+pub mod register_webhook;
+pub mod unregister_webhook;
+
+use crate::pb;
+use crate::webhook::WebhookEvent;
+
+fn event_to_pb(event: WebhookEvent) -> pb::WebhookEvent {
+    match event {
+        WebhookEvent::Submit => pb::WebhookEvent::Submit,
+    }
+}
+
+fn event_from_pb(event: pb::WebhookEvent) -> Option<WebhookEvent> {
+    match event {
+        pb::WebhookEvent::Unspecified => None,
+        pb::WebhookEvent::Submit => Some(WebhookEvent::Submit),
+    }
+}
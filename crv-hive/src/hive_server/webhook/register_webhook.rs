@@ -0,0 +1,164 @@
+use crate::auth::{ADMIN_SCOPE, require_scope};
+use crate::database::dao::{Dao, dao};
+use crate::hive_server::webhook::event_from_pb;
+use crate::logging::HiveLog;
+use crate::pb::{self, RegisterWebhookReq, RegisterWebhookRsp};
+use crate::webhook::WebhookConfig;
+use tonic::{Request, Response, Status};
+
+pub async fn handle_register_webhook(
+    log: HiveLog,
+    r: Request<RegisterWebhookReq>,
+) -> Result<Response<RegisterWebhookRsp>, Status> {
+    let user = require_scope(&r, ADMIN_SCOPE)?.clone();
+    let log = log.with_user(&user.username);
+    let _g = log.enter();
+
+    let request = r.into_inner();
+    log.info(&format!(
+        "register_webhook received: url={}, events={}",
+        request.url,
+        request.events.len()
+    ));
+
+    let rsp = register_webhook(
+        dao().as_ref(),
+        request,
+        &user.username,
+        chrono::Utc::now().timestamp_millis(),
+    )
+    .await?;
+    Ok(Response::new(rsp))
+}
+
+/// 登记 webhook，URL 只支持 http/https，且至少需要订阅一个事件。
+pub(crate) async fn register_webhook(
+    dao: &dyn Dao,
+    request: RegisterWebhookReq,
+    created_by: &str,
+    now: i64,
+) -> Result<RegisterWebhookRsp, Status> {
+    let url = reqwest::Url::parse(request.url.trim())
+        .map_err(|e| Status::invalid_argument(format!("invalid url '{}': {e}", request.url)))?;
+    if url.scheme() != "http" && url.scheme() != "https" {
+        return Err(Status::invalid_argument(format!(
+            "unsupported url scheme '{}', expected http or https",
+            url.scheme()
+        )));
+    }
+    if request.secret.is_empty() {
+        return Err(Status::invalid_argument("secret is required"));
+    }
+
+    let mut events = Vec::new();
+    for event in request.events {
+        let event = pb::WebhookEvent::try_from(event)
+            .ok()
+            .and_then(event_from_pb)
+            .ok_or_else(|| Status::invalid_argument(format!("unknown webhook event {event}")))?;
+        if !events.contains(&event) {
+            events.push(event);
+        }
+    }
+    if events.is_empty() {
+        return Err(Status::invalid_argument("at least one event is required"));
+    }
+
+    let id = dao
+        .insert_webhook(&WebhookConfig {
+            id: 0,
+            url: url.to_string(),
+            secret: request.secret,
+            events,
+            created_by: created_by.to_string(),
+            created_at: now,
+        })
+        .await
+        .map_err(|e| Status::internal(format!("database error while inserting webhook: {e}")))?;
+
+    Ok(RegisterWebhookRsp { id })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::dao::MockDao;
+    use crate::hive_server::webhook::list_webhooks::list_webhooks;
+    use crate::hive_server::webhook::unregister_webhook::unregister_webhook;
+    use crate::pb::{ListWebhooksReq, UnregisterWebhookReq};
+    use tonic::Code;
+
+    fn request(url: &str, events: Vec<pb::WebhookEvent>) -> RegisterWebhookReq {
+        RegisterWebhookReq {
+            url: url.to_string(),
+            secret: "s3cret".to_string(),
+            events: events.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    #[tokio::test]
+    async fn registered_webhooks_are_listed_until_unregistered() {
+        let dao = MockDao::default();
+        let id = register_webhook(
+            &dao,
+            request(
+                "https://ci.example.com/crv",
+                vec![pb::WebhookEvent::Submit, pb::WebhookEvent::Submit],
+            ),
+            "admin",
+            1_000,
+        )
+        .await
+        .unwrap()
+        .id;
+
+        let webhooks = list_webhooks(&dao, ListWebhooksReq {})
+            .await
+            .unwrap()
+            .webhooks;
+        assert_eq!(webhooks.len(), 1);
+        assert_eq!(webhooks[0].id, id);
+        assert_eq!(webhooks[0].url, "https://ci.example.com/crv");
+        assert_eq!(webhooks[0].events, vec![pb::WebhookEvent::Submit as i32]);
+        assert_eq!(webhooks[0].created_by, "admin");
+        assert_eq!(webhooks[0].created_at, 1_000);
+
+        unregister_webhook(&dao, UnregisterWebhookReq { id })
+            .await
+            .unwrap();
+        assert!(
+            list_webhooks(&dao, ListWebhooksReq {})
+                .await
+                .unwrap()
+                .webhooks
+                .is_empty()
+        );
+
+        let err = unregister_webhook(&dao, UnregisterWebhookReq { id })
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::NotFound);
+    }
+
+    #[tokio::test]
+    async fn invalid_webhooks_are_rejected() {
+        let dao = MockDao::default();
+        for req in [
+            request("not a url", vec![pb::WebhookEvent::Submit]),
+            request("ftp://ci.example.com/", vec![pb::WebhookEvent::Submit]),
+            request("https://ci.example.com/", vec![]),
+            request(
+                "https://ci.example.com/",
+                vec![pb::WebhookEvent::Unspecified],
+            ),
+            RegisterWebhookReq {
+                secret: String::new(),
+                ..request("https://ci.example.com/", vec![pb::WebhookEvent::Submit])
+            },
+        ] {
+            let err = register_webhook(&dao, req, "admin", 0).await.unwrap_err();
+            assert_eq!(err.code(), Code::InvalidArgument);
+        }
+        assert!(dao.list_webhooks().await.unwrap().is_empty());
+    }
+}
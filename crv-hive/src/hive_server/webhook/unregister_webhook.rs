@@ -0,0 +1,38 @@
+use crate::auth::{ADMIN_SCOPE, require_scope};
+use crate::database::dao::{Dao, dao};
+use crate::logging::HiveLog;
+use crate::pb::{UnregisterWebhookReq, UnregisterWebhookRsp};
+use tonic::{Request, Response, Status};
+
+pub async fn handle_unregister_webhook(
+    log: HiveLog,
+    r: Request<UnregisterWebhookReq>,
+) -> Result<Response<UnregisterWebhookRsp>, Status> {
+    let user = require_scope(&r, ADMIN_SCOPE)?.clone();
+    let log = log.with_user(&user.username);
+    let _g = log.enter();
+
+    let request = r.into_inner();
+    log.info(&format!("unregister_webhook received: id={}", request.id));
+
+    let rsp = unregister_webhook(dao().as_ref(), request).await?;
+    Ok(Response::new(rsp))
+}
+
+/// 删除 webhook，不存在时返回 `NotFound`。
+pub(crate) async fn unregister_webhook(
+    dao: &dyn Dao,
+    request: UnregisterWebhookReq,
+) -> Result<UnregisterWebhookRsp, Status> {
+    let deleted = dao
+        .delete_webhook(request.id)
+        .await
+        .map_err(|e| Status::internal(format!("database error while deleting webhook: {e}")))?;
+    if !deleted {
+        return Err(Status::not_found(format!(
+            "webhook {} not found",
+            request.id
+        )));
+    }
+    Ok(UnregisterWebhookRsp {})
+}
@@ -0,0 +1,282 @@
+use crate::common::depot_path::DepotPath;
+use crate::database::dao::{Dao, read_dao};
+use crate::hive_server::branch::get_branch_diff::snapshot_at;
+use crate::logging::HiveLog;
+use crate::pb::{DescribeWorkspaceReq, DescribeWorkspaceRsp, WorkspaceFile, WorkspaceInfo};
+use crv_core::path::basic::{DepotPath as CoreDepotPath, LocalPath};
+use crv_core::path::engine::PathEngine;
+use crv_core::workspace::entity::WorkspaceConfig;
+use tonic::{Request, Response, Status};
+
+pub async fn handle_describe_workspace(
+    log: HiveLog,
+    r: Request<DescribeWorkspaceReq>,
+) -> Result<Response<DescribeWorkspaceRsp>, Status> {
+    let _g = log.enter();
+
+    let request = r.into_inner();
+    log.info(&format!(
+        "describe_workspace received: workspace={}, path={}",
+        request.workspace_name, request.depot_wildcard
+    ));
+
+    let rsp = describe_workspace(read_dao().as_ref(), request).await?;
+    log.info(&format!(
+        "describe_workspace finished: changelist={}, files={}",
+        rsp.changelist_id,
+        rsp.files.len()
+    ));
+    Ok(Response::new(rsp))
+}
+
+/// workspace 根目录是否为 `<盘符>:` 开头的 Windows 路径
+fn is_windows_root(root_path: &str) -> bool {
+    let mut chars = root_path.chars();
+    matches!(
+        (chars.next(), chars.next()),
+        (Some(drive), Some(':')) if drive.is_ascii_alphabetic()
+    )
+}
+
+/// 本地路径字符串，统一使用 `/` 分隔；Windows 路径解析后第一级为不带 `:` 的盘符，需要还原
+fn local_path_string(path: &LocalPath, windows: bool) -> String {
+    let unix_path = path.to_unix_path_string();
+    if !windows {
+        return unix_path;
+    }
+    match path.dirs.0.split_first() {
+        Some((drive, _)) => format!("{}:{}", drive, &unix_path[1 + drive.len()..]),
+        None => unix_path,
+    }
+}
+
+/// 列出最新 changelist 中被 workspace 映射的文件及其本地路径。
+///
+/// 未被映射或被排除的文件不会出现在结果中，已删除的文件也不包含在内。
+pub(crate) async fn describe_workspace(
+    dao: &dyn Dao,
+    request: DescribeWorkspaceReq,
+) -> Result<DescribeWorkspaceRsp, Status> {
+    let workspace_name = request.workspace_name.trim();
+    if workspace_name.is_empty() {
+        return Err(Status::invalid_argument("workspace_name is required"));
+    }
+    let depot_wildcard = match request.depot_wildcard.trim() {
+        "" => "//...",
+        depot_wildcard => depot_wildcard,
+    };
+    let depot = DepotPath::parse(depot_wildcard).map_err(|e| {
+        Status::invalid_argument(format!("invalid depot_wildcard '{depot_wildcard}': {e}"))
+    })?;
+
+    let workspace = dao
+        .find_workspace_by_name(workspace_name)
+        .await
+        .map_err(|e| Status::internal(format!("database error while finding workspace: {e}")))?
+        .ok_or_else(|| Status::not_found(format!("workspace '{workspace_name}' not found")))?;
+    let config = WorkspaceConfig::from_specification(
+        workspace_name,
+        &workspace.root_path,
+        &workspace.mapping,
+    )
+    .map_err(|e| {
+        Status::failed_precondition(format!(
+            "workspace '{workspace_name}' has an invalid mapping: {e}"
+        ))
+    })?;
+    let path_engine = PathEngine::new(config, workspace_name);
+    let windows = is_windows_root(&workspace.root_path);
+
+    let changelist_id = dao
+        .find_latest_changelist_id()
+        .await
+        .map_err(|e| Status::internal(format!("database error while finding changelist: {e}")))?
+        .unwrap_or(0);
+
+    let mut files = Vec::new();
+    for (path, m) in snapshot_at(dao, changelist_id, &depot).await? {
+        if m.is_delete {
+            continue;
+        }
+        let depot_path = CoreDepotPath::parse(&path)
+            .map_err(|e| Status::internal(format!("invalid depot path '{path}': {e}")))?;
+        let Some(local_path) = path_engine.mapping_depot_path(&depot_path) else {
+            continue;
+        };
+        files.push(WorkspaceFile {
+            depot_path: path,
+            local_path: local_path_string(&local_path, windows),
+            generation: m.generation,
+            revision: m.revision,
+            changelist_id: m.changelist_id,
+            size: m.size,
+        });
+    }
+    files.sort_by(|a, b| a.depot_path.cmp(&b.depot_path));
+
+    Ok(DescribeWorkspaceRsp {
+        workspace: Some(WorkspaceInfo {
+            workspace_name: workspace.name,
+            owner: workspace.owner,
+            workspace_root: workspace.root_path,
+            workspace_mapping: workspace.mapping,
+            created_at: workspace.created_at,
+        }),
+        changelist_id,
+        files,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::dao::{MockDao, NewFileRevisionInput};
+    use crate::database::entities;
+    use tonic::Code;
+
+    fn revision(depot_path: &str, revision: i64, is_delete: bool) -> NewFileRevisionInput {
+        NewFileRevisionInput {
+            depot_path: depot_path.to_string(),
+            generation: 1,
+            revision,
+            binary_id: serde_json::json!([]),
+            size: revision * 10,
+            is_delete,
+            created_at: 0,
+            metadata: serde_json::json!({}),
+            file_metadata: serde_json::json!({}),
+        }
+    }
+
+    fn request(workspace_name: &str, depot_wildcard: &str) -> DescribeWorkspaceReq {
+        DescribeWorkspaceReq {
+            workspace_name: workspace_name.to_string(),
+            depot_wildcard: depot_wildcard.to_string(),
+        }
+    }
+
+    /// 把 //project/ 映射到 workspace 的 project 目录，排除 //project/tmp/，
+    /// 并把 //shared/logo.png 单独映射到 assets 目录
+    async fn dao_with_workspace() -> MockDao {
+        let dao = MockDao::default();
+        let submits = [
+            vec![
+                revision("//project/main.rs", 1, false),
+                revision("//project/src/lib.rs", 1, false),
+                revision("//project/tmp/cache.bin", 1, false),
+                revision("//shared/logo.png", 1, false),
+                revision("//shared/other.png", 1, false),
+            ],
+            vec![
+                revision("//project/src/lib.rs", 2, false),
+                revision("//project/main.rs", 2, true),
+                revision("//project/README.md", 1, false),
+            ],
+        ];
+        for revisions in submits {
+            dao.commit_submit("alice", "", 0, serde_json::json!({}), revisions)
+                .await
+                .unwrap();
+        }
+        dao.insert_workspace(&entities::workspaces::Model {
+            name: "ws".to_string(),
+            owner: "alice".to_string(),
+            root_path: "/home/alice/ws/".to_string(),
+            mapping: [
+                "//project/... //ws/project/",
+                "-//project/tmp/...",
+                "//shared/logo.png //ws/assets/logo.png",
+            ]
+            .join("\n"),
+            created_at: 7,
+        })
+        .await
+        .unwrap();
+        dao
+    }
+
+    fn files(rsp: &DescribeWorkspaceRsp) -> Vec<(&str, &str, i64, i64)> {
+        rsp.files
+            .iter()
+            .map(|f| {
+                (
+                    f.depot_path.as_str(),
+                    f.local_path.as_str(),
+                    f.revision,
+                    f.size,
+                )
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn files_follow_workspace_mapping() {
+        let dao = dao_with_workspace().await;
+
+        let rsp = describe_workspace(&dao, request("ws", "")).await.unwrap();
+        let workspace = rsp.workspace.as_ref().unwrap();
+        assert_eq!(workspace.owner, "alice");
+        assert_eq!(workspace.created_at, 7);
+        assert_eq!(rsp.changelist_id, 2);
+        assert_eq!(
+            files(&rsp),
+            vec![
+                (
+                    "//project/README.md",
+                    "/home/alice/ws/project/README.md",
+                    1,
+                    10
+                ),
+                (
+                    "//project/src/lib.rs",
+                    "/home/alice/ws/project/src/lib.rs",
+                    2,
+                    20
+                ),
+                ("//shared/logo.png", "/home/alice/ws/assets/logo.png", 1, 10),
+            ]
+        );
+
+        let rsp = describe_workspace(&dao, request("ws", "//project/src/..."))
+            .await
+            .unwrap();
+        assert_eq!(
+            files(&rsp),
+            vec![(
+                "//project/src/lib.rs",
+                "/home/alice/ws/project/src/lib.rs",
+                2,
+                20
+            )]
+        );
+    }
+
+    #[tokio::test]
+    async fn rejects_unknown_workspace_and_invalid_wildcard() {
+        let dao = dao_with_workspace().await;
+
+        let err = describe_workspace(&dao, request("missing", ""))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::NotFound);
+        let err = describe_workspace(&dao, request("ws", "project"))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::InvalidArgument);
+        let err = describe_workspace(&dao, request(" ", ""))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::InvalidArgument);
+    }
+
+    #[test]
+    fn windows_local_paths_keep_drive_first() {
+        assert!(is_windows_root("C:\\Users\\alice\\ws\\"));
+        assert!(!is_windows_root("/home/alice/ws/"));
+
+        let path = LocalPath::parse("C:\\Users\\alice\\ws\\a.txt").unwrap();
+        assert_eq!(local_path_string(&path, true), "C:/Users/alice/ws/a.txt");
+        let path = LocalPath::parse("/home/alice/ws/a.txt").unwrap();
+        assert_eq!(local_path_string(&path, false), "/home/alice/ws/a.txt");
+    }
+}
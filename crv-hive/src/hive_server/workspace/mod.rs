@@ -0,0 +1,3 @@
+pub mod describe_workspace;
+pub mod register_workspace;
+pub mod unregister_workspace;
@@ -0,0 +1,131 @@
+use crate::database::dao::{Dao, dao};
+use crate::database::entities;
+use crate::logging::HiveLog;
+use crate::pb::{RegisterWorkspaceReq, RegisterWorkspaceRsp};
+use tonic::{Request, Response, Status};
+
+pub async fn handle_register_workspace(
+    log: HiveLog,
+    r: Request<RegisterWorkspaceReq>,
+) -> Result<Response<RegisterWorkspaceRsp>, Status> {
+    let _g = log.enter();
+
+    let request = r.into_inner();
+    log.info(&format!(
+        "register_workspace received: workspace={}, owner={}, root={}",
+        request.workspace_name, request.owner, request.workspace_root
+    ));
+
+    let rsp = register_workspace(
+        dao().as_ref(),
+        request,
+        chrono::Utc::now().timestamp_millis(),
+    )
+    .await?;
+    Ok(Response::new(rsp))
+}
+
+/// 在 hive 上登记 workspace，名称已被占用时返回 `AlreadyExists`。
+///
+/// 这是 edge 创建 workspace 两阶段提交的第一阶段，edge 本地写入失败时会调用
+/// `UnregisterWorkspace` 撤销登记。
+pub(crate) async fn register_workspace(
+    dao: &dyn Dao,
+    request: RegisterWorkspaceReq,
+    now: i64,
+) -> Result<RegisterWorkspaceRsp, Status> {
+    let workspace_name = request.workspace_name.trim();
+    if workspace_name.is_empty() {
+        return Err(Status::invalid_argument("workspace_name is required"));
+    }
+
+    let existing = dao
+        .find_workspace_by_name(workspace_name)
+        .await
+        .map_err(|e| Status::internal(format!("database error while finding workspace: {e}")))?;
+    if existing.is_some() {
+        return Err(Status::already_exists(format!(
+            "workspace '{workspace_name}' already exists"
+        )));
+    }
+
+    let workspace = entities::workspaces::Model {
+        name: workspace_name.to_string(),
+        owner: request.owner,
+        root_path: request.workspace_root,
+        mapping: request.workspace_mapping,
+        created_at: now,
+    };
+    dao.insert_workspace(&workspace)
+        .await
+        .map_err(|e| Status::internal(format!("database error while inserting workspace: {e}")))?;
+
+    Ok(RegisterWorkspaceRsp {})
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::dao::MockDao;
+    use crate::hive_server::workspace::unregister_workspace::unregister_workspace;
+    use crate::pb::UnregisterWorkspaceReq;
+    use tonic::Code;
+
+    fn request(name: &str) -> RegisterWorkspaceReq {
+        RegisterWorkspaceReq {
+            workspace_name: name.to_string(),
+            owner: "alice".to_string(),
+            workspace_root: "/home/alice/ws/".to_string(),
+            workspace_mapping: "//main/... //ws/...".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn register_workspace_rejects_duplicate_name() {
+        let dao = MockDao::default();
+
+        register_workspace(&dao, request("ws"), 1).await.unwrap();
+        let workspace = dao.find_workspace_by_name("ws").await.unwrap().unwrap();
+        assert_eq!(workspace.owner, "alice");
+        assert_eq!(workspace.root_path, "/home/alice/ws/");
+        assert_eq!(workspace.created_at, 1);
+
+        let err = register_workspace(&dao, request("ws"), 2)
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::AlreadyExists);
+
+        let err = register_workspace(&dao, request("  "), 3)
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::InvalidArgument);
+    }
+
+    #[tokio::test]
+    async fn unregistered_workspace_name_can_be_reused() {
+        let dao = MockDao::default();
+
+        register_workspace(&dao, request("ws"), 1).await.unwrap();
+        unregister_workspace(
+            &dao,
+            UnregisterWorkspaceReq {
+                workspace_name: "ws".to_string(),
+            },
+        )
+        .await
+        .unwrap();
+        assert!(dao.find_workspace_by_name("ws").await.unwrap().is_none());
+
+        let err = unregister_workspace(
+            &dao,
+            UnregisterWorkspaceReq {
+                workspace_name: "ws".to_string(),
+            },
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(err.code(), Code::NotFound);
+
+        register_workspace(&dao, request("ws"), 2).await.unwrap();
+    }
+}
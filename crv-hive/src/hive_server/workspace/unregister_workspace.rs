@@ -0,0 +1,38 @@
+use crate::database::dao::{Dao, dao};
+use crate::logging::HiveLog;
+use crate::pb::{UnregisterWorkspaceReq, UnregisterWorkspaceRsp};
+use tonic::{Request, Response, Status};
+
+pub async fn handle_unregister_workspace(
+    log: HiveLog,
+    r: Request<UnregisterWorkspaceReq>,
+) -> Result<Response<UnregisterWorkspaceRsp>, Status> {
+    let _g = log.enter();
+
+    let request = r.into_inner();
+    log.info(&format!(
+        "unregister_workspace received: workspace={}",
+        request.workspace_name
+    ));
+
+    let rsp = unregister_workspace(dao().as_ref(), request).await?;
+    Ok(Response::new(rsp))
+}
+
+/// 撤销 workspace 的登记，workspace 不存在时返回 `NotFound`。
+pub(crate) async fn unregister_workspace(
+    dao: &dyn Dao,
+    request: UnregisterWorkspaceReq,
+) -> Result<UnregisterWorkspaceRsp, Status> {
+    let workspace_name = request.workspace_name.trim();
+    let deleted = dao
+        .delete_workspace(workspace_name)
+        .await
+        .map_err(|e| Status::internal(format!("database error while deleting workspace: {e}")))?;
+    if !deleted {
+        return Err(Status::not_found(format!(
+            "workspace '{workspace_name}' not found"
+        )));
+    }
+    Ok(UnregisterWorkspaceRsp {})
+}
@@ -1,15 +1,23 @@
 // 将通过 build.rs 生成到 OUT_DIR 的 protobuf 模块引入并导出
 pub mod pb {
     include!(concat!(env!("OUT_DIR"), "/hive_proto.rs"));
+
+    /// 编码后的 FileDescriptorSet，用于 gRPC 反射
+    pub const FILE_DESCRIPTOR_SET: &[u8] =
+        include_bytes!(concat!(env!("OUT_DIR"), "/hive_descriptor.bin"));
 }
 
-pub mod config;
+pub mod audit;
 pub mod auth;
-pub mod hive_server;
-pub mod database;
 pub mod caching;
 pub mod common;
+pub mod config;
+pub mod database;
+pub mod hive_server;
 pub mod logging;
+pub mod metrics;
+pub mod middleware;
+pub mod webhook;
 
 #[cfg(test)]
 pub mod test_support;
\ No newline at end of file
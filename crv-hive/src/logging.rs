@@ -1,5 +1,6 @@
 use std::time::Instant;
 
+use crate::middleware::request_id::RequestId;
 use tonic::{Request, Status};
 use tracing::Span;
 
@@ -11,8 +12,7 @@ use tracing::Span;
 pub fn init_logging() {
     use tracing_subscriber::EnvFilter;
 
-    let filter = EnvFilter::try_from_default_env()
-        .unwrap_or_else(|_| EnvFilter::new("info"));
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
 
     // 多次调用时避免 panic（测试/多入口场景）
     let _ = tracing_subscriber::fmt()
@@ -32,13 +32,17 @@ pub struct HiveLog {
 
 impl HiveLog {
     pub fn new(method: &'static str) -> Self {
-        let request_id = uuid::Uuid::new_v4();
+        Self::with_request_id(method, &uuid::Uuid::new_v4().to_string())
+    }
+
+    fn with_request_id(method: &'static str, request_id: &str) -> Self {
         let span = tracing::info_span!(
             "hive_rpc",
-            request_id = %request_id,
+            request_id = tracing::field::Empty,
             method = method,
             user = tracing::field::Empty
         );
+        span.record("request_id", tracing::field::display(request_id));
 
         Self {
             span,
@@ -46,16 +50,18 @@ impl HiveLog {
         }
     }
 
-    /// 从 tonic Request 创建日志对象（当前只取 method；其余信息在 handler 中补充）。
-    pub fn from_request<T>(method: &'static str, _req: &Request<T>) -> Self {
-        Self::new(method)
+    /// 从 tonic Request 创建日志对象，沿用拦截器放入的请求 ID；user 在 handler 中补充。
+    pub fn from_request<T>(method: &'static str, req: &Request<T>) -> Self {
+        match req.extensions().get::<RequestId>() {
+            Some(request_id) => Self::with_request_id(method, &request_id.0),
+            None => Self::new(method),
+        }
     }
 
     /// 在认证后补充 username 字段。
     pub fn with_user(self, username: impl Into<String>) -> Self {
         let username = username.into();
-        self.span
-            .record("user", &tracing::field::display(username));
+        self.span.record("user", &tracing::field::display(username));
         self
     }
 
@@ -97,3 +103,49 @@ impl HiveLog {
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::middleware::request_id::{self, REQUEST_ID_HEADER};
+    use std::io::Write;
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Clone, Default)]
+    struct Captured(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Captured {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn request_id_from_edge_appears_in_logs() {
+        let captured = Captured::default();
+        let writer = captured.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_writer(move || writer.clone())
+            .with_ansi(false)
+            .finish();
+
+        let mut request = Request::new(());
+        request
+            .metadata_mut()
+            .insert(REQUEST_ID_HEADER, "cli-42".parse().unwrap());
+        let request = request_id::call(request).unwrap();
+
+        tracing::subscriber::with_default(subscriber, || {
+            let log = HiveLog::from_request("Submit", &request);
+            log.info("rpc start");
+        });
+
+        let output = String::from_utf8(captured.0.lock().unwrap().clone()).unwrap();
+        assert!(output.contains("request_id=cli-42"), "{output}");
+        assert!(output.contains("rpc start"), "{output}");
+    }
+}
@@ -1,4 +1,4 @@
-use crv_hive::{config, hive_server, database};
+use crv_hive::{config, database, hive_server, metrics};
 use std::net::SocketAddr;
 use tokio::signal;
 
@@ -21,6 +21,23 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     println!("Hive gRPC / gRPC-Web service is available at {}", addr);
 
+    // 与 gRPC 服务共用同一个 tokio runtime
+    let metrics_addr = config::holder::get_config()
+        .unwrap()
+        .metrics_address
+        .clone()
+        .filter(|addr| !addr.is_empty());
+    if let Some(metrics_addr) = metrics_addr {
+        let metrics_addr: SocketAddr = metrics_addr
+            .parse()
+            .unwrap_or_else(|_| panic!("unable to parse metrics addr `{}`", metrics_addr));
+        metrics::start_server(metrics_addr).await?;
+        println!(
+            "Hive metrics are available at http://{}/metrics",
+            metrics_addr
+        );
+    }
+
     // Ctrl+C to shutdown gracefully
     let shutdown = async {
         signal::ctrl_c()
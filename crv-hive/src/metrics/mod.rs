@@ -0,0 +1,154 @@
+//! hive 的监控指标，以 Prometheus 文本格式通过 HTTP 的 `/metrics` 暴露
+//!
+//! 指标在进程内全局共享，通过 [`metrics`] 获取；文件锁数量在每次抓取时从 `SubmitService` 中刷新。
+use crate::hive_server::submit::submit_service;
+use axum::Router;
+use axum::http::header::CONTENT_TYPE;
+use axum::response::IntoResponse;
+use axum::routing::get;
+use prometheus::{
+    Encoder, Histogram, HistogramOpts, IntCounter, IntCounterVec, IntGauge, Opts, Registry,
+    TextEncoder,
+};
+use std::net::SocketAddr;
+use std::sync::OnceLock;
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio::task::JoinHandle;
+
+pub struct HiveMetrics {
+    registry: Registry,
+    /// 按结果（success/failure）统计的提交次数，不包含预检
+    submit_total: IntCounterVec,
+    /// 因文件版本冲突而失败的提交次数
+    submit_conflicts_total: IntCounter,
+    /// 上传完成的 chunk 数
+    chunk_uploads_total: IntCounter,
+    /// 上传到缓存的 chunk 字节数
+    chunk_upload_bytes: IntCounter,
+    /// 当前被未完成的提交锁定的文件数
+    active_file_locks: IntGauge,
+    /// 数据库查询耗时
+    db_query_duration_seconds: Histogram,
+}
+
+static METRICS: OnceLock<HiveMetrics> = OnceLock::new();
+
+/// 全局的指标收集器
+pub fn metrics() -> &'static HiveMetrics {
+    METRICS.get_or_init(HiveMetrics::new)
+}
+
+impl HiveMetrics {
+    fn new() -> Self {
+        let registry = Registry::new();
+        let submit_total = IntCounterVec::new(
+            Opts::new("hive_submit_total", "Number of submits handled by hive."),
+            &["result"],
+        )
+        .unwrap();
+        let submit_conflicts_total = IntCounter::new(
+            "hive_submit_conflicts_total",
+            "Number of submits rejected because of revision conflicts.",
+        )
+        .unwrap();
+        let chunk_uploads_total = IntCounter::new(
+            "hive_chunk_uploads_total",
+            "Number of chunks fully uploaded to hive.",
+        )
+        .unwrap();
+        let chunk_upload_bytes =
+            IntCounter::new("hive_chunk_upload_bytes", "Chunk bytes uploaded to hive.").unwrap();
+        let active_file_locks = IntGauge::new(
+            "hive_active_file_locks",
+            "Files currently locked by pending submits.",
+        )
+        .unwrap();
+        let db_query_duration_seconds = Histogram::with_opts(HistogramOpts::new(
+            "hive_db_query_duration_seconds",
+            "Latency of database queries issued by hive.",
+        ))
+        .unwrap();
+
+        registry.register(Box::new(submit_total.clone())).unwrap();
+        registry
+            .register(Box::new(submit_conflicts_total.clone()))
+            .unwrap();
+        registry
+            .register(Box::new(chunk_uploads_total.clone()))
+            .unwrap();
+        registry
+            .register(Box::new(chunk_upload_bytes.clone()))
+            .unwrap();
+        registry
+            .register(Box::new(active_file_locks.clone()))
+            .unwrap();
+        registry
+            .register(Box::new(db_query_duration_seconds.clone()))
+            .unwrap();
+
+        Self {
+            registry,
+            submit_total,
+            submit_conflicts_total,
+            chunk_uploads_total,
+            chunk_upload_bytes,
+            active_file_locks,
+            db_query_duration_seconds,
+        }
+    }
+
+    /// 记录一次提交的结果，`conflicted` 表示因版本冲突失败
+    pub fn observe_submit(&self, success: bool, conflicted: bool) {
+        let result = if success { "success" } else { "failure" };
+        self.submit_total.with_label_values(&[result]).inc();
+        if conflicted {
+            self.submit_conflicts_total.inc();
+        }
+    }
+
+    pub fn add_uploaded_bytes(&self, bytes: usize) {
+        self.chunk_upload_bytes.inc_by(bytes as u64);
+    }
+
+    pub fn inc_chunk_uploads(&self) {
+        self.chunk_uploads_total.inc();
+    }
+
+    pub fn observe_db_query(&self, elapsed: Duration) {
+        self.db_query_duration_seconds
+            .observe(elapsed.as_secs_f64());
+    }
+
+    /// 以 Prometheus 文本格式输出所有指标
+    pub fn encode(&self) -> String {
+        self.active_file_locks
+            .set(submit_service().locked_file_count() as i64);
+
+        let mut buffer = vec![];
+        TextEncoder::new()
+            .encode(&self.registry.gather(), &mut buffer)
+            .unwrap();
+        String::from_utf8(buffer).unwrap()
+    }
+}
+
+/// 在指定地址上启动 `/metrics` 服务
+pub async fn start_server(addr: SocketAddr) -> std::io::Result<JoinHandle<()>> {
+    let listener = TcpListener::bind(addr).await?;
+    Ok(tokio::spawn(serve(listener)))
+}
+
+pub(crate) async fn serve(listener: TcpListener) {
+    let app = Router::new().route("/metrics", get(handle_metrics));
+    if let Err(e) = axum::serve(listener, app).await {
+        eprintln!("Metrics server stopped: {e}");
+    }
+}
+
+async fn handle_metrics() -> impl IntoResponse {
+    (
+        [(CONTENT_TYPE, "text/plain; version=0.0.4")],
+        metrics().encode(),
+    )
+}
@@ -0,0 +1,388 @@
+//! gRPC 请求的限流
+//!
+//! 每个用户对应一个令牌桶：令牌以 `rate_per_sec` 的速度补充，最多积攒 `burst_cap` 个，
+//! 每个请求消耗一个令牌，令牌耗尽时请求被拒绝。已登录的请求按用户名限流，
+//! 未登录的请求（如注册、登录）按客户端 IP 限流。
+//!
+//! 被拒绝的请求在 `x-rate-limit-retry-after-ms` 中给出至少需要等待多久才能重试；
+//! 配合 [`RateLimitHeadersLayer`] 使用时，通过的请求在响应头 `x-rate-limit-remaining` 中给出剩余的令牌数。
+pub mod request_id;
+
+use crate::auth::UserContext;
+use crate::config::holder::get_or_init_config;
+use std::collections::HashMap;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use tonic::codegen::{Service, http};
+use tonic::metadata::MetadataValue;
+use tonic::{Request, Status};
+use tower_layer::Layer;
+
+/// 被拒绝的请求至少需要等待多少毫秒才能重试
+pub const RETRY_AFTER_HEADER: &str = "x-rate-limit-retry-after-ms";
+/// 请求通过后剩余的令牌数
+pub const REMAINING_HEADER: &str = "x-rate-limit-remaining";
+
+/// 桶的数量超过该值时，清理已经补满的桶，避免大量不同的 IP 占用内存
+const MAX_IDLE_BUCKETS: usize = 10_000;
+
+/// 令牌桶
+#[derive(Debug, Clone)]
+pub struct TokenBucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    fn new(burst_cap: f64, now: Instant) -> Self {
+        Self {
+            tokens: burst_cap,
+            last_refill: now,
+        }
+    }
+
+    fn refill(&mut self, rate_per_sec: f64, burst_cap: f64, now: Instant) {
+        let elapsed = now
+            .saturating_duration_since(self.last_refill)
+            .as_secs_f64();
+        self.tokens = (self.tokens + elapsed * rate_per_sec).min(burst_cap);
+        self.last_refill = now;
+    }
+
+    /// 尝试消耗一个令牌，返回剩余的令牌数；令牌不足时返回补充到一个令牌所需的时间
+    fn try_acquire(
+        &mut self,
+        rate_per_sec: f64,
+        burst_cap: f64,
+        now: Instant,
+    ) -> Result<u64, Duration> {
+        self.refill(rate_per_sec, burst_cap, now);
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            Ok(self.tokens as u64)
+        } else {
+            Err(Duration::from_secs_f64((1.0 - self.tokens) / rate_per_sec))
+        }
+    }
+}
+
+/// 按用户限流的令牌桶集合
+///
+/// tonic 的 `Interceptor` 是同步调用的，因此这里使用 `std::sync::Mutex`，临界区内只有简单的计算。
+pub struct RateLimiter {
+    rate_per_sec: f64,
+    burst_cap: f64,
+    buckets: Mutex<HashMap<String, TokenBucket>>,
+}
+
+impl RateLimiter {
+    pub fn new(rate_per_sec: f64, burst_cap: u32) -> Self {
+        Self {
+            rate_per_sec,
+            burst_cap: burst_cap as f64,
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// 基于全局配置创建限流器，`rate_limit_per_sec` 为 0 时不限流
+    pub fn from_config() -> Option<Arc<Self>> {
+        let cfg = get_or_init_config();
+        if cfg.rate_limit_per_sec <= 0.0 {
+            return None;
+        }
+        Some(Arc::new(Self::new(
+            cfg.rate_limit_per_sec,
+            cfg.rate_limit_burst,
+        )))
+    }
+
+    /// 为指定的 key 消耗一个令牌，返回剩余的令牌数；令牌不足时返回需要等待的时间
+    pub fn try_acquire(&self, key: &str) -> Result<u64, Duration> {
+        self.try_acquire_at(key, Instant::now())
+    }
+
+    fn try_acquire_at(&self, key: &str, now: Instant) -> Result<u64, Duration> {
+        let mut buckets = self.buckets.lock().expect("rate limiter poisoned");
+        if buckets.len() >= MAX_IDLE_BUCKETS && !buckets.contains_key(key) {
+            let (rate_per_sec, burst_cap) = (self.rate_per_sec, self.burst_cap);
+            buckets.retain(|_, bucket| {
+                bucket.refill(rate_per_sec, burst_cap, now);
+                bucket.tokens < burst_cap
+            });
+        }
+        buckets
+            .entry(key.to_string())
+            .or_insert_with(|| TokenBucket::new(self.burst_cap, now))
+            .try_acquire(self.rate_per_sec, self.burst_cap, now)
+    }
+
+    /// 在鉴权之后调用：已登录的请求按用户名限流，未登录的请求按客户端 IP 限流
+    pub fn check_request<T>(&self, req: &Request<T>) -> Result<(), Status> {
+        let key = match req.extensions().get::<UserContext>() {
+            Some(user) => format!("user:{}", user.username),
+            None => match req.remote_addr() {
+                Some(addr) => format!("ip:{}", addr.ip()),
+                None => return Ok(()),
+            },
+        };
+        match self.try_acquire(&key) {
+            Ok(remaining) => {
+                if let Some(slot) = req.extensions().get::<RemainingTokens>() {
+                    slot.set(remaining);
+                }
+                Ok(())
+            }
+            Err(retry_after) => {
+                // 向上取整，保证客户端等待这么久之后一定能拿到令牌
+                let retry_after_ms = retry_after.as_micros().div_ceil(1000).max(1) as u64;
+                let mut status = Status::resource_exhausted("rate limit exceeded");
+                status
+                    .metadata_mut()
+                    .insert(RETRY_AFTER_HEADER, MetadataValue::from(retry_after_ms));
+                Err(status)
+            }
+        }
+    }
+}
+
+/// 由 [`RateLimitHeadersLayer`] 放入请求的 extensions，限流通过后记录剩余的令牌数
+#[derive(Debug, Clone)]
+struct RemainingTokens(Arc<AtomicU64>);
+
+impl RemainingTokens {
+    const UNSET: u64 = u64::MAX;
+
+    fn new() -> Self {
+        Self(Arc::new(AtomicU64::new(Self::UNSET)))
+    }
+
+    fn set(&self, remaining: u64) {
+        self.0.store(remaining, Ordering::Relaxed);
+    }
+
+    fn get(&self) -> Option<u64> {
+        let remaining = self.0.load(Ordering::Relaxed);
+        (remaining != Self::UNSET).then_some(remaining)
+    }
+}
+
+/// 把限流后剩余的令牌数写入响应头 `x-rate-limit-remaining`
+///
+/// 拦截器无法修改响应，因此由该 layer 在请求中放入 [`RemainingTokens`]，
+/// 拦截器限流通过后写入剩余令牌数，响应返回时再由该 layer 写入响应头。
+#[derive(Clone, Default)]
+pub struct RateLimitHeadersLayer;
+
+impl<S> Layer<S> for RateLimitHeadersLayer {
+    type Service = RateLimitHeadersService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RateLimitHeadersService { inner }
+    }
+}
+
+#[derive(Clone)]
+pub struct RateLimitHeadersService<S> {
+    inner: S,
+}
+
+impl<S, B, ResBody> Service<http::Request<B>> for RateLimitHeadersService<S>
+where
+    S: Service<http::Request<B>, Response = http::Response<ResBody>>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<S::Response, S::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: http::Request<B>) -> Self::Future {
+        let remaining = RemainingTokens::new();
+        request.extensions_mut().insert(remaining.clone());
+
+        let response = self.inner.call(request);
+        Box::pin(async move {
+            let mut response = response.await?;
+            if let Some(remaining) = remaining.get() {
+                response
+                    .headers_mut()
+                    .insert(REMAINING_HEADER, http::HeaderValue::from(remaining));
+            }
+            Ok(response)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::{AuthInterceptor, AuthService, TokenPolicy};
+    use crate::hive_server::CrvHiveService;
+    use crate::pb::BonjourReq;
+    use crate::pb::hive_service_client::HiveServiceClient;
+    use crate::pb::hive_service_server::HiveServiceServer;
+    use std::time::Duration;
+    use tonic::Code;
+    use tonic::metadata::MetadataValue;
+    use tonic::transport::Server;
+
+    #[test]
+    fn bucket_bursts_then_refills_at_rate() {
+        let limiter = RateLimiter::new(2.0, 3);
+        let start = Instant::now();
+        for _ in 0..3 {
+            assert!(limiter.try_acquire_at("user:alice", start).is_ok());
+        }
+        assert!(limiter.try_acquire_at("user:alice", start).is_err());
+        // 其他用户使用独立的桶
+        assert!(limiter.try_acquire_at("user:bob", start).is_ok());
+
+        let later = start + Duration::from_millis(500);
+        assert!(limiter.try_acquire_at("user:alice", later).is_ok());
+        assert!(limiter.try_acquire_at("user:alice", later).is_err());
+
+        // 长时间空闲后最多只积攒 burst_cap 个令牌
+        let much_later = start + Duration::from_secs(60);
+        for _ in 0..3 {
+            assert!(limiter.try_acquire_at("user:alice", much_later).is_ok());
+        }
+        assert!(limiter.try_acquire_at("user:alice", much_later).is_err());
+    }
+
+    #[test]
+    fn acquire_reports_remaining_tokens_and_wait_time() {
+        let limiter = RateLimiter::new(4.0, 2);
+        let start = Instant::now();
+        assert_eq!(limiter.try_acquire_at("user:alice", start), Ok(1));
+        assert_eq!(limiter.try_acquire_at("user:alice", start), Ok(0));
+        assert_eq!(
+            limiter.try_acquire_at("user:alice", start),
+            Err(Duration::from_millis(250))
+        );
+
+        let later = start + Duration::from_millis(100);
+        let wait = limiter.try_acquire_at("user:alice", later).unwrap_err();
+        assert!((wait.as_secs_f64() - 0.15).abs() < 1e-9, "{wait:?}");
+    }
+
+    /// 启动带有鉴权与限流的 hive，返回连接到它的 channel
+    async fn spawn_hive(
+        auth: Arc<AuthService>,
+        limiter: Arc<RateLimiter>,
+    ) -> (
+        tonic::transport::Channel,
+        tokio::task::JoinHandle<Result<(), tonic::transport::Error>>,
+    ) {
+        let interceptor = AuthInterceptor::new(Arc::clone(&auth)).with_rate_limiter(limiter);
+
+        // 先占用一个空闲端口再释放，交给服务器监听
+        let addr = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let server = tokio::spawn(
+            Server::builder()
+                .layer(RateLimitHeadersLayer)
+                .add_service(HiveServiceServer::with_interceptor(
+                    CrvHiveService::new(Arc::clone(&auth)),
+                    interceptor,
+                ))
+                .serve(addr),
+        );
+
+        let endpoint = tonic::transport::Endpoint::from_shared(format!("http://{addr}")).unwrap();
+        let mut channel = None;
+        for _ in 0..50 {
+            if let Ok(c) = endpoint.connect().await {
+                channel = Some(c);
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        (
+            channel.expect("hive server should accept connections"),
+            server,
+        )
+    }
+
+    fn test_auth() -> Arc<AuthService> {
+        Arc::new(AuthService::new(
+            b"test-secret",
+            TokenPolicy {
+                ttl_secs: 3600,
+                renew_before_secs: 0,
+            },
+        ))
+    }
+
+    #[tokio::test]
+    async fn concurrent_requests_from_one_user_are_throttled() {
+        let auth = test_auth();
+        let (channel, server) =
+            spawn_hive(Arc::clone(&auth), Arc::new(RateLimiter::new(10.0, 100))).await;
+        let (token, _) = auth.issue_token("alice", &[]).unwrap();
+        let bearer = MetadataValue::try_from(format!("Bearer {token}")).unwrap();
+
+        let calls = (0..1000).map(|_| {
+            let mut client = HiveServiceClient::new(channel.clone());
+            let bearer = bearer.clone();
+            tokio::spawn(async move {
+                let mut req = Request::new(BonjourReq {});
+                req.metadata_mut().insert("authorization", bearer);
+                client.bonjour(req).await
+            })
+        });
+        let results = futures::future::join_all(calls).await;
+
+        let mut accepted = 0;
+        let mut rejected = 0;
+        for result in results {
+            match result.unwrap() {
+                Ok(_) => accepted += 1,
+                Err(status) => {
+                    assert_eq!(status.code(), Code::ResourceExhausted);
+                    rejected += 1;
+                }
+            }
+        }
+        assert!(accepted >= 100, "accepted {accepted}");
+        assert!(rejected >= 500, "rejected {rejected}");
+
+        server.abort();
+    }
+
+    #[tokio::test]
+    async fn rate_limit_headers_are_returned() {
+        let (channel, server) = spawn_hive(test_auth(), Arc::new(RateLimiter::new(1.0, 2))).await;
+        let mut client = HiveServiceClient::new(channel);
+
+        for expected in ["1", "0"] {
+            let rsp = client.bonjour(BonjourReq {}).await.unwrap();
+            assert_eq!(rsp.metadata().get(REMAINING_HEADER).unwrap(), expected);
+        }
+
+        let status = client.bonjour(BonjourReq {}).await.unwrap_err();
+        assert_eq!(status.code(), Code::ResourceExhausted);
+        let retry_after_ms: u64 = status
+            .metadata()
+            .get(RETRY_AFTER_HEADER)
+            .expect("rejected call should carry retry-after")
+            .to_str()
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert!(
+            retry_after_ms > 0 && retry_after_ms <= 1000,
+            "{retry_after_ms}"
+        );
+        assert!(status.metadata().get(REMAINING_HEADER).is_none());
+
+        server.abort();
+    }
+}
@@ -0,0 +1,59 @@
+//! 请求 ID：edge 通过 `x-request-id` 头传入，用于关联 CLI、edge 与 hive 的日志
+use tonic::{Request, Status};
+
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// 请求 ID 的最大长度，超过时视为无效并重新生成
+const MAX_REQUEST_ID_LEN: usize = 128;
+
+/// 当前请求的 ID，由 [`call`] 放入 `Request::extensions`，[`crate::logging::HiveLog`] 将其记录到 Span 中
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestId(pub String);
+
+impl RequestId {
+    /// 解析 `x-request-id` 头，只接受不含空白的可见 ASCII 字符
+    fn parse(value: &str) -> Option<Self> {
+        let valid = !value.is_empty()
+            && value.len() <= MAX_REQUEST_ID_LEN
+            && value.bytes().all(|b| b.is_ascii_graphic());
+        valid.then(|| Self(value.to_string()))
+    }
+}
+
+/// 读取或生成请求 ID 并放入 extensions
+pub fn call(mut request: Request<()>) -> Result<Request<()>, Status> {
+    let request_id = request
+        .metadata()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(RequestId::parse)
+        .unwrap_or_else(|| RequestId(uuid::Uuid::new_v4().to_string()));
+    request.extensions_mut().insert(request_id);
+    Ok(request)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn request_id_is_read_from_metadata() {
+        let mut request = Request::new(());
+        request
+            .metadata_mut()
+            .insert(REQUEST_ID_HEADER, "cli-42".parse().unwrap());
+        let request = call(request).unwrap();
+        assert_eq!(
+            request.extensions().get::<RequestId>(),
+            Some(&RequestId("cli-42".to_string()))
+        );
+
+        let mut request = Request::new(());
+        request
+            .metadata_mut()
+            .insert(REQUEST_ID_HEADER, "has space".parse().unwrap());
+        let request = call(request).unwrap();
+        let generated = request.extensions().get::<RequestId>().unwrap();
+        assert!(uuid::Uuid::parse_str(&generated.0).is_ok());
+    }
+}
@@ -1,7 +1,7 @@
 #[cfg(test)]
 use std::future::Future;
 #[cfg(test)]
-use std::sync::{Mutex, OnceLock};
+use std::sync::{Arc, Mutex, OnceLock};
 
 #[cfg(test)]
 use sea_orm::{ConnectionTrait, DatabaseBackend, DatabaseConnection, Statement};
@@ -9,6 +9,8 @@ use sea_orm::{ConnectionTrait, DatabaseBackend, DatabaseConnection, Statement};
 #[cfg(test)]
 static DB_TEST_MUTEX: OnceLock<Mutex<()>> = OnceLock::new();
 #[cfg(test)]
+static MOCK_DAO: OnceLock<Arc<crate::database::dao::MockDao>> = OnceLock::new();
+#[cfg(test)]
 static DB_TEST_RUNTIME: OnceLock<tokio::runtime::Runtime> = OnceLock::new();
 
 #[cfg(test)]
@@ -61,6 +63,18 @@ pub async fn ensure_hive_db() {
     }
 }
 
+/// 将进程内共享的 `MockDao` 设为全局 DAO 并返回。
+///
+/// 全局 DAO 在并行执行的测试之间共享，各测试使用同一个实例，避免互相覆盖导致写入的数据丢失。
+#[cfg(test)]
+pub fn install_mock_dao() -> Arc<crate::database::dao::MockDao> {
+    let dao = MOCK_DAO
+        .get_or_init(|| Arc::new(crate::database::dao::MockDao::default()))
+        .clone();
+    crate::database::dao::set_dao_for_tests(dao.clone());
+    dao
+}
+
 #[cfg(test)]
 pub async fn insert_test_changelist(db: &DatabaseConnection) -> i64 {
     let backend = DatabaseBackend::Postgres;
@@ -68,8 +82,8 @@ pub async fn insert_test_changelist(db: &DatabaseConnection) -> i64 {
         .query_one(Statement::from_sql_and_values(
             backend,
             r#"
-            INSERT INTO changelists (author, description, committed_at, metadata)
-            VALUES ($1, $2, $3, $4)
+            INSERT INTO changelists (author, description, committed_at, metadata, parent_changelist_id)
+            VALUES ($1, $2, $3, $4, (SELECT COALESCE(MAX(id), 0) FROM changelists WHERE branch_id = ''))
             RETURNING id
             "#,
             vec![
@@ -114,4 +128,3 @@ where
         f().await;
     });
 }
-
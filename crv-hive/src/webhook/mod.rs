@@ -0,0 +1,339 @@
+//! Webhook：在提交等事件发生后通知外部 URL，用于对接 CI/CD 流水线
+//!
+//! 配置保存在数据库的 `webhooks` 表中。通知以 JSON 请求体 POST 到所有订阅了该事件的 URL，
+//! 并在 `X-Crv-Signature` 头中携带 `hex(HMAC-SHA256(secret, body))`，接收方可据此校验来源。
+//! 调用方应通过 `tokio::spawn(notify_submit(notification))` 异步发送，发送失败只记录日志，
+//! 不影响操作本身。
+use crate::database::dao::{Dao, dao};
+use hmac::{Hmac, Mac};
+use serde::Serialize;
+use sha2::Sha256;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+/// 携带请求签名的 HTTP 头
+pub const SIGNATURE_HEADER: &str = "X-Crv-Signature";
+/// 携带事件类型的 HTTP 头
+pub const EVENT_HEADER: &str = "X-Crv-Event";
+
+/// 单次通知的超时时间，避免慢速的接收方长期占用连接
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// 可订阅的事件类型
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookEvent {
+    /// 提交成功
+    Submit,
+}
+
+impl WebhookEvent {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WebhookEvent::Submit => "submit",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "submit" => Some(WebhookEvent::Submit),
+            _ => None,
+        }
+    }
+}
+
+/// 一个已登记的 webhook
+#[derive(Clone, Debug, PartialEq)]
+pub struct WebhookConfig {
+    pub id: i64,
+    pub url: String,
+    pub secret: String,
+    pub events: Vec<WebhookEvent>,
+    pub created_by: String,
+    /// 毫秒时间戳
+    pub created_at: i64,
+}
+
+/// 提交成功后发送的通知内容
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct SubmitNotification {
+    /// 提交到的分支，空字符串表示默认分支
+    pub branch_id: String,
+    pub changelist_id: i64,
+    pub author: String,
+    pub files_count: u64,
+    /// 秒级时间戳
+    pub committed_at: i64,
+}
+
+#[derive(Serialize)]
+struct Envelope<'a, T> {
+    event: WebhookEvent,
+    #[serde(flatten)]
+    payload: &'a T,
+}
+
+/// 计算请求体的签名：`hex(HMAC-SHA256(secret, body))`
+pub fn sign(secret: &str, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any size");
+    mac.update(body);
+    hex::encode(mac.finalize().into_bytes())
+}
+
+fn http_client() -> &'static reqwest::Client {
+    static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+    CLIENT.get_or_init(|| {
+        reqwest::Client::builder()
+            .timeout(DELIVERY_TIMEOUT)
+            .build()
+            .expect("build webhook http client")
+    })
+}
+
+/// 通知所有订阅了提交事件的 webhook
+pub async fn notify_submit(notification: SubmitNotification) {
+    deliver(
+        dao().as_ref(),
+        http_client(),
+        WebhookEvent::Submit,
+        &notification,
+    )
+    .await;
+}
+
+/// 将事件 POST 到所有订阅了该事件的 webhook，返回接收方返回成功状态码的数量
+pub(crate) async fn deliver<T: Serialize>(
+    dao: &dyn Dao,
+    client: &reqwest::Client,
+    event: WebhookEvent,
+    payload: &T,
+) -> usize {
+    let webhooks = match dao.list_webhooks().await {
+        Ok(webhooks) => webhooks,
+        Err(e) => {
+            tracing::warn!("failed to load webhooks: event={}: {e}", event.as_str());
+            return 0;
+        }
+    };
+
+    let body = match serde_json::to_vec(&Envelope { event, payload }) {
+        Ok(body) => body,
+        Err(e) => {
+            tracing::warn!(
+                "failed to serialize webhook payload: event={}: {e}",
+                event.as_str()
+            );
+            return 0;
+        }
+    };
+
+    let deliveries = webhooks
+        .iter()
+        .filter(|webhook| webhook.events.contains(&event))
+        .map(|webhook| {
+            let request = client
+                .post(&webhook.url)
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .header(EVENT_HEADER, event.as_str())
+                .header(SIGNATURE_HEADER, sign(&webhook.secret, &body))
+                .body(body.clone());
+            async move {
+                match request.send().await {
+                    Ok(rsp) if rsp.status().is_success() => true,
+                    Ok(rsp) => {
+                        tracing::warn!(
+                            "webhook rejected notification: id={}, url={}, event={}, status={}",
+                            webhook.id,
+                            webhook.url,
+                            event.as_str(),
+                            rsp.status()
+                        );
+                        false
+                    }
+                    Err(e) => {
+                        tracing::warn!(
+                            "failed to deliver webhook: id={}, url={}, event={}: {e}",
+                            webhook.id,
+                            webhook.url,
+                            event.as_str()
+                        );
+                        false
+                    }
+                }
+            }
+        });
+
+    futures::future::join_all(deliveries)
+        .await
+        .into_iter()
+        .filter(|delivered| *delivered)
+        .count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::dao::{MockDao, NewFileRevisionInput};
+    use std::collections::HashMap;
+    use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::TcpListener;
+    use tokio::sync::mpsc;
+
+    struct ReceivedRequest {
+        request_line: String,
+        headers: HashMap<String, String>,
+        body: Vec<u8>,
+    }
+
+    /// 在本地随机端口上启动只会返回 200 的 HTTP 服务，收到的请求通过 channel 返回
+    async fn spawn_http_server() -> (String, mpsc::UnboundedReceiver<ReceivedRequest>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (tx, rx) = mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let mut reader = BufReader::new(stream);
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).await.unwrap();
+
+                let mut headers = HashMap::new();
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).await.unwrap();
+                    let line = line.trim_end();
+                    if line.is_empty() {
+                        break;
+                    }
+                    let (name, value) = line.split_once(':').unwrap();
+                    headers.insert(name.trim().to_ascii_lowercase(), value.trim().to_string());
+                }
+
+                let len = headers
+                    .get("content-length")
+                    .map(|v| v.parse().unwrap())
+                    .unwrap_or(0);
+                let mut body = vec![0; len];
+                reader.read_exact(&mut body).await.unwrap();
+                reader
+                    .get_mut()
+                    .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 0\r\nconnection: close\r\n\r\n")
+                    .await
+                    .unwrap();
+
+                let _ = tx.send(ReceivedRequest {
+                    request_line: request_line.trim_end().to_string(),
+                    headers,
+                    body,
+                });
+            }
+        });
+        (format!("http://{addr}/hooks/crv"), rx)
+    }
+
+    #[test]
+    fn signature_is_hex_encoded_hmac_sha256() {
+        // RFC 4231 test case 2
+        assert_eq!(
+            sign("Jefe", b"what do ya want for nothing?"),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[tokio::test]
+    async fn submit_notifies_registered_webhook_with_signed_payload() {
+        let (url, mut received) = spawn_http_server().await;
+        let dao = MockDao::default();
+        dao.insert_webhook(&WebhookConfig {
+            id: 0,
+            url,
+            secret: "s3cret".to_string(),
+            events: vec![WebhookEvent::Submit],
+            created_by: "admin".to_string(),
+            created_at: 0,
+        })
+        .await
+        .unwrap();
+
+        let changelist_id = dao
+            .commit_submit(
+                "alice",
+                "add texture",
+                1_700_000_000,
+                serde_json::json!({}),
+                vec![NewFileRevisionInput {
+                    depot_path: "//game/textures/grass.png".to_string(),
+                    generation: 1,
+                    revision: 1,
+                    binary_id: serde_json::json!(["abcd"]),
+                    size: 4,
+                    is_delete: false,
+                    created_at: 1_700_000_000,
+                    metadata: serde_json::json!({}),
+                    file_metadata: serde_json::json!({}),
+                }],
+            )
+            .await
+            .unwrap();
+
+        let delivered = deliver(
+            &dao,
+            &reqwest::Client::new(),
+            WebhookEvent::Submit,
+            &SubmitNotification {
+                branch_id: String::new(),
+                changelist_id,
+                author: "alice".to_string(),
+                files_count: 1,
+                committed_at: 1_700_000_000,
+            },
+        )
+        .await;
+        assert_eq!(delivered, 1);
+
+        let request = received.recv().await.unwrap();
+        assert_eq!(request.request_line, "POST /hooks/crv HTTP/1.1");
+        assert_eq!(request.headers["x-crv-event"], "submit");
+        assert_eq!(
+            request.headers["x-crv-signature"],
+            sign("s3cret", &request.body)
+        );
+        let payload: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+        assert_eq!(
+            payload,
+            serde_json::json!({
+                "event": "submit",
+                "branch_id": "",
+                "changelist_id": changelist_id,
+                "author": "alice",
+                "files_count": 1,
+                "committed_at": 1_700_000_000,
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn unreachable_webhook_does_not_count_as_delivered() {
+        let dao = MockDao::default();
+        dao.insert_webhook(&WebhookConfig {
+            id: 0,
+            // 端口 1 上通常没有服务，连接会被拒绝
+            url: "http://127.0.0.1:1/".to_string(),
+            secret: "s3cret".to_string(),
+            events: vec![WebhookEvent::Submit],
+            created_by: "admin".to_string(),
+            created_at: 0,
+        })
+        .await
+        .unwrap();
+
+        let delivered = deliver(
+            &dao,
+            &reqwest::Client::new(),
+            WebhookEvent::Submit,
+            &serde_json::json!({}),
+        )
+        .await;
+        assert_eq!(delivered, 0);
+    }
+}